@@ -0,0 +1,201 @@
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use crate::AppState;
+
+/// A network in CIDR notation, e.g. `10.0.0.0/8`; a bare address is a single host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid CIDR '{}'", value);
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let network: IpAddr = address.trim().parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(invalid)?,
+            None => max,
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Proxies whose `X-Forwarded-For` / `X-Real-IP` headers are believed.
+/// Empty by default: every client is taken to connect directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies(Vec<Cidr>);
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<Cidr>) -> Self {
+        Self(networks)
+    }
+
+    /// Parse `TRUSTED_PROXIES`: comma-separated CIDRs or addresses
+    pub fn parse(list: &str) -> Result<Self, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+
+    /// The client behind a request from `peer`. Forwarding headers count
+    /// only when `peer` is a trusted proxy; `X-Forwarded-For` is read from
+    /// the right, skipping further trusted proxies, so entries a client
+    /// prepended itself are never used. `X-Real-IP` is the fallback.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        if !forwarded.is_empty() {
+            for entry in forwarded.iter().rev() {
+                match entry.parse::<IpAddr>() {
+                    Ok(ip) if self.is_trusted(ip) => continue,
+                    Ok(ip) => return ip,
+                    Err(_) => break,
+                }
+            }
+            // Only proxies, or a garbled hop: nothing beyond the peer can be believed
+            return peer;
+        }
+
+        headers
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(peer)
+    }
+}
+
+impl FromRef<AppState> for TrustedProxies {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.trusted_proxies.clone()
+    }
+}
+
+/// The requesting client's address, `None` when the server was not started
+/// with `into_make_service_with_connect_info::<SocketAddr>()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    TrustedProxies: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(ConnectInfo(peer)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() else {
+            return Ok(Self(None));
+        };
+        Ok(Self(Some(TrustedProxies::from_ref(state).resolve(peer.ip().to_canonical(), &parts.headers))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn client_ip(peer: &str, headers: &[(&str, &str)], trusted: &str) -> Option<IpAddr> {
+        let mut request = Request::get("/api/v1/search/dno");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let mut request = request.body(()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 52144)));
+        let (mut parts, _) = request.into_parts();
+
+        let proxies = TrustedProxies::parse(trusted).unwrap();
+        ClientIp::from_request_parts(&mut parts, &proxies).await.unwrap().0
+    }
+
+    fn ip(ip: &str) -> Option<IpAddr> {
+        Some(ip.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_direct_connection_uses_the_peer() {
+        assert_eq!(client_ip("203.0.113.7", &[], "10.0.0.0/8").await, ip("203.0.113.7"));
+        assert_eq!(client_ip("::ffff:203.0.113.7", &[], "").await, ip("203.0.113.7"));
+    }
+
+    #[tokio::test]
+    async fn test_trusted_proxy_forwards_the_client() {
+        let trusted = "10.0.0.0/8, 192.168.1.5";
+        assert_eq!(client_ip("10.1.2.3", &[("x-forwarded-for", "203.0.113.7")], trusted).await, ip("203.0.113.7"));
+        // A spoofed entry the client prepended is left of the address our proxies saw
+        assert_eq!(
+            client_ip("10.1.2.3", &[("x-forwarded-for", "1.1.1.1, 203.0.113.7, 192.168.1.5")], trusted).await,
+            ip("203.0.113.7")
+        );
+        assert_eq!(client_ip("192.168.1.5", &[("x-real-ip", "2001:db8::7")], trusted).await, ip("2001:db8::7"));
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_header_from_untrusted_peer_is_ignored() {
+        let headers = [("x-forwarded-for", "1.1.1.1"), ("x-real-ip", "1.1.1.1")];
+        assert_eq!(client_ip("203.0.113.7", &headers, "10.0.0.0/8").await, ip("203.0.113.7"));
+        assert_eq!(client_ip("10.1.2.3", &headers, "").await, ip("10.1.2.3"));
+    }
+
+    #[test]
+    fn test_cidr_parsing() {
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("proxy.internal").is_err());
+        let proxies = TrustedProxies::parse("172.16.0.0/12,fd00::/8").unwrap();
+        assert!(proxies.is_trusted("172.31.255.1".parse().unwrap()));
+        assert!(!proxies.is_trusted("172.32.0.1".parse().unwrap()));
+        assert!(proxies.is_trusted("fd12::1".parse().unwrap()));
+    }
+}
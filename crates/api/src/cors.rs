@@ -0,0 +1,145 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::warn;
+
+use crate::idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAY_HEADER};
+use crate::middleware::REQUEST_ID_HEADER;
+use crate::quota::{QUOTA_LIMIT_HEADER, QUOTA_REMAINING_HEADER};
+use crate::AppConfig;
+
+/// Methods the routes answer to
+const ALLOWED_METHODS: [Method; 5] = [Method::GET, Method::POST, Method::PATCH, Method::DELETE, Method::OPTIONS];
+
+/// Seconds browsers may cache a preflight answer
+const PREFLIGHT_MAX_AGE_SECS: u64 = 600;
+
+fn allowed_headers() -> Vec<HeaderName> {
+    vec![
+        header::AUTHORIZATION,
+        header::CONTENT_TYPE,
+        header::ACCEPT,
+        header::IF_NONE_MATCH,
+        HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+        HeaderName::from_static(REQUEST_ID_HEADER),
+    ]
+}
+
+/// Response headers scripts on an allowed origin may read
+fn exposed_headers() -> Vec<HeaderName> {
+    vec![
+        header::ETAG,
+        header::RETRY_AFTER,
+        header::CONTENT_DISPOSITION,
+        HeaderName::from_static(REQUEST_ID_HEADER),
+        HeaderName::from_static(IDEMPOTENT_REPLAY_HEADER),
+        HeaderName::from_static(QUOTA_LIMIT_HEADER),
+        HeaderName::from_static(QUOTA_REMAINING_HEADER),
+    ]
+}
+
+/// CORS for the API: only `cors_origins` get CORS headers, with credentials,
+/// the methods above and the headers the API reads and sets. Other origins
+/// get none, so browsers block their scripts from reading responses.
+/// `cors_allow_all` echoes any origin back instead, for local development.
+pub fn cors_layer(config: &AppConfig) -> CorsLayer {
+    let origin = if config.cors_allow_all {
+        if config.is_production() {
+            warn!("CORS_ALLOW_ALL is set in production: every origin may send credentialed requests");
+        }
+        AllowOrigin::mirror_request()
+    } else {
+        let origins: Vec<HeaderValue> = config
+            .cors_origins
+            .iter()
+            .filter(|origin| !origin.is_empty())
+            .filter_map(|origin| match HeaderValue::from_str(origin.trim_end_matches('/')) {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    warn!("Ignoring invalid CORS origin '{}'", origin);
+                    None
+                }
+            })
+            .collect();
+        if origins.is_empty() && config.is_production() {
+            warn!("CORS_ORIGINS is empty: browsers on other origins can't call the API");
+        }
+        AllowOrigin::list(origins)
+    };
+
+    CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(ALLOWED_METHODS.to_vec())
+        .allow_headers(allowed_headers())
+        .expose_headers(exposed_headers())
+        .allow_credentials(true)
+        .max_age(std::time::Duration::from_secs(PREFLIGHT_MAX_AGE_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(config: &AppConfig) -> Router {
+        Router::new().route("/health", get(|| async { "ok" })).layer(cors_layer(config))
+    }
+
+    fn config(origins: &[&str], allow_all: bool) -> AppConfig {
+        AppConfig {
+            cors_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            cors_allow_all: allow_all,
+            ..AppConfig::default()
+        }
+    }
+
+    async fn get_from(app: Router, origin: &str) -> axum::response::Response {
+        let request = Request::get("/health").header(header::ORIGIN, origin).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_only_allowlisted_origins_get_cors_headers() {
+        let config = config(&["https://dno.example.de", "http://localhost:5173/"], false);
+
+        let allowed = get_from(app(&config), "https://dno.example.de").await;
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://dno.example.de");
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        let exposed = allowed.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap();
+        assert!(exposed.contains(REQUEST_ID_HEADER), "{}", exposed);
+
+        // A trailing slash in the config still matches the browser's origin
+        let local = get_from(app(&config), "http://localhost:5173").await;
+        assert_eq!(local.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:5173");
+
+        let disallowed = get_from(app(&config), "https://evil.example.com").await;
+        assert_eq!(disallowed.status(), axum::http::StatusCode::OK);
+        assert!(disallowed.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_preflight_lists_methods_and_headers() {
+        let request = Request::options("/health")
+            .header(header::ORIGIN, "https://dno.example.de")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization,idempotency-key")
+            .body(Body::empty())
+            .unwrap();
+        let response = app(&config(&["https://dno.example.de"], false)).oneshot(request).await.unwrap();
+
+        let methods = response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap();
+        assert!(methods.contains("PATCH") && methods.contains("DELETE"), "{}", methods);
+        let headers = response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap();
+        assert!(headers.contains("authorization") && headers.contains(IDEMPOTENCY_KEY_HEADER), "{}", headers);
+    }
+
+    #[tokio::test]
+    async fn test_allow_all_mirrors_any_origin() {
+        let response = get_from(app(&config(&[], true)), "http://127.0.0.1:8080").await;
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://127.0.0.1:8080");
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let closed = get_from(app(&config(&[], false)), "http://127.0.0.1:8080").await;
+        assert!(closed.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+}
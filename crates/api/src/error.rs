@@ -0,0 +1,205 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use dno_core::AppError;
+use serde::Serialize;
+use serde_json::json;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::middleware::current_request_id;
+
+/// An error response with a machine-readable code:
+/// `{"error": {"code": "DNO_NOT_FOUND", "message": "...", "request_id": "..."}}`.
+/// The request id is the one `request_span_middleware` put on the request's
+/// span and `X-Request-Id` header, so a client's report can be found in the logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// SCREAMING_SNAKE_CASE, stable across releases
+    pub code: String,
+    pub message: String,
+    /// Sent as `Retry-After` in seconds
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    request_id: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { status, code: code.into(), message: message.into(), retry_after_secs: None }
+    }
+
+    /// A 404 coded after the missing resource: `not_found("dno", ..)` is `DNO_NOT_FOUND`
+    pub fn not_found(resource: &str, message: impl Into<String>) -> Self {
+        let code = format!("{}_NOT_FOUND", resource.to_uppercase().replace([' ', '-'], "_"));
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "BAD_REQUEST", message)
+    }
+}
+
+/// Codes and statuses come from `AppError::error_code` and `status_code`. The
+/// details of a 500 are logged, not sent: they may name tables or queries.
+impl From<AppError> for ApiError {
+    fn from(err: AppError) -> Self {
+        let status = err.status_code();
+        let code = err.error_code().to_uppercase();
+        let message = if status == StatusCode::INTERNAL_SERVER_ERROR {
+            error!(code = %code, "Request failed: {}", err);
+            "Internal server error".to_string()
+        } else {
+            if status.is_server_error() {
+                warn!(code = %code, "Request failed: {}", err);
+            }
+            err.to_string()
+        };
+        let retry_after_secs = match &err {
+            AppError::Conflict { retry_after_secs, .. } => *retry_after_secs,
+            AppError::ServiceUnavailable(open) => Some(open.retry_in.as_secs().max(1)),
+            _ => None,
+        };
+        Self { status, code, message, retry_after_secs }
+    }
+}
+
+/// For helpers that still answer with a bare status: the code is the status'
+/// name, e.g. `UNSUPPORTED_MEDIA_TYPE`
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        let reason = status.canonical_reason().unwrap_or("Error");
+        let code = reason.to_uppercase().replace([' ', '-'], "_").replace('\'', "");
+        Self::new(status, code, reason)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let request_id = current_request_id().unwrap_or_else(|| Uuid::new_v4().to_string());
+        let body = ErrorBody { code: &self.code, message: &self.message, request_id };
+        let mut response = (self.status, Json(json!({ "error": body }))).into_response();
+        if let Some(secs) = self.retry_after_secs {
+            response.headers_mut().insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{request_span_middleware, REQUEST_ID_HEADER};
+    use axum::{body::Body, http::{Request, Uri}, Router};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+    use tracing::instrument::WithSubscriber;
+
+    /// Log output of a `fmt` subscriber, kept for assertions
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Stands in for a DNO lookup by slug
+    async fn dno(uri: Uri) -> Result<&'static str, ApiError> {
+        let slug = uri.path().trim_start_matches("/dnos/");
+        match slug {
+            "netze-bw" => Ok("Netze BW"),
+            "broken" => Err(AppError::Database(sqlx::Error::ColumnNotFound("arbeitspreis".to_string())).into()),
+            _ => Err(ApiError::not_found("dno", format!("No DNO with slug '{}'", slug))),
+        }
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .fallback(dno)
+            .layer(axum::middleware::from_fn(request_span_middleware))
+    }
+
+    async fn body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_error_body_carries_code_message_and_request_id() {
+        let request = Request::get("/dnos/netze-bv").header(REQUEST_ID_HEADER, "req-4711").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-4711");
+        assert_eq!(
+            body(response).await,
+            json!({ "error": {
+                "code": "DNO_NOT_FOUND",
+                "message": "No DNO with slug 'netze-bv'",
+                "request_id": "req-4711"
+            }})
+        );
+
+        // Without an incoming id the generated one is in both header and body
+        let response = app().oneshot(Request::get("/dnos/westnetz").body(Body::empty()).unwrap()).await.unwrap();
+        let header = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert_eq!(body(response).await["error"]["request_id"], header.as_str());
+    }
+
+    #[test]
+    fn test_app_errors_map_to_codes_and_statuses() {
+        let cases = [
+            (AppError::NotFound("Data entry not found".to_string()), StatusCode::NOT_FOUND, "NOT_FOUND"),
+            (AppError::BadRequest("Invalid cursor".to_string()), StatusCode::BAD_REQUEST, "BAD_REQUEST"),
+            (AppError::Forbidden("admin".to_string()), StatusCode::FORBIDDEN, "FORBIDDEN"),
+            (AppError::Database(sqlx::Error::PoolTimedOut), StatusCode::SERVICE_UNAVAILABLE, "DATABASE_UNAVAILABLE"),
+        ];
+        for (error, status, code) in cases {
+            let api = ApiError::from(error);
+            assert_eq!((api.status, api.code.as_str()), (status, code));
+        }
+
+        let conflict = ApiError::from(AppError::Conflict { message: "locked".to_string(), retry_after_secs: Some(5) });
+        assert_eq!((conflict.code.as_str(), conflict.retry_after_secs), ("CONFLICT", Some(5)));
+        assert_eq!(ApiError::from(StatusCode::UNSUPPORTED_MEDIA_TYPE).code, "UNSUPPORTED_MEDIA_TYPE");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_correlates_response_and_logs() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        let request = Request::get("/dnos/broken").header(REQUEST_ID_HEADER, "req-0815").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).with_subscriber(subscriber).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let error = body(response).await["error"].clone();
+        assert_eq!(error["code"], "DATABASE_ERROR");
+        assert_eq!(error["message"], "Internal server error");
+        assert_eq!(error["request_id"], "req-0815");
+
+        let logged = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = logged.lines().find(|line| line.contains("arbeitspreis")).expect("error not logged");
+        assert!(line.contains("correlation_id=req-0815"), "{}", line);
+        assert!(line.contains("DATABASE_ERROR"), "{}", line);
+    }
+}
@@ -0,0 +1,170 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+/// Largest response body hashed for an ETag; search pages are far below this
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Weak ETag of a response: the request's cache identity (path and query,
+/// which include the API version and every filter) and a checksum of the
+/// body built from the cached values. It changes whenever the cached data does.
+pub fn weak_etag(cache_key: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(cache_key.as_bytes());
+    hasher.update([0]);
+    hasher.update(Sha256::digest(body));
+    let digest = format!("{:x}", hasher.finalize());
+    format!("W/\"{}\"", &digest[..32])
+}
+
+/// Whether `If-None-Match` lists `etag`, compared weakly as RFC 9110 asks for
+/// conditional GETs; `*` matches any current representation
+fn none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// Tag successful GET responses and answer `If-None-Match` with
+/// `304 Not Modified` when the client already has the current body.
+/// Other methods and non-200 responses pass through untouched.
+pub async fn etag_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let cache_key = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_default();
+    let conditional = request.headers().clone();
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Could not buffer response of {} for its ETag: {}", cache_key, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = weak_etag(&cache_key, &body);
+    let value = HeaderValue::from_str(&etag).expect("hex ETag is a valid header value");
+
+    if none_match(&conditional, &etag) {
+        // Headers a 200 would have sent that still describe the response, without a body
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        for (name, value) in parts.headers.iter() {
+            if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                not_modified.headers_mut().append(name, value.clone());
+            }
+        }
+        not_modified.headers_mut().insert(header::ETAG, value);
+        return not_modified;
+    }
+
+    parts.headers.insert(header::ETAG, value);
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, middleware, routing::get, Json, Router};
+    use dno_core::cache::{CacheLayer, MemoryCache};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const SEARCH_KEY: &str = "search:netzentgelte:netze-bw:2024";
+
+    /// A search endpoint answering from the cache, like the repositories do
+    fn app(cache: Arc<MemoryCache>) -> Router {
+        async fn search(State(cache): State<Arc<MemoryCache>>) -> Json<Value> {
+            let results: Option<Value> = cache.get(SEARCH_KEY).await.unwrap();
+            Json(json!({ "total": 1, "results": results }))
+        }
+
+        Router::new()
+            .route("/api/v1/search/", get(search))
+            .route_layer(middleware::from_fn(etag_middleware))
+            .with_state(cache)
+    }
+
+    async fn get_search(app: &Router, if_none_match: Option<&str>) -> Response {
+        let mut request = Request::get("/api/v1/search/?dno_name=Netze%20BW&year=2024");
+        if let Some(tag) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, tag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    fn etag_of(response: &Response) -> String {
+        response.headers()[header::ETAG].to_str().unwrap().to_string()
+    }
+
+    async fn store(cache: &MemoryCache, leistung: &str) {
+        let entry = json!([{ "voltage_level": "MS", "leistung": leistung }]);
+        cache.set(SEARCH_KEY, &entry, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_matching_if_none_match_is_not_modified() {
+        let cache = Arc::new(MemoryCache::new());
+        store(&cache, "58.21").await;
+        let app = app(cache);
+
+        let first = get_search(&app, None).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = etag_of(&first);
+        assert!(etag.starts_with("W/\""));
+        assert!(!to_bytes(first.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        let second = get_search(&app, Some(&etag)).await;
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(etag_of(&second), etag);
+        assert!(to_bytes(second.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        // A strong form of the same tag and lists containing it match as well
+        let strong = etag.trim_start_matches("W/").to_string();
+        assert_eq!(get_search(&app, Some(&strong)).await.status(), StatusCode::NOT_MODIFIED);
+        let listed = format!("\"stale\", {}", etag);
+        assert_eq!(get_search(&app, Some(&listed)).await.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn test_etag_changes_when_cached_data_changes() {
+        let cache = Arc::new(MemoryCache::new());
+        store(&cache, "58.21").await;
+        let app = app(cache.clone());
+        let before = etag_of(&get_search(&app, None).await);
+
+        store(&cache, "61.07").await;
+        let after = get_search(&app, Some(&before)).await;
+        assert_eq!(after.status(), StatusCode::OK);
+        assert_ne!(etag_of(&after), before);
+        let body: Value = serde_json::from_slice(&to_bytes(after.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["results"][0]["leistung"], "61.07");
+    }
+
+    #[test]
+    fn test_etag_depends_on_the_request() {
+        let body = br#"{"total":0,"results":[]}"#;
+        assert_eq!(weak_etag("/api/v1/search/?year=2024", body), weak_etag("/api/v1/search/?year=2024", body));
+        assert_ne!(weak_etag("/api/v1/search/?year=2024", body), weak_etag("/api/v2/search/?year=2024", body));
+    }
+}
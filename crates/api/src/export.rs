@@ -0,0 +1,401 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dno_core::models::SearchResult;
+use rust_xlsxwriter::Workbook;
+use serde_json::Value;
+use tracing::warn;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+pub const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// Columns of every export, in this order. Fields of a data type that has
+/// none of them (`netzentgelte` and `hlzf` have all theirs listed) follow
+/// sorted by name.
+pub const EXPORT_COLUMNS: [&str; 20] = [
+    "id",
+    "dno",
+    "dno_slug",
+    "region",
+    "year",
+    "data_type",
+    "status",
+    "voltage_level",
+    "season",
+    "leistung",
+    "arbeit",
+    "leistung_unter_2500h",
+    "arbeit_unter_2500h",
+    "ht",
+    "nt",
+    "start_date",
+    "end_date",
+    "source_url",
+    "confidence",
+    "last_updated",
+];
+
+/// Columns written as numbers in workbooks; prices arrive as decimal strings
+const NUMERIC_COLUMNS: [&str; 6] = ["year", "leistung", "arbeit", "leistung_unter_2500h", "arbeit_unter_2500h", "confidence"];
+
+/// Representation of a search response, from `?format=` or else the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    /// `None` for a format name this API doesn't serve
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "xlsx" | "excel" => Some(Self::Xlsx),
+            _ => None,
+        }
+    }
+
+    /// The first `Accept` type naming a spreadsheet format; anything else is JSON
+    fn from_accept(accept: &str) -> Self {
+        accept
+            .split(',')
+            .map(|media| media.split(';').next().unwrap_or_default().trim())
+            .find_map(|media| match media {
+                "text/csv" => Some(Self::Csv),
+                XLSX_CONTENT_TYPE => Some(Self::Xlsx),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// `?format=csv|xlsx|json` wins over `Accept`; an unknown `format` is a 400
+impl<S: Send + Sync> FromRequestParts<S> for ExportFormat {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let requested = parts
+            .uri
+            .query()
+            .and_then(|query| url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == "format"));
+        if let Some((_, name)) = requested {
+            return Self::parse(&name).ok_or(StatusCode::BAD_REQUEST);
+        }
+        Ok(parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .map(Self::from_accept)
+            .unwrap_or_default())
+    }
+}
+
+/// Search results flattened to one row per result under a fixed header
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportTable {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ExportTable {
+    /// Each result's `data` is `{"<data type>": {field: value}}`; its fields
+    /// become columns next to the DNO, year and source columns
+    pub fn from_results(results: &[SearchResult]) -> Self {
+        let records: Vec<Vec<(String, Value)>> = results.iter().map(record).collect();
+
+        let mut header: Vec<String> = EXPORT_COLUMNS.iter().map(|column| column.to_string()).collect();
+        let mut extra: Vec<String> = records
+            .iter()
+            .flatten()
+            .map(|(column, _)| column.clone())
+            .filter(|column| !EXPORT_COLUMNS.contains(&column.as_str()))
+            .collect();
+        extra.sort();
+        extra.dedup();
+        header.extend(extra);
+
+        let rows = records
+            .into_iter()
+            .map(|record| {
+                header
+                    .iter()
+                    .map(|column| {
+                        record
+                            .iter()
+                            .find(|(name, _)| name == column)
+                            .map(|(_, value)| value.clone())
+                            .unwrap_or(Value::Null)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self { header, rows }
+    }
+
+    pub fn to_csv(&self) -> Result<Vec<u8>, csv::Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.header)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(cell_text))?;
+        }
+        writer.into_inner().map_err(|e| e.into_error().into())
+    }
+
+    pub fn to_xlsx(&self) -> Result<Vec<u8>, rust_xlsxwriter::XlsxError> {
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Ergebnisse")?;
+        for (col, column) in self.header.iter().enumerate() {
+            sheet.write_string(0, col as u16, column)?;
+        }
+        for (row, values) in self.rows.iter().enumerate() {
+            let row = row as u32 + 1;
+            for (col, value) in values.iter().enumerate() {
+                let numeric = NUMERIC_COLUMNS.contains(&self.header[col].as_str());
+                match (numeric, cell_text(value)) {
+                    (_, text) if text.is_empty() => continue,
+                    (true, text) => match text.parse::<f64>() {
+                        Ok(number) => sheet.write_number(row, col as u16, number)?,
+                        Err(_) => sheet.write_string(row, col as u16, &text)?,
+                    },
+                    (false, text) => sheet.write_string(row, col as u16, &text)?,
+                };
+            }
+        }
+        workbook.save_to_buffer()
+    }
+}
+
+/// Flat `(column, value)` pairs of one result
+fn record(result: &SearchResult) -> Vec<(String, Value)> {
+    let source = result.source.as_ref();
+    let mut record = vec![
+        ("id".to_string(), Value::from(result.id.to_string())),
+        ("dno".to_string(), Value::from(result.dno.name.clone())),
+        ("dno_slug".to_string(), Value::from(result.dno.slug.clone())),
+        ("region".to_string(), result.dno.region.clone().map(Value::from).unwrap_or(Value::Null)),
+        ("year".to_string(), Value::from(result.year)),
+        ("data_type".to_string(), Value::from(result.data_type.clone())),
+        ("status".to_string(), Value::from(result.status.clone())),
+        ("source_url".to_string(), source.and_then(|s| s.source_url.clone()).map(Value::from).unwrap_or(Value::Null)),
+        ("confidence".to_string(), source.and_then(|s| s.confidence).map(Value::from).unwrap_or(Value::Null)),
+        ("last_updated".to_string(), Value::from(result.last_updated.to_rfc3339())),
+    ];
+    let fields = result
+        .data
+        .get(&result.data_type)
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten();
+    record.extend(fields.map(|(field, value)| (field.clone(), value.clone())));
+    record
+}
+
+/// Spreadsheet text of a JSON value; `null` is an empty cell
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A CSV or XLSX download of `results`, saved as `<filename>.<extension>`.
+/// `next_cursor` is passed on in `X-Next-Cursor` for paging through exports.
+pub fn export_response(
+    format: ExportFormat,
+    filename: &str,
+    results: &[SearchResult],
+    next_cursor: Option<&str>,
+) -> Result<Response, StatusCode> {
+    let table = ExportTable::from_results(results);
+    let (content_type, body) = match format {
+        ExportFormat::Csv => (CSV_CONTENT_TYPE, table.to_csv().map_err(|e| e.to_string())),
+        ExportFormat::Xlsx => (XLSX_CONTENT_TYPE, table.to_xlsx().map_err(|e| e.to_string())),
+        ExportFormat::Json => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let body = body.map_err(|e| {
+        warn!("Failed to write {} export: {}", format.extension(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let disposition = format!("attachment; filename=\"{}.{}\"", filename, format.extension());
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+            (
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_str(&disposition).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+            ),
+        ],
+        body,
+    )
+        .into_response();
+    if let Some(cursor) = next_cursor.and_then(|cursor| HeaderValue::from_str(cursor).ok()) {
+        response.headers_mut().insert("x-next-cursor", cursor);
+    }
+    Ok(response)
+}
+
+/// Filename-safe form of `parts` joined by `_`, e.g. `netze-bw_netzentgelte_2024`
+pub fn export_filename(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| {
+            part.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '-' })
+                .collect::<String>()
+        })
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use calamine::{open_workbook_from_rs, Data, Reader, Xlsx};
+    use chrono::{TimeZone, Utc};
+    use dno_core::models::{DnoInfo, SourceInfo};
+    use serde_json::json;
+    use std::io::Cursor;
+    use uuid::Uuid;
+
+    fn result(data_type: &str, data: Value) -> SearchResult {
+        let updated = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        SearchResult {
+            id: Uuid::from_u128(10),
+            dno: DnoInfo {
+                id: Uuid::from_u128(1),
+                name: "Netze BW".to_string(),
+                slug: "netze-bw".to_string(),
+                region: Some("Baden-Württemberg".to_string()),
+            },
+            year: 2024,
+            data_type: data_type.to_string(),
+            status: "verified".to_string(),
+            data,
+            source: Some(SourceInfo {
+                id: Uuid::from_u128(20),
+                file_type: "file".to_string(),
+                source_url: Some("https://www.netze-bw.de/preisblatt-2024.pdf".to_string()),
+                file_hash: None,
+                page: Some(2),
+                confidence: Some(0.97),
+                extracted_at: updated,
+            }),
+            last_updated: updated,
+            first_seen_at: None,
+            last_verified_at: None,
+            version: None,
+            superseded_by: None,
+            crawled_at: Some(updated),
+            age_days: None,
+        }
+    }
+
+    fn sample() -> Vec<SearchResult> {
+        vec![
+            result(
+                "netzentgelte",
+                json!({"netzentgelte": {
+                    "voltage_level": "MS",
+                    "leistung": "58.21",
+                    "arbeit": "1.26",
+                    "leistung_unter_2500h": null,
+                    "arbeit_unter_2500h": null
+                }}),
+            ),
+            result(
+                "hlzf",
+                json!({"hlzf": {
+                    "season": "winter",
+                    "voltage_level": "NS",
+                    "ht": "08:00-20:00",
+                    "nt": null,
+                    "start_date": "2024-01-01",
+                    "end_date": "2024-02-28"
+                }}),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_csv_has_stable_header_and_flattened_rows() {
+        let csv = String::from_utf8(ExportTable::from_results(&sample()).to_csv().unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "id,dno,dno_slug,region,year,data_type,status,voltage_level,season,leistung,arbeit,\
+             leistung_unter_2500h,arbeit_unter_2500h,ht,nt,start_date,end_date,source_url,confidence,last_updated"
+        );
+        assert_eq!(
+            lines[1],
+            "00000000-0000-0000-0000-00000000000a,Netze BW,netze-bw,Baden-Württemberg,2024,netzentgelte,verified,\
+             MS,,58.21,1.26,,,,,,,https://www.netze-bw.de/preisblatt-2024.pdf,0.97,2024-03-01T12:00:00+00:00"
+        );
+        assert!(lines[2].contains(",hlzf,verified,NS,winter,,,,,08:00-20:00,,2024-01-01,2024-02-28,"));
+
+        // No results still gives the header
+        let empty = ExportTable::from_results(&[]).to_csv().unwrap();
+        assert_eq!(String::from_utf8(empty).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_unknown_data_type_fields_follow_known_columns() {
+        let table = ExportTable::from_results(&[result("konzessionsabgaben", json!({"konzessionsabgaben": {"tarif": "Sondervertrag", "betrag": "0.11"}}))]);
+        assert_eq!(&table.header[EXPORT_COLUMNS.len()..], ["betrag", "tarif"]);
+        assert_eq!(table.rows[0][EXPORT_COLUMNS.len()], json!("0.11"));
+    }
+
+    #[test]
+    fn test_xlsx_is_a_readable_workbook() {
+        let bytes = ExportTable::from_results(&sample()).to_xlsx().unwrap();
+        let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes)).unwrap();
+        let sheet = workbook.worksheet_range("Ergebnisse").unwrap();
+
+        assert_eq!(sheet.height(), 3);
+        assert_eq!(sheet.get_value((0, 0)), Some(&Data::String("id".to_string())));
+        assert_eq!(sheet.get_value((1, 1)), Some(&Data::String("Netze BW".to_string())));
+        // Prices are numbers, not text
+        assert_eq!(sheet.get_value((1, 9)), Some(&Data::Float(58.21)));
+        assert_eq!(sheet.get_value((1, 4)), Some(&Data::Float(2024.0)));
+    }
+
+    async fn format(uri: &str, accept: Option<&str>) -> Result<ExportFormat, StatusCode> {
+        let mut request = Request::post(uri);
+        if let Some(accept) = accept {
+            request = request.header(header::ACCEPT, accept);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        ExportFormat::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_format_from_query_or_accept_defaults_to_json() {
+        assert_eq!(format("/api/v1/search/dno", None).await, Ok(ExportFormat::Json));
+        assert_eq!(format("/api/v1/search/dno", Some("application/json")).await, Ok(ExportFormat::Json));
+        assert_eq!(format("/api/v1/search/dno", Some("text/csv;q=0.9, */*")).await, Ok(ExportFormat::Csv));
+        assert_eq!(format("/api/v1/search/dno", Some(XLSX_CONTENT_TYPE)).await, Ok(ExportFormat::Xlsx));
+        assert_eq!(format("/api/v1/search/dno?format=xlsx", Some("text/csv")).await, Ok(ExportFormat::Xlsx));
+        assert_eq!(format("/api/v1/search/dno?format=pdf", None).await, Err(StatusCode::BAD_REQUEST));
+        assert_eq!(export_filename(&["netze-bw", "all", "2024"]), "netze-bw_all_2024");
+        assert_eq!(export_filename(&["Stadtwerke München", ""]), "stadtwerke-m-nchen");
+    }
+}
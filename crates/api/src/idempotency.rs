@@ -0,0 +1,306 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use dno_core::cache::{CacheLayer, DistributedLock, LockGuard};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+use crate::{AppState, AuthenticatedUser};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const IDEMPOTENT_REPLAY_HEADER: &str = "idempotent-replayed";
+
+/// Largest request/response body buffered for idempotent replay
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Response captured for the first request carrying an idempotency key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+    /// Hash of the original request body, so a reused key with a different payload is rejected
+    pub request_hash: String,
+}
+
+/// Idempotency key -> response mapping kept in the shared cache
+#[derive(Clone)]
+pub struct IdempotencyStore<C: CacheLayer> {
+    cache: Arc<C>,
+    lock: DistributedLock<C>,
+    window: Duration,
+    lock_ttl: Duration,
+    lock_wait: Duration,
+}
+
+enum Claim {
+    Acquired(LockGuard),
+    Replay(StoredResponse),
+    InProgress,
+}
+
+impl<C: CacheLayer> IdempotencyStore<C> {
+    pub fn new(cache: Arc<C>) -> Self {
+        Self {
+            lock: DistributedLock::new(cache.clone()),
+            cache,
+            window: Duration::from_secs(86400), // Replay window: 24 hours
+            lock_ttl: Duration::from_secs(30),  // Short lock while the first request runs
+            lock_wait: Duration::from_secs(5),  // How long a concurrent duplicate waits for the result
+        }
+    }
+
+    fn result_key(scope: &str) -> String {
+        format!("idempotency:result:{}", scope)
+    }
+
+    fn lock_key(scope: &str) -> String {
+        format!("idempotency:lock:{}", scope)
+    }
+
+    async fn claim(&self, scope: &str) -> Result<Claim, dno_core::cache::CacheError> {
+        if let Some(stored) = self.cache.get::<StoredResponse>(&Self::result_key(scope)).await? {
+            return Ok(Claim::Replay(stored));
+        }
+
+        if let Some(guard) = self.lock.try_acquire(&Self::lock_key(scope), self.lock_ttl).await? {
+            return Ok(Claim::Acquired(guard));
+        }
+
+        // Another request with the same key is running; wait briefly for its result
+        let deadline = tokio::time::Instant::now() + self.lock_wait;
+        while tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if let Some(stored) = self.cache.get::<StoredResponse>(&Self::result_key(scope)).await? {
+                return Ok(Claim::Replay(stored));
+            }
+            if !self.cache.exists(&Self::lock_key(scope)).await? {
+                // First request failed and released the lock; let this one execute
+                if let Some(guard) = self.lock.try_acquire(&Self::lock_key(scope), self.lock_ttl).await? {
+                    return Ok(Claim::Acquired(guard));
+                }
+            }
+        }
+
+        Ok(Claim::InProgress)
+    }
+
+    async fn complete(&self, scope: &str, guard: LockGuard, stored: Option<&StoredResponse>) {
+        if let Some(stored) = stored {
+            if let Err(e) = self.cache.set(&Self::result_key(scope), stored, Some(self.window)).await {
+                warn!("Failed to store idempotent response: {}", e);
+            }
+        }
+        self.lock.release(guard).await;
+    }
+
+    /// Run `handler` at most once per `scope` within the replay window. Repeats
+    /// get the original response; a concurrent duplicate waits for it. Server
+    /// errors are not stored, so a retry after a 5xx executes again.
+    pub async fn run<F, Fut>(&self, scope: &str, request: Request, handler: F) -> Response
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let (parts, body) = request.into_parts();
+        let body = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+        };
+        let request_hash = format!("{:x}", Sha256::digest(&body));
+        let request = Request::from_parts(parts, Body::from(body));
+
+        let guard = match self.claim(scope).await {
+            Ok(Claim::Acquired(guard)) => guard,
+            Ok(Claim::Replay(stored)) => {
+                if stored.request_hash != request_hash {
+                    return error_response(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "idempotency_key_reused",
+                        "Idempotency-Key was already used with a different request body",
+                    );
+                }
+                debug!("Replaying idempotent response for {}", scope);
+                return replay(stored);
+            }
+            Ok(Claim::InProgress) => {
+                return error_response(
+                    StatusCode::CONFLICT,
+                    "idempotency_key_in_progress",
+                    "A request with this Idempotency-Key is still being processed",
+                );
+            }
+            Err(e) => {
+                // Fail open like the repositories: without the cache we cannot dedupe, but we can still serve
+                warn!("Idempotency cache unavailable, executing without deduplication: {}", e);
+                return handler(request).await;
+            }
+        };
+
+        let response = handler(request).await;
+        let (parts, body) = response.into_parts();
+        let body = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                self.complete(scope, guard, None).await;
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+        let stored = (!parts.status.is_server_error())
+            .then(|| String::from_utf8(body.to_vec()).ok())
+            .flatten()
+            .map(|text| StoredResponse {
+                status: parts.status.as_u16(),
+                content_type: parts
+                    .headers
+                    .get(axum::http::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string),
+                body: text,
+                request_hash,
+            });
+        self.complete(scope, guard, stored.as_ref()).await;
+
+        Response::from_parts(parts, Body::from(body))
+    }
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+    if let Some(content_type) = stored.content_type.and_then(|ct| HeaderValue::from_str(&ct).ok()) {
+        response.headers_mut().insert(axum::http::header::CONTENT_TYPE, content_type);
+    }
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAY_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+fn error_response(status: StatusCode, error: &str, message: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "error": error,
+            "message": message,
+            "details": {},
+            "request_id": uuid::Uuid::new_v4().to_string()
+        })),
+    )
+        .into_response()
+}
+
+/// Middleware honouring the `Idempotency-Key` header on crawl and data-mutation
+/// endpoints. Keys are scoped per user, method and path. Requests without the
+/// header pass through unchanged.
+pub async fn idempotency_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(str::to_string)
+    else {
+        return next.run(request).await;
+    };
+
+    if key.len() > 255 {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_idempotency_key",
+            "Idempotency-Key must be at most 255 characters",
+        );
+    }
+
+    let user = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|u| u.id.to_string())
+        .unwrap_or_else(|| "anonymous".to_string());
+    let scope = format!(
+        "{}:{:x}",
+        user,
+        Sha256::digest(format!("{} {} {}", request.method(), request.uri().path(), key))
+    );
+
+    state.idempotency.run(&scope, request, |request| next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dno_core::cache::MemoryCache;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn crawl_request() -> Request {
+        Request::post("/admin/crawl/trigger")
+            .header(IDEMPOTENCY_KEY_HEADER, "retry-123")
+            .body(Body::from(r#"{"dno_name":"Netze BW","year":2024}"#))
+            .unwrap()
+    }
+
+    /// Stands in for the crawl handler: every execution creates a new session
+    async fn start_crawl(sessions: Arc<AtomicUsize>) -> Response {
+        let session = sessions.fetch_add(1, Ordering::SeqCst) + 1;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Json(json!({ "session_id": session })).into_response()
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = to_bytes(response.into_body(), MAX_BODY_BYTES).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_repeated_keyed_crawl_creates_one_session() {
+        let store = IdempotencyStore::new(Arc::new(MemoryCache::new()));
+        let sessions = Arc::new(AtomicUsize::new(0));
+
+        // Concurrent duplicates (client retry racing the original) and a later repeat
+        let (first, second) = tokio::join!(
+            store.run("user:crawl", crawl_request(), |_| start_crawl(sessions.clone())),
+            store.run("user:crawl", crawl_request(), |_| start_crawl(sessions.clone())),
+        );
+        let third = store
+            .run("user:crawl", crawl_request(), |_| start_crawl(sessions.clone()))
+            .await;
+
+        assert_eq!(sessions.load(Ordering::SeqCst), 1);
+        assert!(third.headers().contains_key(IDEMPOTENT_REPLAY_HEADER));
+
+        let first = body_text(first).await;
+        assert_eq!(first, body_text(second).await);
+        assert_eq!(first, body_text(third).await);
+    }
+
+    #[tokio::test]
+    async fn test_reused_key_with_different_body_is_rejected() {
+        let store = IdempotencyStore::new(Arc::new(MemoryCache::new()));
+        let sessions = Arc::new(AtomicUsize::new(0));
+
+        store.run("user:crawl", crawl_request(), |_| start_crawl(sessions.clone())).await;
+
+        let different = Request::post("/admin/crawl/trigger")
+            .header(IDEMPOTENCY_KEY_HEADER, "retry-123")
+            .body(Body::from(r#"{"dno_name":"Westnetz","year":2024}"#))
+            .unwrap();
+        let response = store.run("user:crawl", different, |_| start_crawl(sessions.clone())).await;
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(sessions.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,456 @@
+use chrono::Utc;
+use dno_core::cache::CacheLayer;
+use dno_core::models::{TokenPair, User, UserRole};
+use dno_core::AppError;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+use crate::middleware::Claims;
+
+/// `token_type` claim of tokens that authenticate requests
+pub const ACCESS_TOKEN: &str = "access";
+/// `token_type` claim of tokens that can only be exchanged at `/auth/refresh`
+pub const REFRESH_TOKEN: &str = "refresh";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("invalid or expired token")]
+    Invalid,
+    #[error("token has been revoked")]
+    Revoked,
+    #[error("refresh token was already used; its token family has been revoked")]
+    Reused,
+    #[error("revocation store unavailable: {0}")]
+    Store(String),
+    #[error("failed to sign token: {0}")]
+    Signing(#[from] jsonwebtoken::errors::Error),
+}
+
+impl From<TokenError> for AppError {
+    fn from(error: TokenError) -> Self {
+        match error {
+            TokenError::Invalid | TokenError::Revoked | TokenError::Reused => AppError::Unauthorized(error.to_string()),
+            TokenError::Store(message) => AppError::Cache(message),
+            TokenError::Signing(e) => AppError::InternalServerError(e.to_string()),
+        }
+    }
+}
+
+/// Issues and checks access/refresh token pairs. Every login starts a token
+/// family; each refresh rotates the family's refresh token, and presenting
+/// one that was rotated out revokes the whole family. Revoked token ids and
+/// families live in the shared cache until the tokens would have expired.
+#[derive(Clone)]
+pub struct JwtService<C: CacheLayer> {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    access_ttl: i64,
+    refresh_ttl: i64,
+    cache: Arc<C>,
+}
+
+impl<C: CacheLayer> JwtService<C> {
+    /// Token lifetimes in seconds
+    pub fn new(secret: &str, access_ttl: i64, refresh_ttl: i64, cache: Arc<C>) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret.as_ref()),
+            decoding: DecodingKey::from_secret(secret.as_ref()),
+            access_ttl,
+            refresh_ttl,
+            cache,
+        }
+    }
+
+    fn revoked_token_key(jti: &str) -> String {
+        format!("auth:revoked:jti:{}", jti)
+    }
+
+    fn revoked_family_key(family_id: &str) -> String {
+        format!("auth:revoked:family:{}", family_id)
+    }
+
+    /// The family's one refresh token that may still be exchanged
+    fn current_refresh_key(family_id: &str) -> String {
+        format!("auth:refresh:family:{}", family_id)
+    }
+
+    /// Counter claimed by the one exchange a refresh token allows
+    fn redeemed_refresh_key(jti: &str) -> String {
+        format!("auth:refresh:redeemed:{}", jti)
+    }
+
+    fn store_error(e: dno_core::cache::CacheError) -> TokenError {
+        TokenError::Store(e.to_string())
+    }
+
+    /// A new access/refresh pair for `user`, starting a new token family
+    pub async fn issue_token_pair(&self, user: &User, session_id: Uuid) -> Result<TokenPair, TokenError> {
+        self.issue_in_family(&Self::subject(user, session_id.to_string(), Uuid::new_v4().to_string())).await
+    }
+
+    /// The pair that replaces a refresh token `redeem_refresh_token` accepted.
+    /// Role, email and name come from `user` as it is now, not from the old token.
+    pub async fn issue_refreshed(&self, user: &User, redeemed: &Claims) -> Result<TokenPair, TokenError> {
+        if redeemed.sub != user.id.to_string() {
+            return Err(TokenError::Invalid);
+        }
+        self.issue_in_family(&Self::subject(user, redeemed.session_id.clone(), redeemed.family_id.clone())).await
+    }
+
+    fn subject(user: &User, session_id: String, family_id: String) -> Claims {
+        Claims {
+            sub: user.id.to_string(),
+            email: user.email.clone(),
+            name: user.name.clone(),
+            role: match user.role {
+                UserRole::Pending => "pending".to_string(),
+                UserRole::User => "user".to_string(),
+                UserRole::Admin => "admin".to_string(),
+            },
+            session_id,
+            jti: String::new(),
+            family_id,
+            token_type: String::new(),
+            exp: 0,
+            iat: 0,
+        }
+    }
+
+    /// Sign a pair with the subject and family of `subject` and make its
+    /// refresh token the family's current one
+    async fn issue_in_family(&self, subject: &Claims) -> Result<TokenPair, TokenError> {
+        let now = Utc::now().timestamp();
+        let claims = |token_type: &str, ttl: i64| Claims {
+            jti: Uuid::new_v4().to_string(),
+            token_type: token_type.to_string(),
+            iat: now,
+            exp: now + ttl,
+            ..subject.clone()
+        };
+        let access = claims(ACCESS_TOKEN, self.access_ttl);
+        let refresh = claims(REFRESH_TOKEN, self.refresh_ttl);
+
+        let header = Header::new(Algorithm::HS256);
+        let pair = TokenPair {
+            access_token: encode(&header, &access, &self.encoding)?,
+            refresh_token: encode(&header, &refresh, &self.encoding)?,
+            expires_in: self.access_ttl,
+        };
+        self.cache
+            .set(&Self::current_refresh_key(&refresh.family_id), &refresh.jti, Some(Self::ttl(self.refresh_ttl)))
+            .await
+            .map_err(Self::store_error)?;
+        Ok(pair)
+    }
+
+    fn ttl(seconds: i64) -> Duration {
+        Duration::from_secs(seconds.max(1) as u64)
+    }
+
+    /// Signature and expiry only; no revocation check
+    fn decode(&self, token: &str) -> Result<Claims, TokenError> {
+        decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims)
+            .map_err(|_| TokenError::Invalid)
+    }
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool, TokenError> {
+        let token = self.cache.exists(&Self::revoked_token_key(&claims.jti)).await.map_err(Self::store_error)?;
+        if token {
+            return Ok(true);
+        }
+        self.cache.exists(&Self::revoked_family_key(&claims.family_id)).await.map_err(Self::store_error)
+    }
+
+    /// Claims of a validly signed, unexpired token that was not revoked.
+    /// A revocation store that cannot be reached fails the check.
+    pub async fn verify_jwt_token(&self, token: &str) -> Result<Claims, TokenError> {
+        let claims = self.decode(token)?;
+        if self.is_revoked(&claims).await? {
+            return Err(TokenError::Revoked);
+        }
+        Ok(claims)
+    }
+
+    /// `verify_jwt_token` for request authentication: refresh tokens are refused
+    pub async fn verify_access_token(&self, token: &str) -> Result<Claims, TokenError> {
+        let claims = self.verify_jwt_token(token).await?;
+        if claims.token_type != ACCESS_TOKEN {
+            return Err(TokenError::Invalid);
+        }
+        Ok(claims)
+    }
+
+    /// Accept the family's current refresh token for exchange and revoke it;
+    /// pass the returned claims to `issue_refreshed`. A refresh token that was
+    /// rotated out or revoked is taken as stolen: the whole family is revoked.
+    /// Each token is redeemed at most once, also by concurrent requests; the
+    /// losers of such a race count as reuse.
+    pub async fn redeem_refresh_token(&self, refresh_token: &str) -> Result<Claims, TokenError> {
+        let claims = self.decode(refresh_token)?;
+        if claims.token_type != REFRESH_TOKEN {
+            return Err(TokenError::Invalid);
+        }
+        let family_revoked = self
+            .cache
+            .exists(&Self::revoked_family_key(&claims.family_id))
+            .await
+            .map_err(Self::store_error)?;
+        if family_revoked {
+            return Err(TokenError::Revoked);
+        }
+
+        let current: Option<String> = self
+            .cache
+            .get(&Self::current_refresh_key(&claims.family_id))
+            .await
+            .map_err(Self::store_error)?;
+        if current.as_deref() != Some(claims.jti.as_str()) || self.is_revoked(&claims).await? || !self.claim_redemption(&claims).await? {
+            warn!(
+                "Refresh token {} of family {} (user {}) was used again; revoking the family",
+                claims.jti, claims.family_id, claims.sub
+            );
+            self.revoke_family(&claims.family_id).await?;
+            return Err(TokenError::Reused);
+        }
+
+        self.revoke_jti(&claims.jti, claims.exp).await?;
+        Ok(claims)
+    }
+
+    /// INCR is atomic, so of concurrent exchanges of one token only the first sees 1
+    async fn claim_redemption(&self, claims: &Claims) -> Result<bool, TokenError> {
+        let remaining = claims.exp - Utc::now().timestamp();
+        let count = self
+            .cache
+            .incr(&Self::redeemed_refresh_key(&claims.jti), 1, Some(Self::ttl(remaining)))
+            .await
+            .map_err(Self::store_error)?;
+        Ok(count == 1)
+    }
+
+    async fn revoke_jti(&self, jti: &str, exp: i64) -> Result<(), TokenError> {
+        // Kept until the token would have expired anyway
+        let remaining = exp - Utc::now().timestamp();
+        self.cache
+            .set(&Self::revoked_token_key(jti), &true, Some(Self::ttl(remaining)))
+            .await
+            .map_err(Self::store_error)
+    }
+
+    /// Revoke one token, e.g. the access token of a logout
+    pub async fn revoke(&self, token: &str) -> Result<(), TokenError> {
+        let claims = self.decode(token)?;
+        self.revoke_jti(&claims.jti, claims.exp).await
+    }
+
+    /// Revoke every access and refresh token of a family
+    pub async fn revoke_family(&self, family_id: &str) -> Result<(), TokenError> {
+        self.cache
+            .set(&Self::revoked_family_key(family_id), &true, Some(Self::ttl(self.refresh_ttl)))
+            .await
+            .map_err(Self::store_error)?;
+        self.cache.delete(&Self::current_refresh_key(family_id)).await.map_err(Self::store_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dno_core::cache::MemoryCache;
+
+    fn service() -> JwtService<MemoryCache> {
+        JwtService::new("test-secret", 3600, 2_592_000, Arc::new(MemoryCache::new()))
+    }
+
+    fn user() -> User {
+        let now = Utc::now();
+        User {
+            id: Uuid::new_v4(),
+            email: "netzbetreiber@example.de".to_string(),
+            password_hash: String::new(),
+            name: "Erika Mustermann".to_string(),
+            role: UserRole::User,
+            profile_picture_url: None,
+            is_active: true,
+            email_verified: true,
+            verification_status: None,
+            approved_by: None,
+            approved_at: None,
+            rejected_at: None,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+
+    /// Redeem and reissue, as `/auth/refresh` does
+    async fn refresh<C: CacheLayer>(jwt: &JwtService<C>, user: &User, refresh_token: &str) -> Result<TokenPair, TokenError> {
+        let redeemed = jwt.redeem_refresh_token(refresh_token).await?;
+        jwt.issue_refreshed(user, &redeemed).await
+    }
+
+    /// `MemoryCache` that yields before every operation, so concurrent
+    /// callers interleave the way they do against Redis
+    #[derive(Clone, Default)]
+    struct YieldingCache(MemoryCache);
+
+    #[async_trait::async_trait]
+    impl CacheLayer for YieldingCache {
+        async fn get<T>(&self, key: &str) -> Result<Option<T>, dno_core::cache::CacheError>
+        where
+            T: serde::de::DeserializeOwned + Send,
+        {
+            tokio::task::yield_now().await;
+            self.0.get(key).await
+        }
+
+        async fn set<T>(&self, key: &str, value: &T, ttl: Option<Duration>) -> Result<(), dno_core::cache::CacheError>
+        where
+            T: serde::Serialize + Send + Sync,
+        {
+            tokio::task::yield_now().await;
+            self.0.set(key, value, ttl).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<(), dno_core::cache::CacheError> {
+            tokio::task::yield_now().await;
+            self.0.delete(key).await
+        }
+
+        async fn exists(&self, key: &str) -> Result<bool, dno_core::cache::CacheError> {
+            tokio::task::yield_now().await;
+            self.0.exists(key).await
+        }
+
+        async fn invalidate_pattern(&self, pattern: &str) -> Result<u64, dno_core::cache::CacheError> {
+            tokio::task::yield_now().await;
+            self.0.invalidate_pattern(pattern).await
+        }
+
+        async fn mget<T>(&self, keys: &[String]) -> Result<Vec<Option<T>>, dno_core::cache::CacheError>
+        where
+            T: serde::de::DeserializeOwned + Send,
+        {
+            tokio::task::yield_now().await;
+            self.0.mget(keys).await
+        }
+
+        async fn mset<T>(&self, items: &[(String, T)], ttl: Option<Duration>) -> Result<(), dno_core::cache::CacheError>
+        where
+            T: serde::Serialize + Send + Sync,
+        {
+            tokio::task::yield_now().await;
+            self.0.mset(items, ttl).await
+        }
+
+        async fn incr(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, dno_core::cache::CacheError> {
+            tokio::task::yield_now().await;
+            self.0.incr(key, delta, ttl).await
+        }
+
+        async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, dno_core::cache::CacheError> {
+            tokio::task::yield_now().await;
+            self.0.set_nx(key, value, ttl).await
+        }
+
+        async fn delete_if_eq(&self, key: &str, value: &str) -> Result<bool, dno_core::cache::CacheError> {
+            tokio::task::yield_now().await;
+            self.0.delete_if_eq(key, value).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rotates_the_pair() {
+        let jwt = service();
+        let user = user();
+        let first = jwt.issue_token_pair(&user, Uuid::new_v4()).await.unwrap();
+        let access = jwt.verify_access_token(&first.access_token).await.unwrap();
+        // A refresh token does not authenticate requests
+        assert!(matches!(jwt.verify_access_token(&first.refresh_token).await, Err(TokenError::Invalid)));
+
+        let second = refresh(&jwt, &user, &first.refresh_token).await.unwrap();
+        let rotated = jwt.verify_access_token(&second.access_token).await.unwrap();
+        assert_eq!((rotated.sub.as_str(), rotated.family_id.as_str()), (access.sub.as_str(), access.family_id.as_str()));
+        assert_ne!(rotated.jti, access.jti);
+        assert!(matches!(jwt.verify_jwt_token(&first.refresh_token).await, Err(TokenError::Revoked)));
+
+        // The new refresh token rotates again
+        refresh(&jwt, &user, &second.refresh_token).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refreshed_claims_follow_the_user_row() {
+        let jwt = service();
+        let mut user = user();
+        let first = jwt.issue_token_pair(&user, Uuid::new_v4()).await.unwrap();
+
+        // Promoted and renamed since the login
+        user.role = UserRole::Admin;
+        user.name = "Erika Musterfrau".to_string();
+        let second = refresh(&jwt, &user, &first.refresh_token).await.unwrap();
+        let claims = jwt.verify_access_token(&second.access_token).await.unwrap();
+        assert_eq!((claims.role.as_str(), claims.name.as_str()), ("admin", "Erika Musterfrau"));
+
+        // A refresh token only reissues for its own subject
+        let redeemed = jwt.redeem_refresh_token(&second.refresh_token).await.unwrap();
+        let other = self::user();
+        assert!(matches!(jwt.issue_refreshed(&other, &redeemed).await, Err(TokenError::Invalid)));
+    }
+
+    #[tokio::test]
+    async fn test_reusing_a_rotated_refresh_token_revokes_the_family() {
+        let jwt = service();
+        let user = user();
+        let stolen = jwt.issue_token_pair(&user, Uuid::new_v4()).await.unwrap();
+        let legitimate = refresh(&jwt, &user, &stolen.refresh_token).await.unwrap();
+        let unrelated = jwt.issue_token_pair(&user, Uuid::new_v4()).await.unwrap();
+
+        assert!(matches!(refresh(&jwt, &user, &stolen.refresh_token).await, Err(TokenError::Reused)));
+
+        // Every token of the family is dead, including the legitimate holder's
+        assert!(matches!(jwt.verify_access_token(&legitimate.access_token).await, Err(TokenError::Revoked)));
+        assert!(matches!(refresh(&jwt, &user, &legitimate.refresh_token).await, Err(TokenError::Revoked)));
+        // Other logins are untouched
+        assert!(jwt.verify_access_token(&unrelated.access_token).await.is_ok());
+        assert!(refresh(&jwt, &user, &unrelated.refresh_token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refreshes_redeem_a_token_once() {
+        let jwt = JwtService::new("test-secret", 3600, 2_592_000, Arc::new(YieldingCache::default()));
+        let user = user();
+        let pair = jwt.issue_token_pair(&user, Uuid::new_v4()).await.unwrap();
+
+        // A stolen copy racing the legitimate client
+        let (first, second) = tokio::join!(
+            refresh(&jwt, &user, &pair.refresh_token),
+            refresh(&jwt, &user, &pair.refresh_token),
+        );
+        let (won, lost) = match (first, second) {
+            (Ok(won), Err(lost)) | (Err(lost), Ok(won)) => (won, lost),
+            (first, second) => panic!("expected exactly one refresh to succeed, got {:?} and {:?}", first.is_ok(), second.is_ok()),
+        };
+        assert!(matches!(lost, TokenError::Reused));
+        // The race counts as reuse, so the winner's pair is revoked with the family
+        assert!(matches!(jwt.verify_access_token(&won.access_token).await, Err(TokenError::Revoked)));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_tokens_are_rejected() {
+        let jwt = service();
+        let pair = jwt.issue_token_pair(&user(), Uuid::new_v4()).await.unwrap();
+
+        jwt.revoke(&pair.access_token).await.unwrap();
+        assert!(matches!(jwt.verify_access_token(&pair.access_token).await, Err(TokenError::Revoked)));
+        assert!(jwt.verify_jwt_token(&pair.refresh_token).await.is_ok());
+
+        let forged = JwtService::new("other-secret", 3600, 3600, Arc::new(MemoryCache::new()))
+            .issue_token_pair(&user(), Uuid::new_v4())
+            .await
+            .unwrap();
+        assert!(matches!(jwt.verify_access_token(&forged.access_token).await, Err(TokenError::Invalid)));
+    }
+}
@@ -1,26 +1,80 @@
 pub mod routes;
 pub mod middleware;
+pub mod error;
+pub mod client_ip;
+pub mod cors;
+pub mod etag;
+pub mod export;
+pub mod jwt;
+pub mod idempotency;
+pub mod quota;
+pub mod versioning;
+pub mod warmup;
+
+#[cfg(test)]
+mod test_app;
 
 use sqlx::PgPool;
 use std::sync::Arc;
 
 // Re-export commonly used types
-pub use routes::api_routes;
+pub use routes::{api_routes, versioned_api_routes};
+pub use versioning::ApiVersion;
 pub use middleware::{AuthenticatedUser, UserRole};
+pub use client_ip::ClientIp;
+pub use error::ApiError;
 
 // Re-export cache types
-pub use core::cache::RedisCache;
-pub use core::repository::{UserRepository, SearchRepository, DnoRepository};
+pub use dno_core::cache::{DnoWriteLock, RedisCache};
+pub use dno_core::repository::{UserRepository, SearchRepository, DnoRepository, DataTypeRegistry, QueryRepository};
+pub use dno_core::source_manager::SourceManager;
+pub use dno_core::live::LiveSessions;
+pub use dno_core::crawl_job::CrawlJobs;
+pub use dno_core::metrics::MetricsRegistry;
 
 #[derive(Clone)]
 pub struct AppState {
     pub database: PgPool,
     pub config: Arc<AppConfig>,
     pub jwt_secret: String,
+    /// Token pairs, refresh rotation and revocation
+    pub jwt: jwt::JwtService<RedisCache>,
     pub cache: Arc<RedisCache>,
     pub user_repo: UserRepository<RedisCache>,
     pub search_repo: SearchRepository<RedisCache>,
     pub dno_repo: DnoRepository<RedisCache>,
+    /// Free-text query interpretation by the Ollama model
+    pub query_repo: QueryRepository<RedisCache>,
+    pub idempotency: idempotency::IdempotencyStore<RedisCache>,
+    pub data_types: Arc<DataTypeRegistry<RedisCache>>,
+    pub dno_locks: DnoWriteLock<RedisCache>,
+    pub quota: quota::QuotaStore<RedisCache>,
+    /// Downloaded source documents and their audit trail
+    pub sources: Arc<SourceManager>,
+    /// Running crawl sessions, watched through `/crawl/{session_id}/stream`
+    pub live_sessions: LiveSessions,
+    /// Crawl sessions started through `POST /crawl`, stored in the database
+    pub crawl_jobs: CrawlJobs,
+    /// Process-wide Prometheus metrics, served at `/metrics`
+    pub metrics: MetricsRegistry,
+}
+
+impl axum::extract::FromRef<AppState> for MetricsRegistry {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl axum::extract::FromRef<AppState> for LiveSessions {
+    fn from_ref(state: &AppState) -> Self {
+        state.live_sessions.clone()
+    }
+}
+
+impl axum::extract::FromRef<AppState> for CrawlJobs {
+    fn from_ref(state: &AppState) -> Self {
+        state.crawl_jobs.clone()
+    }
 }
 
 impl AppState {
@@ -32,62 +86,121 @@ impl AppState {
     ) -> Self {
         // Create repository instances with shared cache
         let user_repo = UserRepository::new(database.clone(), cache.clone());
+        let jwt = jwt::JwtService::new(
+            &jwt_secret,
+            config.jwt_access_token_expiry,
+            config.jwt_refresh_token_expiry,
+            cache.clone(),
+        );
         let search_repo = SearchRepository::new(database.clone(), cache.clone());
         let dno_repo = DnoRepository::new(database.clone(), cache.clone());
+        let ollama = dno_core::ollama::OllamaService::new(&dno_core::OllamaConfig::from_env())
+            .expect("HTTP client for Ollama could not be built");
+        let query_repo = QueryRepository::new(ollama, cache.clone());
+        let idempotency = idempotency::IdempotencyStore::new(cache.clone());
+        let data_types = Arc::new(DataTypeRegistry::default());
+        let dno_locks = DnoWriteLock::new(cache.clone());
+        let quota = quota::QuotaStore::new(cache.clone(), quota::QuotaPolicy::from_env());
+        let sources = SourceManager::open(&config.storage_path).unwrap_or_else(|e| {
+            tracing::warn!("Starting without stored source file metadata: {}", e);
+            SourceManager::new(&config.storage_path)
+        });
+        let sources = Arc::new(sources.with_autosave_every(dno_core::source_manager::autosave_every_from_env()));
+        let live_sessions = LiveSessions::new();
+        let runner = crawler::job_runner::AdaptiveCrawlRunner::from_env(sources.clone())
+            .expect("Crawler for crawl sessions could not be configured");
+        let crawl_jobs = CrawlJobs::new(database.clone(), Arc::new(runner), live_sessions.clone());
 
         Self {
             database,
             config: Arc::new(config),
             jwt_secret,
+            jwt,
             cache,
             user_repo,
             search_repo,
             dno_repo,
+            query_repo,
+            idempotency,
+            data_types,
+            dno_locks,
+            quota,
+            sources,
+            live_sessions,
+            crawl_jobs,
+            metrics: dno_core::metrics::global(),
         }
     }
 
     /// Initialize Redis cache from configuration
-    pub async fn init_cache(config: &core::CacheConfig) -> Result<Arc<RedisCache>, core::AppError> {
-        let redis_config = core::RedisCacheConfig::from_env()
-            .map_err(|e| core::AppError::Config(format!("Redis config error: {}", e)))?;
+    pub async fn init_cache(config: &dno_core::CacheConfig) -> Result<Arc<RedisCache>, dno_core::AppError> {
+        let redis_config = dno_core::RedisCacheConfig::from_env()
+            .map_err(|e| dno_core::AppError::Config(format!("Redis config error: {}", e)))?;
         
         let cache = RedisCache::new(redis_config).await
-            .map_err(|e| core::AppError::Cache(format!("Failed to connect to Redis: {}", e)))?;
+            .map_err(|e| dno_core::AppError::Cache(format!("Failed to connect to Redis: {}", e)))?;
         
         Ok(Arc::new(cache))
     }
 
-    /// Warm up caches with commonly accessed data
-    pub async fn warm_caches(&self) -> Result<(), core::AppError> {
-        tracing::info!("Starting cache warm-up");
-        
-        // Warm up repositories in parallel
-        let (user_result, search_result, dno_result) = tokio::join!(
-            async { self.user_repo.warm_cache().await },
-            async { self.search_repo.warm_cache().await },
-            async { self.dno_repo.warm_cache().await }
-        );
-        
-        if let Err(e) = user_result {
-            tracing::warn!("User cache warm-up failed: {}", e);
-        }
-        
-        if let Err(e) = search_result {
-            tracing::warn!("Search cache warm-up failed: {}", e);
+    /// Seed the known German DNOs unless `SEED_DNOS=false`, then drop cached DNO lists
+    pub async fn seed_reference_data(&self) -> Result<(), dno_core::AppError> {
+        if !dno_core::seed::seed_enabled() {
+            tracing::info!("DNO seeding disabled via SEED_DNOS");
+            return Ok(());
         }
-        
-        if let Err(e) = dno_result {
-            tracing::warn!("DNO cache warm-up failed: {}", e);
+
+        dno_core::seed::seed_dnos(&self.database).await?;
+        self.dno_repo.invalidate_all_caches().await
+    }
+
+    /// Mark crawl sessions a previous process left unfinished as failed
+    pub async fn recover_crawl_sessions(&self) -> Result<(), dno_core::AppError> {
+        self.crawl_jobs.fail_interrupted().await.map(|_| ())
+    }
+
+    /// Start the background integrity scan of the stored source files, see
+    /// `IntegrityScanConfig::from_env`; `None` when it is turned off
+    pub fn spawn_integrity_scans(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let config = dno_core::source_manager::IntegrityScanConfig::from_env()?;
+        tracing::info!("Verifying {} source files every {:?}", config.batch, config.interval);
+        Some(dno_core::source_manager::spawn_integrity_scans(self.sources.clone(), config))
+    }
+
+    /// Warm up caches with commonly accessed data. Phases run with bounded
+    /// concurrency and a per-phase timeout (see `WarmupConfig::from_env`), so a
+    /// slow Redis or database delays startup by at most the timeout.
+    pub async fn warm_caches(&self) -> Result<(), dno_core::AppError> {
+        let config = warmup::WarmupConfig::from_env();
+        if !config.enabled {
+            tracing::info!("Cache warm-up disabled via WARM_CACHE");
+            return Ok(());
         }
-        
-        tracing::info!("Cache warm-up completed");
+        tracing::info!("Starting cache warm-up");
+
+        let phases: Vec<warmup::WarmupPhase> = vec![
+            ("users", Box::pin(self.user_repo.warm_cache())),
+            ("search", Box::pin(self.search_repo.warm_cache())),
+            ("dnos", Box::pin(self.dno_repo.warm_cache())),
+        ];
+        let report = warmup::run_phases(phases, &config).await;
+
+        tracing::info!("Cache warm-up finished; completed phases: {:?}", report.completed());
         Ok(())
     }
 
+    /// Sweep stale files from `temp_path` and create this process's temp directory
+    pub fn start_temp_session(&self) -> Result<dno_core::temp::TempSession, dno_core::AppError> {
+        Ok(dno_core::temp::TempSession::start(
+            &self.config.temp_path,
+            dno_core::temp::TempCleanupConfig::from_env(),
+        )?)
+    }
+
     /// Get cache health information
-    pub async fn cache_health(&self) -> Result<serde_json::Value, core::AppError> {
+    pub async fn cache_health(&self) -> Result<serde_json::Value, dno_core::AppError> {
         let cache_health = self.cache.health_check().await
-            .map_err(|e| core::AppError::Cache(format!("Cache health check failed: {}", e)))?;
+            .map_err(|e| dno_core::AppError::Cache(format!("Cache health check failed: {}", e)))?;
         
         Ok(serde_json::to_value(cache_health)?)
     }
@@ -98,6 +211,8 @@ pub struct AppConfig {
     pub server_host: String,
     pub server_port: u16,
     pub cors_origins: Vec<String>,
+    /// Answer every origin, credentials included; for local development only
+    pub cors_allow_all: bool,
     pub rate_limit_per_minute: u32,
     pub rate_limit_per_hour: u32,
     pub jwt_access_token_expiry: i64,
@@ -105,6 +220,17 @@ pub struct AppConfig {
     pub upload_max_size: u64,
     pub storage_path: String,
     pub temp_path: String,
+    /// Deployment environment (`development`, `staging`, `production`)
+    pub environment: String,
+    /// Proxies allowed to name the client in `X-Forwarded-For` / `X-Real-IP`
+    pub trusted_proxies: client_ip::TrustedProxies,
+    /// Probed by `/health`: Ollama's `/api/tags` and the SearXNG front page
+    pub ollama_url: String,
+    pub searxng_url: String,
+    /// Time each `/health` dependency check may take
+    pub health_check_timeout_ms: u64,
+    /// Searches whose newest result was crawled longer ago are flagged `stale`
+    pub search_stale_after_days: i64,
 }
 
 impl Default for AppConfig {
@@ -117,6 +243,7 @@ impl Default for AppConfig {
                 "http://localhost:8000".to_string(),
                 "http://localhost:5173".to_string(),
             ],
+            cors_allow_all: false,
             rate_limit_per_minute: 60,
             rate_limit_per_hour: 1000,
             jwt_access_token_expiry: 3600, // 1 hour
@@ -124,6 +251,12 @@ impl Default for AppConfig {
             upload_max_size: 52428800, // 50MB
             storage_path: "./storage".to_string(),
             temp_path: "./temp".to_string(),
+            environment: "development".to_string(),
+            trusted_proxies: client_ip::TrustedProxies::default(),
+            ollama_url: "http://localhost:11434".to_string(),
+            searxng_url: "http://localhost:8888".to_string(),
+            health_check_timeout_ms: 2000,
+            search_stale_after_days: 400,
         }
     }
 }
@@ -139,7 +272,11 @@ impl AppConfig {
                 .unwrap_or_else(|_| "http://localhost:3000,http://localhost:8000,http://localhost:5173".to_string())
                 .split(',')
                 .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
                 .collect(),
+            cors_allow_all: std::env::var("CORS_ALLOW_ALL")
+                .map(|v| v.trim().eq_ignore_ascii_case("true") || v.trim() == "1")
+                .unwrap_or(false),
             rate_limit_per_minute: std::env::var("RATE_LIMIT_PER_MINUTE")
                 .unwrap_or_else(|_| "60".to_string())
                 .parse()?,
@@ -159,6 +296,56 @@ impl AppConfig {
                 .unwrap_or_else(|_| "./storage".to_string()),
             temp_path: std::env::var("TEMP_PATH")
                 .unwrap_or_else(|_| "./temp".to_string()),
+            environment: std::env::var("APP_ENV")
+                .unwrap_or_else(|_| "development".to_string()),
+            trusted_proxies: client_ip::TrustedProxies::parse(&std::env::var("TRUSTED_PROXIES").unwrap_or_default())?,
+            ollama_url: dno_core::OllamaConfig::from_env().url,
+            searxng_url: dno_core::SearxngConfig::from_env().url,
+            health_check_timeout_ms: std::env::var("HEALTH_CHECK_TIMEOUT_MS")
+                .unwrap_or_else(|_| "2000".to_string())
+                .parse()?,
+            search_stale_after_days: std::env::var("SEARCH_STALE_AFTER_DAYS")
+                .unwrap_or_else(|_| "400".to_string())
+                .parse()?,
         })
     }
-}
\ No newline at end of file
+
+    pub fn is_production(&self) -> bool {
+        self.environment.eq_ignore_ascii_case("production")
+    }
+}
+
+/// Resolves on Ctrl+C or SIGTERM, then removes the session's temp directory.
+/// Pass to `axum::serve(..).with_graceful_shutdown(..)`; serve the router with
+/// `into_make_service_with_connect_info::<SocketAddr>()` so `ClientIp` sees the peer.
+pub async fn shutdown_signal(temp: dno_core::temp::TempSession) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutting down, removing temp session {}", temp.path().display());
+    temp.cleanup();
+}
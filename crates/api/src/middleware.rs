@@ -5,14 +5,12 @@ use axum::{
     response::{Json, Response},
 };
 use serde_json::{json, Value};
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
 use crate::AppState;
 
 // Re-export UserRole from core crate
-pub use core::models::UserRole;
+pub use dno_core::models::UserRole;
 
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
@@ -23,13 +21,19 @@ pub struct AuthenticatedUser {
     pub session_id: Uuid,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // User ID
     pub email: String,
     pub name: String,
     pub role: String,
     pub session_id: String,
+    /// Token id, checked against the revocation list
+    pub jti: String,
+    /// Shared by every token descending from one login through refreshes
+    pub family_id: String,
+    /// `access` or `refresh`
+    pub token_type: String,
     pub exp: i64,
     pub iat: i64,
 }
@@ -37,18 +41,14 @@ pub struct Claims {
 /// Extract user from JWT token using cached repositories
 async fn extract_user_from_token(
     token: &str,
-    jwt_secret: &str,
+    jwt: &crate::jwt::JwtService<crate::RedisCache>,
     user_repo: &crate::UserRepository<crate::RedisCache>,
 ) -> Result<AuthenticatedUser, AuthError> {
-    // Decode JWT token
-    let validation = Validation::new(Algorithm::HS256);
-    let decoding_key = DecodingKey::from_secret(jwt_secret.as_ref());
-    
-    let token_data = decode::<Claims>(token, &decoding_key, &validation)
+    // Verify signature, expiry and revocation
+    let claims = jwt.verify_access_token(token)
+        .await
         .map_err(|_| AuthError::InvalidToken)?;
 
-    let claims = token_data.claims;
-
     // Parse user ID
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| AuthError::InvalidToken)?;
@@ -176,64 +176,196 @@ pub async fn user_auth_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<Value>)> {
-    let token = extract_bearer_token(&headers).map_err(|e| e.to_response(&None))?;
-    let user = extract_user_from_token(&token, &state.jwt_secret, &state.user_repo)
+    let token = extract_bearer_token(&headers).map_err(|e| e.to_response(None))?;
+    let user = extract_user_from_token(&token, &state.jwt, &state.user_repo)
         .await
-        .map_err(|e| e.to_response(&None))?;
-
-    // Check if user has sufficient permissions (user or admin)
-    match user.role {
-        UserRole::User | UserRole::Admin => {
-            // Add user to request extensions for handlers to access
-            request.extensions_mut().insert(user);
-            Ok(next.run(request).await)
+        .map_err(|e| e.to_response(None))?;
+
+    RequireRole(UserRole::User).check(&user).map_err(|e| e.to_response(Some(&user.role)))?;
+    // Add user to request extensions for handlers to access
+    request.extensions_mut().insert(user);
+    Ok(next.run(request).await)
+}
+
+/// Minimum role for a group of routes, under the hierarchy admin > user > pending (guest)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequireRole(pub UserRole);
+
+impl RequireRole {
+    fn check(&self, user: &AuthenticatedUser) -> Result<(), AuthError> {
+        if user.role.includes(&self.0) {
+            return Ok(());
+        }
+        match self.0 {
+            UserRole::Admin => Err(AuthError::InsufficientPermissions),
+            UserRole::User | UserRole::Pending => Err(AuthError::PendingApproval),
         }
-        UserRole::Pending => Err(AuthError::PendingApproval.to_response(&Some(&user.role))),
     }
 }
 
-/// Middleware that requires admin authentication
-pub async fn admin_auth_middleware(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    mut request: Request,
+/// Middleware that rejects users below the `RequireRole` state with 403. It
+/// reads the `AuthenticatedUser`, so layer it inside an authenticating
+/// middleware (`route_layer` runs the last-added layer first):
+///
+/// ```ignore
+/// .route_layer(middleware::from_fn_with_state(RequireRole(UserRole::Admin), require_role))
+/// .route_layer(middleware::from_fn_with_state(state.clone(), pending_allowed_middleware))
+/// ```
+pub async fn require_role(
+    State(required): State<RequireRole>,
+    request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<Value>)> {
-    let token = extract_bearer_token(&headers).map_err(|e| e.to_response(&None))?;
-    let user = extract_user_from_token(&token, &state.jwt_secret, &state.user_repo)
-        .await
-        .map_err(|e| e.to_response(&None))?;
-
-    // Check if user has admin permissions
-    match user.role {
-        UserRole::Admin => {
-            // Add user to request extensions for handlers to access
-            request.extensions_mut().insert(user);
-            Ok(next.run(request).await)
-        }
-        UserRole::User | UserRole::Pending => {
-            Err(AuthError::InsufficientPermissions.to_response(&Some(&user.role)))
-        }
-    }
+    let Some(user) = request.extensions().get::<AuthenticatedUser>() else {
+        return Err(AuthError::MissingToken.to_response(None));
+    };
+    required.check(user).map_err(|e| e.to_response(Some(&user.role)))?;
+    Ok(next.run(request).await)
 }
 
-/// Middleware that allows pending users to access specific endpoints (read-only profile)
+/// Middleware that allows pending users to access specific endpoints (read-only
+/// profile); also the authentication step in front of `require_role`
 pub async fn pending_allowed_middleware(
     State(state): State<AppState>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<Value>)> {
-    let token = extract_bearer_token(&headers).map_err(|e| e.to_response(&None))?;
-    let user = extract_user_from_token(&token, &state.jwt_secret, &state.user_repo)
+    let token = extract_bearer_token(&headers).map_err(|e| e.to_response(None))?;
+    let user = extract_user_from_token(&token, &state.jwt, &state.user_repo)
         .await
-        .map_err(|e| e.to_response(&None))?;
+        .map_err(|e| e.to_response(None))?;
 
     // Allow all authenticated users (including pending)
     request.extensions_mut().insert(user);
     Ok(next.run(request).await)
 }
 
+/// Correlation id header shared with clients and logs
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Correlation id of the request being handled, set by `request_span_middleware`
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Wrap each request in an `http.request` span tagged with its correlation id,
+/// so the crawl and extraction spans started while handling it nest under it.
+/// The id comes from `X-Request-Id` (generated when missing), is set on the
+/// request for later middleware, readable by handlers through
+/// `current_request_id`, and returned on the response. With the `otel`
+/// feature an incoming `traceparent` becomes the span's parent, and the trace
+/// id doubles as the correlation id when the client sent none.
+pub async fn request_span_middleware(mut request: Request, next: Next) -> Response {
+    use tracing::Instrument;
+
+    let span = tracing::info_span!(
+        "http.request",
+        otel.kind = "server",
+        http.method = %request.method(),
+        http.target = %request.uri().path(),
+        http.status_code = tracing::field::Empty,
+        correlation_id = tracing::field::Empty,
+    );
+    #[cfg(feature = "otel")]
+    dno_core::telemetry::set_parent_from_headers(&span, request.headers());
+
+    let requested = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+        .map(str::to_string);
+    #[cfg(feature = "otel")]
+    let requested = requested.or_else(|| dno_core::telemetry::trace_id(&span));
+    let correlation_id = requested.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    // Display, not Debug: logs show `correlation_id=req-0815` without quotes
+    span.record("correlation_id", tracing::field::display(&correlation_id));
+    let header = axum::http::HeaderValue::from_str(&correlation_id).ok();
+    if let Some(value) = &header {
+        request.headers_mut().insert(REQUEST_ID_HEADER, value.clone());
+    }
+
+    let mut response = REQUEST_ID
+        .scope(correlation_id, next.run(request))
+        .instrument(span.clone())
+        .await;
+    span.record("http.status_code", response.status().as_u16());
+    if let Some(value) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Record each request's duration in the process metrics under its route
+/// pattern (`/api/v1/search/dno`, `/api/v1/data/:id/timeline`), never the
+/// concrete path, so ids don't multiply the series
+pub async fn http_metrics_middleware(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().clone();
+    let start = std::time::Instant::now();
+
+    let response = next.run(request).await;
+    dno_core::metrics::global().observe_http(method.as_str(), &route, response.status().as_u16(), start.elapsed());
+    response
+}
+
+/// Header listing the last cache events of a request (non-production only)
+#[cfg(feature = "cache-debug")]
+pub const CACHE_DEBUG_HEADER: &str = "x-cache-debug";
+
+/// Cache events returned in `X-Cache-Debug`
+#[cfg(feature = "cache-debug")]
+const CACHE_DEBUG_EVENTS: usize = 20;
+
+/// Middleware that records every cache key read/written while handling the
+/// request, tagged with its correlation id. Outside production the last
+/// events are returned in `X-Cache-Debug`.
+#[cfg(feature = "cache-debug")]
+pub async fn cache_trace_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    use dno_core::cache::trace::{with_trace, CacheTrace};
+
+    let correlation_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let trace = CacheTrace::new(correlation_id);
+    let mut response = with_trace(trace.clone(), next.run(request)).await;
+
+    if !state.config.is_production() {
+        let events = trace
+            .last(CACHE_DEBUG_EVENTS)
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Ok(value) = axum::http::HeaderValue::from_str(&events) {
+            response.headers_mut().insert(CACHE_DEBUG_HEADER, value);
+        }
+    }
+    if let Ok(value) = axum::http::HeaderValue::from_str(trace.correlation_id()) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
 /// Extract Bearer token from Authorization header
 fn extract_bearer_token(headers: &HeaderMap) -> Result<String, AuthError> {
     let auth_header = headers
@@ -254,36 +386,6 @@ fn extract_bearer_token(headers: &HeaderMap) -> Result<String, AuthError> {
     Ok(token.to_string())
 }
 
-/// Generate JWT token for user
-pub fn generate_jwt_token(
-    user: &core::models::User,
-    session_id: Uuid,
-    jwt_secret: &str,
-    expires_in_seconds: i64,
-) -> Result<String, jsonwebtoken::errors::Error> {
-    let now = Utc::now().timestamp();
-    let expiration = now + expires_in_seconds;
-
-    let claims = Claims {
-        sub: user.id.to_string(),
-        email: user.email.clone(),
-        name: user.name.clone(),
-        role: match user.role {
-            UserRole::Pending => "pending".to_string(),
-            UserRole::User => "user".to_string(),
-            UserRole::Admin => "admin".to_string(),
-        },
-        session_id: session_id.to_string(),
-        exp: expiration,
-        iat: now,
-    };
-
-    let header = jsonwebtoken::Header::new(Algorithm::HS256);
-    let encoding_key = jsonwebtoken::EncodingKey::from_secret(jwt_secret.as_ref());
-
-    jsonwebtoken::encode(&header, &claims, &encoding_key)
-}
-
 /// Hash password using bcrypt
 pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
     bcrypt::hash(password, bcrypt::DEFAULT_COST)
@@ -292,4 +394,136 @@ pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
 /// Verify password against hash
 pub fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
     bcrypt::verify(password, hash)
-}
\ No newline at end of file
+}
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+    use tracing::instrument::WithSubscriber;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Accepts OTLP/HTTP exports and forwards `(path, body)` of each
+    async fn mock_collector() -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let (path, body) = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        return;
+                    }
+                    buffer.extend_from_slice(&chunk[..n]);
+                    let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") else { continue };
+                    let head = String::from_utf8_lossy(&buffer[..end]).to_string();
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if buffer.len() >= end + 4 + length {
+                        let path = head.split_whitespace().nth(1).unwrap_or_default().to_string();
+                        break (path, buffer[end + 4..end + 4 + length].to_vec());
+                    }
+                };
+                socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+                let _ = tx.send((path, body));
+            }
+        });
+
+        (endpoint, rx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_served_request_exports_span() {
+        let (endpoint, mut exports) = mock_collector().await;
+        let config = dno_core::telemetry::OtelConfig { endpoint, service_name: "api-test".to_string() };
+        let provider = dno_core::telemetry::tracer_provider(&config).unwrap();
+        let subscriber = tracing_subscriber::registry().with(dno_core::telemetry::layer(&provider));
+
+        let app = Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(request_span_middleware));
+        let request = Request::builder()
+            .uri("/health")
+            .header(REQUEST_ID_HEADER, "req-4711")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).with_subscriber(subscriber).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-4711");
+
+        tokio::task::spawn_blocking(move || provider.force_flush()).await.unwrap();
+        let (path, body) = tokio::time::timeout(std::time::Duration::from_secs(5), exports.recv())
+            .await
+            .expect("no span exported")
+            .unwrap();
+        assert_eq!(path, "/v1/traces");
+        let contains = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"http.request"));
+        assert!(contains(b"req-4711"));
+    }
+}
+
+#[cfg(test)]
+mod role_tests {
+    use super::*;
+    use axum::{body::Body, http::Request as HttpRequest, routing::get, Router};
+    use tower::ServiceExt;
+
+    /// Stands in for token authentication: the role comes from a test header
+    async fn authenticate_from_header(mut request: Request, next: Next) -> Response {
+        let role = match request.headers().get("x-test-role").and_then(|v| v.to_str().ok()) {
+            Some("admin") => Some(UserRole::Admin),
+            Some("user") => Some(UserRole::User),
+            Some("pending") => Some(UserRole::Pending),
+            _ => None,
+        };
+        if let Some(role) = role {
+            request.extensions_mut().insert(AuthenticatedUser {
+                id: Uuid::new_v4(),
+                email: "netzbetreiber@example.de".to_string(),
+                role,
+                name: "Erika Mustermann".to_string(),
+                session_id: Uuid::new_v4(),
+            });
+        }
+        next.run(request).await
+    }
+
+    fn protected(required: UserRole) -> Router {
+        Router::new()
+            .route("/admin/overview", get(|| async { "ok" }))
+            .route_layer(axum::middleware::from_fn_with_state(RequireRole(required), require_role))
+            .route_layer(axum::middleware::from_fn(authenticate_from_header))
+    }
+
+    async fn status(router: Router, role: Option<&str>) -> StatusCode {
+        let mut request = HttpRequest::get("/admin/overview");
+        if let Some(role) = role {
+            request = request.header("x-test-role", role);
+        }
+        router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_route_rejects_guests_and_admits_admins() {
+        assert_eq!(status(protected(UserRole::Admin), Some("pending")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(protected(UserRole::Admin), Some("user")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(protected(UserRole::Admin), Some("admin")).await, StatusCode::OK);
+        assert_eq!(status(protected(UserRole::Admin), None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_higher_roles_pass_lower_requirements() {
+        assert_eq!(status(protected(UserRole::User), Some("pending")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(protected(UserRole::User), Some("user")).await, StatusCode::OK);
+        assert_eq!(status(protected(UserRole::User), Some("admin")).await, StatusCode::OK);
+        assert_eq!(status(protected(UserRole::Pending), Some("pending")).await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,244 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use dno_core::cache::CacheLayer;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+use crate::{AppState, AuthenticatedUser, UserRole};
+
+pub const QUOTA_LIMIT_HEADER: &str = "x-quota-limit";
+pub const QUOTA_REMAINING_HEADER: &str = "x-quota-remaining";
+
+/// Quota tier a caller is billed against
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaTier {
+    /// Pending accounts
+    Guest,
+    User,
+    Admin,
+}
+
+impl QuotaTier {
+    pub fn for_role(role: &UserRole) -> Self {
+        match role {
+            UserRole::Pending => QuotaTier::Guest,
+            UserRole::User => QuotaTier::User,
+            UserRole::Admin => QuotaTier::Admin,
+        }
+    }
+}
+
+/// Monthly request allowance per tier; admins are unlimited
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaPolicy {
+    pub guest_monthly: u64,
+    pub user_monthly: u64,
+}
+
+impl Default for QuotaPolicy {
+    fn default() -> Self {
+        Self {
+            guest_monthly: 50,
+            user_monthly: 5000,
+        }
+    }
+}
+
+impl QuotaPolicy {
+    /// Read `QUOTA_GUEST_MONTHLY` / `QUOTA_USER_MONTHLY`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        Self {
+            guest_monthly: read("QUOTA_GUEST_MONTHLY", defaults.guest_monthly),
+            user_monthly: read("QUOTA_USER_MONTHLY", defaults.user_monthly),
+        }
+    }
+
+    pub fn limit(&self, tier: QuotaTier) -> Option<u64> {
+        match tier {
+            QuotaTier::Guest => Some(self.guest_monthly),
+            QuotaTier::User => Some(self.user_monthly),
+            QuotaTier::Admin => None,
+        }
+    }
+}
+
+/// Usage in the current monthly window
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct QuotaStatus {
+    pub tier: QuotaTier,
+    /// `None` means unlimited
+    pub limit: Option<u64>,
+    pub used: u64,
+    pub remaining: Option<u64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl QuotaStatus {
+    pub fn is_exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.used > limit)
+    }
+}
+
+/// Per-user monthly counters kept in the shared cache
+#[derive(Clone)]
+pub struct QuotaStore<C: CacheLayer> {
+    cache: Arc<C>,
+    policy: QuotaPolicy,
+}
+
+/// First instant of the month after `now`
+fn next_window_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 { (now.year() + 1, 1) } else { (now.year(), now.month() + 1) };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single().unwrap_or(now)
+}
+
+impl<C: CacheLayer> QuotaStore<C> {
+    pub fn new(cache: Arc<C>, policy: QuotaPolicy) -> Self {
+        Self { cache, policy }
+    }
+
+    fn key(user_id: Uuid, now: DateTime<Utc>) -> String {
+        format!("quota:{}:{}", user_id, now.format("%Y-%m"))
+    }
+
+    fn status(&self, tier: QuotaTier, used: u64, now: DateTime<Utc>) -> QuotaStatus {
+        let limit = self.policy.limit(tier);
+        QuotaStatus {
+            tier,
+            limit,
+            used,
+            remaining: limit.map(|limit| limit.saturating_sub(used)),
+            resets_at: next_window_start(now),
+        }
+    }
+
+    /// Count one qualifying request. Admins are not counted. Cache failures
+    /// fail open, so an outage never locks users out.
+    pub async fn consume(&self, user_id: Uuid, role: &UserRole, now: DateTime<Utc>) -> QuotaStatus {
+        let tier = QuotaTier::for_role(role);
+        if self.policy.limit(tier).is_none() {
+            return self.status(tier, 0, now);
+        }
+
+        // Keep the counter a day past the window end so late requests still see it
+        let ttl = (next_window_start(now) - now).to_std().unwrap_or_default() + Duration::from_secs(86400);
+        let used = match self.cache.incr(&Self::key(user_id, now), 1, Some(ttl)).await {
+            Ok(used) => used.max(0) as u64,
+            Err(e) => {
+                warn!("Quota cache unavailable, not counting request: {}", e);
+                0
+            }
+        };
+        self.status(tier, used, now)
+    }
+
+    /// Current usage without counting a request
+    pub async fn current(&self, user_id: Uuid, role: &UserRole, now: DateTime<Utc>) -> QuotaStatus {
+        let tier = QuotaTier::for_role(role);
+        let used = match self.policy.limit(tier) {
+            None => 0,
+            Some(_) => match self.cache.get::<i64>(&Self::key(user_id, now)).await {
+                Ok(used) => used.unwrap_or(0).max(0) as u64,
+                Err(e) => {
+                    warn!("Quota cache unavailable: {}", e);
+                    0
+                }
+            },
+        };
+        self.status(tier, used, now)
+    }
+}
+
+fn quota_headers(response: &mut Response, status: &QuotaStatus) {
+    if let (Some(limit), Some(remaining)) = (status.limit, status.remaining) {
+        let headers = response.headers_mut();
+        headers.insert(QUOTA_LIMIT_HEADER, HeaderValue::from(limit));
+        headers.insert(QUOTA_REMAINING_HEADER, HeaderValue::from(remaining));
+    }
+}
+
+/// Middleware that charges each request against the caller's monthly quota.
+/// Must run after an auth middleware has inserted the `AuthenticatedUser`.
+pub async fn quota_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(user) = request.extensions().get::<AuthenticatedUser>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let now = Utc::now();
+    let status = state.quota.consume(user.id, &user.role, now).await;
+    if status.is_exhausted() {
+        let retry_after = (status.resets_at - now).num_seconds().max(1);
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({
+                "error": "quota_exceeded",
+                "message": "Monthly request quota exhausted",
+                "details": status,
+                "request_id": Uuid::new_v4().to_string()
+            })),
+        )
+            .into_response();
+        response.headers_mut().insert(axum::http::header::RETRY_AFTER, HeaderValue::from(retry_after));
+        quota_headers(&mut response, &status);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    quota_headers(&mut response, &status);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dno_core::cache::MemoryCache;
+
+    #[tokio::test]
+    async fn test_guest_exhausting_quota_is_blocked_while_user_proceeds() {
+        let store = QuotaStore::new(
+            Arc::new(MemoryCache::new()),
+            QuotaPolicy { guest_monthly: 2, user_monthly: 10 },
+        );
+        let now = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+        let (guest, user, admin) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        for _ in 0..2 {
+            assert!(!store.consume(guest, &UserRole::Pending, now).await.is_exhausted());
+        }
+        let blocked = store.consume(guest, &UserRole::Pending, now).await;
+        assert!(blocked.is_exhausted());
+        assert_eq!(blocked.remaining, Some(0));
+        assert_eq!(blocked.resets_at, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+
+        for _ in 0..3 {
+            assert!(!store.consume(user, &UserRole::User, now).await.is_exhausted());
+        }
+        let user_status = store.current(user, &UserRole::User, now).await;
+        assert_eq!((user_status.used, user_status.remaining), (3, Some(7)));
+
+        for _ in 0..20 {
+            assert!(!store.consume(admin, &UserRole::Admin, now).await.is_exhausted());
+        }
+
+        // A new month starts a fresh window
+        let next_month = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        assert!(!store.consume(guest, &UserRole::Pending, next_month).await.is_exhausted());
+    }
+}
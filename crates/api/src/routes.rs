@@ -1,34 +1,72 @@
 mod account;
 mod admin;
 mod auth;
+mod crawl;
 mod dashboard;
+mod data;
+mod dnos;
+mod feed;
 mod files;
 mod health;
 mod metrics;
+mod query;
 mod search;
 mod websocket;
 
 use axum::{
-    Router,
+    Extension, Router,
     routing::{get, post, put, delete, patch},
 };
-use crate::AppState;
+use crate::{cors, AppState};
+use crate::versioning::ApiVersion;
 
-pub fn api_routes() -> Router<AppState> {
-    Router::new()
+/// v1 routes, for servers that mount the API under `/api/v1` themselves
+pub fn api_routes(state: &AppState) -> Router<AppState> {
+    routes_for(ApiVersion::V1, state)
+}
+
+/// Every API version under its own prefix (`/api/v1`, `/api/v2`). The versions
+/// share handlers and differ only in the response DTOs of the search endpoints.
+pub fn versioned_api_routes(state: &AppState) -> Router<AppState> {
+    [ApiVersion::V1, ApiVersion::V2]
+        .into_iter()
+        .fold(Router::new(), |router, version| router.nest(version.prefix(), routes_for(version, state)))
+        .layer(cors::cors_layer(&state.config))
+}
+
+fn routes_for(version: ApiVersion, state: &AppState) -> Router<AppState> {
+    let router = Router::new()
         // Public endpoints (no auth required)
         .route("/health", get(health::health_check))
         .route("/ready", get(health::readiness_check))
+        .route("/feed.xml", get(feed::get_feed))
         .nest("/auth", auth_routes())
         // User authenticated endpoints
-        .nest("/search", search_routes())
-        .nest("/dashboard", dashboard_routes())
-        .nest("/account", account_routes())
+        .nest("/search", search_routes(state))
+        .nest("/query", query_routes(state))
+        .nest("/data", data_routes(state))
+        .nest("/dnos", dnos_routes(state))
+        .nest("/dashboard", dashboard_routes(state))
+        .nest("/account", account_routes(state))
+        .nest("/me", me_routes(state))
+        .nest("/crawl", crawl_routes(state))
         // Admin only endpoints
-        .nest("/admin", admin_routes())
-        .nest("/metrics", metrics_routes())
-        .nest("/files", files_routes())
-        .route("/ws", get(websocket::websocket_handler))
+        .nest("/admin", admin_routes(state))
+        .nest("/metrics", metrics_routes(state))
+        .nest("/files", files_routes(state))
+        .route("/ws", get(websocket::websocket_handler));
+
+    #[cfg(feature = "cache-debug")]
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        crate::middleware::cache_trace_middleware,
+    ));
+
+    // Added last so it wraps every other middleware in the request span
+    router
+        .layer(axum::middleware::from_fn(crate::middleware::http_metrics_middleware))
+        .layer(axum::middleware::from_fn(crate::middleware::request_span_middleware))
+        .layer(Extension(version))
 }
 
 fn auth_routes() -> Router<AppState> {
@@ -39,37 +77,90 @@ fn auth_routes() -> Router<AppState> {
         .route("/logout", post(auth::logout))
 }
 
-fn search_routes() -> Router<AppState> {
+fn search_routes(state: &AppState) -> Router<AppState> {
     use axum::middleware;
     use crate::middleware::user_auth_middleware;
+    use crate::quota::quota_middleware;
+    use crate::etag::etag_middleware;
     
     Router::new()
         .route("/dno", post(search::search_by_dno))
         .route("/year", post(search::search_by_year))
         .route("/data-type", post(search::search_by_data_type))
+        .route("/batch", post(search::search_batch))
         .route("/", get(search::search_with_filters))
-        .route_layer(middleware::from_fn_with_state((), user_auth_middleware))
+        // GET searches carry an ETag and answer a matching If-None-Match with 304
+        .route_layer(middleware::from_fn(etag_middleware))
+        // Searches count against the monthly quota; layers run bottom-up, so auth runs first
+        .route_layer(middleware::from_fn_with_state(state.clone(), quota_middleware))
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
+}
+
+fn query_routes(state: &AppState) -> Router<AppState> {
+    use axum::middleware;
+    use crate::middleware::user_auth_middleware;
+
+    Router::new()
+        .route("/interpret", post(query::interpret_query))
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
+}
+
+fn data_routes(state: &AppState) -> Router<AppState> {
+    use axum::middleware;
+    use crate::middleware::user_auth_middleware;
+
+    Router::new()
+        .route("/{id}/timeline", get(data::get_timeline))
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
+}
+
+fn dnos_routes(state: &AppState) -> Router<AppState> {
+    use axum::middleware;
+    use crate::middleware::user_auth_middleware;
+
+    Router::new()
+        .route("/autocomplete", get(dnos::autocomplete))
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
+}
+
+fn crawl_routes(state: &AppState) -> Router<AppState> {
+    use axum::middleware;
+    use crate::idempotency::idempotency_middleware;
+    use crate::middleware::user_auth_middleware;
+
+    Router::new()
+        .route("/{session_id}", get(crawl::get_crawl_session))
+        .route("/validate", post(crawl::validate_crawl))
+        .route("/paths", post(crawl::create_navigation_path))
+        .route("/{session_id}/stream", get(crawl::stream_crawl))
+        .merge(
+            Router::new()
+                // A retried start must not create a second session
+                .route("/", post(crawl::start_crawl))
+                .route_layer(middleware::from_fn_with_state(state.clone(), idempotency_middleware))
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
 }
 
-fn dashboard_routes() -> Router<AppState> {
+fn dashboard_routes(state: &AppState) -> Router<AppState> {
     use axum::middleware;
     use crate::middleware::user_auth_middleware;
     
     Router::new()
         .route("/stats", get(dashboard::get_stats))
         .route("/history", get(dashboard::get_history))
-        .route("/history/:id", delete(dashboard::delete_history))
-        .route_layer(middleware::from_fn_with_state((), user_auth_middleware))
+        .route("/history/{id}", delete(dashboard::delete_history))
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
 }
 
-fn account_routes() -> Router<AppState> {
+fn account_routes(state: &AppState) -> Router<AppState> {
     use axum::middleware;
     use crate::middleware::{user_auth_middleware, pending_allowed_middleware};
     
     Router::new()
         // Profile GET is allowed for pending users (read-only)
         .route("/profile", get(account::get_profile))
-        .route_layer(middleware::from_fn_with_state((), pending_allowed_middleware))
+        .route_layer(middleware::from_fn_with_state(state.clone(), pending_allowed_middleware))
         .merge(
             Router::new()
                 // All other account endpoints require user/admin role
@@ -80,30 +171,36 @@ fn account_routes() -> Router<AppState> {
                 .route("/profile-picture", delete(account::delete_profile_picture))
                 .route("/api-keys", get(account::list_api_keys))
                 .route("/api-keys", post(account::create_api_key))
-                .route("/api-keys/:id", delete(account::delete_api_key))
+                .route("/api-keys/{id}", delete(account::delete_api_key))
                 .route("/", delete(account::delete_account))
-                .route_layer(middleware::from_fn_with_state((), user_auth_middleware))
+                .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
         )
 }
 
-fn admin_routes() -> Router<AppState> {
+fn me_routes(state: &AppState) -> Router<AppState> {
     use axum::middleware;
-    use crate::middleware::admin_auth_middleware;
+    use crate::middleware::pending_allowed_middleware;
+    
+    Router::new()
+        .route("/quota", get(account::get_quota))
+        .route_layer(middleware::from_fn_with_state(state.clone(), pending_allowed_middleware))
+}
+
+fn admin_routes(state: &AppState) -> Router<AppState> {
+    use axum::middleware;
+    use crate::idempotency::idempotency_middleware;
+    use crate::middleware::{pending_allowed_middleware, require_role, RequireRole, UserRole};
     
     Router::new()
         .route("/overview", get(admin::get_overview))
         .route("/users", get(admin::list_users))
-        .route("/users/:id", patch(admin::update_user))
-        .route("/users/:id", delete(admin::delete_user))
-        .route("/users/:id/approve", post(admin::approve_user))
-        .route("/users/:id/reject", post(admin::reject_user))
+        .route("/users/{id}", patch(admin::update_user))
+        .route("/users/{id}", delete(admin::delete_user))
+        .route("/users/{id}/approve", post(admin::approve_user))
+        .route("/users/{id}/reject", post(admin::reject_user))
         .route("/data-entries", get(admin::list_data_entries))
-        .route("/data-entries/:id", get(admin::get_data_entry))
-        .route("/data-entries/:id/source", get(admin::get_data_entry_source))
-        .route("/data-entries/:id/verify", post(admin::verify_data_entry))
-        .route("/data-entries/:id", patch(admin::update_data_entry))
-        .route("/data-entries/:id", delete(admin::delete_data_entry))
-        .route("/data-entries/bulk", post(admin::bulk_data_entries))
+        .route("/data-entries/{id}", get(admin::get_data_entry))
+        .route("/data-entries/{id}/source", get(admin::get_data_entry_source))
         .route("/crawl-settings", get(admin::get_crawl_settings))
         .route("/crawl-settings", patch(admin::update_crawl_settings))
         .route("/queries", get(admin::get_queries))
@@ -112,28 +209,155 @@ fn admin_routes() -> Router<AppState> {
         .route("/jobs/automated", get(admin::list_automated_jobs))
         .route("/jobs/automated", post(admin::create_automated_job))
         .route("/logs", get(admin::get_logs))
-        .route("/crawl/trigger", post(admin::trigger_crawl))
         .route("/metrics/dashboard", get(admin::get_metrics_dashboard))
         .route("/metrics/query", post(admin::query_metrics))
         .route("/metrics/export", get(admin::export_metrics))
         .route("/metrics/timeseries", get(admin::get_timeseries))
-        .route_layer(middleware::from_fn_with_state((), admin_auth_middleware))
+        .route("/metrics/accuracy", get(admin::get_accuracy_metrics))
+        .route("/metrics/calibration", get(admin::get_confidence_calibration))
+        .route("/conflicts", get(admin::list_conflicts))
+        .route("/manual-review", get(admin::list_manual_review))
+        .route("/audit/files", get(admin::list_file_audit))
+        .route("/dedup/runs", get(admin::list_dedup_runs))
+        .merge(
+            Router::new()
+                // Crawl and data-mutation endpoints honour the Idempotency-Key header
+                .route("/crawl/trigger", post(admin::trigger_crawl))
+                .route("/data-entries/{id}/verify", post(admin::verify_data_entry))
+                .route("/data-entries/{id}", patch(admin::update_data_entry))
+                .route("/data-entries/{id}", delete(admin::delete_data_entry))
+                .route("/data-entries/bulk", post(admin::bulk_data_entries))
+                .route("/data-entries/recompute-verification", post(admin::recompute_verification))
+                .route("/conflicts/{id}/resolve", post(admin::resolve_conflict))
+                .route("/manual-review/{id}/retry", post(admin::retry_manual_review))
+                .route("/dedup/run", post(admin::run_deduplication))
+                .route("/files/purge", post(admin::purge_inactive_files))
+                .route_layer(middleware::from_fn_with_state(state.clone(), idempotency_middleware))
+        )
+        .route_layer(middleware::from_fn_with_state(RequireRole(UserRole::Admin), require_role))
+        .route_layer(middleware::from_fn_with_state(state.clone(), pending_allowed_middleware))
 }
 
-fn metrics_routes() -> Router<AppState> {
+fn metrics_routes(state: &AppState) -> Router<AppState> {
     use axum::middleware;
-    use crate::middleware::admin_auth_middleware;
+    use crate::middleware::{pending_allowed_middleware, require_role, RequireRole, UserRole};
     
     Router::new()
         .route("/", get(metrics::get_prometheus_metrics))
-        .route_layer(middleware::from_fn_with_state((), admin_auth_middleware))
+        .route_layer(middleware::from_fn_with_state(RequireRole(UserRole::Admin), require_role))
+        .route_layer(middleware::from_fn_with_state(state.clone(), pending_allowed_middleware))
 }
 
-fn files_routes() -> Router<AppState> {
+fn files_routes(state: &AppState) -> Router<AppState> {
     use axum::middleware;
     use crate::middleware::user_auth_middleware;
     
     Router::new()
-        .route("/:type/:id", get(files::download_file))
-        .route_layer(middleware::from_fn_with_state((), user_auth_middleware))
-}
\ No newline at end of file
+        .route("/{type}/{id}", get(files::download_file))
+        .route_layer(middleware::from_fn_with_state(state.clone(), user_auth_middleware))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAY_HEADER};
+    use crate::test_app::{sign_in, state};
+    use crate::UserRole;
+    use axum::{body::{to_bytes, Body}, http::{header, Request, StatusCode}};
+    use serde_json::Value;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn status(app: &Router, uri: &str, token: &str) -> StatusCode {
+        let request = Request::get(uri).header("authorization", token).body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_admin_routes_require_the_admin_role(pool: PgPool) {
+        let state = state(pool).await;
+        let app = versioned_api_routes(&state).with_state(state.clone());
+        let user = sign_in(&state, UserRole::User).await;
+        let admin = sign_in(&state, UserRole::Admin).await;
+
+        assert_eq!(status(&app, "/api/v1/admin/cache/status", &user).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(&app, "/api/v1/metrics", &user).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(&app, "/api/v1/metrics", &admin).await, StatusCode::OK);
+        assert_eq!(status(&app, "/api/v1/admin/cache/status", "Bearer forged").await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_only_configured_origins_get_cors_headers(pool: PgPool) {
+        let state = state(pool).await;
+        let app = versioned_api_routes(&state).with_state(state.clone());
+        let from = |origin: &str| Request::get("/api/v1/health").header(header::ORIGIN, origin).body(Body::empty()).unwrap();
+
+        let allowed = app.clone().oneshot(from("http://localhost:5173")).await.unwrap();
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:5173");
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let disallowed = app.clone().oneshot(from("https://evil.example.com")).await.unwrap();
+        assert!(disallowed.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        // Preflights are answered on every version without a token
+        let preflight = Request::options("/api/v2/health")
+            .header(header::ORIGIN, "http://localhost:5173")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(preflight).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:5173");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_batch_search_is_served(pool: PgPool) {
+        let state = state(pool).await;
+        let app = versioned_api_routes(&state).with_state(state.clone());
+        let user = sign_in(&state, UserRole::User).await;
+
+        let request = Request::post("/api/v1/search/batch")
+            .header("authorization", &user)
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"queries": [{"dno_name": "Netze BW", "year": 2024}, {"dno_name": "Netze BW", "data_type": "strompreis"}]}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!((body["total"].as_u64(), body["failed"].as_u64()), (Some(2), Some(2)));
+        assert_eq!(body["results"][0]["error"]["code"], "NOT_FOUND");
+        assert_eq!(body["results"][1]["error"]["code"], "BAD_REQUEST");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_retried_crawl_start_is_replayed(pool: PgPool) {
+        let state = state(pool.clone()).await;
+        // An internal website: the background crawl is refused without leaving the host
+        sqlx::query("INSERT INTO dnos (slug, name, website) VALUES ('netze-bw', 'Netze BW', 'http://127.0.0.1/')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let app = versioned_api_routes(&state).with_state(state.clone());
+        let user = sign_in(&state, UserRole::User).await;
+
+        let start = || {
+            Request::post("/api/v1/crawl")
+                .header("authorization", &user)
+                .header("content-type", "application/json")
+                .header(IDEMPOTENCY_KEY_HEADER, "crawl-netze-bw-2024")
+                .body(Body::from(r#"{"dno": "netze-bw", "data_types": ["netzentgelte"], "years": [2024]}"#))
+                .unwrap()
+        };
+        let first = app.clone().oneshot(start()).await.unwrap();
+        let retry = app.oneshot(start()).await.unwrap();
+
+        assert_eq!((first.status(), retry.status()), (StatusCode::ACCEPTED, StatusCode::ACCEPTED));
+        assert!(!first.headers().contains_key(IDEMPOTENT_REPLAY_HEADER));
+        assert_eq!(retry.headers()[IDEMPOTENT_REPLAY_HEADER], "true");
+        let first = to_bytes(first.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(first, to_bytes(retry.into_body(), usize::MAX).await.unwrap());
+
+        let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM crawl_sessions").fetch_one(&pool).await.unwrap();
+        assert_eq!(sessions, 1);
+    }
+}
@@ -1,6 +1,14 @@
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{extract::State, http::StatusCode, response::Json, Extension};
 use serde_json::{json, Value};
-use crate::AppState;
+use crate::{quota::QuotaStatus, AppState, AuthenticatedUser};
+
+/// Remaining monthly quota for the calling user
+pub async fn get_quota(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Json<QuotaStatus> {
+    Json(state.quota.current(user.id, &user.role, chrono::Utc::now()).await)
+}
 
 pub async fn get_profile(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // TODO: Implement actual profile retrieval logic here
@@ -1,6 +1,36 @@
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, Extension};
+use chrono::{DateTime, Duration, Utc};
+use dno_core::cache::DistributedLock;
+use dno_core::review::ReviewOutcome;
+use dno_core::CacheKeys;
+use dno_core::AppError;
+use serde::Deserialize;
 use serde_json::{json, Value};
-use crate::AppState;
+use std::future::Future;
+use uuid::Uuid;
+use crate::{AppState, AuthenticatedUser};
+
+/// Run an edit while holding the owning DNO's write lock, so it cannot interleave
+/// with crawl persistence for that DNO. Fails fast with 409 if a crawl is writing.
+async fn with_dno_write_lock<Fut>(state: &AppState, entry_id: Uuid, edit: Fut) -> Result<Json<Value>, AppError>
+where
+    Fut: Future<Output = Result<Json<Value>, AppError>>,
+{
+    let dno_id = dno_core::database::get_data_entry_dno_id(&state.database, entry_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Data entry not found".to_string()))?;
+
+    let guard = state.dno_locks.for_edit(dno_id).await?;
+    let result = edit.await;
+    state.dno_locks.release(guard).await;
+
+    result
+}
+
+/// Adapt a mock handler's status error to `AppError`
+fn from_status(status: StatusCode) -> AppError {
+    AppError::InternalServerError(format!("Request failed with status {}", status))
+}
 
 pub async fn get_overview(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // TODO: Implement actual overview logic here
@@ -141,10 +171,27 @@ pub async fn _get_data_entry(State(_state): State<AppState>) -> Result<Json<Valu
     })))
 }
 
-pub async fn get_data_entry_source(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement actual data entry source retrieval logic here
-    // For now, fallback to mock
-    _get_data_entry_source(State(state)).await
+/// Source document of a data entry, with per-field provenance of the extracted values
+pub async fn get_data_entry_source(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    let source = dno_core::database::get_data_source_for_entry(&state.database, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No source recorded for this data entry".to_string()))?;
+
+    Ok(Json(json!({
+        "source": {
+            "type": source.source_type,
+            "url": source.source_url,
+            "file_path": source.file_path,
+            "page": source.page_number,
+            "extraction_method": source.extraction_method,
+            "confidence": source.confidence,
+            "extracted_at": source.extracted_at
+        },
+        "provenance": source.extracted_data.unwrap_or_else(|| json!({}))
+    })))
 }
 
 pub async fn _get_data_entry_source(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
@@ -157,10 +204,36 @@ pub async fn _get_data_entry_source(State(_state): State<AppState>) -> Result<Js
     })))
 }
 
-pub async fn verify_data_entry(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement actual data entry verification logic here
-    // For now, fallback to mock
-    _verify_data_entry(State(state)).await
+#[derive(Debug, Deserialize)]
+pub struct VerifyDataEntryRequest {
+    pub status: ReviewOutcome,
+    pub notes: Option<String>,
+}
+
+/// Record an admin's verify/reject decision; feeds the accuracy metrics
+pub async fn verify_data_entry(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<VerifyDataEntryRequest>,
+) -> Result<Json<Value>, AppError> {
+    with_dno_write_lock(&state, id, async {
+        let entry = dno_core::review::review_data_entry(&state.database, id, request.status, user.id, request.notes.as_deref())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Data entry not found".to_string()))?;
+        state.search_repo.invalidate_search_caches(None).await?;
+
+        Ok(Json(json!({
+            "id": id,
+            "verification": {
+                "status": entry["verification_status"],
+                "verified_by": user.email,
+                "verified_at": entry["verified_at"],
+                "notes": entry["verification_notes"]
+            }
+        })))
+    })
+    .await
 }
 
 pub async fn _verify_data_entry(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
@@ -175,10 +248,25 @@ pub async fn _verify_data_entry(State(_state): State<AppState>) -> Result<Json<V
     })))
 }
 
-pub async fn update_data_entry(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement actual data entry update logic here
-    // For now, fallback to mock
-    _update_data_entry(State(state)).await
+/// Correct extracted values; recorded as a correction for the accuracy metrics
+pub async fn update_data_entry(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(changes): Json<serde_json::Map<String, Value>>,
+) -> Result<Json<Value>, AppError> {
+    with_dno_write_lock(&state, id, async {
+        let entry = dno_core::review::correct_data_entry(&state.database, id, &changes, user.id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Data entry not found".to_string()))?;
+        state.search_repo.invalidate_search_caches(None).await?;
+
+        Ok(Json(json!({
+            "message": "Data entry updated successfully",
+            "entry": entry
+        })))
+    })
+    .await
 }
 
 pub async fn _update_data_entry(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
@@ -187,10 +275,13 @@ pub async fn _update_data_entry(State(_state): State<AppState>) -> Result<Json<V
     })))
 }
 
-pub async fn delete_data_entry(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+pub async fn delete_data_entry(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
     // TODO: Implement actual data entry deletion logic here
     // For now, fallback to mock
-    _delete_data_entry(State(state)).await
+    with_dno_write_lock(&state, id, async { _delete_data_entry(State(state.clone())).await.map_err(from_status) }).await
 }
 
 pub async fn _delete_data_entry(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
@@ -199,6 +290,42 @@ pub async fn _delete_data_entry(State(_state): State<AppState>) -> Result<Json<V
     })))
 }
 
+/// Request body for `recompute_verification`; thresholds default to the configured policy
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct RecomputeVerificationRequest {
+    #[serde(flatten)]
+    pub filter: dno_core::verification::RecomputeFilter,
+    pub auto_verify_confidence: Option<f64>,
+}
+
+/// Re-evaluate automatic verification statuses against the current thresholds
+/// and schema checks, then drop the search caches of the affected data
+pub async fn recompute_verification(
+    State(state): State<AppState>,
+    body: Option<Json<RecomputeVerificationRequest>>,
+) -> Result<Json<Value>, AppError> {
+    let Json(request) = body.unwrap_or_default();
+    let mut policy = dno_core::verification::VerificationPolicy::from_env();
+    if let Some(threshold) = request.auto_verify_confidence {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(AppError::BadRequest("auto_verify_confidence must be between 0 and 1".to_string()));
+        }
+        policy.auto_verify_confidence = threshold;
+    }
+
+    let report = dno_core::verification::recompute_verification(&state.database, &policy, &request.filter).await?;
+    if report.changed > 0 {
+        // Coverage and count caches span data types, so drop every search cache
+        state.search_repo.invalidate_search_caches(None).await?;
+    }
+
+    Ok(Json(json!({
+        "policy": policy,
+        "report": report
+    })))
+}
+
 pub async fn bulk_data_entries(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // TODO: Implement actual bulk operations logic here
     // For now, fallback to mock
@@ -338,10 +465,20 @@ pub async fn _get_logs(State(_state): State<AppState>) -> Result<Json<Value>, St
     })))
 }
 
-pub async fn trigger_crawl(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement actual crawl triggering logic here
+/// Launch a crawl. Runs the same validation as `POST /crawl/validate`, so a
+/// request that validated cleanly is accepted here.
+pub async fn trigger_crawl(
+    State(state): State<AppState>,
+    Json(request): Json<dno_core::crawl_request::CrawlRequest>,
+) -> Result<Json<Value>, AppError> {
+    if let Err(errors) = super::crawl::plan_crawl(&state, &request).await? {
+        let fields: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        return Err(AppError::BadRequest(format!("Invalid crawl request ({})", fields.join("; "))));
+    }
+
+    // TODO: Enqueue the planned sessions
     // For now, fallback to mock
-    _trigger_crawl(State(state)).await
+    _trigger_crawl(State(state)).await.map_err(from_status)
 }
 
 pub async fn _trigger_crawl(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
@@ -396,6 +533,227 @@ pub async fn _export_metrics(State(_state): State<AppState>) -> Result<Json<Valu
     })))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AccuracyQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Extraction accuracy from admin reviews, by extraction method and data type.
+/// Defaults to the last 30 days.
+pub async fn get_accuracy_metrics(
+    State(state): State<AppState>,
+    Query(query): Query<AccuracyQuery>,
+) -> Result<Json<Value>, AppError> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    if from >= to {
+        return Err(AppError::BadRequest("'from' must be before 'to'".to_string()));
+    }
+
+    let report = dno_core::review::accuracy_metrics(&state.database, from, to).await?;
+    let breakdown: Vec<Value> = report
+        .breakdown
+        .iter()
+        .map(|b| json!({
+            "extraction_method": b.extraction_method,
+            "data_type": b.data_type,
+            "total": b.total,
+            "verified": b.verified,
+            "rejected": b.rejected,
+            "corrected": b.corrected,
+            "precision": b.precision(),
+            "correction_rate": b.correction_rate(),
+            "rejection_rate": b.rejection_rate()
+        }))
+        .collect();
+
+    Ok(Json(json!({
+        "from": report.from,
+        "to": report.to,
+        "breakdown": breakdown
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalibrationQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Number of equal-width confidence buckets, 10 by default
+    pub buckets: Option<usize>,
+}
+
+/// Whether reported extraction confidence matches how often admins verify the
+/// data, per confidence bucket. Defaults to the last 30 days.
+pub async fn get_confidence_calibration(
+    State(state): State<AppState>,
+    Query(query): Query<CalibrationQuery>,
+) -> Result<Json<Value>, AppError> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    if from >= to {
+        return Err(AppError::BadRequest("'from' must be before 'to'".to_string()));
+    }
+
+    let report = dno_core::review::calibration_report(&state.database, from, to, query.buckets.unwrap_or(10)).await?;
+    Ok(Json(json!(report)))
+}
+
+/// Conflicting values from different sources; open ones by default
+pub async fn list_conflicts(
+    State(state): State<AppState>,
+    Query(filter): Query<dno_core::conflicts::ConflictFilter>,
+) -> Result<Json<Value>, AppError> {
+    let conflicts = dno_core::conflicts::list_conflicts(&state.database, &filter).await?;
+    Ok(Json(json!({
+        "total": conflicts.len(),
+        "conflicts": conflicts
+    })))
+}
+
+/// Keep one candidate of a conflict, or enter a corrected value
+pub async fn resolve_conflict(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(resolution): Json<dno_core::conflicts::ConflictResolution>,
+) -> Result<Json<Value>, AppError> {
+    let conflict = dno_core::conflicts::resolve_conflict(&state.database, id, &resolution, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Conflict not found".to_string()))?;
+    state.search_repo.invalidate_search_caches(None).await?;
+
+    Ok(Json(json!({
+        "message": "Conflict resolved",
+        "conflict": conflict
+    })))
+}
+
+/// URLs crawls gave up on because they need a human, e.g. access denied;
+/// pending ones by default
+pub async fn list_manual_review(
+    State(state): State<AppState>,
+    Query(filter): Query<dno_core::manual_review::ManualReviewFilter>,
+) -> Result<Json<Value>, AppError> {
+    let items = dno_core::manual_review::list_manual_review(&state.database, &filter).await?;
+    Ok(Json(json!({
+        "total": items.len(),
+        "items": items
+    })))
+}
+
+/// Re-enqueue a pending URL: starts a crawl session at it. 409 if it was already re-submitted.
+pub async fn retry_manual_review(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+    let (item, session) = dno_core::manual_review::retry_manual_review(&state.database, &state.crawl_jobs, id, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Manual review item not found".to_string()))?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "message": "URL re-submitted for crawling",
+            "item": item,
+            "session_id": session.id
+        })),
+    ))
+}
+
+/// Audit trail of stored source files, newest first; filter by `operation`,
+/// `actor`, `target_contains`, `file_id` and `from`/`to`, paginate with `limit`/`offset`
+pub async fn list_file_audit(
+    State(state): State<AppState>,
+    Query(filter): Query<dno_core::source_manager::AuditFilter>,
+) -> Result<Json<Value>, AppError> {
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+        if from >= to {
+            return Err(AppError::BadRequest("'from' must be before 'to'".to_string()));
+        }
+    }
+    let page = state.sources.query_audit_trail(&filter);
+    Ok(Json(json!(page)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DedupRunQuery {
+    /// Report files at least this similar (0–1) for manual review instead of
+    /// removing identical ones
+    pub threshold: Option<f64>,
+}
+
+/// Remove duplicate source files, keeping the earliest stored copy of each.
+/// Only one run at a time; file storage by running crawls waits for the run.
+pub async fn run_deduplication(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<DedupRunQuery>,
+) -> Result<Json<Value>, AppError> {
+    let lock = DistributedLock::new(state.cache.clone());
+    let guard = lock
+        .acquire(
+            &CacheKeys::dedup_lock(),
+            std::time::Duration::from_secs(600),
+            std::time::Duration::from_secs(2),
+        )
+        .await?;
+
+    let sources = state.sources.clone();
+    let actor = user.email.clone();
+    let result = tokio::task::spawn_blocking(move || match query.threshold {
+        Some(threshold) => sources.perform_fuzzy_deduplication(threshold),
+        None => sources.perform_deduplication(&actor),
+    })
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Deduplication task failed: {}", e)));
+    lock.release(guard).await;
+
+    let result = result??;
+    dno_core::dedup::record_dedup_run(&state.database, &result, Some(user.id)).await?;
+    Ok(Json(json!(result)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeQuery {
+    /// Only purge files stored at least this many days ago (default 30)
+    pub older_than_days: Option<u32>,
+}
+
+/// Delete the bytes of rejected and deduplicated source files from disk
+pub async fn purge_inactive_files(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<PurgeQuery>,
+) -> Result<Json<Value>, AppError> {
+    let older_than = std::time::Duration::from_secs(u64::from(query.older_than_days.unwrap_or(30)) * 24 * 3600);
+    let sources = state.sources.clone();
+    let report = tokio::task::spawn_blocking(move || sources.purge_inactive(older_than, &user.email))
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Purge task failed: {}", e)))??;
+    Ok(Json(json!(report)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DedupRunsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Past deduplication runs, newest first
+pub async fn list_dedup_runs(
+    State(state): State<AppState>,
+    Query(query): Query<DedupRunsQuery>,
+) -> Result<Json<Value>, AppError> {
+    let runs =
+        dno_core::dedup::list_dedup_runs(&state.database, query.limit.unwrap_or(50), query.offset.unwrap_or(0)).await?;
+    Ok(Json(json!({
+        "total": runs.len(),
+        "runs": runs
+    })))
+}
+
 pub async fn get_timeseries(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // TODO: Implement actual timeseries data logic here
     // For now, fallback to mock
@@ -2,8 +2,9 @@ use axum::{extract::State, http::StatusCode, response::Json, Extension};
 use serde_json::{json, Value};
 use uuid::Uuid;
 use chrono::{Utc, Duration};
-use crate::{AppState, AuthenticatedUser, middleware::{generate_jwt_token, hash_password, verify_password}};
-use core::models::*;
+use crate::{AppState, AuthenticatedUser, middleware::{hash_password, verify_password}};
+use dno_core::models::*;
+use dno_core::AppError;
 
 pub async fn login(
     State(state): State<AppState>, 
@@ -63,29 +64,14 @@ pub async fn login(
     let access_token_expiry = Duration::seconds(state.config.jwt_access_token_expiry);
     let refresh_token_expiry = Duration::seconds(state.config.jwt_refresh_token_expiry);
 
-    let access_token = match generate_jwt_token(
-        &user,
-        session_id,
-        &state.jwt_secret,
-        state.config.jwt_access_token_expiry,
-    ) {
-        Ok(token) => token,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-
-    let refresh_token = match generate_jwt_token(
-        &user,
-        session_id,
-        &state.jwt_secret,
-        state.config.jwt_refresh_token_expiry,
-    ) {
-        Ok(token) => token,
+    let tokens = match state.jwt.issue_token_pair(&user, session_id).await {
+        Ok(tokens) => tokens,
         Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     };
 
     // Hash tokens for storage
-    let access_token_hash = format!("{:x}", md5::compute(&access_token));
-    let refresh_token_hash = format!("{:x}", md5::compute(&refresh_token));
+    let access_token_hash = format!("{:x}", md5::compute(&tokens.access_token));
+    let refresh_token_hash = format!("{:x}", md5::compute(&tokens.refresh_token));
 
     // Create session in database
     let session = CreateSession {
@@ -104,11 +90,6 @@ pub async fn login(
 
     // Prepare response
     let user_public = UserPublic::from(user.clone());
-    let tokens = TokenPair {
-        access_token,
-        refresh_token,
-        expires_in: state.config.jwt_access_token_expiry,
-    };
 
     let message = match user.role {
         UserRole::Pending => Some("Account pending approval. Contact admin for verification.".to_string()),
@@ -194,29 +175,14 @@ pub async fn register(
     let access_token_expiry = Duration::seconds(state.config.jwt_access_token_expiry);
     let refresh_token_expiry = Duration::seconds(state.config.jwt_refresh_token_expiry);
 
-    let access_token = match generate_jwt_token(
-        &user,
-        session_id,
-        &state.jwt_secret,
-        state.config.jwt_access_token_expiry,
-    ) {
-        Ok(token) => token,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-
-    let refresh_token = match generate_jwt_token(
-        &user,
-        session_id,
-        &state.jwt_secret,
-        state.config.jwt_refresh_token_expiry,
-    ) {
-        Ok(token) => token,
+    let tokens = match state.jwt.issue_token_pair(&user, session_id).await {
+        Ok(tokens) => tokens,
         Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     };
 
     // Hash tokens for storage
-    let access_token_hash = format!("{:x}", md5::compute(&access_token));
-    let refresh_token_hash = format!("{:x}", md5::compute(&refresh_token));
+    let access_token_hash = format!("{:x}", md5::compute(&tokens.access_token));
+    let refresh_token_hash = format!("{:x}", md5::compute(&tokens.refresh_token));
 
     // Create session in database
     let session = CreateSession {
@@ -235,11 +201,6 @@ pub async fn register(
 
     // Prepare response
     let user_public = UserPublic::from(user);
-    let tokens = TokenPair {
-        access_token,
-        refresh_token,
-        expires_in: state.config.jwt_access_token_expiry,
-    };
 
     Ok(Json(json!({
         "user": user_public,
@@ -248,15 +209,38 @@ pub async fn register(
     })))
 }
 
-pub async fn refresh(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement token refresh logic
-    // This would extract refresh token from header, validate it, and issue new access token
-    Ok(Json(json!({
-        "error": "not_implemented",
-        "message": "Token refresh not yet implemented",
-        "details": {},
-        "request_id": Uuid::new_v4().to_string()
-    })))
+/// Exchange a refresh token for a new pair. The old refresh token stops
+/// working; presenting it again revokes every token of its login.
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(request): Json<RefreshTokenRequest>
+) -> Result<Json<Value>, AppError> {
+    let redeemed = state.jwt.redeem_refresh_token(&request.refresh_token).await?;
+    let user_id = Uuid::parse_str(&redeemed.sub)
+        .map_err(|_| AppError::Unauthorized("Invalid token subject".to_string()))?;
+
+    // The new pair carries the user's current role, email and name
+    let user = state.user_repo.get_user_by_id(user_id).await?
+        .filter(|user| user.is_active && user.deleted_at.is_none());
+    let Some(user) = user else {
+        state.jwt.revoke_family(&redeemed.family_id).await?;
+        return Err(AppError::Unauthorized("Account has been disabled".to_string()));
+    };
+    let tokens = state.jwt.issue_refreshed(&user, &redeemed).await?;
+
+    // The new access token needs its own session row to pass authentication
+    let session = CreateSession {
+        user_id,
+        token_hash: format!("{:x}", md5::compute(&tokens.access_token)),
+        refresh_token_hash: Some(format!("{:x}", md5::compute(&tokens.refresh_token))),
+        expires_at: Utc::now() + Duration::seconds(state.config.jwt_access_token_expiry),
+        refresh_expires_at: Some(Utc::now() + Duration::seconds(state.config.jwt_refresh_token_expiry)),
+        ip_address: None,
+        user_agent: None,
+    };
+    state.user_repo.create_session(session).await?;
+
+    Ok(Json(json!({ "tokens": tokens })))
 }
 
 pub async fn logout(
@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json,
+    },
+    Extension,
+};
+use chrono::Datelike;
+use dno_core::crawl_job::{CrawlJobs, CrawlSessionReport};
+use dno_core::crawl_plan::{CrawlContext, CrawlPlan, CrawlStrategy};
+use dno_core::crawl_request::{CrawlModeRequest, CrawlRequest, CrawlRequestPlan, FieldError};
+use dno_core::live::{LiveEvent, LiveSessions};
+use dno_core::navigation::NavigationPathRequest;
+use dno_core::AppError;
+use futures::Stream;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use crate::{AppState, AuthenticatedUser};
+
+/// Validate a crawl request against the current DNO list; shared by the
+/// dry-run endpoint and the admin crawl trigger
+pub async fn plan_crawl(state: &AppState, request: &CrawlRequest) -> Result<Result<CrawlRequestPlan, Vec<FieldError>>, AppError> {
+    let dnos = state.dno_repo.get_all_dnos().await?;
+    Ok(request.validate(&dnos, chrono::Utc::now().year()))
+}
+
+/// Dry run: report field errors or the sessions the crawl would create, each
+/// with its seeds, strategy order and request estimate, without creating or
+/// fetching anything
+pub async fn validate_crawl(
+    State(state): State<AppState>,
+    Json(request): Json<CrawlRequest>,
+) -> Result<Json<Value>, AppError> {
+    let response = match plan_crawl(&state, &request).await? {
+        Ok(plan) => {
+            let dnos = state.dno_repo.get_all_dnos().await?;
+            let order = CrawlStrategy::configured_order();
+            // Patterns learned by a running crawler are not known here, so
+            // only the stored websites and search queries appear as seeds
+            let crawl_plans: Vec<CrawlPlan> = CrawlContext::for_sessions(&plan, &dnos)
+                .iter()
+                .map(|context| CrawlPlan::build(context, &order, Vec::new()))
+                .collect();
+            json!({ "valid": true, "errors": [], "plan": plan, "crawl_plans": crawl_plans })
+        }
+        Err(errors) => json!({ "valid": false, "errors": errors, "plan": null, "crawl_plans": [] }),
+    };
+    Ok(Json(response))
+}
+
+/// Body of `POST /crawl`: one DNO, crawled for every year and data type
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StartCrawlRequest {
+    /// DNO id, slug or name
+    pub dno: String,
+    pub data_types: Vec<String>,
+    pub years: Vec<i32>,
+    pub mode: Option<CrawlModeRequest>,
+}
+
+/// Start a crawl session and return its id right away with `202 Accepted`.
+/// The request is validated like `/crawl/validate`; the crawl jobs then run
+/// in the background. Poll `GET /crawl/{session_id}` or follow its stream.
+pub async fn start_crawl(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(request): Json<StartCrawlRequest>,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+    let request = CrawlRequest {
+        dnos: vec![request.dno],
+        years: request.years,
+        data_types: request.data_types,
+        mode: request.mode,
+        ..Default::default()
+    };
+    let plan = plan_crawl(&state, &request).await?.map_err(|errors| {
+        let fields: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        AppError::BadRequest(format!("Invalid crawl request ({})", fields.join("; ")))
+    })?;
+    let dnos = state.dno_repo.get_all_dnos().await?;
+    let contexts = CrawlContext::for_sessions(&plan, &dnos);
+    let session = state.crawl_jobs.start(Some(user.id), &plan.mode, contexts).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "session_id": session.id, "status": session.status, "jobs": plan.session_count })),
+    ))
+}
+
+/// Status of a crawl session: progress counts per job status, totals over the
+/// finished jobs and every job with its result. 404 for unknown sessions.
+pub async fn get_crawl_session(
+    State(jobs): State<CrawlJobs>,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<CrawlSessionReport>, AppError> {
+    jobs.status(session_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("No crawl session {}", session_id)))
+}
+
+/// Store a hand-authored navigation path to a DNO's data. Reverse crawls
+/// replay it by id instead of discovering the way themselves.
+pub async fn create_navigation_path(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(request): Json<NavigationPathRequest>,
+) -> Result<Json<Value>, AppError> {
+    let dnos = state.dno_repo.get_all_dnos().await?;
+    let (dno, steps) = request.validate(&dnos).map_err(|errors| {
+        let fields: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        AppError::BadRequest(format!("Invalid navigation path ({})", fields.join("; ")))
+    })?;
+
+    let name = request.name.as_deref().map(str::trim).filter(|name| !name.is_empty());
+    let path = dno_core::navigation::create_navigation_path(&state.database, dno.id, name, &steps, Some(user.id)).await?;
+    Ok(Json(json!({ "path": path })))
+}
+
+/// Follow a running crawl session as server-sent events: a `log` event per
+/// log line, then one `finished` event with the final status, after which
+/// the stream ends. A client too slow to keep up gets a `lagged` event with
+/// the number of lines it missed. 404 once the session has finished.
+pub async fn stream_crawl(
+    State(sessions): State<LiveSessions>,
+    Path(session_id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let receiver = sessions
+        .subscribe(session_id)
+        .ok_or_else(|| AppError::NotFound(format!("No running crawl session {}", session_id)))?;
+
+    // A disconnecting client drops the stream and with it the receiver
+    let events = futures::stream::unfold(Some(receiver), |receiver| async move {
+        let mut receiver = receiver?;
+        let (event, open) = match receiver.recv().await {
+            Ok(event @ LiveEvent::Log(_)) => (Event::default().event("log").data(to_json(&event)), true),
+            Ok(event @ LiveEvent::Finished { .. }) => (Event::default().event("finished").data(to_json(&event)), false),
+            Err(RecvError::Lagged(skipped)) => (Event::default().event("lagged").data(skipped.to_string()), true),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), open.then_some(receiver)))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn to_json(event: &LiveEvent) -> String {
+    serde_json::to_string(event).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use dno_core::live::{CrawlSessionStatus, LogLevel};
+
+    #[tokio::test]
+    async fn test_stream_delivers_logs_in_order_then_ends() {
+        let sessions = LiveSessions::new();
+        let id = Uuid::new_v4();
+        let session = sessions.open(id);
+
+        let response = stream_crawl(State(sessions.clone()), Path(id)).await.unwrap().into_response();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        session.log(LogLevel::Info, "Seite 1 geladen");
+        session.log(LogLevel::Warn, "Seite 2 ohne Preisblatt");
+        session.log(LogLevel::Info, "Seite 3 geladen");
+        session.finish(CrawlSessionStatus::Completed);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let events: Vec<&str> = body.lines().filter_map(|line| line.strip_prefix("event: ")).collect();
+        assert_eq!(events, ["log", "log", "log", "finished"]);
+        let positions: Vec<usize> = ["Seite 1", "Seite 2", "Seite 3", "\"completed\""]
+            .iter()
+            .map(|text| body.find(text).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "out of order: {}", body);
+
+        assert_eq!(sessions.running(), 0);
+        assert!(stream_crawl(State(sessions), Path(id)).await.is_err());
+    }
+}
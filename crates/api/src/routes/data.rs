@@ -0,0 +1,18 @@
+use axum::{extract::{Path, State}, response::Json};
+use dno_core::AppError;
+use serde_json::Value;
+use uuid::Uuid;
+use crate::{ApiError, AppState};
+
+/// When a data point first appeared, when a crawl last confirmed it, and its
+/// reviews and corrections, in chronological order
+pub async fn get_timeline(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, ApiError> {
+    let timeline = dno_core::timeline::get_data_timeline(&state.database, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("data entry", format!("No data entry with id {}", id)))?;
+
+    Ok(Json(serde_json::to_value(timeline).map_err(AppError::from)?))
+}
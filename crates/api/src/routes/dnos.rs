@@ -0,0 +1,26 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::{ApiError, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct AutocompleteQuery {
+    #[serde(default)]
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+/// Type-ahead suggestions for DNO names: prefix matches first, then fuzzy
+/// ones, each with slug and region. Served from the in-memory index of
+/// `DnoRepository`, so no database query runs per keystroke.
+pub async fn autocomplete(
+    State(state): State<AppState>,
+    Query(query): Query<AutocompleteQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let suggestions = state.dno_repo.autocomplete(&query.q, limit).await?;
+    Ok(Json(json!({ "query": query.q, "suggestions": suggestions })))
+}
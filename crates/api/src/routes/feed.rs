@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use dno_core::cache::CacheLayer;
+use dno_core::{AppError, CacheKeys};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+use crate::versioning::ApiVersion;
+use crate::AppState;
+
+/// Long enough to absorb feed readers polling together, short enough that
+/// new verifications show up within a minute
+const FEED_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    /// Only entries verified before this time, for older pages
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// Atom feed of recently verified data records, newest first
+pub async fn get_feed(
+    State(state): State<AppState>,
+    Extension(version): Extension<ApiVersion>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Response, AppError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let cache_key = format!("{}:{}", CacheKeys::verified_feed(query.before, limit), version.prefix());
+
+    let xml = match state.cache.get::<String>(&cache_key).await {
+        Ok(Some(xml)) => xml,
+        cached => {
+            if let Err(e) = cached {
+                warn!("Cache error for feed: {}", e);
+            }
+            let entries = dno_core::feed::list_verified_entries(&state.database, query.before, limit).await?;
+            let xml = dno_core::feed::render_atom(version.prefix(), &entries, entries.len() as i64 == limit);
+            if let Err(e) = state.cache.set(&cache_key, &xml, Some(FEED_TTL)).await {
+                warn!("Failed to cache feed: {}", e);
+            }
+            xml
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/atom+xml; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=60"),
+        ],
+        xml,
+    )
+        .into_response())
+}
@@ -1,19 +1,147 @@
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{extract::{Query, State}, http::StatusCode, response::Json};
+use futures::future::{join_all, BoxFuture};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::time::{Duration, Instant};
 use crate::AppState;
 
-pub async fn health_check(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement actual health check logic here
-    // For now, fallback to mock
-    _health_check(State(state)).await
+#[derive(Debug, Deserialize)]
+pub struct HealthParams {
+    /// `false` answers without touching any dependency (liveness)
+    pub deep: Option<bool>,
 }
 
-pub async fn _health_check(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    Ok(Json(json!({
-        "status": "ok",
-        "timestamp": "2024-01-15T15:00:00Z",
-        "version": "1.0.0"
-    })))
+/// Outcome of one dependency probe
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyHealth {
+    pub name: &'static str,
+    /// `up` or `down`
+    pub status: &'static str,
+    /// A down optional dependency degrades the service instead of failing it
+    pub required: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A dependency check that has not run yet
+pub struct Probe {
+    pub name: &'static str,
+    pub required: bool,
+    pub check: BoxFuture<'static, Result<(), String>>,
+}
+
+impl Probe {
+    pub fn new(
+        name: &'static str,
+        required: bool,
+        check: impl std::future::Future<Output = Result<(), String>> + Send + 'static,
+    ) -> Self {
+        Self { name, required, check: Box::pin(check) }
+    }
+}
+
+/// Run every probe concurrently, each bounded by `timeout`
+pub async fn run_probes(probes: Vec<Probe>, timeout: Duration) -> Vec<DependencyHealth> {
+    join_all(probes.into_iter().map(|probe| async move {
+        let start = Instant::now();
+        let outcome = match tokio::time::timeout(timeout, probe.check).await {
+            Ok(outcome) => outcome,
+            Err(_) => Err(format!("no answer within {} ms", timeout.as_millis())),
+        };
+        DependencyHealth {
+            name: probe.name,
+            status: if outcome.is_ok() { "up" } else { "down" },
+            required: probe.required,
+            latency_ms: start.elapsed().as_millis() as u64,
+            error: outcome.err(),
+        }
+    }))
+    .await
+}
+
+/// Overall status and HTTP status: any required dependency down is `503`,
+/// an optional one down is `degraded` but still `200`
+pub fn overall(checks: &[DependencyHealth]) -> (&'static str, StatusCode) {
+    let down = |required: bool| checks.iter().any(|check| check.required == required && check.status != "up");
+    if down(true) {
+        ("unhealthy", StatusCode::SERVICE_UNAVAILABLE)
+    } else if down(false) {
+        ("degraded", StatusCode::OK)
+    } else {
+        ("ok", StatusCode::OK)
+    }
+}
+
+/// GET `url` and expect a success status
+pub async fn http_probe(client: reqwest::Client, url: String) -> Result<(), String> {
+    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{} answered {}", url, response.status()))
+    }
+}
+
+/// Readiness probe over Postgres, Redis, Ollama and SearXNG; `?deep=false`
+/// is a liveness check that only shows the process answers
+pub async fn health_check(
+    State(state): State<AppState>,
+    Query(params): Query<HealthParams>,
+) -> (StatusCode, Json<Value>) {
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    if params.deep == Some(false) {
+        return (
+            StatusCode::OK,
+            Json(json!({
+                "status": "ok",
+                "deep": false,
+                "timestamp": timestamp,
+                "version": env!("CARGO_PKG_VERSION")
+            })),
+        );
+    }
+
+    let timeout = Duration::from_millis(state.config.health_check_timeout_ms);
+    let client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(e) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "unhealthy", "error": e.to_string(), "timestamp": timestamp })),
+            )
+        }
+    };
+
+    let database = state.database.clone();
+    let cache = state.cache.clone();
+    let probes = vec![
+        Probe::new("database", true, async move {
+            dno_core::database::health_check(&database).await.map_err(|e| e.to_string())
+        }),
+        Probe::new("redis", true, async move {
+            cache.health_check().await.map(|_| ()).map_err(|e| e.to_string())
+        }),
+        Probe::new(
+            "ollama",
+            false,
+            http_probe(client.clone(), format!("{}/api/tags", state.config.ollama_url.trim_end_matches('/'))),
+        ),
+        Probe::new("searxng", false, http_probe(client, state.config.searxng_url.clone())),
+    ];
+    let checks = run_probes(probes, timeout).await;
+    let (status, code) = overall(&checks);
+
+    (
+        code,
+        Json(json!({
+            "status": status,
+            "deep": true,
+            "checks": checks,
+            "timestamp": timestamp,
+            "version": env!("CARGO_PKG_VERSION")
+        })),
+    )
 }
 
 pub async fn readiness_check(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
@@ -32,4 +160,92 @@ pub async fn _readiness_check(State(_state): State<AppState>) -> Result<Json<Val
         },
         "timestamp": "2024-01-15T15:00:00Z"
     })))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// HTTP server answering every request with `status`
+    async fn mock_server(status: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buffer = [0u8; 1024];
+                    let _ = socket.read(&mut buffer).await;
+                    let response = format!("HTTP/1.1 {}\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{{}}", status);
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// A port nothing listens on
+    async fn closed_port() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{}", addr)
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().timeout(Duration::from_secs(2)).build().unwrap()
+    }
+
+    fn probes(database: Result<(), String>, redis_delay: Duration, ollama: String, searxng: String) -> Vec<Probe> {
+        vec![
+            Probe::new("database", true, async move { database }),
+            Probe::new("redis", true, async move {
+                tokio::time::sleep(redis_delay).await;
+                Ok(())
+            }),
+            Probe::new("ollama", false, http_probe(client(), format!("{}/api/tags", ollama))),
+            Probe::new("searxng", false, http_probe(client(), searxng)),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_all_dependencies_up_is_ok() {
+        let ollama = mock_server("200 OK").await;
+        let searxng = mock_server("200 OK").await;
+        let checks = run_probes(probes(Ok(()), Duration::ZERO, ollama, searxng), Duration::from_secs(2)).await;
+
+        assert_eq!(checks.iter().map(|c| c.name).collect::<Vec<_>>(), ["database", "redis", "ollama", "searxng"]);
+        assert!(checks.iter().all(|c| c.status == "up" && c.error.is_none()));
+        assert_eq!(overall(&checks), ("ok", StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_optional_dependencies_down_degrade() {
+        let ollama = closed_port().await;
+        let searxng = mock_server("502 Bad Gateway").await;
+        let checks = run_probes(probes(Ok(()), Duration::ZERO, ollama, searxng), Duration::from_secs(2)).await;
+
+        assert_eq!(checks[2].status, "down");
+        assert!(checks[3].error.as_deref().unwrap().contains("502"));
+        assert_eq!(overall(&checks), ("degraded", StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_required_dependency_down_or_slow_is_unavailable() {
+        let ollama = mock_server("200 OK").await;
+        let searxng = mock_server("200 OK").await;
+        let probes = probes(Err("connection refused".to_string()), Duration::from_secs(30), ollama, searxng);
+
+        let start = Instant::now();
+        let checks = run_probes(probes, Duration::from_millis(200)).await;
+        // Probes run side by side, so the slow Redis bounds the whole run
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        assert_eq!(checks[0].error.as_deref(), Some("connection refused"));
+        assert_eq!((checks[1].status, checks[1].error.as_deref()), ("down", Some("no answer within 200 ms")));
+        assert!(checks[1].latency_ms >= 200);
+        assert_eq!(checks[2].status, "up");
+        assert_eq!(overall(&checks), ("unhealthy", StatusCode::SERVICE_UNAVAILABLE));
+    }
+}
@@ -1,15 +1,80 @@
-use axum::{extract::State, http::StatusCode, response::Json};
-use serde_json::{json, Value};
-use crate::AppState;
-
-pub async fn get_prometheus_metrics(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    // TODO: Implement actual prometheus metrics logic here
-    // For now, fallback to mock
-    _get_prometheus_metrics(State(state)).await
+use axum::{extract::State, http::header, response::IntoResponse};
+use dno_core::metrics::MetricsRegistry;
+
+/// Content type of the Prometheus text exposition format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Cache, crawl, extraction and HTTP metrics for Prometheus to scrape
+pub async fn get_prometheus_metrics(State(metrics): State<MetricsRegistry>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], metrics.render())
 }
 
-pub async fn _get_prometheus_metrics(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
-    Ok(Json(json!({
-        "metrics": "# HELP dno_crawler_queries_total Total number of queries\n# TYPE dno_crawler_queries_total counter\ndno_crawler_queries_total{status=\"success\",cache=\"hit\"} 2805\ndno_crawler_queries_total{status=\"success\",cache=\"miss\"} 616\n\n# HELP dno_crawler_data_entries Total data entries in system\n# TYPE dno_crawler_data_entries gauge\ndno_crawler_data_entries{type=\"netzentgelte\",verified=\"true\"} 12450\ndno_crawler_data_entries{type=\"netzentgelte\",verified=\"false\"} 2970"
-    })))
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::{to_bytes, Body}, http::{Request, StatusCode}, middleware, routing::get, Json, Router};
+    use dno_core::cache::{CacheLayer, MemoryCache};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// A search answering from the cache, and the metrics endpoint, behind the HTTP metrics layer
+    fn app(cache: Arc<MemoryCache>) -> Router {
+        let search = move || {
+            let cache = cache.clone();
+            async move {
+                let key = "search:netzentgelte:netze-bw-2024";
+                let cached: Option<Value> = cache.get(key).await.unwrap();
+                let results = match cached {
+                    Some(results) => results,
+                    None => {
+                        let results = json!([{ "voltage_level": "MS", "leistung": "58.21" }]);
+                        cache.set(key, &results, None).await.unwrap();
+                        results
+                    }
+                };
+                Json(json!({ "results": results }))
+            }
+        };
+
+        Router::new()
+            .route("/api/v1/search/", get(search))
+            .route("/api/v1/metrics/", get(get_prometheus_metrics))
+            .layer(middleware::from_fn(crate::middleware::http_metrics_middleware))
+            .with_state(dno_core::metrics::global())
+    }
+
+    async fn get_text(app: &Router, uri: &str) -> (StatusCode, Option<String>, String) {
+        let response = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let content_type = response.headers().get(header::CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string());
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_scrape_after_search_lists_known_metrics() {
+        let app = app(Arc::new(MemoryCache::new()));
+        for _ in 0..2 {
+            assert_eq!(get_text(&app, "/api/v1/search/?year=2024").await.0, StatusCode::OK);
+        }
+        dno_core::metrics::global().observe_extraction("html_table", 0.9);
+
+        let (status, content_type, text) = get_text(&app, "/api/v1/metrics/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some(PROMETHEUS_CONTENT_TYPE));
+        for name in [
+            "# TYPE dno_cache_requests_total counter",
+            "dno_cache_requests_total{prefix=\"search:netzentgelte:*\",outcome=\"miss\"}",
+            "dno_cache_requests_total{prefix=\"search:netzentgelte:*\",outcome=\"hit\"}",
+            "dno_cache_hit_ratio{prefix=\"search:netzentgelte:*\"}",
+            "# TYPE dno_crawls_total counter",
+            "dno_extraction_confidence_bucket{method=\"html_table\",le=\"0.9\"}",
+            "dno_http_request_duration_seconds_count{method=\"GET\",route=\"/api/v1/search/\",status=\"200\"}",
+        ] {
+            assert!(text.contains(name), "{} missing from\n{}", name, text);
+        }
+        // The concrete query string never becomes a label
+        assert!(!text.contains("year=2024"));
+    }
+}
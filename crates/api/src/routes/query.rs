@@ -0,0 +1,18 @@
+use axum::{extract::State, response::Json};
+use dno_core::ollama::ProcessedQuery;
+use serde::Deserialize;
+use crate::{ApiError, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct InterpretRequest {
+    pub query: String,
+}
+
+/// Classify a free-text query (intent, DNO, year, data type) without
+/// searching; clients turn the result into a search request themselves
+pub async fn interpret_query(
+    State(state): State<AppState>,
+    Json(request): Json<InterpretRequest>,
+) -> Result<Json<ProcessedQuery>, ApiError> {
+    Ok(Json(state.query_repo.interpret(&request.query).await?))
+}
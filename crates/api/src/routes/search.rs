@@ -1,46 +1,99 @@
-use axum::{extract::{Query, State}, http::StatusCode, response::Json, Extension};
+use axum::{extract::{rejection::JsonRejection, Query, State}, http::StatusCode, response::{IntoResponse, Json, Response}, Extension};
+use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
-use crate::{AppState, AuthenticatedUser};
-use core::models::*;
+use crate::{ApiError, AppState, AuthenticatedUser, ClientIp};
+use crate::export::{export_filename, export_response, ExportFormat};
+use crate::versioning::{render_search, ApiVersion, SearchKind, SearchOutcome};
+use dno_core::models::*;
+use dno_core::SearchDiagnostics;
+use dno_core::paging::{validate_limit, Paging, SearchCursor};
+use dno_core::repository::{DataTypeQuery, DataTypeSearch};
+use dno_core::AppError;
+use futures::stream::{self, StreamExt};
+use std::future::Future;
+use tracing::warn;
+
+/// DNOs suggested in `available_dnos` when a name matches none exactly
+const FUZZY_SUGGESTIONS: i64 = 5;
+/// Most queries one `/search/batch` request may carry
+pub const MAX_BATCH_QUERIES: usize = 50;
+/// Batch queries searched at the same time
+const BATCH_CONCURRENCY: usize = 8;
+/// Results returned per batch query
+const BATCH_RESULT_LIMIT: i64 = 50;
+
+/// `?version=latest|all`: whether superseded versions of entries are returned too
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct VersionQuery {
+    #[serde(default)]
+    pub version: DataVersions,
+}
 
-/// Search for data by DNO name or ID
+/// Search for data by DNO name or ID, as JSON or a CSV/XLSX export
 pub async fn search_by_dno(
     State(state): State<AppState>,
     Extension(user): Extension<AuthenticatedUser>,
-    Json(request): Json<SearchByDnoRequest>,
-) -> Result<Json<Value>, StatusCode> {
+    Extension(version): Extension<ApiVersion>,
+    ClientIp(client_ip): ClientIp,
+    format: ExportFormat,
+    Query(versions): Query<VersionQuery>,
+    request: Result<Json<SearchByDnoRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
     let start_time = std::time::Instant::now();
+    let Json(request) = request.map_err(request_rejection)?;
     
     // Determine search parameters
     let dno_id = request.dno_id;
     let dno_name = request.dno_name.as_deref();
     let year = request.year;
     let data_type = request.data_type.as_deref().unwrap_or("all");
+    let (limit, after) = page_request(request.limit, request.cursor.as_deref())?;
 
     // Get DNO if searching by name using cached repository
     let target_dno = if let Some(name) = dno_name {
         match state.dno_repo.get_dno_by_name(name).await {
             Ok(Some(dno)) => Some(dno),
             Ok(None) => {
-                return Ok(Json(json!({
-                    "total": 0,
-                    "results": [],
-                    "filters_applied": {
+                // Offer the closest names instead, e.g. "Netze BV" -> Netze BW
+                let suggestions = state.dno_repo.search_dnos_fuzzy(name, FUZZY_SUGGESTIONS).await.unwrap_or_else(|e| {
+                    warn!("Fuzzy DNO lookup for '{}' failed: {}", name, e);
+                    Vec::new()
+                });
+                let outcome = SearchOutcome {
+                    kind: SearchKind::Dno,
+                    total: 0,
+                    results: Vec::new(),
+                    filters_applied: json!({
                         "dno_name": name,
                         "year": year,
                         "data_type": data_type
+                    }),
+                    available: AvailableFilters {
+                        years: Vec::new(),
+                        dnos: suggestions
+                            .into_iter()
+                            .map(|dno| DnoInfo { id: dno.id, name: dno.name, slug: dno.slug, region: dno.region })
+                            .collect(),
+                        regions: Vec::new(),
+                        data_types: Vec::new(),
                     },
-                    "available_years": [],
-                    "available_dnos": []
-                })));
+                    data_types: state.data_types.names(),
+                    limit,
+                    offset: 0,
+                    keyset: true,
+                    next_cursor: None,
+                    diagnostics: Some(SearchDiagnostics::dno_not_found(name)),
+                };
+                let filename = export_filename(&[name, data_type, &year_part(year)]);
+                return respond(&state, version, format, &filename, outcome).await;
             }
-            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            Err(e) => return Err(e.into()),
         }
     } else if let Some(id) = dno_id {
         match state.dno_repo.get_dno_by_id(id).await {
             Ok(dno) => dno,
-            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            Err(e) => return Err(e.into()),
         }
     } else {
         None
@@ -49,174 +102,44 @@ pub async fn search_by_dno(
     let final_dno_id = target_dno.as_ref().map(|d| d.id).or(dno_id);
     let final_dno_name = target_dno.as_ref().map(|d| d.name.as_str()).or(dno_name);
 
-    // Search data based on type
-    let mut search_results = Vec::new();
-    let mut total_count = 0i64;
-
-    match data_type {
-        "netzentgelte" => {
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                final_dno_id,
-                final_dno_name,
-                year,
-                Some("verified"),
-                Some(50),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            total_count = state.search_repo.count_netzentgelte_data(
-                final_dno_id,
-                final_dno_name,
-                year,
-                Some("verified"),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None, // TODO: Add source info
-                    last_updated: entry.updated_at,
-                });
-            }
-        }
-        "hlzf" => {
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                final_dno_id,
-                final_dno_name,
-                year,
-                Some("verified"),
-                Some(50),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None, // TODO: Add source info
-                    last_updated: entry.updated_at,
-                });
-            }
-        }
-        _ => {
-            // Search both types using cached repository
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                final_dno_id,
-                final_dno_name,
-                year,
-                Some("verified"),
-                Some(25),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                final_dno_id,
-                final_dno_name,
-                year,
-                Some("verified"),
-                Some(25),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            // Add netzentgelte results
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-
-            // Add hlzf results  
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
+    // Search through the data type registry ("all" or an unknown type searches every type)
+    let query = DataTypeQuery {
+        dno_id: final_dno_id,
+        dno_name: final_dno_name,
+        year,
+        verification_status: Some("verified"),
+        limit,
+        offset: 0,
+        paging: Paging::Keyset { after },
+        versions: versions.version,
+    };
+    let selected = state.data_types.contains(data_type).then_some(data_type);
+    let found = state.data_types.search(&state.search_repo, selected, &query)
+        .await
+        ?;
+    let next_cursor = found.next_cursor.map(|cursor| cursor.to_string());
+    let search_results = found.results;
+    let total_count = found.total;
 
-            total_count = search_results.len() as i64;
-        }
-    }
+    // Explain empty results so clients can tell an unknown DNO from missing or unverified data
+    let diagnostics = if !search_results.is_empty() {
+        None
+    } else if let (None, Some(id)) = (&target_dno, dno_id) {
+        Some(SearchDiagnostics::dno_not_found(&id.to_string()))
+    } else {
+        // Coverage of every data type, so a missing type can be told from a missing year
+        let coverage = state.search_repo.get_data_coverage(
+            final_dno_id,
+            final_dno_name,
+            None,
+        ).await?;
+        SearchDiagnostics::from_coverage(&coverage, selected, year)
+    };
 
     // Get available filters using cached repository
     let available_filters = state.search_repo.get_available_years_and_dnos()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        ?;
 
     // Log query
     let response_time = start_time.elapsed().as_millis() as i32;
@@ -228,203 +151,76 @@ pub async fn search_by_dno(
         query: query_text,
         interpretation: Some(format!("DNO search for {}", data_type)),
         response_time_ms: Some(response_time),
-        source_ip: None, // TODO: Extract from request
+        source_ip: client_ip.map(|ip| ip.to_string()),
     };
     
-    let _ = core::database::log_query(&state.database, log).await;
+    let _ = dno_core::database::log_query(&state.database, log).await;
 
-    Ok(Json(json!({
-        "total": total_count,
-        "results": search_results,
-        "filters_applied": {
+    let outcome = SearchOutcome {
+        kind: SearchKind::Dno,
+        total: total_count,
+        results: search_results,
+        filters_applied: json!({
             "dno_name": final_dno_name,
             "dno_id": final_dno_id,
             "year": year,
-            "data_type": data_type
-        },
-        "available_years": available_filters.years,
-        "available_dnos": available_filters.dnos
-    })))
+            "data_type": data_type,
+            "version": query.versions
+        }),
+        available: available_filters,
+        data_types: state.data_types.names(),
+        limit: query.limit,
+        offset: query.offset,
+        keyset: true,
+        next_cursor,
+        diagnostics,
+    };
+    let dno_part = target_dno.as_ref().map(|d| d.slug.as_str()).or(final_dno_name).unwrap_or("search");
+    let filename = export_filename(&[dno_part, data_type, &year_part(year)]);
+    respond(&state, version, format, &filename, outcome).await
 }
 
-/// Search for data by year
+/// Search for data by year, as JSON or a CSV/XLSX export
 pub async fn search_by_year(
     State(state): State<AppState>,
     Extension(user): Extension<AuthenticatedUser>,
-    Json(request): Json<SearchByYearRequest>,
-) -> Result<Json<Value>, StatusCode> {
+    Extension(version): Extension<ApiVersion>,
+    ClientIp(client_ip): ClientIp,
+    format: ExportFormat,
+    Query(versions): Query<VersionQuery>,
+    request: Result<Json<SearchByYearRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
     let start_time = std::time::Instant::now();
+    let Json(request) = request.map_err(request_rejection)?;
     
     let year = request.year;
     let dno_name = request.dno_name.as_deref();
     let dno_id = request.dno_id;
     let data_type = request.data_type.as_deref().unwrap_or("all");
-
-    let mut search_results = Vec::new();
-    let mut total_count = 0i64;
-
-    match data_type {
-        "netzentgelte" => {
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                dno_id,
-                dno_name,
-                Some(year),
-                Some("verified"),
-                Some(50),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            total_count = state.search_repo.count_netzentgelte_data(
-                dno_id,
-                dno_name,
-                Some(year),
-                Some("verified"),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-        }
-        "hlzf" => {
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                dno_id,
-                dno_name,
-                Some(year),
-                Some("verified"),
-                Some(50),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-            total_count = search_results.len() as i64;
-        }
-        _ => {
-            // Search both
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                dno_id,
-                dno_name,
-                Some(year),
-                Some("verified"),
-                Some(25),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                dno_id,
-                dno_name,
-                Some(year),
-                Some("verified"),
-                Some(25),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            // Process results (similar to above)
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-            total_count = search_results.len() as i64;
-        }
-    }
+    let (limit, after) = page_request(request.limit, request.cursor.as_deref())?;
+
+    // Search through the data type registry ("all" or an unknown type searches every type)
+    let query = DataTypeQuery {
+        dno_id,
+        dno_name,
+        year: Some(year),
+        verification_status: Some("verified"),
+        limit,
+        offset: 0,
+        paging: Paging::Keyset { after },
+        versions: versions.version,
+    };
+    let selected = state.data_types.contains(data_type).then_some(data_type);
+    let found = state.data_types.search(&state.search_repo, selected, &query)
+        .await
+        ?;
+    let next_cursor = found.next_cursor.map(|cursor| cursor.to_string());
+    let search_results = found.results;
+    let total_count = found.total;
 
     let available_filters = state.search_repo.get_available_years_and_dnos()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        ?;
 
     // Log query
     let response_time = start_time.elapsed().as_millis() as i32;
@@ -433,30 +229,42 @@ pub async fn search_by_year(
         query: format!("Search by year: {} (type: {})", year, data_type),
         interpretation: Some(format!("Year-based search for {}", data_type)),
         response_time_ms: Some(response_time),
-        source_ip: None,
+        source_ip: client_ip.map(|ip| ip.to_string()),
     };
-    let _ = core::database::log_query(&state.database, log).await;
+    let _ = dno_core::database::log_query(&state.database, log).await;
 
-    Ok(Json(json!({
-        "total": total_count,
-        "results": search_results,
-        "filters_applied": {
+    let outcome = SearchOutcome {
+        kind: SearchKind::Year,
+        total: total_count,
+        results: search_results,
+        filters_applied: json!({
             "year": year,
             "dno_name": dno_name,
             "dno_id": dno_id,
-            "data_type": data_type
-        },
-        "available_years": available_filters.years,
-        "available_dnos": available_filters.dnos
-    })))
+            "data_type": data_type,
+            "version": query.versions
+        }),
+        available: available_filters,
+        data_types: state.data_types.names(),
+        limit: query.limit,
+        offset: query.offset,
+        keyset: true,
+        next_cursor,
+        diagnostics: None,
+    };
+    let filename = export_filename(&[dno_name.unwrap_or("all-dnos"), data_type, &year.to_string()]);
+    respond(&state, version, format, &filename, outcome).await
 }
 
 /// Search for data by data type (netzentgelte or hlzf)
 pub async fn search_by_data_type(
     State(state): State<AppState>,
     Extension(user): Extension<AuthenticatedUser>,
+    Extension(version): Extension<ApiVersion>,
+    ClientIp(client_ip): ClientIp,
+    Query(versions): Query<VersionQuery>,
     Json(request): Json<SearchByDataTypeRequest>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let start_time = std::time::Instant::now();
     
     let data_type = &request.data_type;
@@ -464,99 +272,30 @@ pub async fn search_by_data_type(
     let dno_id = request.dno_id;
     let year = request.year;
 
-    let mut search_results = Vec::new();
-    let total_count;
-
-    match data_type.as_str() {
-        "netzentgelte" => {
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-                Some(50),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            total_count = state.search_repo.count_netzentgelte_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-        }
-        "hlzf" => {
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-                Some(50),
-                Some(0),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-            total_count = search_results.len() as i64;
-        }
-        _ => {
-            return Err(StatusCode::BAD_REQUEST);
-        }
+    if !state.data_types.contains(data_type) {
+        return Err(ApiError::bad_request(format!("Unknown data type: {}", data_type)));
     }
 
+    // Search through the data type registry
+    let query = DataTypeQuery {
+        dno_id,
+        dno_name,
+        year,
+        verification_status: Some("verified"),
+        limit: 50,
+        offset: 0,
+        paging: Paging::Offset,
+        versions: versions.version,
+    };
+    let found = state.data_types.search(&state.search_repo, Some(data_type.as_str()), &query)
+        .await
+        ?;
+    let search_results = found.results;
+    let total_count = found.total;
+
     let available_filters = state.search_repo.get_available_years_and_dnos()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        ?;
 
     // Log query
     let response_time = start_time.elapsed().as_millis() as i32;
@@ -565,30 +304,40 @@ pub async fn search_by_data_type(
         query: format!("Search by data type: {}", data_type),
         interpretation: Some(format!("Data type search for {}", data_type)),
         response_time_ms: Some(response_time),
-        source_ip: None,
+        source_ip: client_ip.map(|ip| ip.to_string()),
     };
-    let _ = core::database::log_query(&state.database, log).await;
+    let _ = dno_core::database::log_query(&state.database, log).await;
 
-    Ok(Json(json!({
-        "total": total_count,
-        "results": search_results,
-        "filters_applied": {
+    let outcome = SearchOutcome {
+        kind: SearchKind::DataType,
+        total: total_count,
+        results: search_results,
+        filters_applied: json!({
             "data_type": data_type,
             "dno_name": dno_name,
             "dno_id": dno_id,
-            "year": year
-        },
-        "available_years": available_filters.years,
-        "available_dnos": available_filters.dnos
-    })))
+            "year": year,
+            "version": query.versions
+        }),
+        available: available_filters,
+        data_types: state.data_types.names(),
+        limit: query.limit,
+        offset: query.offset,
+        keyset: false,
+        next_cursor: None,
+        diagnostics: None,
+    };
+    Ok(render_search(&state, version, outcome).await?)
 }
 
 /// Search with multiple filters using query parameters
 pub async fn search_with_filters(
     State(state): State<AppState>,
     Extension(user): Extension<AuthenticatedUser>,
+    Extension(version): Extension<ApiVersion>,
+    ClientIp(client_ip): ClientIp,
     Query(filters): Query<SearchFilters>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let start_time = std::time::Instant::now();
     
     let dno_name = filters.dno_name.as_deref();
@@ -598,174 +347,27 @@ pub async fn search_with_filters(
     let limit = filters.limit.map(|l| l as i64).unwrap_or(50);
     let offset = filters.offset.map(|o| o as i64).unwrap_or(0);
 
-    let mut search_results = Vec::new();
-    let mut total_count = 0i64;
-
-    match data_type {
-        "netzentgelte" => {
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-                Some(limit),
-                Some(offset),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            total_count = state.search_repo.count_netzentgelte_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-        }
-        "hlzf" => {
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-                Some(limit),
-                Some(offset),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-            total_count = search_results.len() as i64;
-        }
-        _ => {
-            // Mixed search - limit per type
-            let half_limit = limit / 2;
-            
-            let netzentgelte_data = state.search_repo.search_netzentgelte_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-                Some(half_limit),
-                Some(offset / 2),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let hlzf_data = state.search_repo.search_hlzf_data(
-                dno_id,
-                dno_name,
-                year,
-                Some("verified"),
-                Some(half_limit),
-                Some(offset / 2),
-            ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            // Add both result types
-            for entry in netzentgelte_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "netzentgelte".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "netzentgelte": {
-                            "voltage_level": entry.voltage_level,
-                            "leistung": entry.leistung,
-                            "arbeit": entry.arbeit,
-                            "leistung_unter_2500h": entry.leistung_unter_2500h,
-                            "arbeit_unter_2500h": entry.arbeit_unter_2500h
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-
-            for entry in hlzf_data {
-                search_results.push(SearchResult {
-                    id: entry.id,
-                    dno: DnoInfo {
-                        id: entry.dno_id_full,
-                        name: entry.dno_name,
-                        slug: entry.dno_slug,
-                        region: entry.dno_region,
-                    },
-                    year: entry.year,
-                    data_type: "hlzf".to_string(),
-                    status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
-                    data: json!({
-                        "hlzf": {
-                            "season": entry.season,
-                            "voltage_level": entry.voltage_level,
-                            "ht": entry.ht,
-                            "nt": entry.nt,
-                            "start_date": entry.start_date,
-                            "end_date": entry.end_date
-                        }
-                    }),
-                    source: None,
-                    last_updated: entry.updated_at,
-                });
-            }
-
-            total_count = search_results.len() as i64;
-        }
-    }
+    // Search through the data type registry ("all" or an unknown type searches every type)
+    let query = DataTypeQuery {
+        dno_id,
+        dno_name,
+        year,
+        verification_status: Some("verified"),
+        limit,
+        offset,
+        paging: Paging::Offset,
+        versions: filters.version.unwrap_or_default(),
+    };
+    let selected = state.data_types.contains(data_type).then_some(data_type);
+    let found = state.data_types.search(&state.search_repo, selected, &query)
+        .await
+        ?;
+    let search_results = found.results;
+    let total_count = found.total;
 
     let available_filters = state.search_repo.get_available_years_and_dnos()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        ?;
 
     // Log query
     let response_time = start_time.elapsed().as_millis() as i32;
@@ -776,32 +378,259 @@ pub async fn search_with_filters(
         query: query_text,
         interpretation: Some(format!("Filtered search with {} results", search_results.len())),
         response_time_ms: Some(response_time),
-        source_ip: None,
+        source_ip: client_ip.map(|ip| ip.to_string()),
     };
-    let _ = core::database::log_query(&state.database, log).await;
+    let _ = dno_core::database::log_query(&state.database, log).await;
 
-    Ok(Json(json!({
-        "total": total_count,
-        "results": search_results,
-        "pagination": {
-            "limit": limit,
-            "offset": offset,
-            "total": total_count,
-            "has_more": (offset + limit) < total_count
-        },
-        "filters_applied": {
+    let outcome = SearchOutcome {
+        kind: SearchKind::Filters,
+        total: total_count,
+        results: search_results,
+        filters_applied: json!({
             "dno_name": dno_name,
             "dno_id": dno_id,
             "year": year,
             "data_type": data_type,
             "region": filters.region,
             "limit": limit,
-            "offset": offset
-        },
-        "available_filters": {
-            "years": available_filters.years,
-            "data_types": ["netzentgelte", "hlzf"],
-            "regions": available_filters.regions
-        }
+            "offset": offset,
+            "version": query.versions
+        }),
+        available: available_filters,
+        data_types: state.data_types.names(),
+        limit,
+        offset,
+        keyset: false,
+        next_cursor: None,
+        diagnostics: None,
+    };
+    Ok(render_search(&state, version, outcome).await?)
+}
+
+/// Search several DNOs in one request. Queries run concurrently but results
+/// keep the order of `queries`; a query that fails reports its own error
+/// without failing the others.
+pub async fn search_batch(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    ClientIp(client_ip): ClientIp,
+    request: Result<Json<BatchSearchRequest>, JsonRejection>,
+) -> Result<Json<Value>, ApiError> {
+    let start_time = std::time::Instant::now();
+    let Json(request) = request.map_err(request_rejection)?;
+    if request.queries.is_empty() || request.queries.len() > MAX_BATCH_QUERIES {
+        return Err(ApiError::bad_request(format!("A batch takes 1 to {} queries", MAX_BATCH_QUERIES)));
+    }
+
+    // Each search owns its query and a handle to the state, so the futures borrow nothing from the handler
+    let outcomes = run_batch(request.queries.iter().cloned(), BATCH_CONCURRENCY, |query| {
+        let state = state.clone();
+        async move { batch_query(&state, &query).await }
+    })
+    .await;
+    let results: Vec<Value> = request
+        .queries
+        .iter()
+        .zip(outcomes)
+        .enumerate()
+        .map(|(index, (query, outcome))| batch_item(index, query, outcome))
+        .collect();
+    let failed = results.iter().filter(|item| item.get("error").is_some()).count();
+
+    // Log query
+    let response_time = start_time.elapsed().as_millis() as i32;
+    let log = CreateQueryLog {
+        user_id: Some(user.id),
+        query: format!("Batch search: {} queries", request.queries.len()),
+        interpretation: Some(format!("Batch search with {} failed queries", failed)),
+        response_time_ms: Some(response_time),
+        source_ip: client_ip.map(|ip| ip.to_string()),
+    };
+    let _ = dno_core::database::log_query(&state.database, log).await;
+
+    Ok(Json(json!({
+        "total": results.len(),
+        "failed": failed,
+        "results": results
     })))
-}
\ No newline at end of file
+}
+
+/// Run `search` over `queries`, at most `concurrency` at a time. Outcomes are
+/// in the order of `queries`, not in the order the searches finish.
+async fn run_batch<Q, T, F, Fut>(queries: impl IntoIterator<Item = Q>, concurrency: usize, search: F) -> Vec<Result<T, AppError>>
+where
+    F: FnMut(Q) -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    stream::iter(queries).map(search).buffered(concurrency.max(1)).collect().await
+}
+
+/// Verified entries of one DNO, through the same cached repositories as
+/// `/search/dno`. An unknown DNO is a 404 and an unknown data type a 400.
+async fn batch_query(state: &AppState, query: &BatchSearchQuery) -> Result<DataTypeSearch, AppError> {
+    let data_type = query.data_type.as_deref().unwrap_or("all");
+    let selected = (data_type != "all").then_some(data_type);
+    if selected.is_some_and(|name| !state.data_types.contains(name)) {
+        return Err(AppError::BadRequest(format!("Unknown data type: {}", data_type)));
+    }
+
+    let dno = state
+        .dno_repo
+        .get_dno_by_name(&query.dno_name)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("DNO '{}'", query.dno_name)))?;
+    let search = DataTypeQuery {
+        dno_id: Some(dno.id),
+        dno_name: Some(&dno.name),
+        year: query.year,
+        verification_status: Some("verified"),
+        limit: BATCH_RESULT_LIMIT,
+        offset: 0,
+        paging: Paging::Offset,
+        versions: DataVersions::Latest,
+    };
+    state.data_types.search(&state.search_repo, selected, &search).await
+}
+
+/// Response entry of one batch query: its results, or the status and error it
+/// failed with, coded and sanitized like an `ApiError` response
+fn batch_item(index: usize, query: &BatchSearchQuery, outcome: Result<DataTypeSearch, AppError>) -> Value {
+    let mut item = json!({
+        "index": index,
+        "dno_name": query.dno_name,
+        "year": query.year,
+        "data_type": query.data_type.as_deref().unwrap_or("all")
+    });
+    match outcome {
+        Ok(found) => {
+            item["status"] = json!(StatusCode::OK.as_u16());
+            item["total"] = json!(found.total);
+            item["results"] = json!(found.results);
+        }
+        Err(error) => {
+            let error = ApiError::from(error);
+            item["status"] = json!(error.status.as_u16());
+            item["error"] = json!({ "code": error.code, "message": error.message });
+        }
+    }
+    item
+}
+
+/// A search outcome as the versioned JSON response or, when asked for, a
+/// spreadsheet download named `filename`
+async fn respond(
+    state: &AppState,
+    version: ApiVersion,
+    format: ExportFormat,
+    filename: &str,
+    outcome: SearchOutcome,
+) -> Result<Response, ApiError> {
+    match format {
+        ExportFormat::Json => Ok(render_search(state, version, outcome).await?.into_response()),
+        export => Ok(export_response(export, filename, &outcome.results, outcome.next_cursor.as_deref())?),
+    }
+}
+
+fn year_part(year: Option<i32>) -> String {
+    year.map(|y| y.to_string()).unwrap_or_else(|| "all-years".to_string())
+}
+
+/// Page size and start of a cursor-paged search; a bad limit or cursor is a 400
+fn page_request(limit: Option<i64>, cursor: Option<&str>) -> Result<(i64, Option<SearchCursor>), AppError> {
+    let limit = validate_limit(limit)?;
+    let after = cursor.map(str::parse::<SearchCursor>).transpose()?;
+    Ok((limit, after))
+}
+/// A search request body that isn't valid JSON or doesn't fit the request,
+/// such as a malformed `dno_id`, is a 400; a missing JSON content type keeps its 415
+fn request_rejection(rejection: JsonRejection) -> StatusCode {
+    match rejection {
+        JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_) => StatusCode::BAD_REQUEST,
+        other => other.status(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::FromRequest;
+    use axum::http::{header, Request};
+
+    async fn parse(body: &str) -> Result<SearchByDnoRequest, StatusCode> {
+        let request = Request::post("/api/v1/search/dno")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let Json(parsed) = Json::<SearchByDnoRequest>::from_request(request, &()).await.map_err(request_rejection)?;
+        Ok(parsed)
+    }
+
+    #[tokio::test]
+    async fn test_malformed_dno_id_is_a_bad_request() {
+        assert_eq!(parse(r#"{"dno_id": "not-a-uuid"}"#).await.unwrap_err(), StatusCode::BAD_REQUEST);
+        assert_eq!(parse(r#"{"dno_id": "#).await.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        let id = Uuid::new_v4();
+        assert_eq!(parse(&format!(r#"{{"dno_id": "{}"}}"#, id)).await.unwrap().dno_id, Some(id));
+    }
+
+    #[test]
+    fn test_bad_limit_or_cursor_is_a_bad_request() {
+        let error = ApiError::from(page_request(Some(0), None).unwrap_err());
+        assert_eq!((error.status, error.code.as_str()), (StatusCode::BAD_REQUEST, "BAD_REQUEST"));
+        assert!(error.message.contains("limit must be between 1"), "{}", error.message);
+        assert_eq!(ApiError::from(page_request(None, Some("not-a-cursor")).unwrap_err()).status, StatusCode::BAD_REQUEST);
+    }
+
+    fn lookup(dno_name: &str) -> BatchSearchQuery {
+        BatchSearchQuery { dno_name: dno_name.to_string(), year: Some(2024), data_type: None }
+    }
+
+    #[tokio::test]
+    async fn test_batch_keeps_input_order_when_later_queries_finish_first() {
+        let delays: Vec<u64> = vec![40, 30, 20, 10, 0];
+        let outcomes = run_batch(delays.iter().copied(), 3, |delay| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            Ok::<_, AppError>(delay)
+        })
+        .await;
+
+        let finished: Vec<u64> = outcomes.into_iter().map(Result::unwrap).collect();
+        assert_eq!(finished, delays);
+    }
+
+    #[tokio::test]
+    async fn test_failing_batch_query_does_not_fail_the_others() {
+        let queries = [lookup("Netze BW"), lookup("Netze BV"), lookup("Westnetz"), lookup("E.DIS")];
+        let outcomes = run_batch(queries.iter().cloned(), 2, |query| async move {
+            match query.dno_name.as_str() {
+                "Netze BV" => Err(AppError::NotFound(format!("DNO '{}'", query.dno_name))),
+                "Westnetz" => Err(AppError::Database(sqlx::Error::PoolTimedOut)),
+                "E.DIS" => Err(AppError::Database(sqlx::Error::ColumnNotFound("arbeitspreis".to_string()))),
+                _ => Ok(DataTypeSearch { results: Vec::new(), total: 0, next_cursor: None }),
+            }
+        })
+        .await;
+
+        let items: Vec<Value> = queries
+            .iter()
+            .zip(outcomes)
+            .enumerate()
+            .map(|(index, (query, outcome))| batch_item(index, query, outcome))
+            .collect();
+        assert_eq!(items[0]["status"], 200);
+        assert_eq!(items[0]["total"], 0);
+        assert!(items[0].get("error").is_none());
+        assert_eq!((items[1]["index"].as_u64(), items[1]["dno_name"].as_str()), (Some(1), Some("Netze BV")));
+        assert_eq!(items[1]["status"], 404);
+        assert_eq!(items[1]["error"]["code"], "NOT_FOUND");
+        assert_eq!(items[2]["status"], 503);
+        assert_eq!(items[2]["error"]["code"], "DATABASE_UNAVAILABLE");
+        assert!(items[2].get("results").is_none());
+        // Internal errors are not spelled out to the client
+        assert_eq!(items[3]["status"], 500);
+        assert_eq!(items[3]["error"]["code"], "DATABASE_ERROR");
+        assert_eq!(items[3]["error"]["message"], "Internal server error");
+    }
+}
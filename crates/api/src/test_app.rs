@@ -0,0 +1,55 @@
+use crate::{AppConfig, AppState, RedisCache};
+use chrono::{Duration, Utc};
+use dno_core::models::{CreateSession, CreateUser, UserRole};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A real `AppState` for router tests, over the `init.sql` schema in `pool`.
+/// Like the deployment it needs Redis, at `APP_REDIS_URL`; each state gets
+/// its own key prefix, so nothing cached for another test database is read.
+pub async fn state(pool: PgPool) -> AppState {
+    dno_core::test_schema::create(&pool).await;
+    let redis = dno_core::RedisCacheConfig {
+        key_prefix: format!("test-{}", Uuid::new_v4()),
+        ..dno_core::RedisCacheConfig::from_env().expect("APP_REDIS_URL is set for router tests")
+    };
+    let cache = RedisCache::new(redis).await.expect("Redis at APP_REDIS_URL is reachable");
+
+    let scratch = std::env::temp_dir().join(format!("api-test-{}", Uuid::new_v4()));
+    let config = AppConfig {
+        storage_path: scratch.join("storage").display().to_string(),
+        temp_path: scratch.join("temp").display().to_string(),
+        ..AppConfig::default()
+    };
+    AppState::new(pool, config, "test-secret".to_string(), Arc::new(cache))
+}
+
+/// Bearer token of a new active user with `role`, signed in the way `/auth/login` does
+pub async fn sign_in(state: &AppState, role: UserRole) -> String {
+    let user = state
+        .user_repo
+        .create_user(CreateUser {
+            email: format!("{}@example.de", Uuid::new_v4()),
+            password_hash: String::new(),
+            name: "Erika Mustermann".to_string(),
+            role: Some(role),
+        })
+        .await
+        .unwrap();
+    let tokens = state.jwt.issue_token_pair(&user, Uuid::new_v4()).await.unwrap();
+    state
+        .user_repo
+        .create_session(CreateSession {
+            user_id: user.id,
+            token_hash: format!("{:x}", md5::compute(&tokens.access_token)),
+            refresh_token_hash: Some(format!("{:x}", md5::compute(&tokens.refresh_token))),
+            expires_at: Utc::now() + Duration::seconds(state.config.jwt_access_token_expiry),
+            refresh_expires_at: Some(Utc::now() + Duration::seconds(state.config.jwt_refresh_token_expiry)),
+            ip_address: None,
+            user_agent: None,
+        })
+        .await
+        .unwrap();
+    format!("Bearer {}", tokens.access_token)
+}
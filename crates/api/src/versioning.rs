@@ -0,0 +1,479 @@
+use axum::{http::StatusCode, response::Json};
+use chrono::{DateTime, Utc};
+use dno_core::models::{AvailableFilters, CrawlType, DataSource, DnoInfo, ExtractedField, Freshness, SearchResult};
+use dno_core::SearchDiagnostics;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+use crate::AppState;
+
+/// API version a request was routed through. Handlers share their business
+/// logic and only differ in how the result is mapped to a response DTO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    /// Original response shapes, kept stable for existing clients
+    V1,
+    /// Typed responses with verification status and per-field provenance
+    V2,
+}
+
+impl ApiVersion {
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/api/v1",
+            ApiVersion::V2 => "/api/v2",
+        }
+    }
+}
+
+/// Which search endpoint produced an outcome; v1 responses differ slightly per endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    Dno,
+    Year,
+    DataType,
+    Filters,
+}
+
+/// Version-independent result of a search, produced by the shared handler logic
+#[derive(Debug, Clone)]
+pub struct SearchOutcome {
+    pub kind: SearchKind,
+    pub total: i64,
+    pub results: Vec<SearchResult>,
+    pub filters_applied: Value,
+    pub available: AvailableFilters,
+    pub data_types: Vec<&'static str>,
+    pub limit: i64,
+    pub offset: i64,
+    /// Paged by cursor: `has_more` follows `next_cursor` instead of offset and total
+    pub keyset: bool,
+    pub next_cursor: Option<String>,
+    pub diagnostics: Option<SearchDiagnostics>,
+}
+
+impl SearchOutcome {
+    fn has_more(&self) -> bool {
+        if self.keyset {
+            self.next_cursor.is_some()
+        } else {
+            (self.offset + self.limit) < self.total
+        }
+    }
+
+    /// Set the results' `age_days` as of `now` and tell whether the newest
+    /// is older than `stale_after_days`
+    pub fn assess_freshness(&mut self, stale_after_days: i64, now: DateTime<Utc>) -> Freshness {
+        Freshness::assess(&mut self.results, stale_after_days, now)
+    }
+
+    /// v1 shapes, exactly as the search endpoints returned them before v2
+    /// existed, plus `next_cursor` when there is another page
+    pub fn into_v1(self) -> Value {
+        let has_more = self.has_more();
+        let next_cursor = self.next_cursor.clone();
+        let mut v1 = match self.kind {
+            SearchKind::Dno => json!({
+                "total": self.total,
+                "results": self.results,
+                "filters_applied": self.filters_applied,
+                "available_years": self.available.years,
+                "available_dnos": self.available.dnos,
+                "diagnostics": self.diagnostics
+            }),
+            SearchKind::Year | SearchKind::DataType => json!({
+                "total": self.total,
+                "results": self.results,
+                "filters_applied": self.filters_applied,
+                "available_years": self.available.years,
+                "available_dnos": self.available.dnos
+            }),
+            SearchKind::Filters => json!({
+                "total": self.total,
+                "results": self.results,
+                "pagination": {
+                    "limit": self.limit,
+                    "offset": self.offset,
+                    "total": self.total,
+                    "has_more": has_more
+                },
+                "filters_applied": self.filters_applied,
+                "available_filters": {
+                    "years": self.available.years,
+                    "data_types": self.data_types,
+                    "regions": self.available.regions
+                }
+            }),
+        };
+        if let (Some(cursor), Some(object)) = (next_cursor, v1.as_object_mut()) {
+            object.insert("next_cursor".to_string(), Value::String(cursor));
+        }
+        v1
+    }
+
+    /// v2 shape: one layout for every search endpoint, with the source and
+    /// field provenance of each result where `sources` has it
+    pub fn into_v2(self, sources: &[DataSource]) -> SearchResponseV2 {
+        let sources: HashMap<(Uuid, i32, &str), &DataSource> = sources
+            .iter()
+            .map(|source| ((source.dno_id, source.year, source.data_type.as_str()), source))
+            .collect();
+
+        let has_more = self.has_more();
+        let results = self
+            .results
+            .into_iter()
+            .map(|result| {
+                let source = sources
+                    .get(&(result.dno.id, result.year, result.data_type.as_str()))
+                    .map(|source| SourceV2::from(*source));
+                SearchResultV2::new(result, source)
+            })
+            .collect();
+
+        SearchResponseV2 {
+            freshness: Freshness::default(),
+            results,
+            pagination: PaginationV2 {
+                limit: self.limit,
+                offset: self.offset,
+                total: self.total,
+                has_more,
+                next_cursor: self.next_cursor,
+            },
+            filters_applied: self.filters_applied,
+            available_filters: AvailableFiltersV2 {
+                years: self.available.years,
+                dnos: self.available.dnos,
+                data_types: self.data_types.into_iter().map(str::to_string).collect(),
+                regions: self.available.regions,
+            },
+            diagnostics: self.diagnostics,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResponseV2 {
+    /// `newest_crawled_at`, `stale` and `suggested_recrawl`
+    #[serde(flatten)]
+    pub freshness: Freshness,
+    pub results: Vec<SearchResultV2>,
+    pub pagination: PaginationV2,
+    pub filters_applied: Value,
+    pub available_filters: AvailableFiltersV2,
+    pub diagnostics: Option<SearchDiagnostics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResultV2 {
+    pub id: Uuid,
+    pub dno: DnoInfo,
+    pub year: i32,
+    pub data_type: String,
+    pub verification_status: String,
+    /// The data type's fields, without the v1 `{ "<data_type>": { .. } }` wrapper
+    pub values: Value,
+    pub source: Option<SourceV2>,
+    pub last_updated: DateTime<Utc>,
+    pub first_seen_at: Option<DateTime<Utc>>,
+    pub last_verified_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<Uuid>,
+    pub crawled_at: Option<DateTime<Utc>>,
+    pub age_days: Option<i64>,
+}
+
+impl SearchResultV2 {
+    fn new(result: SearchResult, source: Option<SourceV2>) -> Self {
+        let values = match result.data {
+            Value::Object(mut data) if data.len() == 1 && data.contains_key(&result.data_type) => {
+                data.remove(&result.data_type).unwrap_or_default()
+            }
+            data => data,
+        };
+        Self {
+            id: result.id,
+            dno: result.dno,
+            year: result.year,
+            data_type: result.data_type,
+            verification_status: result.status,
+            values,
+            source,
+            last_updated: result.last_updated,
+            first_seen_at: result.first_seen_at,
+            last_verified_at: result.last_verified_at,
+            version: result.version,
+            superseded_by: result.superseded_by,
+            crawled_at: result.crawled_at,
+            age_days: result.age_days,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceV2 {
+    pub id: Uuid,
+    pub source_type: CrawlType,
+    pub source_url: Option<String>,
+    pub page: Option<i32>,
+    pub extraction_method: Option<String>,
+    pub confidence: Option<f64>,
+    pub extracted_at: DateTime<Utc>,
+    /// Where each extracted value came from, keyed by field path
+    pub provenance: BTreeMap<String, ExtractedField>,
+}
+
+impl From<&DataSource> for SourceV2 {
+    fn from(source: &DataSource) -> Self {
+        Self {
+            id: source.id,
+            source_type: source.source_type.clone(),
+            source_url: source.source_url.clone(),
+            page: source.page_number,
+            extraction_method: source.extraction_method.clone(),
+            confidence: source.confidence.and_then(|c| c.to_f64()),
+            extracted_at: source.extracted_at,
+            provenance: source
+                .extracted_data
+                .clone()
+                .and_then(|data| serde_json::from_value(data).ok())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginationV2 {
+    pub limit: i64,
+    pub offset: i64,
+    pub total: i64,
+    pub has_more: bool,
+    /// Pass as `cursor` to get the next page of a cursor-paged search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailableFiltersV2 {
+    pub years: Vec<i32>,
+    pub dnos: Vec<DnoInfo>,
+    pub data_types: Vec<String>,
+    pub regions: Vec<String>,
+}
+
+/// Map a search outcome to the response DTO of `version`. Only v2 loads the
+/// data sources for provenance. Both carry the freshness flags at the top level.
+pub async fn render_search(
+    state: &AppState,
+    version: ApiVersion,
+    mut outcome: SearchOutcome,
+) -> Result<Json<Value>, StatusCode> {
+    let freshness = outcome.assess_freshness(state.config.search_stale_after_days, Utc::now());
+    match version {
+        ApiVersion::V1 => Ok(Json(with_freshness(outcome.into_v1(), &freshness))),
+        ApiVersion::V2 => {
+            let keys: Vec<(Uuid, i32, String)> = outcome
+                .results
+                .iter()
+                .map(|result| (result.dno.id, result.year, result.data_type.clone()))
+                .collect();
+            let sources = dno_core::database::get_data_sources_for_results(&state.database, &keys)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let response = SearchResponseV2 { freshness, ..outcome.into_v2(&sources) };
+            serde_json::to_value(response)
+                .map(Json)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// A v1 response with the freshness flags added next to its other top-level fields
+fn with_freshness(mut v1: Value, freshness: &Freshness) -> Value {
+    if let (Ok(Value::Object(flags)), Some(object)) = (serde_json::to_value(freshness), v1.as_object_mut()) {
+        object.extend(flags);
+    }
+    v1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dno_core::models::{DataType, FieldProvenance};
+
+    fn outcome(kind: SearchKind) -> (SearchOutcome, DataSource) {
+        let dno = DnoInfo {
+            id: Uuid::from_u128(1),
+            name: "Netze BW".to_string(),
+            slug: "netze-bw".to_string(),
+            region: Some("Baden-Württemberg".to_string()),
+        };
+        let result = SearchResult {
+            id: Uuid::from_u128(10),
+            dno: dno.clone(),
+            year: 2024,
+            data_type: "netzentgelte".to_string(),
+            status: "verified".to_string(),
+            data: json!({ "netzentgelte": { "voltage_level": "ms", "arbeit": "1.26" } }),
+            source: None,
+            last_updated: Utc::now(),
+            first_seen_at: None,
+            last_verified_at: None,
+            version: None,
+            superseded_by: None,
+            crawled_at: Some(Utc::now() - chrono::Duration::days(30)),
+            age_days: None,
+        };
+
+        let mut provenance = BTreeMap::new();
+        provenance.insert(
+            "tables[0].rows[0].Arbeitspreis".to_string(),
+            ExtractedField {
+                value: json!("1,26 ct/kWh"),
+                provenance: FieldProvenance {
+                    source_url: "https://netze.example/netzentgelte".to_string(),
+                    page: None,
+                    row: Some(1),
+                    column: Some(2),
+                    selector: None,
+                    archive: None,
+                    method: "html_table".to_string(),
+                    confidence: 0.9,
+                },
+            },
+        );
+        let source = DataSource {
+            id: Uuid::from_u128(20),
+            dno_id: dno.id,
+            year: 2024,
+            data_type: DataType::Netzentgelte,
+            source_type: CrawlType::Table,
+            source_url: Some("https://netze.example/netzentgelte".to_string()),
+            file_path: None,
+            file_hash: None,
+            extracted_at: Utc::now(),
+            confidence: Some(rust_decimal::Decimal::new(90, 2)),
+            page_number: None,
+            extraction_method: Some("html_table".to_string()),
+            extraction_region: None,
+            ocr_text: None,
+            extraction_log: None,
+            extracted_data: Some(serde_json::to_value(&provenance).unwrap()),
+            created_at: Utc::now(),
+        };
+
+        let outcome = SearchOutcome {
+            kind,
+            total: 1,
+            results: vec![result],
+            filters_applied: json!({ "dno_name": "Netze BW", "year": 2024, "data_type": "netzentgelte" }),
+            available: AvailableFilters {
+                years: vec![2023, 2024],
+                dnos: vec![dno],
+                regions: vec!["Baden-Württemberg".to_string()],
+                data_types: vec![],
+            },
+            data_types: vec!["netzentgelte", "hlzf"],
+            limit: 50,
+            offset: 0,
+            keyset: kind != SearchKind::Filters,
+            next_cursor: None,
+            diagnostics: None,
+        };
+        (outcome, source)
+    }
+
+    #[test]
+    fn test_same_search_maps_to_v1_and_v2_shapes() {
+        let (outcome, source) = outcome(SearchKind::Dno);
+
+        let v1 = outcome.clone().into_v1();
+        let keys: Vec<&str> = v1.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec!["available_dnos", "available_years", "diagnostics", "filters_applied", "results", "total"]
+        );
+        assert_eq!(v1["results"][0]["status"], "verified");
+        assert_eq!(v1["results"][0]["data"]["netzentgelte"]["arbeit"], "1.26");
+        assert!(v1["results"][0]["source"].is_null());
+
+        let v2 = serde_json::to_value(outcome.into_v2(&[source])).unwrap();
+        assert!(v2.get("total").is_none());
+        assert_eq!(v2["pagination"]["total"], 1);
+        assert_eq!(v2["available_filters"]["years"], json!([2023, 2024]));
+        let result = &v2["results"][0];
+        assert_eq!(result["id"], v1["results"][0]["id"]);
+        assert_eq!(result["verification_status"], "verified");
+        assert_eq!(result["values"]["arbeit"], "1.26");
+        assert_eq!(result["source"]["confidence"], 0.9);
+        assert_eq!(
+            result["source"]["provenance"]["tables[0].rows[0].Arbeitspreis"]["provenance"]["row"],
+            1
+        );
+    }
+
+    #[test]
+    fn test_v1_filter_search_keeps_pagination_block() {
+        let (outcome, _) = outcome(SearchKind::Filters);
+        let v1 = outcome.into_v1();
+        assert_eq!(v1["pagination"]["has_more"], false);
+        assert_eq!(v1["available_filters"]["data_types"], json!(["netzentgelte", "hlzf"]));
+        assert!(v1.get("available_dnos").is_none());
+    }
+
+    #[test]
+    fn test_next_cursor_is_returned_while_rows_remain() {
+        let (outcome, source) = outcome(SearchKind::Year);
+        let next = SearchOutcome { next_cursor: Some("2024:0b7f6c3e-8f1a-4a55-9a41-5b3c0f7f2d10".to_string()), ..outcome.clone() };
+
+        assert_eq!(next.clone().into_v1()["next_cursor"], "2024:0b7f6c3e-8f1a-4a55-9a41-5b3c0f7f2d10");
+        let v2 = serde_json::to_value(next.into_v2(std::slice::from_ref(&source))).unwrap();
+        assert_eq!(v2["pagination"]["has_more"], true);
+        assert_eq!(v2["pagination"]["next_cursor"], "2024:0b7f6c3e-8f1a-4a55-9a41-5b3c0f7f2d10");
+
+        // Last page: total counts every page, but nothing follows
+        let last = SearchOutcome { total: 250, ..outcome };
+        assert!(last.clone().into_v1().get("next_cursor").is_none());
+        let v2 = serde_json::to_value(last.into_v2(&[source])).unwrap();
+        assert_eq!(v2["pagination"]["has_more"], false);
+        assert!(v2["pagination"].get("next_cursor").is_none());
+    }
+
+    #[test]
+    fn test_stale_results_suggest_a_recrawl() {
+        let now = Utc::now();
+        let days_ago = |days: i64| Some(now - chrono::Duration::days(days));
+
+        let (mut fresh, source) = outcome(SearchKind::Dno);
+        fresh.results[0].crawled_at = days_ago(30);
+        let mut older = fresh.results[0].clone();
+        older.crawled_at = days_ago(500);
+        fresh.results.push(older);
+        let freshness = fresh.assess_freshness(400, now);
+        assert_eq!((freshness.stale, freshness.suggested_recrawl), (false, false));
+        let v1 = with_freshness(fresh.into_v1(), &freshness);
+        assert_eq!((v1["stale"].clone(), v1["suggested_recrawl"].clone()), (json!(false), json!(false)));
+        assert_eq!(v1["results"][0]["age_days"], 30);
+        assert_eq!(v1["results"][1]["age_days"], 500);
+
+        let (mut stale, _) = outcome(SearchKind::Year);
+        stale.results[0].crawled_at = days_ago(401);
+        let freshness = stale.assess_freshness(400, now);
+        assert!(freshness.stale && freshness.suggested_recrawl);
+        assert_eq!(freshness.newest_crawled_at, days_ago(401));
+        let v2 = serde_json::to_value(SearchResponseV2 { freshness, ..stale.into_v2(&[source]) }).unwrap();
+        assert_eq!((v2["stale"].clone(), v2["suggested_recrawl"].clone()), (json!(true), json!(true)));
+        assert_eq!(v2["results"][0]["age_days"], 401);
+
+        // Nothing found, nothing stale
+        let (mut empty, _) = outcome(SearchKind::Filters);
+        empty.results.clear();
+        assert_eq!(empty.assess_freshness(400, now), Freshness::default());
+    }
+}
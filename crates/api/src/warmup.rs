@@ -0,0 +1,128 @@
+use dno_core::AppError;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How `AppState::warm_caches` runs its phases
+#[derive(Debug, Clone)]
+pub struct WarmupConfig {
+    pub enabled: bool,
+    /// Phases running at the same time
+    pub concurrency: usize,
+    /// A phase still running after this is abandoned so startup can continue
+    pub phase_timeout: Duration,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            concurrency: 2,
+            phase_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl WarmupConfig {
+    /// Read `WARM_CACHE` (set to `false` to skip warm-up), `WARM_CACHE_CONCURRENCY`
+    /// and `WARM_CACHE_PHASE_TIMEOUT_SECS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("WARM_CACHE")
+                .map(|v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | "no" | "off"))
+                .unwrap_or(defaults.enabled),
+            concurrency: std::env::var("WARM_CACHE_CONCURRENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &usize| n > 0)
+                .unwrap_or(defaults.concurrency),
+            phase_timeout: std::env::var("WARM_CACHE_PHASE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.phase_timeout),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "outcome", content = "error")]
+pub enum PhaseOutcome {
+    Completed,
+    Failed(String),
+    TimedOut,
+}
+
+/// Outcome of each warm-up phase, in the order the phases finished
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WarmupReport {
+    pub phases: Vec<(&'static str, PhaseOutcome)>,
+}
+
+impl WarmupReport {
+    pub fn completed(&self) -> Vec<&'static str> {
+        self.phases
+            .iter()
+            .filter(|(_, outcome)| *outcome == PhaseOutcome::Completed)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+pub type WarmupPhase<'a> = (&'static str, BoxFuture<'a, Result<(), AppError>>);
+
+/// Run named warm-up phases, at most `config.concurrency` at a time. A failed
+/// or timed-out phase is logged and skipped; warm-up never fails startup.
+pub async fn run_phases(phases: Vec<WarmupPhase<'_>>, config: &WarmupConfig) -> WarmupReport {
+    let phase_timeout = config.phase_timeout;
+    let phases = stream::iter(phases)
+        .map(|(name, phase)| async move {
+            let outcome = match tokio::time::timeout(phase_timeout, phase).await {
+                Ok(Ok(())) => PhaseOutcome::Completed,
+                Ok(Err(e)) => {
+                    tracing::warn!("Cache warm-up phase '{}' failed: {}", name, e);
+                    PhaseOutcome::Failed(e.to_string())
+                }
+                Err(_) => {
+                    tracing::warn!("Cache warm-up phase '{}' abandoned after {:?}", name, phase_timeout);
+                    PhaseOutcome::TimedOut
+                }
+            };
+            (name, outcome)
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    WarmupReport { phases }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn test_stuck_phase_is_abandoned_and_others_complete() {
+        let config = WarmupConfig { concurrency: 2, phase_timeout: Duration::from_millis(50), ..Default::default() };
+        let phases: Vec<WarmupPhase> = vec![
+            ("users", async { Ok(()) }.boxed()),
+            ("search", std::future::pending().boxed()),
+            ("dnos", async { Err(AppError::Cache("connection refused".to_string())) }.boxed()),
+            ("filters", async { Ok(()) }.boxed()),
+        ];
+
+        let report = tokio::time::timeout(Duration::from_secs(5), run_phases(phases, &config))
+            .await
+            .expect("warm-up must not block startup");
+
+        assert_eq!(report.phases.len(), 4);
+        let mut completed = report.completed();
+        completed.sort_unstable();
+        assert_eq!(completed, ["filters", "users"]);
+        assert!(report.phases.contains(&("search", PhaseOutcome::TimedOut)));
+        assert!(report.phases.iter().any(|(name, outcome)| *name == "dnos" && matches!(outcome, PhaseOutcome::Failed(_))));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::source_manager::{
+    AdminDataVerificationStatus, AuditEntry, AuditOperation, FileMetadata, ProvenanceRecord, SourceManager,
+};
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Audit entry counts since a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub since: Option<DateTime<Utc>>,
+    pub total: usize,
+    pub by_operation: BTreeMap<AuditOperation, usize>,
+    pub by_actor: BTreeMap<String, usize>,
+    pub latest: Option<AuditEntry>,
+}
+
+/// Admin operations on the files held by a `SourceManager`
+#[derive(Clone)]
+pub struct AdminInterface {
+    sources: Arc<SourceManager>,
+}
+
+impl AdminInterface {
+    pub fn new(sources: Arc<SourceManager>) -> Self {
+        Self { sources }
+    }
+
+    pub fn sources(&self) -> &SourceManager {
+        &self.sources
+    }
+
+    /// Move a file to the next review status, recording the transition in its
+    /// provenance. Transitions the workflow doesn't allow (e.g. `Verified` back
+    /// to `Pending`) are a `Conflict` and leave the file unchanged. Rejected
+    /// files become inactive; reopening one makes it active again.
+    pub fn review_file(
+        &self,
+        id: Uuid,
+        status: AdminDataVerificationStatus,
+        reviewer: &str,
+        notes: Option<&str>,
+    ) -> Result<FileMetadata, AppError> {
+        let file = self.sources.try_update_file(id, |file| {
+            let from = file.verification_status;
+            if !from.can_transition_to(status) {
+                let allowed: Vec<&str> = from.allowed_transitions().iter().map(|s| s.as_str()).collect();
+                return Err(AppError::Conflict {
+                    message: format!(
+                        "Source file {} can't go from {} to {} (allowed: {})",
+                        file.id,
+                        from.as_str(),
+                        status.as_str(),
+                        if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") }
+                    ),
+                    retry_after_secs: None,
+                });
+            }
+            file.verification_status = status;
+            file.is_active = status != AdminDataVerificationStatus::Rejected;
+            file.provenance.push(ProvenanceRecord {
+                at: Utc::now(),
+                actor: reviewer.to_string(),
+                action: format!("reviewed: {} -> {}", from.as_str(), status.as_str()),
+                details: notes.map(str::to_string),
+            });
+            Ok(())
+        })?;
+        self.sources
+            .record_audit(AuditOperation::FileReviewed, reviewer, &file, notes.map(str::to_string));
+        Ok(file)
+    }
+
+    pub fn get_audit_report(&self, since: Option<DateTime<Utc>>) -> AuditReport {
+        let entries: Vec<AuditEntry> = self
+            .sources
+            .get_audit_trail(None)
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .collect();
+
+        let mut by_operation = BTreeMap::new();
+        let mut by_actor = BTreeMap::new();
+        for entry in &entries {
+            *by_operation.entry(entry.operation).or_insert(0) += 1;
+            *by_actor.entry(entry.actor.clone()).or_insert(0) += 1;
+        }
+
+        AuditReport { since, total: entries.len(), by_operation, by_actor, latest: entries.last().cloned() }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{slugify_with, Dno};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How a suggestion matched the typed text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// The name, official name or slug starts with the text
+    Prefix,
+    /// A later word of the name starts with the text, e.g. `bw` for "Netze BW"
+    WordPrefix,
+    /// The text appears inside the name, or is within a typo or two of a word's start
+    Fuzzy,
+}
+
+/// A DNO name suggested for typed text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DnoSuggestion {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub region: Option<String>,
+    pub match_kind: MatchKind,
+}
+
+struct IndexedDno {
+    id: Uuid,
+    name: String,
+    slug: String,
+    region: Option<String>,
+    /// Names folded like slugs, with spaces between words
+    keys: Vec<String>,
+}
+
+struct LoadedIndex {
+    entries: Vec<IndexedDno>,
+    loaded_at: Instant,
+}
+
+/// In-memory index of DNO names for type-ahead suggestions. It is rebuilt
+/// from the (Redis-cached) DNO list on DNO mutations and when it gets older
+/// than the repository's refresh interval.
+#[derive(Default)]
+pub struct DnoAutocompleteIndex {
+    loaded: RwLock<Option<LoadedIndex>>,
+}
+
+impl DnoAutocompleteIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the indexed DNOs
+    pub fn rebuild(&self, dnos: &[Dno]) {
+        let entries = dnos
+            .iter()
+            .map(|dno| IndexedDno {
+                id: dno.id,
+                name: dno.name.clone(),
+                slug: dno.slug.clone(),
+                region: dno.region.clone(),
+                keys: std::iter::once(&dno.name)
+                    .chain(&dno.official_name)
+                    .map(|name| slugify_with(name, ' '))
+                    .collect(),
+            })
+            .collect();
+        *self.loaded.write().unwrap() = Some(LoadedIndex { entries, loaded_at: Instant::now() });
+    }
+
+    /// Whether the index was never built or was built longer than `max_age` ago
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.loaded
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|index| index.loaded_at.elapsed() > max_age)
+    }
+
+    /// Up to `limit` suggestions for `query`: prefix matches first, then word
+    /// prefixes, then fuzzy matches. Within each kind closer matches and
+    /// shorter names come first. Empty for blank text or an unbuilt index.
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<DnoSuggestion> {
+        let query = slugify_with(query, ' ');
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let slug_query = query.replace(' ', "-");
+        let loaded = self.loaded.read().unwrap();
+        let Some(index) = loaded.as_ref() else {
+            return Vec::new();
+        };
+
+        let mut matches: Vec<((MatchKind, usize), &IndexedDno)> = index
+            .entries
+            .iter()
+            .filter_map(|dno| {
+                let rank = if dno.keys.iter().any(|key| key.starts_with(&query)) || dno.slug.starts_with(&slug_query) {
+                    (MatchKind::Prefix, 0)
+                } else if dno.keys.iter().flat_map(|key| key.split(' ').skip(1)).any(|word| word.starts_with(&query)) {
+                    (MatchKind::WordPrefix, 0)
+                } else {
+                    (MatchKind::Fuzzy, fuzzy_distance(&query, &dno.keys)?)
+                };
+                Some((rank, dno))
+            })
+            .collect();
+        matches.sort_by(|(a_rank, a), (b_rank, b)| {
+            a_rank
+                .cmp(b_rank)
+                .then(a.name.len().cmp(&b.name.len()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|((match_kind, _), dno)| DnoSuggestion {
+                id: dno.id,
+                name: dno.name.clone(),
+                slug: dno.slug.clone(),
+                region: dno.region.clone(),
+                match_kind,
+            })
+            .collect()
+    }
+}
+
+/// 0 when `query` occurs inside a name; otherwise the edit distance to the
+/// closest word start, if within the typos allowed for the query's length
+fn fuzzy_distance(query: &str, keys: &[String]) -> Option<usize> {
+    if keys.iter().any(|key| key.contains(query)) {
+        return Some(0);
+    }
+    let allowed = match query.chars().count() {
+        0..=2 => return None,
+        3..=5 => 1,
+        _ => 2,
+    };
+    let query: Vec<char> = query.chars().collect();
+    let query = query.as_slice();
+    keys.iter()
+        .flat_map(|key| key.split(' '))
+        .flat_map(|word| {
+            // Word starts one shorter or longer catch a dropped or doubled letter
+            let word: Vec<char> = word.chars().collect();
+            (query.len() - 1..=query.len() + 1).map(move |len| levenshtein(query, &word[..len.min(word.len())]))
+        })
+        .min()
+        .filter(|&distance| distance <= allowed)
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn dno(name: &str, region: &str) -> Dno {
+        Dno {
+            id: Uuid::new_v4(),
+            slug: crate::slugify_dno(name),
+            name: name.to_string(),
+            official_name: None,
+            description: None,
+            region: Some(region.to_string()),
+            website: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_two_letter_prefix_is_ranked_and_refresh_adds_new_dnos() {
+        let mut dnos = vec![
+            dno("Westnetz", "Nordrhein-Westfalen"),
+            dno("Netzgesellschaft Berlin", "Berlin"),
+            dno("Bayernwerk Netz", "Bayern"),
+            dno("Netze BW", "Baden-Württemberg"),
+            dno("Thüringer Energienetze", "Thüringen"),
+            dno("Netze ODR", "Baden-Württemberg"),
+            dno("E.DIS Netz", "Brandenburg"),
+        ];
+        let index = DnoAutocompleteIndex::new();
+        assert!(index.is_stale(Duration::from_secs(60)));
+        index.rebuild(&dnos);
+
+        let suggestions = index.suggest("Ne", 10);
+        let names: Vec<(&str, MatchKind)> = suggestions.iter().map(|s| (s.name.as_str(), s.match_kind)).collect();
+        assert_eq!(
+            names,
+            [
+                ("Netze BW", MatchKind::Prefix),
+                ("Netze ODR", MatchKind::Prefix),
+                ("Netzgesellschaft Berlin", MatchKind::Prefix),
+                ("E.DIS Netz", MatchKind::WordPrefix),
+                ("Bayernwerk Netz", MatchKind::WordPrefix),
+                ("Westnetz", MatchKind::Fuzzy),
+                ("Thüringer Energienetze", MatchKind::Fuzzy),
+            ]
+        );
+        assert_eq!(suggestions[0].slug, "netze-bw");
+        assert_eq!(suggestions[0].region.as_deref(), Some("Baden-Württemberg"));
+        assert_eq!(index.suggest("ne", 2).len(), 2);
+
+        // A typo still finds the DNO once the query is long enough
+        assert_eq!(index.suggest("bayrn", 5)[0].name, "Bayernwerk Netz");
+
+        dnos.push(dno("NEW Netz", "Nordrhein-Westfalen"));
+        assert!(!index.suggest("ne", 10).iter().any(|s| s.name == "NEW Netz"));
+        index.rebuild(&dnos);
+        assert_eq!(index.suggest("ne", 10)[0].name, "NEW Netz");
+    }
+}
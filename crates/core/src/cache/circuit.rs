@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Tracks Redis connectivity so a dead cache is skipped for a cooldown
+/// instead of every request waiting on a connection timeout
+#[derive(Debug)]
+pub struct CacheCircuit {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CacheCircuit {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            consecutive_failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    /// Whether a cache operation may be attempted. Once the cooldown has
+    /// elapsed a single trial call is let through (half-open).
+    pub fn allow(&self) -> bool {
+        let mut open_until = self.open_until.lock().unwrap();
+        match *open_until {
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                // Half-open: re-arm the cooldown so only this caller probes Redis
+                *open_until = Some(Instant::now() + self.cooldown);
+                true
+            }
+            None => true,
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        let mut open_until = self.open_until.lock().unwrap();
+        if open_until.take().is_some() {
+            info!("Redis reachable again, re-enabling cache");
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.failure_threshold {
+            let mut open_until = self.open_until.lock().unwrap();
+            if open_until.is_none() {
+                warn!(
+                    "Redis failed {} times in a row, disabling cache for {}s",
+                    failures,
+                    self.cooldown.as_secs()
+                );
+            }
+            *open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// True while the cache is disabled
+    pub fn is_open(&self) -> bool {
+        matches!(*self.open_until.lock().unwrap(), Some(until) if Instant::now() < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_after_threshold_and_recovers() {
+        let circuit = CacheCircuit::new(2, Duration::from_millis(20));
+
+        circuit.record_failure();
+        assert!(circuit.allow());
+        circuit.record_failure();
+        assert!(circuit.is_open());
+        assert!(!circuit.allow());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(circuit.allow()); // half-open trial
+        assert!(!circuit.allow()); // only one probe at a time
+
+        circuit.record_success();
+        assert!(!circuit.is_open());
+        assert!(circuit.allow());
+    }
+}
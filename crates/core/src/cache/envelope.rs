@@ -0,0 +1,87 @@
+use super::CacheError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+/// Version of the envelope layout written by `seal`
+const ENVELOPE_VERSION: u32 = 1;
+
+/// How values are stored in Redis: the payload with a SHA-256 of its JSON,
+/// so values that were cut short or altered are noticed instead of being
+/// read as garbage
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope {
+    v: u32,
+    checksum: String,
+    payload: Value,
+}
+
+fn checksum(payload: &str) -> String {
+    format!("{:x}", Sha256::digest(payload.as_bytes()))
+}
+
+/// Serialize a value into its envelope
+pub fn seal<T: Serialize>(value: &T) -> Result<String, CacheError> {
+    let payload = serde_json::to_value(value)?;
+    let envelope = Envelope { v: ENVELOPE_VERSION, checksum: checksum(&payload.to_string()), payload };
+    Ok(serde_json::to_string(&envelope)?)
+}
+
+/// Read a stored value. A checksum mismatch is logged and read as a miss;
+/// values written before envelopes existed are read as they are.
+pub fn open<T: DeserializeOwned>(key: &str, stored: &str) -> Result<Option<T>, CacheError> {
+    let Ok(envelope) = serde_json::from_str::<Envelope>(stored) else {
+        return Ok(Some(serde_json::from_str(stored)?));
+    };
+    if envelope.checksum != checksum(&envelope.payload.to_string()) {
+        warn!("Cache value for key {} failed its checksum (envelope v{}), treating it as a miss", key, envelope.v);
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_value(envelope.payload)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Netzentgelt {
+        dno: String,
+        year: i32,
+        arbeitspreis: f64,
+    }
+
+    fn sample() -> Netzentgelt {
+        Netzentgelt { dno: "Netze BW".to_string(), year: 2024, arbeitspreis: 6.71 }
+    }
+
+    #[test]
+    fn test_sealed_value_round_trips() {
+        let stored = seal(&sample()).unwrap();
+        let envelope: Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(envelope["v"], 1);
+        assert_eq!(envelope["checksum"].as_str().unwrap().len(), 64);
+
+        assert_eq!(open::<Netzentgelt>("search:netzentgelte:ab12", &stored).unwrap(), Some(sample()));
+    }
+
+    #[test]
+    fn test_corrupted_value_is_a_miss() {
+        let mut envelope: Value = serde_json::from_str(&seal(&sample()).unwrap()).unwrap();
+        envelope["payload"]["arbeitspreis"] = json!(67.1);
+
+        assert_eq!(open::<Netzentgelt>("search:netzentgelte:ab12", &envelope.to_string()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_legacy_value_is_read_as_is() {
+        let legacy = serde_json::to_string(&sample()).unwrap();
+        assert_eq!(open::<Netzentgelt>("search:netzentgelte:ab12", &legacy).unwrap(), Some(sample()));
+        assert_eq!(open::<i64>("rate_limit:ip:1.2.3.4:1", "42").unwrap(), Some(42));
+        assert!(open::<Netzentgelt>("search:netzentgelte:ab12", "{\"dno\": \"Netze").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use super::{CacheError, CacheKeys, CacheLayer};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Why a lock could not be taken
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("Lock {key} is held by another operation")]
+    Held { key: String, retry_after: Duration },
+
+    #[error("Lock backend error: {0}")]
+    Cache(#[from] CacheError),
+}
+
+/// Proof of a held lock; hand it back to `release` when the write phase ends.
+/// If it is never released the lock expires after its TTL.
+#[derive(Debug)]
+#[must_use = "a held lock must be released"]
+pub struct LockGuard {
+    key: String,
+    /// Value stored under the key; only a release presenting it deletes the lock
+    token: String,
+    /// False when the cache was unreachable and the caller proceeded unlocked
+    pub acquired: bool,
+}
+
+impl LockGuard {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Redis-backed distributed lock. The key is taken with SET NX PX, so the TTL
+/// is set in the same step and bounds how long a crashed holder can block
+/// others. Its value is a token only the holder knows, and a release deletes
+/// the key only while it still holds that token: a holder whose lock expired
+/// cannot release the lock someone else took since.
+#[derive(Clone)]
+pub struct DistributedLock<C: CacheLayer> {
+    cache: Arc<C>,
+}
+
+impl<C: CacheLayer> DistributedLock<C> {
+    pub fn new(cache: Arc<C>) -> Self {
+        Self { cache }
+    }
+
+    /// Single attempt; `Ok(None)` if someone else holds the lock
+    pub async fn try_acquire(&self, key: &str, ttl: Duration) -> Result<Option<LockGuard>, CacheError> {
+        let token = uuid::Uuid::new_v4().to_string();
+        if !self.cache.set_nx(key, &token, ttl).await? {
+            return Ok(None);
+        }
+        Ok(Some(LockGuard { key: key.to_string(), token, acquired: true }))
+    }
+
+    /// Retry until `wait` elapses. Cache outages fail open (the guard reports
+    /// `acquired: false`) so writes keep working without Redis, as reads do.
+    pub async fn acquire(&self, key: &str, ttl: Duration, wait: Duration) -> Result<LockGuard, LockError> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            match self.try_acquire(key, ttl).await {
+                Ok(Some(guard)) => {
+                    debug!("Acquired lock {}", key);
+                    return Ok(guard);
+                }
+                Ok(None) if tokio::time::Instant::now() >= deadline => {
+                    return Err(LockError::Held { key: key.to_string(), retry_after: ttl.min(Duration::from_secs(5)) });
+                }
+                Ok(None) => tokio::time::sleep(Duration::from_millis(100)).await,
+                Err(e) => {
+                    warn!("Lock backend unavailable for {}, continuing unlocked: {}", key, e);
+                    return Ok(LockGuard { key: key.to_string(), token: String::new(), acquired: false });
+                }
+            }
+        }
+    }
+
+    pub async fn release(&self, guard: LockGuard) {
+        if !guard.acquired {
+            return;
+        }
+        match self.cache.delete_if_eq(&guard.key, &guard.token).await {
+            Ok(true) => {}
+            Ok(false) => warn!("Lock {} expired before it was released", guard.key),
+            Err(e) => warn!("Failed to release lock {}: {}", guard.key, e),
+        }
+    }
+}
+
+/// Per-DNO write lock shared by crawl persistence and admin edits. Reads never
+/// take it; only the write phase is serialized.
+#[derive(Clone)]
+pub struct DnoWriteLock<C: CacheLayer> {
+    lock: DistributedLock<C>,
+    ttl: Duration,
+    edit_wait: Duration,
+    crawl_wait: Duration,
+}
+
+impl<C: CacheLayer> DnoWriteLock<C> {
+    pub fn new(cache: Arc<C>) -> Self {
+        Self {
+            lock: DistributedLock::new(cache),
+            ttl: Duration::from_secs(120),        // Upper bound for a single write phase
+            edit_wait: Duration::from_secs(2),    // Interactive edits fail fast
+            crawl_wait: Duration::from_secs(60),  // Crawls can afford to queue behind an edit
+        }
+    }
+
+    /// Lock for an interactive admin edit; returns `LockError::Held` quickly if a crawl is writing
+    pub async fn for_edit(&self, dno_id: uuid::Uuid) -> Result<LockGuard, LockError> {
+        self.lock.acquire(&CacheKeys::dno_write_lock(dno_id), self.ttl, self.edit_wait).await
+    }
+
+    /// Lock for persisting crawl results; waits longer for in-flight edits to finish
+    pub async fn for_crawl(&self, dno_id: uuid::Uuid) -> Result<LockGuard, LockError> {
+        self.lock.acquire(&CacheKeys::dno_write_lock(dno_id), self.ttl, self.crawl_wait).await
+    }
+
+    pub async fn release(&self, guard: LockGuard) {
+        self.lock.release(guard).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+
+    #[tokio::test]
+    async fn test_crawl_holding_lock_makes_edit_fail_fast() {
+        let locks = DnoWriteLock {
+            edit_wait: Duration::from_millis(200),
+            ..DnoWriteLock::new(Arc::new(MemoryCache::new()))
+        };
+        let dno_id = uuid::Uuid::nil();
+
+        let crawl = locks.for_crawl(dno_id).await.unwrap();
+        assert!(crawl.acquired);
+
+        let started = std::time::Instant::now();
+        let edit = locks.for_edit(dno_id).await;
+        assert!(matches!(edit, Err(LockError::Held { .. })));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        locks.release(crawl).await;
+        let edit = locks.for_edit(dno_id).await.unwrap();
+        assert!(edit.acquired);
+        locks.release(edit).await;
+    }
+
+    #[tokio::test]
+    async fn test_expired_holder_cannot_release_the_next_lock() {
+        let lock = DistributedLock::new(Arc::new(MemoryCache::new()));
+
+        let stale = lock.try_acquire("lock:dno", Duration::from_millis(50)).await.unwrap().unwrap();
+        assert!(lock.try_acquire("lock:dno", Duration::from_secs(60)).await.unwrap().is_none());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let current = lock.try_acquire("lock:dno", Duration::from_secs(60)).await.unwrap().unwrap();
+
+        // The first holder's release must not free the lock it no longer owns
+        lock.release(stale).await;
+        assert!(lock.try_acquire("lock:dno", Duration::from_secs(60)).await.unwrap().is_none());
+
+        lock.release(current).await;
+        let next = lock.try_acquire("lock:dno", Duration::from_secs(60)).await.unwrap();
+        assert!(next.is_some_and(|guard| guard.acquired));
+    }
+
+    #[tokio::test]
+    async fn test_edit_waits_for_short_crawl_write() {
+        let cache = Arc::new(MemoryCache::new());
+        let locks = DnoWriteLock::new(cache);
+        let dno_id = uuid::Uuid::nil();
+
+        let crawl = locks.for_crawl(dno_id).await.unwrap();
+        let crawl_locks = locks.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            crawl_locks.release(crawl).await;
+        });
+
+        // Within the edit wait, so the edit goes through once the crawl finishes
+        let edit = locks.for_edit(dno_id).await.unwrap();
+        assert!(edit.acquired);
+    }
+}
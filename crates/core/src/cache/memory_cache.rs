@@ -0,0 +1,159 @@
+use super::trace::{record, CacheOp, CacheOutcome};
+use super::{CacheError, CacheLayer};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// In-process implementation of the CacheLayer trait, used in tests and
+/// for running without Redis
+#[derive(Clone, Default)]
+pub struct MemoryCache {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+struct Entry {
+    json: String,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_live(&self) -> bool {
+        self.expires_at.is_none_or(|at| Instant::now() < at)
+    }
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of live keys, mainly for test assertions
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().values().filter(|e| e.is_live()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn expiry(ttl: Option<Duration>) -> Option<Instant> {
+        ttl.map(|ttl| Instant::now() + ttl)
+    }
+}
+
+#[async_trait]
+impl CacheLayer for MemoryCache {
+    async fn get<T>(&self, key: &str) -> Result<Option<T>, CacheError>
+    where
+        T: serde::de::DeserializeOwned + Send,
+    {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(key).filter(|e| e.is_live()) {
+            Some(entry) => {
+                record(CacheOp::Get, key, CacheOutcome::Hit);
+                Ok(Some(serde_json::from_str(&entry.json)?))
+            }
+            None => {
+                record(CacheOp::Get, key, CacheOutcome::Miss);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn set<T>(&self, key: &str, value: &T, ttl: Option<Duration>) -> Result<(), CacheError>
+    where
+        T: serde::Serialize + Send + Sync,
+    {
+        let json = serde_json::to_string(value)?;
+        self.entries.lock().unwrap().insert(
+            key.to_string(),
+            Entry { json, expires_at: Self::expiry(ttl) },
+        );
+        record(CacheOp::Set, key, CacheOutcome::Write);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.entries.lock().unwrap().remove(key);
+        record(CacheOp::Delete, key, CacheOutcome::Write);
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, CacheError> {
+        Ok(self.entries.lock().unwrap().get(key).is_some_and(|e| e.is_live()))
+    }
+
+    async fn invalidate_pattern(&self, pattern: &str) -> Result<u64, CacheError> {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, _| !key.starts_with(pattern));
+        record(CacheOp::Invalidate, pattern, CacheOutcome::Write);
+        Ok((before - entries.len()) as u64)
+    }
+
+    async fn mget<T>(&self, keys: &[String]) -> Result<Vec<Option<T>>, CacheError>
+    where
+        T: serde::de::DeserializeOwned + Send,
+    {
+        let entries = self.entries.lock().unwrap();
+        keys.iter()
+            .map(|key| match entries.get(key).filter(|e| e.is_live()) {
+                Some(entry) => Ok(serde_json::from_str(&entry.json).ok()),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    async fn mset<T>(&self, items: &[(String, T)], ttl: Option<Duration>) -> Result<(), CacheError>
+    where
+        T: serde::Serialize + Send + Sync,
+    {
+        let mut entries = self.entries.lock().unwrap();
+        for (key, value) in items {
+            let json = serde_json::to_string(value)?;
+            entries.insert(key.clone(), Entry { json, expires_at: Self::expiry(ttl) });
+        }
+        Ok(())
+    }
+
+    async fn incr(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, CacheError> {
+        let mut entries = self.entries.lock().unwrap();
+        let current = match entries.get(key).filter(|e| e.is_live()) {
+            Some(entry) => Some((serde_json::from_str::<i64>(&entry.json)?, entry.expires_at)),
+            None => None,
+        };
+
+        // Like Redis INCR + EXPIRE on a new key: the TTL is only applied on creation
+        let (value, expires_at) = match current {
+            Some((value, expires_at)) => (value + delta, expires_at),
+            None => (delta, Self::expiry(ttl)),
+        };
+        entries.insert(key.to_string(), Entry { json: value.to_string(), expires_at });
+        Ok(value)
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, CacheError> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.get(key).is_some_and(|e| e.is_live()) {
+            return Ok(false);
+        }
+        let json = serde_json::to_string(value)?;
+        entries.insert(key.to_string(), Entry { json, expires_at: Self::expiry(Some(ttl)) });
+        record(CacheOp::Set, key, CacheOutcome::Write);
+        Ok(true)
+    }
+
+    async fn delete_if_eq(&self, key: &str, value: &str) -> Result<bool, CacheError> {
+        let mut entries = self.entries.lock().unwrap();
+        let holds = entries
+            .get(key)
+            .filter(|e| e.is_live())
+            .is_some_and(|e| serde_json::from_str::<String>(&e.json).is_ok_and(|stored| stored == value));
+        if holds {
+            entries.remove(key);
+            record(CacheOp::Delete, key, CacheOutcome::Write);
+        }
+        Ok(holds)
+    }
+}
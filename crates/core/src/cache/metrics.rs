@@ -223,7 +223,7 @@ pub enum CacheOperationResult {
 }
 
 /// Extract cache key pattern for metrics grouping
-fn extract_key_pattern(key: &str) -> String {
+pub(crate) fn extract_key_pattern(key: &str) -> String {
     let parts: Vec<&str> = key.split(':').collect();
     if parts.len() >= 3 {
         format!("{}:{}:*", parts[0], parts[1])
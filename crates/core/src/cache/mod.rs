@@ -1,11 +1,21 @@
 use async_trait::async_trait;
+use std::future::Future;
 use std::time::Duration;
 use thiserror::Error;
+use tracing::{debug, warn};
 
 pub mod redis_cache;
+pub mod envelope;
 pub mod metrics;
+pub mod circuit;
+pub mod memory_cache;
+pub mod trace;
+pub mod lock;
 
 pub use redis_cache::RedisCache;
+pub use memory_cache::MemoryCache;
+pub use lock::{DistributedLock, DnoWriteLock, LockError, LockGuard};
+pub use circuit::CacheCircuit;
 
 #[derive(Error, Debug)]
 pub enum CacheError {
@@ -23,6 +33,9 @@ pub enum CacheError {
     
     #[error("Cache operation timeout")]
     Timeout,
+
+    #[error("Cache unavailable (circuit open)")]
+    Unavailable,
 }
 
 /// Trait defining cache operations for the DNO data gatherer system
@@ -59,6 +72,54 @@ pub trait CacheLayer: Send + Sync + Clone {
 
     /// Increment a numeric value (for counters, rate limiting)
     async fn incr(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, CacheError>;
+
+    /// Set `key` to the raw `value` with `ttl` only if it does not exist yet,
+    /// in one step (SET NX PX); true if this call set it
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, CacheError>;
+
+    /// Delete `key` only while it still holds the raw `value`, in one step;
+    /// true if this call deleted it
+    async fn delete_if_eq(&self, key: &str, value: &str) -> Result<bool, CacheError>;
+
+    /// The cached value for `key`, or the one `compute` produces, which is
+    /// then cached for `ttl`. An error from `compute` is returned and not
+    /// cached. Cache failures are logged and never fail the lookup: a
+    /// failed read computes the value, a failed write just isn't cached.
+    async fn get_or_compute<T, E, F, Fut>(&self, key: &str, ttl: Option<Duration>, compute: F) -> Result<T, E>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + Send + Sync,
+        E: Send,
+        F: FnOnce() -> Fut + Send,
+        Fut: Future<Output = Result<T, E>> + Send,
+    {
+        self.get_or_compute_with(key, |_| ttl, compute).await
+    }
+
+    /// `get_or_compute` with a TTL chosen per value, e.g. a shorter one for
+    /// negative results
+    async fn get_or_compute_with<T, E, F, Fut, L>(&self, key: &str, ttl_for: L, compute: F) -> Result<T, E>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + Send + Sync,
+        E: Send,
+        F: FnOnce() -> Fut + Send,
+        Fut: Future<Output = Result<T, E>> + Send,
+        L: FnOnce(&T) -> Option<Duration> + Send,
+    {
+        match self.get::<T>(key).await {
+            Ok(Some(value)) => {
+                debug!("Cache HIT for {}", key);
+                return Ok(value);
+            }
+            Ok(None) => debug!("Cache MISS for {}", key),
+            Err(e) => warn!("Cache error for {}: {}", key, e),
+        }
+
+        let value = compute().await?;
+        if let Err(e) = self.set(key, &value, ttl_for(&value)).await {
+            warn!("Failed to cache {}: {}", key, e);
+        }
+        Ok(value)
+    }
 }
 
 /// Cache key utilities for consistent naming
@@ -98,6 +159,11 @@ impl CacheKeys {
         format!("search:count:netzentgelte:{}", filter_hash)
     }
 
+    pub fn search_coverage(filters: &SearchFilters) -> String {
+        let filter_hash = Self::hash_search_filters(filters);
+        format!("search:coverage:{}", filter_hash)
+    }
+
     /// Dashboard and analytics cache keys
     pub fn dashboard_stats(user_role: &str) -> String {
         let window = chrono::Utc::now().timestamp() / 900; // 15-minute windows
@@ -115,17 +181,40 @@ impl CacheKeys {
     }
 
     pub fn dno_by_name(name: &str) -> String {
-        format!("reference:dno:name:{}", Self::normalize_name(name))
+        format!("reference:dno:name:{}", crate::slug::slugify_dno(name))
     }
 
     pub fn dno_by_slug(slug: &str) -> String {
-        format!("reference:dno:slug:{}", slug.to_lowercase())
+        format!("reference:dno:slug:{}", crate::slug::slugify_dno(slug))
     }
 
     pub fn all_dnos() -> String {
         "reference:dnos:all".to_string()
     }
 
+    /// Rendered feed pages, keyed by their `before` cursor
+    pub fn verified_feed(before: Option<chrono::DateTime<chrono::Utc>>, limit: i64) -> String {
+        match before {
+            Some(before) => format!("feed:verified:{}:{}", before.timestamp_micros(), limit),
+            None => format!("feed:verified:latest:{}", limit),
+        }
+    }
+
+    /// Distributed lock keys
+    pub fn dno_write_lock(dno_id: uuid::Uuid) -> String {
+        format!("lock:dno:{}", dno_id)
+    }
+
+    pub fn dedup_lock() -> String {
+        "lock:dedup".to_string()
+    }
+
+    /// Model interpretations of free-text queries, keyed by the normalized query
+    pub fn query_interpretation(normalized_query: &str) -> String {
+        use sha2::{Sha256, Digest};
+        format!("query:interpret:{}", &format!("{:x}", Sha256::digest(normalized_query.as_bytes()))[..16])
+    }
+
     /// Query history cache keys
     pub fn user_query_history(user_id: uuid::Uuid, page: i64) -> String {
         format!("history:user:{}:page:{}", user_id, page)
@@ -162,16 +251,11 @@ impl CacheKeys {
         hasher.update(filters.region.as_deref().unwrap_or(""));
         hasher.update(filters.limit.map(|l| l.to_string()).unwrap_or_default());
         hasher.update(filters.offset.map(|o| o.to_string()).unwrap_or_default());
+        hasher.update(filters.paging.cache_key_part());
+        hasher.update(filters.versions.cache_key_part());
         
         format!("{:x}", hasher.finalize())[..16].to_string()
     }
-
-    fn normalize_name(name: &str) -> String {
-        name.to_lowercase()
-            .trim()
-            .replace(' ', "_")
-            .replace(|c: char| !c.is_alphanumeric() && c != '_', "")
-    }
 }
 
 /// Search filters struct for cache key generation
@@ -184,24 +268,33 @@ pub struct SearchFilters {
     pub region: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    pub paging: crate::paging::Paging,
+    pub versions: crate::models::DataVersions,
 }
 
 /// Cache configuration structure for Redis connection
 #[derive(Debug, Clone)]
 pub struct RedisCacheConfig {
     pub redis_url: String,
+    /// Namespace in front of every key (`dno`), so several apps or test runs can share one Redis
+    pub key_prefix: String,
     pub max_connections: u32,
     pub connection_timeout: u64,
     pub default_ttl: Duration,
     pub session_ttl: Duration,
     pub found_data_ttl: Duration,
     pub not_found_ttl: Duration,
+    /// Consecutive connection failures before the cache is disabled
+    pub circuit_failure_threshold: u32,
+    /// How long the cache stays disabled before Redis is probed again
+    pub circuit_cooldown: Duration,
 }
 
 impl RedisCacheConfig {
     pub fn from_env() -> Result<Self, std::env::VarError> {
         Ok(Self {
             redis_url: std::env::var("APP_REDIS_URL")?,
+            key_prefix: "dno".to_string(),
             max_connections: std::env::var("REDIS_MAX_CONNECTIONS")
                 .unwrap_or_else(|_| "100".to_string())
                 .parse()
@@ -229,6 +322,50 @@ impl RedisCacheConfig {
                     .parse()
                     .unwrap_or(3600)
             ),
+            circuit_failure_threshold: std::env::var("REDIS_CIRCUIT_THRESHOLD")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            circuit_cooldown: Duration::from_secs(
+                std::env::var("REDIS_CIRCUIT_COOLDOWN")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30)
+            ),
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_get_or_compute_runs_the_closure_once() {
+        let cache = MemoryCache::new();
+        let computed = AtomicUsize::new(0);
+        let compute = || async {
+            computed.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, String>(vec!["netze-bw".to_string()])
+        };
+
+        let first = cache.get_or_compute("reference:dnos:all", None, compute).await.unwrap();
+        let second = cache.get_or_compute("reference:dnos:all", None, compute).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_compute_does_not_cache_errors() {
+        let cache = MemoryCache::new();
+
+        let failed: Result<i64, String> = cache.get_or_compute("stats:total", None, || async { Err("database down".to_string()) }).await;
+        assert_eq!(failed.unwrap_err(), "database down");
+        assert!(cache.is_empty());
+
+        let recovered: Result<i64, String> = cache.get_or_compute("stats:total", None, || async { Ok(42) }).await;
+        assert_eq!(recovered.unwrap(), 42);
+    }
+}
@@ -1,7 +1,10 @@
-use super::{CacheLayer, CacheError, RedisCacheConfig};
+use super::envelope::{open, seal};
+use super::trace::{record, CacheOp, CacheOutcome};
+use super::{CacheCircuit, CacheLayer, CacheError, RedisCacheConfig};
 use async_trait::async_trait;
 use bb8_redis::{bb8, redis::AsyncCommands, RedisConnectionManager};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
@@ -12,6 +15,7 @@ pub type RedisPool = bb8::Pool<RedisConnectionManager>;
 pub struct RedisCache {
     pool: RedisPool,
     config: RedisCacheConfig,
+    circuit: Arc<CacheCircuit>,
 }
 
 impl RedisCache {
@@ -37,12 +41,51 @@ impl RedisCache {
 
         debug!("Redis cache initialized successfully with {} max connections", config.max_connections);
 
-        Ok(Self { pool, config })
+        let circuit = Arc::new(CacheCircuit::new(config.circuit_failure_threshold, config.circuit_cooldown));
+        Ok(Self { pool, config, circuit })
+    }
+
+    /// Create a cache without connecting up front; Redis being down only
+    /// trips the circuit on first use
+    pub fn connect_lazy(config: RedisCacheConfig) -> Result<Self, CacheError> {
+        let manager = RedisConnectionManager::new(config.redis_url.clone())
+            .map_err(|e| CacheError::Pool(format!("Failed to create Redis manager: {}", e)))?;
+
+        let pool = bb8::Pool::builder()
+            .max_size(config.max_connections)
+            .connection_timeout(Duration::from_secs(config.connection_timeout))
+            .build_unchecked(manager);
+
+        let circuit = Arc::new(CacheCircuit::new(config.circuit_failure_threshold, config.circuit_cooldown));
+        Ok(Self { pool, config, circuit })
+    }
+
+    /// Whether cache calls are currently being skipped because Redis is unreachable
+    pub fn is_disabled(&self) -> bool {
+        self.circuit.is_open()
+    }
+
+    /// Check out a pooled connection, failing fast while the circuit is open
+    async fn connection(&self) -> Result<bb8::PooledConnection<'_, RedisConnectionManager>, CacheError> {
+        if !self.circuit.allow() {
+            return Err(CacheError::Unavailable);
+        }
+
+        match self.pool.get().await {
+            Ok(conn) => {
+                self.circuit.record_success();
+                Ok(conn)
+            }
+            Err(e) => {
+                self.circuit.record_failure();
+                Err(CacheError::Pool(format!("Failed to get connection: {}", e)))
+            }
+        }
     }
 
     /// Create cache key with DNO prefix for namespace separation
     fn make_key(&self, key: &str) -> String {
-        format!("dno:{}", key)
+        format!("{}:{}", self.config.key_prefix, key)
     }
 
     /// Get TTL based on data type and configuration
@@ -60,28 +103,40 @@ impl CacheLayer for RedisCache {
         let start = std::time::Instant::now();
         let cache_key = self.make_key(key);
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await.inspect_err(|_| {
+            record(CacheOp::Get, key, CacheOutcome::Error);
+        })?;
 
-        let data: Option<String> = conn.get(&cache_key).await?;
+        let data: Option<String> = conn.get(&cache_key).await.inspect_err(|_| {
+            record(CacheOp::Get, key, CacheOutcome::Error);
+        })?;
 
         let result = match data {
             Some(json) => {
-                match serde_json::from_str::<T>(&json) {
-                    Ok(value) => {
+                match open::<T>(key, &json) {
+                    Ok(Some(value)) => {
                         debug!("Cache HIT for key: {} ({}ms)", key, start.elapsed().as_millis());
+                        record(CacheOp::Get, key, CacheOutcome::Hit);
                         Some(value)
                     }
+                    Ok(None) => {
+                        // Checksum mismatch: drop the value so the next set replaces it
+                        record(CacheOp::Get, key, CacheOutcome::Miss);
+                        let _: () = conn.del(&cache_key).await.unwrap_or(());
+                        None
+                    }
                     Err(e) => {
                         warn!("Cache deserialization error for key {}: {}", key, e);
+                        record(CacheOp::Get, key, CacheOutcome::Error);
                         // Delete corrupted data
                         let _: () = conn.del(&cache_key).await.unwrap_or(());
-                        return Err(CacheError::Serialization(e));
+                        return Err(e);
                     }
                 }
             }
             None => {
                 debug!("Cache MISS for key: {} ({}ms)", key, start.elapsed().as_millis());
+                record(CacheOp::Get, key, CacheOutcome::Miss);
                 None
             }
         };
@@ -96,13 +151,17 @@ impl CacheLayer for RedisCache {
         let start = std::time::Instant::now();
         let cache_key = self.make_key(key);
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await.inspect_err(|_| {
+            record(CacheOp::Set, key, CacheOutcome::Error);
+        })?;
 
-        let json = serde_json::to_string(value)?;
+        let json = seal(value)?;
         let ttl_seconds = self.get_ttl(ttl).as_secs();
 
-        let _: () = conn.set_ex(&cache_key, json, ttl_seconds).await?;
+        let _: () = conn.set_ex(&cache_key, json, ttl_seconds).await.inspect_err(|_| {
+            record(CacheOp::Set, key, CacheOutcome::Error);
+        })?;
+        record(CacheOp::Set, key, CacheOutcome::Write);
 
         debug!("Cache SET for key: {} with TTL {}s ({}ms)", 
                key, ttl_seconds, start.elapsed().as_millis());
@@ -113,14 +172,14 @@ impl CacheLayer for RedisCache {
     async fn delete(&self, key: &str) -> Result<(), CacheError> {
         let cache_key = self.make_key(key);
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await?;
 
         let deleted: i32 = conn.del(&cache_key).await?;
 
         if deleted > 0 {
             debug!("Cache DELETE for key: {}", key);
         }
+        record(CacheOp::Delete, key, CacheOutcome::Write);
 
         Ok(())
     }
@@ -128,8 +187,7 @@ impl CacheLayer for RedisCache {
     async fn exists(&self, key: &str) -> Result<bool, CacheError> {
         let cache_key = self.make_key(key);
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await?;
 
         let exists: bool = conn.exists(&cache_key).await?;
         Ok(exists)
@@ -139,8 +197,7 @@ impl CacheLayer for RedisCache {
         let start = std::time::Instant::now();
         let cache_pattern = self.make_key(&format!("{}*", pattern));
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await?;
 
         // Get all keys matching the pattern
         let keys: Vec<String> = conn.keys(&cache_pattern).await?;
@@ -152,6 +209,7 @@ impl CacheLayer for RedisCache {
                    pattern, key_count, start.elapsed().as_millis());
         }
 
+        record(CacheOp::Invalidate, pattern, CacheOutcome::Write);
         Ok(key_count)
     }
 
@@ -166,8 +224,7 @@ impl CacheLayer for RedisCache {
         let start = std::time::Instant::now();
         let cache_keys: Vec<String> = keys.iter().map(|k| self.make_key(k)).collect();
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await?;
 
         let data: Vec<Option<String>> = conn.mget(&cache_keys).await?;
 
@@ -177,11 +234,17 @@ impl CacheLayer for RedisCache {
         for (i, value) in data.into_iter().enumerate() {
             match value {
                 Some(json) => {
-                    match serde_json::from_str::<T>(&json) {
-                        Ok(parsed) => {
+                    match open::<T>(&keys[i], &json) {
+                        Ok(Some(parsed)) => {
+                            record(CacheOp::Get, &keys[i], CacheOutcome::Hit);
                             results.push(Some(parsed));
                             hits += 1;
                         }
+                        Ok(None) => {
+                            record(CacheOp::Get, &keys[i], CacheOutcome::Miss);
+                            let _: () = conn.del(&cache_keys[i]).await.unwrap_or(());
+                            results.push(None);
+                        }
                         Err(e) => {
                             warn!("Cache deserialization error for key {}: {}", keys[i], e);
                             // Delete corrupted data
@@ -191,7 +254,10 @@ impl CacheLayer for RedisCache {
                         }
                     }
                 }
-                None => results.push(None),
+                None => {
+                    record(CacheOp::Get, &keys[i], CacheOutcome::Miss);
+                    results.push(None);
+                }
             }
         }
 
@@ -212,19 +278,21 @@ impl CacheLayer for RedisCache {
         let start = std::time::Instant::now();
         let ttl_seconds = self.get_ttl(ttl).as_secs();
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await?;
 
         // Use pipeline for efficiency
         let mut pipe = redis::pipe();
 
         for (key, value) in items {
             let cache_key = self.make_key(key);
-            let json = serde_json::to_string(value)?;
+            let json = seal(value)?;
             pipe.set_ex(&cache_key, json, ttl_seconds);
         }
 
         pipe.query_async::<_, ()>(&mut *conn).await?;
+        for (key, _) in items {
+            record(CacheOp::Set, key, CacheOutcome::Write);
+        }
 
         debug!("Cache MSET for {} keys with TTL {}s ({}ms)", 
                items.len(), ttl_seconds, start.elapsed().as_millis());
@@ -235,8 +303,7 @@ impl CacheLayer for RedisCache {
     async fn incr(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, CacheError> {
         let cache_key = self.make_key(key);
 
-        let mut conn = self.pool.get().await
-            .map_err(|e| CacheError::Pool(format!("Failed to get connection: {}", e)))?;
+        let mut conn = self.connection().await?;
 
         // Increment and set expiry if it's a new key
         let result: i64 = conn.incr(&cache_key, delta).await?;
@@ -249,8 +316,55 @@ impl CacheLayer for RedisCache {
 
         Ok(result)
     }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, CacheError> {
+        let cache_key = self.make_key(key);
+
+        let mut conn = self.connection().await?;
+
+        // Nil reply when the key already exists
+        let set: Option<String> = redis::cmd("SET")
+            .arg(&cache_key)
+            .arg(value)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async(&mut *conn)
+            .await?;
+        if set.is_some() {
+            record(CacheOp::Set, key, CacheOutcome::Write);
+        }
+
+        Ok(set.is_some())
+    }
+
+    async fn delete_if_eq(&self, key: &str, value: &str) -> Result<bool, CacheError> {
+        let cache_key = self.make_key(key);
+
+        let mut conn = self.connection().await?;
+
+        let deleted: i64 = redis::Script::new(DELETE_IF_EQ_SCRIPT)
+            .key(&cache_key)
+            .arg(value)
+            .invoke_async(&mut *conn)
+            .await?;
+        if deleted > 0 {
+            record(CacheOp::Delete, key, CacheOutcome::Write);
+        }
+
+        Ok(deleted > 0)
+    }
 }
 
+/// Compare-and-delete: GET and DEL run as one script, so a key that changed
+/// hands in between is never deleted
+const DELETE_IF_EQ_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
 /// Cached result wrapper to track cache metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedResult<T> {
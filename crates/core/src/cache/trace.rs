@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// Cache events kept per request; older events are dropped
+const MAX_EVENTS: usize = 100;
+
+tokio::task_local! {
+    static CACHE_TRACE: Arc<CacheTrace>;
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheOp {
+    Get,
+    Set,
+    Delete,
+    Invalidate,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheOutcome {
+    Hit,
+    Miss,
+    Write,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct CacheEvent {
+    pub op: CacheOp,
+    pub key: String,
+    pub outcome: CacheOutcome,
+}
+
+impl CacheOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheOp::Get => "get",
+            CacheOp::Set => "set",
+            CacheOp::Delete => "delete",
+            CacheOp::Invalidate => "invalidate",
+        }
+    }
+}
+
+impl CacheOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheOutcome::Hit => "hit",
+            CacheOutcome::Miss => "miss",
+            CacheOutcome::Write => "write",
+            CacheOutcome::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for CacheEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.op.as_str(), self.key, self.outcome.as_str())
+    }
+}
+
+/// Cache keys consulted while handling one request, tagged with its correlation id
+#[derive(Debug)]
+pub struct CacheTrace {
+    correlation_id: String,
+    events: Mutex<VecDeque<CacheEvent>>,
+}
+
+impl CacheTrace {
+    pub fn new(correlation_id: impl Into<String>) -> Arc<Self> {
+        Arc::new(Self {
+            correlation_id: correlation_id.into(),
+            events: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn correlation_id(&self) -> &str {
+        &self.correlation_id
+    }
+
+    pub fn events(&self) -> Vec<CacheEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The most recent `n` events, oldest first
+    pub fn last(&self, n: usize) -> Vec<CacheEvent> {
+        let events = self.events.lock().unwrap();
+        events.iter().skip(events.len().saturating_sub(n)).cloned().collect()
+    }
+
+    fn push(&self, event: CacheEvent) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+}
+
+/// Run `future` with `trace` collecting every cache access made on this task
+pub async fn with_trace<F: Future>(trace: Arc<CacheTrace>, future: F) -> F::Output {
+    CACHE_TRACE.scope(trace, future).await
+}
+
+/// Record a cache access for the current request and count it in the
+/// process's metrics. The request trace is skipped outside `with_trace`;
+/// with the `cache-debug` feature each event is also logged at trace level.
+pub fn record(op: CacheOp, key: &str, outcome: CacheOutcome) {
+    crate::metrics::global().record_cache(op, key, outcome);
+    let _ = CACHE_TRACE.try_with(|trace| {
+        let event = CacheEvent { op, key: key.to_string(), outcome };
+
+        #[cfg(feature = "cache-debug")]
+        tracing::trace!(
+            correlation_id = %trace.correlation_id,
+            op = event.op.as_str(),
+            key = %event.key,
+            outcome = event.outcome.as_str(),
+            "cache event"
+        );
+
+        trace.push(event);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CacheLayer, CacheKeys, MemoryCache};
+
+    #[tokio::test]
+    async fn test_miss_then_set_is_recorded_with_key() {
+        let cache = MemoryCache::new();
+        let trace = CacheTrace::new("req-42");
+        let key = CacheKeys::dno_by_slug("netze-bw");
+
+        with_trace(trace.clone(), async {
+            let cached: Option<String> = cache.get(&key).await.unwrap();
+            assert!(cached.is_none());
+            cache.set(&key, &"Netze BW".to_string(), None).await.unwrap();
+        })
+        .await;
+
+        assert_eq!(trace.correlation_id(), "req-42");
+        assert_eq!(
+            trace.events(),
+            vec![
+                CacheEvent { op: CacheOp::Get, key: key.clone(), outcome: CacheOutcome::Miss },
+                CacheEvent { op: CacheOp::Set, key: key.clone(), outcome: CacheOutcome::Write },
+            ]
+        );
+        assert_eq!(trace.last(1)[0].to_string(), format!("set {} write", key));
+    }
+
+    #[tokio::test]
+    async fn test_nothing_recorded_outside_a_request() {
+        let cache = MemoryCache::new();
+        let trace = CacheTrace::new("req-43");
+        let _: Option<String> = cache.get("reference:dnos:all").await.unwrap();
+        assert!(trace.events().is_empty());
+    }
+}
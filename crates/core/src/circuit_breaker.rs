@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Where a `CircuitBreaker` stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through; consecutive failures are counted
+    Closed,
+    /// Calls fail fast until the cooldown is over
+    Open,
+    /// The cooldown is over; the next call is a probe and decides whether
+    /// the circuit closes or opens again
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit fails fast before it lets a probe through
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self { failure_threshold: 5, cooldown: Duration::from_secs(30) }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// `<PREFIX>_BREAKER_FAILURES` and `<PREFIX>_BREAKER_COOLDOWN_SECS`, e.g.
+    /// `OLLAMA_BREAKER_FAILURES`; defaults for what is unset or invalid
+    pub fn from_env(prefix: &str) -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(format!("{}_{}", prefix, name)).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            failure_threshold: var("BREAKER_FAILURES")
+                .filter(|&n| n > 0)
+                .map_or(defaults.failure_threshold, |n| n as u32),
+            cooldown: var("BREAKER_COOLDOWN_SECS").map_or(defaults.cooldown, Duration::from_secs),
+        }
+    }
+}
+
+/// A call refused because the service's circuit is open
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{service} is unavailable (circuit open, next attempt in {}s)", .retry_in.as_secs())]
+pub struct CircuitOpen {
+    pub service: &'static str,
+    /// Time left until a probe is let through
+    pub retry_in: Duration,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// A half-open probe is running; other calls fail fast meanwhile
+    probing: bool,
+}
+
+/// Fails calls to an external service fast while it keeps failing, instead
+/// of letting every request wait for its timeout. Clones share one circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    service: &'static str,
+    config: CircuitBreakerConfig,
+    circuit: Arc<Mutex<Circuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(service: &'static str, config: CircuitBreakerConfig) -> Self {
+        let circuit = Circuit { state: CircuitState::Closed, consecutive_failures: 0, opened_at: None, probing: false };
+        Self { service, config, circuit: Arc::new(Mutex::new(circuit)) }
+    }
+
+    pub fn service(&self) -> &'static str {
+        self.service
+    }
+
+    /// The current state; an open circuit whose cooldown is over reports `HalfOpen`
+    pub fn state(&self) -> CircuitState {
+        let mut circuit = self.circuit.lock().unwrap();
+        self.cool_down(&mut circuit);
+        circuit.state
+    }
+
+    /// Let a call through, or refuse it while the circuit is open or a probe
+    /// is running. Settle the permit with the call's outcome.
+    pub fn try_acquire(&self) -> Result<CircuitPermit<'_>, CircuitOpen> {
+        let mut circuit = self.circuit.lock().unwrap();
+        self.cool_down(&mut circuit);
+        let probe = match circuit.state {
+            CircuitState::Closed => false,
+            CircuitState::HalfOpen if !circuit.probing => {
+                circuit.probing = true;
+                true
+            }
+            CircuitState::HalfOpen | CircuitState::Open => {
+                let retry_in = circuit
+                    .opened_at
+                    .map_or(Duration::ZERO, |opened| self.config.cooldown.saturating_sub(opened.elapsed()));
+                return Err(CircuitOpen { service: self.service, retry_in });
+            }
+        };
+        Ok(CircuitPermit { breaker: self, probe, settled: false })
+    }
+
+    /// Run `call` through the breaker; its `Err` counts as a failure
+    pub async fn call<T, E>(&self, call: impl Future<Output = Result<T, E>>) -> Result<Result<T, E>, CircuitOpen> {
+        let permit = self.try_acquire()?;
+        let result = call.await;
+        permit.settle(result.is_ok());
+        Ok(result)
+    }
+
+    fn cool_down(&self, circuit: &mut Circuit) {
+        if circuit.state == CircuitState::Open
+            && circuit.opened_at.is_some_and(|opened| opened.elapsed() >= self.config.cooldown)
+        {
+            circuit.state = CircuitState::HalfOpen;
+            circuit.probing = false;
+        }
+    }
+
+    fn record(&self, success: bool, probe: bool) {
+        let mut circuit = self.circuit.lock().unwrap();
+        if probe {
+            circuit.probing = false;
+        }
+        if success {
+            if circuit.state != CircuitState::Closed {
+                info!("{} recovered, circuit closed", self.service);
+            }
+            circuit.state = CircuitState::Closed;
+            circuit.consecutive_failures = 0;
+            circuit.opened_at = None;
+            return;
+        }
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        let trips = probe || circuit.consecutive_failures >= self.config.failure_threshold;
+        if trips && circuit.state != CircuitState::Open {
+            warn!(
+                "{} failed {} times in a row, circuit open for {}s",
+                self.service,
+                circuit.consecutive_failures,
+                self.config.cooldown.as_secs()
+            );
+            circuit.state = CircuitState::Open;
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// A call the breaker let through. Dropped unsettled, e.g. when the call
+/// was cancelled, it counts as neither success nor failure.
+pub struct CircuitPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    settled: bool,
+}
+
+impl CircuitPermit<'_> {
+    pub fn settle(mut self, success: bool) {
+        self.settled = true;
+        self.breaker.record(success, self.probe);
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if !self.settled && self.probe {
+            self.breaker.circuit.lock().unwrap().probing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// A service that fails until it is told to recover; counts the calls it got
+    #[derive(Default)]
+    struct Flaky {
+        healthy: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    impl Flaky {
+        async fn call(&self) -> Result<&'static str, &'static str> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.healthy.load(Ordering::SeqCst) {
+                Ok("ok")
+            } else {
+                Err("connection refused")
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_probes_and_closes() {
+        let breaker = CircuitBreaker::new(
+            "ollama",
+            CircuitBreakerConfig { failure_threshold: 3, cooldown: Duration::from_millis(50) },
+        );
+        let flaky = Flaky::default();
+
+        // Closed: failures go through until the threshold
+        for _ in 0..3 {
+            assert_eq!(breaker.state(), CircuitState::Closed);
+            assert_eq!(breaker.call(flaky.call()).await.unwrap(), Err("connection refused"));
+        }
+
+        // Open: fail fast without calling the service
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let refused = breaker.call(flaky.call()).await.unwrap_err();
+        assert_eq!(refused.service, "ollama");
+        assert!(refused.retry_in <= Duration::from_millis(50));
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+
+        // Half-open: one probe at a time; a failed probe opens the circuit again
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        let probe = breaker.try_acquire().unwrap();
+        assert!(breaker.try_acquire().is_err());
+        probe.settle(flaky.call().await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.call(flaky.call()).await.is_err());
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 4);
+
+        // A successful probe closes it and resets the count
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        flaky.healthy.store(true, Ordering::SeqCst);
+        assert_eq!(breaker.call(flaky.call()).await.unwrap(), Ok("ok"));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        flaky.healthy.store(false, Ordering::SeqCst);
+        assert!(breaker.call(flaky.call()).await.unwrap().is_err());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_probe_frees_the_half_open_slot() {
+        let breaker = CircuitBreaker::new(
+            "searxng",
+            CircuitBreakerConfig { failure_threshold: 1, cooldown: Duration::from_millis(10) },
+        );
+        breaker.try_acquire().unwrap().settle(false);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        drop(breaker.try_acquire().unwrap());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire().is_ok());
+    }
+}
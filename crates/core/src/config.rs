@@ -73,6 +73,36 @@ pub struct CrawlerConfig {
     pub max_retries: u32,
 }
 
+impl SearxngConfig {
+    /// Read `SEARXNG_URL` and `SEARXNG_TIMEOUT` (seconds)
+    pub fn from_env() -> Self {
+        Self {
+            url: env::var("SEARXNG_URL")
+                .unwrap_or_else(|_| "http://localhost:8888".to_string()),
+            timeout: env::var("SEARXNG_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+        }
+    }
+}
+
+impl OllamaConfig {
+    /// Read `OLLAMA_URL`, `OLLAMA_MODEL` and `OLLAMA_TIMEOUT` (seconds)
+    pub fn from_env() -> Self {
+        Self {
+            url: env::var("OLLAMA_URL")
+                .unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            model: env::var("OLLAMA_MODEL")
+                .unwrap_or_else(|_| "llama3".to_string()),
+            timeout: env::var("OLLAMA_TIMEOUT")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+        }
+    }
+}
+
 impl Config {
     pub fn from_env() -> Result<Self, crate::AppError> {
         Ok(Self {
@@ -149,24 +179,8 @@ impl Config {
                     .unwrap_or(604800),
             },
             external: ExternalConfig {
-                searxng: SearxngConfig {
-                    url: env::var("SEARXNG_URL")
-                        .unwrap_or_else(|_| "http://localhost:8888".to_string()),
-                    timeout: env::var("SEARXNG_TIMEOUT")
-                        .unwrap_or_else(|_| "30".to_string())
-                        .parse()
-                        .unwrap_or(30),
-                },
-                ollama: OllamaConfig {
-                    url: env::var("OLLAMA_URL")
-                        .unwrap_or_else(|_| "http://localhost:11434".to_string()),
-                    model: env::var("OLLAMA_MODEL")
-                        .unwrap_or_else(|_| "llama3".to_string()),
-                    timeout: env::var("OLLAMA_TIMEOUT")
-                        .unwrap_or_else(|_| "60".to_string())
-                        .parse()
-                        .unwrap_or(60),
-                },
+                searxng: SearxngConfig::from_env(),
+                ollama: OllamaConfig::from_env(),
             },
             crawler: CrawlerConfig {
                 max_concurrent: env::var("CRAWLER_MAX_CONCURRENT")
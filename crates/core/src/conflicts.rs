@@ -0,0 +1,449 @@
+use crate::models::{CreateNetzentgelteData, NetzentgelteData};
+use crate::review::apply_correction;
+use crate::verification::STATUS_FLAGGED;
+use crate::webhooks::WebhookEvent;
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+use tracing::warn;
+use uuid::Uuid;
+
+pub const CONFLICT_OPEN: &str = "open";
+pub const CONFLICT_RESOLVED: &str = "resolved";
+
+/// Where a candidate value came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ConflictSource {
+    pub source_id: Option<Uuid>,
+    pub source_url: Option<String>,
+    pub extraction_method: Option<String>,
+    pub confidence: Option<f64>,
+}
+
+/// One of the disagreeing values of a conflict
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConflictCandidate {
+    pub value: Value,
+    #[serde(flatten)]
+    pub source: ConflictSource,
+    pub observed_at: DateTime<Utc>,
+}
+
+/// Two or more sources disagreeing on one field of a data entry. While a
+/// conflict is open the entry keeps its first value and is flagged.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DataConflict {
+    pub id: Uuid,
+    /// `netzentgelte` or `hlzf`
+    pub entry_type: String,
+    pub entry_id: Uuid,
+    pub dno_id: Uuid,
+    pub year: i32,
+    pub field: String,
+    pub candidates: Json<Vec<ConflictCandidate>>,
+    /// `open` or `resolved`
+    pub status: String,
+    pub resolved_value: Option<Value>,
+    /// Index into `candidates` of the value kept; `None` for a value entered by the admin
+    pub resolved_candidate: Option<i32>,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolution_notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An admin's decision on a conflict: keep one of the candidates, or enter a corrected value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConflictResolution {
+    pub candidate: Option<usize>,
+    pub value: Option<Value>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConflictFilter {
+    /// `open` (default), `resolved` or `all`
+    pub status: Option<String>,
+    pub dno_id: Option<Uuid>,
+    pub year: Option<i32>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+fn candidate(value: Decimal, source: &ConflictSource) -> ConflictCandidate {
+    ConflictCandidate {
+        value: value.to_f64().map(Value::from).unwrap_or_default(),
+        source: source.clone(),
+        observed_at: Utc::now(),
+    }
+}
+
+/// Store a Netzentgelte extraction without silently overwriting what another
+/// source reported. Values that differ from the stored ones are kept as they
+/// were and recorded as (or added to) an open conflict, and the entry is
+/// flagged unless an admin has already decided on it. Missing values are
+/// filled in; values absent from the new extraction are left untouched.
+pub async fn upsert_netzentgelte_with_conflicts(
+    pool: &PgPool,
+    mut data: CreateNetzentgelteData,
+    source: &ConflictSource,
+) -> Result<(NetzentgelteData, Vec<DataConflict>), AppError> {
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+
+    let existing = crate::database::lock_current_netzentgelte(&mut tx, data.dno_id, data.year, &data.voltage_level).await?;
+
+    let Some(existing) = existing else {
+        let entry = crate::database::upsert_netzentgelte_data(&mut tx, data).await?;
+        tx.commit().await.map_err(AppError::Database)?;
+        return Ok((entry, Vec::new()));
+    };
+
+    let mut disagreements = Vec::new();
+    for (field, current, incoming) in [
+        ("leistung", existing.leistung, &mut data.leistung),
+        ("arbeit", existing.arbeit, &mut data.arbeit),
+        ("leistung_unter_2500h", existing.leistung_unter_2500h, &mut data.leistung_unter_2500h),
+        ("arbeit_unter_2500h", existing.arbeit_unter_2500h, &mut data.arbeit_unter_2500h),
+    ] {
+        match (current, *incoming) {
+            (Some(current), Some(new)) if current != new => {
+                disagreements.push((field, current, new));
+                *incoming = Some(current);
+            }
+            (current, new) => *incoming = new.or(current),
+        }
+    }
+    if !disagreements.is_empty() {
+        // The raw strings must keep describing the stored values
+        data.raw_values = existing.raw_values.clone();
+    }
+
+    let previous_source = current_source(&mut tx, existing.dno_id, existing.year).await?;
+    let mut entry = crate::database::upsert_netzentgelte_data(&mut tx, data).await?;
+
+    let mut conflicts = Vec::with_capacity(disagreements.len());
+    for (field, current, new) in disagreements {
+        let candidates = [candidate(current, &previous_source), candidate(new, source)];
+        conflicts.push(open_conflict(&mut tx, "netzentgelte", &entry, field, candidates).await?);
+    }
+
+    if !conflicts.is_empty() {
+        // A disagreeing crawl does not confirm the stored values
+        let status = match entry.verified_by {
+            Some(_) => entry.verification_status.clone(),
+            None => Some(STATUS_FLAGGED.to_string()),
+        };
+        sqlx::query("UPDATE netzentgelte_data SET verification_status = $1, last_verified_at = $2 WHERE id = $3")
+            .bind(&status)
+            .bind(existing.last_verified_at)
+            .bind(entry.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        entry.verification_status = status;
+        entry.last_verified_at = existing.last_verified_at;
+    }
+
+    tx.commit().await.map_err(AppError::Database)?;
+    if !conflicts.is_empty() {
+        warn!("{} conflicting values for netzentgelte entry {}", conflicts.len(), entry.id);
+        if entry.verified_by.is_none() {
+            crate::webhooks::global().notify(WebhookEvent::EntriesFlagged {
+                entry_type: "netzentgelte".to_string(),
+                dno_id: entry.dno_id,
+                entry_ids: vec![entry.id],
+                reason: "conflicting values".to_string(),
+            });
+        }
+    }
+    Ok((entry, conflicts))
+}
+
+/// Source the stored values were taken from, as far as `data_sources` knows
+async fn current_source(conn: &mut PgConnection, dno_id: Uuid, year: i32) -> Result<ConflictSource, AppError> {
+    let source = sqlx::query_as::<_, ConflictSource>(
+        r#"
+        SELECT id AS source_id, source_url, extraction_method, confidence::float8 AS confidence
+        FROM data_sources
+        WHERE dno_id = $1 AND year = $2 AND data_type = 'netzentgelte'
+        ORDER BY extracted_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(dno_id)
+    .bind(year)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(source.unwrap_or_default())
+}
+
+/// Open a conflict for a field, or add the new candidate to the one already open
+async fn open_conflict(
+    conn: &mut PgConnection,
+    entry_type: &str,
+    entry: &NetzentgelteData,
+    field: &str,
+    [stored, incoming]: [ConflictCandidate; 2],
+) -> Result<DataConflict, AppError> {
+    let incoming_value = serde_json::json!([{ "value": incoming.value }]);
+    let conflict = sqlx::query_as::<_, DataConflict>(
+        r#"
+        INSERT INTO data_conflicts (entry_type, entry_id, dno_id, year, field, candidates)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (entry_id, field) WHERE status = 'open' DO UPDATE SET
+            candidates = CASE
+                WHEN data_conflicts.candidates @> $7 THEN data_conflicts.candidates
+                ELSE data_conflicts.candidates || $8
+            END
+        RETURNING *
+        "#,
+    )
+    .bind(entry_type)
+    .bind(entry.id)
+    .bind(entry.dno_id)
+    .bind(entry.year)
+    .bind(field)
+    .bind(Json([&stored, &incoming]))
+    .bind(incoming_value)
+    .bind(Json([&incoming]))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(conflict)
+}
+
+/// Conflicts matching the filter, newest first
+pub async fn list_conflicts(pool: &PgPool, filter: &ConflictFilter) -> Result<Vec<DataConflict>, AppError> {
+    let mut query = sqlx::QueryBuilder::new("SELECT * FROM data_conflicts WHERE TRUE");
+    match filter.status.as_deref().unwrap_or(CONFLICT_OPEN) {
+        "all" => {}
+        status @ (CONFLICT_OPEN | CONFLICT_RESOLVED) => {
+            query.push(" AND status = ").push_bind(status.to_string());
+        }
+        other => return Err(AppError::BadRequest(format!("Unknown conflict status '{}'", other))),
+    }
+    if let Some(dno_id) = filter.dno_id {
+        query.push(" AND dno_id = ").push_bind(dno_id);
+    }
+    if let Some(year) = filter.year {
+        query.push(" AND year = ").push_bind(year);
+    }
+    query.push(" ORDER BY created_at DESC LIMIT ").push_bind(filter.limit.unwrap_or(50).clamp(1, 500));
+    query.push(" OFFSET ").push_bind(filter.offset.unwrap_or(0).max(0));
+
+    query
+        .build_query_as::<DataConflict>()
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)
+}
+
+/// Apply an admin's decision: the chosen value is written to the entry as a
+/// correction (recorded in `data_entry_history`), and the conflict keeps which
+/// candidate won, who decided and why. The entry stays flagged while other
+/// conflicts on it are still open. Returns `None` if the conflict does not exist.
+pub async fn resolve_conflict(
+    pool: &PgPool,
+    conflict_id: Uuid,
+    resolution: &ConflictResolution,
+    admin: Uuid,
+) -> Result<Option<DataConflict>, AppError> {
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+
+    let conflict = sqlx::query_as::<_, DataConflict>("SELECT * FROM data_conflicts WHERE id = $1 FOR UPDATE")
+        .bind(conflict_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    let Some(conflict) = conflict else {
+        return Ok(None);
+    };
+    if conflict.status != CONFLICT_OPEN {
+        return Err(AppError::BadRequest("Conflict is already resolved".to_string()));
+    }
+
+    let value = match (resolution.candidate, &resolution.value) {
+        (Some(index), None) => conflict
+            .candidates
+            .get(index)
+            .map(|candidate| candidate.value.clone())
+            .ok_or_else(|| AppError::BadRequest(format!("Conflict has no candidate {}", index)))?,
+        (None, Some(value)) => value.clone(),
+        _ => {
+            return Err(AppError::BadRequest(
+                "Provide either a candidate index or a corrected value".to_string(),
+            ))
+        }
+    };
+
+    let mut changes = serde_json::Map::new();
+    changes.insert(conflict.field.clone(), value.clone());
+    apply_correction(&mut tx, conflict.entry_id, &changes, admin)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Data entry of this conflict no longer exists".to_string()))?;
+
+    let resolved = sqlx::query_as::<_, DataConflict>(
+        r#"
+        UPDATE data_conflicts
+        SET status = $2, resolved_value = $3, resolved_candidate = $4, resolved_by = $5,
+            resolved_at = CURRENT_TIMESTAMP, resolution_notes = $6
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(conflict_id)
+    .bind(CONFLICT_RESOLVED)
+    .bind(&value)
+    .bind(resolution.candidate.map(|index| index as i32))
+    .bind(admin)
+    .bind(&resolution.notes)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+
+    let table = if conflict.entry_type == "hlzf" { "hlzf_data" } else { "netzentgelte_data" };
+    sqlx::query(&format!(
+        r#"
+        UPDATE {table} SET verification_status = $1
+        WHERE id = $2 AND EXISTS (SELECT 1 FROM data_conflicts WHERE entry_id = $2 AND status = 'open')
+        "#
+    ))
+    .bind(STATUS_FLAGGED)
+    .bind(conflict.entry_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(Some(resolved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::{get_data_timeline, TimelineEventKind};
+    use std::str::FromStr;
+
+    const DNO: &str = "00000000-0000-0000-0000-000000000001";
+
+    async fn seed(pool: &PgPool) {
+        crate::test_schema::create(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (id, slug, name) VALUES ('00000000-0000-0000-0000-000000000001', 'netze-bw', 'Netze BW');
+            INSERT INTO users (id, email, password_hash, name, role) VALUES
+                ('00000000-0000-0000-0000-0000000000aa', 'admin@example.com', 'x', 'Admin', 'admin');
+            INSERT INTO data_sources (dno_id, year, data_type, source_type, source_url, extracted_at, extraction_method, confidence) VALUES
+                ('00000000-0000-0000-0000-000000000001', 2024, 'netzentgelte', 'table', 'https://netze.example/preise.html', CURRENT_TIMESTAMP, 'html_table', 0.90);
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn extraction(leistung: &str, arbeit: &str) -> CreateNetzentgelteData {
+        CreateNetzentgelteData {
+            dno_id: Uuid::parse_str(DNO).unwrap(),
+            year: 2024,
+            voltage_level: "ms".to_string(),
+            leistung: Some(Decimal::from_str(leistung).unwrap()),
+            arbeit: Some(Decimal::from_str(arbeit).unwrap()),
+            leistung_unter_2500h: None,
+            arbeit_unter_2500h: None,
+            raw_values: None,
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_differing_extractions_create_conflict(pool: PgPool) {
+        seed(&pool).await;
+        let ocr = ConflictSource {
+            source_url: Some("https://netze.example/preisblatt.pdf".to_string()),
+            extraction_method: Some("ocr".to_string()),
+            confidence: Some(0.7),
+            ..Default::default()
+        };
+
+        let (_, conflicts) = upsert_netzentgelte_with_conflicts(&pool, extraction("58.21", "1.26"), &ConflictSource::default())
+            .await
+            .unwrap();
+        assert!(conflicts.is_empty());
+
+        // Same Arbeitspreis, different Leistungspreis
+        let (entry, conflicts) = upsert_netzentgelte_with_conflicts(&pool, extraction("85.21", "1.26"), &ocr).await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!((conflict.field.as_str(), conflict.status.as_str()), ("leistung", CONFLICT_OPEN));
+        assert_eq!(conflict.candidates[0].value, serde_json::json!(58.21));
+        assert_eq!(conflict.candidates[0].source.extraction_method.as_deref(), Some("html_table"));
+        assert_eq!(conflict.candidates[1].value, serde_json::json!(85.21));
+        assert_eq!(conflict.candidates[1].source.confidence, Some(0.7));
+        assert_eq!(entry.leistung, Some(Decimal::from_str("58.21").unwrap()));
+        assert_eq!(entry.verification_status.as_deref(), Some(STATUS_FLAGGED));
+
+        // Seeing the same disagreement again does not duplicate it
+        let (_, again) = upsert_netzentgelte_with_conflicts(&pool, extraction("85.21", "1.26"), &ocr).await.unwrap();
+        assert_eq!(again[0].id, conflict.id);
+        assert_eq!(again[0].candidates.len(), 2);
+
+        let admin = Uuid::from_u128(0xaa);
+        let resolution = ConflictResolution { candidate: Some(1), notes: Some("Preisblatt is authoritative".to_string()), ..Default::default() };
+        let resolved = resolve_conflict(&pool, conflict.id, &resolution, admin).await.unwrap().unwrap();
+        assert_eq!(resolved.status, CONFLICT_RESOLVED);
+        assert_eq!(resolved.resolved_candidate, Some(1));
+
+        let (leistung, status): (Decimal, String) =
+            sqlx::query_as("SELECT leistung, verification_status FROM netzentgelte_data WHERE id = $1")
+                .bind(entry.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(leistung, Decimal::from_str("85.21").unwrap());
+        assert_eq!(status, "verified");
+        assert!(list_conflicts(&pool, &ConflictFilter::default()).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_reconfirming_updates_last_verified_only(pool: PgPool) {
+        seed(&pool).await;
+        let source = ConflictSource::default();
+        let (entry, _) = upsert_netzentgelte_with_conflicts(&pool, extraction("58.21", "1.26"), &source).await.unwrap();
+        let (first_seen, last_verified): (DateTime<Utc>, DateTime<Utc>) = sqlx::query_as(
+            r#"
+            UPDATE netzentgelte_data
+            SET first_seen_at = first_seen_at - INTERVAL '30 days', last_verified_at = last_verified_at - INTERVAL '30 days'
+            WHERE id = $1
+            RETURNING first_seen_at, last_verified_at
+            "#,
+        )
+        .bind(entry.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let (confirmed, _) = upsert_netzentgelte_with_conflicts(&pool, extraction("58.21", "1.26"), &source).await.unwrap();
+        assert_eq!(confirmed.first_seen_at, first_seen);
+        assert!(confirmed.last_verified_at > last_verified);
+
+        // A crawl that disagrees does not count as a confirmation
+        let (disputed, _) = upsert_netzentgelte_with_conflicts(&pool, extraction("85.21", "1.26"), &source).await.unwrap();
+        assert_eq!(disputed.first_seen_at, first_seen);
+        assert_eq!(disputed.last_verified_at, confirmed.last_verified_at);
+
+        let timeline = get_data_timeline(&pool, entry.id).await.unwrap().unwrap();
+        let kinds: Vec<_> = timeline.events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, [TimelineEventKind::FirstSeen, TimelineEventKind::LastVerified]);
+        assert_eq!(timeline.first_seen_at, first_seen);
+    }
+}
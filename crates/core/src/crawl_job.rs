@@ -0,0 +1,441 @@
+use crate::crawl_plan::CrawlContext;
+use crate::live::{CrawlSessionStatus, LiveCrawlSession, LiveSessions, LogLevel};
+use crate::manual_review::{enqueue_manual_review, ManualReviewRequest};
+use crate::models::JobStatus;
+use crate::AppError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// What one crawl job (a DNO, year and data type) did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrawlJobSummary {
+    /// How the crawl ended, e.g. `complete` or `timed_out`
+    pub completion: String,
+    pub pages_processed: u32,
+    pub documents_found: u32,
+    pub failed_urls: u32,
+    /// URLs given up on until someone looks at them; queued in `manual_review_queue`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub manual_review: Vec<ManualReviewRequest>,
+}
+
+/// Runs the crawl of one job; the crawler crate provides the real one
+#[async_trait]
+pub trait CrawlRunner: Send + Sync {
+    /// Crawl `context`, logging progress to `session`
+    async fn run(&self, context: CrawlContext, session: &LiveCrawlSession) -> Result<CrawlJobSummary, AppError>;
+}
+
+/// A crawl started through `POST /api/v1/crawl`. It runs one crawl job per
+/// DNO, year and data type of the request.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CrawlSessionRecord {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub mode: String,
+    pub status: JobStatus,
+    pub error_message: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One job of a session as stored in `crawl_jobs`
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CrawlJobReport {
+    pub id: Uuid,
+    pub dno_id: Uuid,
+    pub year: i32,
+    pub data_type: String,
+    pub status: JobStatus,
+    pub error_message: Option<String>,
+    pub result: Option<Json<CrawlJobSummary>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Jobs of a session by status
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrawlProgress {
+    pub total: usize,
+    pub pending: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+/// Totals over the finished jobs of a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrawlTotals {
+    pub pages_processed: u32,
+    pub documents_found: u32,
+    pub failed_urls: u32,
+}
+
+/// A session with its jobs, as `GET /api/v1/crawl/{session_id}` returns it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlSessionReport {
+    #[serde(flatten)]
+    pub session: CrawlSessionRecord,
+    pub progress: CrawlProgress,
+    pub summary: CrawlTotals,
+    pub jobs: Vec<CrawlJobReport>,
+}
+
+impl CrawlSessionReport {
+    fn new(session: CrawlSessionRecord, jobs: Vec<CrawlJobReport>) -> Self {
+        let mut progress = CrawlProgress { total: jobs.len(), ..Default::default() };
+        let mut summary = CrawlTotals::default();
+        for job in &jobs {
+            match job.status {
+                JobStatus::Pending => progress.pending += 1,
+                JobStatus::Running => progress.running += 1,
+                JobStatus::Completed => progress.completed += 1,
+                JobStatus::Failed | JobStatus::Cancelled => progress.failed += 1,
+            }
+            if let Some(Json(result)) = &job.result {
+                summary.pages_processed += result.pages_processed;
+                summary.documents_found += result.documents_found;
+                summary.failed_urls += result.failed_urls;
+            }
+        }
+        Self { session, progress, summary, jobs }
+    }
+}
+
+const SESSION_COLUMNS: &str =
+    "id, user_id, mode, status, error_message, started_at, completed_at, created_at, updated_at";
+
+/// Starts crawl sessions in the background and reports on them. Sessions and
+/// their jobs are stored in the database, so their status outlives the process.
+#[derive(Clone)]
+pub struct CrawlJobs {
+    pool: PgPool,
+    runner: Arc<dyn CrawlRunner>,
+    live: LiveSessions,
+}
+
+impl CrawlJobs {
+    pub fn new(pool: PgPool, runner: Arc<dyn CrawlRunner>, live: LiveSessions) -> Self {
+        Self { pool, runner, live }
+    }
+
+    /// Store a pending session with a job per context and return it. The jobs
+    /// then run one after another on a background task, watchable through
+    /// `LiveSessions` under the session id.
+    pub async fn start(
+        &self,
+        user_id: Option<Uuid>,
+        mode: &str,
+        contexts: Vec<CrawlContext>,
+    ) -> Result<CrawlSessionRecord, AppError> {
+        let session_id = Uuid::new_v4();
+        let mut tx = self.pool.begin().await?;
+        let session: CrawlSessionRecord = sqlx::query_as(&format!(
+            "INSERT INTO crawl_sessions (id, user_id, mode) VALUES ($1, $2, $3) RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(session_id)
+        .bind(user_id)
+        .bind(mode)
+        .fetch_one(&mut *tx)
+        .await?;
+        let mut jobs = Vec::with_capacity(contexts.len());
+        for context in contexts {
+            let job_id: Uuid = sqlx::query_scalar(
+                "INSERT INTO crawl_jobs (session_id, user_id, dno_id, year, data_type)
+                 VALUES ($1, $2, $3, $4, $5::data_type) RETURNING id",
+            )
+            .bind(session_id)
+            .bind(user_id)
+            .bind(context.dno.id)
+            .bind(context.year)
+            .bind(&context.data_type)
+            .fetch_one(&mut *tx)
+            .await?;
+            jobs.push((job_id, context));
+        }
+        tx.commit().await?;
+
+        let live = self.live.open(session_id);
+        let this = self.clone();
+        tokio::spawn(async move { this.run_session(session_id, jobs, live).await });
+        Ok(session)
+    }
+
+    async fn run_session(&self, session_id: Uuid, jobs: Vec<(Uuid, CrawlContext)>, live: LiveCrawlSession) {
+        if let Err(e) = self.mark_session_running(session_id).await {
+            warn!("Failed to mark crawl session {} running: {}", session_id, e);
+        }
+        let mut failed = 0;
+        for (job_id, context) in &jobs {
+            let label = format!("{} {} {}", context.dno.slug, context.year, context.data_type);
+            live.log(LogLevel::Info, format!("Starting crawl of {}", label));
+            if let Err(e) = self.mark_job_running(*job_id).await {
+                warn!("Failed to mark crawl job {} running: {}", job_id, e);
+            }
+            let outcome = self.runner.run(context.clone(), &live).await;
+            match &outcome {
+                Ok(summary) if !summary.manual_review.is_empty() => {
+                    let queued =
+                        enqueue_manual_review(&self.pool, context, Some(session_id), Some(*job_id), &summary.manual_review);
+                    match queued.await {
+                        Ok(items) => live.log(LogLevel::Warn, format!("{} URLs of {} need manual review", items.len(), label)),
+                        Err(e) => warn!("Failed to queue URLs of crawl job {} for manual review: {}", job_id, e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    failed += 1;
+                    live.log(LogLevel::Error, format!("Crawl of {} failed: {}", label, e));
+                }
+            }
+            if let Err(e) = self.finish_job(*job_id, outcome).await {
+                warn!("Failed to store the outcome of crawl job {}: {}", job_id, e);
+            }
+        }
+
+        // A session fails only if none of its jobs got through
+        let (status, error) = if failed > 0 && failed == jobs.len() {
+            (JobStatus::Failed, Some(format!("all {} crawl jobs failed", failed)))
+        } else {
+            (JobStatus::Completed, None)
+        };
+        if let Err(e) = self.finish_session(session_id, &status, error.as_deref()).await {
+            warn!("Failed to store the outcome of crawl session {}: {}", session_id, e);
+        }
+        info!("Crawl session {} finished {:?} ({} of {} jobs failed)", session_id, status, failed, jobs.len());
+        live.finish(match status {
+            JobStatus::Completed => CrawlSessionStatus::Completed,
+            _ => CrawlSessionStatus::Failed,
+        });
+    }
+
+    async fn mark_session_running(&self, session_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE crawl_sessions SET status = 'running', started_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn mark_job_running(&self, job_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE crawl_jobs SET status = 'running', started_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(job_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn finish_job(&self, job_id: Uuid, outcome: Result<CrawlJobSummary, AppError>) -> Result<(), AppError> {
+        let (status, result, error) = match outcome {
+            Ok(summary) => (JobStatus::Completed, Some(Json(summary)), None),
+            Err(e) => (JobStatus::Failed, None, Some(e.to_string())),
+        };
+        sqlx::query(
+            "UPDATE crawl_jobs
+             SET status = $2, progress = 100, result = $3, error_message = $4, completed_at = CURRENT_TIMESTAMP
+             WHERE id = $1",
+        )
+        .bind(job_id)
+        .bind(status)
+        .bind(result)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_session(&self, session_id: Uuid, status: &JobStatus, error: Option<&str>) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE crawl_sessions SET status = $2, error_message = $3, completed_at = CURRENT_TIMESTAMP WHERE id = $1",
+        )
+        .bind(session_id)
+        .bind(status)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The session with its jobs, progress counts and totals; `None` if unknown
+    pub async fn status(&self, session_id: Uuid) -> Result<Option<CrawlSessionReport>, AppError> {
+        let session: Option<CrawlSessionRecord> =
+            sqlx::query_as(&format!("SELECT {} FROM crawl_sessions WHERE id = $1", SESSION_COLUMNS))
+                .bind(session_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        let Some(session) = session else {
+            return Ok(None);
+        };
+        let jobs: Vec<CrawlJobReport> = sqlx::query_as(
+            "SELECT id, dno_id, year, data_type::text AS data_type, status, error_message, result, started_at, completed_at
+             FROM crawl_jobs WHERE session_id = $1
+             ORDER BY created_at, year, data_type",
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Some(CrawlSessionReport::new(session, jobs)))
+    }
+
+    /// Fail the sessions and jobs a previous process left pending or running;
+    /// their background task is gone. Call once at startup.
+    pub async fn fail_interrupted(&self) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE crawl_jobs SET status = 'failed', error_message = 'interrupted by a restart', completed_at = CURRENT_TIMESTAMP
+             WHERE session_id IS NOT NULL AND status IN ('pending', 'running')",
+        )
+        .execute(&mut *tx)
+        .await?;
+        let sessions = sqlx::query(
+            "UPDATE crawl_sessions SET status = 'failed', error_message = 'interrupted by a restart', completed_at = CURRENT_TIMESTAMP
+             WHERE status IN ('pending', 'running')",
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        if sessions > 0 {
+            warn!("Marked {} crawl sessions interrupted by a restart as failed", sessions);
+        }
+        Ok(sessions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawl_request::CrawlConstraintsPlan;
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    async fn seed(pool: &PgPool) {
+        crate::test_schema::create(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (id, slug, name) VALUES ('00000000-0000-0000-0000-000000000001', 'netze-bw', 'Netze BW');
+            INSERT INTO users (id, email, password_hash, name, role) VALUES
+                ('00000000-0000-0000-0000-0000000000aa', 'admin@example.com', 'x', 'Admin', 'admin');
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// Waits for a permit per job; Netzentgelte crawls find two documents,
+    /// HLZF crawls fail
+    struct GatedRunner {
+        gate: Semaphore,
+    }
+
+    #[async_trait]
+    impl CrawlRunner for GatedRunner {
+        async fn run(&self, context: CrawlContext, session: &LiveCrawlSession) -> Result<CrawlJobSummary, AppError> {
+            self.gate.acquire().await.unwrap().forget();
+            session.log(LogLevel::Info, format!("Crawled {}", context.dno.slug));
+            if context.data_type == "hlzf" {
+                return Err(AppError::InternalServerError("https://www.netze-bw.de/ returned HTTP 503".to_string()));
+            }
+            Ok(CrawlJobSummary {
+                completion: "complete".to_string(),
+                pages_processed: 12,
+                documents_found: 2,
+                failed_urls: 1,
+                ..Default::default()
+            })
+        }
+    }
+
+    fn context(data_type: &str) -> CrawlContext {
+        let now = Utc::now();
+        CrawlContext {
+            dno: crate::Dno {
+                id: Uuid::from_u128(1),
+                slug: "netze-bw".to_string(),
+                name: "Netze BW".to_string(),
+                official_name: None,
+                description: None,
+                region: None,
+                website: Some("www.netze-bw.de".to_string()),
+                created_at: now,
+                updated_at: now,
+            },
+            year: 2024,
+            data_type: data_type.to_string(),
+            mode: "discovery".to_string(),
+            constraints: CrawlConstraintsPlan { max_depth: 2, max_pages: 50, timeout_secs: 300 },
+            ignore_robots: false,
+            bypass_http_cache: false,
+            http_cache_ttl_secs: None,
+            start_urls: Vec::new(),
+            strategy_order: None,
+            preferred_content_type: None,
+        }
+    }
+
+    /// Poll until `done` holds for the session's report
+    async fn wait_for(jobs: &CrawlJobs, id: Uuid, done: impl Fn(&CrawlSessionReport) -> bool) -> CrawlSessionReport {
+        for _ in 0..250 {
+            let report = jobs.status(id).await.unwrap().unwrap();
+            if done(&report) {
+                return report;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("crawl session {} never reached the expected state", id);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_session_runs_its_jobs_in_the_background(pool: PgPool) {
+        seed(&pool).await;
+        let runner = Arc::new(GatedRunner { gate: Semaphore::new(0) });
+        let live = LiveSessions::new();
+        let jobs = CrawlJobs::new(pool, runner.clone(), live.clone());
+
+        let session = jobs.start(Some(Uuid::from_u128(0xaa)), "discovery", vec![context("netzentgelte"), context("hlzf")]).await.unwrap();
+        assert_eq!(session.status, JobStatus::Pending);
+        assert!(live.subscribe(session.id).is_some());
+
+        let running = wait_for(&jobs, session.id, |r| r.progress.running == 1).await;
+        assert_eq!(running.session.status, JobStatus::Running);
+        assert_eq!(running.progress, CrawlProgress { total: 2, pending: 1, running: 1, completed: 0, failed: 0 });
+
+        runner.gate.add_permits(2);
+        let done = wait_for(&jobs, session.id, |r| r.session.completed_at.is_some()).await;
+        // One of two jobs got through, so the session completed
+        assert_eq!(done.session.status, JobStatus::Completed);
+        assert_eq!(done.progress, CrawlProgress { total: 2, pending: 0, running: 0, completed: 1, failed: 1 });
+        assert_eq!(done.summary, CrawlTotals { pages_processed: 12, documents_found: 2, failed_urls: 1 });
+        let hlzf = done.jobs.iter().find(|job| job.data_type == "hlzf").unwrap();
+        assert!(hlzf.error_message.as_deref().unwrap().contains("503"));
+        assert!(live.subscribe(session.id).is_none());
+
+        assert!(jobs.status(Uuid::new_v4()).await.unwrap().is_none());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_sessions_interrupted_by_a_restart_are_failed(pool: PgPool) {
+        seed(&pool).await;
+        // Never gets a permit, like a process that died mid-crawl
+        let stuck = CrawlJobs::new(pool.clone(), Arc::new(GatedRunner { gate: Semaphore::new(0) }), LiveSessions::new());
+        let session = stuck.start(None, "targeted", vec![context("netzentgelte")]).await.unwrap();
+        wait_for(&stuck, session.id, |r| r.progress.running == 1).await;
+
+        let restarted = CrawlJobs::new(pool, Arc::new(GatedRunner { gate: Semaphore::new(0) }), LiveSessions::new());
+        assert_eq!(restarted.fail_interrupted().await.unwrap(), 1);
+        let report = restarted.status(session.id).await.unwrap().unwrap();
+        assert_eq!(report.session.status, JobStatus::Failed);
+        assert_eq!(report.session.error_message.as_deref(), Some("interrupted by a restart"));
+        assert_eq!(report.progress.failed, 1);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::crawl_request::{CrawlConstraintsPlan, CrawlRequestPlan};
+use crate::Dno;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Pages a discovery crawl expects to follow from one seed per level of depth
+const LINKS_PER_LEVEL: u32 = 10;
+
+/// Ways a crawl looks for a DNO's documents, tried in the configured order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrawlStrategy {
+    /// Source URLs that produced data in earlier crawls
+    KnownSources,
+    /// URL patterns learned from earlier finds, filled in with the target year
+    LearnedPatterns,
+    /// Follow links from the DNO website
+    SiteNavigation,
+    /// Follow paginated archive listings to their last page
+    ArchivePagination,
+    /// Query SearXNG for the DNO's documents
+    Search,
+}
+
+impl CrawlStrategy {
+    pub const DEFAULT_ORDER: [CrawlStrategy; 5] = [
+        CrawlStrategy::KnownSources,
+        CrawlStrategy::LearnedPatterns,
+        CrawlStrategy::SiteNavigation,
+        CrawlStrategy::ArchivePagination,
+        CrawlStrategy::Search,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CrawlStrategy::KnownSources => "known_sources",
+            CrawlStrategy::LearnedPatterns => "learned_patterns",
+            CrawlStrategy::SiteNavigation => "site_navigation",
+            CrawlStrategy::ArchivePagination => "archive_pagination",
+            CrawlStrategy::Search => "search",
+        }
+    }
+
+    /// Whether a targeted crawl, which only fetches URLs it already knows, uses it
+    pub fn is_targeted(&self) -> bool {
+        matches!(self, CrawlStrategy::KnownSources | CrawlStrategy::LearnedPatterns)
+    }
+
+    /// Strategy order from `CRAWLER_STRATEGY_ORDER` (comma-separated names);
+    /// the default order when unset. Unknown names are skipped with a warning.
+    pub fn configured_order() -> Vec<CrawlStrategy> {
+        let Ok(names) = std::env::var("CRAWLER_STRATEGY_ORDER") else {
+            return Self::DEFAULT_ORDER.to_vec();
+        };
+        let mut order = Vec::new();
+        for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match Self::DEFAULT_ORDER.into_iter().find(|s| s.as_str().eq_ignore_ascii_case(name)) {
+                Some(strategy) if !order.contains(&strategy) => order.push(strategy),
+                Some(_) => {}
+                None => warn!("Ignoring unknown crawl strategy '{}' in CRAWLER_STRATEGY_ORDER", name),
+            }
+        }
+        if order.is_empty() {
+            Self::DEFAULT_ORDER.to_vec()
+        } else {
+            order
+        }
+    }
+}
+
+/// One crawl session to plan: a DNO, year and data type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlContext {
+    pub dno: Dno,
+    pub year: i32,
+    pub data_type: String,
+    /// `targeted`, `discovery` or `hybrid`
+    pub mode: String,
+    pub constraints: CrawlConstraintsPlan,
+    /// Skip `robots.txt` and `Crawl-delay`, for sites we run ourselves
+    #[serde(default)]
+    pub ignore_robots: bool,
+    /// Download every page in full instead of revalidating the crawler's HTTP cache
+    #[serde(default)]
+    pub bypass_http_cache: bool,
+    /// How long cached pages are revalidated rather than refetched, instead
+    /// of `CRAWLER_HTTP_CACHE_TTL_SECS`
+    #[serde(default)]
+    pub http_cache_ttl_secs: Option<u64>,
+    /// URLs crawled before anything else, e.g. pinned by a DNO override
+    #[serde(default)]
+    pub start_urls: Vec<String>,
+    /// Strategy order for this crawl instead of the crawler's configured one
+    #[serde(default)]
+    pub strategy_order: Option<Vec<CrawlStrategy>>,
+    /// Kind of document ranked first among found links (a crawler
+    /// `ContentType` name such as `pdf`)
+    #[serde(default)]
+    pub preferred_content_type: Option<String>,
+}
+
+impl CrawlContext {
+    /// Contexts for every session of a validated request
+    pub fn for_sessions(plan: &CrawlRequestPlan, dnos: &[Dno]) -> Vec<CrawlContext> {
+        plan.sessions
+            .iter()
+            .filter_map(|session| {
+                let dno = dnos.iter().find(|dno| dno.id == session.dno_id)?;
+                Some(CrawlContext {
+                    dno: dno.clone(),
+                    year: session.year,
+                    data_type: session.data_type.clone(),
+                    mode: plan.mode.clone(),
+                    constraints: plan.constraints.clone(),
+                    ignore_robots: false,
+                    bypass_http_cache: false,
+                    http_cache_ttl_secs: None,
+                    start_urls: Vec::new(),
+                    strategy_order: None,
+                    preferred_content_type: None,
+                })
+            })
+            .collect()
+    }
+
+    /// `https://` URL of the DNO's stored website, which may lack the scheme
+    pub fn website_url(&self) -> Option<String> {
+        let website = self.dno.website.as_deref()?.trim().trim_end_matches('/');
+        if website.is_empty() {
+            None
+        } else if website.starts_with("http://") || website.starts_with("https://") {
+            Some(format!("{}/", website))
+        } else {
+            Some(format!("https://{}/", website))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedSource {
+    /// A start URL set on the context
+    StartUrl,
+    /// The website stored for the DNO
+    DnoWebsite,
+    /// A learned URL pattern filled in with the target year
+    LearnedPattern,
+    /// A search query, for DNOs without a stored website
+    SearchQuery,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedSeed {
+    /// A URL, or the query text for `SearchQuery` seeds
+    pub value: String,
+    pub source: SeedSource,
+}
+
+/// What a crawl session would do, resolved without fetching anything
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlPlan {
+    pub dno_slug: String,
+    pub year: i32,
+    pub data_type: String,
+    pub mode: String,
+    pub seeds: Vec<PlannedSeed>,
+    /// Strategies the crawl would use, in order
+    pub strategies: Vec<CrawlStrategy>,
+    pub constraints: CrawlConstraintsPlan,
+    /// Rough number of requests, capped at `constraints.max_pages`
+    pub estimated_requests: u32,
+}
+
+impl CrawlPlan {
+    /// Plan a session from its context, the strategy order and URLs filled in
+    /// from learned patterns. Targeted crawls only use `KnownSources` and
+    /// `LearnedPatterns`; known source URLs are looked up when the crawl runs.
+    pub fn build(context: &CrawlContext, order: &[CrawlStrategy], learned_urls: Vec<String>) -> Self {
+        // Hybrid crawls fall back to discovery, so they plan for it
+        let discovery = context.mode != "targeted";
+        let strategies: Vec<CrawlStrategy> =
+            order.iter().copied().filter(|strategy| discovery || strategy.is_targeted()).collect();
+        let uses = |strategy: CrawlStrategy| strategies.contains(&strategy);
+
+        let mut seeds: Vec<PlannedSeed> = context
+            .start_urls
+            .iter()
+            .map(|value| PlannedSeed { value: value.clone(), source: SeedSource::StartUrl })
+            .collect();
+        if uses(CrawlStrategy::LearnedPatterns) {
+            seeds.extend(learned_urls.into_iter().map(|value| PlannedSeed { value, source: SeedSource::LearnedPattern }));
+        }
+        if let Some(website) = context.website_url().filter(|_| uses(CrawlStrategy::SiteNavigation)) {
+            seeds.push(PlannedSeed { value: website, source: SeedSource::DnoWebsite });
+        } else if uses(CrawlStrategy::Search) {
+            seeds.push(PlannedSeed {
+                value: format!("{} {} {}", context.dno.name, context.data_type, context.year),
+                source: SeedSource::SearchQuery,
+            });
+        }
+
+        let constraints = context.constraints.clone();
+        let estimated = seeds
+            .iter()
+            .map(|seed| match seed.source {
+                SeedSource::StartUrl | SeedSource::DnoWebsite => {
+                    1 + LINKS_PER_LEVEL.saturating_mul(constraints.max_depth)
+                }
+                SeedSource::LearnedPattern => 1,
+                // The search itself, then its result pages
+                SeedSource::SearchQuery => 1 + LINKS_PER_LEVEL,
+            })
+            .fold(0u32, u32::saturating_add);
+
+        CrawlPlan {
+            dno_slug: context.dno.slug.clone(),
+            year: context.year,
+            data_type: context.data_type.clone(),
+            mode: context.mode.clone(),
+            seeds,
+            strategies,
+            estimated_requests: estimated.min(constraints.max_pages),
+            constraints,
+        }
+    }
+}
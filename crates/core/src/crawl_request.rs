@@ -0,0 +1,495 @@
+use crate::{slugify_dno, DataType, Dno};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Earliest year DNOs publish Netzentgelte for in a crawlable form
+pub const MIN_CRAWL_YEAR: i32 = 2010;
+/// Upper bound on sessions one request may create
+pub const MAX_CRAWL_SESSIONS: usize = 500;
+
+/// A batch crawl as submitted by a user: every DNO x year x data type
+/// combination becomes one crawl session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlRequest {
+    /// DNO ids, slugs or names
+    pub dnos: Vec<String>,
+    pub years: Vec<i32>,
+    /// `netzentgelte`, `hlzf` or `all`; both when empty
+    pub data_types: Vec<String>,
+    /// `targeted` (known source URLs, default), `discovery` (search and
+    /// explore), or a `CrawlModeSpec` object such as a hybrid of both
+    pub mode: Option<CrawlModeRequest>,
+    pub constraints: CrawlConstraints,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlConstraints {
+    /// Link depth for discovery crawls
+    pub max_depth: Option<u32>,
+    /// Pages fetched per session
+    pub max_pages: Option<u32>,
+    pub timeout_secs: Option<u64>,
+}
+
+const MODES: [&str; 2] = ["targeted", "discovery"];
+const MAX_DEPTH: u32 = 10;
+const MAX_PAGES: u32 = 1000;
+const MAX_TIMEOUT_SECS: u64 = 3600;
+/// Hybrid modes nested inside a hybrid mode, at most
+pub const MAX_HYBRID_NESTING: usize = 2;
+const DEFAULT_DISCOVERY_DEPTH: u32 = 3;
+
+/// `mode` as sent by clients: a mode name or a full mode object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CrawlModeRequest {
+    Name(String),
+    Spec(CrawlModeSpec),
+}
+
+/// A crawl mode as submitted, checked by `validate` before it becomes a `CrawlMode`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CrawlModeSpec {
+    /// Takes no options; they are accepted here only to be reported
+    Targeted {
+        max_depth: Option<u32>,
+        exploration_budget: Option<u32>,
+    },
+    Discovery {
+        max_depth: Option<u32>,
+        exploration_budget: Option<u32>,
+    },
+    /// Run `primary`, then `fallback` if it found nothing
+    Hybrid {
+        primary: Box<CrawlModeSpec>,
+        fallback: Box<CrawlModeSpec>,
+    },
+}
+
+/// How a crawl session finds documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CrawlMode {
+    /// Fetch known source URLs and learned patterns only
+    Targeted,
+    /// Explore the DNO website and search results
+    Discovery {
+        max_depth: u32,
+        /// Pages the exploration may fetch
+        exploration_budget: u32,
+    },
+    Hybrid {
+        primary: Box<CrawlMode>,
+        fallback: Box<CrawlMode>,
+    },
+}
+
+impl CrawlMode {
+    /// `targeted`, `discovery` or `hybrid`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrawlMode::Targeted => "targeted",
+            CrawlMode::Discovery { .. } => "discovery",
+            CrawlMode::Hybrid { .. } => "hybrid",
+        }
+    }
+
+    /// Deepest link depth of any discovery part; `None` without discovery
+    pub fn max_depth(&self) -> Option<u32> {
+        match self {
+            CrawlMode::Targeted => None,
+            CrawlMode::Discovery { max_depth, .. } => Some(*max_depth),
+            CrawlMode::Hybrid { primary, fallback } => primary.max_depth().max(fallback.max_depth()),
+        }
+    }
+
+    pub fn uses_discovery(&self) -> bool {
+        self.max_depth().is_some()
+    }
+}
+
+impl CrawlModeSpec {
+    /// Check bounds and contradictions, reporting errors under `field`
+    /// (`mode`, `mode.primary`, ...). `nesting` counts the enclosing hybrids.
+    fn validate(
+        &self,
+        field: &str,
+        nesting: usize,
+        max_pages: Option<u32>,
+        errors: &mut Vec<FieldError>,
+    ) -> Option<CrawlMode> {
+        match self {
+            CrawlModeSpec::Targeted { max_depth, exploration_budget } => {
+                for (name, value) in [("max_depth", max_depth), ("exploration_budget", exploration_budget)] {
+                    if value.is_some() {
+                        errors.push(FieldError::new(format!("{}.{}", field, name), "only applies to discovery crawls"));
+                    }
+                }
+                Some(CrawlMode::Targeted)
+            }
+            CrawlModeSpec::Discovery { max_depth, exploration_budget } => {
+                let before = errors.len();
+                let max_depth = max_depth.unwrap_or(DEFAULT_DISCOVERY_DEPTH);
+                let exploration_budget = exploration_budget.unwrap_or(max_pages.unwrap_or(50));
+                if max_depth == 0 || max_depth > MAX_DEPTH {
+                    errors.push(FieldError::new(
+                        format!("{}.max_depth", field),
+                        format!("must be between 1 and {}", MAX_DEPTH),
+                    ));
+                }
+                if exploration_budget == 0 || exploration_budget > MAX_PAGES {
+                    errors.push(FieldError::new(
+                        format!("{}.exploration_budget", field),
+                        format!("must be between 1 and {}", MAX_PAGES),
+                    ));
+                } else if exploration_budget < max_depth {
+                    errors.push(FieldError::new(
+                        format!("{}.exploration_budget", field),
+                        format!("{} pages cannot reach depth {}", exploration_budget, max_depth),
+                    ));
+                } else if max_pages.is_some_and(|pages| exploration_budget > pages) {
+                    errors.push(FieldError::new(
+                        format!("{}.exploration_budget", field),
+                        "exceeds constraints.max_pages",
+                    ));
+                }
+                (errors.len() == before).then_some(CrawlMode::Discovery { max_depth, exploration_budget })
+            }
+            CrawlModeSpec::Hybrid { primary, fallback } => {
+                if nesting >= MAX_HYBRID_NESTING {
+                    // Not descending further bounds the work on adversarial input
+                    errors.push(FieldError::new(
+                        field,
+                        format!("hybrid modes may be nested at most {} deep", MAX_HYBRID_NESTING),
+                    ));
+                    return None;
+                }
+                let primary = primary.validate(&format!("{}.primary", field), nesting + 1, max_pages, errors);
+                let fallback = fallback.validate(&format!("{}.fallback", field), nesting + 1, max_pages, errors);
+                let (primary, fallback) = (primary?, fallback?);
+                if primary == fallback {
+                    errors.push(FieldError::new(format!("{}.fallback", field), "repeats the primary mode"));
+                    return None;
+                }
+                Some(CrawlMode::Hybrid { primary: Box::new(primary), fallback: Box::new(fallback) })
+            }
+        }
+    }
+}
+
+/// Validation failure for one request field, e.g. `dnos[1]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub(crate) fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedSession {
+    pub dno_id: Uuid,
+    pub dno_slug: String,
+    pub year: i32,
+    pub data_type: String,
+}
+
+/// What launching a valid request would do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlRequestPlan {
+    /// `CrawlMode::name` of `crawl_mode`
+    pub mode: String,
+    pub crawl_mode: CrawlMode,
+    pub constraints: CrawlConstraintsPlan,
+    pub session_count: usize,
+    pub sessions: Vec<PlannedSession>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlConstraintsPlan {
+    pub max_depth: u32,
+    pub max_pages: u32,
+    pub timeout_secs: u64,
+}
+
+impl CrawlRequest {
+    /// Check the request against the known DNOs and build the session plan.
+    /// Used by both the dry-run validation endpoint and the real launch, so a
+    /// request that validates cleanly launches exactly the planned sessions.
+    pub fn validate(&self, known_dnos: &[Dno], current_year: i32) -> Result<CrawlRequestPlan, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if self.dnos.is_empty() {
+            errors.push(FieldError::new("dnos", "at least one DNO is required"));
+        }
+        let mut dnos: Vec<&Dno> = Vec::new();
+        for (i, reference) in self.dnos.iter().enumerate() {
+            match resolve_dno(known_dnos, reference) {
+                Some(dno) if dnos.iter().any(|d| d.id == dno.id) => {}
+                Some(dno) => dnos.push(dno),
+                None => errors.push(FieldError::new(format!("dnos[{}]", i), format!("unknown DNO '{}'", reference))),
+            }
+        }
+
+        let max_year = current_year + 1;
+        if self.years.is_empty() {
+            errors.push(FieldError::new("years", "at least one year is required"));
+        }
+        for (i, year) in self.years.iter().enumerate() {
+            if !(MIN_CRAWL_YEAR..=max_year).contains(year) {
+                errors.push(FieldError::new(
+                    format!("years[{}]", i),
+                    format!("{} is outside {}-{}", year, MIN_CRAWL_YEAR, max_year),
+                ));
+            }
+        }
+        let mut years = self.years.clone();
+        years.sort_unstable();
+        years.dedup();
+
+        let mut data_types: Vec<&'static str> = Vec::new();
+        let requested = if self.data_types.is_empty() { vec!["all".to_string()] } else { self.data_types.clone() };
+        for (i, name) in requested.iter().enumerate() {
+            let expanded: &[DataType] = match name.to_lowercase().as_str() {
+                "netzentgelte" => &[DataType::Netzentgelte],
+                "hlzf" => &[DataType::Hlzf],
+                "all" => &[DataType::Netzentgelte, DataType::Hlzf],
+                _ => {
+                    errors.push(FieldError::new(format!("data_types[{}]", i), format!("unknown data type '{}'", name)));
+                    &[]
+                }
+            };
+            for data_type in expanded {
+                if !data_types.contains(&data_type.as_str()) {
+                    data_types.push(data_type.as_str());
+                }
+            }
+        }
+
+        let constraints = &self.constraints;
+        let crawl_mode = match &self.mode {
+            None => Some(CrawlMode::Targeted),
+            Some(CrawlModeRequest::Name(name)) => match name.to_lowercase().as_str() {
+                "targeted" => Some(CrawlMode::Targeted),
+                "discovery" => Some(CrawlMode::Discovery {
+                    max_depth: constraints.max_depth.unwrap_or(DEFAULT_DISCOVERY_DEPTH),
+                    exploration_budget: constraints.max_pages.unwrap_or(50),
+                }),
+                _ => {
+                    errors.push(FieldError::new(
+                        "mode",
+                        format!("must be one of {} or a mode object", MODES.join(", ")),
+                    ));
+                    None
+                }
+            },
+            Some(CrawlModeRequest::Spec(spec)) => {
+                let crawl_mode = spec.validate("mode", 0, constraints.max_pages, &mut errors);
+                let mode_depth = crawl_mode.as_ref().and_then(CrawlMode::max_depth);
+                if let (Some(depth), Some(mode_depth)) = (constraints.max_depth, mode_depth) {
+                    if depth != mode_depth {
+                        errors.push(FieldError::new(
+                            "constraints.max_depth",
+                            format!("conflicts with the mode's max_depth of {}", mode_depth),
+                        ));
+                    }
+                }
+                crawl_mode
+            }
+        };
+
+        if constraints.max_depth.is_some_and(|d| d == 0 || d > MAX_DEPTH) {
+            errors.push(FieldError::new("constraints.max_depth", format!("must be between 1 and {}", MAX_DEPTH)));
+        }
+        if constraints.max_depth.is_some() && crawl_mode.as_ref().is_some_and(|mode| !mode.uses_discovery()) {
+            errors.push(FieldError::new("constraints.max_depth", "only applies to discovery crawls"));
+        }
+        if constraints.max_pages.is_some_and(|p| p == 0 || p > MAX_PAGES) {
+            errors.push(FieldError::new("constraints.max_pages", format!("must be between 1 and {}", MAX_PAGES)));
+        }
+        if constraints.timeout_secs.is_some_and(|t| t == 0 || t > MAX_TIMEOUT_SECS) {
+            errors.push(FieldError::new("constraints.timeout_secs", format!("must be between 1 and {}", MAX_TIMEOUT_SECS)));
+        }
+
+        let session_count = dnos.len() * years.len() * data_types.len();
+        if session_count > MAX_CRAWL_SESSIONS {
+            errors.push(FieldError::new(
+                "dnos",
+                format!("{} sessions requested, at most {} per request", session_count, MAX_CRAWL_SESSIONS),
+            ));
+        }
+
+        let Some(crawl_mode) = crawl_mode.filter(|_| errors.is_empty()) else {
+            return Err(errors);
+        };
+
+        let (years, data_types) = (&years, &data_types);
+        let sessions = dnos
+            .iter()
+            .flat_map(|dno| {
+                years.iter().flat_map(move |year| {
+                    data_types.iter().map(move |data_type| PlannedSession {
+                        dno_id: dno.id,
+                        dno_slug: dno.slug.clone(),
+                        year: *year,
+                        data_type: data_type.to_string(),
+                    })
+                })
+            })
+            .collect();
+
+        Ok(CrawlRequestPlan {
+            constraints: CrawlConstraintsPlan {
+                max_depth: constraints.max_depth.or(crawl_mode.max_depth()).unwrap_or(0),
+                max_pages: constraints.max_pages.unwrap_or(50),
+                timeout_secs: constraints.timeout_secs.unwrap_or(300),
+            },
+            mode: crawl_mode.name().to_string(),
+            crawl_mode,
+            session_count,
+            sessions,
+        })
+    }
+}
+
+pub(crate) fn resolve_dno<'a>(known: &'a [Dno], reference: &str) -> Option<&'a Dno> {
+    let reference = reference.trim();
+    if let Ok(id) = Uuid::parse_str(reference) {
+        return known.iter().find(|dno| dno.id == id);
+    }
+    let slug = slugify_dno(reference);
+    known.iter().find(|dno| {
+        dno.slug == slug
+            || dno.name.eq_ignore_ascii_case(reference)
+            || dno.official_name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(reference))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn dno(slug: &str, name: &str) -> Dno {
+        Dno {
+            id: Uuid::new_v4(),
+            slug: slug.to_string(),
+            name: name.to_string(),
+            official_name: None,
+            description: None,
+            region: None,
+            website: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_unknown_dno_and_out_of_range_year_are_reported() {
+        let known = vec![dno("netze-bw", "Netze BW"), dno("westnetz", "Westnetz")];
+        let request = CrawlRequest {
+            dnos: vec!["netze-bw".to_string(), "Stadtwerke Atlantis".to_string()],
+            years: vec![2024, 1999],
+            ..Default::default()
+        };
+
+        let errors = request.validate(&known, 2025).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["dnos[1]", "years[1]"]);
+        assert!(errors[0].message.contains("Stadtwerke Atlantis"));
+    }
+
+    #[test]
+    fn test_valid_request_returns_plan() {
+        let known = vec![dno("netze-bw", "Netze BW"), dno("westnetz", "Westnetz")];
+        let request = CrawlRequest {
+            dnos: vec!["Netze BW".to_string(), "westnetz".to_string(), "netze-bw".to_string()],
+            years: vec![2024, 2023, 2024],
+            data_types: vec!["netzentgelte".to_string()],
+            ..Default::default()
+        };
+
+        let plan = request.validate(&known, 2025).unwrap();
+        assert_eq!(plan.mode, "targeted");
+        assert_eq!(plan.session_count, 4);
+        assert_eq!(plan.sessions.len(), 4);
+        assert_eq!(plan.sessions[0].dno_slug, "netze-bw");
+        assert_eq!(plan.sessions[0].year, 2023);
+    }
+
+    fn parse(json: &str) -> CrawlRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_valid_hybrid_mode_maps_to_crawl_mode() {
+        let known = vec![dno("netze-bw", "Netze BW")];
+        let request = parse(
+            r#"{
+                "dnos": ["netze-bw"],
+                "years": [2024],
+                "mode": {
+                    "type": "hybrid",
+                    "primary": { "type": "targeted" },
+                    "fallback": { "type": "discovery", "max_depth": 4, "exploration_budget": 40 }
+                },
+                "constraints": { "max_pages": 80 }
+            }"#,
+        );
+
+        let plan = request.validate(&known, 2025).unwrap();
+        assert_eq!(plan.mode, "hybrid");
+        assert_eq!(
+            plan.crawl_mode,
+            CrawlMode::Hybrid {
+                primary: Box::new(CrawlMode::Targeted),
+                fallback: Box::new(CrawlMode::Discovery { max_depth: 4, exploration_budget: 40 }),
+            }
+        );
+        assert_eq!(plan.constraints.max_depth, 4);
+
+        // Plain names keep working
+        let request = parse(r#"{"dnos": ["netze-bw"], "years": [2024], "mode": "discovery"}"#);
+        let plan = request.validate(&known, 2025).unwrap();
+        assert_eq!(plan.crawl_mode, CrawlMode::Discovery { max_depth: 3, exploration_budget: 50 });
+    }
+
+    #[test]
+    fn test_over_nested_and_contradictory_modes_are_rejected() {
+        let known = vec![dno("netze-bw", "Netze BW")];
+        let discovery = r#"{ "type": "discovery" }"#;
+        let mut mode = discovery.to_string();
+        for _ in 0..4 {
+            mode = format!(r#"{{ "type": "hybrid", "primary": {{ "type": "targeted" }}, "fallback": {} }}"#, mode);
+        }
+        let request = parse(&format!(r#"{{"dnos": ["netze-bw"], "years": [2024], "mode": {}}}"#, mode));
+        let errors = request.validate(&known, 2025).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "mode.fallback.fallback");
+        assert!(errors[0].message.contains("nested at most 2"));
+
+        let request = parse(
+            r#"{
+                "dnos": ["netze-bw"],
+                "years": [2024],
+                "mode": {
+                    "type": "hybrid",
+                    "primary": { "type": "targeted", "max_depth": 2 },
+                    "fallback": { "type": "discovery", "max_depth": 5, "exploration_budget": 3 }
+                },
+                "constraints": { "max_depth": 2 }
+            }"#,
+        );
+        let errors = request.validate(&known, 2025).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["mode.primary.max_depth", "mode.fallback.exploration_budget"]);
+        assert!(errors[1].message.contains("cannot reach depth 5"));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::{config::DatabaseConfig, AppError};
 use crate::models::*;
+use crate::paging::Paging;
+use crate::repository::DataTypeQuery;
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use std::time::Duration;
 use tracing::{info, error};
@@ -394,6 +396,34 @@ pub async fn get_dno_by_name(pool: &PgPool, name: &str) -> Result<Option<Dno>, A
     Ok(result)
 }
 
+/// DNOs whose name or official name resembles `query` by trigram similarity
+/// (`pg_trgm`), best match first. Catches partial, misspelled and
+/// differently cased names that `get_dno_by_name` misses.
+pub async fn search_dnos_fuzzy(pool: &PgPool, query: &str, limit: i64) -> Result<Vec<Dno>, AppError> {
+    let result = sqlx::query_as::<_, Dno>(
+        r#"
+        SELECT id, slug, name, official_name, description, region, website,
+               created_at, updated_at
+        FROM dnos
+        WHERE deleted_at IS NULL
+          AND (name % $1 OR official_name % $1 OR $1 <% name OR $1 <% official_name)
+        ORDER BY GREATEST(
+                     similarity(name, $1), word_similarity($1, name),
+                     COALESCE(similarity(official_name, $1), 0), COALESCE(word_similarity($1, official_name), 0)
+                 ) DESC,
+                 name ASC
+        LIMIT $2
+        "#,
+    )
+    .bind(query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(result)
+}
+
 pub async fn get_dno_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Dno>, AppError> {
     let result = sqlx::query_as!(
         Dno,
@@ -481,15 +511,9 @@ pub async fn delete_dno(pool: &PgPool, dno_id: Uuid) -> Result<(), AppError> {
 // Netzentgelte data search functions
 pub async fn search_netzentgelte_data(
     pool: &PgPool,
-    dno_id: Option<Uuid>,
-    dno_name: Option<&str>,
-    year: Option<i32>,
-    verification_status: Option<&str>,
-    limit: Option<i64>,
-    offset: Option<i64>,
+    query: &DataTypeQuery<'_>,
 ) -> Result<Vec<NetzentgelteDataWithDno>, AppError> {
-    let limit = limit.unwrap_or(50);
-    let offset = offset.unwrap_or(0);
+    let DataTypeQuery { dno_id, dno_name, year, verification_status, limit, offset, paging, versions } = *query;
 
     let mut query_builder = sqlx::QueryBuilder::new(
         r#"
@@ -497,11 +521,16 @@ pub async fn search_netzentgelte_data(
             n.id, n.dno_id, n.year, n.voltage_level,
             n.leistung, n.arbeit, n.leistung_unter_2500h, n.arbeit_unter_2500h,
             n.verification_status, n.verified_by, n.verified_at, n.verification_notes,
+            n.first_seen_at, n.last_verified_at, n.version, n.superseded_by,
             n.created_at, n.updated_at, n.deleted_at,
             d.id as dno_id_full, d.slug as dno_slug, d.name as dno_name, 
-            d.official_name as dno_official_name, d.region as dno_region
+            d.official_name as dno_official_name, d.region as dno_region,
+            s.id as source_id, s.source_type::text as source_type, s.source_url,
+            s.file_hash as source_file_hash, s.confidence as source_confidence,
+            s.page_number as source_page, s.extracted_at as source_extracted_at
         FROM netzentgelte_data n
         JOIN dnos d ON n.dno_id = d.id
+        LEFT JOIN data_sources s ON s.dno_id = n.dno_id AND s.year = n.year AND s.data_type = 'netzentgelte'
         WHERE n.deleted_at IS NULL AND d.deleted_at IS NULL
         "#
     );
@@ -531,10 +560,29 @@ pub async fn search_netzentgelte_data(
         query_builder.push_bind(status);
     }
 
-    query_builder.push(" ORDER BY n.created_at DESC, d.name ASC LIMIT ");
-    query_builder.push_bind(limit);
-    query_builder.push(" OFFSET ");
-    query_builder.push_bind(offset);
+    if versions == DataVersions::Latest {
+        query_builder.push(" AND n.superseded_by IS NULL");
+    }
+
+    match paging {
+        Paging::Offset => {
+            query_builder.push(" ORDER BY n.created_at DESC, d.name ASC LIMIT ");
+            query_builder.push_bind(limit);
+            query_builder.push(" OFFSET ");
+            query_builder.push_bind(offset);
+        }
+        Paging::Keyset { after } => {
+            if let Some(after) = after {
+                query_builder.push(" AND (n.year, n.id) > (");
+                query_builder.push_bind(after.year);
+                query_builder.push(", ");
+                query_builder.push_bind(after.id);
+                query_builder.push(")");
+            }
+            query_builder.push(" ORDER BY n.year ASC, n.id ASC LIMIT ");
+            query_builder.push_bind(limit);
+        }
+    }
 
     let query = query_builder.build_query_as::<NetzentgelteDataWithDno>();
     let result = query.fetch_all(pool).await.map_err(AppError::Database)?;
@@ -548,6 +596,7 @@ pub async fn count_netzentgelte_data(
     dno_name: Option<&str>,
     year: Option<i32>,
     verification_status: Option<&str>,
+    versions: DataVersions,
 ) -> Result<i64, AppError> {
     let mut query_builder = sqlx::QueryBuilder::new(
         r#"
@@ -581,24 +630,82 @@ pub async fn count_netzentgelte_data(
         query_builder.push_bind(status);
     }
 
+    if versions == DataVersions::Latest {
+        query_builder.push(" AND n.superseded_by IS NULL");
+    }
+
     let query = query_builder.build_query_scalar::<i64>();
     let result = query.fetch_one(pool).await.map_err(AppError::Database)?;
 
     Ok(result)
 }
 
-// HLZF data search functions
-pub async fn search_hlzf_data(
+/// Per-year row counts behind a search, ignoring the year and verification
+/// filters, so an empty result can be explained
+pub async fn get_data_coverage(
     pool: &PgPool,
     dno_id: Option<Uuid>,
     dno_name: Option<&str>,
-    year: Option<i32>,
-    verification_status: Option<&str>,
-    limit: Option<i64>,
-    offset: Option<i64>,
+    data_type: Option<&str>,
+) -> Result<Vec<YearCoverage>, AppError> {
+    let mut query_builder = sqlx::QueryBuilder::new("");
+    let tables = [("netzentgelte", "netzentgelte_data"), ("hlzf", "hlzf_data")];
+    let mut first = true;
+
+    for (name, table) in tables {
+        if data_type.is_some_and(|t| t != name && t != "all") {
+            continue;
+        }
+        if !first {
+            query_builder.push(" UNION ALL ");
+        }
+        first = false;
+
+        query_builder.push(format!(
+            r#"
+            SELECT '{name}' AS data_type, t.year,
+                COUNT(*) FILTER (WHERE t.verification_status = 'verified') AS verified,
+                COUNT(*) FILTER (WHERE t.verification_status IS DISTINCT FROM 'verified') AS unverified
+            FROM {table} t
+            JOIN dnos d ON t.dno_id = d.id
+            WHERE t.deleted_at IS NULL AND t.superseded_by IS NULL AND d.deleted_at IS NULL
+            "#
+        ));
+
+        if let Some(dno_id) = dno_id {
+            query_builder.push(" AND t.dno_id = ");
+            query_builder.push_bind(dno_id);
+        }
+
+        if let Some(dno_name) = dno_name {
+            query_builder.push(" AND (d.name ILIKE ");
+            query_builder.push_bind(format!("%{}%", dno_name));
+            query_builder.push(" OR d.official_name ILIKE ");
+            query_builder.push_bind(format!("%{}%", dno_name));
+            query_builder.push(")");
+        }
+
+        query_builder.push(" GROUP BY t.year");
+    }
+
+    if first {
+        return Ok(Vec::new());
+    }
+
+    query_builder.push(" ORDER BY year DESC");
+
+    let query = query_builder.build_query_as::<YearCoverage>();
+    let result = query.fetch_all(pool).await.map_err(AppError::Database)?;
+
+    Ok(result)
+}
+
+// HLZF data search functions
+pub async fn search_hlzf_data(
+    pool: &PgPool,
+    query: &DataTypeQuery<'_>,
 ) -> Result<Vec<HlzfDataWithDno>, AppError> {
-    let limit = limit.unwrap_or(50);
-    let offset = offset.unwrap_or(0);
+    let DataTypeQuery { dno_id, dno_name, year, verification_status, limit, offset, paging, versions } = *query;
 
     let mut query_builder = sqlx::QueryBuilder::new(
         r#"
@@ -606,11 +713,16 @@ pub async fn search_hlzf_data(
             h.id, h.dno_id, h.year, h.season, h.voltage_level,
             h.ht, h.nt, h.start_date, h.end_date,
             h.verification_status, h.verified_by, h.verified_at, h.verification_notes,
+            h.first_seen_at, h.last_verified_at, h.version, h.superseded_by,
             h.created_at, h.updated_at, h.deleted_at,
             d.id as dno_id_full, d.slug as dno_slug, d.name as dno_name, 
-            d.official_name as dno_official_name, d.region as dno_region
+            d.official_name as dno_official_name, d.region as dno_region,
+            s.id as source_id, s.source_type::text as source_type, s.source_url,
+            s.file_hash as source_file_hash, s.confidence as source_confidence,
+            s.page_number as source_page, s.extracted_at as source_extracted_at
         FROM hlzf_data h
         JOIN dnos d ON h.dno_id = d.id
+        LEFT JOIN data_sources s ON s.dno_id = h.dno_id AND s.year = h.year AND s.data_type = 'hlzf'
         WHERE h.deleted_at IS NULL AND d.deleted_at IS NULL
         "#
     );
@@ -638,10 +750,29 @@ pub async fn search_hlzf_data(
         query_builder.push_bind(status);
     }
 
-    query_builder.push(" ORDER BY h.created_at DESC, d.name ASC LIMIT ");
-    query_builder.push_bind(limit);
-    query_builder.push(" OFFSET ");
-    query_builder.push_bind(offset);
+    if versions == DataVersions::Latest {
+        query_builder.push(" AND h.superseded_by IS NULL");
+    }
+
+    match paging {
+        Paging::Offset => {
+            query_builder.push(" ORDER BY h.created_at DESC, d.name ASC LIMIT ");
+            query_builder.push_bind(limit);
+            query_builder.push(" OFFSET ");
+            query_builder.push_bind(offset);
+        }
+        Paging::Keyset { after } => {
+            if let Some(after) = after {
+                query_builder.push(" AND (h.year, h.id) > (");
+                query_builder.push_bind(after.year);
+                query_builder.push(", ");
+                query_builder.push_bind(after.id);
+                query_builder.push(")");
+            }
+            query_builder.push(" ORDER BY h.year ASC, h.id ASC LIMIT ");
+            query_builder.push_bind(limit);
+        }
+    }
 
     let query = query_builder.build_query_as::<HlzfDataWithDno>();
     let result = query.fetch_all(pool).await.map_err(AppError::Database)?;
@@ -649,6 +780,179 @@ pub async fn search_hlzf_data(
     Ok(result)
 }
 
+/// Columns of `NetzentgelteData`, for `SELECT` and `RETURNING` lists
+const NETZENTGELTE_COLUMNS: &str = "id, dno_id, year, voltage_level, leistung, arbeit, leistung_unter_2500h, \
+    arbeit_unter_2500h, raw_values, verification_status, verified_by, verified_at, verification_notes, \
+    first_seen_at, last_verified_at, version, superseded_by, created_at, updated_at";
+
+/// Current version of the Netzentgelte entry for a DNO, year and voltage level,
+/// locked until the surrounding transaction ends
+pub async fn lock_current_netzentgelte(
+    conn: &mut sqlx::PgConnection,
+    dno_id: Uuid,
+    year: i32,
+    voltage_level: &str,
+) -> Result<Option<NetzentgelteData>, AppError> {
+    let result = sqlx::query_as::<_, NetzentgelteData>(&format!(
+        "SELECT {} FROM netzentgelte_data
+         WHERE dno_id = $1 AND year = $2 AND voltage_level = $3 AND superseded_by IS NULL
+         FOR UPDATE",
+        NETZENTGELTE_COLUMNS
+    ))
+    .bind(dno_id)
+    .bind(year)
+    .bind(voltage_level)
+    .fetch_optional(conn)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(result)
+}
+
+/// Store the Netzentgelte values for a DNO, year and voltage level without
+/// losing earlier ones. Values are expected to be normalized already (see
+/// `normalize::RawNetzentgelte`).
+///
+/// The first write inserts version 1. A write with the same values only bumps
+/// `last_verified_at`. A write with different values inserts the next version,
+/// unverified and with the original `first_seen_at`, and points the prior
+/// version's `superseded_by` at it; the prior row is not changed otherwise.
+pub async fn upsert_netzentgelte_data(
+    conn: &mut sqlx::PgConnection,
+    data: CreateNetzentgelteData,
+) -> Result<NetzentgelteData, AppError> {
+    use sqlx::Connection;
+
+    // A savepoint when called inside a transaction; superseded_by is only checked on commit
+    let mut tx = conn.begin().await.map_err(AppError::Database)?;
+    let current = lock_current_netzentgelte(&mut tx, data.dno_id, data.year, &data.voltage_level).await?;
+
+    let result = match current {
+        Some(current)
+            if (current.leistung, current.arbeit, current.leistung_unter_2500h, current.arbeit_unter_2500h)
+                == (data.leistung, data.arbeit, data.leistung_unter_2500h, data.arbeit_unter_2500h) =>
+        {
+            sqlx::query_as::<_, NetzentgelteData>(&format!(
+                "UPDATE netzentgelte_data SET last_verified_at = CURRENT_TIMESTAMP WHERE id = $1 RETURNING {}",
+                NETZENTGELTE_COLUMNS
+            ))
+            .bind(current.id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::Database)?
+        }
+        current => {
+            let id = Uuid::new_v4();
+            if let Some(current) = &current {
+                sqlx::query("UPDATE netzentgelte_data SET superseded_by = $1 WHERE id = $2")
+                    .bind(id)
+                    .bind(current.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(AppError::Database)?;
+            }
+            sqlx::query_as::<_, NetzentgelteData>(&format!(
+                "INSERT INTO netzentgelte_data
+                    (id, dno_id, year, voltage_level, leistung, arbeit, leistung_unter_2500h, arbeit_unter_2500h,
+                     raw_values, version, first_seen_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, COALESCE($11, CURRENT_TIMESTAMP))
+                 RETURNING {}",
+                NETZENTGELTE_COLUMNS
+            ))
+            .bind(id)
+            .bind(data.dno_id)
+            .bind(data.year)
+            .bind(data.voltage_level)
+            .bind(data.leistung)
+            .bind(data.arbeit)
+            .bind(data.leistung_unter_2500h)
+            .bind(data.arbeit_unter_2500h)
+            .bind(data.raw_values)
+            .bind(current.as_ref().map_or(1, |current| current.version + 1))
+            .bind(current.as_ref().map(|current| current.first_seen_at))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::Database)?
+        }
+    };
+
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(result)
+}
+
+/// DNO owning a netzentgelte or hlzf entry, used to take the DNO write lock before editing it
+pub async fn get_data_entry_dno_id(pool: &PgPool, entry_id: Uuid) -> Result<Option<Uuid>, AppError> {
+    let result = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT dno_id FROM netzentgelte_data WHERE id = $1
+        UNION ALL
+        SELECT dno_id FROM hlzf_data WHERE id = $1
+        LIMIT 1
+        "#
+    )
+    .bind(entry_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(result)
+}
+
+/// Most recent data source for the DNO, year and data type of a netzentgelte or hlzf entry
+pub async fn get_data_source_for_entry(pool: &PgPool, entry_id: Uuid) -> Result<Option<DataSource>, AppError> {
+    let result = sqlx::query_as::<_, DataSource>(
+        r#"
+        SELECT ds.*
+        FROM data_sources ds
+        JOIN (
+            SELECT dno_id, year, 'netzentgelte'::data_type AS data_type FROM netzentgelte_data WHERE id = $1
+            UNION ALL
+            SELECT dno_id, year, 'hlzf'::data_type AS data_type FROM hlzf_data WHERE id = $1
+        ) entry ON ds.dno_id = entry.dno_id AND ds.year = entry.year AND ds.data_type = entry.data_type
+        ORDER BY ds.extracted_at DESC
+        LIMIT 1
+        "#
+    )
+    .bind(entry_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(result)
+}
+
+/// Latest data source for each `(dno_id, year, data_type)`, used to attach
+/// provenance to a page of search results in one query
+pub async fn get_data_sources_for_results(
+    pool: &PgPool,
+    keys: &[(Uuid, i32, String)],
+) -> Result<Vec<DataSource>, AppError> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dno_ids: Vec<Uuid> = keys.iter().map(|(dno_id, _, _)| *dno_id).collect();
+    let years: Vec<i32> = keys.iter().map(|(_, year, _)| *year).collect();
+    let data_types: Vec<String> = keys.iter().map(|(_, _, data_type)| data_type.clone()).collect();
+
+    let result = sqlx::query_as::<_, DataSource>(
+        r#"
+        SELECT DISTINCT ON (ds.dno_id, ds.year, ds.data_type) ds.*
+        FROM data_sources ds
+        JOIN UNNEST($1::uuid[], $2::int[], $3::text[]) AS k(dno_id, year, data_type)
+            ON ds.dno_id = k.dno_id AND ds.year = k.year AND ds.data_type::text = k.data_type
+        ORDER BY ds.dno_id, ds.year, ds.data_type, ds.extracted_at DESC
+        "#
+    )
+    .bind(&dno_ids)
+    .bind(&years)
+    .bind(&data_types)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(result)
+}
+
 // Dashboard and analytics functions
 pub async fn get_dashboard_stats(pool: &PgPool, user_id: Uuid) -> Result<DashboardStats, AppError> {
     // Get user's query count for today
@@ -0,0 +1,60 @@
+use crate::source_manager::{DeduplicationResult, DuplicateGroup};
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Summary of a deduplication run, kept in `dedup_runs`
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DedupRun {
+    pub id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// The admin who started the run
+    pub triggered_by: Option<Uuid>,
+    pub files_scanned: i32,
+    pub groups_found: i32,
+    pub duplicates_removed: i32,
+    pub bytes_saved: i64,
+    pub groups: Json<Vec<DuplicateGroup>>,
+}
+
+/// Record the outcome of a run under its `run_id`
+pub async fn record_dedup_run(
+    pool: &PgPool,
+    result: &DeduplicationResult,
+    triggered_by: Option<Uuid>,
+) -> Result<DedupRun, AppError> {
+    sqlx::query_as::<_, DedupRun>(
+        r#"
+        INSERT INTO dedup_runs (id, started_at, finished_at, triggered_by, files_scanned,
+                                groups_found, duplicates_removed, bytes_saved, groups)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING *
+        "#,
+    )
+    .bind(result.run_id)
+    .bind(result.started_at)
+    .bind(result.finished_at)
+    .bind(triggered_by)
+    .bind(result.files_scanned as i32)
+    .bind(result.groups.len() as i32)
+    .bind(result.duplicates_removed as i32)
+    .bind(result.bytes_saved as i64)
+    .bind(Json(&result.groups))
+    .fetch_one(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+/// Past runs, newest first
+pub async fn list_dedup_runs(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<DedupRun>, AppError> {
+    sqlx::query_as::<_, DedupRun>("SELECT * FROM dedup_runs ORDER BY started_at DESC LIMIT $1 OFFSET $2")
+        .bind(limit.clamp(1, 500))
+        .bind(offset.max(0))
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)
+}
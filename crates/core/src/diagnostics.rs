@@ -0,0 +1,186 @@
+use crate::models::YearCoverage;
+use serde::{Deserialize, Serialize};
+
+/// Why a search returned no results
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoResultsReason {
+    /// The requested DNO name or ID does not match any known DNO
+    DnoNotFound,
+    /// The DNO is known but nothing has been collected for it yet
+    NoDataForDno,
+    /// Data exists for the DNO, but not of the requested data type
+    NoDataForDataType,
+    /// Data exists for the DNO, but not for the requested year
+    NoDataForYear,
+    /// Matching rows exist but none of them are verified yet
+    FilteredByVerification,
+}
+
+/// Explanation attached to empty search responses
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchDiagnostics {
+    pub reason: NoResultsReason,
+    pub message: String,
+    pub suggestions: Vec<String>,
+    /// Years that do have data for this DNO and data type
+    pub years_with_data: Vec<i32>,
+    /// Unverified rows that matched and were hidden
+    pub unverified_matches: i64,
+}
+
+impl SearchDiagnostics {
+    pub fn dno_not_found(query: &str) -> Self {
+        Self {
+            reason: NoResultsReason::DnoNotFound,
+            message: format!("No DNO matches '{}'", query),
+            suggestions: vec![
+                "Check the spelling or search by slug/ID; see available_dnos for known DNOs".to_string(),
+                "Ask an admin to add the DNO if it is missing".to_string(),
+            ],
+            years_with_data: Vec::new(),
+            unverified_matches: 0,
+        }
+    }
+
+    /// Explain an empty verified-only search from the coverage of the DNO
+    /// (all data types, all years, all verification states). `None` when
+    /// nothing is hidden: the matching rows are verified, so the search only
+    /// came up empty because it paged past them.
+    pub fn from_coverage(coverage: &[YearCoverage], data_type: Option<&str>, year: Option<i32>) -> Option<Self> {
+        let of_type: Vec<&YearCoverage> = coverage
+            .iter()
+            .filter(|c| data_type.is_none_or(|t| c.data_type == t))
+            .collect();
+
+        let mut years_with_data: Vec<i32> = of_type.iter().map(|c| c.year).collect();
+        years_with_data.sort_unstable_by(|a, b| b.cmp(a));
+        years_with_data.dedup();
+
+        let in_scope: Vec<&YearCoverage> = of_type
+            .iter()
+            .copied()
+            .filter(|c| year.is_none_or(|y| c.year == y))
+            .collect();
+        let unverified_matches: i64 = in_scope.iter().map(|c| c.unverified).sum();
+
+        if coverage.is_empty() {
+            return Some(Self {
+                reason: NoResultsReason::NoDataForDno,
+                message: "No data has been collected for this DNO yet".to_string(),
+                suggestions: vec!["Request a crawl for this DNO from an admin".to_string()],
+                years_with_data,
+                unverified_matches,
+            });
+        }
+
+        if of_type.is_empty() {
+            let data_type = data_type.unwrap_or_default();
+            let mut types: Vec<&str> = coverage.iter().map(|c| c.data_type.as_str()).collect();
+            types.sort_unstable();
+            types.dedup();
+            let listed = types.join(", ");
+            return Some(Self {
+                reason: NoResultsReason::NoDataForDataType,
+                message: format!("No {} data for this DNO; data exists for: {}", data_type, listed),
+                suggestions: vec![
+                    format!("Search one of the data types with data ({})", listed),
+                    format!("Request a {} crawl from an admin", data_type),
+                ],
+                years_with_data,
+                unverified_matches,
+            });
+        }
+
+        if in_scope.is_empty() {
+            let year = year.unwrap_or_default();
+            let listed = years_with_data.iter().map(|y| y.to_string()).collect::<Vec<_>>().join(", ");
+            return Some(Self {
+                reason: NoResultsReason::NoDataForYear,
+                message: format!("No data for {}; data exists for: {}", year, listed),
+                suggestions: vec![
+                    format!("Search one of the years with data ({})", listed),
+                    format!("Request a crawl for {} from an admin", year),
+                ],
+                years_with_data,
+                unverified_matches,
+            });
+        }
+
+        if unverified_matches == 0 {
+            return None;
+        }
+
+        Some(Self {
+            reason: NoResultsReason::FilteredByVerification,
+            message: format!(
+                "{} matching entr{} exist but are not verified yet",
+                unverified_matches,
+                if unverified_matches == 1 { "y" } else { "ies" }
+            ),
+            suggestions: vec!["Data exists but is unverified; request admin review".to_string()],
+            years_with_data,
+            unverified_matches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage(year: i32, verified: i64, unverified: i64) -> YearCoverage {
+        YearCoverage { data_type: "netzentgelte".to_string(), year, verified, unverified }
+    }
+
+    #[test]
+    fn test_unknown_dno() {
+        let diagnostics = SearchDiagnostics::dno_not_found("Netze XY");
+        assert_eq!(diagnostics.reason, NoResultsReason::DnoNotFound);
+        assert!(diagnostics.message.contains("Netze XY"));
+    }
+
+    #[test]
+    fn test_known_dno_without_any_data() {
+        let diagnostics = SearchDiagnostics::from_coverage(&[], Some("netzentgelte"), Some(2024)).unwrap();
+        assert_eq!(diagnostics.reason, NoResultsReason::NoDataForDno);
+        assert!(diagnostics.years_with_data.is_empty());
+    }
+
+    #[test]
+    fn test_no_data_for_requested_year() {
+        let coverage = [coverage(2023, 5, 0), coverage(2022, 5, 0)];
+        let diagnostics = SearchDiagnostics::from_coverage(&coverage, Some("netzentgelte"), Some(2024)).unwrap();
+        assert_eq!(diagnostics.reason, NoResultsReason::NoDataForYear);
+        assert_eq!(diagnostics.years_with_data, vec![2023, 2022]);
+        assert!(diagnostics.message.contains("2023, 2022"));
+    }
+
+    #[test]
+    fn test_data_hidden_by_verification_filter() {
+        let coverage = [coverage(2024, 0, 3), coverage(2023, 5, 0)];
+        let diagnostics = SearchDiagnostics::from_coverage(&coverage, Some("netzentgelte"), Some(2024)).unwrap();
+        assert_eq!(diagnostics.reason, NoResultsReason::FilteredByVerification);
+        assert_eq!(diagnostics.unverified_matches, 3);
+        assert!(diagnostics.suggestions[0].contains("request admin review"));
+    }
+
+    #[test]
+    fn test_verified_rows_are_not_blamed_on_verification() {
+        // Only HLZF was collected for 2024, and all of it is verified
+        let hlzf = YearCoverage { data_type: "hlzf".to_string(), year: 2024, verified: 4, unverified: 0 };
+        let diagnostics = SearchDiagnostics::from_coverage(std::slice::from_ref(&hlzf), Some("netzentgelte"), Some(2024)).unwrap();
+        assert_eq!(diagnostics.reason, NoResultsReason::NoDataForDataType);
+        assert_eq!(diagnostics.unverified_matches, 0);
+        assert!(diagnostics.message.contains("hlzf"));
+
+        // Verified Netzentgelte exist for 2023 only
+        let coverage = [hlzf, coverage(2023, 5, 0)];
+        let diagnostics = SearchDiagnostics::from_coverage(&coverage, Some("netzentgelte"), Some(2024)).unwrap();
+        assert_eq!(diagnostics.reason, NoResultsReason::NoDataForYear);
+        assert_eq!(diagnostics.years_with_data, vec![2023]);
+
+        // Every matching row is verified: nothing was filtered out
+        assert_eq!(SearchDiagnostics::from_coverage(&coverage, Some("netzentgelte"), Some(2023)), None);
+    }
+}
@@ -39,15 +39,31 @@ pub enum AppError {
     #[error("Too many requests")]
     TooManyRequests,
 
+    #[error("Conflict: {message}")]
+    Conflict { message: String, retry_after_secs: Option<u64> },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// An external service's circuit breaker is open
+    #[error("{0}")]
+    ServiceUnavailable(#[from] crate::circuit_breaker::CircuitOpen),
+
     #[error("Internal server error: {0}")]
     InternalServerError(String),
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
 
+/// The database could not be reached, as opposed to a query that failed;
+/// worth retrying later, so it is answered with a 503
+fn database_unavailable(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) | sqlx::Error::Tls(_)
+    )
+}
+
 impl AppError {
     pub fn status_code(&self) -> StatusCode {
         match self {
@@ -55,13 +71,17 @@ impl AppError {
             AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,       // 401
             AppError::Forbidden(_) => StatusCode::FORBIDDEN,             // 403
             AppError::NotFound(_) => StatusCode::NOT_FOUND,              // 404
+            AppError::Conflict { .. } => StatusCode::CONFLICT,             // 409
             AppError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,  // 429
+            AppError::Database(e) if database_unavailable(e) => StatusCode::SERVICE_UNAVAILABLE, // 503
+            AppError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE, // 503
             _ => StatusCode::INTERNAL_SERVER_ERROR,                      // 500
         }
     }
 
     pub fn error_code(&self) -> &'static str {
         match self {
+            AppError::Database(e) if database_unavailable(e) => "database_unavailable",
             AppError::Database(_) => "database_error",
             AppError::Http(_) => "http_error",
             AppError::Json(_) => "json_error",
@@ -71,8 +91,10 @@ impl AppError {
             AppError::Forbidden(_) => "forbidden",
             AppError::BadRequest(_) => "bad_request",
             AppError::NotFound(_) => "not_found",
+            AppError::Conflict { .. } => "conflict",
             AppError::TooManyRequests => "too_many_requests",
             AppError::Io(_) => "io_error",
+            AppError::ServiceUnavailable(_) => "service_unavailable",
             AppError::InternalServerError(_) => "internal_server_error",
         }
     }
@@ -83,13 +105,58 @@ impl IntoResponse for AppError {
         let status = self.status_code();
         let request_id = Uuid::new_v4();
         
+        let retry_after = match &self {
+            AppError::Conflict { retry_after_secs, .. } => *retry_after_secs,
+            _ => None,
+        };
+
         let body = Json(json!({
             "error": self.error_code(),
             "message": self.to_string(),
-            "details": {},
+            "details": match retry_after {
+                Some(secs) => json!({ "retry_after_seconds": secs }),
+                None => json!({}),
+            },
             "request_id": request_id
         }));
         
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response.headers_mut().insert(axum::http::header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
+
+impl From<crate::cache::LockError> for AppError {
+    fn from(err: crate::cache::LockError) -> Self {
+        match err {
+            crate::cache::LockError::Held { key, retry_after } => AppError::Conflict {
+                message: format!("{} is being modified by another operation, retry shortly", key),
+                retry_after_secs: Some(retry_after.as_secs().max(1)),
+            },
+            crate::cache::LockError::Cache(e) => AppError::Cache(e.to_string()),
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreachable_database_is_503_and_failed_query_is_500() {
+        for unreachable in [sqlx::Error::PoolTimedOut, sqlx::Error::PoolClosed, sqlx::Error::Io(std::io::ErrorKind::ConnectionRefused.into())] {
+            let error = AppError::Database(unreachable);
+            assert_eq!(error.status_code(), StatusCode::SERVICE_UNAVAILABLE, "{}", error);
+            assert_eq!(error.error_code(), "database_unavailable");
+        }
+
+        let failed = AppError::Database(sqlx::Error::ColumnNotFound("arbeitspreis".to_string()));
+        assert_eq!(failed.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(failed.error_code(), "database_error");
+
+        assert_eq!(AppError::BadRequest("Invalid cursor".to_string()).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(AppError::NotFound("dno".to_string()).status_code(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,465 @@
+use crate::cache::{CacheLayer, DnoWriteLock};
+use crate::conflicts::{upsert_netzentgelte_with_conflicts, ConflictSource};
+use crate::models::{CreateNetzentgelteData, NetzentgelteData};
+use crate::normalize::{normalize_value, TariffUnit};
+use crate::verification::STATUS_FLAGGED;
+use crate::webhooks::WebhookEvent;
+use crate::AppError;
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::fmt;
+use std::str::FromStr;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Earliest year a price sheet is accepted for
+const MIN_YEAR: i32 = 2000;
+/// DNOs publish the coming year's prices in autumn, so one year ahead is plausible
+const MAX_YEARS_AHEAD: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoltageLevel {
+    #[serde(rename = "hs")]
+    Hs,
+    #[serde(rename = "hs/ms")]
+    HsMs,
+    #[serde(rename = "ms")]
+    Ms,
+    #[serde(rename = "ms/ns")]
+    MsNs,
+    #[serde(rename = "ns")]
+    Ns,
+}
+
+impl VoltageLevel {
+    /// Value stored in `netzentgelte_data.voltage_level`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoltageLevel::Hs => "hs",
+            VoltageLevel::HsMs => "hs/ms",
+            VoltageLevel::Ms => "ms",
+            VoltageLevel::MsNs => "ms/ns",
+            VoltageLevel::Ns => "ns",
+        }
+    }
+}
+
+impl FromStr for VoltageLevel {
+    type Err = ();
+
+    /// Case-insensitive, with `-` or spaces accepted in place of `/` (`HS-MS`, `MS / NS`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key: String = s
+            .trim()
+            .to_lowercase()
+            .replace('-', "/")
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        match key.as_str() {
+            "hs" => Ok(VoltageLevel::Hs),
+            "hs/ms" => Ok(VoltageLevel::HsMs),
+            "ms" => Ok(VoltageLevel::Ms),
+            "ms/ns" => Ok(VoltageLevel::MsNs),
+            "ns" => Ok(VoltageLevel::Ns),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One voltage level of an extracted Netzentgelte table, checked against the schema.
+/// Prices are in EUR/kW (Leistung) and ct/kWh (Arbeit).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetzentgelteRecord {
+    pub year: i32,
+    pub voltage_level: VoltageLevel,
+    pub leistung: Option<Decimal>,
+    pub arbeit: Option<Decimal>,
+    pub leistung_unter_2500h: Option<Decimal>,
+    pub arbeit_unter_2500h: Option<Decimal>,
+}
+
+impl NetzentgelteRecord {
+    pub fn into_create(self, dno_id: Uuid, raw_values: Option<Value>) -> CreateNetzentgelteData {
+        CreateNetzentgelteData {
+            dno_id,
+            year: self.year,
+            voltage_level: self.voltage_level.as_str().to_string(),
+            leistung: self.leistung,
+            arbeit: self.arbeit,
+            leistung_unter_2500h: self.leistung_unter_2500h,
+            arbeit_unter_2500h: self.arbeit_unter_2500h,
+            raw_values,
+        }
+    }
+}
+
+/// Why part of an extraction does not fit the schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationError {
+    /// Index into the payload's entries; `None` for the payload itself
+    pub record: Option<usize>,
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(record: Option<usize>, field: &str, message: impl Into<String>) -> Self {
+        Self { record, field: field.to_string(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.record {
+            Some(record) => write!(f, "entries[{}].{}: {}", record, self.field, self.message),
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
+
+/// Price fields of an entry: the name stored, the names extractors use and the expected unit
+const PRICE_FIELDS: [(&str, &[&str], TariffUnit); 4] = [
+    ("leistung", &["leistung", "leistungspreis"], TariffUnit::EurPerKw),
+    ("arbeit", &["arbeit", "arbeitspreis"], TariffUnit::CtPerKwh),
+    ("leistung_unter_2500h", &["leistung_unter_2500h"], TariffUnit::EurPerKw),
+    ("arbeit_unter_2500h", &["arbeit_unter_2500h"], TariffUnit::CtPerKwh),
+];
+
+/// A price as a number or a string such as `"12,34 ct/kWh"`; strings must carry
+/// `expected` or no unit at all
+fn price(value: &Value, expected: TariffUnit) -> Result<Decimal, String> {
+    match value {
+        Value::Number(number) => {
+            let text = number.to_string();
+            Decimal::from_str(&text)
+                .or_else(|_| Decimal::from_scientific(&text))
+                .map(|d| d.normalize())
+                .map_err(|_| format!("{} is not a usable number", number))
+        }
+        Value::String(raw) => {
+            let normalized = normalize_value(raw, Some(expected.as_str()));
+            match (normalized.value, normalized.unit) {
+                (Some(value), Some(unit)) if unit == expected => Ok(value),
+                (Some(_), _) => Err(format!("{:?} is not in {}", raw, expected.as_str())),
+                (None, _) => Err(format!("{:?} is not a number", raw)),
+            }
+        }
+        other => Err(format!("expected a number, got {}", other)),
+    }
+}
+
+fn plausible_year(year: i32) -> bool {
+    (MIN_YEAR..=chrono::Utc::now().year() + MAX_YEARS_AHEAD).contains(&year)
+}
+
+/// Check one entry. The record is returned whenever it can be keyed (a known
+/// voltage level and a plausible year), even with errors in its prices, so the
+/// caller can still store it for review; a price that is not a number is left out.
+fn check_entry(index: usize, entry: &Value, default_year: Option<&Value>) -> (Option<NetzentgelteRecord>, Vec<ValidationError>) {
+    let mut errors = Vec::new();
+    let at = Some(index);
+    let Some(fields) = entry.as_object() else {
+        errors.push(ValidationError::new(at, "entry", "expected an object"));
+        return (None, errors);
+    };
+
+    let voltage_level = match fields.get("voltage_level") {
+        None | Some(Value::Null) => {
+            errors.push(ValidationError::new(at, "voltage_level", "missing"));
+            None
+        }
+        Some(Value::String(level)) => match level.parse::<VoltageLevel>() {
+            Ok(level) => Some(level),
+            Err(()) => {
+                errors.push(ValidationError::new(at, "voltage_level", format!("unknown voltage level {:?}", level)));
+                None
+            }
+        },
+        Some(other) => {
+            errors.push(ValidationError::new(at, "voltage_level", format!("expected a string, got {}", other)));
+            None
+        }
+    };
+
+    let year = match fields.get("year").or(default_year) {
+        None | Some(Value::Null) => {
+            errors.push(ValidationError::new(at, "year", "missing"));
+            None
+        }
+        Some(year) => match year.as_i64().and_then(|y| i32::try_from(y).ok()).filter(|y| plausible_year(*y)) {
+            Some(year) => Some(year),
+            None => {
+                errors.push(ValidationError::new(at, "year", format!("{} is not a plausible year", year)));
+                None
+            }
+        },
+    };
+
+    let mut prices = [None; 4];
+    for (slot, (name, aliases, unit)) in prices.iter_mut().zip(PRICE_FIELDS) {
+        let Some(value) = aliases.iter().find_map(|alias| fields.get(*alias)).filter(|v| !v.is_null()) else {
+            continue;
+        };
+        match price(value, unit) {
+            Ok(price) => {
+                if price.is_sign_negative() && !price.is_zero() {
+                    errors.push(ValidationError::new(at, name, format!("{} is negative", price)));
+                }
+                *slot = Some(price);
+            }
+            Err(message) => errors.push(ValidationError::new(at, name, message)),
+        }
+    }
+    if prices.iter().all(Option::is_none) && !errors.iter().any(|e| PRICE_FIELDS.iter().any(|(name, ..)| e.field == *name)) {
+        errors.push(ValidationError::new(at, "leistung", "entry has no price"));
+    }
+
+    let [leistung, arbeit, leistung_unter_2500h, arbeit_unter_2500h] = prices;
+    let record = voltage_level.zip(year).map(|(voltage_level, year)| NetzentgelteRecord {
+        year,
+        voltage_level,
+        leistung,
+        arbeit,
+        leistung_unter_2500h,
+        arbeit_unter_2500h,
+    });
+    (record, errors)
+}
+
+/// Entries of a payload: `{"year": 2024, "entries": [...]}` (the shape the PDF
+/// prompt asks for) or a bare array; the top-level year applies to entries without one
+fn entries(value: &Value) -> Result<(&Vec<Value>, Option<&Value>), ValidationError> {
+    match value {
+        Value::Array(entries) => Ok((entries, None)),
+        Value::Object(payload) => match payload.get("entries") {
+            Some(Value::Array(entries)) => Ok((entries, payload.get("year"))),
+            _ => Err(ValidationError::new(None, "entries", "missing or not an array")),
+        },
+        _ => Err(ValidationError::new(None, "entries", "expected an object or an array")),
+    }
+}
+
+/// Check a free-form extraction (AI or table output) against the Netzentgelte
+/// schema: known voltage levels, non-negative prices in the expected units and
+/// a plausible year. Every problem is reported, not just the first.
+pub fn validate_extraction(value: &Value) -> Result<Vec<NetzentgelteRecord>, Vec<ValidationError>> {
+    let (entries, default_year) = entries(value).map_err(|e| vec![e])?;
+    if entries.is_empty() {
+        return Err(vec![ValidationError::new(None, "entries", "no entries")]);
+    }
+
+    let mut records = Vec::with_capacity(entries.len());
+    let mut errors = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let (record, entry_errors) = check_entry(index, entry, default_year);
+        match record {
+            Some(record) if entry_errors.is_empty() => records.push(record),
+            _ => errors.extend(entry_errors),
+        }
+    }
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        Err(errors)
+    }
+}
+
+/// What `persist_extraction` did with a payload
+#[derive(Debug, Default, Serialize)]
+pub struct PersistedExtraction {
+    pub stored: Vec<NetzentgelteData>,
+    /// Entries stored but flagged for an admin because they failed validation
+    pub flagged: Vec<Uuid>,
+    /// Every validation error, including those of entries that could not be stored at all
+    pub errors: Vec<ValidationError>,
+}
+
+/// Validate an extraction and store its entries through the conflict-aware
+/// upsert. Entries that fail validation but still name a voltage level and
+/// year are stored and flagged with the errors in `verification_notes`, so
+/// they reach the review queue instead of passing as regular data. Entries
+/// an admin has already decided on keep their status. The writes run under
+/// the DNO's crawl write lock, so they cannot interleave with an admin edit.
+pub async fn persist_extraction<C: CacheLayer>(
+    pool: &PgPool,
+    locks: &DnoWriteLock<C>,
+    dno_id: Uuid,
+    value: &Value,
+    source: &ConflictSource,
+) -> Result<PersistedExtraction, AppError> {
+    let mut persisted = PersistedExtraction::default();
+    let (entries, default_year) = match entries(value) {
+        Ok(entries) => entries,
+        Err(error) => {
+            persisted.errors.push(error);
+            return Ok(persisted);
+        }
+    };
+
+    let guard = locks.for_crawl(dno_id).await?;
+    let stored = store_entries(pool, dno_id, entries, default_year, source, &mut persisted).await;
+    locks.release(guard).await;
+    stored?;
+
+    if !persisted.flagged.is_empty() {
+        crate::webhooks::global().notify(WebhookEvent::EntriesFlagged {
+            entry_type: "netzentgelte".to_string(),
+            dno_id,
+            entry_ids: persisted.flagged.clone(),
+            reason: "failed validation".to_string(),
+        });
+    }
+    Ok(persisted)
+}
+
+/// The write phase of `persist_extraction`
+async fn store_entries(
+    pool: &PgPool,
+    dno_id: Uuid,
+    entries: &[Value],
+    default_year: Option<&Value>,
+    source: &ConflictSource,
+    persisted: &mut PersistedExtraction,
+) -> Result<(), AppError> {
+    for (index, entry) in entries.iter().enumerate() {
+        let (record, errors) = check_entry(index, entry, default_year);
+        let Some(record) = record else {
+            warn!("Dropping extracted entry {} of DNO {}: {} problems", index, dno_id, errors.len());
+            persisted.errors.extend(errors);
+            continue;
+        };
+
+        let (mut stored, _) =
+            upsert_netzentgelte_with_conflicts(pool, record.into_create(dno_id, Some(entry.clone())), source).await?;
+        if !errors.is_empty() && stored.verified_by.is_none() {
+            let notes = errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+            sqlx::query("UPDATE netzentgelte_data SET verification_status = $1, verification_notes = $2 WHERE id = $3")
+                .bind(STATUS_FLAGGED)
+                .bind(&notes)
+                .bind(stored.id)
+                .execute(pool)
+                .await
+                .map_err(AppError::Database)?;
+            stored.verification_status = Some(STATUS_FLAGGED.to_string());
+            stored.verification_notes = Some(notes);
+            persisted.flagged.push(stored.id);
+        }
+        persisted.errors.extend(errors);
+        persisted.stored.push(stored);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_valid_payload_yields_records() {
+        let payload = json!({
+            "year": 2024,
+            "entries": [
+                { "voltage_level": "HS", "leistungspreis": 58.21, "arbeitspreis": 1.26 },
+                { "voltage_level": "MS/NS", "leistung": "112,40 €/kW", "arbeit": "3,1 ct/kWh", "year": 2025 },
+                { "voltage_level": "ns", "arbeit": 0 },
+            ]
+        });
+
+        let records = validate_extraction(&payload).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].voltage_level, VoltageLevel::Hs);
+        assert_eq!(records[0].year, 2024);
+        assert_eq!(records[0].leistung, Some(Decimal::from_str("58.21").unwrap()));
+        assert_eq!(records[1].year, 2025);
+        assert_eq!(records[1].arbeit, Some(Decimal::from_str("3.1").unwrap()));
+        assert_eq!(records[2].arbeit, Some(Decimal::ZERO));
+        assert_eq!(records[1].clone().into_create(Uuid::nil(), None).voltage_level, "ms/ns");
+    }
+
+    #[test]
+    fn test_negative_prices_and_wrong_units_are_rejected() {
+        let payload = json!([
+            { "voltage_level": "ms", "year": 2024, "leistung": -12.5, "arbeit": 1.4 },
+            { "voltage_level": "ns", "year": 2024, "arbeit": "4,20 €/kW" },
+        ]);
+
+        let errors = validate_extraction(&payload).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].record, errors[0].field.as_str()), (Some(0), "leistung"));
+        assert!(errors[0].message.contains("negative"));
+        assert_eq!((errors[1].record, errors[1].field.as_str()), (Some(1), "arbeit"));
+
+        // Still keyed, so it can be stored flagged
+        let (record, _) = check_entry(0, &payload[0], None);
+        assert_eq!(record.unwrap().leistung, Some(Decimal::from_str("-12.5").unwrap()));
+    }
+
+    #[test]
+    fn test_missing_fields_are_reported_per_entry() {
+        let payload = json!({
+            "entries": [
+                { "leistungspreis": 58.21, "year": 2024 },
+                { "voltage_level": "Umspannung HöS", "year": 1987, "arbeitspreis": 1.2 },
+                { "voltage_level": "ms" },
+            ]
+        });
+
+        let errors = validate_extraction(&payload).unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| (e.record.unwrap(), e.field.as_str())).collect();
+        assert_eq!(
+            fields,
+            [(0, "voltage_level"), (1, "voltage_level"), (1, "year"), (2, "year"), (2, "leistung")]
+        );
+        assert_eq!(errors[0].to_string(), "entries[0].voltage_level: missing");
+        assert!(check_entry(0, &payload["entries"][0], None).0.is_none());
+
+        let not_a_table = validate_extraction(&json!({ "rows": [] })).unwrap_err();
+        assert_eq!(not_a_table[0].to_string(), "entries: missing or not an array");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_crawl_write_waits_for_admin_edit(pool: PgPool) {
+        crate::test_schema::create(&pool).await;
+        sqlx::raw_sql("INSERT INTO dnos (id, slug, name) VALUES ('00000000-0000-0000-0000-000000000001', 'netze-bw', 'Netze BW')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let dno_id = Uuid::from_u128(1);
+        let locks = DnoWriteLock::new(Arc::new(MemoryCache::new()));
+
+        let edit = locks.for_edit(dno_id).await.unwrap();
+        let crawl = tokio::spawn({
+            let (pool, locks) = (pool.clone(), locks.clone());
+            async move {
+                let payload = json!({
+                    "year": 2024,
+                    "entries": [{ "voltage_level": "ms", "leistungspreis": 58.21, "arbeitspreis": 1.26 }]
+                });
+                persist_extraction(&pool, &locks, dno_id, &payload, &ConflictSource::default()).await
+            }
+        });
+
+        // The crawl queues behind the edit instead of writing
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM netzentgelte_data").fetch_one(&pool).await.unwrap();
+        assert_eq!(stored, 0);
+        assert!(!crawl.is_finished());
+
+        locks.release(edit).await;
+        let persisted = crawl.await.unwrap().unwrap();
+        assert_eq!(persisted.stored.len(), 1);
+
+        // The crawl released its lock once the write phase ended
+        let edit = locks.for_edit(dno_id).await.unwrap();
+        assert!(edit.acquired);
+        locks.release(edit).await;
+    }
+}
@@ -0,0 +1,157 @@
+use crate::verification::STATUS_VERIFIED;
+use crate::AppError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Feed id; entry ids are `urn:uuid:<entry id>`, so they survive re-rendering
+const FEED_ID: &str = "tag:dno-crawler,2024:feed/verified";
+
+/// A verified netzentgelte or hlzf record, as listed in the feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeedEntry {
+    pub entry_id: Uuid,
+    /// `netzentgelte` or `hlzf`
+    pub entry_type: String,
+    pub dno_name: String,
+    pub dno_slug: String,
+    pub year: i32,
+    /// Voltage level, or season and period for hlzf
+    pub detail: String,
+    /// When the record was verified, or last changed if verified automatically
+    pub updated: DateTime<Utc>,
+}
+
+/// Verified records, most recently verified first. `before` pages back in
+/// time: pass the `updated` of the last entry of the previous page.
+pub async fn list_verified_entries(
+    pool: &PgPool,
+    before: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<FeedEntry>, AppError> {
+    sqlx::query_as::<_, FeedEntry>(
+        r#"
+        SELECT * FROM (
+            SELECT n.id AS entry_id, 'netzentgelte' AS entry_type, d.name AS dno_name, d.slug AS dno_slug,
+                   n.year, UPPER(n.voltage_level) AS detail,
+                   COALESCE(n.verified_at, n.updated_at, n.last_verified_at) AS updated
+            FROM netzentgelte_data n JOIN dnos d ON d.id = n.dno_id
+            WHERE n.verification_status = $1 AND n.deleted_at IS NULL
+            UNION ALL
+            SELECT h.id, 'hlzf', d.name, d.slug, h.year, h.season::text || ' ' || h.period_number,
+                   COALESCE(h.verified_at, h.updated_at, h.last_verified_at)
+            FROM hlzf_data h JOIN dnos d ON d.id = h.dno_id
+            WHERE h.verification_status = $1 AND h.deleted_at IS NULL
+        ) entries
+        WHERE $2::timestamptz IS NULL OR updated < $2
+        ORDER BY updated DESC, entry_id
+        LIMIT $3
+        "#,
+    )
+    .bind(STATUS_VERIFIED)
+    .bind(before)
+    .bind(limit.clamp(1, 200))
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+/// Render entries as an Atom feed, newest first. `prefix` is the API prefix
+/// (`/api/v1`) used for links. With `next_page`, a `rel="next"` link points
+/// at the entries before the oldest one shown.
+pub fn render_atom(prefix: &str, entries: &[FeedEntry], next_page: bool) -> String {
+    let mut entries: Vec<&FeedEntry> = entries.iter().collect();
+    entries.sort_by(|a, b| b.updated.cmp(&a.updated).then(a.entry_id.cmp(&b.entry_id)));
+    let timestamp = |at: &DateTime<Utc>| at.to_rfc3339_opts(SecondsFormat::Secs, true);
+    // An empty feed still needs an `updated`; the epoch keeps the output stable
+    let updated = entries.first().map_or_else(DateTime::default, |entry| entry.updated);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <id>{}</id>\n", FEED_ID));
+    xml.push_str("  <title>Verified DNO tariff data</title>\n");
+    xml.push_str(&format!("  <updated>{}</updated>\n", timestamp(&updated)));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}/feed.xml\"/>\n", prefix));
+    if let Some(oldest) = entries.last().filter(|_| next_page) {
+        // Full precision, so the next page does not skip entries in the same second
+        let before = oldest.updated.to_rfc3339_opts(SecondsFormat::Micros, true);
+        xml.push_str(&format!("  <link rel=\"next\" href=\"{}/feed.xml?before={}\"/>\n", prefix, before));
+    }
+
+    for entry in entries {
+        let data_type = if entry.entry_type == "hlzf" { "HLZF" } else { "Netzentgelte" };
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>urn:uuid:{}</id>\n", entry.entry_id));
+        xml.push_str(&format!(
+            "    <title>{} {} {} ({})</title>\n",
+            escape(&entry.dno_name),
+            data_type,
+            entry.year,
+            escape(&entry.detail)
+        ));
+        xml.push_str(&format!("    <updated>{}</updated>\n", timestamp(&entry.updated)));
+        xml.push_str(&format!(
+            "    <link rel=\"related\" href=\"{}/admin/data-entries/{}/source\"/>\n",
+            prefix, entry.entry_id
+        ));
+        xml.push_str(&format!(
+            "    <category term=\"{}\"/>\n    <category term=\"{}\"/>\n    <category term=\"{}\"/>\n",
+            escape(&entry.dno_slug),
+            entry.entry_type,
+            entry.year
+        ));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(detail: &str, updated: DateTime<Utc>) -> FeedEntry {
+        FeedEntry {
+            entry_id: Uuid::new_v4(),
+            entry_type: "netzentgelte".to_string(),
+            dno_name: "Netze BW".to_string(),
+            dno_slug: "netze-bw".to_string(),
+            year: 2024,
+            detail: detail.to_string(),
+            updated,
+        }
+    }
+
+    #[test]
+    fn test_newly_verified_record_is_first_entry() {
+        let earlier = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let mut entries = vec![entry("HS", earlier), entry("MS", earlier + chrono::Duration::hours(1))];
+        let before = render_atom("/api/v1", &entries, false);
+
+        let verified = entry("NS", Utc.with_ymd_and_hms(2024, 3, 2, 14, 30, 0).unwrap());
+        entries.push(verified.clone());
+        let feed = render_atom("/api/v1", &entries, true);
+
+        let first = &feed[feed.find("<entry>").unwrap()..];
+        assert!(first.starts_with(&format!("<entry>\n    <id>urn:uuid:{}</id>", verified.entry_id)));
+        assert!(first.contains("<title>Netze BW Netzentgelte 2024 (NS)</title>"));
+        assert!(feed.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <id>tag:dno-crawler,2024:feed/verified</id>"));
+        assert!(feed.contains("  <updated>2024-03-02T14:30:00Z</updated>\n  <link rel=\"self\""));
+        assert!(feed.contains("<link rel=\"next\" href=\"/api/v1/feed.xml?before=2024-03-01T09:00:00.000000Z\"/>"));
+
+        // Entries already in the feed keep their ids
+        for old in &entries[..2] {
+            let id = format!("<id>urn:uuid:{}</id>", old.entry_id);
+            assert!(before.contains(&id) && feed.contains(&id));
+        }
+    }
+}
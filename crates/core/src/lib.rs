@@ -4,9 +4,41 @@ pub mod database;
 pub mod models;
 pub mod cache;
 pub mod repository;
+pub mod diagnostics;
+pub mod normalize;
+pub mod extraction_schema;
+pub mod seed;
+pub mod verification;
+pub mod temp;
+pub mod review;
+pub mod crawl_request;
+pub mod crawl_plan;
+pub mod crawl_job;
+pub mod slug;
+pub mod conflicts;
+pub mod timeline;
+pub mod source_manager;
+pub mod admin_interface;
+pub mod dedup;
+pub mod feed;
+pub mod navigation;
+pub mod autocomplete;
+pub mod paging;
+pub mod ollama;
+pub mod live;
+pub mod metrics;
+pub mod circuit_breaker;
+pub mod webhooks;
+pub mod manual_review;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(any(test, feature = "test-schema"))]
+pub mod test_schema;
 
 pub use error::*;
 pub use config::*;
 pub use models::*;
+pub use slug::{slugify_dno, slugify_with};
+pub use diagnostics::{NoResultsReason, SearchDiagnostics};
 pub use cache::{CacheLayer, RedisCacheConfig, CacheKeys, SearchFilters};
 pub use repository::{UserRepository, SearchRepository, DnoRepository};
\ No newline at end of file
@@ -0,0 +1,151 @@
+use crate::webhooks::WebhookEvent;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events buffered per subscriber; one that falls further behind skips ahead
+const SESSION_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// One line of a running crawl's log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiveLog {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrawlSessionStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// What watchers of a session receive: log lines, then the final status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LiveEvent {
+    Log(LiveLog),
+    Finished { status: CrawlSessionStatus },
+}
+
+/// Crawl sessions that can be watched while they run. Each session has a
+/// broadcast channel; its sender lives in the registry until the session
+/// finishes, which ends every subscription after the final status.
+#[derive(Clone, Default)]
+pub struct LiveSessions {
+    channels: Arc<Mutex<HashMap<Uuid, broadcast::Sender<LiveEvent>>>>,
+}
+
+impl LiveSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a session; log through the returned handle
+    pub fn open(&self, session_id: Uuid) -> LiveCrawlSession {
+        let (sender, _) = broadcast::channel(SESSION_CHANNEL_CAPACITY);
+        self.channels.lock().unwrap().insert(session_id, sender.clone());
+        LiveCrawlSession { id: session_id, sender, sessions: self.clone(), finished: false }
+    }
+
+    /// Watch a running session; `None` once it finished or if it never existed
+    pub fn subscribe(&self, session_id: Uuid) -> Option<broadcast::Receiver<LiveEvent>> {
+        self.channels.lock().unwrap().get(&session_id).map(broadcast::Sender::subscribe)
+    }
+
+    /// Sessions running right now
+    pub fn running(&self) -> usize {
+        self.channels.lock().unwrap().len()
+    }
+}
+
+/// Publishing side of a running session. Dropping it without `finish`
+/// reports the session as failed.
+pub struct LiveCrawlSession {
+    pub id: Uuid,
+    sender: broadcast::Sender<LiveEvent>,
+    sessions: LiveSessions,
+    finished: bool,
+}
+
+impl LiveCrawlSession {
+    pub fn log(&self, level: LogLevel, message: impl Into<String>) {
+        // Nobody watching is fine
+        let _ = self.sender.send(LiveEvent::Log(LiveLog { timestamp: Utc::now(), level, message: message.into() }));
+    }
+
+    /// Send the final status and close the session's channel
+    pub fn finish(mut self, status: CrawlSessionStatus) {
+        self.close(status);
+    }
+
+    fn close(&mut self, status: CrawlSessionStatus) {
+        if std::mem::replace(&mut self.finished, true) {
+            return;
+        }
+        let _ = self.sender.send(LiveEvent::Finished { status });
+        self.sessions.channels.lock().unwrap().remove(&self.id);
+        crate::metrics::global().record_crawl(status);
+        let session_id = self.id;
+        match status {
+            CrawlSessionStatus::Completed => crate::webhooks::global().notify(WebhookEvent::CrawlCompleted { session_id }),
+            CrawlSessionStatus::Failed => crate::webhooks::global().notify(WebhookEvent::CrawlFailed { session_id }),
+            CrawlSessionStatus::Running => {}
+        }
+    }
+}
+
+impl Drop for LiveCrawlSession {
+    fn drop(&mut self) {
+        self.close(CrawlSessionStatus::Failed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_watchers_get_logs_then_the_final_status() {
+        let sessions = LiveSessions::new();
+        let id = Uuid::new_v4();
+        let session = sessions.open(id);
+        let mut watcher = sessions.subscribe(id).unwrap();
+
+        session.log(LogLevel::Info, "Fetching https://www.netze-bw.de/netzentgelte");
+        session.finish(CrawlSessionStatus::Completed);
+
+        assert!(matches!(watcher.recv().await.unwrap(), LiveEvent::Log(log) if log.level == LogLevel::Info));
+        assert_eq!(watcher.recv().await.unwrap(), LiveEvent::Finished { status: CrawlSessionStatus::Completed });
+        assert!(matches!(watcher.recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(sessions.subscribe(id).is_none());
+        assert_eq!(sessions.running(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_session_is_reported_failed() {
+        let sessions = LiveSessions::new();
+        let id = Uuid::new_v4();
+        let mut watcher = {
+            let _session = sessions.open(id);
+            sessions.subscribe(id).unwrap()
+        };
+
+        assert_eq!(watcher.recv().await.unwrap(), LiveEvent::Finished { status: CrawlSessionStatus::Failed });
+        assert_eq!(sessions.running(), 0);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::crawl_job::{CrawlJobs, CrawlSessionRecord};
+use crate::crawl_plan::CrawlContext;
+use crate::crawl_request::CrawlConstraintsPlan;
+use crate::models::Dno;
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub const REVIEW_PENDING: &str = "pending";
+pub const REVIEW_RESUBMITTED: &str = "resubmitted";
+
+/// A retried URL is crawled on its own plus the links on it
+const RETRY_CONSTRAINTS: CrawlConstraintsPlan = CrawlConstraintsPlan { max_depth: 1, max_pages: 20, timeout_secs: 300 };
+
+/// A URL the crawler gave up on because only a human can get past the
+/// failure, e.g. a login wall or an access-denied page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManualReviewRequest {
+    pub url: String,
+    /// The failure, as the crawler logged it
+    pub reason: String,
+    /// Whatever helps the reviewer: error kind, link depth, why the URL was queued
+    #[serde(default)]
+    pub context: Value,
+}
+
+/// A queued URL as stored in `manual_review_queue`
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ManualReviewItem {
+    pub id: Uuid,
+    pub url: String,
+    pub reason: String,
+    pub context: Value,
+    pub dno_id: Uuid,
+    pub year: i32,
+    pub data_type: String,
+    /// Crawl session and job that gave up on the URL
+    pub session_id: Option<Uuid>,
+    pub job_id: Option<Uuid>,
+    /// How many crawls failed on the URL while it waited
+    pub occurrences: i32,
+    /// `pending` or `resubmitted`
+    pub status: String,
+    pub retried_by: Option<Uuid>,
+    pub retried_at: Option<DateTime<Utc>>,
+    /// Session started by the retry
+    pub retry_session_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManualReviewFilter {
+    /// `pending` (default), `resubmitted` or `all`
+    pub status: Option<String>,
+    pub dno_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Queue the URLs a crawl of `context` gave up on. A URL already waiting for
+/// the same DNO, year and data type is not queued twice: its reason and
+/// context are replaced by the latest failure and its count goes up.
+pub async fn enqueue_manual_review(
+    pool: &PgPool,
+    context: &CrawlContext,
+    session_id: Option<Uuid>,
+    job_id: Option<Uuid>,
+    requests: &[ManualReviewRequest],
+) -> Result<Vec<ManualReviewItem>, AppError> {
+    let mut tx = pool.begin().await?;
+    let mut items = Vec::with_capacity(requests.len());
+    for request in requests {
+        let item: ManualReviewItem = sqlx::query_as(
+            "INSERT INTO manual_review_queue (url, reason, context, dno_id, year, data_type, session_id, job_id)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             ON CONFLICT (url, dno_id, year, data_type) WHERE status = 'pending'
+             DO UPDATE SET reason = EXCLUDED.reason, context = EXCLUDED.context,
+                           session_id = EXCLUDED.session_id, job_id = EXCLUDED.job_id,
+                           occurrences = manual_review_queue.occurrences + 1, updated_at = CURRENT_TIMESTAMP
+             RETURNING *",
+        )
+        .bind(&request.url)
+        .bind(&request.reason)
+        .bind(&request.context)
+        .bind(context.dno.id)
+        .bind(context.year)
+        .bind(&context.data_type)
+        .bind(session_id)
+        .bind(job_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        items.push(item);
+    }
+    tx.commit().await?;
+    if !items.is_empty() {
+        info!("Queued {} URLs of {} {} for manual review", items.len(), context.dno.slug, context.year);
+    }
+    Ok(items)
+}
+
+/// Queued URLs, newest first
+pub async fn list_manual_review(pool: &PgPool, filter: &ManualReviewFilter) -> Result<Vec<ManualReviewItem>, AppError> {
+    let mut query = sqlx::QueryBuilder::new("SELECT * FROM manual_review_queue WHERE TRUE");
+    match filter.status.as_deref().unwrap_or(REVIEW_PENDING) {
+        "all" => {}
+        status @ (REVIEW_PENDING | REVIEW_RESUBMITTED) => {
+            query.push(" AND status = ").push_bind(status.to_string());
+        }
+        other => return Err(AppError::BadRequest(format!("Unknown manual review status '{}'", other))),
+    }
+    if let Some(dno_id) = filter.dno_id {
+        query.push(" AND dno_id = ").push_bind(dno_id);
+    }
+    query.push(" ORDER BY updated_at DESC LIMIT ").push_bind(filter.limit.unwrap_or(50).clamp(1, 500));
+    query.push(" OFFSET ").push_bind(filter.offset.unwrap_or(0).max(0));
+
+    query
+        .build_query_as::<ManualReviewItem>()
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)
+}
+
+/// Re-submit a pending URL once an admin has dealt with what blocked it: a
+/// discovery crawl of its DNO, year and data type starts at the URL. Returns
+/// the updated item and the new session, `None` if the item does not exist,
+/// and a conflict if it was already re-submitted.
+pub async fn retry_manual_review(
+    pool: &PgPool,
+    crawl_jobs: &CrawlJobs,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<(ManualReviewItem, CrawlSessionRecord)>, AppError> {
+    // Claimed first, so two admins retrying at once start one crawl
+    let claimed: Option<ManualReviewItem> = sqlx::query_as(
+        "UPDATE manual_review_queue
+         SET status = 'resubmitted', retried_by = $2, retried_at = CURRENT_TIMESTAMP
+         WHERE id = $1 AND status = 'pending'
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some(item) = claimed else {
+        let exists: Option<String> = sqlx::query_scalar("SELECT status FROM manual_review_queue WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        return match exists {
+            None => Ok(None),
+            Some(status) => Err(AppError::Conflict {
+                message: format!("Manual review item {} is already {}", id, status),
+                retry_after_secs: None,
+            }),
+        };
+    };
+
+    let session = match start_retry(pool, crawl_jobs, &item, user_id).await {
+        Ok(session) => session,
+        Err(e) => {
+            if let Err(release) = sqlx::query(
+                "UPDATE manual_review_queue SET status = 'pending', retried_by = NULL, retried_at = NULL WHERE id = $1",
+            )
+            .bind(id)
+            .execute(pool)
+            .await
+            {
+                warn!("Failed to put manual review item {} back in the queue: {}", id, release);
+            }
+            return Err(e);
+        }
+    };
+
+    let item: ManualReviewItem =
+        sqlx::query_as("UPDATE manual_review_queue SET retry_session_id = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(session.id)
+            .fetch_one(pool)
+            .await?;
+    info!("Re-submitted {} for crawling in session {}", item.url, session.id);
+    Ok(Some((item, session)))
+}
+
+async fn start_retry(
+    pool: &PgPool,
+    crawl_jobs: &CrawlJobs,
+    item: &ManualReviewItem,
+    user_id: Uuid,
+) -> Result<CrawlSessionRecord, AppError> {
+    let dno: Dno = sqlx::query_as(
+        "SELECT id, slug, name, official_name, description, region, website, created_at, updated_at
+         FROM dnos WHERE id = $1",
+    )
+    .bind(item.dno_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("DNO {} of manual review item {}", item.dno_id, item.id)))?;
+
+    let context = CrawlContext {
+        dno,
+        year: item.year,
+        data_type: item.data_type.clone(),
+        mode: "discovery".to_string(),
+        constraints: RETRY_CONSTRAINTS,
+        ignore_robots: false,
+        bypass_http_cache: false,
+        http_cache_ttl_secs: None,
+        start_urls: vec![item.url.clone()],
+        strategy_order: None,
+        preferred_content_type: None,
+    };
+    crawl_jobs.start(Some(user_id), "discovery", vec![context]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawl_job::{CrawlJobSummary, CrawlRunner};
+    use crate::live::{LiveCrawlSession, LiveSessions};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const DNO: u128 = 1;
+
+    async fn seed(pool: &PgPool) {
+        crate::test_schema::create(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO users (id, email, password_hash, name, role) VALUES
+                ('00000000-0000-0000-0000-0000000000ad', 'admin@example.com', 'x', 'Admin', 'admin');
+            INSERT INTO dnos (id, slug, name, website) VALUES
+                ('00000000-0000-0000-0000-000000000001', 'netze-bw', 'Netze BW', 'www.netze-bw.de');
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn context() -> CrawlContext {
+        let now = Utc::now();
+        CrawlContext {
+            dno: Dno {
+                id: Uuid::from_u128(DNO),
+                slug: "netze-bw".to_string(),
+                name: "Netze BW".to_string(),
+                official_name: None,
+                description: None,
+                region: None,
+                website: Some("www.netze-bw.de".to_string()),
+                created_at: now,
+                updated_at: now,
+            },
+            year: 2024,
+            data_type: "netzentgelte".to_string(),
+            mode: "discovery".to_string(),
+            constraints: CrawlConstraintsPlan { max_depth: 2, max_pages: 50, timeout_secs: 300 },
+            ignore_robots: false,
+            bypass_http_cache: false,
+            http_cache_ttl_secs: None,
+            start_urls: Vec::new(),
+            strategy_order: None,
+            preferred_content_type: None,
+        }
+    }
+
+    fn access_denied(url: &str) -> ManualReviewRequest {
+        ManualReviewRequest {
+            url: url.to_string(),
+            reason: format!("{} returned HTTP 403", url),
+            context: json!({ "error_kind": "http", "status": 403, "depth": 1 }),
+        }
+    }
+
+    /// Records the contexts it is asked to crawl
+    #[derive(Default)]
+    struct RecordingRunner {
+        crawled: Mutex<Vec<CrawlContext>>,
+    }
+
+    #[async_trait]
+    impl CrawlRunner for RecordingRunner {
+        async fn run(&self, context: CrawlContext, _session: &LiveCrawlSession) -> Result<CrawlJobSummary, AppError> {
+            self.crawled.lock().unwrap().push(context);
+            Ok(CrawlJobSummary { completion: "complete".to_string(), pages_processed: 1, documents_found: 1, ..Default::default() })
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_access_denied_urls_are_queued_once(pool: PgPool) {
+        seed(&pool).await;
+        let url = "https://www.netze-bw.de/intern/preisblatt-2024.pdf";
+
+        let first = enqueue_manual_review(&pool, &context(), None, None, &[access_denied(url)]).await.unwrap();
+        let again = enqueue_manual_review(&pool, &context(), None, None, &[access_denied(url)]).await.unwrap();
+        assert_eq!(first[0].id, again[0].id);
+        assert_eq!(again[0].occurrences, 2);
+
+        let pending = list_manual_review(&pool, &ManualReviewFilter::default()).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].url, url);
+        assert_eq!(pending[0].status, REVIEW_PENDING);
+        assert!(pending[0].reason.contains("403"));
+        assert_eq!(pending[0].context["depth"], 1);
+
+        let unknown = ManualReviewFilter { status: Some("open".to_string()), ..Default::default() };
+        assert!(matches!(list_manual_review(&pool, &unknown).await, Err(AppError::BadRequest(_))));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_retry_resubmits_the_url_as_a_start_url(pool: PgPool) {
+        seed(&pool).await;
+        let url = "https://www.netze-bw.de/intern/preisblatt-2024.pdf";
+        let queued = enqueue_manual_review(&pool, &context(), None, None, &[access_denied(url)]).await.unwrap();
+        let runner = Arc::new(RecordingRunner::default());
+        let jobs = CrawlJobs::new(pool.clone(), runner.clone(), LiveSessions::new());
+        let admin = Uuid::from_u128(0xad);
+
+        let (item, session) = retry_manual_review(&pool, &jobs, queued[0].id, admin).await.unwrap().unwrap();
+        assert_eq!(item.status, REVIEW_RESUBMITTED);
+        assert_eq!(item.retried_by, Some(admin));
+        assert_eq!(item.retry_session_id, Some(session.id));
+        assert_eq!(session.user_id, Some(admin));
+
+        for _ in 0..250 {
+            if !runner.crawled.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let crawled = runner.crawled.lock().unwrap().clone();
+        assert_eq!(crawled.len(), 1);
+        assert_eq!(crawled[0].start_urls, [url]);
+        assert_eq!((crawled[0].dno.slug.as_str(), crawled[0].year), ("netze-bw", 2024));
+
+        // Re-submitted once only; unknown items are not found
+        assert!(matches!(retry_manual_review(&pool, &jobs, item.id, admin).await, Err(AppError::Conflict { .. })));
+        assert!(retry_manual_review(&pool, &jobs, Uuid::new_v4(), admin).await.unwrap().is_none());
+        assert!(list_manual_review(&pool, &ManualReviewFilter::default()).await.unwrap().is_empty());
+
+        // Failing again after the retry queues the URL afresh
+        let requeued = enqueue_manual_review(&pool, &context(), None, None, &[access_denied(url)]).await.unwrap();
+        assert_ne!(requeued[0].id, item.id);
+    }
+}
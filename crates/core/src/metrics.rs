@@ -0,0 +1,228 @@
+use crate::cache::metrics::extract_key_pattern;
+use crate::cache::trace::{CacheOp, CacheOutcome};
+use crate::live::CrawlSessionStatus;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Upper bounds of the HTTP request duration buckets, in seconds
+const DURATION_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+/// Upper bounds of the extraction confidence buckets
+const CONFIDENCE_BUCKETS: [f64; 9] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    /// Observations per bucket, not cumulative; `+Inf` is the count
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self { bounds, buckets: vec![0; bounds.len()], sum: 0.0, count: 0 }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, observed) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += observed;
+            let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, labels, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, labels, self.count);
+        let labels = labels.trim_end_matches(',');
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Families {
+    /// `(key prefix, outcome)` of cache reads
+    cache_reads: BTreeMap<(String, &'static str), u64>,
+    /// Finished crawl sessions by final status
+    crawls: BTreeMap<&'static str, u64>,
+    extraction_confidence: BTreeMap<String, Histogram>,
+    /// Source file integrity checks by result
+    integrity_checks: BTreeMap<&'static str, u64>,
+    /// `(method, route, status)`
+    http_durations: BTreeMap<(String, String, u16), Histogram>,
+}
+
+/// Counters and histograms rendered in the Prometheus text format at `/metrics`.
+/// Clones share their data.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    families: Arc<Mutex<Families>>,
+}
+
+static GLOBAL: OnceLock<MetricsRegistry> = OnceLock::new();
+
+/// Registry of this process. Cache reads, crawl sessions and extractions are
+/// recorded here from wherever they happen; the API exposes it via `AppState`.
+pub fn global() -> MetricsRegistry {
+    GLOBAL.get_or_init(MetricsRegistry::default).clone()
+}
+
+/// Escape a label value as the text format requires
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a cache read by key prefix (e.g. `search:netzentgelte:*`); writes are not counted
+    pub fn record_cache(&self, op: CacheOp, key: &str, outcome: CacheOutcome) {
+        if op != CacheOp::Get {
+            return;
+        }
+        let mut families = self.families.lock().unwrap();
+        *families.cache_reads.entry((extract_key_pattern(key), outcome.as_str())).or_default() += 1;
+    }
+
+    pub fn record_crawl(&self, status: CrawlSessionStatus) {
+        let status = match status {
+            CrawlSessionStatus::Running => return,
+            CrawlSessionStatus::Completed => "completed",
+            CrawlSessionStatus::Failed => "failed",
+        };
+        *self.families.lock().unwrap().crawls.entry(status).or_default() += 1;
+    }
+
+    /// Source files found intact and corrupted by one integrity scan
+    pub fn record_integrity(&self, intact: usize, corrupted: usize) {
+        let mut families = self.families.lock().unwrap();
+        *families.integrity_checks.entry("intact").or_default() += intact as u64;
+        *families.integrity_checks.entry("corrupted").or_default() += corrupted as u64;
+    }
+
+    /// Confidence (0 to 1) of data produced by an extraction method
+    pub fn observe_extraction(&self, method: &str, confidence: f64) {
+        self.families
+            .lock()
+            .unwrap()
+            .extraction_confidence
+            .entry(method.to_string())
+            .or_insert_with(|| Histogram::new(&CONFIDENCE_BUCKETS))
+            .observe(confidence);
+    }
+
+    /// `route` is the matched route pattern, not the concrete path, to keep label values bounded
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, duration: Duration) {
+        self.families
+            .lock()
+            .unwrap()
+            .http_durations
+            .entry((method.to_string(), route.to_string(), status))
+            .or_insert_with(|| Histogram::new(&DURATION_BUCKETS))
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Everything recorded so far, in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP dno_cache_requests_total Cache reads by key prefix and outcome\n");
+        out.push_str("# TYPE dno_cache_requests_total counter\n");
+        for ((prefix, outcome), count) in &families.cache_reads {
+            let _ = writeln!(out, "dno_cache_requests_total{{prefix=\"{}\",outcome=\"{}\"}} {}", label(prefix), outcome, count);
+        }
+
+        out.push_str("# HELP dno_cache_hit_ratio Share of cache reads that hit, by key prefix\n");
+        out.push_str("# TYPE dno_cache_hit_ratio gauge\n");
+        let mut prefixes: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+        for ((prefix, outcome), count) in &families.cache_reads {
+            let (hits, total) = prefixes.entry(prefix.as_str()).or_default();
+            if *outcome == CacheOutcome::Hit.as_str() {
+                *hits += count;
+            }
+            *total += count;
+        }
+        for (prefix, (hits, total)) in prefixes {
+            let _ = writeln!(out, "dno_cache_hit_ratio{{prefix=\"{}\"}} {}", label(prefix), hits as f64 / total as f64);
+        }
+
+        out.push_str("# HELP dno_crawls_total Finished crawl sessions by status\n");
+        out.push_str("# TYPE dno_crawls_total counter\n");
+        for (status, count) in &families.crawls {
+            let _ = writeln!(out, "dno_crawls_total{{status=\"{}\"}} {}", status, count);
+        }
+
+        out.push_str("# HELP dno_extraction_confidence Confidence of extracted data by extraction method\n");
+        out.push_str("# TYPE dno_extraction_confidence histogram\n");
+        for (method, histogram) in &families.extraction_confidence {
+            histogram.render(&mut out, "dno_extraction_confidence", &format!("method=\"{}\",", label(method)));
+        }
+
+        out.push_str("# HELP dno_source_integrity_checks_total Source file integrity checks by result\n");
+        out.push_str("# TYPE dno_source_integrity_checks_total counter\n");
+        for (result, count) in &families.integrity_checks {
+            let _ = writeln!(out, "dno_source_integrity_checks_total{{result=\"{}\"}} {}", result, count);
+        }
+
+        out.push_str("# HELP dno_http_request_duration_seconds HTTP request durations by route\n");
+        out.push_str("# TYPE dno_http_request_duration_seconds histogram\n");
+        for ((method, route, status), histogram) in &families.http_durations {
+            let labels = format!("method=\"{}\",route=\"{}\",status=\"{}\",", label(method), label(route), status);
+            histogram.render(&mut out, "dno_http_request_duration_seconds", &labels);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_groups_cache_reads_by_prefix() {
+        let metrics = MetricsRegistry::new();
+        metrics.record_cache(CacheOp::Get, "search:netzentgelte:abc", CacheOutcome::Miss);
+        metrics.record_cache(CacheOp::Set, "search:netzentgelte:abc", CacheOutcome::Write);
+        metrics.record_cache(CacheOp::Get, "search:netzentgelte:abc", CacheOutcome::Hit);
+        metrics.record_cache(CacheOp::Get, "search:netzentgelte:def", CacheOutcome::Hit);
+        metrics.record_cache(CacheOp::Get, "search:netzentgelte:ghi", CacheOutcome::Miss);
+
+        let text = metrics.render();
+        assert!(text.contains("dno_cache_requests_total{prefix=\"search:netzentgelte:*\",outcome=\"hit\"} 2\n"));
+        assert!(text.contains("dno_cache_requests_total{prefix=\"search:netzentgelte:*\",outcome=\"miss\"} 2\n"));
+        assert!(text.contains("dno_cache_hit_ratio{prefix=\"search:netzentgelte:*\"} 0.5\n"));
+        assert!(!text.contains("write"));
+    }
+
+    #[test]
+    fn test_histograms_are_cumulative() {
+        let metrics = MetricsRegistry::new();
+        metrics.observe_extraction("html_table", 0.75);
+        metrics.observe_extraction("html_table", 0.25);
+        metrics.observe_extraction("ocr", 0.55);
+        metrics.record_crawl(CrawlSessionStatus::Completed);
+        metrics.record_crawl(CrawlSessionStatus::Failed);
+        metrics.record_crawl(CrawlSessionStatus::Completed);
+
+        let text = metrics.render();
+        assert!(text.contains("dno_extraction_confidence_bucket{method=\"html_table\",le=\"0.2\"} 0\n"));
+        assert!(text.contains("dno_extraction_confidence_bucket{method=\"html_table\",le=\"0.3\"} 1\n"));
+        assert!(text.contains("dno_extraction_confidence_bucket{method=\"html_table\",le=\"0.8\"} 2\n"));
+        assert!(text.contains("dno_extraction_confidence_bucket{method=\"html_table\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("dno_extraction_confidence_sum{method=\"html_table\"} 1\n"));
+        assert!(text.contains("dno_extraction_confidence_count{method=\"ocr\"} 1\n"));
+        assert!(text.contains("dno_crawls_total{status=\"completed\"} 2\n"));
+        assert!(text.contains("dno_crawls_total{status=\"failed\"} 1\n"));
+    }
+}
@@ -12,6 +12,22 @@ pub enum UserRole {
     Admin,
 }
 
+impl UserRole {
+    /// Rank in the hierarchy admin > user > pending (guest)
+    fn level(&self) -> u8 {
+        match self {
+            UserRole::Pending => 0,
+            UserRole::User => 1,
+            UserRole::Admin => 2,
+        }
+    }
+
+    /// Whether this role grants everything `required` may do
+    pub fn includes(&self, required: &UserRole) -> bool {
+        self.level() >= required.level()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq)]
 #[sqlx(type_name = "job_status", rename_all = "lowercase")]
 pub enum JobStatus {
@@ -38,6 +54,16 @@ pub enum DataType {
     All,
 }
 
+impl DataType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataType::Netzentgelte => "netzentgelte",
+            DataType::Hlzf => "hlzf",
+            DataType::All => "all",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq)]
 #[sqlx(type_name = "season", rename_all = "lowercase")]
 pub enum Season {
@@ -122,10 +148,20 @@ pub struct NetzentgelteData {
     pub arbeit: Option<rust_decimal::Decimal>,
     pub leistung_unter_2500h: Option<rust_decimal::Decimal>,
     pub arbeit_unter_2500h: Option<rust_decimal::Decimal>,
+    /// Original strings, units and normalization flags per value (see `normalize`)
+    pub raw_values: Option<serde_json::Value>,
     pub verification_status: Option<String>,
     pub verified_by: Option<Uuid>,
     pub verified_at: Option<DateTime<Utc>>,
     pub verification_notes: Option<String>,
+    /// When the entry first appeared in the dataset
+    pub first_seen_at: DateTime<Utc>,
+    /// When a crawl last found the stored values unchanged
+    pub last_verified_at: DateTime<Utc>,
+    /// 1 for the first values stored, counting up with each re-crawl that changed them
+    pub version: i32,
+    /// The version that replaced this one; `None` for the current version
+    pub superseded_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -139,6 +175,7 @@ pub struct CreateNetzentgelteData {
     pub arbeit: Option<rust_decimal::Decimal>,
     pub leistung_unter_2500h: Option<rust_decimal::Decimal>,
     pub arbeit_unter_2500h: Option<rust_decimal::Decimal>,
+    pub raw_values: Option<serde_json::Value>,
 }
 
 // HLZF data model
@@ -155,6 +192,10 @@ pub struct HlzfData {
     pub verified_by: Option<Uuid>,
     pub verified_at: Option<DateTime<Utc>>,
     pub verification_notes: Option<String>,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_verified_at: DateTime<Utc>,
+    pub version: i32,
+    pub superseded_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -187,6 +228,8 @@ pub struct DataSource {
     pub extraction_region: Option<serde_json::Value>,
     pub ocr_text: Option<String>,
     pub extraction_log: Option<serde_json::Value>,
+    /// `{ field: ExtractedField }` for every value taken from this source
+    pub extracted_data: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -205,6 +248,34 @@ pub struct CreateDataSource {
     pub extraction_region: Option<serde_json::Value>,
     pub ocr_text: Option<String>,
     pub extraction_log: Option<serde_json::Value>,
+    pub extracted_data: Option<serde_json::Value>,
+}
+
+/// Where in a source document an extracted value came from and how it was read
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldProvenance {
+    pub source_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// CSS selector of the element, for HTML sources
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    /// Archive the source document was unpacked from, for members of a zip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+    pub method: String,
+    pub confidence: f64,
+}
+
+/// One extracted value with its provenance, as stored in `data_sources.extracted_data`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtractedField {
+    pub value: serde_json::Value,
+    pub provenance: FieldProvenance,
 }
 
 // User model
@@ -497,6 +568,11 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegisterRequest {
     pub email: String,
@@ -587,6 +663,10 @@ pub struct NetzentgelteDataWithDno {
     pub verified_by: Option<Uuid>,
     pub verified_at: Option<DateTime<Utc>>,
     pub verification_notes: Option<String>,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_verified_at: DateTime<Utc>,
+    pub version: i32,
+    pub superseded_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
@@ -596,6 +676,10 @@ pub struct NetzentgelteDataWithDno {
     pub dno_name: String,
     pub dno_official_name: Option<String>,
     pub dno_region: Option<String>,
+    // Data source fields (prefixed), all null when no source is on record
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub source: SourceColumns,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -614,6 +698,10 @@ pub struct HlzfDataWithDno {
     pub verified_by: Option<Uuid>,
     pub verified_at: Option<DateTime<Utc>>,
     pub verification_notes: Option<String>,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_verified_at: DateTime<Utc>,
+    pub version: i32,
+    pub superseded_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
@@ -623,6 +711,10 @@ pub struct HlzfDataWithDno {
     pub dno_name: String,
     pub dno_official_name: Option<String>,
     pub dno_region: Option<String>,
+    // Data source fields (prefixed), all null when no source is on record
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub source: SourceColumns,
 }
 
 // Dashboard and statistics DTOs
@@ -643,6 +735,15 @@ pub struct DnoInfo {
     pub region: Option<String>,
 }
 
+/// Row counts for one data type and year, split by verification status
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq)]
+pub struct YearCoverage {
+    pub data_type: String,
+    pub year: i32,
+    pub verified: i64,
+    pub unverified: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AvailableFilters {
     pub years: Vec<i32>,
@@ -659,6 +760,10 @@ pub struct SearchByDnoRequest {
     pub dno_id: Option<Uuid>,
     pub year: Option<i32>,
     pub data_type: Option<String>,
+    /// Page size, 1 to `paging::MAX_SEARCH_LIMIT` (default 50)
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -667,6 +772,10 @@ pub struct SearchByYearRequest {
     pub dno_name: Option<String>,
     pub dno_id: Option<Uuid>,
     pub data_type: Option<String>,
+    /// Page size, 1 to `paging::MAX_SEARCH_LIMIT` (default 50)
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -677,6 +786,19 @@ pub struct SearchByDataTypeRequest {
     pub year: Option<i32>,
 }
 
+/// One lookup of a `/search/batch` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSearchQuery {
+    pub dno_name: String,
+    pub year: Option<i32>,
+    pub data_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSearchRequest {
+    pub queries: Vec<BatchSearchQuery>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchFilters {
     pub dno_name: Option<String>,
@@ -686,6 +808,29 @@ pub struct SearchFilters {
     pub region: Option<String>,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// `latest` (default) or `all`
+    pub version: Option<DataVersions>,
+}
+
+/// Which versions of each entry a search returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataVersions {
+    /// Only the current version
+    #[default]
+    Latest,
+    /// Superseded versions as well
+    All,
+}
+
+impl DataVersions {
+    /// Part of the search cache key; `Latest` adds nothing, so existing keys stay valid
+    pub fn cache_key_part(&self) -> &'static str {
+        match self {
+            DataVersions::Latest => "",
+            DataVersions::All => "versions:all",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -698,17 +843,94 @@ pub struct SearchResult {
     pub data: serde_json::Value,
     pub source: Option<SourceInfo>,
     pub last_updated: DateTime<Utc>,
+    /// When the value first appeared in the dataset
+    pub first_seen_at: Option<DateTime<Utc>>,
+    /// When a crawl last confirmed the value
+    pub last_verified_at: Option<DateTime<Utc>>,
+    /// Version of the entry; only set for data types that keep versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    /// Set on superseded versions, which only `version=all` searches return
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<Uuid>,
+    /// When the data was crawled: its source's extraction time, else the
+    /// last crawl that confirmed it
+    #[serde(default)]
+    pub crawled_at: Option<DateTime<Utc>>,
+    /// Whole days since `crawled_at` when the response was built
+    #[serde(default)]
+    pub age_days: Option<i64>,
+}
+
+/// How current the data of a search response is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Freshness {
+    /// The latest `crawled_at` among the results
+    pub newest_crawled_at: Option<DateTime<Utc>>,
+    /// Even the newest result was crawled more than the threshold ago
+    pub stale: bool,
+    /// Set with `stale`: a new crawl would likely find newer data
+    pub suggested_recrawl: bool,
+}
+
+impl Freshness {
+    /// Set `age_days` of every result as of `now` and flag the results as
+    /// stale when the newest was crawled more than `stale_after_days` ago.
+    /// Results without a crawl time are never stale.
+    pub fn assess(results: &mut [SearchResult], stale_after_days: i64, now: DateTime<Utc>) -> Self {
+        for result in results.iter_mut() {
+            result.age_days = result.crawled_at.map(|crawled| (now - crawled).num_days().max(0));
+        }
+        let newest = results.iter().filter_map(|result| result.crawled_at).max();
+        let stale = newest.is_some_and(|newest| (now - newest).num_days() > stale_after_days);
+        Self { newest_crawled_at: newest, stale, suggested_recrawl: stale }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SourceInfo {
     pub id: Uuid,
+    /// How the data was obtained: `file`, `table` or `api`
     pub file_type: String,
-    pub file_url: Option<String>,
+    pub source_url: Option<String>,
+    /// Hex SHA-256 of the source file
+    pub file_hash: Option<String>,
     pub page: Option<i32>,
+    /// Extraction confidence between 0 and 1
+    pub confidence: Option<f64>,
     pub extracted_at: DateTime<Utc>,
 }
 
+/// The `data_sources` row a search row was extracted from, as LEFT JOINed
+/// onto the search query
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct SourceColumns {
+    pub source_id: Option<Uuid>,
+    pub source_type: Option<String>,
+    pub source_url: Option<String>,
+    pub source_file_hash: Option<String>,
+    pub source_confidence: Option<rust_decimal::Decimal>,
+    pub source_page: Option<i32>,
+    pub source_extracted_at: Option<DateTime<Utc>>,
+}
+
+impl SourceColumns {
+    /// `None` when the row has no source on record
+    pub fn into_source_info(self) -> Option<SourceInfo> {
+        use rust_decimal::prelude::ToPrimitive;
+
+        Some(SourceInfo {
+            id: self.source_id?,
+            file_type: self.source_type?,
+            source_url: self.source_url,
+            file_hash: self.source_file_hash,
+            page: self.source_page,
+            confidence: self.source_confidence.and_then(|c| c.to_f64()),
+            extracted_at: self.source_extracted_at?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResponse {
     pub total: u32,
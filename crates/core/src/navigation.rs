@@ -0,0 +1,251 @@
+use crate::crawl_request::{resolve_dno, FieldError};
+use crate::{AppError, Dno};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use url::Url;
+use uuid::Uuid;
+
+/// Upper bound on the steps of one path
+pub const MAX_NAVIGATION_STEPS: usize = 20;
+
+/// What one step of a navigation path does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NavigationAction {
+    /// Load `url`
+    Visit,
+    /// Follow the link on the current page whose text contains `link_text`, or go to `url`
+    FollowLink,
+    /// Fetch the document at `url` or behind `link_text` and store it; only as the last step
+    Download,
+}
+
+impl NavigationAction {
+    pub const ALL: [NavigationAction; 3] = [NavigationAction::Visit, NavigationAction::FollowLink, NavigationAction::Download];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NavigationAction::Visit => "visit",
+            NavigationAction::FollowLink => "follow_link",
+            NavigationAction::Download => "download",
+        }
+    }
+}
+
+/// One step of a validated path. `url` may be relative to the current page
+/// for `follow_link` and `download`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NavigationStep {
+    pub action: NavigationAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_text: Option<String>,
+}
+
+/// A step as submitted; `action` is checked by `NavigationPathRequest::validate`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NavigationStepRequest {
+    pub action: String,
+    pub url: Option<String>,
+    pub link_text: Option<String>,
+}
+
+/// A hand-authored way to a DNO's data, as submitted by an operator
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NavigationPathRequest {
+    /// DNO id, slug or name
+    pub dno: String,
+    pub name: Option<String>,
+    pub steps: Vec<NavigationStepRequest>,
+}
+
+/// A stored navigation path, replayed by reverse crawls
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NavigationPath {
+    pub id: Uuid,
+    pub dno_id: Uuid,
+    pub dno_name: String,
+    pub name: Option<String>,
+    pub steps: Json<Vec<NavigationStep>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl NavigationPathRequest {
+    /// Resolve the DNO and check every step: a known action, an absolute
+    /// http(s) URL for the first `visit`, a URL or link text for the others,
+    /// and `download` only at the end
+    pub fn validate<'a>(&self, known_dnos: &'a [Dno]) -> Result<(&'a Dno, Vec<NavigationStep>), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let dno = resolve_dno(known_dnos, &self.dno);
+        if dno.is_none() {
+            errors.push(FieldError::new("dno", format!("unknown DNO '{}'", self.dno)));
+        }
+        if self.steps.is_empty() || self.steps.len() > MAX_NAVIGATION_STEPS {
+            errors.push(FieldError::new("steps", format!("must have 1 to {} steps", MAX_NAVIGATION_STEPS)));
+        }
+
+        let mut steps = Vec::with_capacity(self.steps.len());
+        for (i, step) in self.steps.iter().enumerate() {
+            let field = |name: &str| format!("steps[{}].{}", i, name);
+            let Some(action) = NavigationAction::ALL.into_iter().find(|a| a.as_str() == step.action.trim()) else {
+                let known: Vec<&str> = NavigationAction::ALL.iter().map(NavigationAction::as_str).collect();
+                errors.push(FieldError::new(field("action"), format!("must be one of {}", known.join(", "))));
+                continue;
+            };
+            let url = step.url.as_deref().map(str::trim).filter(|url| !url.is_empty());
+            let link_text = step.link_text.as_deref().map(str::trim).filter(|text| !text.is_empty());
+
+            match (action, url) {
+                (NavigationAction::Visit, None) => errors.push(FieldError::new(field("url"), "is required for visit")),
+                (NavigationAction::Visit, Some(url)) => {
+                    if let Err(message) = absolute_http_url(url) {
+                        errors.push(FieldError::new(field("url"), message));
+                    }
+                }
+                (_, None) if link_text.is_none() => {
+                    errors.push(FieldError::new(field("link_text"), "a URL or link text is required"))
+                }
+                (_, Some(url)) if i == 0 => {
+                    if let Err(message) = absolute_http_url(url) {
+                        errors.push(FieldError::new(field("url"), message));
+                    }
+                }
+                (_, Some(url)) => {
+                    // Relative to the page reached so far
+                    let base = Url::parse("https://relative.invalid/").expect("static URL");
+                    if !base.join(url).is_ok_and(|joined| matches!(joined.scheme(), "http" | "https")) {
+                        errors.push(FieldError::new(field("url"), format!("'{}' is not a valid http(s) URL", url)));
+                    }
+                }
+                _ => {}
+            }
+            if i == 0 && action != NavigationAction::Visit && url.is_none() {
+                errors.push(FieldError::new(field("url"), "the first step needs an absolute URL to start from"));
+            }
+            if action == NavigationAction::Download && i + 1 != self.steps.len() {
+                errors.push(FieldError::new(field("action"), "download must be the last step"));
+            }
+
+            steps.push(NavigationStep {
+                action,
+                url: url.map(str::to_string),
+                link_text: link_text.map(str::to_string),
+            });
+        }
+
+        match dno {
+            Some(dno) if errors.is_empty() => Ok((dno, steps)),
+            _ => Err(errors),
+        }
+    }
+}
+
+fn absolute_http_url(url: &str) -> Result<(), String> {
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some() => Ok(()),
+        _ => Err(format!("'{}' is not an absolute http(s) URL", url)),
+    }
+}
+
+pub async fn create_navigation_path(
+    pool: &PgPool,
+    dno_id: Uuid,
+    name: Option<&str>,
+    steps: &[NavigationStep],
+    created_by: Option<Uuid>,
+) -> Result<NavigationPath, AppError> {
+    sqlx::query_as::<_, NavigationPath>(
+        r#"
+        WITH inserted AS (
+            INSERT INTO navigation_paths (dno_id, name, steps, created_by) VALUES ($1, $2, $3, $4) RETURNING *
+        )
+        SELECT inserted.*, d.name AS dno_name FROM inserted JOIN dnos d ON d.id = inserted.dno_id
+        "#,
+    )
+    .bind(dno_id)
+    .bind(name)
+    .bind(Json(steps))
+    .bind(created_by)
+    .fetch_one(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+pub async fn get_navigation_path(pool: &PgPool, id: Uuid) -> Result<Option<NavigationPath>, AppError> {
+    sqlx::query_as::<_, NavigationPath>(
+        "SELECT p.*, d.name AS dno_name FROM navigation_paths p JOIN dnos d ON d.id = p.dno_id WHERE p.id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+/// Paths stored for a DNO, newest first
+pub async fn list_navigation_paths(pool: &PgPool, dno_id: Uuid) -> Result<Vec<NavigationPath>, AppError> {
+    sqlx::query_as::<_, NavigationPath>(
+        "SELECT p.*, d.name AS dno_name FROM navigation_paths p JOIN dnos d ON d.id = p.dno_id
+         WHERE p.dno_id = $1 ORDER BY p.created_at DESC",
+    )
+    .bind(dno_id)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn netze_bw() -> Dno {
+        Dno {
+            id: Uuid::new_v4(),
+            slug: "netze-bw".to_string(),
+            name: "Netze BW".to_string(),
+            official_name: None,
+            description: None,
+            region: None,
+            website: Some("netze-bw.de".to_string()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_invalid_urls_and_unknown_actions_are_reported() {
+        let known = vec![netze_bw()];
+        let request: NavigationPathRequest = serde_json::from_value(serde_json::json!({
+            "dno": "netze-bw",
+            "steps": [
+                { "action": "visit", "url": "ftp://netze.example/" },
+                { "action": "hover", "link_text": "Netzentgelte" },
+                { "action": "download", "link_text": "Preisblatt" },
+                { "action": "follow_link" }
+            ]
+        }))
+        .unwrap();
+
+        let errors = request.validate(&known).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["steps[0].url", "steps[1].action", "steps[2].action", "steps[3].link_text"]);
+
+        let request: NavigationPathRequest = serde_json::from_value(serde_json::json!({
+            "dno": "Netze BW",
+            "steps": [
+                { "action": "visit", "url": "https://netze.example/" },
+                { "action": "follow_link", "link_text": "Netzentgelte" },
+                { "action": "download", "url": "/files/preisblatt-2024.pdf" }
+            ]
+        }))
+        .unwrap();
+        let (dno, steps) = request.validate(&known).unwrap();
+        assert_eq!(dno.slug, "netze-bw");
+        assert_eq!(steps[2].action, NavigationAction::Download);
+    }
+}
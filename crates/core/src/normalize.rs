@@ -0,0 +1,351 @@
+use crate::models::CreateNetzentgelteData;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Canonical units Netzentgelte values are stored in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TariffUnit {
+    /// Arbeitspreis
+    #[serde(rename = "ct/kWh")]
+    CtPerKwh,
+    /// Leistungspreis (per year)
+    #[serde(rename = "EUR/kW")]
+    EurPerKw,
+    /// Grund-/Messpreis
+    #[serde(rename = "EUR/a")]
+    EurPerYear,
+}
+
+impl TariffUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TariffUnit::CtPerKwh => "ct/kWh",
+            TariffUnit::EurPerKw => "EUR/kW",
+            TariffUnit::EurPerYear => "EUR/a",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "detail")]
+pub enum NormalizationFlag {
+    /// A unit suffix was present but not one we know how to convert
+    UnknownUnit(String),
+    /// No unit in the value and none supplied by the caller
+    MissingUnit,
+    /// The numeric part could not be parsed
+    InvalidNumber,
+}
+
+/// A parsed tariff value: the canonical number plus what it was parsed from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NormalizedValue {
+    /// Value converted to `unit`; for an unknown unit, the number as written
+    pub value: Option<Decimal>,
+    pub unit: Option<TariffUnit>,
+    pub original: String,
+    pub original_unit: Option<String>,
+    pub flag: Option<NormalizationFlag>,
+}
+
+impl NormalizedValue {
+    pub fn is_flagged(&self) -> bool {
+        self.flag.is_some()
+    }
+}
+
+/// Parse a number written in German (`1.234,56`) or plain (`1234.56`) notation.
+/// Spaces, non-breaking spaces and apostrophes are accepted as thousands separators.
+pub fn parse_german_number(input: &str) -> Option<Decimal> {
+    let cleaned: String = input
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '\''))
+        .map(|c| if c == '−' { '-' } else { c })
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+
+    let canonical = if cleaned.contains(',') {
+        // German: dots group thousands, the single comma is the decimal separator
+        if cleaned.matches(',').count() > 1 || !valid_groups(cleaned.split(',').next()?) {
+            return None;
+        }
+        cleaned.replace('.', "").replace(',', ".")
+    } else if cleaned.matches('.').count() > 1 || is_thousands_grouped(&cleaned) {
+        // `1.234` or `1.234.567` without a comma are thousands separators
+        if !valid_groups(&cleaned) {
+            return None;
+        }
+        cleaned.replace('.', "")
+    } else {
+        cleaned
+    };
+
+    let unsigned = canonical.strip_prefix(['-', '+']).unwrap_or(&canonical);
+    if unsigned.is_empty() || !unsigned.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    Decimal::from_str(&canonical).ok()
+}
+
+/// Currency and unit suffixes `parse_german_amount` strips besides the tariff units
+const BARE_UNITS: [&str; 3] = ["eur", "ct", "%"];
+
+/// A number as `parse_german_number` reads it, after stripping a unit or
+/// currency suffix such as `ct/kWh`, `€/kW` or `€`. The suffix is not
+/// converted; any other trailing text makes the input malformed.
+pub fn parse_german_amount(input: &str) -> Option<Decimal> {
+    let (number, unit) = split_unit(input);
+    if unit.is_some_and(|unit| canonical_unit(unit).is_none() && !BARE_UNITS.contains(&unit_key(unit).as_str())) {
+        return None;
+    }
+    parse_german_number(number)
+}
+
+/// `parse_german_amount` as a float: `"1.234,56"` → 1234.56, `"12,5 ct/kWh"` → 12.5
+pub fn parse_german_decimal(input: &str) -> Option<f64> {
+    parse_german_amount(input)?.to_f64()
+}
+
+/// Every group after the first thousands separator has exactly three digits
+fn valid_groups(integer_part: &str) -> bool {
+    let mut groups = integer_part.split('.');
+    let first = groups.next().unwrap_or("");
+    let first = first.strip_prefix(['-', '+']).unwrap_or(first);
+    if !integer_part.contains('.') {
+        return true;
+    }
+    (1..=3).contains(&first.len()) && groups.all(|g| g.len() == 3)
+}
+
+/// `1.234` reads as one thousand in German text, but `0.567` is still a decimal
+fn is_thousands_grouped(s: &str) -> bool {
+    s.split_once('.').is_some_and(|(head, rest)| {
+        rest.len() == 3 && !matches!(head.trim_start_matches(['-', '+']), "" | "0")
+    })
+}
+
+/// Split `"12,5 ct/kWh"` into the numeric part and the unit suffix
+fn split_unit(input: &str) -> (&str, Option<&str>) {
+    let input = input.trim();
+    let split_at = input
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+' | '−' | ' ' | '\u{a0}' | '\u{202f}' | '\'')))
+        .map(|(i, _)| i);
+
+    match split_at {
+        Some(i) => (input[..i].trim(), Some(input[i..].trim()).filter(|u| !u.is_empty())),
+        None => (input, None),
+    }
+}
+
+/// Map a unit as written to its canonical unit and the factor that converts into it
+fn canonical_unit(unit: &str) -> Option<(TariffUnit, Decimal)> {
+    let hundred = Decimal::from(100);
+    match unit_key(unit).as_str() {
+        "ct/kwh" => Some((TariffUnit::CtPerKwh, Decimal::ONE)),
+        "eur/kwh" => Some((TariffUnit::CtPerKwh, hundred)),
+        "eur/kw" | "eur/kwa" | "eur/kw/a" | "eur/(kwa)" | "eur/kwjahr" | "eur/kw/jahr" => {
+            Some((TariffUnit::EurPerKw, Decimal::ONE))
+        }
+        "ct/kw" | "ct/kwa" | "ct/kw/a" => Some((TariffUnit::EurPerKw, Decimal::ONE / hundred)),
+        "eur/a" | "eur/jahr" | "eur/year" => Some((TariffUnit::EurPerYear, Decimal::ONE)),
+        _ => None,
+    }
+}
+
+/// A unit as written, lowercased with `€`/`euro` as `eur`, `cent` as `ct`
+/// and without spaces, dots and multiplication signs
+fn unit_key(unit: &str) -> String {
+    unit.to_lowercase()
+        .replace("€", "eur")
+        .replace("euro", "eur")
+        .replace("cent", "ct")
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '.' | '*' | '·'))
+        .collect()
+}
+
+/// Normalize a raw extracted value such as `"1.234,56 €/a"`. `default_unit` is
+/// used when the value itself carries no unit, e.g. when it came from a table
+/// column whose header named the unit.
+pub fn normalize_value(raw: &str, default_unit: Option<&str>) -> NormalizedValue {
+    let (number, written_unit) = split_unit(raw);
+    let unit_text = written_unit.or(default_unit);
+
+    let mut normalized = NormalizedValue {
+        value: parse_german_number(number),
+        unit: None,
+        original: raw.to_string(),
+        original_unit: unit_text.map(str::to_string),
+        flag: None,
+    };
+
+    if normalized.value.is_none() {
+        normalized.flag = Some(NormalizationFlag::InvalidNumber);
+        return normalized;
+    }
+
+    match unit_text.map(|u| (u, canonical_unit(u))) {
+        Some((_, Some((unit, factor)))) => {
+            normalized.value = normalized.value.map(|v| (v * factor).normalize());
+            normalized.unit = Some(unit);
+        }
+        Some((u, None)) => normalized.flag = Some(NormalizationFlag::UnknownUnit(u.to_string())),
+        None => normalized.flag = Some(NormalizationFlag::MissingUnit),
+    }
+
+    normalized
+}
+
+/// Netzentgelte row as extracted, before normalization
+#[derive(Debug, Clone, Default)]
+pub struct RawNetzentgelte {
+    pub dno_id: Uuid,
+    pub year: i32,
+    pub voltage_level: String,
+    pub leistung: Option<String>,
+    pub arbeit: Option<String>,
+    pub leistung_unter_2500h: Option<String>,
+    pub arbeit_unter_2500h: Option<String>,
+}
+
+impl RawNetzentgelte {
+    /// Convert into a row ready to persist. Leistung defaults to EUR/kW and
+    /// Arbeit to ct/kWh when the cell has no unit; the originals are kept in
+    /// `raw_values` and any flagged field leaves its numeric column empty.
+    pub fn normalize(self) -> (CreateNetzentgelteData, Vec<(String, NormalizedValue)>) {
+        let fields = [
+            ("leistung", self.leistung, TariffUnit::EurPerKw),
+            ("arbeit", self.arbeit, TariffUnit::CtPerKwh),
+            ("leistung_unter_2500h", self.leistung_unter_2500h, TariffUnit::EurPerKw),
+            ("arbeit_unter_2500h", self.arbeit_unter_2500h, TariffUnit::CtPerKwh),
+        ];
+
+        let mut values = [None, None, None, None];
+        let mut raw_values = serde_json::Map::new();
+        let mut flagged = Vec::new();
+
+        for (slot, (name, raw, expected)) in values.iter_mut().zip(fields) {
+            let Some(raw) = raw else { continue };
+            let mut normalized = normalize_value(&raw, Some(expected.as_str()));
+            if normalized.unit.is_some_and(|unit| unit != expected) {
+                // Recognized, but the wrong kind of price for this column
+                normalized.flag = Some(NormalizationFlag::UnknownUnit(normalized.original_unit.clone().unwrap_or_default()));
+            }
+
+            raw_values.insert(name.to_string(), serde_json::to_value(&normalized).unwrap_or_default());
+            if normalized.is_flagged() {
+                flagged.push((name.to_string(), normalized));
+            } else {
+                *slot = normalized.value;
+            }
+        }
+
+        let [leistung, arbeit, leistung_unter_2500h, arbeit_unter_2500h] = values;
+        let data = CreateNetzentgelteData {
+            dno_id: self.dno_id,
+            year: self.year,
+            voltage_level: self.voltage_level,
+            leistung,
+            arbeit,
+            leistung_unter_2500h,
+            arbeit_unter_2500h,
+            raw_values: Some(serde_json::Value::Object(raw_values)),
+        };
+        (data, flagged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_comma_decimal() {
+        assert_eq!(parse_german_number("0,00"), Some(dec("0.00")));
+        assert_eq!(parse_german_number("12,5"), Some(dec("12.5")));
+
+        let value = normalize_value("5,67 ct/kWh", None);
+        assert_eq!(value.value, Some(dec("5.67")));
+        assert_eq!(value.unit, Some(TariffUnit::CtPerKwh));
+        assert_eq!(value.original, "5,67 ct/kWh");
+        assert!(!value.is_flagged());
+
+        // EUR/kWh is converted to ct/kWh
+        let value = normalize_value("0,0567 €/kWh", None);
+        assert_eq!(value.value, Some(dec("5.67")));
+        assert_eq!(value.unit, Some(TariffUnit::CtPerKwh));
+    }
+
+    #[test]
+    fn test_thousands_separator() {
+        assert_eq!(parse_german_number("1.234,56"), Some(dec("1234.56")));
+        assert_eq!(parse_german_number("1.234.567"), Some(dec("1234567")));
+        assert_eq!(parse_german_number("1 234,5"), Some(dec("1234.5")));
+        assert_eq!(parse_german_number("12.5"), Some(dec("12.5")));
+        assert_eq!(parse_german_number("0.567"), Some(dec("0.567")));
+        assert_eq!(parse_german_number("1.23,4"), None);
+        assert_eq!(parse_german_number("abc"), None);
+
+        let value = normalize_value("1.234,56 €/a", None);
+        assert_eq!(value.value, Some(dec("1234.56")));
+        assert_eq!(value.unit, Some(TariffUnit::EurPerYear));
+    }
+
+    #[test]
+    fn test_german_decimal_strips_units() {
+        assert_eq!(parse_german_decimal("1.234,56"), Some(1234.56));
+        assert_eq!(parse_german_decimal("0,00"), Some(0.0));
+        assert_eq!(parse_german_decimal("12,5 ct/kWh"), Some(12.5));
+        assert_eq!(parse_german_decimal("63,18 €/kW"), Some(63.18));
+        assert_eq!(parse_german_decimal("1.234,50 EUR"), Some(1234.5));
+        assert_eq!(parse_german_amount("-0,5 %"), Some(dec("-0.5")));
+
+        for malformed in ["", "abc", "ct/kWh", "1,2,3", "1.23,4", "12,5 Gulden", "12,5x"] {
+            assert_eq!(parse_german_decimal(malformed), None, "{:?}", malformed);
+        }
+    }
+
+    #[test]
+    fn test_unknown_unit_is_flagged() {
+        let value = normalize_value("12,34 MWh/Monat", None);
+        assert_eq!(value.value, Some(dec("12.34")));
+        assert_eq!(value.unit, None);
+        assert_eq!(value.flag, Some(NormalizationFlag::UnknownUnit("MWh/Monat".to_string())));
+
+        assert_eq!(normalize_value("12,34", None).flag, Some(NormalizationFlag::MissingUnit));
+    }
+
+    #[test]
+    fn test_raw_row_keeps_originals_and_drops_flagged_values() {
+        let (data, flagged) = RawNetzentgelte {
+            year: 2024,
+            voltage_level: "ms".to_string(),
+            leistung: Some("1.234,50 €/kWa".to_string()),
+            arbeit: Some("3,2".to_string()),
+            arbeit_unter_2500h: Some("7 Gulden".to_string()),
+            ..Default::default()
+        }
+        .normalize();
+
+        assert_eq!(data.leistung, Some(dec("1234.5")));
+        assert_eq!(data.arbeit, Some(dec("3.2")));
+        assert_eq!(data.arbeit_unter_2500h, None);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].0, "arbeit_unter_2500h");
+
+        let raw = data.raw_values.unwrap();
+        assert_eq!(raw["leistung"]["original"], "1.234,50 €/kWa");
+        assert_eq!(raw["leistung"]["unit"], "EUR/kW");
+    }
+}
@@ -0,0 +1,385 @@
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use crate::{AppError, DataType, OllamaConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Instructions sent with every query; the model answers with JSON only
+const INTERPRET_PROMPT: &str = r#"You classify search queries about German electricity distribution network operators (DNOs).
+Answer with a JSON object only, using these keys:
+- "intent": "search" (data of one DNO or year), "compare" (several DNOs or years), "list_dnos" (which DNOs exist) or "unknown"
+- "dno_name": the DNO named in the query, spelled as written, or null
+- "year": the four-digit year asked for, or null
+- "data_type": "netzentgelte" (network charges, Netzentgelte, Preisblatt) or "hlzf" (Hochlastzeitfenster, peak load windows), or null
+- "confidence": how sure you are of the whole interpretation, between 0 and 1
+Query: "#;
+
+/// What a free-text query asks for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryIntent {
+    /// Data of one DNO or year
+    Search,
+    /// Data of several DNOs or years side by side
+    Compare,
+    /// Which DNOs are known
+    ListDnos,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+/// A query as interpreted by the model, ready to become search parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessedQuery {
+    pub query: String,
+    pub intent: QueryIntent,
+    pub dno_name: Option<String>,
+    pub year: Option<i32>,
+    /// `netzentgelte` or `hlzf`; `None` when the query names neither
+    pub data_type: Option<String>,
+    /// Between 0 and 1, as reported by the model
+    pub confidence: f64,
+}
+
+/// The model's answer before validation; models get types wrong, so
+/// everything is optional and the year may come as a string
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ModelAnswer {
+    intent: QueryIntent,
+    dno_name: Option<String>,
+    year: Option<Value>,
+    data_type: Option<String>,
+    confidence: Option<f64>,
+}
+
+impl ModelAnswer {
+    fn into_processed(self, query: &str) -> ProcessedQuery {
+        let year = match self.year {
+            Some(Value::Number(year)) => year.as_i64().and_then(|year| i32::try_from(year).ok()),
+            Some(Value::String(year)) => year.trim().parse().ok(),
+            _ => None,
+        };
+        let data_type = self
+            .data_type
+            .map(|data_type| data_type.trim().to_lowercase())
+            .filter(|data_type| [DataType::Netzentgelte.as_str(), DataType::Hlzf.as_str()].contains(&data_type.as_str()));
+        ProcessedQuery {
+            query: query.to_string(),
+            intent: self.intent,
+            dno_name: self.dno_name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()),
+            year: year.filter(|year| (1990..=2100).contains(year)),
+            data_type,
+            confidence: self.confidence.filter(|c| c.is_finite()).unwrap_or(0.0).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Lowercased with whitespace collapsed, so trivially different spellings
+/// of a query share one interpretation
+pub fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Cost of one model call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AiCallStats {
+    /// Model that answered, as reported by Ollama
+    pub model: String,
+    /// Wall time of the HTTP round trip
+    pub duration_ms: u64,
+    /// Ollama's `prompt_eval_count`; 0 when the prompt came from its cache
+    pub prompt_tokens: u64,
+    /// Ollama's `eval_count`
+    pub completion_tokens: u64,
+}
+
+/// A model's response text with what producing it cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AIResponse {
+    pub text: String,
+    pub stats: AiCallStats,
+}
+
+/// Totals over every call a service (and its clones) made since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OllamaStats {
+    pub calls: u64,
+    pub total_duration_ms: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+#[derive(Debug, Default)]
+struct StatsCounters {
+    calls: AtomicU64,
+    total_duration_ms: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+}
+
+impl StatsCounters {
+    fn add(&self, stats: &AiCallStats) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.total_duration_ms.fetch_add(stats.duration_ms, Ordering::Relaxed);
+        self.prompt_tokens.fetch_add(stats.prompt_tokens, Ordering::Relaxed);
+        self.completion_tokens.fetch_add(stats.completion_tokens, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> OllamaStats {
+        OllamaStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            total_duration_ms: self.total_duration_ms.load(Ordering::Relaxed),
+            prompt_tokens: self.prompt_tokens.load(Ordering::Relaxed),
+            completion_tokens: self.completion_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Client for the local Ollama server
+#[derive(Debug, Clone)]
+pub struct OllamaService {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    counters: Arc<StatsCounters>,
+    /// Fails calls fast while Ollama is down
+    breaker: CircuitBreaker,
+}
+
+impl OllamaService {
+    pub fn new(config: &OllamaConfig) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(config.timeout)).build()?;
+        Ok(Self {
+            client,
+            url: config.url.trim_end_matches('/').to_string(),
+            model: config.model.clone(),
+            counters: Arc::default(),
+            breaker: CircuitBreaker::new("ollama", CircuitBreakerConfig::from_env("OLLAMA")),
+        })
+    }
+
+    /// Use this breaker instead of one configured from `OLLAMA_BREAKER_*`
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Ask the model what `query` is after. Nothing is searched.
+    pub async fn interpret_query(&self, query: &str) -> Result<ProcessedQuery, AppError> {
+        let response = self.generate_json(&format!("{}{}", INTERPRET_PROMPT, query)).await?;
+        let answer: ModelAnswer = serde_json::from_str(&response.text)
+            .map_err(|e| AppError::InternalServerError(format!("Ollama returned an unreadable interpretation: {}", e)))?;
+        Ok(answer.into_processed(query))
+    }
+
+    /// Calls, durations and tokens summed over this service's lifetime
+    pub fn stats(&self) -> OllamaStats {
+        self.counters.snapshot()
+    }
+
+    /// Run `prompt` with JSON output forced and return the model's response
+    /// text, which callers parse into their own shape. Fails with
+    /// `AppError::ServiceUnavailable` while the circuit breaker is open.
+    pub async fn generate_json(&self, prompt: &str) -> Result<AIResponse, AppError> {
+        let request = json!({
+            "model": self.model,
+            "prompt": prompt,
+            "format": "json",
+            "stream": false,
+            "options": { "temperature": 0 },
+        });
+        let started = Instant::now();
+        let reply: Value = self
+            .breaker
+            .call(async {
+                self.client
+                    .post(format!("{}/api/generate", self.url))
+                    .json(&request)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<Value>()
+                    .await
+            })
+            .await??;
+
+        let stats = AiCallStats {
+            model: reply["model"].as_str().unwrap_or(&self.model).to_string(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            prompt_tokens: reply["prompt_eval_count"].as_u64().unwrap_or(0),
+            completion_tokens: reply["eval_count"].as_u64().unwrap_or(0),
+        };
+        self.counters.add(&stats);
+        info!(
+            model = %stats.model,
+            duration_ms = stats.duration_ms,
+            prompt_tokens = stats.prompt_tokens,
+            completion_tokens = stats.completion_tokens,
+            "Ollama call finished"
+        );
+
+        let text = reply["response"]
+            .as_str()
+            .ok_or_else(|| AppError::InternalServerError("Ollama reply has no response text".to_string()))?;
+        Ok(AIResponse { text: text.to_string(), stats })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Token counts the mock reports for every call
+    pub const PROMPT_EVAL_COUNT: u64 = 26;
+    pub const EVAL_COUNT: u64 = 42;
+
+    /// An Ollama server that answers every generate call with `answer` as
+    /// the model's response text. Returns its URL and a request counter.
+    pub async fn mock_ollama(answer: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 8192];
+                // Read head and body before answering
+                loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buffer.extend_from_slice(&chunk[..n]);
+                    let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") else { continue };
+                    let head = String::from_utf8_lossy(&buffer[..end]).to_lowercase();
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if buffer.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                let body = serde_json::json!({
+                    "model": "llama3",
+                    "response": answer,
+                    "done": true,
+                    "prompt_eval_count": PROMPT_EVAL_COUNT,
+                    "eval_count": EVAL_COUNT,
+                })
+                .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(url: String) -> OllamaService {
+        OllamaService::new(&OllamaConfig { url, model: "llama3".to_string(), timeout: 5 }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_model_answer_becomes_processed_query() {
+        let (url, _) = testing::mock_ollama(
+            r#"{"intent": "search", "dno_name": "Netze BW", "year": 2023, "data_type": "Netzentgelte", "confidence": 0.92}"#,
+        )
+        .await;
+
+        let processed = service(url).interpret_query("Netzentgelte Netze BW 2023").await.unwrap();
+        assert_eq!(
+            processed,
+            ProcessedQuery {
+                query: "Netzentgelte Netze BW 2023".to_string(),
+                intent: QueryIntent::Search,
+                dno_name: Some("Netze BW".to_string()),
+                year: Some(2023),
+                data_type: Some("netzentgelte".to_string()),
+                confidence: 0.92,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sloppy_answers_are_tidied() {
+        let (url, _) = testing::mock_ollama(
+            r#"{"intent": "weather", "dno_name": " ", "year": "2024", "data_type": "strompreise", "confidence": 7}"#,
+        )
+        .await;
+
+        let processed = service(url).interpret_query("wie wird das wetter 2024").await.unwrap();
+        assert_eq!(processed.intent, QueryIntent::Unknown);
+        assert_eq!(processed.dno_name, None);
+        assert_eq!(processed.year, Some(2024));
+        assert_eq!(processed.data_type, None);
+        assert_eq!(processed.confidence, 1.0);
+
+        let (url, _) = testing::mock_ollama("Netze BW, 2023").await;
+        assert!(matches!(service(url).interpret_query("Netze BW 2023").await, Err(AppError::InternalServerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_token_counts_are_surfaced_and_summed() {
+        let (url, _) = testing::mock_ollama(r#"{"intent": "list_dnos", "confidence": 0.8}"#).await;
+        let ollama = service(url);
+
+        let response = ollama.generate_json("Welche Netzbetreiber gibt es?").await.unwrap();
+        assert_eq!(response.stats.model, "llama3");
+        assert_eq!(response.stats.prompt_tokens, testing::PROMPT_EVAL_COUNT);
+        assert_eq!(response.stats.completion_tokens, testing::EVAL_COUNT);
+
+        // Clones share the totals
+        ollama.clone().interpret_query("Welche Netzbetreiber gibt es?").await.unwrap();
+        let stats = ollama.stats();
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.prompt_tokens, 2 * testing::PROMPT_EVAL_COUNT);
+        assert_eq!(stats.completion_tokens, 2 * testing::EVAL_COUNT);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_ollama_opens_the_circuit() {
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let breaker = CircuitBreaker::new(
+            "ollama",
+            CircuitBreakerConfig { failure_threshold: 2, cooldown: Duration::from_secs(60) },
+        );
+        let ollama = service(format!("http://{}", closed)).with_breaker(breaker);
+
+        for _ in 0..2 {
+            assert!(matches!(ollama.interpret_query("Netze BW 2023").await, Err(AppError::Http(_))));
+        }
+        let error = ollama.interpret_query("Netze BW 2023").await.unwrap_err();
+        assert!(matches!(error, AppError::ServiceUnavailable(_)), "{}", error);
+        assert_eq!(error.status_code(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(ollama.breaker().state(), crate::circuit_breaker::CircuitState::Open);
+    }
+
+    #[test]
+    fn test_normalized_queries_match() {
+        assert_eq!(normalize_query("  Netzentgelte   Netze BW\t2023 "), normalize_query("netzentgelte netze bw 2023"));
+    }
+}
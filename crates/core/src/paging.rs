@@ -0,0 +1,90 @@
+use crate::AppError;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Page size used when a search request gives none
+pub const DEFAULT_SEARCH_LIMIT: i64 = 50;
+/// Largest page a search request may ask for
+pub const MAX_SEARCH_LIMIT: i64 = 200;
+
+/// The last row of a page in `(year, id)` order; the next page starts after
+/// it. Written as `<year>:<id>`, e.g. `2024:0b7f…`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SearchCursor {
+    pub year: i32,
+    pub id: Uuid,
+}
+
+impl fmt::Display for SearchCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.year, self.id)
+    }
+}
+
+impl FromStr for SearchCursor {
+    type Err = AppError;
+
+    fn from_str(cursor: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::BadRequest(format!("Invalid cursor '{}'", cursor));
+        let (year, id) = cursor.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            year: year.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// How a search pages through its rows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Paging {
+    /// Newest rows first, skipping `offset` rows
+    #[default]
+    Offset,
+    /// Rows in `(year, id)` order after the cursor, from the start without one
+    Keyset { after: Option<SearchCursor> },
+}
+
+impl Paging {
+    /// Part of the search cache key; offset paging adds nothing, so existing keys stay valid
+    pub fn cache_key_part(&self) -> String {
+        match self {
+            Paging::Offset => String::new(),
+            Paging::Keyset { after: None } => "keyset".to_string(),
+            Paging::Keyset { after: Some(cursor) } => format!("keyset:{}", cursor),
+        }
+    }
+}
+
+/// Check a requested page size: `DEFAULT_SEARCH_LIMIT` when absent, between 1
+/// and `MAX_SEARCH_LIMIT` otherwise
+pub fn validate_limit(limit: Option<i64>) -> Result<i64, AppError> {
+    match limit {
+        None => Ok(DEFAULT_SEARCH_LIMIT),
+        Some(limit) if (1..=MAX_SEARCH_LIMIT).contains(&limit) => Ok(limit),
+        Some(limit) => Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {}, got {}",
+            MAX_SEARCH_LIMIT, limit
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trips_and_rejects_garbage() {
+        let cursor = SearchCursor { year: 2024, id: Uuid::new_v4() };
+        assert_eq!(cursor.to_string().parse::<SearchCursor>().unwrap(), cursor);
+
+        for garbage in ["", "2024", "2024:not-a-uuid", "zwanzig:0b7f6c3e-8f1a-4a55-9a41-5b3c0f7f2d10"] {
+            assert!(matches!(garbage.parse::<SearchCursor>(), Err(AppError::BadRequest(_))), "{}", garbage);
+        }
+
+        assert_eq!(validate_limit(None).unwrap(), DEFAULT_SEARCH_LIMIT);
+        assert_eq!(validate_limit(Some(200)).unwrap(), 200);
+        assert!(validate_limit(Some(201)).is_err());
+        assert!(validate_limit(Some(0)).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use crate::{
+    cache::CacheLayer,
+    paging::{Paging, SearchCursor},
+    repository::SearchRepository,
+    AppError, DataType, DataVersions, DnoInfo, HlzfDataWithDno, NetzentgelteDataWithDno, SearchResult,
+};
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Filters shared by every data type search
+#[derive(Debug, Clone, Default)]
+pub struct DataTypeQuery<'a> {
+    pub dno_id: Option<Uuid>,
+    pub dno_name: Option<&'a str>,
+    pub year: Option<i32>,
+    pub verification_status: Option<&'a str>,
+    pub limit: i64,
+    pub offset: i64,
+    pub paging: Paging,
+    pub versions: DataVersions,
+}
+
+/// A searchable data type: how to query it and how to shape its rows into search results
+#[async_trait]
+pub trait DataTypeSource<C: CacheLayer>: Send + Sync {
+    /// Name used in requests and in `SearchResult::data_type`
+    fn name(&self) -> &'static str;
+
+    async fn search(
+        &self,
+        repo: &SearchRepository<C>,
+        query: &DataTypeQuery<'_>,
+    ) -> Result<Vec<SearchResult>, AppError>;
+
+    /// Total matches ignoring limit/offset; `None` if the type has no count query
+    async fn count(
+        &self,
+        _repo: &SearchRepository<C>,
+        _query: &DataTypeQuery<'_>,
+    ) -> Result<Option<i64>, AppError> {
+        Ok(None)
+    }
+}
+
+/// Results of a registry-driven search
+#[derive(Debug, Clone)]
+pub struct DataTypeSearch {
+    pub results: Vec<SearchResult>,
+    pub total: i64,
+    /// Where the next page starts, for keyset searches with more rows
+    pub next_cursor: Option<SearchCursor>,
+}
+
+/// Registry mapping each data type to its query and result shaping, so search
+/// handlers iterate generically instead of matching on type names
+pub struct DataTypeRegistry<C: CacheLayer> {
+    sources: Vec<Arc<dyn DataTypeSource<C>>>,
+}
+
+impl<C: CacheLayer + 'static> Default for DataTypeRegistry<C> {
+    /// Registry with every built-in `DataType`
+    fn default() -> Self {
+        Self::empty()
+            .register(NetzentgelteSource)
+            .register(HlzfSource)
+    }
+}
+
+impl<C: CacheLayer + 'static> DataTypeRegistry<C> {
+    pub fn empty() -> Self {
+        Self { sources: Vec::new() }
+    }
+
+    /// Register a data type; a later registration with the same name replaces the earlier one
+    pub fn register(mut self, source: impl DataTypeSource<C> + 'static) -> Self {
+        self.sources.retain(|s| s.name() != source.name());
+        self.sources.push(Arc::new(source));
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.sources.iter().any(|s| s.name() == name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.sources.iter().map(|s| s.name()).collect()
+    }
+
+    /// Search one registered data type, or all of them when `data_type` is `None`.
+    /// Across all types the limit and offset are split evenly per type; keyset
+    /// searches instead merge every type's rows in `(year, id)` order.
+    pub async fn search(
+        &self,
+        repo: &SearchRepository<C>,
+        data_type: Option<&str>,
+        query: &DataTypeQuery<'_>,
+    ) -> Result<DataTypeSearch, AppError> {
+        if let Paging::Keyset { .. } = query.paging {
+            return self.search_keyset(repo, data_type, query).await;
+        }
+        if let Some(name) = data_type {
+            let source = self
+                .sources
+                .iter()
+                .find(|s| s.name() == name)
+                .ok_or_else(|| AppError::BadRequest(format!("Unknown data type: {}", name)))?;
+
+            let results = source.search(repo, query).await?;
+            let total = match source.count(repo, query).await? {
+                Some(total) => total,
+                None => results.len() as i64,
+            };
+            return Ok(DataTypeSearch { results, total, next_cursor: None });
+        }
+
+        let per_type = (self.sources.len() as i64).max(1);
+        let split = DataTypeQuery {
+            limit: query.limit / per_type,
+            offset: query.offset / per_type,
+            ..query.clone()
+        };
+
+        let mut results = Vec::new();
+        for source in &self.sources {
+            results.extend(source.search(repo, &split).await?);
+        }
+        let total = results.len() as i64;
+
+        Ok(DataTypeSearch { results, total, next_cursor: None })
+    }
+
+    /// One keyset page: each searched type's first `limit + 1` rows after the
+    /// cursor, merged in `(year, id)` order. The extra row only tells whether
+    /// there is a next page.
+    async fn search_keyset(
+        &self,
+        repo: &SearchRepository<C>,
+        data_type: Option<&str>,
+        query: &DataTypeQuery<'_>,
+    ) -> Result<DataTypeSearch, AppError> {
+        let sources: Vec<&Arc<dyn DataTypeSource<C>>> = match data_type {
+            Some(name) => vec![self
+                .sources
+                .iter()
+                .find(|s| s.name() == name)
+                .ok_or_else(|| AppError::BadRequest(format!("Unknown data type: {}", name)))?],
+            None => self.sources.iter().collect(),
+        };
+
+        let probe = DataTypeQuery { limit: query.limit + 1, offset: 0, ..query.clone() };
+        let mut results = Vec::new();
+        let mut total = 0;
+        for source in &sources {
+            let found = source.search(repo, &probe).await?;
+            total += match source.count(repo, query).await? {
+                Some(count) => count,
+                None => found.len() as i64,
+            };
+            results.extend(found);
+        }
+        results.sort_by_key(|result| (result.year, result.id));
+
+        let has_more = results.len() as i64 > query.limit;
+        results.truncate(query.limit.max(0) as usize);
+        let next_cursor = results
+            .last()
+            .filter(|_| has_more)
+            .map(|last| SearchCursor { year: last.year, id: last.id });
+        Ok(DataTypeSearch { results, total, next_cursor })
+    }
+}
+
+fn dno_info(id: Uuid, name: String, slug: String, region: Option<String>) -> DnoInfo {
+    DnoInfo { id, name, slug, region }
+}
+
+/// Netzentgelte (network charges) per voltage level
+pub struct NetzentgelteSource;
+
+#[async_trait]
+impl<C: CacheLayer> DataTypeSource<C> for NetzentgelteSource {
+    fn name(&self) -> &'static str {
+        DataType::Netzentgelte.as_str()
+    }
+
+    async fn search(
+        &self,
+        repo: &SearchRepository<C>,
+        query: &DataTypeQuery<'_>,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let rows = repo.search_netzentgelte_data(query).await?;
+
+        Ok(rows.into_iter().map(|entry: NetzentgelteDataWithDno| SearchResult {
+            id: entry.id,
+            dno: dno_info(entry.dno_id_full, entry.dno_name, entry.dno_slug, entry.dno_region),
+            year: entry.year,
+            data_type: DataType::Netzentgelte.as_str().to_string(),
+            status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
+            data: json!({
+                "netzentgelte": {
+                    "voltage_level": entry.voltage_level,
+                    "leistung": entry.leistung,
+                    "arbeit": entry.arbeit,
+                    "leistung_unter_2500h": entry.leistung_unter_2500h,
+                    "arbeit_unter_2500h": entry.arbeit_unter_2500h
+                }
+            }),
+            crawled_at: entry.source.source_extracted_at.or(Some(entry.last_verified_at)),
+            age_days: None,
+            source: entry.source.into_source_info(),
+            last_updated: entry.updated_at,
+            first_seen_at: Some(entry.first_seen_at),
+            last_verified_at: Some(entry.last_verified_at),
+            version: Some(entry.version),
+            superseded_by: entry.superseded_by,
+        }).collect())
+    }
+
+    async fn count(
+        &self,
+        repo: &SearchRepository<C>,
+        query: &DataTypeQuery<'_>,
+    ) -> Result<Option<i64>, AppError> {
+        repo.count_netzentgelte_data(query.dno_id, query.dno_name, query.year, query.verification_status, query.versions)
+            .await
+            .map(Some)
+    }
+}
+
+/// HLZF (Hochlastzeitfenster) per season and voltage level
+pub struct HlzfSource;
+
+#[async_trait]
+impl<C: CacheLayer> DataTypeSource<C> for HlzfSource {
+    fn name(&self) -> &'static str {
+        DataType::Hlzf.as_str()
+    }
+
+    async fn search(
+        &self,
+        repo: &SearchRepository<C>,
+        query: &DataTypeQuery<'_>,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let rows = repo.search_hlzf_data(query).await?;
+
+        Ok(rows.into_iter().map(|entry: HlzfDataWithDno| SearchResult {
+            id: entry.id,
+            dno: dno_info(entry.dno_id_full, entry.dno_name, entry.dno_slug, entry.dno_region),
+            year: entry.year,
+            data_type: DataType::Hlzf.as_str().to_string(),
+            status: entry.verification_status.unwrap_or_else(|| "unverified".to_string()),
+            data: json!({
+                "hlzf": {
+                    "season": entry.season,
+                    "voltage_level": entry.voltage_level,
+                    "ht": entry.ht,
+                    "nt": entry.nt,
+                    "start_date": entry.start_date,
+                    "end_date": entry.end_date
+                }
+            }),
+            crawled_at: entry.source.source_extracted_at.or(Some(entry.last_verified_at)),
+            age_days: None,
+            source: entry.source.into_source_info(),
+            last_updated: entry.updated_at,
+            first_seen_at: Some(entry.first_seen_at),
+            last_verified_at: Some(entry.last_verified_at),
+            version: Some(entry.version),
+            superseded_by: entry.superseded_by,
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+
+    /// A third data type that only exists in this test
+    struct KonzessionsabgabenSource;
+
+    #[async_trait]
+    impl DataTypeSource<MemoryCache> for KonzessionsabgabenSource {
+        fn name(&self) -> &'static str {
+            "konzessionsabgaben"
+        }
+
+        async fn search(
+            &self,
+            _repo: &SearchRepository<MemoryCache>,
+            query: &DataTypeQuery<'_>,
+        ) -> Result<Vec<SearchResult>, AppError> {
+            Ok(vec![SearchResult {
+                id: Uuid::nil(),
+                dno: dno_info(Uuid::nil(), "Netze BW".to_string(), "netze-bw".to_string(), None),
+                year: query.year.unwrap_or(2024),
+                data_type: self.name().to_string(),
+                status: "verified".to_string(),
+                data: json!({ "konzessionsabgaben": { "tarifkunden_ct_kwh": 1.59 } }),
+                source: None,
+                last_updated: chrono::Utc::now(),
+                first_seen_at: None,
+                last_verified_at: None,
+                version: None,
+                superseded_by: None,
+                crawled_at: None,
+                age_days: None,
+            }])
+        }
+    }
+
+    fn repo() -> SearchRepository<MemoryCache> {
+        // Never connected: the test source does not touch the database
+        let pool = sqlx::PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        SearchRepository::new(pool, Arc::new(MemoryCache::new()))
+    }
+
+    #[tokio::test]
+    async fn test_registered_type_is_searchable() {
+        let registry = DataTypeRegistry::<MemoryCache>::empty().register(KonzessionsabgabenSource);
+        assert!(registry.contains("konzessionsabgaben"));
+
+        let query = DataTypeQuery { year: Some(2024), limit: 50, ..Default::default() };
+        let found = registry.search(&repo(), Some("konzessionsabgaben"), &query).await.unwrap();
+
+        assert_eq!(found.total, 1);
+        assert_eq!(found.results[0].data_type, "konzessionsabgaben");
+        assert_eq!(found.results[0].year, 2024);
+
+        // "All types" iterates the registry, so the new type shows up there too
+        let all = registry.search(&repo(), None, &query).await.unwrap();
+        assert_eq!(all.results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_type_is_rejected() {
+        let registry = DataTypeRegistry::<MemoryCache>::default();
+        assert_eq!(registry.names(), vec!["netzentgelte", "hlzf"]);
+
+        let query = DataTypeQuery { limit: 50, ..Default::default() };
+        let result = registry.search(&repo(), Some("konzessionsabgaben"), &query).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_keyset_pages_reach_every_row(pool: sqlx::PgPool) {
+        crate::test_schema::create(&pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (slug, name) VALUES ('netze-bw', 'Netze BW');
+            -- 250 rows over five years, all created at the same moment
+            INSERT INTO netzentgelte_data (dno_id, year, voltage_level, leistung, verification_status)
+            SELECT d.id, 2020 + n % 5, 'ms' || n, 58.21, 'verified'
+            FROM dnos d, generate_series(1, 250) AS n;
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = SearchRepository::new(pool, Arc::new(MemoryCache::new()));
+        let registry = DataTypeRegistry::<MemoryCache>::default();
+
+        let mut after = None;
+        let mut seen = Vec::new();
+        let mut pages = Vec::new();
+        loop {
+            let query = DataTypeQuery {
+                verification_status: Some("verified"),
+                limit: 100,
+                paging: Paging::Keyset { after },
+                ..Default::default()
+            };
+            let page = registry.search(&repo, Some("netzentgelte"), &query).await.unwrap();
+            assert_eq!(page.total, 250);
+            pages.push(page.results.len());
+            seen.extend(page.results.iter().map(|result| (result.year, result.id)));
+            match page.next_cursor {
+                // Cursors travel through the request as text
+                Some(cursor) => after = Some(cursor.to_string().parse().unwrap()),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, [100, 100, 50]);
+        let mut ordered = seen.clone();
+        ordered.sort();
+        ordered.dedup();
+        assert_eq!(seen, ordered, "rows repeated or out of (year, id) order");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_rows_carry_their_source_when_one_is_on_record(pool: sqlx::PgPool) {
+        crate::test_schema::create(&pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (slug, name) VALUES ('netze-bw', 'Netze BW');
+            INSERT INTO netzentgelte_data (dno_id, year, voltage_level, leistung, verification_status)
+            SELECT id, year, 'ms', 58.21, 'verified' FROM dnos, (VALUES (2023), (2024)) AS y(year);
+            INSERT INTO data_sources (dno_id, year, data_type, source_type, source_url, file_hash, extracted_at, confidence, page_number)
+            SELECT id, 2024, 'netzentgelte', 'file', 'https://www.netze-bw.de/preisblatt-2024.pdf', repeat('ab', 32), now(), 0.92, 3
+            FROM dnos;
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = SearchRepository::new(pool, Arc::new(MemoryCache::new()));
+        let query = DataTypeQuery { verification_status: Some("verified"), limit: 50, ..Default::default() };
+        let found = DataTypeRegistry::<MemoryCache>::default()
+            .search(&repo, Some("netzentgelte"), &query)
+            .await
+            .unwrap();
+
+        let row = |year| found.results.iter().find(|result| result.year == year).unwrap();
+        let source = row(2024).source.clone().expect("2024 row has a source");
+        assert_eq!(source.source_url.as_deref(), Some("https://www.netze-bw.de/preisblatt-2024.pdf"));
+        assert_eq!(source.confidence, Some(0.92));
+        assert_eq!(source.file_type, "file");
+        assert_eq!(source.page, Some(3));
+        assert!(row(2023).source.is_none(), "rows without a source still come back");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_reingest_adds_a_version_and_search_returns_the_latest(pool: sqlx::PgPool) {
+        crate::test_schema::create(&pool).await;
+        let dno_id: Uuid = sqlx::query_scalar("INSERT INTO dnos (slug, name) VALUES ('netze-bw', 'Netze BW') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let extraction = |leistung: &str| crate::CreateNetzentgelteData {
+            dno_id,
+            year: 2024,
+            voltage_level: "ms".to_string(),
+            leistung: Some(leistung.parse().unwrap()),
+            arbeit: Some("1.26".parse().unwrap()),
+            leistung_unter_2500h: None,
+            arbeit_unter_2500h: None,
+            raw_values: None,
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        let v1 = crate::database::upsert_netzentgelte_data(&mut conn, extraction("58.21")).await.unwrap();
+        let v2 = crate::database::upsert_netzentgelte_data(&mut conn, extraction("61.04")).await.unwrap();
+        assert_eq!((v1.version, v2.version), (1, 2));
+        assert_eq!(v2.first_seen_at, v1.first_seen_at);
+        let superseded_by: Option<Uuid> = sqlx::query_scalar("SELECT superseded_by FROM netzentgelte_data WHERE id = $1")
+            .bind(v1.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(superseded_by, Some(v2.id));
+        // Unchanged values confirm the current version instead of adding one
+        let confirmed = crate::database::upsert_netzentgelte_data(&mut conn, extraction("61.04")).await.unwrap();
+        assert_eq!((confirmed.id, confirmed.version), (v2.id, 2));
+
+        let repo = SearchRepository::new(pool, Arc::new(MemoryCache::new()));
+        let registry = DataTypeRegistry::<MemoryCache>::default();
+        let query = DataTypeQuery { limit: 50, ..Default::default() };
+
+        let latest = registry.search(&repo, Some("netzentgelte"), &query).await.unwrap();
+        assert_eq!(latest.total, 1);
+        assert_eq!((latest.results[0].id, latest.results[0].version), (v2.id, Some(2)));
+        assert_eq!(latest.results[0].data["netzentgelte"]["leistung"], serde_json::json!("61.04"));
+
+        let all_query = DataTypeQuery { versions: DataVersions::All, ..query };
+        let all = registry.search(&repo, Some("netzentgelte"), &all_query).await.unwrap();
+        assert_eq!(all.total, 2);
+        let mut versions: Vec<_> = all.results.iter().map(|result| (result.version, result.superseded_by)).collect();
+        versions.sort();
+        assert_eq!(versions, [(Some(1), Some(v2.id)), (Some(2), None)]);
+    }
+}
@@ -1,19 +1,34 @@
 use crate::{
+    autocomplete::{DnoAutocompleteIndex, DnoSuggestion},
     cache::{CacheLayer, CacheKeys},
-    database, AppError, Dno, CreateDno, UpdateDno,
+    database, slug::slugify_dno, AppError, Dno, CreateDno, UpdateDno,
 };
 use sqlx::PgPool;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
+/// Outcome of `DnoRepository::upsert_many`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpsertReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
 /// Repository for DNO operations with Redis caching
 #[derive(Clone)]
 pub struct DnoRepository<C: CacheLayer> {
     db: PgPool,
     cache: Arc<C>,
     dno_ttl: Duration,
+    /// Name suggestions, shared by all clones of the repository
+    autocomplete: Arc<DnoAutocompleteIndex>,
+    /// Rebuild the index from the cached DNO list at least this often, so
+    /// mutations made through other instances show up
+    autocomplete_max_age: Duration,
 }
 
 impl<C: CacheLayer> DnoRepository<C> {
@@ -22,6 +37,32 @@ impl<C: CacheLayer> DnoRepository<C> {
             db,
             cache,
             dno_ttl: Duration::from_secs(14400), // 4 hours - DNO data rarely changes
+            autocomplete: Arc::new(DnoAutocompleteIndex::new()),
+            autocomplete_max_age: Duration::from_secs(60),
+        }
+    }
+
+    /// Ranked DNO name suggestions for type-ahead search, served from the
+    /// in-memory index; it is built from the cached DNO list when stale
+    pub async fn autocomplete(&self, query: &str, limit: usize) -> Result<Vec<DnoSuggestion>, AppError> {
+        if self.autocomplete.is_stale(self.autocomplete_max_age) {
+            self.refresh_autocomplete().await?;
+        }
+        Ok(self.autocomplete.suggest(query, limit))
+    }
+
+    /// Rebuild the autocomplete index from the DNO list
+    pub async fn refresh_autocomplete(&self) -> Result<(), AppError> {
+        let dnos = self.get_all_dnos().await?;
+        self.autocomplete.rebuild(&dnos);
+        debug!("Rebuilt DNO autocomplete index: {} entries", dnos.len());
+        Ok(())
+    }
+
+    /// Called after a mutation; a failed rebuild is retried on the next stale lookup
+    async fn refresh_autocomplete_after_change(&self) {
+        if let Err(e) = self.refresh_autocomplete().await {
+            warn!("Failed to refresh DNO autocomplete index: {}", e);
         }
     }
 
@@ -74,53 +115,29 @@ impl<C: CacheLayer> DnoRepository<C> {
         Ok(dnos)
     }
 
-    /// Get DNO by ID with caching
+    /// Get DNO by ID with caching; an unknown ID is cached for 5 minutes
     pub async fn get_dno_by_id(&self, dno_id: Uuid) -> Result<Option<Dno>, AppError> {
-        let cache_key = CacheKeys::dno_by_id(dno_id);
-
-        // Try cache first
-        match self.cache.get::<Dno>(&cache_key).await {
-            Ok(Some(dno)) => {
-                debug!("Cache HIT for DNO by ID: {}", dno_id);
-                return Ok(Some(dno));
-            }
-            Ok(None) => {
-                debug!("Cache MISS for DNO by ID: {}", dno_id);
-            }
-            Err(e) => {
-                warn!("Cache error for DNO by ID {}: {}", dno_id, e);
-            }
-        }
+        let negative_ttl = Duration::from_secs(300);
+        let ttl_for = |dno: &Option<Dno>| Some(if dno.is_some() { self.dno_ttl } else { negative_ttl });
 
-        // Cache miss - fetch from database
-        let dno = database::get_dno_by_id(&self.db, dno_id).await?;
+        self.cache.get_or_compute_with(&CacheKeys::dno_by_id(dno_id), ttl_for, || async {
+            let dno = database::get_dno_by_id(&self.db, dno_id).await?;
 
-        // Cache the result
-        if let Some(ref dno) = dno {
-            // Cache by ID
-            if let Err(e) = self.cache.set(&cache_key, dno, Some(self.dno_ttl)).await {
-                warn!("Failed to cache DNO by ID: {}", e);
-            }
-            
             // Also cache by name and slug for cross-reference
-            let name_key = CacheKeys::dno_by_name(&dno.name);
-            let slug_key = CacheKeys::dno_by_slug(&dno.slug);
-            
-            if let Err(e) = self.cache.set(&name_key, dno, Some(self.dno_ttl)).await {
-                warn!("Failed to cache DNO by name: {}", e);
-            }
-            
-            if let Err(e) = self.cache.set(&slug_key, dno, Some(self.dno_ttl)).await {
-                warn!("Failed to cache DNO by slug: {}", e);
-            }
-        } else {
-            // Cache negative result with shorter TTL
-            if let Err(e) = self.cache.set(&cache_key, &Option::<Dno>::None, Some(Duration::from_secs(300))).await {
-                warn!("Failed to cache negative DNO result: {}", e);
-            }
-        }
+            if let Some(ref dno) = dno {
+                let name_key = CacheKeys::dno_by_name(&dno.name);
+                let slug_key = CacheKeys::dno_by_slug(&dno.slug);
 
-        Ok(dno)
+                if let Err(e) = self.cache.set(&name_key, dno, Some(self.dno_ttl)).await {
+                    warn!("Failed to cache DNO by name: {}", e);
+                }
+
+                if let Err(e) = self.cache.set(&slug_key, dno, Some(self.dno_ttl)).await {
+                    warn!("Failed to cache DNO by slug: {}", e);
+                }
+            }
+            Ok(dno)
+        }).await
     }
 
     /// Get DNO by name with caching (handles ILIKE pattern matching)
@@ -172,8 +189,20 @@ impl<C: CacheLayer> DnoRepository<C> {
         Ok(dno)
     }
 
-    /// Get DNO by slug with caching
+    /// Up to `limit` DNOs with a name similar to `query`, best match first.
+    /// Not cached: the queries are free text and rarely repeat.
+    pub async fn search_dnos_fuzzy(&self, query: &str, limit: i64) -> Result<Vec<Dno>, AppError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        database::search_dnos_fuzzy(&self.db, query, limit).await
+    }
+
+    /// Get DNO by slug with caching. The slug is canonicalized first, so
+    /// `Thüringer Energienetze` finds `thueringer-energienetze`.
     pub async fn get_dno_by_slug(&self, slug: &str) -> Result<Option<Dno>, AppError> {
+        let slug = &slugify_dno(slug);
         let cache_key = CacheKeys::dno_by_slug(slug);
 
         // Try cache first
@@ -221,8 +250,10 @@ impl<C: CacheLayer> DnoRepository<C> {
         Ok(dno)
     }
 
-    /// Create a new DNO and invalidate cache
-    pub async fn create_dno(&self, dno: CreateDno) -> Result<Dno, AppError> {
+    /// Create a new DNO and invalidate cache. The slug is canonicalized, and
+    /// derived from the name when left empty.
+    pub async fn create_dno(&self, mut dno: CreateDno) -> Result<Dno, AppError> {
+        dno.slug = slugify_dno(if dno.slug.trim().is_empty() { &dno.name } else { &dno.slug });
         let created_dno = database::create_dno(&self.db, dno).await?;
 
         // Invalidate the all DNOs cache
@@ -247,12 +278,108 @@ impl<C: CacheLayer> DnoRepository<C> {
             warn!("Failed to cache new DNO by slug: {}", e);
         }
 
+        self.refresh_autocomplete_after_change().await;
         debug!("Created and cached new DNO: {}", created_dno.id);
         Ok(created_dno)
     }
 
+    /// Insert or update DNOs by slug in one transaction, e.g. from an imported
+    /// operator list. Names are trimmed with inner whitespace collapsed. An
+    /// entry without a slug matches the DNO whose slug or official name
+    /// slugifies like its name (`Netze BW GmbH` finds `netze-bw`), and keeps
+    /// that DNO's short name; otherwise its slug is derived from the name. A
+    /// later entry with the same slug replaces an earlier one. Fields left
+    /// empty keep their stored value, so rows whose fields all match count as
+    /// unchanged and are not written.
+    pub async fn upsert_many(&self, dnos: Vec<CreateDno>) -> Result<UpsertReport, AppError> {
+        let mut tx = self.db.begin().await.map_err(AppError::Database)?;
+        let existing: Vec<(String, String, Option<String>)> =
+            sqlx::query_as("SELECT slug, name, official_name FROM dnos")
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(AppError::Database)?;
+        let by_official_name: BTreeMap<String, (String, String)> = existing
+            .into_iter()
+            .filter_map(|(slug, name, official_name)| Some((slugify_dno(&official_name?), (slug, name))))
+            .collect();
+
+        let mut by_slug = BTreeMap::new();
+        for mut dno in dnos {
+            dno.name = dno.name.split_whitespace().collect::<Vec<_>>().join(" ");
+            if dno.slug.trim().is_empty() {
+                dno.slug = slugify_dno(&dno.name);
+                if let Some((slug, name)) = by_official_name.get(&dno.slug) {
+                    dno.official_name.get_or_insert_with(|| dno.name.clone());
+                    dno.slug = slug.clone();
+                    dno.name = name.clone();
+                }
+            } else {
+                dno.slug = slugify_dno(&dno.slug);
+            }
+            if dno.slug.is_empty() {
+                return Err(AppError::BadRequest(format!("DNO '{}' has neither a name nor a slug", dno.name)));
+            }
+            by_slug.insert(dno.slug.clone(), dno);
+        }
+
+        let mut report = UpsertReport::default();
+        for dno in by_slug.into_values() {
+            // No row comes back when the WHERE clause skips the update; xmax = 0
+            // only for rows created by this statement
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO dnos (slug, name, official_name, description, region, website)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (slug) DO UPDATE SET
+                    name = EXCLUDED.name,
+                    official_name = COALESCE(EXCLUDED.official_name, dnos.official_name),
+                    description = COALESCE(EXCLUDED.description, dnos.description),
+                    region = COALESCE(EXCLUDED.region, dnos.region),
+                    website = COALESCE(EXCLUDED.website, dnos.website),
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE (dnos.name, dnos.official_name, dnos.description, dnos.region, dnos.website)
+                    IS DISTINCT FROM (
+                        EXCLUDED.name,
+                        COALESCE(EXCLUDED.official_name, dnos.official_name),
+                        COALESCE(EXCLUDED.description, dnos.description),
+                        COALESCE(EXCLUDED.region, dnos.region),
+                        COALESCE(EXCLUDED.website, dnos.website)
+                    )
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&dno.slug)
+            .bind(&dno.name)
+            .bind(&dno.official_name)
+            .bind(&dno.description)
+            .bind(&dno.region)
+            .bind(&dno.website)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+
+            match inserted {
+                Some(true) => report.inserted += 1,
+                Some(false) => report.updated += 1,
+                None => report.unchanged += 1,
+            }
+        }
+        tx.commit().await.map_err(AppError::Database)?;
+
+        if report.inserted + report.updated > 0 {
+            self.invalidate_all_caches().await?;
+            self.refresh_autocomplete_after_change().await;
+        }
+        debug!(
+            "Upserted DNOs: {} inserted, {} updated, {} unchanged",
+            report.inserted, report.updated, report.unchanged
+        );
+        Ok(report)
+    }
+
     /// Update DNO and refresh cache
-    pub async fn update_dno(&self, dno_id: Uuid, updates: UpdateDno) -> Result<Dno, AppError> {
+    pub async fn update_dno(&self, dno_id: Uuid, mut updates: UpdateDno) -> Result<Dno, AppError> {
+        updates.slug = updates.slug.as_deref().map(slugify_dno);
         // Get the old DNO first to invalidate old cache entries
         let old_dno = self.get_dno_by_id(dno_id).await?;
 
@@ -294,6 +421,7 @@ impl<C: CacheLayer> DnoRepository<C> {
             warn!("Failed to cache updated DNO by slug: {}", e);
         }
 
+        self.refresh_autocomplete_after_change().await;
         debug!("Updated and re-cached DNO: {}", updated_dno.id);
         Ok(updated_dno)
     }
@@ -334,6 +462,7 @@ impl<C: CacheLayer> DnoRepository<C> {
             warn!("Failed to invalidate available filters cache: {}", e);
         }
 
+        self.refresh_autocomplete_after_change().await;
         debug!("Deleted DNO and invalidated cache: {}", dno_id);
         Ok(())
     }
@@ -342,8 +471,9 @@ impl<C: CacheLayer> DnoRepository<C> {
     pub async fn warm_cache(&self) -> Result<(), AppError> {
         debug!("Starting DNO cache warm-up");
         
-        // Pre-load all DNOs (this will cache individual DNOs as well)
-        let _ = self.get_all_dnos().await?;
+        // Pre-load all DNOs (this will cache individual DNOs as well) and
+        // build the autocomplete index from them
+        self.refresh_autocomplete().await?;
         
         debug!("DNO cache warm-up completed");
         Ok(())
@@ -368,4 +498,41 @@ impl<C: CacheLayer> DnoRepository<C> {
         debug!("Invalidated all DNO-related caches");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+
+    #[sqlx::test(migrations = false)]
+    async fn test_fuzzy_search_finds_near_names(pool: PgPool) {
+        crate::test_schema::create(&pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (slug, name, official_name) VALUES
+                ('netze-bw', 'Netze BW', 'Netze BW GmbH'),
+                ('westnetz', 'Westnetz', 'Westnetz GmbH'),
+                ('bayernwerk-netz', 'Bayernwerk Netz', 'Bayernwerk Netz GmbH'),
+                ('e-dis-netz', 'E.DIS Netz', 'E.DIS Netz GmbH');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = DnoRepository::new(pool, Arc::new(MemoryCache::new()));
+        let best = |found: Vec<Dno>| found.first().map(|dno| dno.slug.clone());
+
+        // Partial
+        assert_eq!(best(repo.search_dnos_fuzzy("Bayernwerk", 5).await.unwrap()).as_deref(), Some("bayernwerk-netz"));
+        // Misspelled
+        assert_eq!(best(repo.search_dnos_fuzzy("Westnets", 5).await.unwrap()).as_deref(), Some("westnetz"));
+        // Case differs
+        assert_eq!(best(repo.search_dnos_fuzzy("NETZE bw gmbh", 5).await.unwrap()).as_deref(), Some("netze-bw"));
+
+        assert!(repo.search_dnos_fuzzy("Stadtwerke Flensburg", 5).await.unwrap().is_empty());
+        assert!(repo.search_dnos_fuzzy("  ", 5).await.unwrap().is_empty());
+        assert_eq!(repo.search_dnos_fuzzy("Netz", 2).await.unwrap().len(), 2);
+    }
+}
@@ -1,7 +1,13 @@
 pub mod user_repository;
 pub mod search_repository;
 pub mod dno_repository;
+pub mod data_types;
+pub mod single_flight;
+pub mod query_repository;
 
 pub use user_repository::UserRepository;
 pub use search_repository::SearchRepository;
-pub use dno_repository::DnoRepository;
\ No newline at end of file
+pub use dno_repository::{DnoRepository, UpsertReport};
+pub use single_flight::SingleFlight;
+pub use query_repository::QueryRepository;
+pub use data_types::{DataTypeQuery, DataTypeRegistry, DataTypeSearch, DataTypeSource};
\ No newline at end of file
@@ -0,0 +1,90 @@
+use crate::{
+    cache::{CacheLayer, CacheKeys},
+    ollama::{normalize_query, OllamaService, ProcessedQuery},
+    AppError,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Repository for free-text query interpretation with Redis caching
+#[derive(Clone)]
+pub struct QueryRepository<C: CacheLayer> {
+    ollama: OllamaService,
+    cache: Arc<C>,
+    found_data_ttl: Duration,
+    /// Concurrent identical queries that miss the cache share one model call
+    flights: super::SingleFlight<ProcessedQuery>,
+}
+
+impl<C: CacheLayer> QueryRepository<C> {
+    pub fn new(ollama: OllamaService, cache: Arc<C>) -> Self {
+        Self {
+            ollama,
+            cache,
+            found_data_ttl: Duration::from_secs(86400), // 24 hours, like found search data
+            flights: super::SingleFlight::new(),
+        }
+    }
+
+    /// Interpret a query with the model, cached by its normalized text
+    pub async fn interpret(&self, query: &str) -> Result<ProcessedQuery, AppError> {
+        let normalized = normalize_query(query);
+        if normalized.is_empty() {
+            return Err(AppError::BadRequest("query must not be empty".to_string()));
+        }
+        let cache_key = CacheKeys::query_interpretation(&normalized);
+
+        // Try cache first
+        match self.cache.get::<ProcessedQuery>(&cache_key).await {
+            Ok(Some(processed)) => {
+                debug!("Cache HIT for query interpretation: {}", normalized);
+                return Ok(ProcessedQuery { query: query.to_string(), ..processed });
+            }
+            Ok(None) => {
+                debug!("Cache MISS for query interpretation: {}", normalized);
+            }
+            Err(e) => {
+                warn!("Cache error for query interpretation: {}", e);
+            }
+        }
+
+        let processed = self.flights.run(&cache_key, || async {
+            let processed = self.ollama.interpret_query(query).await?;
+            if let Err(e) = self.cache.set(&cache_key, &processed, Some(self.found_data_ttl)).await {
+                warn!("Failed to cache query interpretation: {}", e);
+            }
+            Ok(processed)
+        }).await?;
+        Ok(ProcessedQuery { query: query.to_string(), ..processed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+    use crate::ollama::{testing::mock_ollama, QueryIntent};
+    use crate::OllamaConfig;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_interpretations_are_cached_by_normalized_query() {
+        let (url, requests) = mock_ollama(
+            r#"{"intent": "search", "dno_name": "Netze BW", "year": 2023, "data_type": "netzentgelte", "confidence": 0.9}"#,
+        )
+        .await;
+        let ollama = OllamaService::new(&OllamaConfig { url, model: "llama3".to_string(), timeout: 5 }).unwrap();
+        let repo = QueryRepository::new(ollama, Arc::new(MemoryCache::new()));
+
+        let first = repo.interpret("Netzentgelte Netze BW 2023").await.unwrap();
+        let second = repo.interpret("  netzentgelte  netze bw 2023").await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(first.intent, QueryIntent::Search);
+        assert_eq!(second.dno_name.as_deref(), Some("Netze BW"));
+        assert_eq!(second.query, "  netzentgelte  netze bw 2023");
+
+        assert!(matches!(repo.interpret(" \t").await, Err(AppError::BadRequest(_))));
+    }
+}
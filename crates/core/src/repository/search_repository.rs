@@ -1,6 +1,9 @@
+use super::data_types::DataTypeQuery;
+use super::single_flight::SingleFlight;
+use crate::paging::Paging;
 use crate::{
     cache::{CacheLayer, CacheKeys, SearchFilters},
-    database, AppError, NetzentgelteDataWithDno, HlzfDataWithDno, AvailableFilters,
+    database, AppError, DataVersions, NetzentgelteDataWithDno, HlzfDataWithDno, AvailableFilters, YearCoverage,
 };
 use chrono::Datelike;
 use sqlx::PgPool;
@@ -17,6 +20,9 @@ pub struct SearchRepository<C: CacheLayer> {
     found_data_ttl: Duration,
     not_found_ttl: Duration,
     filters_ttl: Duration,
+    /// Concurrent identical searches that miss the cache share one query
+    netzentgelte_flights: SingleFlight<Vec<NetzentgelteDataWithDno>>,
+    hlzf_flights: SingleFlight<Vec<HlzfDataWithDno>>,
 }
 
 impl<C: CacheLayer> SearchRepository<C> {
@@ -27,27 +33,26 @@ impl<C: CacheLayer> SearchRepository<C> {
             found_data_ttl: Duration::from_secs(86400), // 24 hours for found data
             not_found_ttl: Duration::from_secs(3600),   // 1 hour for not found
             filters_ttl: Duration::from_secs(3600),     // 1 hour for available filters
+            netzentgelte_flights: SingleFlight::new(),
+            hlzf_flights: SingleFlight::new(),
         }
     }
 
     /// Search netzentgelte data with caching
     pub async fn search_netzentgelte_data(
         &self,
-        dno_id: Option<Uuid>,
-        dno_name: Option<&str>,
-        year: Option<i32>,
-        verification_status: Option<&str>,
-        limit: Option<i64>,
-        offset: Option<i64>,
+        query: &DataTypeQuery<'_>,
     ) -> Result<Vec<NetzentgelteDataWithDno>, AppError> {
         let filters = SearchFilters {
-            dno_id,
-            dno_name: dno_name.map(|s| s.to_string()),
-            year,
+            dno_id: query.dno_id,
+            dno_name: query.dno_name.map(|s| s.to_string()),
+            year: query.year,
             data_type: Some("netzentgelte".to_string()),
             region: None, // Not used in this search
-            limit,
-            offset,
+            limit: Some(query.limit),
+            offset: Some(query.offset),
+            paging: query.paging,
+            versions: query.versions,
         };
 
         let cache_key = CacheKeys::search_netzentgelte(&filters);
@@ -66,50 +71,41 @@ impl<C: CacheLayer> SearchRepository<C> {
             }
         }
 
-        // Cache miss - fetch from database
-        let data = database::search_netzentgelte_data(
-            &self.db,
-            dno_id,
-            dno_name,
-            year,
-            verification_status,
-            limit,
-            offset,
-        ).await?;
+        // Cache miss - fetch from database, once for all concurrent identical searches
+        self.netzentgelte_flights.run(&cache_key, || async {
+            let data = database::search_netzentgelte_data(&self.db, query).await?;
 
-        // Cache the result with appropriate TTL
-        let ttl = if data.is_empty() {
-            self.not_found_ttl
-        } else {
-            self.found_data_ttl
-        };
+            // Cache the result with appropriate TTL
+            let ttl = if data.is_empty() {
+                self.not_found_ttl
+            } else {
+                self.found_data_ttl
+            };
 
-        if let Err(e) = self.cache.set(&cache_key, &data, Some(ttl)).await {
-            warn!("Failed to cache netzentgelte search results: {}", e);
-        }
+            if let Err(e) = self.cache.set(&cache_key, &data, Some(ttl)).await {
+                warn!("Failed to cache netzentgelte search results: {}", e);
+            }
 
-        debug!("Cached netzentgelte search: {} results", data.len());
-        Ok(data)
+            debug!("Cached netzentgelte search: {} results", data.len());
+            Ok(data)
+        }).await
     }
 
     /// Search HLZF data with caching
     pub async fn search_hlzf_data(
         &self,
-        dno_id: Option<Uuid>,
-        dno_name: Option<&str>,
-        year: Option<i32>,
-        verification_status: Option<&str>,
-        limit: Option<i64>,
-        offset: Option<i64>,
+        query: &DataTypeQuery<'_>,
     ) -> Result<Vec<HlzfDataWithDno>, AppError> {
         let filters = SearchFilters {
-            dno_id,
-            dno_name: dno_name.map(|s| s.to_string()),
-            year,
+            dno_id: query.dno_id,
+            dno_name: query.dno_name.map(|s| s.to_string()),
+            year: query.year,
             data_type: Some("hlzf".to_string()),
             region: None, // Not used in this search
-            limit,
-            offset,
+            limit: Some(query.limit),
+            offset: Some(query.offset),
+            paging: query.paging,
+            versions: query.versions,
         };
 
         let cache_key = CacheKeys::search_hlzf(&filters);
@@ -128,30 +124,24 @@ impl<C: CacheLayer> SearchRepository<C> {
             }
         }
 
-        // Cache miss - fetch from database
-        let data = database::search_hlzf_data(
-            &self.db,
-            dno_id,
-            dno_name,
-            year,
-            verification_status,
-            limit,
-            offset,
-        ).await?;
+        // Cache miss - fetch from database, once for all concurrent identical searches
+        self.hlzf_flights.run(&cache_key, || async {
+            let data = database::search_hlzf_data(&self.db, query).await?;
 
-        // Cache the result with appropriate TTL
-        let ttl = if data.is_empty() {
-            self.not_found_ttl
-        } else {
-            self.found_data_ttl
-        };
+            // Cache the result with appropriate TTL
+            let ttl = if data.is_empty() {
+                self.not_found_ttl
+            } else {
+                self.found_data_ttl
+            };
 
-        if let Err(e) = self.cache.set(&cache_key, &data, Some(ttl)).await {
-            warn!("Failed to cache HLZF search results: {}", e);
-        }
+            if let Err(e) = self.cache.set(&cache_key, &data, Some(ttl)).await {
+                warn!("Failed to cache HLZF search results: {}", e);
+            }
 
-        debug!("Cached HLZF search: {} results", data.len());
-        Ok(data)
+            debug!("Cached HLZF search: {} results", data.len());
+            Ok(data)
+        }).await
     }
 
     /// Count netzentgelte data with caching
@@ -161,6 +151,7 @@ impl<C: CacheLayer> SearchRepository<C> {
         dno_name: Option<&str>,
         year: Option<i32>,
         verification_status: Option<&str>,
+        versions: DataVersions,
     ) -> Result<i64, AppError> {
         let filters = SearchFilters {
             dno_id,
@@ -170,6 +161,8 @@ impl<C: CacheLayer> SearchRepository<C> {
             region: None,
             limit: None,
             offset: None,
+            paging: Paging::Offset,
+            versions,
         };
 
         let cache_key = CacheKeys::search_count_netzentgelte(&filters);
@@ -195,6 +188,7 @@ impl<C: CacheLayer> SearchRepository<C> {
             dno_name,
             year,
             verification_status,
+            versions,
         ).await?;
 
         // Cache the result
@@ -212,6 +206,53 @@ impl<C: CacheLayer> SearchRepository<C> {
         Ok(count)
     }
 
+    /// Get per-year data coverage (all verification states) with caching,
+    /// used to explain empty search results
+    pub async fn get_data_coverage(
+        &self,
+        dno_id: Option<Uuid>,
+        dno_name: Option<&str>,
+        data_type: Option<&str>,
+    ) -> Result<Vec<YearCoverage>, AppError> {
+        let filters = SearchFilters {
+            dno_id,
+            dno_name: dno_name.map(|s| s.to_string()),
+            year: None,
+            data_type: data_type.map(|s| s.to_string()),
+            region: None,
+            limit: None,
+            offset: None,
+            paging: Paging::Offset,
+            versions: DataVersions::Latest,
+        };
+
+        let cache_key = CacheKeys::search_coverage(&filters);
+
+        // Try cache first
+        match self.cache.get::<Vec<YearCoverage>>(&cache_key).await {
+            Ok(Some(coverage)) => {
+                debug!("Cache HIT for data coverage: {} years", coverage.len());
+                return Ok(coverage);
+            }
+            Ok(None) => {
+                debug!("Cache MISS for data coverage");
+            }
+            Err(e) => {
+                warn!("Cache error for data coverage: {}", e);
+            }
+        }
+
+        // Cache miss - fetch from database
+        let coverage = database::get_data_coverage(&self.db, dno_id, dno_name, data_type).await?;
+
+        if let Err(e) = self.cache.set(&cache_key, &coverage, Some(self.not_found_ttl)).await {
+            warn!("Failed to cache data coverage: {}", e);
+        }
+
+        debug!("Cached data coverage: {} years", coverage.len());
+        Ok(coverage)
+    }
+
     /// Get available years and DNOs with caching
     pub async fn get_available_years_and_dnos(&self) -> Result<AvailableFilters, AppError> {
         let cache_key = CacheKeys::available_filters();
@@ -326,13 +367,15 @@ impl<C: CacheLayer> SearchRepository<C> {
 
         for year in years_to_warm {
             // Search for both data types with basic filters
-            let _ = self.search_netzentgelte_data(
-                None, None, Some(year), Some("verified"), Some(50), Some(0)
-            ).await;
+            let query = DataTypeQuery {
+                year: Some(year),
+                verification_status: Some("verified"),
+                limit: 50,
+                ..Default::default()
+            };
+            let _ = self.search_netzentgelte_data(&query).await;
             
-            let _ = self.search_hlzf_data(
-                None, None, Some(year), Some("verified"), Some(50), Some(0)
-            ).await;
+            let _ = self.search_hlzf_data(&query).await;
         }
 
         debug!("Cache warm-up completed");
@@ -378,4 +421,103 @@ pub struct CacheHealthInfo {
     pub status: String,
     pub latency_ms: u64,
     pub operations_tested: u32,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{MemoryCache, RedisCache, RedisCacheConfig};
+
+    /// Cache pointed at a port nothing listens on, i.e. Redis is down
+    fn unreachable_cache() -> Arc<RedisCache> {
+        let config = RedisCacheConfig {
+            redis_url: "redis://127.0.0.1:1".to_string(),
+            key_prefix: "dno".to_string(),
+            max_connections: 2,
+            connection_timeout: 1,
+            default_ttl: Duration::from_secs(60),
+            session_ttl: Duration::from_secs(60),
+            found_data_ttl: Duration::from_secs(60),
+            not_found_ttl: Duration::from_secs(60),
+            circuit_failure_threshold: 2,
+            circuit_cooldown: Duration::from_secs(60),
+        };
+        Arc::new(RedisCache::connect_lazy(config).unwrap())
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_search_by_dno_falls_back_to_db_when_cache_down(pool: PgPool) {
+        crate::test_schema::create(&pool).await;
+
+        let dno_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO dnos (slug, name) VALUES ('netze-bw', 'Netze BW') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO netzentgelte_data (dno_id, year, voltage_level, leistung, verification_status)
+             VALUES ($1, 2024, 'ms', 58.21, 'verified')",
+        )
+        .bind(dno_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let cache = unreachable_cache();
+        let repo = SearchRepository::new(pool, cache.clone());
+
+        // Every call misses Redis but must still be answered from the database
+        let query = DataTypeQuery {
+            dno_id: Some(dno_id),
+            year: Some(2024),
+            verification_status: Some("verified"),
+            limit: 50,
+            ..Default::default()
+        };
+        for _ in 0..3 {
+            let results = repo
+                .search_netzentgelte_data(&query)
+                .await
+                .unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].dno_name, "Netze BW");
+        }
+
+        // Repeated connection failures disable the cache instead of retrying it on every call
+        assert!(cache.is_disabled());
+        let count = repo
+            .count_netzentgelte_data(Some(dno_id), None, Some(2024), Some("verified"), DataVersions::Latest)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_search_is_503_while_the_database_is_unreachable(pool: PgPool) {
+        crate::test_schema::create(&pool).await;
+        // A pool whose only connection is held, so every search times out acquiring one
+        let busy = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_with((*pool.connect_options()).clone())
+            .await
+            .unwrap();
+        let _held = busy.acquire().await.unwrap();
+        let repo = SearchRepository::new(busy.clone(), Arc::new(MemoryCache::new()));
+
+        let query = DataTypeQuery { dno_name: Some("Netze BW"), year: Some(2024), limit: 50, ..Default::default() };
+        let netzentgelte = repo
+            .search_netzentgelte_data(&query)
+            .await
+            .unwrap_err();
+        let hlzf = repo
+            .search_hlzf_data(&query)
+            .await
+            .unwrap_err();
+        for error in [netzentgelte, hlzf] {
+            assert!(matches!(error, AppError::Database(sqlx::Error::PoolTimedOut)), "{:?}", error);
+            assert_eq!(error.status_code(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(error.error_code(), "database_unavailable");
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::AppError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+type Flight<T> = Arc<OnceCell<Result<T, Arc<AppError>>>>;
+
+/// Collapses concurrent identical lookups into one: while a lookup for a key
+/// is running, callers asking for the same key wait for its result instead
+/// of starting their own. The entry is dropped as soon as the lookup
+/// finishes, so neither results nor errors outlive it.
+pub struct SingleFlight<T> {
+    flights: Arc<Mutex<HashMap<String, Flight<T>>>>,
+}
+
+impl<T> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        Self { flights: self.flights.clone() }
+    }
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self { flights: Arc::new(Mutex::new(HashMap::new())) }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `lookup` for `key`, or join the run already in flight. If the
+    /// caller running it is cancelled, one of the waiters takes over. The
+    /// caller that ran the lookup gets its error as it was; waiters get a
+    /// copy of the same variant, so the status and error code match.
+    pub async fn run<F, Fut>(&self, key: &str, lookup: F) -> Result<T, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let flight = self.flights.lock().unwrap().entry(key.to_string()).or_default().clone();
+        let mut original = None;
+        let result = flight
+            .get_or_init(|| async {
+                lookup().await.map_err(|error| {
+                    let shared = Arc::new(copy_error(&error));
+                    original = Some(error);
+                    shared
+                })
+            })
+            .await
+            .clone();
+
+        let mut flights = self.flights.lock().unwrap();
+        if flights.get(key).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+            flights.remove(key);
+        }
+        drop(flights);
+
+        result.map_err(|shared| original.unwrap_or_else(|| copy_error(&shared)))
+    }
+
+    /// Keys with a lookup running
+    pub fn in_flight(&self) -> usize {
+        self.flights.lock().unwrap().len()
+    }
+}
+
+/// `error` rebuilt for another caller. `AppError` is not `Clone` because of
+/// its sources; those are rebuilt where they can be, and otherwise keep
+/// their message under a variant with the same status.
+fn copy_error(error: &AppError) -> AppError {
+    match error {
+        AppError::Database(e) => AppError::Database(match e {
+            sqlx::Error::PoolTimedOut => sqlx::Error::PoolTimedOut,
+            sqlx::Error::PoolClosed => sqlx::Error::PoolClosed,
+            sqlx::Error::WorkerCrashed => sqlx::Error::WorkerCrashed,
+            sqlx::Error::RowNotFound => sqlx::Error::RowNotFound,
+            sqlx::Error::Io(io) => sqlx::Error::Io(std::io::Error::new(io.kind(), io.to_string())),
+            sqlx::Error::Tls(tls) => sqlx::Error::Tls(tls.to_string().into()),
+            sqlx::Error::ColumnNotFound(column) => sqlx::Error::ColumnNotFound(column.clone()),
+            other => sqlx::Error::Protocol(other.to_string()),
+        }),
+        AppError::Io(e) => AppError::Io(std::io::Error::new(e.kind(), e.to_string())),
+        AppError::Cache(message) => AppError::Cache(message.clone()),
+        AppError::Config(message) => AppError::Config(message.clone()),
+        AppError::Unauthorized(message) => AppError::Unauthorized(message.clone()),
+        AppError::Forbidden(message) => AppError::Forbidden(message.clone()),
+        AppError::BadRequest(message) => AppError::BadRequest(message.clone()),
+        AppError::NotFound(message) => AppError::NotFound(message.clone()),
+        AppError::TooManyRequests => AppError::TooManyRequests,
+        AppError::Conflict { message, retry_after_secs } => {
+            AppError::Conflict { message: message.clone(), retry_after_secs: *retry_after_secs }
+        }
+        AppError::ServiceUnavailable(open) => AppError::ServiceUnavailable(open.clone()),
+        AppError::Http(_) | AppError::Json(_) | AppError::InternalServerError(_) => {
+            AppError::InternalServerError(error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_identical_misses_query_once() {
+        let flights = SingleFlight::<Vec<i32>>::new();
+        let queries = Arc::new(AtomicUsize::new(0));
+
+        let searches: Vec<_> = (0..50)
+            .map(|_| {
+                let (flights, queries) = (flights.clone(), queries.clone());
+                tokio::spawn(async move {
+                    flights
+                        .run("search:netzentgelte:ab12", || async {
+                            queries.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            Ok(vec![2024])
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for search in searches {
+            assert_eq!(search.await.unwrap().unwrap(), vec![2024]);
+        }
+
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_kept() {
+        let flights = SingleFlight::<Vec<i32>>::new();
+
+        let failed = flights
+            .run("search:netzentgelte:ab12", || async { Err(AppError::InternalServerError("pool timed out".to_string())) })
+            .await;
+        assert!(failed.unwrap_err().to_string().contains("pool timed out"));
+        assert_eq!(flights.in_flight(), 0);
+
+        let retried = flights.run("search:netzentgelte:ab12", || async { Ok(vec![2024]) }).await;
+        assert_eq!(retried.unwrap(), vec![2024]);
+    }
+
+    #[tokio::test]
+    async fn test_shared_database_outage_stays_503() {
+        let flights = SingleFlight::<Vec<i32>>::new();
+        let lookup = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err(AppError::Database(sqlx::Error::PoolTimedOut))
+        };
+
+        // The first call runs the lookup, the second joins it
+        let (ran, joined) = tokio::join!(flights.run("search:hlzf:ab12", lookup), flights.run("search:hlzf:ab12", lookup));
+        for error in [ran.unwrap_err(), joined.unwrap_err()] {
+            assert!(matches!(error, AppError::Database(sqlx::Error::PoolTimedOut)), "{:?}", error);
+            assert_eq!(error.status_code(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+}
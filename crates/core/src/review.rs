@@ -0,0 +1,568 @@
+use crate::normalize::parse_german_amount;
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// What an admin concluded about an extracted entry. Each review is written
+/// to `data_entry_history` with the outcome in `changes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewOutcome {
+    Verified,
+    Rejected,
+    /// Values were edited; the extraction was wrong but fixable
+    Corrected,
+}
+
+impl ReviewOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewOutcome::Verified => "verified",
+            ReviewOutcome::Rejected => "rejected",
+            ReviewOutcome::Corrected => "corrected",
+        }
+    }
+}
+
+/// Entry types with their table and the value columns an admin may correct
+const CORRECTABLE: [(&str, &str, &[&str]); 2] = [
+    ("netzentgelte", "netzentgelte_data", &["leistung", "arbeit", "leistung_unter_2500h", "arbeit_unter_2500h"]),
+    ("hlzf", "hlzf_data", &["start_time", "end_time"]),
+];
+
+/// Entry row as JSON, locked for the rest of the transaction
+async fn lock_entry(conn: &mut PgConnection, entry_id: Uuid) -> Result<Option<(&'static str, &'static str, Value)>, AppError> {
+    for (entry_type, table, _) in CORRECTABLE {
+        let row: Option<Value> = sqlx::query_scalar(&format!(
+            "SELECT to_jsonb(t) FROM {table} t WHERE t.id = $1 AND t.deleted_at IS NULL FOR UPDATE"
+        ))
+        .bind(entry_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(AppError::Database)?;
+        if let Some(row) = row {
+            return Ok(Some((entry_type, table, row)));
+        }
+    }
+    Ok(None)
+}
+
+async fn record_review(
+    conn: &mut PgConnection,
+    entry_type: &str,
+    entry_id: Uuid,
+    reviewer: Uuid,
+    outcome: ReviewOutcome,
+    before: &Value,
+    after: &Value,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO data_entry_history (entry_type, entry_id, version, changed_by, changes, data_before, data_after)
+        SELECT $1, $2, COALESCE(MAX(version), 0) + 1, $3, $4, $5, $6
+        FROM data_entry_history WHERE entry_type = $1 AND entry_id = $2
+        "#,
+    )
+    .bind(entry_type)
+    .bind(entry_id)
+    .bind(reviewer)
+    .bind(outcome.as_str())
+    .bind(before)
+    .bind(after)
+    .execute(&mut *conn)
+    .await
+    .map_err(AppError::Database)?;
+    Ok(())
+}
+
+/// Mark an entry verified or rejected by an admin and record the review.
+/// Returns the updated entry, or `None` if it does not exist.
+pub async fn review_data_entry(
+    pool: &PgPool,
+    entry_id: Uuid,
+    outcome: ReviewOutcome,
+    reviewer: Uuid,
+    notes: Option<&str>,
+) -> Result<Option<Value>, AppError> {
+    if outcome == ReviewOutcome::Corrected {
+        return Err(AppError::BadRequest("Corrections are made by updating the entry".to_string()));
+    }
+
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    let Some((entry_type, table, before)) = lock_entry(&mut tx, entry_id).await? else {
+        return Ok(None);
+    };
+
+    let after: Value = sqlx::query_scalar(&format!(
+        r#"
+        UPDATE {table} t
+        SET verification_status = $1, verified_by = $2, verified_at = CURRENT_TIMESTAMP, verification_notes = $3
+        WHERE t.id = $4
+        RETURNING to_jsonb(t)
+        "#
+    ))
+    .bind(outcome.as_str())
+    .bind(reviewer)
+    .bind(notes)
+    .bind(entry_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+
+    record_review(&mut tx, entry_type, entry_id, reviewer, outcome, &before, &after).await?;
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(Some(after))
+}
+
+/// Overwrite extracted values of an entry and record the review as a correction.
+/// Netzentgelte values accept numbers or German-formatted strings ("58,21");
+/// HLZF times are `HH:MM[:SS]` strings. Unknown fields are rejected.
+pub async fn correct_data_entry(
+    pool: &PgPool,
+    entry_id: Uuid,
+    changes: &serde_json::Map<String, Value>,
+    reviewer: Uuid,
+) -> Result<Option<Value>, AppError> {
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    let corrected = apply_correction(&mut tx, entry_id, changes, reviewer).await?;
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(corrected)
+}
+
+/// `correct_data_entry` inside the caller's transaction, for workflows that
+/// record their own decision alongside the correction
+pub(crate) async fn apply_correction(
+    conn: &mut PgConnection,
+    entry_id: Uuid,
+    changes: &serde_json::Map<String, Value>,
+    reviewer: Uuid,
+) -> Result<Option<Value>, AppError> {
+    if changes.is_empty() {
+        return Err(AppError::BadRequest("No fields to update".to_string()));
+    }
+
+    let Some((entry_type, table, before)) = lock_entry(&mut *conn, entry_id).await? else {
+        return Ok(None);
+    };
+    let (_, _, fields) = CORRECTABLE.iter().find(|(t, _, _)| *t == entry_type).expect("entry type from CORRECTABLE");
+
+    let mut query = sqlx::QueryBuilder::new(format!("UPDATE {table} t SET "));
+    let mut set = query.separated(", ");
+    for (field, value) in changes {
+        if !fields.contains(&field.as_str()) {
+            return Err(AppError::BadRequest(format!("Field '{}' cannot be edited on {} entries", field, entry_type)));
+        }
+        set.push(format!("{field} = "));
+        if entry_type == "netzentgelte" {
+            set.push_bind_unseparated(decimal_value(field, value)?);
+        } else {
+            let time = match value {
+                Value::Null => None,
+                Value::String(time) if !time.is_empty() => Some(time.clone()),
+                _ => return Err(AppError::BadRequest(format!("Field '{}' must be a time string or null", field))),
+            };
+            set.push_bind_unseparated(time).push_unseparated("::time");
+        }
+    }
+    query.push(", verification_status = ").push_bind(ReviewOutcome::Verified.as_str());
+    query.push(", verified_by = ").push_bind(reviewer);
+    query.push(", verified_at = CURRENT_TIMESTAMP WHERE t.id = ").push_bind(entry_id);
+    query.push(" RETURNING to_jsonb(t)");
+
+    let after: Value = query
+        .build_query_scalar()
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(AppError::Database)?;
+
+    record_review(&mut *conn, entry_type, entry_id, reviewer, ReviewOutcome::Corrected, &before, &after).await?;
+    Ok(Some(after))
+}
+
+fn decimal_value(field: &str, value: &Value) -> Result<Option<Decimal>, AppError> {
+    let invalid = || AppError::BadRequest(format!("Field '{}' must be a number or null", field));
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(n) => n.to_string().parse::<Decimal>().map(Some).map_err(|_| invalid()),
+        Value::String(s) => parse_german_amount(s).map(Some).ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+/// Review counts for one extraction method and data type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AccuracyBreakdown {
+    pub extraction_method: String,
+    pub data_type: String,
+    /// Entries reviewed in the window
+    pub total: i64,
+    /// Latest decision was `verified` (including after a correction)
+    pub verified: i64,
+    pub rejected: i64,
+    /// Entries that needed at least one correction
+    pub corrected: i64,
+}
+
+impl AccuracyBreakdown {
+    /// Share of reviewed entries that ended up verified
+    pub fn precision(&self) -> f64 {
+        ratio(self.verified, self.total)
+    }
+
+    pub fn correction_rate(&self) -> f64 {
+        ratio(self.corrected, self.total)
+    }
+
+    pub fn rejection_rate(&self) -> f64 {
+        ratio(self.rejected, self.total)
+    }
+}
+
+fn ratio(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccuracyReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub breakdown: Vec<AccuracyBreakdown>,
+}
+
+/// Accuracy of extractions reviewed between `from` and `to`, by extraction
+/// method (from the entry's latest data source) and data type. Each entry
+/// counts once: its latest verify/reject decision in the window decides
+/// `verified`/`rejected`, and any correction in the window counts as `corrected`.
+pub async fn accuracy_metrics(pool: &PgPool, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<AccuracyReport, AppError> {
+    let breakdown = sqlx::query_as::<_, AccuracyBreakdown>(
+        r#"
+        WITH reviews AS (
+            SELECT entry_type, entry_id,
+                (array_agg(changes ORDER BY changed_at DESC, version DESC)
+                    FILTER (WHERE changes IN ('verified', 'rejected')))[1] AS decision,
+                bool_or(changes = 'corrected') AS corrected
+            FROM data_entry_history
+            WHERE changes IN ('verified', 'rejected', 'corrected')
+                AND changed_at >= $1 AND changed_at < $2
+            GROUP BY entry_type, entry_id
+        ),
+        entries AS (
+            SELECT id, dno_id, year, 'netzentgelte' AS data_type FROM netzentgelte_data
+            UNION ALL
+            SELECT id, dno_id, year, 'hlzf' AS data_type FROM hlzf_data
+        )
+        SELECT
+            COALESCE(src.extraction_method, 'unknown') AS extraction_method,
+            r.entry_type AS data_type,
+            COUNT(*) AS total,
+            COUNT(*) FILTER (WHERE r.decision = 'verified' OR (r.decision IS NULL AND r.corrected)) AS verified,
+            COUNT(*) FILTER (WHERE r.decision = 'rejected') AS rejected,
+            COUNT(*) FILTER (WHERE r.corrected) AS corrected
+        FROM reviews r
+        JOIN entries e ON e.id = r.entry_id AND e.data_type = r.entry_type
+        LEFT JOIN LATERAL (
+            SELECT ds.extraction_method
+            FROM data_sources ds
+            WHERE ds.dno_id = e.dno_id AND ds.year = e.year AND ds.data_type::text = e.data_type
+            ORDER BY ds.extracted_at DESC
+            LIMIT 1
+        ) src ON true
+        GROUP BY 1, 2
+        ORDER BY 1, 2
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(AccuracyReport { from, to, breakdown })
+}
+
+/// Reviewed entries whose source reported a confidence in `[lower, upper)`
+/// (the last bucket includes 1.0), with how admins decided on them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub total: i64,
+    pub verified: i64,
+    pub rejected: i64,
+    /// Average reported confidence of the bucket's entries
+    pub mean_confidence: f64,
+    /// Share of the bucket's entries that ended up verified
+    pub observed_rate: f64,
+    /// `mean_confidence - observed_rate`; positive when the extractor is overconfident
+    pub gap: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Buckets with at least one entry, lowest confidence first
+    pub buckets: Vec<CalibrationBucket>,
+    /// Reviewed entries whose source recorded no confidence
+    pub without_confidence: i64,
+    /// Expected calibration error: the entry-weighted mean of `|gap|`
+    pub calibration_error: f64,
+    /// `calibration_error` is at most `CALIBRATION_TOLERANCE`
+    pub well_calibrated: bool,
+}
+
+/// Largest calibration error still reported as well calibrated
+pub const CALIBRATION_TOLERANCE: f64 = 0.1;
+
+impl CalibrationReport {
+    /// Bucket `(confidence, verified)` review outcomes into `bucket_count`
+    /// equal-width confidence ranges
+    pub fn from_outcomes(
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket_count: usize,
+        outcomes: &[(Option<f64>, bool)],
+    ) -> Self {
+        let bucket_count = bucket_count.clamp(1, 100);
+        let width = 1.0 / bucket_count as f64;
+        // (total, verified, confidence sum) per bucket
+        let mut tallies = vec![(0i64, 0i64, 0.0f64); bucket_count];
+        let mut without_confidence = 0;
+        for &(confidence, verified) in outcomes {
+            let Some(confidence) = confidence else {
+                without_confidence += 1;
+                continue;
+            };
+            let confidence = confidence.clamp(0.0, 1.0);
+            // The epsilon keeps e.g. 0.3 out of the 0.2 bucket despite 0.3 / 0.1 < 3.0
+            let index = ((confidence / width + 1e-9) as usize).min(bucket_count - 1);
+            let tally = &mut tallies[index];
+            tally.0 += 1;
+            tally.1 += verified as i64;
+            tally.2 += confidence;
+        }
+
+        let scored: i64 = tallies.iter().map(|tally| tally.0).sum();
+        let buckets: Vec<CalibrationBucket> = tallies
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (total, _, _))| *total > 0)
+            .map(|(index, (total, verified, confidence_sum))| {
+                let mean_confidence = confidence_sum / total as f64;
+                let observed_rate = ratio(verified, total);
+                CalibrationBucket {
+                    lower: index as f64 * width,
+                    upper: (index + 1) as f64 * width,
+                    total,
+                    verified,
+                    rejected: total - verified,
+                    mean_confidence,
+                    observed_rate,
+                    gap: mean_confidence - observed_rate,
+                }
+            })
+            .collect();
+        let calibration_error = buckets
+            .iter()
+            .map(|bucket| ratio(bucket.total, scored) * bucket.gap.abs())
+            .sum::<f64>();
+
+        Self {
+            from,
+            to,
+            buckets,
+            without_confidence,
+            calibration_error,
+            well_calibrated: calibration_error <= CALIBRATION_TOLERANCE,
+        }
+    }
+}
+
+/// How well the confidence sources report matches admin decisions on the
+/// entries reviewed between `from` and `to`. Each entry counts once with its
+/// latest decision, as in `accuracy_metrics`, and the confidence of its latest
+/// data source.
+pub async fn calibration_report(
+    pool: &PgPool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    bucket_count: usize,
+) -> Result<CalibrationReport, AppError> {
+    let outcomes = sqlx::query_as::<_, (Option<f64>, bool)>(
+        r#"
+        WITH reviews AS (
+            SELECT entry_type, entry_id,
+                (array_agg(changes ORDER BY changed_at DESC, version DESC)
+                    FILTER (WHERE changes IN ('verified', 'rejected')))[1] AS decision,
+                bool_or(changes = 'corrected') AS corrected
+            FROM data_entry_history
+            WHERE changes IN ('verified', 'rejected', 'corrected')
+                AND changed_at >= $1 AND changed_at < $2
+            GROUP BY entry_type, entry_id
+        ),
+        entries AS (
+            SELECT id, dno_id, year, 'netzentgelte' AS data_type FROM netzentgelte_data
+            UNION ALL
+            SELECT id, dno_id, year, 'hlzf' AS data_type FROM hlzf_data
+        )
+        SELECT
+            src.confidence,
+            COALESCE(r.decision = 'verified' OR (r.decision IS NULL AND r.corrected), false) AS verified
+        FROM reviews r
+        JOIN entries e ON e.id = r.entry_id AND e.data_type = r.entry_type
+        LEFT JOIN LATERAL (
+            SELECT ds.confidence::float8 AS confidence
+            FROM data_sources ds
+            WHERE ds.dno_id = e.dno_id AND ds.year = e.year AND ds.data_type::text = e.data_type
+            ORDER BY ds.extracted_at DESC
+            LIMIT 1
+        ) src ON true
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    Ok(CalibrationReport::from_outcomes(from, to, bucket_count, &outcomes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    async fn seed(pool: &PgPool) {
+        crate::test_schema::create(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (id, slug, name) VALUES ('00000000-0000-0000-0000-000000000001', 'netze-bw', 'Netze BW');
+            INSERT INTO users (id, email, password_hash, name, role) VALUES
+                ('00000000-0000-0000-0000-0000000000aa', 'admin@example.com', 'x', 'Admin', 'admin');
+
+            -- 2023 was read from HTML tables, 2024 by OCR
+            INSERT INTO data_sources (dno_id, year, data_type, source_type, extracted_at, extraction_method, confidence) VALUES
+                ('00000000-0000-0000-0000-000000000001', 2023, 'netzentgelte', 'table', CURRENT_TIMESTAMP, 'html_table', 0.95),
+                ('00000000-0000-0000-0000-000000000001', 2024, 'netzentgelte', 'file', CURRENT_TIMESTAMP, 'ocr', 0.55);
+            INSERT INTO netzentgelte_data (id, dno_id, year, voltage_level, leistung, arbeit) VALUES
+                ('00000000-0000-0000-0000-00000000a001', '00000000-0000-0000-0000-000000000001', 2023, 'hs', 10.00, 0.50),
+                ('00000000-0000-0000-0000-00000000a002', '00000000-0000-0000-0000-000000000001', 2023, 'ms', 58.21, 1.26),
+                ('00000000-0000-0000-0000-00000000a003', '00000000-0000-0000-0000-000000000001', 2023, 'ns', 21.04, 5.67),
+                ('00000000-0000-0000-0000-00000000b001', '00000000-0000-0000-0000-000000000001', 2024, 'ms', 6.02, 1.30),
+                ('00000000-0000-0000-0000-00000000b002', '00000000-0000-0000-0000-000000000001', 2024, 'ns', 99.99, 9.99);
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_accuracy_by_method_over_reviewed_entries(pool: PgPool) {
+        seed(&pool).await;
+        let admin = Uuid::from_u128(0xaa);
+        let id = |s: &str| Uuid::parse_str(s).unwrap();
+
+        // HTML tables: two verified, one rejected
+        for entry in ["00000000-0000-0000-0000-00000000a001", "00000000-0000-0000-0000-00000000a002"] {
+            review_data_entry(&pool, id(entry), ReviewOutcome::Verified, admin, None).await.unwrap();
+        }
+        review_data_entry(&pool, id("00000000-0000-0000-0000-00000000a003"), ReviewOutcome::Rejected, admin, Some("wrong year"))
+            .await
+            .unwrap();
+
+        // OCR: one misread decimal corrected, one rejected
+        let mut changes = serde_json::Map::new();
+        changes.insert("leistung".to_string(), Value::String("60,20".to_string()));
+        let corrected = correct_data_entry(&pool, id("00000000-0000-0000-0000-00000000b001"), &changes, admin)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(corrected["leistung"], 60.2);
+        review_data_entry(&pool, id("00000000-0000-0000-0000-00000000b002"), ReviewOutcome::Rejected, admin, None)
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        let report = accuracy_metrics(&pool, now - Duration::hours(1), now + Duration::hours(1)).await.unwrap();
+        assert_eq!(report.breakdown.len(), 2);
+
+        let html = report.breakdown.iter().find(|b| b.extraction_method == "html_table").unwrap();
+        assert_eq!((html.total, html.verified, html.rejected, html.corrected), (3, 2, 1, 0));
+        assert!((html.precision() - 2.0 / 3.0).abs() < 1e-9);
+
+        let ocr = report.breakdown.iter().find(|b| b.extraction_method == "ocr").unwrap();
+        assert_eq!((ocr.total, ocr.verified, ocr.rejected, ocr.corrected), (2, 1, 1, 1));
+        assert_eq!(ocr.correction_rate(), 0.5);
+
+        // Reviews outside the window are not counted
+        let report = accuracy_metrics(&pool, now - Duration::days(2), now - Duration::days(1)).await.unwrap();
+        assert!(report.breakdown.is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_calibration_buckets_by_reported_confidence(pool: PgPool) {
+        seed(&pool).await;
+        let admin = Uuid::from_u128(0xaa);
+        let id = |s: &str| Uuid::parse_str(s).unwrap();
+
+        // Confidence 0.95 (HTML tables): two verified, one rejected
+        for entry in ["00000000-0000-0000-0000-00000000a001", "00000000-0000-0000-0000-00000000a002"] {
+            review_data_entry(&pool, id(entry), ReviewOutcome::Verified, admin, None).await.unwrap();
+        }
+        review_data_entry(&pool, id("00000000-0000-0000-0000-00000000a003"), ReviewOutcome::Rejected, admin, None)
+            .await
+            .unwrap();
+        // Confidence 0.55 (OCR): one verified, one rejected
+        review_data_entry(&pool, id("00000000-0000-0000-0000-00000000b001"), ReviewOutcome::Verified, admin, None)
+            .await
+            .unwrap();
+        review_data_entry(&pool, id("00000000-0000-0000-0000-00000000b002"), ReviewOutcome::Rejected, admin, None)
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        let report = calibration_report(&pool, now - Duration::hours(1), now + Duration::hours(1), 10).await.unwrap();
+        assert_eq!(report.buckets.len(), 2);
+        assert_eq!(report.without_confidence, 0);
+
+        let low = &report.buckets[0];
+        assert!((low.lower - 0.5).abs() < 1e-9 && (low.upper - 0.6).abs() < 1e-9);
+        assert_eq!((low.total, low.verified, low.rejected), (2, 1, 1));
+        assert!((low.observed_rate - 0.5).abs() < 1e-9);
+        assert!((low.gap - 0.05).abs() < 1e-9);
+
+        let high = &report.buckets[1];
+        assert!((high.lower - 0.9).abs() < 1e-9);
+        assert_eq!((high.total, high.verified, high.rejected), (3, 2, 1));
+        assert!((high.observed_rate - 2.0 / 3.0).abs() < 1e-9);
+        // 95% reported against 67% verified: overconfident
+        assert!((high.gap - (0.95 - 2.0 / 3.0)).abs() < 1e-9);
+
+        let expected_error = 0.4 * 0.05 + 0.6 * (0.95 - 2.0 / 3.0);
+        assert!((report.calibration_error - expected_error).abs() < 1e-9);
+        assert!(!report.well_calibrated);
+    }
+
+    #[test]
+    fn test_ratios_handle_empty_totals() {
+        let empty = AccuracyBreakdown {
+            extraction_method: "ocr".to_string(),
+            data_type: "netzentgelte".to_string(),
+            total: 0,
+            verified: 0,
+            rejected: 0,
+            corrected: 0,
+        };
+        assert_eq!(empty.precision(), 0.0);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{models::CreateDno, AppError};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::info;
+
+/// Known German DNOs, committed alongside the code so every deployment starts with the same list
+const GERMAN_DNOS: &str = include_str!("../data/german_dnos.json");
+
+#[derive(Debug, Deserialize)]
+struct KnownDno {
+    slug: String,
+    name: String,
+    official_name: Option<String>,
+    region: Option<String>,
+    website: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeedReport {
+    pub inserted: usize,
+    pub updated: usize,
+}
+
+/// Whether reference data should be seeded on startup. Set `SEED_DNOS=false`
+/// for test databases that manage their own fixtures.
+pub fn seed_enabled() -> bool {
+    std::env::var("SEED_DNOS")
+        .map(|v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | "no" | "off"))
+        .unwrap_or(true)
+}
+
+/// Parse the committed DNO list
+pub fn known_dnos() -> Result<Vec<CreateDno>, AppError> {
+    let dnos: Vec<KnownDno> = serde_json::from_str(GERMAN_DNOS)?;
+    Ok(dnos
+        .into_iter()
+        .map(|dno| CreateDno {
+            slug: dno.slug,
+            name: dno.name,
+            official_name: dno.official_name,
+            description: None,
+            region: dno.region,
+            website: dno.website,
+        })
+        .collect())
+}
+
+/// Upsert the known DNOs by slug. Safe to run on every start: existing rows
+/// keep their id and description, and only the seeded fields are refreshed.
+pub async fn seed_dnos(pool: &PgPool) -> Result<SeedReport, AppError> {
+    let mut report = SeedReport::default();
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+
+    for dno in known_dnos()? {
+        // xmax = 0 only for rows created by this statement
+        let inserted: bool = sqlx::query_scalar(
+            r#"
+            INSERT INTO dnos (slug, name, official_name, region, website)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (slug) DO UPDATE SET
+                name = EXCLUDED.name,
+                official_name = EXCLUDED.official_name,
+                region = EXCLUDED.region,
+                website = EXCLUDED.website
+            RETURNING (xmax = 0)
+            "#,
+        )
+        .bind(&dno.slug)
+        .bind(&dno.name)
+        .bind(&dno.official_name)
+        .bind(&dno.region)
+        .bind(&dno.website)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+
+        if inserted {
+            report.inserted += 1;
+        } else {
+            report.updated += 1;
+        }
+    }
+
+    tx.commit().await.map_err(AppError::Database)?;
+    info!("Seeded DNOs: {} inserted, {} updated", report.inserted, report.updated);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_dnos_have_unique_slugs() {
+        let dnos = known_dnos().unwrap();
+        let mut slugs: Vec<_> = dnos.iter().map(|d| d.slug.as_str()).collect();
+        slugs.sort_unstable();
+        slugs.dedup();
+        assert_eq!(slugs.len(), dnos.len());
+        assert!(slugs.contains(&"netze-bw"));
+        for slug in slugs {
+            assert_eq!(crate::slugify_dno(slug), slug, "seed slug is not canonical");
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_seeding_twice_yields_no_duplicates(pool: PgPool) {
+        crate::test_schema::create(&pool).await;
+
+        let expected = known_dnos().unwrap().len();
+
+        let first = seed_dnos(&pool).await.unwrap();
+        assert_eq!(first, SeedReport { inserted: expected, updated: 0 });
+
+        let second = seed_dnos(&pool).await.unwrap();
+        assert_eq!(second, SeedReport { inserted: 0, updated: expected });
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM dnos")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count as usize, expected);
+    }
+}
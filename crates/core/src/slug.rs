@@ -0,0 +1,77 @@
+/// Separator between words in DNO slugs
+pub const SLUG_SEPARATOR: char = '-';
+
+/// Canonical slug for a DNO name, used for the `dnos.slug` column, cache keys,
+/// storage paths and lookups so every code path agrees on one spelling.
+///
+/// Lowercases, transliterates German umlauts and ß (`Thüringer` → `thueringer`),
+/// folds common accented Latin letters to ASCII, and turns every other run of
+/// non-alphanumeric characters into a single separator. Slugifying a slug
+/// returns it unchanged.
+pub fn slugify_dno(name: &str) -> String {
+    slugify_with(name, SLUG_SEPARATOR)
+}
+
+/// `slugify_dno` with a different word separator, e.g. `_` for file names
+pub fn slugify_with(name: &str, separator: char) -> String {
+    let mut slug = String::with_capacity(name.len());
+    let mut pending_separator = false;
+
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let replacement = match c {
+            'ä' => "ae",
+            'ö' => "oe",
+            'ü' => "ue",
+            'ß' => "ss",
+            'à' | 'á' | 'â' | 'ã' | 'å' => "a",
+            'ç' => "c",
+            'è' | 'é' | 'ê' | 'ë' => "e",
+            'ì' | 'í' | 'î' | 'ï' => "i",
+            'ñ' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ø' => "o",
+            'ù' | 'ú' | 'û' => "u",
+            c if c.is_ascii_alphanumeric() => {
+                push_word(&mut slug, &mut pending_separator, separator, c.encode_utf8(&mut [0; 4]));
+                continue;
+            }
+            _ => {
+                pending_separator = true;
+                continue;
+            }
+        };
+        push_word(&mut slug, &mut pending_separator, separator, replacement);
+    }
+
+    slug
+}
+
+fn push_word(slug: &mut String, pending_separator: &mut bool, separator: char, part: &str) {
+    if *pending_separator && !slug.is_empty() {
+        slug.push(separator);
+    }
+    *pending_separator = false;
+    slug.push_str(part);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_umlauts_and_punctuation() {
+        assert_eq!(slugify_dno("Thüringer Energienetze"), "thueringer-energienetze");
+        assert_eq!(slugify_dno("Netzgesellschaft Düsseldorf"), "netzgesellschaft-duesseldorf");
+        assert_eq!(slugify_dno("ÖSTERREICHISCHE Straße"), "oesterreichische-strasse");
+        assert_eq!(slugify_dno("E.DIS Netz"), "e-dis-netz");
+        assert_eq!(slugify_dno("  Stadtwerke Musterstadt GmbH & Co. KG  "), "stadtwerke-musterstadt-gmbh-co-kg");
+        assert_eq!(slugify_with("Netze BW", '_'), "netze_bw");
+    }
+
+    #[test]
+    fn test_slugify_is_idempotent() {
+        for name in ["Thüringer Energienetze", "N-ERGIE Netz", "e-netz Südhessen", "--Netze  ODR--", "netze-bw"] {
+            let slug = slugify_dno(name);
+            assert_eq!(slugify_dno(&slug), slug);
+        }
+    }
+}
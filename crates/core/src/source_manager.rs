@@ -0,0 +1,1370 @@
+use crate::models::Pagination;
+use crate::slug::slugify_dno;
+use crate::webhooks::WebhookEvent;
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Directory under `base_dir` holding the downloaded source documents
+pub const DATA_DIR: &str = "dno-data";
+
+/// Sidecar file under `base_dir`, next to `DATA_DIR`, holding the file
+/// metadata and audit trail across restarts
+pub const METADATA_FILE: &str = ".metadata.json";
+
+/// Mutations between sidecar writes unless `SOURCE_METADATA_AUTOSAVE_EVERY` says otherwise
+pub const DEFAULT_AUTOSAVE_EVERY: u32 = 1;
+
+/// Read `SOURCE_METADATA_AUTOSAVE_EVERY` (0 turns autosave off);
+/// `DEFAULT_AUTOSAVE_EVERY` when unset or invalid
+pub fn autosave_every_from_env() -> u32 {
+    std::env::var("SOURCE_METADATA_AUTOSAVE_EVERY")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_AUTOSAVE_EVERY)
+}
+
+/// Where a stored source file is in admin review. Only the transitions in
+/// `allowed_transitions` are possible; see `AdminInterface::review_file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminDataVerificationStatus {
+    Pending,
+    /// An admin is checking the file against its extraction
+    InReview,
+    /// The file alone can't confirm the data; a better source is needed
+    NeedsSource,
+    Verified,
+    Rejected,
+    /// A newer verified file replaced this one
+    Superseded,
+}
+
+impl AdminDataVerificationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminDataVerificationStatus::Pending => "pending",
+            AdminDataVerificationStatus::InReview => "in_review",
+            AdminDataVerificationStatus::NeedsSource => "needs_source",
+            AdminDataVerificationStatus::Verified => "verified",
+            AdminDataVerificationStatus::Rejected => "rejected",
+            AdminDataVerificationStatus::Superseded => "superseded",
+        }
+    }
+
+    /// Statuses a file in this status may move to. Obvious junk can be
+    /// rejected straight from `Pending`; a rejected file can be reopened.
+    pub fn allowed_transitions(&self) -> &'static [AdminDataVerificationStatus] {
+        use AdminDataVerificationStatus::*;
+        match self {
+            Pending => &[InReview, Rejected],
+            InReview => &[Verified, Rejected, NeedsSource, Pending],
+            NeedsSource => &[InReview, Rejected],
+            Verified => &[Superseded],
+            Rejected => &[InReview],
+            Superseded => &[],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: AdminDataVerificationStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+}
+
+/// One step in a file's history: download, extraction, review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceRecord {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+/// A source document stored under `base_dir/dno-data/<dno>/<year>/`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub id: Uuid,
+    /// DNO slug (see `slugify_dno`)
+    pub dno_key: String,
+    pub year: i32,
+    pub data_type: String,
+    pub file_name: String,
+    pub file_path: PathBuf,
+    pub source_url: Option<String>,
+    pub content_type: Option<String>,
+    /// Hex SHA-256 of the file contents
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub stored_at: DateTime<Utc>,
+    /// `false` once an admin rejected the file
+    pub is_active: bool,
+    pub verification_status: AdminDataVerificationStatus,
+    /// Values extracted from the file, with their provenance
+    pub extraction: Option<serde_json::Value>,
+    pub provenance: Vec<ProvenanceRecord>,
+    /// The file this one was found to duplicate; its copy on disk was removed
+    #[serde(default)]
+    pub duplicate_of: Option<Uuid>,
+    /// When `purge_inactive` deleted the file from disk; the metadata stays
+    #[serde(default)]
+    pub purged_at: Option<DateTime<Utc>>,
+    /// When the bytes on disk were last checked against `sha256`
+    #[serde(default)]
+    pub last_verified_at: Option<DateTime<Utc>>,
+    /// What the last integrity check found wrong; `None` when it passed
+    #[serde(default)]
+    pub integrity_error: Option<String>,
+}
+
+/// A document to store, as downloaded by the crawler
+#[derive(Debug, Clone)]
+pub struct NewSourceFile<'a> {
+    pub dno: &'a str,
+    pub year: i32,
+    pub data_type: &'a str,
+    pub file_name: &'a str,
+    pub source_url: Option<&'a str>,
+    pub content_type: Option<&'a str>,
+    pub bytes: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    FileStored,
+    ExtractionUpdated,
+    FileReviewed,
+    FileDeleted,
+    Deduplicated,
+    /// An integrity check found the file missing or changed on disk
+    IntegrityFailed,
+}
+
+/// Who did what to which file, and when
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub operation: AuditOperation,
+    /// User email or id, or `crawler` for automated operations
+    pub actor: String,
+    /// Path of the file relative to `base_dir`
+    pub target: String,
+    pub file_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+/// Audit trail query; every set field must match. `from` is inclusive, `to` exclusive.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    pub operation: Option<AuditOperation>,
+    /// Exact actor, case-insensitive
+    pub actor: Option<String>,
+    /// Substring of the target path, case-insensitive
+    pub target_contains: Option<String>,
+    pub file_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.operation.is_none_or(|operation| entry.operation == operation)
+            && self.actor.as_deref().is_none_or(|actor| entry.actor.eq_ignore_ascii_case(actor))
+            && self
+                .target_contains
+                .as_deref()
+                .is_none_or(|part| entry.target.to_lowercase().contains(&part.to_lowercase()))
+            && self.file_id.is_none_or(|id| entry.file_id == Some(id))
+            && self.from.is_none_or(|from| entry.timestamp >= from)
+            && self.to.is_none_or(|to| entry.timestamp < to)
+    }
+}
+
+/// One page of matching audit entries, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    pub pagination: Pagination,
+}
+
+/// What to do with the duplicates of a group
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendedAction {
+    /// Identical contents; exact deduplication removes them
+    #[default]
+    Remove,
+    /// Nearly identical contents (e.g. a republished PDF with a new footer date);
+    /// an admin decides which copy stays
+    ManualReview,
+}
+
+/// Files with identical or, for fuzzy runs, similar contents; the earliest
+/// stored one is kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// Hash of the kept file
+    pub sha256: String,
+    pub kept: Uuid,
+    pub kept_path: PathBuf,
+    pub duplicates: Vec<Uuid>,
+    pub bytes_saved: u64,
+    #[serde(default)]
+    pub recommended_action: RecommendedAction,
+    /// Lowest similarity of a duplicate to the kept file, for fuzzy runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
+}
+
+/// Outcome of one `purge_inactive` run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PurgeReport {
+    /// Inactive files past the cutoff that were not purged before
+    pub files_scanned: usize,
+    pub purged: Vec<Uuid>,
+    /// Files whose bytes were actually deleted; deduplicated copies are usually gone already
+    pub files_deleted: usize,
+    pub bytes_reclaimed: u64,
+    /// Purged files whose path is still used by an active file, left on disk
+    pub shared_paths_kept: usize,
+}
+
+/// Outcome of one `perform_deduplication` run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeduplicationResult {
+    pub run_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Active files compared
+    pub files_scanned: usize,
+    pub groups: Vec<DuplicateGroup>,
+    pub duplicates_removed: usize,
+    pub bytes_saved: u64,
+}
+
+/// Outcome of one `verify_all_incremental` pass
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub verified: usize,
+    /// Files found missing or changed, including ones already known to be
+    pub corrupted: Vec<Uuid>,
+    /// Files found corrupted that passed their previous check
+    pub newly_corrupted: usize,
+}
+
+/// Cadence of the background integrity scan, see `spawn_integrity_scans`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegrityScanConfig {
+    pub interval: std::time::Duration,
+    /// Files checked per run
+    pub batch: usize,
+}
+
+impl Default for IntegrityScanConfig {
+    fn default() -> Self {
+        Self { interval: std::time::Duration::from_secs(3600), batch: 100 }
+    }
+}
+
+impl IntegrityScanConfig {
+    /// `INTEGRITY_SCAN_INTERVAL_SECS` (default 3600, 0 turns the scan off) and
+    /// `INTEGRITY_SCAN_BATCH` (default 100); `None` when the scan is off
+    pub fn from_env() -> Option<Self> {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let interval = var("INTEGRITY_SCAN_INTERVAL_SECS").map_or(defaults.interval, std::time::Duration::from_secs);
+        if interval.is_zero() {
+            return None;
+        }
+        let batch = var("INTEGRITY_SCAN_BATCH").filter(|&n| n > 0).map_or(defaults.batch, |n| n as usize);
+        Some(Self { interval, batch })
+    }
+}
+
+/// Run `verify_all_incremental` every `config.interval` until the task is
+/// aborted. A batch runs on the blocking pool, since it reads whole files.
+pub fn spawn_integrity_scans(sources: Arc<SourceManager>, config: IntegrityScanConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(config.interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            let sources = sources.clone();
+            match tokio::task::spawn_blocking(move || sources.verify_all_incremental(config.batch)).await {
+                Ok(report) if !report.corrupted.is_empty() => {
+                    warn!("Integrity scan: {} of {} source files corrupted", report.corrupted.len(), report.verified);
+                }
+                Ok(report) => debug!("Integrity scan: {} source files intact", report.verified),
+                Err(e) => warn!("Integrity scan failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Contents of the `METADATA_FILE` sidecar
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetadataSnapshot {
+    files: Vec<FileMetadata>,
+    audit_trail: Vec<AuditEntry>,
+}
+
+/// Stores downloaded source documents on disk and keeps their metadata and an
+/// audit trail of every operation on them. Both are written to the
+/// `METADATA_FILE` sidecar as they change and read back by `open`.
+pub struct SourceManager {
+    base_dir: PathBuf,
+    file_cache: RwLock<HashMap<Uuid, FileMetadata>>,
+    audit_trail: RwLock<Vec<AuditEntry>>,
+    /// Write the sidecar after this many mutations; 0 only saves on `save_metadata` and drop
+    autosave_every: u32,
+    unsaved: AtomicU32,
+    /// Serializes sidecar writes
+    save_lock: Mutex<()>,
+}
+
+impl SourceManager {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            file_cache: RwLock::new(HashMap::new()),
+            audit_trail: RwLock::new(Vec::new()),
+            autosave_every: DEFAULT_AUTOSAVE_EVERY,
+            unsaved: AtomicU32::new(0),
+            save_lock: Mutex::new(()),
+        }
+    }
+
+    /// A manager for `base_dir` with the files stored there before, see `scan_existing_files`
+    pub fn open(base_dir: impl Into<PathBuf>) -> Result<Self, AppError> {
+        let sources = Self::new(base_dir);
+        sources.scan_existing_files()?;
+        Ok(sources)
+    }
+
+    pub fn with_autosave_every(mut self, mutations: u32) -> Self {
+        self.autosave_every = mutations;
+        self
+    }
+
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Load the metadata and audit trail from the sidecar, then reconstruct
+    /// metadata for files under `DATA_DIR` that it does not know (stored
+    /// after the last save, or before there was a sidecar). Reconstructed
+    /// files lack their source URL, extraction and provenance. Returns the
+    /// number of files known afterwards.
+    pub fn scan_existing_files(&self) -> Result<usize, AppError> {
+        let sidecar = self.base_dir.join(METADATA_FILE);
+        let snapshot = match fs::read(&sidecar) {
+            Ok(bytes) => serde_json::from_slice::<MetadataSnapshot>(&bytes).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable {}: {}", sidecar.display(), e);
+                MetadataSnapshot::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => MetadataSnapshot::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut files = self.file_cache.write().unwrap();
+        files.extend(snapshot.files.into_iter().map(|file| (file.id, file)));
+        self.audit_trail.write().unwrap().extend(snapshot.audit_trail);
+
+        let mut reconstructed = 0;
+        for relative in self.stored_paths()? {
+            // Rejected files stay on disk, inactive
+            if files.values().any(|file| file.file_path == relative) {
+                continue;
+            }
+            let Some(file) = self.reconstruct(&relative)? else { continue };
+            files.insert(file.id, file);
+            reconstructed += 1;
+        }
+        let known = files.len();
+        drop(files);
+        info!("Loaded {} source files from {}", known, self.base_dir.display());
+        if reconstructed > 0 {
+            warn!("Reconstructed metadata for {} source files missing from {}", reconstructed, sidecar.display());
+            // Keep the reconstructed ids stable across further restarts
+            self.save_metadata()?;
+        }
+        Ok(known)
+    }
+
+    /// Paths of the files under `DATA_DIR/<dno>/<year>/`, relative to `base_dir`
+    fn stored_paths(&self) -> Result<Vec<PathBuf>, AppError> {
+        let mut paths = Vec::new();
+        let data_dir = self.base_dir.join(DATA_DIR);
+        if !data_dir.is_dir() {
+            return Ok(paths);
+        }
+        for dno in fs::read_dir(&data_dir)?.filter_map(Result::ok).filter(|entry| entry.path().is_dir()) {
+            for year in fs::read_dir(dno.path())?.filter_map(Result::ok).filter(|entry| entry.path().is_dir()) {
+                for file in fs::read_dir(year.path())?.filter_map(Result::ok).filter(|entry| entry.path().is_file()) {
+                    paths.push(Path::new(DATA_DIR).join(dno.file_name()).join(year.file_name()).join(file.file_name()));
+                }
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Metadata for a file known only from its place on disk; `None` if the
+    /// path does not follow the `<dno>/<year>/<file>` layout
+    fn reconstruct(&self, relative: &Path) -> Result<Option<FileMetadata>, AppError> {
+        let parts: Vec<String> = relative.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+        let [_, dno_key, year, file_name] = parts.as_slice() else {
+            return Ok(None);
+        };
+        let Ok(year) = year.parse() else {
+            return Ok(None);
+        };
+        let path = self.base_dir.join(relative);
+        let bytes = fs::read(&path)?;
+        let stored_at = fs::metadata(&path)?.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+        Ok(Some(FileMetadata {
+            id: Uuid::new_v4(),
+            dno_key: dno_key.clone(),
+            year,
+            data_type: "unknown".to_string(),
+            file_name: file_name.clone(),
+            file_path: relative.to_path_buf(),
+            source_url: None,
+            content_type: None,
+            sha256: format!("{:x}", Sha256::digest(&bytes)),
+            size_bytes: bytes.len() as u64,
+            stored_at,
+            is_active: true,
+            verification_status: AdminDataVerificationStatus::Pending,
+            extraction: None,
+            provenance: vec![ProvenanceRecord {
+                at: Utc::now(),
+                actor: "system".to_string(),
+                action: "reconstructed".to_string(),
+                details: Some("found on disk without saved metadata".to_string()),
+            }],
+            duplicate_of: None,
+            purged_at: None,
+            last_verified_at: None,
+            integrity_error: None,
+        }))
+    }
+
+    /// Write the metadata and audit trail to the sidecar. The file is
+    /// replaced atomically, so a crash mid-write keeps the previous version.
+    pub fn save_metadata(&self) -> Result<(), AppError> {
+        let _guard = self.save_lock.lock().unwrap();
+        let snapshot = MetadataSnapshot {
+            files: self.list_files(),
+            audit_trail: self.audit_trail.read().unwrap().clone(),
+        };
+        let json = serde_json::to_vec_pretty(&snapshot)?;
+
+        fs::create_dir_all(&self.base_dir)?;
+        let sidecar = self.base_dir.join(METADATA_FILE);
+        let temporary = sidecar.with_extension("json.tmp");
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, &sidecar)?;
+        self.unsaved.store(0, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Count a mutation and write the sidecar if autosave is due. Failures
+    /// are logged; the in-memory state stays authoritative.
+    fn autosave(&self) {
+        let unsaved = self.unsaved.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.autosave_every == 0 || unsaved < self.autosave_every {
+            return;
+        }
+        if let Err(e) = self.save_metadata() {
+            warn!("Failed to save source file metadata to {}: {}", self.base_dir.display(), e);
+        }
+    }
+
+    /// Write a document to `dno-data/<dno slug>/<year>/<file name>` and record it
+    pub fn store_file(&self, file: NewSourceFile<'_>, actor: &str) -> Result<FileMetadata, AppError> {
+        let dno_key = slugify_dno(file.dno);
+        let file_name = Path::new(file.file_name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| AppError::BadRequest(format!("Invalid file name '{}'", file.file_name)))?
+            .to_string();
+
+        let relative = Path::new(DATA_DIR).join(&dno_key).join(file.year.to_string()).join(&file_name);
+        let path = self.base_dir.join(&relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, file.bytes)?;
+
+        let now = Utc::now();
+        let metadata = FileMetadata {
+            id: Uuid::new_v4(),
+            dno_key,
+            year: file.year,
+            data_type: file.data_type.to_string(),
+            file_name,
+            file_path: relative,
+            source_url: file.source_url.map(str::to_string),
+            content_type: file.content_type.map(str::to_string),
+            sha256: format!("{:x}", Sha256::digest(file.bytes)),
+            size_bytes: file.bytes.len() as u64,
+            stored_at: now,
+            is_active: true,
+            verification_status: AdminDataVerificationStatus::Pending,
+            extraction: None,
+            provenance: vec![ProvenanceRecord {
+                at: now,
+                actor: actor.to_string(),
+                action: "stored".to_string(),
+                details: file.source_url.map(|url| format!("downloaded from {}", url)),
+            }],
+            duplicate_of: None,
+            purged_at: None,
+            last_verified_at: None,
+            integrity_error: None,
+        };
+
+        self.file_cache.write().unwrap().insert(metadata.id, metadata.clone());
+        self.record_audit(AuditOperation::FileStored, actor, &metadata, file.source_url.map(str::to_string));
+        debug!("Stored source file {}", metadata.file_path.display());
+        Ok(metadata)
+    }
+
+    pub fn get_file(&self, id: Uuid) -> Option<FileMetadata> {
+        self.file_cache.read().unwrap().get(&id).cloned()
+    }
+
+    /// All known files, oldest first
+    pub fn list_files(&self) -> Vec<FileMetadata> {
+        let mut files: Vec<FileMetadata> = self.file_cache.read().unwrap().values().cloned().collect();
+        files.sort_by_key(|file| file.stored_at);
+        files
+    }
+
+    /// Attach the values extracted from a file
+    pub fn update_extraction_results(
+        &self,
+        id: Uuid,
+        extraction: serde_json::Value,
+        actor: &str,
+    ) -> Result<FileMetadata, AppError> {
+        let metadata = self.update_file(id, |file| {
+            file.extraction = Some(extraction);
+            file.provenance.push(ProvenanceRecord {
+                at: Utc::now(),
+                actor: actor.to_string(),
+                action: "extracted".to_string(),
+                details: None,
+            });
+        })?;
+        self.record_audit(AuditOperation::ExtractionUpdated, actor, &metadata, None);
+        Ok(metadata)
+    }
+
+    /// Apply `change` to a file's metadata and return the result
+    pub(crate) fn update_file(
+        &self,
+        id: Uuid,
+        change: impl FnOnce(&mut FileMetadata),
+    ) -> Result<FileMetadata, AppError> {
+        self.try_update_file(id, |file| {
+            change(file);
+            Ok(())
+        })
+    }
+
+    /// `update_file` for changes that check the file first; on an error the file is left as it was
+    pub(crate) fn try_update_file(
+        &self,
+        id: Uuid,
+        change: impl FnOnce(&mut FileMetadata) -> Result<(), AppError>,
+    ) -> Result<FileMetadata, AppError> {
+        let mut files = self.file_cache.write().unwrap();
+        let file = files
+            .get_mut(&id)
+            .ok_or_else(|| AppError::NotFound(format!("Source file {} not found", id)))?;
+        change(file)?;
+        Ok(file.clone())
+    }
+
+    pub(crate) fn record_audit(
+        &self,
+        operation: AuditOperation,
+        actor: &str,
+        file: &FileMetadata,
+        details: Option<String>,
+    ) {
+        self.push_audit(operation, actor, file, details);
+        self.autosave();
+    }
+
+    /// `record_audit` without counting towards autosave
+    fn push_audit(&self, operation: AuditOperation, actor: &str, file: &FileMetadata, details: Option<String>) {
+        self.audit_trail.write().unwrap().push(AuditEntry {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            operation,
+            actor: actor.to_string(),
+            target: file.file_path.to_string_lossy().into_owned(),
+            file_id: Some(file.id),
+            details,
+        });
+    }
+
+    /// Find active files with identical contents and keep only the earliest
+    /// stored copy of each. Duplicates are deleted from disk, marked inactive
+    /// and point at the kept file through `duplicate_of`. The file cache stays
+    /// locked for the whole run, so files stored meanwhile wait for it.
+    pub fn perform_deduplication(&self, actor: &str) -> Result<DeduplicationResult, AppError> {
+        let started_at = Utc::now();
+        let mut files = self.file_cache.write().unwrap();
+
+        let mut by_hash: BTreeMap<String, Vec<(DateTime<Utc>, Uuid)>> = BTreeMap::new();
+        for file in files.values().filter(|file| file.is_active && file.duplicate_of.is_none()) {
+            by_hash.entry(file.sha256.clone()).or_default().push((file.stored_at, file.id));
+        }
+        let files_scanned = by_hash.values().map(Vec::len).sum();
+
+        let mut groups = Vec::new();
+        let mut removed = Vec::new();
+        for (sha256, mut members) in by_hash.into_iter().filter(|(_, members)| members.len() > 1) {
+            members.sort();
+            let kept = members[0].1;
+            let kept_path = files[&kept].file_path.clone();
+            let mut group = DuplicateGroup {
+                sha256,
+                kept,
+                kept_path: kept_path.clone(),
+                duplicates: Vec::new(),
+                bytes_saved: 0,
+                recommended_action: RecommendedAction::Remove,
+                similarity: None,
+            };
+
+            for (_, id) in &members[1..] {
+                let file = files.get_mut(id).expect("grouped from the cache");
+                // A later download of the same name overwrote the kept copy's path
+                if file.file_path != kept_path {
+                    match fs::remove_file(self.base_dir.join(&file.file_path)) {
+                        Ok(()) => group.bytes_saved += file.size_bytes,
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => {
+                            warn!("Keeping duplicate {}: {}", file.file_path.display(), e);
+                            continue;
+                        }
+                    }
+                }
+                file.is_active = false;
+                file.duplicate_of = Some(kept);
+                file.provenance.push(ProvenanceRecord {
+                    at: Utc::now(),
+                    actor: actor.to_string(),
+                    action: "deduplicated".to_string(),
+                    details: Some(format!("same contents as {}", kept_path.display())),
+                });
+                group.duplicates.push(*id);
+                removed.push(file.clone());
+            }
+            if !group.duplicates.is_empty() {
+                groups.push(group);
+            }
+        }
+        drop(files);
+
+        for file in &removed {
+            let details = file.duplicate_of.map(|kept| format!("duplicate of {}", kept));
+            self.record_audit(AuditOperation::Deduplicated, actor, file, details);
+        }
+        let result = DeduplicationResult {
+            run_id: Uuid::new_v4(),
+            started_at,
+            finished_at: Utc::now(),
+            files_scanned,
+            duplicates_removed: removed.len(),
+            bytes_saved: groups.iter().map(|group| group.bytes_saved).sum(),
+            groups,
+        };
+        info!(
+            "Deduplication removed {} files in {} groups, {} bytes saved",
+            result.duplicates_removed,
+            result.groups.len(),
+            result.bytes_saved
+        );
+        Ok(result)
+    }
+
+    /// Delete the bytes of inactive files (rejected or deduplicated) stored
+    /// more than `older_than` ago and record a `FileDeleted` audit entry for
+    /// each. Metadata is kept with `purged_at` set. A path still used by an
+    /// active file (a duplicate stored under its original's name) is left on
+    /// disk so the original keeps its contents.
+    pub fn purge_inactive(&self, older_than: std::time::Duration, actor: &str) -> Result<PurgeReport, AppError> {
+        let older_than = chrono::Duration::from_std(older_than)
+            .map_err(|_| AppError::BadRequest("Purge age is too large".to_string()))?;
+        let cutoff = Utc::now() - older_than;
+
+        let mut report = PurgeReport::default();
+        let mut purged = Vec::new();
+        {
+            let mut files = self.file_cache.write().unwrap();
+            let active_paths: HashSet<PathBuf> =
+                files.values().filter(|file| file.is_active).map(|file| file.file_path.clone()).collect();
+
+            for file in files.values_mut() {
+                if file.is_active || file.purged_at.is_some() || file.stored_at > cutoff {
+                    continue;
+                }
+                report.files_scanned += 1;
+                let details = if active_paths.contains(&file.file_path) {
+                    report.shared_paths_kept += 1;
+                    "bytes kept: path is used by an active file".to_string()
+                } else {
+                    match fs::remove_file(self.base_dir.join(&file.file_path)) {
+                        Ok(()) => {
+                            report.files_deleted += 1;
+                            report.bytes_reclaimed += file.size_bytes;
+                            format!("deleted {} bytes", file.size_bytes)
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "already removed from disk".to_string(),
+                        Err(e) => {
+                            warn!("Not purging {}: {}", file.file_path.display(), e);
+                            continue;
+                        }
+                    }
+                };
+                let now = Utc::now();
+                file.purged_at = Some(now);
+                file.provenance.push(ProvenanceRecord {
+                    at: now,
+                    actor: actor.to_string(),
+                    action: "purged".to_string(),
+                    details: Some(details.clone()),
+                });
+                report.purged.push(file.id);
+                purged.push((file.clone(), details));
+            }
+        }
+
+        for (file, details) in &purged {
+            self.record_audit(AuditOperation::FileDeleted, actor, file, Some(details.clone()));
+        }
+        info!(
+            "Purged {} inactive source files, {} bytes reclaimed",
+            report.purged.len(),
+            report.bytes_reclaimed
+        );
+        Ok(report)
+    }
+
+    /// Group active files whose contents are at least `threshold` (0–1)
+    /// similar to an earlier stored file, comparing content-defined chunks so
+    /// that a changed footer or trailer only affects the chunks around it.
+    /// Nothing is removed: the groups recommend `ManualReview`. Files that
+    /// cannot be read are skipped. Compares every pair, so it is meant for
+    /// occasional admin runs rather than every crawl.
+    pub fn perform_fuzzy_deduplication(&self, threshold: f64) -> Result<DeduplicationResult, AppError> {
+        if !(f64::MIN_POSITIVE..=1.0).contains(&threshold) {
+            return Err(AppError::BadRequest(format!("Similarity threshold must be in (0, 1], got {}", threshold)));
+        }
+        let started_at = Utc::now();
+
+        let candidates: Vec<(FileMetadata, ChunkFingerprint)> = self
+            .list_files()
+            .into_iter()
+            .filter(|file| file.is_active && file.duplicate_of.is_none())
+            .filter_map(|file| match fs::read(self.base_dir.join(&file.file_path)) {
+                Ok(bytes) => Some((file, ChunkFingerprint::of(&bytes))),
+                Err(e) => {
+                    warn!("Skipping {} for fuzzy deduplication: {}", file.file_path.display(), e);
+                    None
+                }
+            })
+            .collect();
+
+        let mut grouped = vec![false; candidates.len()];
+        let mut groups = Vec::new();
+        for i in 0..candidates.len() {
+            if grouped[i] {
+                continue;
+            }
+            let (kept, kept_fingerprint) = &candidates[i];
+            let mut group = DuplicateGroup {
+                sha256: kept.sha256.clone(),
+                kept: kept.id,
+                kept_path: kept.file_path.clone(),
+                duplicates: Vec::new(),
+                bytes_saved: 0,
+                recommended_action: RecommendedAction::ManualReview,
+                similarity: None,
+            };
+            for j in i + 1..candidates.len() {
+                if grouped[j] {
+                    continue;
+                }
+                let similarity = kept_fingerprint.similarity(&candidates[j].1);
+                if similarity >= threshold {
+                    grouped[j] = true;
+                    group.duplicates.push(candidates[j].0.id);
+                    group.similarity = Some(group.similarity.map_or(similarity, |lowest| lowest.min(similarity)));
+                }
+            }
+            if !group.duplicates.is_empty() {
+                groups.push(group);
+            }
+        }
+
+        let result = DeduplicationResult {
+            run_id: Uuid::new_v4(),
+            started_at,
+            finished_at: Utc::now(),
+            files_scanned: candidates.len(),
+            duplicates_removed: 0,
+            bytes_saved: 0,
+            groups,
+        };
+        info!(
+            "Fuzzy deduplication found {} groups of similar files at threshold {}",
+            result.groups.len(),
+            threshold
+        );
+        Ok(result)
+    }
+
+    /// Check a file's bytes on disk against its stored hash and record the
+    /// result in `last_verified_at` and `integrity_error`. Returns the problem
+    /// found, `None` if the file is intact. A file that turns corrupted gets
+    /// an `IntegrityFailed` audit entry; one that is already known to be
+    /// corrupted does not get another.
+    pub fn verify_file_integrity(&self, id: Uuid) -> Result<Option<String>, AppError> {
+        let problem = self.check_integrity(id)?;
+        self.autosave();
+        Ok(problem)
+    }
+
+    /// `verify_file_integrity` without saving the sidecar
+    fn check_integrity(&self, id: Uuid) -> Result<Option<String>, AppError> {
+        let file = self
+            .get_file(id)
+            .ok_or_else(|| AppError::NotFound(format!("Source file {} not found", id)))?;
+        let problem = match fs::read(self.base_dir.join(&file.file_path)) {
+            Ok(bytes) => {
+                let sha256 = format!("{:x}", Sha256::digest(&bytes));
+                (sha256 != file.sha256).then(|| format!("checksum mismatch: expected {}, found {}", file.sha256, sha256))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some("missing from disk".to_string()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let newly_corrupted = problem.is_some() && file.integrity_error.is_none();
+        let metadata = self.update_file(id, |file| {
+            let now = Utc::now();
+            file.last_verified_at = Some(now);
+            file.integrity_error = problem.clone();
+            if newly_corrupted {
+                file.provenance.push(ProvenanceRecord {
+                    at: now,
+                    actor: "system".to_string(),
+                    action: "integrity_failed".to_string(),
+                    details: problem.clone(),
+                });
+            }
+        })?;
+        if newly_corrupted {
+            warn!("Source file {} is corrupted: {}", metadata.file_path.display(), problem.as_deref().unwrap_or_default());
+            self.push_audit(AuditOperation::IntegrityFailed, "system", &metadata, problem.clone());
+            crate::webhooks::global().notify(WebhookEvent::FileCorrupted {
+                file_id: metadata.id,
+                path: metadata.file_path.display().to_string(),
+                problem: problem.clone().unwrap_or_default(),
+            });
+        }
+        Ok(problem)
+    }
+
+    /// Verify the `batch` files checked longest ago, never-checked files
+    /// first. Files without bytes on disk by design (inactive ones) are
+    /// skipped, so repeated passes cycle through every active file. Counts
+    /// go to the `dno_source_integrity_checks_total` metric. The sidecar is
+    /// written once per pass.
+    pub fn verify_all_incremental(&self, batch: usize) -> IntegrityReport {
+        let mut due: Vec<FileMetadata> = self.list_files().into_iter().filter(|file| file.is_active).collect();
+        due.sort_by_key(|file| (file.last_verified_at, file.stored_at));
+
+        let mut report = IntegrityReport::default();
+        for file in due.into_iter().take(batch) {
+            match self.check_integrity(file.id) {
+                Ok(problem) => {
+                    report.verified += 1;
+                    if problem.is_some() {
+                        report.newly_corrupted += usize::from(file.integrity_error.is_none());
+                        report.corrupted.push(file.id);
+                    }
+                }
+                Err(e) => warn!("Could not verify {}: {}", file.file_path.display(), e),
+            }
+        }
+        if report.verified > 0 {
+            if let Err(e) = self.save_metadata() {
+                warn!("Failed to save source file metadata to {}: {}", self.base_dir.display(), e);
+            }
+        }
+        crate::metrics::global().record_integrity(report.verified - report.corrupted.len(), report.corrupted.len());
+        report
+    }
+
+    /// Audit entries of one file, or of all files, oldest first
+    pub fn get_audit_trail(&self, file_id: Option<Uuid>) -> Vec<AuditEntry> {
+        self.audit_trail
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|entry| file_id.is_none() || entry.file_id == file_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Audit entries matching `filter`, newest first, paginated
+    /// (`limit` defaults to 50, at most 500)
+    pub fn query_audit_trail(&self, filter: &AuditFilter) -> AuditPage {
+        let limit = filter.limit.unwrap_or(50).clamp(1, 500);
+        let offset = filter.offset.unwrap_or(0);
+
+        let trail = self.audit_trail.read().unwrap();
+        let matching: Vec<&AuditEntry> = trail.iter().rev().filter(|entry| filter.matches(entry)).collect();
+        let total = matching.len() as u32;
+        let entries = matching.into_iter().skip(offset as usize).take(limit as usize).cloned().collect();
+
+        AuditPage {
+            entries,
+            pagination: Pagination { limit, offset, total, has_more: offset.saturating_add(limit) < total },
+        }
+    }
+}
+
+/// Gear table for the rolling hash: 256 pseudo-random words (splitmix64)
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Chunk boundaries fall where the top 8 bits of the rolling hash are zero,
+/// giving chunks of about 256 bytes
+const CHUNK_MASK: u64 = 0xFF00_0000_0000_0000;
+const MIN_CHUNK: usize = 64;
+const MAX_CHUNK: usize = 4096;
+
+/// Content-defined chunks of a file: chunk hash → bytes covered. Boundaries
+/// depend only on the bytes around them, so an edit shifts no chunks except
+/// those it touches.
+struct ChunkFingerprint {
+    chunks: HashMap<[u8; 8], usize>,
+    size: usize,
+}
+
+impl ChunkFingerprint {
+    fn of(bytes: &[u8]) -> Self {
+        let mut chunks: HashMap<[u8; 8], usize> = HashMap::new();
+        let mut start = 0;
+        let mut hash: u64 = 0;
+        for (i, &byte) in bytes.iter().enumerate() {
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            let len = i + 1 - start;
+            if (len >= MIN_CHUNK && hash & CHUNK_MASK == 0) || len >= MAX_CHUNK || i + 1 == bytes.len() {
+                let digest = Sha256::digest(&bytes[start..=i]);
+                *chunks.entry(digest[..8].try_into().expect("8 bytes")).or_default() += len;
+                start = i + 1;
+                hash = 0;
+            }
+        }
+        Self { chunks, size: bytes.len() }
+    }
+
+    /// Share of the larger file's bytes in chunks both files have (0–1)
+    fn similarity(&self, other: &ChunkFingerprint) -> f64 {
+        let larger = self.size.max(other.size);
+        if larger == 0 {
+            return 1.0;
+        }
+        let shared: usize = self
+            .chunks
+            .iter()
+            .filter_map(|(hash, bytes)| other.chunks.get(hash).map(|other_bytes| (*bytes).min(*other_bytes)))
+            .sum();
+        shared as f64 / larger as f64
+    }
+}
+
+impl Drop for SourceManager {
+    fn drop(&mut self) {
+        if self.unsaved.load(Ordering::SeqCst) > 0 {
+            if let Err(e) = self.save_metadata() {
+                warn!("Failed to save source file metadata to {}: {}", self.base_dir.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin_interface::AdminInterface;
+    use std::sync::Arc;
+
+    fn pdf<'a>(dno: &'a str, file_name: &'a str) -> NewSourceFile<'a> {
+        NewSourceFile {
+            dno,
+            year: 2024,
+            data_type: "netzentgelte",
+            file_name,
+            source_url: Some("https://netze.example/preisblatt.pdf"),
+            content_type: Some("application/pdf"),
+            bytes: b"%PDF-1.7 Preisblatt",
+        }
+    }
+
+    /// Two crawler downloads, an extraction and an admin rejection
+    fn seeded(dir: &Path) -> (Arc<SourceManager>, FileMetadata) {
+        let sources = Arc::new(SourceManager::new(dir));
+        let netze_bw = sources.store_file(pdf("Netze BW", "preisblatt-2024.pdf"), "crawler").unwrap();
+        let bayernwerk = sources.store_file(pdf("Bayernwerk Netz", "entgelte-2024.pdf"), "crawler").unwrap();
+        sources.update_extraction_results(netze_bw.id, serde_json::json!({ "tables": [] }), "crawler").unwrap();
+        AdminInterface::new(sources.clone())
+            .review_file(bayernwerk.id, AdminDataVerificationStatus::Rejected, "admin@example.com", Some("Wrong year"))
+            .unwrap();
+        (sources, netze_bw)
+    }
+
+    #[test]
+    fn test_review_follows_the_verification_workflow() {
+        use AdminDataVerificationStatus::*;
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let admin = AdminInterface::new(sources.clone());
+        let file = sources.store_file(pdf("Netze BW", "preisblatt-2024.pdf"), "crawler").unwrap();
+
+        for status in [InReview, NeedsSource, InReview, Verified] {
+            admin.review_file(file.id, status, "admin@example.com", None).unwrap();
+        }
+        let verified = admin
+            .review_file(file.id, Superseded, "admin@example.com", Some("Corrected Preisblatt published"))
+            .unwrap();
+        assert_eq!(verified.verification_status, Superseded);
+        let transitions: Vec<&str> = verified.provenance.iter().skip(1).map(|record| record.action.as_str()).collect();
+        assert_eq!(
+            transitions,
+            [
+                "reviewed: pending -> in_review",
+                "reviewed: in_review -> needs_source",
+                "reviewed: needs_source -> in_review",
+                "reviewed: in_review -> verified",
+                "reviewed: verified -> superseded",
+            ]
+        );
+        assert_eq!(verified.provenance.last().unwrap().details.as_deref(), Some("Corrected Preisblatt published"));
+    }
+
+    #[test]
+    fn test_illegal_review_transition_is_rejected() {
+        use AdminDataVerificationStatus::*;
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let admin = AdminInterface::new(sources.clone());
+        let file = sources.store_file(pdf("Netze BW", "preisblatt-2024.pdf"), "crawler").unwrap();
+        admin.review_file(file.id, InReview, "admin@example.com", None).unwrap();
+        let verified = admin.review_file(file.id, Verified, "admin@example.com", None).unwrap();
+        let audit_entries = sources.get_audit_trail(None).len();
+
+        let error = admin.review_file(file.id, Pending, "admin@example.com", None).unwrap_err();
+        assert!(matches!(error, AppError::Conflict { .. }));
+        assert!(error.to_string().contains("from verified to pending (allowed: superseded)"));
+        // Neither the file nor the audit trail changed
+        assert_eq!(sources.get_file(file.id).unwrap(), verified);
+        assert_eq!(sources.get_audit_trail(None).len(), audit_entries);
+
+        let pending = sources.store_file(pdf("Bayernwerk Netz", "entgelte-2024.pdf"), "crawler").unwrap();
+        assert!(admin.review_file(pending.id, Verified, "admin@example.com", None).is_err());
+    }
+
+    #[test]
+    fn test_filter_audit_trail_by_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sources, _) = seeded(dir.path());
+
+        let stored = sources.query_audit_trail(&AuditFilter {
+            operation: Some(AuditOperation::FileStored),
+            ..Default::default()
+        });
+        assert_eq!(stored.pagination.total, 2);
+        assert!(stored.entries.iter().all(|entry| entry.operation == AuditOperation::FileStored));
+        // Newest first
+        assert!(stored.entries[0].target.contains("bayernwerk-netz"));
+
+        let page = sources.query_audit_trail(&AuditFilter {
+            operation: Some(AuditOperation::FileStored),
+            limit: Some(1),
+            offset: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(page.entries.len(), 1);
+        assert!(page.entries[0].target.contains("netze-bw"));
+        assert!(!page.pagination.has_more);
+    }
+
+    #[test]
+    fn test_filter_audit_trail_by_actor_and_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sources, netze_bw) = seeded(dir.path());
+
+        let by_admin = sources.query_audit_trail(&AuditFilter {
+            actor: Some("Admin@Example.com".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(by_admin.pagination.total, 1);
+        assert_eq!(by_admin.entries[0].operation, AuditOperation::FileReviewed);
+
+        let crawler_on_netze_bw = sources.query_audit_trail(&AuditFilter {
+            actor: Some("crawler".to_string()),
+            target_contains: Some("NETZE-BW".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(crawler_on_netze_bw.pagination.total, 2);
+        assert!(crawler_on_netze_bw.entries.iter().all(|entry| entry.file_id == Some(netze_bw.id)));
+
+        let future = sources.query_audit_trail(&AuditFilter { from: Some(Utc::now() + chrono::Duration::hours(1)), ..Default::default() });
+        assert_eq!(future.pagination.total, 0);
+    }
+
+    #[test]
+    fn test_deduplication_keeps_earliest_copy_and_reports_space_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = SourceManager::new(dir.path());
+        let preisblatt = b"%PDF-1.7 Preisblatt Netzentgelte 2024";
+        let store = |dno: &'static str, file_name: &'static str, bytes: &'static [u8]| {
+            sources
+                .store_file(NewSourceFile { bytes, ..pdf(dno, file_name) }, "crawler")
+                .unwrap()
+        };
+
+        let original = store("Netze BW", "preisblatt-2024.pdf", preisblatt);
+        let mirror = store("Netze BW", "preisblatt-2024-kopie.pdf", preisblatt);
+        let shared = store("Netze BW Regional", "preisblatt-2024.pdf", preisblatt);
+        let hlzf = store("Netze BW", "hlzf-2024.pdf", b"%PDF-1.7 HLZF 2024");
+        let hlzf_copy = store("Netze BW", "hlzf-2024-v2.pdf", b"%PDF-1.7 HLZF 2024");
+        let unique = store("Netze BW", "preisblatt-2023.pdf", b"%PDF-1.7 Preisblatt 2023");
+
+        let result = sources.perform_deduplication("admin@example.com").unwrap();
+
+        assert_eq!(result.files_scanned, 6);
+        assert_eq!(result.groups.len(), 2);
+        let group = result.groups.iter().find(|group| group.kept == original.id).unwrap();
+        let mut duplicates = group.duplicates.clone();
+        duplicates.sort();
+        let mut expected = vec![mirror.id, shared.id];
+        expected.sort();
+        assert_eq!(duplicates, expected);
+        assert_eq!(group.bytes_saved, 2 * preisblatt.len() as u64);
+        assert!(result.groups.iter().any(|group| group.kept == hlzf.id && group.duplicates == [hlzf_copy.id]));
+        assert_eq!(result.duplicates_removed, 3);
+        assert_eq!(result.bytes_saved, 2 * preisblatt.len() as u64 + hlzf.size_bytes);
+
+        assert!(dir.path().join(&original.file_path).exists());
+        assert!(!dir.path().join(&mirror.file_path).exists());
+        assert!(dir.path().join(&unique.file_path).exists());
+        let mirror = sources.get_file(mirror.id).unwrap();
+        assert_eq!((mirror.is_active, mirror.duplicate_of), (false, Some(original.id)));
+        let audited = sources.query_audit_trail(&AuditFilter {
+            operation: Some(AuditOperation::Deduplicated),
+            ..Default::default()
+        });
+        assert_eq!(audited.pagination.total, 3);
+
+        // Nothing left to remove
+        let again = sources.perform_deduplication("admin@example.com").unwrap();
+        assert!(again.groups.is_empty());
+        assert_eq!(again.files_scanned, 3);
+    }
+
+    #[test]
+    fn test_purge_keeps_bytes_of_an_active_original_sharing_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let admin = AdminInterface::new(sources.clone());
+        let preisblatt = b"%PDF-1.7 Preisblatt Netzentgelte 2024";
+        let store = |file_name: &'static str, bytes: &'static [u8]| {
+            sources.store_file(NewSourceFile { bytes, ..pdf("Netze BW", file_name) }, "crawler").unwrap()
+        };
+
+        let original = store("preisblatt-2024.pdf", preisblatt);
+        // The same download again overwrites the original's path
+        let redownload = store("preisblatt-2024.pdf", preisblatt);
+        let mirror = store("preisblatt-2024-kopie.pdf", preisblatt);
+        let rejected = store("hlzf-2023.pdf", b"%PDF-1.7 HLZF 2023");
+        sources.perform_deduplication("admin@example.com").unwrap();
+        admin
+            .review_file(rejected.id, AdminDataVerificationStatus::Rejected, "admin@example.com", Some("Wrong year"))
+            .unwrap();
+
+        // Nothing is old enough yet
+        let report = sources.purge_inactive(std::time::Duration::from_secs(3600), "admin@example.com").unwrap();
+        assert_eq!(report, PurgeReport::default());
+
+        let report = sources.purge_inactive(std::time::Duration::ZERO, "admin@example.com").unwrap();
+
+        assert_eq!(report.files_scanned, 3);
+        assert_eq!(report.shared_paths_kept, 1);
+        assert_eq!((report.files_deleted, report.bytes_reclaimed), (1, rejected.size_bytes));
+        assert!(dir.path().join(&original.file_path).exists());
+        assert!(!dir.path().join(&rejected.file_path).exists());
+        assert!(sources.get_file(original.id).unwrap().purged_at.is_none());
+        for id in [redownload.id, mirror.id, rejected.id] {
+            assert!(report.purged.contains(&id));
+            assert!(sources.get_file(id).unwrap().purged_at.is_some());
+        }
+        let deleted = sources.query_audit_trail(&AuditFilter {
+            operation: Some(AuditOperation::FileDeleted),
+            ..Default::default()
+        });
+        assert_eq!(deleted.pagination.total, 3);
+
+        // Already purged files are not reported again
+        let again = sources.purge_inactive(std::time::Duration::ZERO, "admin@example.com").unwrap();
+        assert!(again.purged.is_empty());
+    }
+
+    #[test]
+    fn test_two_incremental_passes_cover_every_file_and_flag_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = SourceManager::new(dir.path());
+        let files: Vec<FileMetadata> = ["preisblatt-2024.pdf", "hlzf-2024.pdf", "preisblatt-2023.pdf"]
+            .into_iter()
+            .map(|name| sources.store_file(pdf("Netze BW", name), "crawler").unwrap())
+            .collect();
+        fs::write(dir.path().join(&files[2].file_path), b"%PDF-1.7 truncated").unwrap();
+
+        let first = sources.verify_all_incremental(2);
+        assert_eq!((first.verified, first.corrupted.len()), (2, 0));
+        assert!(sources.get_file(files[2].id).unwrap().last_verified_at.is_none());
+
+        // The file never checked goes first, then the one checked longest ago
+        let second = sources.verify_all_incremental(2);
+        assert_eq!(second.verified, 2);
+        assert_eq!((second.corrupted.clone(), second.newly_corrupted), (vec![files[2].id], 1));
+        assert!(files.iter().all(|file| sources.get_file(file.id).unwrap().last_verified_at.is_some()));
+        let corrupted = sources.get_file(files[2].id).unwrap();
+        assert!(corrupted.integrity_error.unwrap().starts_with("checksum mismatch"));
+        assert!(sources.get_file(files[0].id).unwrap().integrity_error.is_none());
+
+        // Known corruption is reported again but audited once
+        fs::remove_file(dir.path().join(&files[2].file_path)).unwrap();
+        let third = sources.verify_all_incremental(3);
+        assert_eq!((third.corrupted.len(), third.newly_corrupted), (1, 0));
+        let audited = sources.query_audit_trail(&AuditFilter {
+            operation: Some(AuditOperation::IntegrityFailed),
+            ..Default::default()
+        });
+        assert_eq!(audited.pagination.total, 1);
+        assert_eq!(audited.entries[0].file_id, Some(files[2].id));
+        assert_eq!(sources.get_file(files[2].id).unwrap().integrity_error.as_deref(), Some("missing from disk"));
+    }
+
+    /// Deterministic filler standing in for a PDF's compressed streams
+    fn pdf_body(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        let mut bytes = b"%PDF-1.7\n".to_vec();
+        while bytes.len() < len {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            bytes.push((state >> 33) as u8);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_fuzzy_deduplication_groups_pdfs_differing_in_their_trailer() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = SourceManager::new(dir.path());
+        let body = pdf_body(7, 40_000);
+        let with_trailer = |trailer: &str| [body.as_slice(), trailer.as_bytes()].concat();
+        let first = with_trailer("/ModDate (D:20240105120000)\n%%EOF\n");
+        let republished = with_trailer("/ModDate (D:20240312093015)\n%%EOF\n");
+        let other = pdf_body(8, 40_000);
+        let store = |file_name: &'static str, bytes: &[u8]| {
+            sources.store_file(NewSourceFile { bytes, ..pdf("Netze BW", file_name) }, "crawler").unwrap()
+        };
+        let original = store("preisblatt-2024.pdf", &first);
+        let update = store("preisblatt-2024-maerz.pdf", &republished);
+        store("hlzf-2024.pdf", &other);
+        assert_ne!(original.sha256, update.sha256);
+
+        assert!(sources.perform_deduplication("admin@example.com").unwrap().groups.is_empty());
+        let result = sources.perform_fuzzy_deduplication(0.95).unwrap();
+
+        assert_eq!(result.files_scanned, 3);
+        assert_eq!(result.groups.len(), 1);
+        let group = &result.groups[0];
+        assert_eq!((group.kept, group.duplicates.as_slice()), (original.id, &[update.id][..]));
+        assert_eq!(group.recommended_action, RecommendedAction::ManualReview);
+        assert!(group.similarity.unwrap() > 0.95);
+        // Only reported, nothing removed
+        assert_eq!(result.duplicates_removed, 0);
+        assert!(dir.path().join(&update.file_path).exists());
+        assert!(sources.get_file(update.id).unwrap().is_active);
+
+        assert!(sources.perform_fuzzy_deduplication(1.5).is_err());
+    }
+
+    #[test]
+    fn test_metadata_survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sources, netze_bw) = seeded(dir.path());
+        let files = sources.list_files();
+        let trail = sources.get_audit_trail(None);
+        drop(sources);
+        assert!(dir.path().join(METADATA_FILE).exists());
+
+        // A download written by a crashed process before it could save
+        let orphan = dir.path().join(DATA_DIR).join("westnetz").join("2023").join("entgelte.pdf");
+        fs::create_dir_all(orphan.parent().unwrap()).unwrap();
+        fs::write(&orphan, b"%PDF-1.7 Westnetz").unwrap();
+
+        let sources = SourceManager::open(dir.path()).unwrap();
+
+        let restored = sources.list_files();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(&restored[..2], &files[..]);
+        let netze_bw = sources.get_file(netze_bw.id).unwrap();
+        assert_eq!(netze_bw.source_url.as_deref(), Some("https://netze.example/preisblatt.pdf"));
+        assert_eq!(netze_bw.extraction, Some(serde_json::json!({ "tables": [] })));
+        assert_eq!(netze_bw.provenance.len(), 2);
+        assert_eq!(sources.get_audit_trail(None), trail);
+
+        let westnetz = &restored[2];
+        assert_eq!((westnetz.dno_key.as_str(), westnetz.year, westnetz.source_url.as_ref()), ("westnetz", 2023, None));
+        assert_eq!(westnetz.provenance[0].action, "reconstructed");
+    }
+}
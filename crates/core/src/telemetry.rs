@@ -0,0 +1,97 @@
+use crate::AppError;
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Span;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Where spans are exported to
+#[derive(Debug, Clone)]
+pub struct OtelConfig {
+    /// OTLP/HTTP collector base URL, e.g. `http://otel-collector:4318`
+    pub endpoint: String,
+    pub service_name: String,
+}
+
+impl OtelConfig {
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT`; export is off when it is unset.
+    /// `OTEL_SERVICE_NAME` overrides `default_service`.
+    pub fn from_env(default_service: &str) -> Option<Self> {
+        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|e| !e.trim().is_empty())?;
+        Some(Self {
+            endpoint,
+            service_name: std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| default_service.to_string()),
+        })
+    }
+}
+
+/// Tracer provider exporting spans to `<endpoint>/v1/traces` in batches.
+/// Call `shutdown` before exiting so buffered spans are sent.
+pub fn tracer_provider(config: &OtelConfig) -> Result<TracerProvider, AppError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", config.endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| AppError::Config(format!("OTLP exporter: {}", e)))?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", config.service_name.clone())]))
+        .build())
+}
+
+/// `tracing` layer turning spans into OpenTelemetry spans of `provider`
+pub fn layer<S>(provider: &TracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("dno-crawler"))
+}
+
+/// Continue the trace named by an incoming `traceparent` header, if any
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    if context.span().span_context().is_valid() {
+        span.set_parent(context);
+    }
+}
+
+/// `traceparent` (and `tracestate`) headers continuing `span`'s trace in an outgoing request
+pub fn trace_headers(span: &Span) -> Vec<(String, String)> {
+    let mut headers = HeaderInjector(Vec::new());
+    TraceContextPropagator::new().inject_context(&span.context(), &mut headers);
+    headers.0
+}
+
+/// Hex trace id of `span`, or `None` when it is not being exported
+pub fn trace_id(span: &Span) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    span_context.is_valid().then(|| span_context.trace_id().to_string())
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+struct HeaderInjector(Vec<(String, String)>);
+
+impl Injector for HeaderInjector {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.push((key.to_string(), value));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Prefix of per-process session directories under `temp_path`
+const SESSION_PREFIX: &str = "session-";
+
+/// How old leftover temp files must be before the startup sweep removes them
+#[derive(Debug, Clone, Copy)]
+pub struct TempCleanupConfig {
+    pub stale_after: Duration,
+}
+
+impl Default for TempCleanupConfig {
+    fn default() -> Self {
+        Self { stale_after: Duration::from_secs(24 * 3600) }
+    }
+}
+
+impl TempCleanupConfig {
+    /// `TEMP_STALE_AFTER_SECS`, falling back to 24 hours
+    pub fn from_env() -> Self {
+        std::env::var("TEMP_STALE_AFTER_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|secs| Self { stale_after: Duration::from_secs(secs) })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SweepReport {
+    pub files_removed: usize,
+    pub dirs_removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Remove everything under `root` last modified more than `stale_after` ago:
+/// `.part` downloads, extraction artifacts and session directories left
+/// behind by a crash. Entries that can't be removed are logged and skipped.
+pub fn sweep_stale(root: &Path, stale_after: Duration) -> io::Result<SweepReport> {
+    let mut report = SweepReport::default();
+    if !root.exists() {
+        return Ok(report);
+    }
+    let cutoff = SystemTime::now().checked_sub(stale_after).unwrap_or(SystemTime::UNIX_EPOCH);
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if metadata.modified().map(|modified| modified > cutoff).unwrap_or(true) {
+            continue;
+        }
+
+        let removed = if metadata.is_dir() {
+            let size = dir_size(&path);
+            fs::remove_dir_all(&path).map(|_| {
+                report.dirs_removed += 1;
+                size
+            })
+        } else {
+            fs::remove_file(&path).map(|_| {
+                report.files_removed += 1;
+                metadata.len()
+            })
+        };
+        match removed {
+            Ok(bytes) => {
+                report.bytes_freed += bytes;
+                debug!("Removed stale temp entry {}", path.display());
+            }
+            Err(e) => warn!("Failed to remove stale temp entry {}: {}", path.display(), e),
+        }
+    }
+
+    Ok(report)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| match entry.metadata() {
+                    Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+                    Ok(metadata) => metadata.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Temp directory owned by this process. Created after sweeping stale
+/// leftovers and removed again on `cleanup` or when dropped (including
+/// during a panic unwind).
+#[derive(Debug)]
+pub struct TempSession {
+    dir: PathBuf,
+}
+
+impl TempSession {
+    /// Sweep stale entries from `root`, then create this session's directory in it
+    pub fn start(root: impl AsRef<Path>, config: TempCleanupConfig) -> io::Result<Self> {
+        let root = root.as_ref();
+        fs::create_dir_all(root)?;
+
+        let report = sweep_stale(root, config.stale_after)?;
+        if report != SweepReport::default() {
+            info!(
+                "Removed {} stale temp files and {} directories ({} bytes) from {}",
+                report.files_removed,
+                report.dirs_removed,
+                report.bytes_freed,
+                root.display()
+            );
+        }
+
+        let dir = root.join(format!("{}{}", SESSION_PREFIX, Uuid::new_v4()));
+        fs::create_dir(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path for a partial download; rename it into place once complete
+    pub fn part_file(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.part", name))
+    }
+
+    /// Remove the session directory
+    pub fn cleanup(self) {
+        // Drop does the work
+    }
+}
+
+impl Drop for TempSession {
+    fn drop(&mut self) {
+        match fs::remove_dir_all(&self.dir) {
+            Ok(()) => debug!("Removed temp session {}", self.dir.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove temp session {}: {}", self.dir.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_startup_removes_stale_part_file_and_keeps_fresh_one() {
+        let root = tempfile::tempdir().unwrap();
+        let stale = root.path().join("netze-bw-2024.pdf.part");
+        let fresh = root.path().join("westnetz-2024.pdf.part");
+
+        File::create(&stale)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(3 * 24 * 3600))
+            .unwrap();
+        File::create(&fresh).unwrap();
+
+        let session = TempSession::start(root.path(), TempCleanupConfig::default()).unwrap();
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(session.path().is_dir());
+
+        let dir = session.path().to_path_buf();
+        session.cleanup();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,15 @@
+use sqlx::PgPool;
+
+/// The database schema, as deployed
+const INIT_SQL: &str = include_str!("../../../init.sql");
+
+/// Start of the sample data at the end of `init.sql`
+const SAMPLE_DATA: &str = "-- Insert example storage from the JSON";
+
+/// Create the types, tables, indexes and triggers of `init.sql` without its
+/// sample data, so database tests run against the real schema and seed only
+/// the rows they need
+pub async fn create(pool: &PgPool) {
+    let schema = INIT_SQL.split(SAMPLE_DATA).next().expect("init.sql has a schema");
+    sqlx::raw_sql(schema).execute(pool).await.expect("init.sql schema applies");
+}
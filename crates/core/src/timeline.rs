@@ -0,0 +1,98 @@
+use crate::models::DataEntryHistory;
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// What happened to a data entry at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    FirstSeen,
+    /// A review or correction recorded in `data_entry_history`
+    Revision,
+    LastVerified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub at: DateTime<Utc>,
+    pub kind: TimelineEventKind,
+    /// History version, for revisions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    /// Review outcome (`verified`, `rejected`, `corrected`), for revisions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_before: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_after: Option<Value>,
+}
+
+impl TimelineEvent {
+    fn at(at: DateTime<Utc>, kind: TimelineEventKind) -> Self {
+        Self { at, kind, version: None, changes: None, changed_by: None, data_before: None, data_after: None }
+    }
+}
+
+/// First-seen and last-verified times of a data entry with its version history,
+/// as one chronological list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataTimeline {
+    pub entry_id: Uuid,
+    /// `netzentgelte` or `hlzf`
+    pub entry_type: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_verified_at: DateTime<Utc>,
+    pub events: Vec<TimelineEvent>,
+}
+
+/// Timeline of a netzentgelte or hlzf entry; `None` if it does not exist
+pub async fn get_data_timeline(pool: &PgPool, entry_id: Uuid) -> Result<Option<DataTimeline>, AppError> {
+    let entry = sqlx::query_as::<_, (String, DateTime<Utc>, DateTime<Utc>)>(
+        r#"
+        SELECT 'netzentgelte', first_seen_at, last_verified_at FROM netzentgelte_data WHERE id = $1 AND deleted_at IS NULL
+        UNION ALL
+        SELECT 'hlzf', first_seen_at, last_verified_at FROM hlzf_data WHERE id = $1 AND deleted_at IS NULL
+        LIMIT 1
+        "#,
+    )
+    .bind(entry_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)?;
+    let Some((entry_type, first_seen_at, last_verified_at)) = entry else {
+        return Ok(None);
+    };
+
+    let history = sqlx::query_as::<_, DataEntryHistory>(
+        "SELECT * FROM data_entry_history WHERE entry_type = $1 AND entry_id = $2 ORDER BY version",
+    )
+    .bind(&entry_type)
+    .bind(entry_id)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    let mut events = vec![TimelineEvent::at(first_seen_at, TimelineEventKind::FirstSeen)];
+    events.extend(history.into_iter().map(|revision| TimelineEvent {
+        version: Some(revision.version),
+        changes: Some(revision.changes),
+        changed_by: revision.changed_by,
+        data_before: revision.data_before,
+        data_after: revision.data_after,
+        ..TimelineEvent::at(revision.changed_at, TimelineEventKind::Revision)
+    }));
+    if last_verified_at > first_seen_at {
+        events.push(TimelineEvent::at(last_verified_at, TimelineEventKind::LastVerified));
+    }
+    // Stable, so events at the same instant keep first-seen, revision, last-verified order
+    events.sort_by_key(|event| event.at);
+
+    Ok(Some(DataTimeline { entry_id, entry_type, first_seen_at, last_verified_at, events }))
+}
@@ -0,0 +1,280 @@
+use crate::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeSet;
+use tracing::info;
+use uuid::Uuid;
+
+pub const STATUS_VERIFIED: &str = "verified";
+pub const STATUS_UNVERIFIED: &str = "unverified";
+pub const STATUS_FLAGGED: &str = "flagged";
+
+/// Thresholds that decide the automatic verification status of extracted data.
+/// Entries an admin has decided on (`verified_by` set) are never touched.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VerificationPolicy {
+    /// Source confidence at or above which an entry counts as verified
+    pub auto_verify_confidence: f64,
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        Self { auto_verify_confidence: 0.95 }
+    }
+}
+
+impl VerificationPolicy {
+    /// `AUTO_VERIFY_CONFIDENCE` (0.0-1.0), falling back to the default
+    pub fn from_env() -> Self {
+        let auto_verify_confidence = std::env::var("AUTO_VERIFY_CONFIDENCE")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|c| (0.0..=1.0).contains(c))
+            .unwrap_or(Self::default().auto_verify_confidence);
+        Self { auto_verify_confidence }
+    }
+
+    /// Status an entry should have under this policy. Values that fail the
+    /// schema checks are flagged regardless of confidence.
+    pub fn evaluate(&self, confidence: Option<f64>, schema_valid: bool) -> &'static str {
+        if !schema_valid {
+            STATUS_FLAGGED
+        } else if confidence.is_some_and(|c| c >= self.auto_verify_confidence) {
+            STATUS_VERIFIED
+        } else {
+            STATUS_UNVERIFIED
+        }
+    }
+}
+
+/// Which entries to re-evaluate; all automatic entries when empty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecomputeFilter {
+    pub dno_id: Option<Uuid>,
+    pub year: Option<i32>,
+    /// `netzentgelte` or `hlzf`; both when unset
+    pub data_type: Option<String>,
+    /// Continue an interrupted run from `RecomputeReport::cursor`
+    pub resume: Option<RecomputeCursor>,
+}
+
+/// Position of a recompute run: the table and the last entry id committed in it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecomputeCursor {
+    pub data_type: String,
+    pub last_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RecomputeReport {
+    pub examined: u64,
+    pub changed: u64,
+    pub promoted: u64,
+    pub demoted: u64,
+    /// DNOs whose entries changed, for cache invalidation
+    pub affected_dnos: BTreeSet<Uuid>,
+    /// Last committed position; pass as `RecomputeFilter::resume` to continue an interrupted run
+    pub cursor: Option<RecomputeCursor>,
+}
+
+#[derive(sqlx::FromRow)]
+struct Candidate {
+    id: Uuid,
+    dno_id: Uuid,
+    verification_status: Option<String>,
+    confidence: Option<f64>,
+    schema_valid: bool,
+}
+
+/// Tables covered by the recompute, with the schema check applied to their rows
+const TABLES: [(&str, &str, &str); 2] = [
+    (
+        "netzentgelte",
+        "netzentgelte_data",
+        "COALESCE(t.leistung, 0) >= 0 AND COALESCE(t.arbeit, 0) >= 0 \
+         AND COALESCE(t.leistung_unter_2500h, 0) >= 0 AND COALESCE(t.arbeit_unter_2500h, 0) >= 0 \
+         AND t.voltage_level IN ('hs', 'hs/ms', 'ms', 'ms/ns', 'ns')",
+    ),
+    ("hlzf", "hlzf_data", "t.start_time IS NULL OR t.end_time IS NULL OR t.start_time <> t.end_time"),
+];
+
+const BATCH_SIZE: i64 = 500;
+
+/// Re-evaluate the verification status of automatically verified entries
+/// against `policy`. Works in id-ordered batches, each committed in its own
+/// transaction, so an interrupted run loses at most one batch and can be
+/// resumed from `cursor`.
+pub async fn recompute_verification(
+    pool: &PgPool,
+    policy: &VerificationPolicy,
+    filter: &RecomputeFilter,
+) -> Result<RecomputeReport, AppError> {
+    let mut report = RecomputeReport::default();
+    // Tables before the resume cursor's table were already finished
+    let mut resuming = filter.resume.clone();
+
+    for (data_type, table, schema_check) in TABLES {
+        if filter.data_type.as_deref().is_some_and(|t| t != data_type && t != "all") {
+            continue;
+        }
+        let mut cursor = match &resuming {
+            Some(resume) if resume.data_type == data_type => {
+                let last_id = resume.last_id;
+                resuming = None;
+                Some(last_id)
+            }
+            Some(_) => continue,
+            None => None,
+        };
+        loop {
+            let mut tx = pool.begin().await.map_err(AppError::Database)?;
+
+            let mut query = sqlx::QueryBuilder::new(format!(
+                r#"
+                SELECT t.id, t.dno_id, t.verification_status,
+                    ds.confidence::float8 AS confidence,
+                    COALESCE({schema_check}, true) AS schema_valid
+                FROM {table} t
+                LEFT JOIN data_sources ds
+                    ON ds.dno_id = t.dno_id AND ds.year = t.year AND ds.data_type = '{data_type}'::data_type
+                WHERE t.verified_by IS NULL AND t.deleted_at IS NULL
+                "#
+            ));
+            if let Some(cursor) = cursor {
+                query.push(" AND t.id > ").push_bind(cursor);
+            }
+            if let Some(dno_id) = filter.dno_id {
+                query.push(" AND t.dno_id = ").push_bind(dno_id);
+            }
+            if let Some(year) = filter.year {
+                query.push(" AND t.year = ").push_bind(year);
+            }
+            query.push(" ORDER BY t.id LIMIT ").push_bind(BATCH_SIZE);
+            // Lock the batch so a concurrent admin decision isn't overwritten
+            query.push(" FOR UPDATE OF t");
+
+            let batch: Vec<Candidate> = query
+                .build_query_as()
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(AppError::Database)?;
+            let Some(last) = batch.last() else { break };
+            cursor = Some(last.id);
+
+            let mut changes: Vec<(&'static str, Vec<Uuid>)> = Vec::new();
+            for candidate in &batch {
+                report.examined += 1;
+                let status = policy.evaluate(candidate.confidence, candidate.schema_valid);
+                let current = candidate.verification_status.as_deref().unwrap_or(STATUS_UNVERIFIED);
+                if current == status {
+                    continue;
+                }
+
+                report.changed += 1;
+                if status == STATUS_VERIFIED {
+                    report.promoted += 1;
+                } else if current == STATUS_VERIFIED {
+                    report.demoted += 1;
+                }
+                report.affected_dnos.insert(candidate.dno_id);
+                match changes.iter_mut().find(|(s, _)| *s == status) {
+                    Some((_, ids)) => ids.push(candidate.id),
+                    None => changes.push((status, vec![candidate.id])),
+                }
+            }
+
+            for (status, ids) in changes {
+                sqlx::query(&format!(
+                    "UPDATE {table} SET verification_status = $1, updated_at = CURRENT_TIMESTAMP WHERE id = ANY($2)"
+                ))
+                .bind(status)
+                .bind(&ids)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::Database)?;
+            }
+
+            tx.commit().await.map_err(AppError::Database)?;
+            report.cursor = Some(RecomputeCursor { data_type: data_type.to_string(), last_id: last.id });
+
+            if (batch.len() as i64) < BATCH_SIZE {
+                break;
+            }
+        }
+    }
+
+    info!(
+        "Verification recompute: {} examined, {} changed ({} promoted, {} demoted)",
+        report.examined, report.changed, report.promoted, report.demoted
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn seed(pool: &PgPool) {
+        crate::test_schema::create(pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (id, slug, name) VALUES ('00000000-0000-0000-0000-000000000001', 'netze-bw', 'Netze BW');
+            INSERT INTO users (id, email, password_hash, name, role) VALUES
+                ('00000000-0000-0000-0000-0000000000aa', 'admin@example.com', 'x', 'Admin', 'admin');
+
+            -- 2023 sources are 0.85 confident, 2024 only 0.60
+            INSERT INTO data_sources (dno_id, year, data_type, source_type, extracted_at, confidence) VALUES
+                ('00000000-0000-0000-0000-000000000001', 2023, 'netzentgelte', 'file', CURRENT_TIMESTAMP, 0.85),
+                ('00000000-0000-0000-0000-000000000001', 2024, 'netzentgelte', 'file', CURRENT_TIMESTAMP, 0.60);
+            INSERT INTO netzentgelte_data (dno_id, year, voltage_level, leistung, arbeit, verified_by) VALUES
+                ('00000000-0000-0000-0000-000000000001', 2023, 'ms', 58.21, 1.26, NULL),
+                ('00000000-0000-0000-0000-000000000001', 2023, 'ns', 21.04, 5.67, NULL),
+                ('00000000-0000-0000-0000-000000000001', 2024, 'ms', 60.00, 1.30, NULL),
+                -- Rejected by an admin: must stay as it is
+                ('00000000-0000-0000-0000-000000000001', 2023, 'hs', 10.00, 0.50, '00000000-0000-0000-0000-0000000000aa');
+            UPDATE netzentgelte_data SET verification_status = 'rejected' WHERE verified_by IS NOT NULL;
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_lowering_threshold_promotes_pending_records(pool: PgPool) {
+        seed(&pool).await;
+
+        let strict = VerificationPolicy { auto_verify_confidence: 0.9 };
+        let report = recompute_verification(&pool, &strict, &RecomputeFilter::default()).await.unwrap();
+        assert_eq!((report.examined, report.changed), (3, 0));
+
+        let relaxed = VerificationPolicy { auto_verify_confidence: 0.8 };
+        let report = recompute_verification(&pool, &relaxed, &RecomputeFilter::default()).await.unwrap();
+        assert_eq!(report.changed, 2);
+        assert_eq!(report.promoted, 2);
+        assert_eq!(report.affected_dnos.len(), 1);
+
+        let verified: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM netzentgelte_data WHERE verification_status = 'verified'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(verified, 2);
+        let rejected: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM netzentgelte_data WHERE verification_status = 'rejected'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rejected, 1);
+
+        // Re-running with the same policy is a no-op
+        let report = recompute_verification(&pool, &relaxed, &RecomputeFilter::default()).await.unwrap();
+        assert_eq!(report.changed, 0);
+    }
+
+    #[test]
+    fn test_schema_failures_are_flagged() {
+        let policy = VerificationPolicy::default();
+        assert_eq!(policy.evaluate(Some(0.99), false), STATUS_FLAGGED);
+        assert_eq!(policy.evaluate(Some(0.99), true), STATUS_VERIFIED);
+        assert_eq!(policy.evaluate(None, true), STATUS_UNVERIFIED);
+    }
+}
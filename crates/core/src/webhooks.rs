@@ -0,0 +1,374 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">` when a secret is set
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+/// Unix seconds the delivery attempt was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Id";
+
+/// Something operators are told about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data")]
+pub enum WebhookEvent {
+    #[serde(rename = "crawl.completed")]
+    CrawlCompleted { session_id: Uuid },
+    #[serde(rename = "crawl.failed")]
+    CrawlFailed { session_id: Uuid },
+    /// An integrity check found a stored source file missing or changed
+    #[serde(rename = "file.corrupted")]
+    FileCorrupted { file_id: Uuid, path: String, problem: String },
+    /// Entries were flagged for admin review
+    #[serde(rename = "entries.flagged")]
+    EntriesFlagged { entry_type: String, dno_id: Uuid, entry_ids: Vec<Uuid>, reason: String },
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CrawlCompleted { .. } => "crawl.completed",
+            Self::CrawlFailed { .. } => "crawl.failed",
+            Self::FileCorrupted { .. } => "file.corrupted",
+            Self::EntriesFlagged { .. } => "entries.flagged",
+        }
+    }
+}
+
+/// Body of every webhook POST: `{"id", "occurred_at", "event", "data"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    /// Signs every body when set
+    pub secret: Option<String>,
+    /// Attempts after the first one for 5xx, 429 and connection errors
+    pub max_retries: u32,
+    /// Wait before the first retry; doubles with each further one
+    pub backoff: Duration,
+    pub timeout: Duration,
+    /// JSON lines file of deliveries that failed for good
+    pub dead_letter: PathBuf,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            secret: None,
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(10),
+            dead_letter: PathBuf::from("./storage/webhooks/dead-letter.jsonl"),
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// `WEBHOOK_URLS` (comma-separated), `WEBHOOK_SECRET`, `WEBHOOK_MAX_RETRIES`,
+    /// `WEBHOOK_RETRY_BACKOFF_MS` and `WEBHOOK_DEAD_LETTER_PATH`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        Self {
+            urls: var("WEBHOOK_URLS")
+                .map(|urls| urls.split(',').map(str::trim).filter(|url| !url.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default(),
+            secret: var("WEBHOOK_SECRET"),
+            max_retries: var("WEBHOOK_MAX_RETRIES").and_then(|v| v.parse().ok()).unwrap_or(defaults.max_retries),
+            backoff: var("WEBHOOK_RETRY_BACKOFF_MS")
+                .and_then(|v| v.parse().ok())
+                .map_or(defaults.backoff, Duration::from_millis),
+            timeout: defaults.timeout,
+            dead_letter: var("WEBHOOK_DEAD_LETTER_PATH").map_or(defaults.dead_letter, PathBuf::from),
+        }
+    }
+}
+
+/// Outcome of delivering one payload to one URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+    pub url: String,
+    pub attempts: u32,
+    /// Why the last attempt failed; `None` once the endpoint accepted the payload
+    pub error: Option<String>,
+}
+
+/// A line of the dead-letter log
+#[derive(Debug, Serialize)]
+struct DeadLetter<'a> {
+    failed_at: DateTime<Utc>,
+    url: &'a str,
+    attempts: u32,
+    error: &'a str,
+    payload: &'a WebhookPayload,
+}
+
+/// Posts `WebhookEvent`s to the configured URLs. Clones share their client.
+#[derive(Clone)]
+pub struct Webhooks {
+    config: Arc<WebhookConfig>,
+    client: reqwest::Client,
+}
+
+static GLOBAL: OnceLock<Webhooks> = OnceLock::new();
+
+/// Webhooks of this process, from `WebhookConfig::from_env`. Events are sent
+/// from wherever they happen, like metrics.
+pub fn global() -> Webhooks {
+    GLOBAL.get_or_init(|| Webhooks::new(WebhookConfig::from_env())).clone()
+}
+
+impl Webhooks {
+    pub fn new(config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder().timeout(config.timeout).build().unwrap_or_default();
+        Self { config: Arc::new(config), client }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.urls.is_empty()
+    }
+
+    /// Send `event` in the background. Without URLs, or outside a tokio
+    /// runtime, nothing is sent.
+    pub fn notify(&self, event: WebhookEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let this = self.clone();
+                runtime.spawn(async move { this.send(event).await });
+            }
+            Err(_) => warn!("Webhook {} not sent: no async runtime", event.name()),
+        }
+    }
+
+    /// Deliver `event` to every URL, one after another, and wait for the outcomes
+    pub async fn send(&self, event: WebhookEvent) -> Vec<Delivery> {
+        let payload = WebhookPayload { id: Uuid::new_v4(), occurred_at: Utc::now(), event };
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Webhook {} not sent: {}", payload.event.name(), e);
+                return Vec::new();
+            }
+        };
+        let mut deliveries = Vec::with_capacity(self.config.urls.len());
+        for url in &self.config.urls {
+            deliveries.push(self.deliver(url, &payload, &body).await);
+        }
+        deliveries
+    }
+
+    async fn deliver(&self, url: &str, payload: &WebhookPayload, body: &[u8]) -> Delivery {
+        let mut delivery = Delivery { url: url.to_string(), attempts: 0, error: None };
+        loop {
+            delivery.attempts += 1;
+            let mut request = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, payload.event.name())
+                .header(DELIVERY_HEADER, payload.id.to_string())
+                .body(body.to_vec());
+            if let Some(secret) = &self.config.secret {
+                let timestamp = Utc::now().timestamp();
+                request = request
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(SIGNATURE_HEADER, signature(secret.as_bytes(), timestamp, body));
+            }
+
+            let retryable = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Webhook {} delivered to {}", payload.event.name(), url);
+                    delivery.error = None;
+                    return delivery;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    delivery.error = Some(format!("HTTP {}", status));
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => {
+                    delivery.error = Some(e.to_string());
+                    true
+                }
+            };
+            if !retryable || delivery.attempts > self.config.max_retries {
+                break;
+            }
+            tokio::time::sleep(self.config.backoff * 2u32.saturating_pow(delivery.attempts - 1)).await;
+        }
+
+        let error = delivery.error.as_deref().unwrap_or_default();
+        warn!("Webhook {} to {} failed after {} attempts: {}", payload.event.name(), url, delivery.attempts, error);
+        self.dead_letter(&DeadLetter { failed_at: Utc::now(), url, attempts: delivery.attempts, error, payload });
+        delivery
+    }
+
+    fn dead_letter(&self, letter: &DeadLetter<'_>) {
+        let path = &self.config.dead_letter;
+        let written = (|| -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut line = serde_json::to_vec(letter)?;
+            line.push(b'\n');
+            OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)
+        })();
+        if let Err(e) = written {
+            warn!("Failed to write webhook dead letter to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `<timestamp>.<body>` under `secret`.
+/// Receivers recompute it from `X-Webhook-Timestamp` and the raw body, and
+/// reject timestamps far from their own clock, so a captured delivery cannot
+/// be replayed later.
+pub fn signature(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A received request: lower-cased header lines and the body
+    type Received = (Vec<String>, Vec<u8>);
+
+    /// An endpoint answering every request with `status`
+    async fn endpoint(status: u16, received: Arc<Mutex<Vec<Received>>>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let head_end = loop {
+                    let read = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..read]);
+                    if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..head_end]).to_lowercase();
+                let headers: Vec<String> = head.lines().skip(1).map(str::to_string).collect();
+                let length = headers
+                    .iter()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                while buf.len() < head_end + 4 + length {
+                    let read = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..read]);
+                }
+                received.lock().unwrap().push((headers, buf[head_end + 4..head_end + 4 + length].to_vec()));
+                let response = format!("HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    fn header<'a>(headers: &'a [String], name: &str) -> Option<&'a str> {
+        let prefix = format!("{}:", name.to_lowercase());
+        headers.iter().find_map(|line| line.strip_prefix(&prefix)).map(str::trim)
+    }
+
+    #[test]
+    fn test_signature_covers_timestamp_and_body() {
+        assert_eq!(
+            signature(b"Jefe", 1_700_000_000, b"what do ya want for nothing?"),
+            "sha256=1cdd0650c8be1cb0974b1788d458b1e781206cfef59b85faafc582d2e182c57e"
+        );
+        // A replayed body with a fresh timestamp no longer matches
+        assert_ne!(
+            signature(b"Jefe", 1_700_000_300, b"what do ya want for nothing?"),
+            signature(b"Jefe", 1_700_000_000, b"what do ya want for nothing?")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_payload_is_signed_json() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let addr = endpoint(204, received.clone()).await;
+        let webhooks = Webhooks::new(WebhookConfig {
+            urls: vec![format!("http://{}/hooks/dno", addr)],
+            secret: Some("s3cret".to_string()),
+            ..Default::default()
+        });
+        let session_id = Uuid::new_v4();
+
+        let deliveries = webhooks.send(WebhookEvent::CrawlFailed { session_id }).await;
+
+        assert_eq!(deliveries[0].attempts, 1);
+        assert_eq!(deliveries[0].error, None);
+        let (headers, body) = received.lock().unwrap().remove(0);
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["event"], "crawl.failed");
+        assert_eq!(payload["data"], serde_json::json!({ "session_id": session_id }));
+        assert!(payload["id"].is_string() && payload["occurred_at"].is_string());
+        assert_eq!(header(&headers, EVENT_HEADER), Some("crawl.failed"));
+        let timestamp: i64 = header(&headers, TIMESTAMP_HEADER).unwrap().parse().unwrap();
+        assert!((Utc::now().timestamp() - timestamp).abs() < 60);
+        assert_eq!(header(&headers, SIGNATURE_HEADER), Some(signature(b"s3cret", timestamp, &body).as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried_then_dead_lettered() {
+        let dir = tempfile::tempdir().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let addr = endpoint(500, received.clone()).await;
+        let webhooks = Webhooks::new(WebhookConfig {
+            urls: vec![format!("http://{}/hooks/dno", addr)],
+            max_retries: 2,
+            backoff: Duration::from_millis(1),
+            dead_letter: dir.path().join("dead-letter.jsonl"),
+            ..Default::default()
+        });
+        let event = WebhookEvent::FileCorrupted {
+            file_id: Uuid::new_v4(),
+            path: "dno-data/netze-bw/2024/preisblatt-2024.pdf".to_string(),
+            problem: "missing from disk".to_string(),
+        };
+
+        let deliveries = webhooks.send(event.clone()).await;
+
+        assert_eq!(deliveries[0].attempts, 3);
+        assert_eq!(deliveries[0].error.as_deref(), Some("HTTP 500 Internal Server Error"));
+        assert_eq!(received.lock().unwrap().len(), 3);
+        let log = std::fs::read_to_string(dir.path().join("dead-letter.jsonl")).unwrap();
+        let letters: Vec<serde_json::Value> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0]["attempts"], 3);
+        assert_eq!(letters[0]["payload"]["event"], "file.corrupted");
+        assert_eq!(letters[0]["payload"]["data"]["problem"], "missing from disk");
+    }
+}
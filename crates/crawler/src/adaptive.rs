@@ -0,0 +1,1550 @@
+use crate::checkpoint::{CheckpointStore, CrawlCheckpoint};
+use crate::error::CrawlError;
+use crate::content::ContentType;
+use crate::http::{CacheDirective, HttpRequest, HttpResponse, HttpTransport};
+use crate::pagination::{collect_pages, is_archive_index, links_on_page};
+use crate::pipeline::{PipelineConfig, RecoveryStrategy};
+use crate::overrides::DnoOverrides;
+use crate::queue::{CrawlQueue, QueuedUrl};
+use crate::reconstruct::{UrlPattern, UrlReconstructor};
+use crate::recovery::{FailureRecoverySystem, RetryBudget, RetryLimits};
+use crate::robots::RobotsCache;
+use crate::search::SearchService;
+use anyhow::{Context, Result};
+use dno_core::crawl_plan::{CrawlContext, CrawlPlan, CrawlStrategy, SeedSource};
+use dno_core::manual_review::ManualReviewRequest;
+use dno_core::source_manager::{NewSourceFile, SourceManager};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+static YEAR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[^0-9])((?:19|20)[0-9]{2})(?:[^0-9]|$)").unwrap());
+/// A month or quarter right after the year, as in `/2024/03/` or `2024-q1`
+static MONTH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{year\}[/_-](0[1-9]|1[0-2])(?:[^0-9]|$)").unwrap());
+static QUARTER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{year\}[/_-](q[1-4])(?:[^0-9]|$)").unwrap());
+static VARIANT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[^a-z])(ht|nt|hochtarif|niedertarif)(?:[^a-z]|$)").unwrap());
+
+/// Words that mark a link as tariff-related even without a learned pattern
+const TARIFF_KEYWORDS: [&str; 7] = [
+    "netzentgelt",
+    "entgelt",
+    "preisblatt",
+    "preise",
+    "hlzf",
+    "hochlast",
+    "tarif",
+];
+const DOCUMENT_EXTENSIONS: [&str; 5] = [".pdf", ".xlsx", ".xls", ".csv", ".zip"];
+/// Pages of a paginated archive index followed during discovery
+const MAX_ARCHIVE_PAGES: usize = 20;
+/// Ranked search results a `SearchQuery` seed turns into
+const MAX_SEARCH_SEEDS: usize = 5;
+
+/// Why a link was considered related to a successful find
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelatedReason {
+    /// Same document series, another year
+    AdjacentYear,
+    /// Same year as the find, e.g. an HT/NT variant or companion document
+    SameYear,
+    /// Tariff keywords but no recognizable year
+    Keyword,
+}
+
+/// A sibling link of a successful extraction page, ranked by how likely it
+/// leads to more tariff data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedDoc {
+    pub url: String,
+    pub anchor_text: String,
+    pub year: Option<i32>,
+    /// `ht`, `nt`, `hochtarif` or `niedertarif` when the link names a tariff variant
+    pub variant: Option<String>,
+    pub reason: RelatedReason,
+    /// Whether the link's file name follows a pattern learned from earlier finds
+    pub learned_pattern: bool,
+    pub score: f64,
+}
+
+/// How a discovery crawl ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrawlCompletion {
+    /// The queue ran empty
+    Complete,
+    /// The time budget (`constraints.timeout_secs`) ran out
+    TimedOut,
+    /// `AdaptiveCrawler::cancel` was called
+    Cancelled,
+    /// `constraints.max_pages` URLs were processed
+    BudgetExhausted,
+}
+
+/// A document a crawl downloaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoundDocument {
+    pub url: String,
+    pub year: Option<i32>,
+    /// Id in the `SourceManager`; `None` when the crawler has no storage attached
+    pub file_id: Option<Uuid>,
+}
+
+/// What a discovery crawl did, also when it stopped early
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlResult {
+    pub dno_slug: String,
+    pub year: i32,
+    pub completion: CrawlCompletion,
+    /// Why the crawl stopped before the queue ran empty
+    pub reason: Option<String>,
+    /// URLs fetched and handled, in order
+    pub processed_urls: Vec<String>,
+    pub failed_urls: Vec<String>,
+    /// Failed URLs only a human can get past, e.g. behind a login; also in `failed_urls`
+    #[serde(default)]
+    pub manual_review: Vec<ManualReviewRequest>,
+    pub documents: Vec<FoundDocument>,
+    /// Fetches repeated after a retryable failure, out of the session's retry budget
+    #[serde(default)]
+    pub retries: u32,
+    pub elapsed_ms: u64,
+    /// How far the crawl can be trusted to have found the year's data, factor by factor
+    #[serde(default)]
+    pub confidence: ConfidenceBreakdown,
+}
+
+impl CrawlResult {
+    fn new(context: &CrawlContext) -> Self {
+        Self {
+            dno_slug: context.dno.slug.clone(),
+            year: context.year,
+            completion: CrawlCompletion::Complete,
+            reason: None,
+            processed_urls: Vec::new(),
+            failed_urls: Vec::new(),
+            manual_review: Vec::new(),
+            documents: Vec::new(),
+            retries: 0,
+            elapsed_ms: 0,
+            confidence: ConfidenceBreakdown::default(),
+        }
+    }
+
+    fn stop(&mut self, completion: CrawlCompletion, reason: String) {
+        self.completion = completion;
+        self.reason = Some(reason);
+    }
+}
+
+/// URLs processed for `url_count_factor` to reach 1
+const CONFIDENCE_URL_TARGET: f64 = 10.0;
+/// Documents found for `file_factor` to reach 1
+const CONFIDENCE_FILE_TARGET: f64 = 2.0;
+
+/// How much each factor counts towards a crawl's confidence. Weights are
+/// relative; `normalized` scales them to sum to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceWeights {
+    pub url_count: f64,
+    pub files: f64,
+    pub data_richness: f64,
+    pub nav_success: f64,
+}
+
+impl Default for ConfidenceWeights {
+    /// Finding the year's documents matters more than crawling widely
+    fn default() -> Self {
+        Self { url_count: 0.1, files: 0.35, data_richness: 0.35, nav_success: 0.2 }
+    }
+}
+
+impl ConfidenceWeights {
+    /// `CRAWLER_CONFIDENCE_WEIGHTS` as `url_count=1,files=3,data_richness=3,nav_success=2`;
+    /// factors left out weigh 0. Defaults when unset or unparsable.
+    pub fn from_env() -> Self {
+        std::env::var("CRAWLER_CONFIDENCE_WEIGHTS")
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut weights = Self { url_count: 0.0, files: 0.0, data_richness: 0.0, nav_success: 0.0 };
+        for pair in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (name, weight) = pair.split_once('=')?;
+            let weight = weight.trim().parse::<f64>().ok().filter(|w| w.is_finite() && *w >= 0.0)?;
+            match name.trim() {
+                "url_count" => weights.url_count = weight,
+                "files" => weights.files = weight,
+                "data_richness" => weights.data_richness = weight,
+                "nav_success" => weights.nav_success = weight,
+                _ => return None,
+            }
+        }
+        (weights.sum() > 0.0).then_some(weights)
+    }
+
+    fn sum(&self) -> f64 {
+        self.url_count + self.files + self.data_richness + self.nav_success
+    }
+
+    /// Scaled to sum to 1; the defaults when no weight is positive
+    pub fn normalized(&self) -> Self {
+        let sum = self.sum();
+        if !(sum.is_finite() && sum > 0.0) {
+            return Self::default().normalized();
+        }
+        Self {
+            url_count: self.url_count / sum,
+            files: self.files / sum,
+            data_richness: self.data_richness / sum,
+            nav_success: self.nav_success / sum,
+        }
+    }
+}
+
+/// The factors behind `CrawlResult::confidence`, each in 0..=1, and the
+/// normalized weights they were combined with
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ConfidenceBreakdown {
+    /// URLs processed, up to `CONFIDENCE_URL_TARGET`
+    pub url_count_factor: f64,
+    /// Documents found, up to `CONFIDENCE_FILE_TARGET`
+    pub file_factor: f64,
+    /// Share of found documents that name the crawled year
+    pub data_richness: f64,
+    /// Share of processed URLs that did not fail
+    pub nav_success_rate: f64,
+    pub weights: ConfidenceWeights,
+    /// The weighted sum of the factors
+    pub score: f64,
+}
+
+impl ConfidenceBreakdown {
+    /// What each factor adds to `score`: url count, files, data richness, navigation
+    pub fn contributions(&self) -> [f64; 4] {
+        [
+            self.url_count_factor * self.weights.url_count,
+            self.file_factor * self.weights.files,
+            self.data_richness * self.weights.data_richness,
+            self.nav_success_rate * self.weights.nav_success,
+        ]
+    }
+}
+
+/// How hard discovery may hit DNO sites: requests in flight overall and per
+/// host, and a token bucket per host that spaces out request starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrawlLimits {
+    /// URLs processed at once across all hosts
+    pub max_concurrent: usize,
+    /// URLs processed at once against one host
+    pub max_per_host: usize,
+    /// Sustained request rate per host; 0 turns rate limiting off
+    pub requests_per_second: f64,
+    /// Requests a host that was left alone may get right away
+    pub burst: u32,
+}
+
+impl Default for CrawlLimits {
+    fn default() -> Self {
+        Self { max_concurrent: 10, max_per_host: 2, requests_per_second: 2.0, burst: 4 }
+    }
+}
+
+impl CrawlLimits {
+    /// Read `CRAWLER_MAX_CONCURRENT`, `CRAWLER_MAX_CONCURRENT_PER_HOST`,
+    /// `CRAWLER_HOST_REQUESTS_PER_SEC` and `CRAWLER_HOST_BURST`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            max_concurrent: var("CRAWLER_MAX_CONCURRENT")
+                .filter(|&n| n > 0)
+                .map_or(defaults.max_concurrent, |n| n as usize),
+            max_per_host: var("CRAWLER_MAX_CONCURRENT_PER_HOST")
+                .filter(|&n| n > 0)
+                .map_or(defaults.max_per_host, |n| n as usize),
+            requests_per_second: std::env::var("CRAWLER_HOST_REQUESTS_PER_SEC")
+                .ok()
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|rate| rate.is_finite() && *rate >= 0.0)
+                .unwrap_or(defaults.requests_per_second),
+            burst: var("CRAWLER_HOST_BURST").filter(|&n| n > 0).map_or(defaults.burst, |n| n as u32),
+        }
+    }
+}
+
+/// Slots and token bucket of one host
+struct HostLimiter {
+    slots: Arc<Semaphore>,
+    /// Tokens left and when they were last topped up
+    bucket: tokio::sync::Mutex<(f64, tokio::time::Instant)>,
+}
+
+impl HostLimiter {
+    fn new(limits: &CrawlLimits) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(limits.max_per_host.max(1))),
+            bucket: tokio::sync::Mutex::new((limits.burst.max(1) as f64, tokio::time::Instant::now())),
+        }
+    }
+
+    /// Wait for a free slot on the host, then for a token
+    async fn enter(&self, limits: &CrawlLimits) -> OwnedSemaphorePermit {
+        let permit = self.slots.clone().acquire_owned().await.expect("host semaphore is never closed");
+        let rate = limits.requests_per_second;
+        if rate > 0.0 {
+            // Held while waiting, so waiters get tokens in arrival order
+            let mut bucket = self.bucket.lock().await;
+            let (tokens, topped_up) = &mut *bucket;
+            let now = tokio::time::Instant::now();
+            *tokens = (*tokens + now.duration_since(*topped_up).as_secs_f64() * rate).min(limits.burst.max(1) as f64);
+            *topped_up = now;
+            if *tokens < 1.0 {
+                tokio::time::sleep(Duration::from_secs_f64((1.0 - *tokens) / rate)).await;
+                *tokens = 1.0;
+                *topped_up = tokio::time::Instant::now();
+            }
+            *tokens -= 1.0;
+        }
+        permit
+    }
+}
+
+/// Crawls DNO sites and remembers which file-naming patterns led to data, so
+/// later crawls can go straight for the documents that are likely to matter
+pub struct AdaptiveCrawler {
+    transport: Arc<dyn HttpTransport>,
+    /// Learned URL shapes per host, with how often each produced data
+    patterns: Mutex<HashMap<String, HashMap<String, u32>>>,
+    queue: Mutex<CrawlQueue>,
+    strategies: Vec<CrawlStrategy>,
+    /// Per-DNO mode, start URLs, content type and strategy order
+    overrides: Arc<DnoOverrides>,
+    /// Where downloaded documents are stored
+    sources: Option<Arc<SourceManager>>,
+    /// Where checkpointed crawls save their progress
+    checkpoints: Option<Arc<CheckpointStore>>,
+    cancelled: AtomicBool,
+    /// Decides which failures are worth a retry
+    recovery: FailureRecoverySystem,
+    retry_limits: RetryLimits,
+    /// Keeps discovery crawls out of paths `robots.txt` disallows
+    robots: Option<Arc<RobotsCache>>,
+    /// Resolves `SearchQuery` seeds; without it they are skipped
+    search: Option<Arc<SearchService>>,
+    /// Fills learned shapes in with the target year and its months or quarters
+    reconstructor: UrlReconstructor,
+    limits: CrawlLimits,
+    confidence_weights: ConfidenceWeights,
+    /// Caps URLs in flight across all hosts
+    permits: Semaphore,
+    hosts: Mutex<HashMap<String, Arc<HostLimiter>>>,
+}
+
+impl AdaptiveCrawler {
+    pub fn new(transport: Arc<dyn HttpTransport>, limits: CrawlLimits) -> Self {
+        Self {
+            transport,
+            patterns: Mutex::new(HashMap::new()),
+            queue: Mutex::new(CrawlQueue::new()),
+            strategies: CrawlStrategy::configured_order(),
+            overrides: Arc::new(DnoOverrides::default()),
+            sources: None,
+            checkpoints: None,
+            cancelled: AtomicBool::new(false),
+            recovery: FailureRecoverySystem::new(&PipelineConfig::default()),
+            retry_limits: RetryLimits::from_env(),
+            robots: None,
+            search: None,
+            reconstructor: UrlReconstructor::from_env(),
+            limits,
+            confidence_weights: ConfidenceWeights::from_env(),
+            permits: Semaphore::new(limits.max_concurrent.max(1)),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Honour `robots.txt` and `Crawl-delay`, unless a crawl's context opts out
+    pub fn with_robots(mut self, robots: Arc<RobotsCache>) -> Self {
+        self.robots = Some(robots);
+        self
+    }
+
+    /// Seed crawls of DNOs without a website from ranked SearXNG results
+    pub fn with_search(mut self, search: Arc<SearchService>) -> Self {
+        self.search = Some(search);
+        self
+    }
+
+    /// Use this recovery order to decide which failures are retried
+    pub fn with_recovery(mut self, recovery: FailureRecoverySystem) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Retry limits per URL and per discovery crawl, instead of the environment's
+    pub fn with_retry_limits(mut self, limits: RetryLimits) -> Self {
+        self.retry_limits = limits;
+        self
+    }
+
+    /// Store documents found by discovery crawls
+    pub fn with_sources(mut self, sources: Arc<SourceManager>) -> Self {
+        self.sources = Some(sources);
+        self
+    }
+
+    /// Save the progress of `execute_checkpointed_crawl` here
+    pub fn with_checkpoints(mut self, checkpoints: Arc<CheckpointStore>) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Weigh the confidence factors like this instead of `CRAWLER_CONFIDENCE_WEIGHTS`
+    pub fn with_confidence_weights(mut self, weights: ConfidenceWeights) -> Self {
+        self.confidence_weights = weights;
+        self
+    }
+
+    /// Stop running discovery crawls after the URL in flight
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Use these strategies, in this order, instead of `CRAWLER_STRATEGY_ORDER`
+    pub fn with_strategies(mut self, strategies: Vec<CrawlStrategy>) -> Self {
+        self.strategies = strategies;
+        self
+    }
+
+    /// Merge these per-DNO overrides into every crawl's context
+    pub fn with_overrides(mut self, overrides: Arc<DnoOverrides>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// How confident `result` is to hold the year's tariff documents. Only
+    /// depends on the result and the weights, so the same crawl always scores the same.
+    pub fn calculate_success_confidence(&self, result: &CrawlResult) -> ConfidenceBreakdown {
+        let processed = result.processed_urls.len() as f64;
+        let documents = result.documents.len() as f64;
+        let share = |part: f64, whole: f64| if whole > 0.0 { (part / whole).clamp(0.0, 1.0) } else { 0.0 };
+        let mut breakdown = ConfidenceBreakdown {
+            url_count_factor: (processed / CONFIDENCE_URL_TARGET).min(1.0),
+            file_factor: (documents / CONFIDENCE_FILE_TARGET).min(1.0),
+            data_richness: share(
+                result.documents.iter().filter(|doc| doc.year == Some(result.year)).count() as f64,
+                documents,
+            ),
+            nav_success_rate: share(processed - result.failed_urls.len() as f64, processed),
+            weights: self.confidence_weights.normalized(),
+            score: 0.0,
+        };
+        breakdown.score = breakdown.contributions().iter().sum();
+        breakdown
+    }
+
+    /// What a crawl for `context` would do: seeds from the DNO's stored website
+    /// and from patterns learned on its host, the strategies in order, the
+    /// constraints and a request estimate. Nothing is fetched. The DNO's
+    /// override, if any, is merged in first.
+    pub fn plan(&self, context: &CrawlContext) -> CrawlPlan {
+        self.plan_merged(&self.overrides.apply(context.clone()))
+    }
+
+    fn plan_merged(&self, context: &CrawlContext) -> CrawlPlan {
+        let website = context.website_url().and_then(|url| Url::parse(&url).ok());
+        let learned_urls = website
+            .as_ref()
+            .map(|website| {
+                self.learned_shapes(website.host_str().unwrap_or_default())
+                    .into_iter()
+                    // Which variant to fetch is only known once a page lists them
+                    .filter(|shape| !shape.contains("{variant}"))
+                    .flat_map(|shape| {
+                        self.reconstructor.generate_urls_for_year(&UrlPattern::new(shape), context.year)
+                    })
+                    .filter_map(|path| website.join(&path).ok())
+                    .map(|url| url.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let order = context.strategy_order.as_deref().unwrap_or(&self.strategies);
+        CrawlPlan::build(context, order, learned_urls)
+    }
+
+    /// Learn the naming pattern of a URL that produced data, e.g.
+    /// `/files/preisblatt-{year}.pdf` or `/archiv/{year}/{month}/tarif.pdf`
+    pub fn record_success(&self, url: &str) {
+        let Some((host, shape)) = Url::parse(url)
+            .ok()
+            .and_then(|url| Some((url.host_str()?.to_string(), url_shape(&url))))
+        else {
+            return;
+        };
+        *self
+            .patterns
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_default()
+            .entry(shape)
+            .or_default() += 1;
+    }
+
+    /// Next URL to crawl, highest priority first
+    pub fn next_url(&self) -> Option<QueuedUrl> {
+        self.queue.lock().unwrap().pop()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    /// Find links on a page that produced data which likely lead to more: the
+    /// same document for other years, HT/NT variants and companion documents.
+    /// Links following a learned pattern, or forming a year series on the page,
+    /// rank higher, as do years closer to the find's. On an archive index, all
+    /// pages of the listing are followed. The results, best first, are also
+    /// added to the crawl queue.
+    /// Links `robots.txt` disallows are left out.
+    pub async fn discover_related(&self, found_url: &str) -> Result<Vec<RelatedDoc>> {
+        self.discover_related_at(found_url, 1, self.robots.as_deref(), None).await
+    }
+
+    async fn discover_related_at(
+        &self,
+        found_url: &str,
+        depth: u32,
+        robots: Option<&RobotsCache>,
+        preferred: Option<ContentType>,
+    ) -> Result<Vec<RelatedDoc>> {
+        let base = Url::parse(found_url).with_context(|| format!("Invalid URL: {}", found_url))?;
+        let response = self.transport.execute(HttpRequest::get(found_url)).await?;
+        if !response.is_success() {
+            return Err(CrawlError::Http { url: found_url.to_string(), status: response.status }.into());
+        }
+
+        let found_year = year_in(base.path());
+        let learned = self.learned_shapes(base.host_str().unwrap_or_default());
+        let html = response.text();
+        let links = if is_archive_index(&base, &html) {
+            // Older years sit on later pages of an archive listing
+            collect_pages(self.transport.as_ref(), base.clone(), html, MAX_ARCHIVE_PAGES)
+                .await?
+                .items
+        } else {
+            links_on_page(&base, &html)
+        };
+
+        // Shapes that occur with several years on this page form a document series
+        let mut series: HashMap<String, BTreeSet<i32>> = HashMap::new();
+        for (url, _) in &links {
+            if let Some(year) = year_in(url.path()) {
+                series.entry(url_shape(url)).or_default().insert(year);
+            }
+        }
+
+        let mut related: Vec<RelatedDoc> = Vec::new();
+        for (url, anchor_text) in links {
+            if url.as_str() == found_url || related.iter().any(|doc| doc.url == url.as_str()) {
+                continue;
+            }
+            let path = url.path().to_lowercase();
+            let text = format!("{} {}", path, anchor_text.to_lowercase());
+            let year = year_in(&path).or_else(|| year_in(&anchor_text));
+            let has_keyword = TARIFF_KEYWORDS.iter().any(|keyword| text.contains(keyword));
+            let shape = url_shape(&url);
+            let learned_pattern = learned.contains(&shape);
+            let in_series = series.get(&shape).is_some_and(|years| years.len() > 1);
+
+            let (reason, mut score) = match (year, found_year) {
+                (Some(year), Some(found)) if year == found => (RelatedReason::SameYear, 0.5),
+                (Some(year), Some(found)) => (
+                    RelatedReason::AdjacentYear,
+                    0.4 + 0.3 / (1.0 + (year - found).abs() as f64),
+                ),
+                (Some(_), None) => (RelatedReason::AdjacentYear, 0.4),
+                (None, _) if has_keyword => (RelatedReason::Keyword, 0.2),
+                (None, _) => continue,
+            };
+            if year.is_some() && !(has_keyword || learned_pattern || in_series) {
+                // A year alone (news items, press releases) is not enough
+                continue;
+            }
+            if learned_pattern {
+                score += 0.3;
+            } else if in_series {
+                score += 0.15;
+            }
+            if has_keyword {
+                score += 0.1;
+            }
+            if DOCUMENT_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+                score += 0.05;
+            }
+            if preferred.is_some_and(|preferred| ContentType::from_path(&path) == preferred) {
+                score += 0.1;
+            }
+
+            related.push(RelatedDoc {
+                url: url.to_string(),
+                anchor_text,
+                year,
+                variant: VARIANT.captures(&path).map(|c| c[1].to_string()),
+                reason,
+                learned_pattern,
+                score: score.min(1.0),
+            });
+        }
+
+        related.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.year.cmp(&a.year))
+        });
+        if let Some(robots) = robots {
+            let mut allowed = Vec::with_capacity(related.len());
+            for doc in related {
+                if robots.is_allowed(&doc.url).await {
+                    allowed.push(doc);
+                }
+            }
+            related = allowed;
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        let queued = related
+            .iter()
+            .filter(|doc| {
+                queue.push(QueuedUrl {
+                    url: doc.url.clone(),
+                    priority: doc.score,
+                    depth,
+                    reason: format!("related to {}", found_url),
+                })
+            })
+            .count();
+        debug!(
+            "{} related documents on {} ({} newly queued)",
+            related.len(),
+            found_url,
+            queued
+        );
+
+        Ok(related)
+    }
+
+    /// Crawl from the planned seeds until the queue runs empty or a limit of
+    /// `context.constraints` is hit. Documents are stored as they are found,
+    /// so a crawl that stops early has persisted everything in its result.
+    pub async fn execute_discovery_crawl(&self, context: &CrawlContext) -> CrawlResult {
+        let context = self.overrides.apply(context.clone());
+        self.run_discovery(&context, Duration::from_secs(context.constraints.timeout_secs))
+            .await
+    }
+
+    /// Like `execute_discovery_crawl`, but the queue, the URLs seen and the
+    /// result so far are saved to the checkpoint store under `session_id`, so
+    /// `resume` can continue the crawl after a crash or `cancel`. Without a
+    /// checkpoint store nothing is saved.
+    pub async fn execute_checkpointed_crawl(&self, session_id: Uuid, context: &CrawlContext) -> CrawlResult {
+        let context = self.overrides.apply(context.clone());
+        self.seed_queue(&context).await;
+        let time_budget = Duration::from_secs(context.constraints.timeout_secs);
+        self.crawl_queue(Some(session_id), &context, time_budget, CrawlResult::new(&context))
+            .await
+    }
+
+    /// Continue the checkpointed crawl `session_id` where its last checkpoint
+    /// left off. URLs processed or queued before are not queued again, and
+    /// the time, page and retry budgets count what was spent before.
+    pub async fn resume(&self, session_id: Uuid) -> Result<CrawlResult> {
+        let checkpoints = self.checkpoints.as_ref().context("No checkpoint store configured")?;
+        let checkpoint = checkpoints.load(session_id)?;
+        info!(
+            "Resuming discovery crawl {} for {} {} after {} URLs ({} queued)",
+            session_id,
+            checkpoint.context.dno.slug,
+            checkpoint.context.year,
+            checkpoint.result.processed_urls.len(),
+            checkpoint.queue.len()
+        );
+        let time_budget = Duration::from_secs(checkpoint.context.constraints.timeout_secs)
+            .saturating_sub(Duration::from_millis(checkpoint.result.elapsed_ms));
+        *self.queue.lock().unwrap() = checkpoint.queue;
+        let mut result = checkpoint.result;
+        result.completion = CrawlCompletion::Complete;
+        result.reason = None;
+        Ok(self.crawl_queue(Some(session_id), &checkpoint.context, time_budget, result).await)
+    }
+
+    async fn run_discovery(&self, context: &CrawlContext, time_budget: Duration) -> CrawlResult {
+        self.seed_queue(context).await;
+        self.crawl_queue(None, context, time_budget, CrawlResult::new(context)).await
+    }
+
+    /// Start the queue afresh with the planned seeds `robots.txt` allows
+    async fn seed_queue(&self, context: &CrawlContext) {
+        let plan = self.plan_merged(context);
+        let robots = self.robots.as_deref().filter(|_| !context.ignore_robots);
+        let mut candidates = Vec::new();
+        for seed in &plan.seeds {
+            if seed.source == SeedSource::SearchQuery {
+                candidates.extend(self.search_seeds(&seed.value, context).await);
+            } else {
+                candidates.push(QueuedUrl {
+                    url: seed.value.clone(),
+                    priority: 1.0,
+                    depth: 0,
+                    reason: format!("{:?} seed", seed.source),
+                });
+            }
+        }
+        let mut seeds = Vec::new();
+        for seed in candidates {
+            match robots {
+                Some(robots) if !robots.is_allowed(&seed.url).await => {}
+                _ => seeds.push(seed),
+            }
+        }
+        let mut queue = self.queue.lock().unwrap();
+        *queue = CrawlQueue::new();
+        for seed in seeds {
+            queue.push(seed);
+        }
+    }
+
+    /// The best-ranked results of a search, highest priority first. Results
+    /// the ranking penalized are left out; a failed search yields nothing.
+    async fn search_seeds(&self, query: &str, context: &CrawlContext) -> Vec<QueuedUrl> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        match search.search(query, Some(&context.dno)).await {
+            Ok(found) => found
+                .into_iter()
+                .filter(|source| source.score > 0.0)
+                .take(MAX_SEARCH_SEEDS)
+                .enumerate()
+                .map(|(rank, source)| QueuedUrl {
+                    url: source.url,
+                    priority: 1.0 / (1.0 + rank as f64),
+                    depth: 0,
+                    reason: format!("search result (score {:.2})", source.score),
+                })
+                .collect(),
+            Err(e) => {
+                warn!("Search for '{}' failed: {:#}", query, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Crawl what is queued until the queue runs empty or a limit is hit,
+    /// adding to `result`. With a `session_id`, progress is checkpointed.
+    async fn crawl_queue(
+        &self,
+        session_id: Option<Uuid>,
+        context: &CrawlContext,
+        time_budget: Duration,
+        mut result: CrawlResult,
+    ) -> CrawlResult {
+        let started = Instant::now();
+        let plan = self.plan_merged(context);
+        let robots = self.robots.as_deref().filter(|_| !context.ignore_robots);
+        let cache = CacheDirective {
+            bypass: context.bypass_http_cache,
+            ttl: context.http_cache_ttl_secs.map(Duration::from_secs),
+        };
+        let preferred = context.preferred_content_type.as_deref().and_then(ContentType::from_name);
+        let checkpoints = session_id.zip(self.checkpoints.as_deref());
+        // Spent before a resume
+        let (retries_before, elapsed_before) = (result.retries, result.elapsed_ms);
+        let retries = RetryBudget::new(RetryLimits {
+            per_session: self.retry_limits.per_session.saturating_sub(retries_before),
+            ..self.retry_limits
+        });
+        loop {
+            if self.cancelled.load(Ordering::Relaxed) {
+                result.stop(CrawlCompletion::Cancelled, "cancelled".to_string());
+                break;
+            }
+            let Some(remaining) = time_budget.checked_sub(started.elapsed()).filter(|r| !r.is_zero()) else {
+                result.stop(CrawlCompletion::TimedOut, format!("time budget of {:?} used up", time_budget));
+                break;
+            };
+            if result.processed_urls.len() >= plan.constraints.max_pages as usize {
+                result.stop(
+                    CrawlCompletion::BudgetExhausted,
+                    format!("page budget of {} used up", plan.constraints.max_pages),
+                );
+                break;
+            }
+            let Some(next) = self.next_url() else {
+                break;
+            };
+            if next.depth > plan.constraints.max_depth {
+                continue;
+            }
+
+            let outcome = match tokio::time::timeout(remaining, self.process_url(&next, robots, cache, preferred)).await {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    result.stop(
+                        CrawlCompletion::TimedOut,
+                        format!("time budget of {:?} ran out while fetching {}", time_budget, next.url),
+                    );
+                    break;
+                }
+            };
+
+            let outcome = outcome.map_err(CrawlError::from_anyhow);
+            let mut needs_review = false;
+            if let Err(error) = &outcome {
+                let strategy = self.recovery.classify_failure(error);
+                needs_review = strategy == Some(RecoveryStrategy::ManualIntervention);
+                if strategy == Some(RecoveryStrategy::Retry) {
+                    if retries.try_acquire(&next.url) {
+                        info!("Retrying {} after: {} ({} session retries left)", next.url, error, retries.remaining());
+                        // Behind the rest of the queue, which gives the site a moment
+                        self.queue.lock().unwrap().requeue(QueuedUrl { priority: 0.0, ..next });
+                        continue;
+                    }
+                    if retries.is_exhausted() {
+                        warn!("Session retry budget used up, not retrying {}", next.url);
+                    }
+                }
+            }
+
+            result.processed_urls.push(next.url.clone());
+            match outcome {
+                Ok(Some(response)) => {
+                    // Stored outside the timeout so a found document is never half-written
+                    let year = year_in(&next.url);
+                    let file_id = self.store_document(context, year, &next.url, &response);
+                    self.record_success(&next.url);
+                    result.documents.push(FoundDocument { url: next.url, year, file_id });
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Discovery of {} failed: {:#}", next.url, e);
+                    if needs_review {
+                        result.manual_review.push(ManualReviewRequest {
+                            url: next.url.clone(),
+                            reason: e.to_string(),
+                            context: json!({
+                                "error_kind": e.kind(),
+                                "depth": next.depth,
+                                "queued_because": next.reason,
+                                "dno": context.dno.slug,
+                                "year": context.year,
+                            }),
+                        });
+                    }
+                    result.failed_urls.push(next.url);
+                }
+            }
+            if let Some((session_id, checkpoints)) = checkpoints {
+                if checkpoints.is_due(result.processed_urls.len()) {
+                    result.retries = retries_before + retries.used();
+                    result.elapsed_ms = elapsed_before + started.elapsed().as_millis() as u64;
+                    self.save_checkpoint(checkpoints, session_id, context, &result);
+                }
+            }
+        }
+
+        result.retries = retries_before + retries.used();
+        result.elapsed_ms = elapsed_before + started.elapsed().as_millis() as u64;
+        result.confidence = self.calculate_success_confidence(&result);
+        if let Some((session_id, checkpoints)) = checkpoints {
+            // A cancelled crawl can be resumed; any other ending is final
+            if result.completion == CrawlCompletion::Cancelled {
+                self.save_checkpoint(checkpoints, session_id, context, &result);
+            } else if let Err(e) = checkpoints.remove(session_id) {
+                warn!("Failed to remove checkpoint of {}: {:#}", session_id, e);
+            }
+        }
+        info!(
+            "Discovery crawl for {} {} ended {:?} after {} URLs, {} documents, {} retries ({} left), confidence {:.2}",
+            result.dno_slug,
+            result.year,
+            result.completion,
+            result.processed_urls.len(),
+            result.documents.len(),
+            result.retries,
+            retries.remaining(),
+            result.confidence.score
+        );
+        result
+    }
+
+    /// Failing to save only costs the progress since the last checkpoint, so
+    /// the crawl goes on
+    fn save_checkpoint(&self, checkpoints: &CheckpointStore, session_id: Uuid, context: &CrawlContext, result: &CrawlResult) {
+        let checkpoint = CrawlCheckpoint {
+            session_id,
+            context: context.clone(),
+            queue: self.queue.lock().unwrap().clone(),
+            result: result.clone(),
+            saved_at: chrono::Utc::now(),
+        };
+        if let Err(e) = checkpoints.save(&checkpoint) {
+            warn!("Failed to save checkpoint of {}: {:#}", session_id, e);
+        }
+    }
+
+    fn host_limiter(&self, host: &str) -> Arc<HostLimiter> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(HostLimiter::new(&self.limits)))
+            .clone()
+    }
+
+    /// Download a document, or queue the related links of a page, within the
+    /// crawl limits and after the host's `Crawl-delay`
+    async fn process_url(
+        &self,
+        next: &QueuedUrl,
+        robots: Option<&RobotsCache>,
+        cache: CacheDirective,
+        preferred: Option<ContentType>,
+    ) -> Result<Option<HttpResponse>> {
+        let host = Url::parse(&next.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        // Host first: a URL waiting on its busy host must not hold a global slot
+        let _host_permit = self.host_limiter(&host).enter(&self.limits).await;
+        let _permit = self.permits.acquire().await.expect("crawl semaphore is never closed");
+        if let Some(robots) = robots {
+            robots.wait_turn(&next.url).await;
+        }
+        let is_document = DOCUMENT_EXTENSIONS.iter().any(|ext| next.url.to_lowercase().ends_with(ext));
+        if !is_document {
+            self.discover_related_at(&next.url, next.depth + 1, robots, preferred).await?;
+            return Ok(None);
+        }
+        let response = self.transport.execute(HttpRequest::get(&next.url).with_cache(cache)).await?;
+        if !response.is_success() {
+            return Err(CrawlError::Http { url: next.url.clone(), status: response.status }.into());
+        }
+        Ok(Some(response))
+    }
+
+    fn store_document(
+        &self,
+        context: &CrawlContext,
+        year: Option<i32>,
+        url: &str,
+        response: &HttpResponse,
+    ) -> Option<Uuid> {
+        let sources = self.sources.as_ref()?;
+        let file_name = Url::parse(url)
+            .ok()?
+            .path_segments()?
+            .next_back()
+            .filter(|name| !name.is_empty())?
+            .to_string();
+        let stored = sources.store_file(
+            NewSourceFile {
+                dno: &context.dno.name,
+                year: year.unwrap_or(context.year),
+                data_type: &context.data_type,
+                file_name: &file_name,
+                source_url: Some(url),
+                content_type: response.header("content-type"),
+                bytes: &response.body,
+            },
+            "crawler",
+        );
+        match stored {
+            Ok(file) => Some(file.id),
+            Err(e) => {
+                warn!("Failed to store {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    fn learned_shapes(&self, host: &str) -> BTreeSet<String> {
+        self.patterns
+            .lock()
+            .unwrap()
+            .get(host)
+            .map(|shapes| shapes.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+pub(crate) fn year_in(text: &str) -> Option<i32> {
+    YEAR.captures(text).and_then(|c| c[1].parse().ok())
+}
+
+/// Host-relative path with years, the months or quarters after them and
+/// HT/NT variants replaced by placeholders, so `netzentgelte-2022-ht.pdf` and
+/// `netzentgelte-2023-nt.pdf` share a shape
+pub(crate) fn url_shape(url: &Url) -> String {
+    let path = url.path().to_lowercase();
+    let path = YEAR.replace_all(&path, |c: &regex::Captures| c[0].replace(&c[1], "{year}"));
+    let path = MONTH.replace_all(&path, |c: &regex::Captures| c[0].replacen(&c[1], "{month}", 1));
+    let path = QUARTER.replace_all(&path, |c: &regex::Captures| c[0].replacen(&c[1], "q{quarter}", 1));
+    VARIANT
+        .replace_all(&path, |c: &regex::Captures| {
+            c[0].replace(&c[1], "{variant}")
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use dno_core::crawl_request::CrawlConstraintsPlan;
+
+    /// Default concurrency without rate limiting, so tests run at full speed
+    fn unthrottled() -> CrawlLimits {
+        CrawlLimits { requests_per_second: 0.0, ..CrawlLimits::default() }
+    }
+
+    struct TariffPage;
+
+    #[async_trait]
+    impl HttpTransport for TariffPage {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            assert_eq!(request.url, "https://netze.example/netzentgelte/2022");
+            let body = r#"
+                <h1>Netzentgelte 2022</h1>
+                <a href="/files/preisblatt-netzentgelte-2021.pdf">Preisblatt 2021</a>
+                <a href="/files/preisblatt-netzentgelte-2022.pdf">Preisblatt 2022</a>
+                <a href="/files/preisblatt-netzentgelte-2023.pdf">Preisblatt 2023</a>
+                <a href="/files/preisblatt-netzentgelte-2022-ht.pdf">Hochtarif 2022</a>
+                <a href="/presse/2022-jahresbilanz">Jahresbilanz 2022</a>
+                <a href="/impressum">Impressum</a>
+            "#;
+            Ok(HttpResponse {
+                status: 200,
+                headers: vec![],
+                body: body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_years_around_a_hit_are_discovered() {
+        let crawler = AdaptiveCrawler::new(Arc::new(TariffPage), unthrottled());
+        crawler.record_success("https://netze.example/files/preisblatt-netzentgelte-2020.pdf");
+
+        let related = crawler
+            .discover_related("https://netze.example/netzentgelte/2022")
+            .await
+            .unwrap();
+
+        let mut years: Vec<i32> = related
+            .iter()
+            .filter(|doc| doc.url.contains("preisblatt-netzentgelte-20") && doc.variant.is_none())
+            .filter_map(|doc| doc.year)
+            .collect();
+        years.sort_unstable();
+        assert_eq!(years, vec![2021, 2022, 2023]);
+        assert!(related
+            .iter()
+            .all(|doc| doc.learned_pattern || doc.variant.is_some()));
+
+        let variant = related.iter().find(|doc| doc.variant.is_some()).unwrap();
+        assert_eq!(
+            (variant.variant.as_deref(), variant.reason),
+            (Some("ht"), RelatedReason::SameYear)
+        );
+        assert!(!related
+            .iter()
+            .any(|doc| doc.url.contains("impressum") || doc.url.contains("jahresbilanz")));
+
+        // Everything found is queued, best first
+        assert_eq!(crawler.queued(), related.len());
+        assert_eq!(crawler.next_url().unwrap().priority, related[0].score);
+    }
+
+    /// Fails any fetch: planning must not touch the network
+    struct Offline;
+
+    #[async_trait]
+    impl HttpTransport for Offline {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            panic!("planning fetched {}", request.url);
+        }
+    }
+
+    fn netze_bw(mode: &str) -> CrawlContext {
+        let now = chrono::Utc::now();
+        CrawlContext {
+            dno: dno_core::Dno {
+                id: Uuid::new_v4(),
+                slug: "netze-bw".to_string(),
+                name: "Netze BW".to_string(),
+                official_name: None,
+                description: None,
+                region: None,
+                website: Some("www.netze-bw.de".to_string()),
+                created_at: now,
+                updated_at: now,
+            },
+            year: 2024,
+            data_type: "netzentgelte".to_string(),
+            mode: mode.to_string(),
+            constraints: CrawlConstraintsPlan { max_depth: 2, max_pages: 50, timeout_secs: 300 },
+            ignore_robots: false,
+            bypass_http_cache: false,
+            http_cache_ttl_secs: None,
+            start_urls: Vec::new(),
+            strategy_order: None,
+            preferred_content_type: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_uses_stored_domain_and_strategy_order() {
+        let crawler = AdaptiveCrawler::new(Arc::new(Offline), unthrottled()).with_strategies(vec![
+            CrawlStrategy::LearnedPatterns,
+            CrawlStrategy::Search,
+            CrawlStrategy::SiteNavigation,
+        ]);
+        crawler.record_success("https://www.netze-bw.de/files/preisblatt-netzentgelte-2022.pdf");
+
+        let context = netze_bw("discovery");
+        let plan = crawler.plan(&context);
+        assert_eq!(
+            plan.strategies,
+            [CrawlStrategy::LearnedPatterns, CrawlStrategy::Search, CrawlStrategy::SiteNavigation]
+        );
+        let seeds: Vec<(&str, SeedSource)> = plan.seeds.iter().map(|seed| (seed.value.as_str(), seed.source)).collect();
+        assert_eq!(
+            seeds,
+            [
+                ("https://www.netze-bw.de/files/preisblatt-netzentgelte-2024.pdf", SeedSource::LearnedPattern),
+                ("https://www.netze-bw.de/", SeedSource::DnoWebsite),
+            ]
+        );
+        // One learned URL, the website and two levels of links from it
+        assert_eq!(plan.estimated_requests, 22);
+
+        let targeted = crawler.plan(&CrawlContext { mode: "targeted".to_string(), ..context });
+        assert_eq!(targeted.strategies, [CrawlStrategy::LearnedPatterns]);
+        assert_eq!(targeted.seeds.len(), 1);
+    }
+
+    #[test]
+    fn test_plan_applies_the_dno_override() {
+        let overrides = DnoOverrides::from_toml(
+            r#"
+            [dno_overrides.netze-bw]
+            mode = "targeted"
+            start_urls = ["https://www.netze-bw.de/netzentgelte-strom"]
+            strategy_order = ["known_sources", "learned_patterns"]
+            "#,
+        )
+        .unwrap();
+        let crawler = AdaptiveCrawler::new(Arc::new(Offline), unthrottled()).with_overrides(Arc::new(overrides));
+
+        let plan = crawler.plan(&netze_bw("discovery"));
+        assert_eq!(plan.mode, "targeted");
+        assert_eq!(plan.strategies, [CrawlStrategy::KnownSources, CrawlStrategy::LearnedPatterns]);
+        assert_eq!(plan.seeds[0].value, "https://www.netze-bw.de/netzentgelte-strom");
+        assert_eq!(plan.seeds[0].source, SeedSource::StartUrl);
+    }
+
+    #[test]
+    fn test_confidence_breakdown_sums_to_the_score() {
+        let weights = ConfidenceWeights::parse("url_count=1, files=1, data_richness=2").unwrap();
+        let crawler = AdaptiveCrawler::new(Arc::new(Offline), unthrottled()).with_confidence_weights(weights);
+        let mut result = CrawlResult::new(&netze_bw("discovery"));
+        result.processed_urls = (0..5).map(|page| format!("https://www.netze-bw.de/seite-{}", page)).collect();
+        result.failed_urls = vec![result.processed_urls[4].clone()];
+        for (file, year) in [("netzentgelte-2024.pdf", 2024), ("hlzf-2024.pdf", 2024), ("netzentgelte-2023.pdf", 2023)] {
+            let url = format!("https://www.netze-bw.de/files/{}", file);
+            result.documents.push(FoundDocument { url, year: Some(year), file_id: None });
+        }
+
+        let breakdown = crawler.calculate_success_confidence(&result);
+        assert_eq!(
+            (breakdown.url_count_factor, breakdown.file_factor, breakdown.nav_success_rate),
+            (0.5, 1.0, 0.8)
+        );
+        assert!((breakdown.data_richness - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            breakdown.weights,
+            ConfidenceWeights { url_count: 0.25, files: 0.25, data_richness: 0.5, nav_success: 0.0 }
+        );
+        let sum: f64 = breakdown.contributions().iter().sum();
+        assert!((sum - breakdown.score).abs() < 1e-9);
+        assert!((breakdown.score - (0.125 + 0.25 + 1.0 / 3.0)).abs() < 1e-9);
+        // Same input, same score
+        assert_eq!(crawler.calculate_success_confidence(&result), breakdown);
+
+        let defaults = AdaptiveCrawler::new(Arc::new(Offline), unthrottled())
+            .with_confidence_weights(ConfidenceWeights::default())
+            .calculate_success_confidence(&result);
+        assert!((defaults.contributions().iter().sum::<f64>() - defaults.score).abs() < 1e-9);
+        assert_eq!(crawler.calculate_success_confidence(&CrawlResult::new(&netze_bw("discovery"))).score, 0.0);
+        assert_eq!(ConfidenceWeights::parse("files=2,links=1"), None);
+        assert_eq!(ConfidenceWeights::parse("files=0"), None);
+    }
+
+    #[test]
+    fn test_monthly_archive_pattern_plans_every_month_of_the_year() {
+        let crawler =
+            AdaptiveCrawler::new(Arc::new(Offline), unthrottled()).with_strategies(vec![CrawlStrategy::LearnedPatterns]);
+        crawler.record_success("https://www.netze-bw.de/archiv/2023/03/preisblatt.pdf");
+        crawler.record_success("https://www.netze-bw.de/hlzf/2023-Q2.pdf");
+
+        let plan = crawler.plan(&netze_bw("targeted"));
+        let mut seeds: Vec<&str> = plan.seeds.iter().map(|seed| seed.value.as_str()).collect();
+        seeds.sort_unstable();
+        let mut expected: Vec<String> = (1..=12)
+            .map(|month| format!("https://www.netze-bw.de/archiv/2024/{:02}/preisblatt.pdf", month))
+            .chain((1..=4).map(|quarter| format!("https://www.netze-bw.de/hlzf/2024-q{}.pdf", quarter)))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(seeds, expected);
+    }
+
+    /// A DNO homepage linking two price sheets and an archive page that never loads
+    struct StuckArchive;
+
+    #[async_trait]
+    impl HttpTransport for StuckArchive {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let body = match request.url.as_str() {
+                "https://www.netze-bw.de/" => r#"
+                    <a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>
+                    <a href="/files/preisblatt-2023.pdf">Preisblatt 2023</a>
+                    <a href="/netzentgelte-archiv">Netzentgelte Archiv</a>
+                "#,
+                "https://www.netze-bw.de/netzentgelte-archiv" => std::future::pending().await,
+                _ => "%PDF-1.7 Preisblatt",
+            };
+            Ok(HttpResponse { status: 200, headers: vec![], body: body.as_bytes().to_vec() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_crawl_returns_what_it_processed() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let crawler = AdaptiveCrawler::new(Arc::new(StuckArchive), unthrottled())
+            .with_strategies(vec![CrawlStrategy::SiteNavigation])
+            .with_sources(sources.clone());
+
+        let result = crawler.run_discovery(&netze_bw("discovery"), Duration::from_millis(300)).await;
+
+        assert_eq!(result.completion, CrawlCompletion::TimedOut);
+        assert!(result.reason.as_deref().unwrap().contains("netzentgelte-archiv"));
+        let mut processed = result.processed_urls.clone();
+        processed.sort();
+        assert_eq!(
+            processed,
+            [
+                "https://www.netze-bw.de/",
+                "https://www.netze-bw.de/files/preisblatt-2023.pdf",
+                "https://www.netze-bw.de/files/preisblatt-2024.pdf",
+            ]
+        );
+        // Both price sheets were stored before the crawl returned
+        assert_eq!(result.documents.len(), 2);
+        assert!(result.documents.iter().all(|document| document.file_id.is_some()));
+        assert_eq!(sources.list_files().len(), 2);
+    }
+
+    /// A homepage listing ten price sheets, every one of which answers 503
+    #[derive(Default)]
+    struct FlakySite {
+        fetches: Mutex<HashMap<String, u32>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for FlakySite {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            *self.fetches.lock().unwrap().entry(request.url.clone()).or_default() += 1;
+            if request.url == "https://www.netze-bw.de/" {
+                let links: String = (2015..2025)
+                    .map(|year| format!(r#"<a href="/files/preisblatt-{}.pdf">Preisblatt {}</a>"#, year, year))
+                    .collect();
+                return Ok(HttpResponse { status: 200, headers: vec![], body: links.into_bytes() });
+            }
+            Ok(HttpResponse { status: 503, headers: vec![], body: Vec::new() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_urls_stop_retrying_once_session_budget_is_spent() {
+        let transport = Arc::new(FlakySite::default());
+        let crawler = AdaptiveCrawler::new(transport.clone(), unthrottled())
+            .with_strategies(vec![CrawlStrategy::SiteNavigation])
+            .with_retry_limits(RetryLimits { per_url: 2, per_session: 5 });
+
+        let result = crawler.run_discovery(&netze_bw("discovery"), Duration::from_secs(10)).await;
+
+        assert_eq!(result.completion, CrawlCompletion::Complete);
+        assert_eq!(result.retries, 5);
+        assert_eq!(result.failed_urls.len(), 10);
+        let fetches = transport.fetches.lock().unwrap();
+        let documents: Vec<u32> = fetches.iter().filter(|(url, _)| url.ends_with(".pdf")).map(|(_, n)| *n).collect();
+        // One fetch per document plus the five retries of the session, none above the per-URL limit
+        assert_eq!(documents.len(), 10);
+        assert_eq!(documents.iter().sum::<u32>(), 15);
+        assert!(documents.iter().all(|&n| n <= 3));
+    }
+
+    /// A homepage linking a public price sheet and one behind a login
+    struct LoginWall;
+
+    #[async_trait]
+    impl HttpTransport for LoginWall {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let (status, body) = match request.url.as_str() {
+                "https://www.netze-bw.de/" => (
+                    200,
+                    r#"<a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>
+                       <a href="/intern/netzentgelte-2024.pdf">Netzentgelte 2024</a>"#,
+                ),
+                url if url.contains("/intern/") => (403, "Zugriff verweigert"),
+                _ => (200, "%PDF-1.7 Preisblatt"),
+            };
+            Ok(HttpResponse { status, headers: vec![], body: body.as_bytes().to_vec() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_access_denied_urls_are_handed_to_manual_review() {
+        let crawler = AdaptiveCrawler::new(Arc::new(LoginWall), unthrottled())
+            .with_strategies(vec![CrawlStrategy::SiteNavigation]);
+
+        let result = crawler.run_discovery(&netze_bw("discovery"), Duration::from_secs(10)).await;
+
+        let denied = "https://www.netze-bw.de/intern/netzentgelte-2024.pdf";
+        assert_eq!(result.failed_urls, [denied]);
+        // Not retried: another fetch would be denied just the same
+        assert_eq!(result.retries, 0);
+        assert_eq!(result.manual_review.len(), 1);
+        let review = &result.manual_review[0];
+        assert_eq!(review.url, denied);
+        assert!(review.reason.contains("HTTP 403"), "{}", review.reason);
+        assert_eq!(review.context["error_kind"], "http");
+        assert_eq!(review.context["depth"], 1);
+        assert_eq!(result.documents.len(), 1);
+    }
+
+    /// A site whose robots.txt keeps crawlers out of `/private`
+    #[derive(Default)]
+    struct PrivateArea {
+        fetched: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for PrivateArea {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.fetched.lock().unwrap().push(request.url.clone());
+            let body = match request.url.as_str() {
+                "https://www.netze-bw.de/robots.txt" => "User-agent: *\nDisallow: /private\n",
+                "https://www.netze-bw.de/" => r#"
+                    <a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>
+                    <a href="/private/preisblatt-2024-entwurf.pdf">Preisblatt 2024 Entwurf</a>
+                    <a href="/private/netzentgelte-2023.pdf">Netzentgelte 2023</a>
+                "#,
+                _ => "%PDF-1.7 Preisblatt",
+            };
+            Ok(HttpResponse { status: 200, headers: vec![], body: body.as_bytes().to_vec() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_paths_never_enter_the_queue() {
+        let transport = Arc::new(PrivateArea::default());
+        let robots = Arc::new(RobotsCache::new(transport.clone(), "DNO-Data-Gatherer/0.0.1"));
+        let crawler = AdaptiveCrawler::new(transport.clone(), unthrottled())
+            .with_strategies(vec![CrawlStrategy::SiteNavigation])
+            .with_robots(robots);
+
+        let related = crawler.discover_related("https://www.netze-bw.de/").await.unwrap();
+        assert!(related.iter().all(|doc| !doc.url.contains("/private")));
+        let mut queued = Vec::new();
+        while let Some(next) = crawler.next_url() {
+            queued.push(next.url);
+        }
+        assert_eq!(queued, ["https://www.netze-bw.de/files/preisblatt-2024.pdf"]);
+
+        let result = crawler.run_discovery(&netze_bw("discovery"), Duration::from_secs(10)).await;
+        assert!(result.processed_urls.iter().all(|url| !url.contains("/private")));
+        let fetched = transport.fetched.lock().unwrap().clone();
+        assert!(fetched.iter().all(|url| !url.contains("/private")));
+        // Fetched once for the whole crawl
+        assert_eq!(fetched.iter().filter(|url| url.ends_with("/robots.txt")).count(), 1);
+
+        // Our own sites can opt out
+        transport.fetched.lock().unwrap().clear();
+        let own_site = CrawlContext { ignore_robots: true, ..netze_bw("discovery") };
+        let result = crawler.run_discovery(&own_site, Duration::from_secs(10)).await;
+        assert!(result.processed_urls.iter().any(|url| url.contains("/private")));
+    }
+
+    /// Serves every URL slowly and records the peak number of requests in
+    /// flight, overall and per host
+    #[derive(Default)]
+    struct SlowHosts {
+        in_flight: Mutex<(usize, HashMap<String, usize>)>,
+        peak: Mutex<(usize, HashMap<String, usize>)>,
+    }
+
+    impl SlowHosts {
+        fn enter(&self, host: &str, step: isize) {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight.0 = in_flight.0.checked_add_signed(step).unwrap();
+            let on_host = in_flight.1.entry(host.to_string()).or_default();
+            *on_host = on_host.checked_add_signed(step).unwrap();
+            let on_host = *on_host;
+
+            let total = in_flight.0;
+            let mut peak = self.peak.lock().unwrap();
+            peak.0 = peak.0.max(total);
+            let host_peak = peak.1.entry(host.to_string()).or_default();
+            *host_peak = (*host_peak).max(on_host);
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for SlowHosts {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let host = Url::parse(&request.url)?.host_str().unwrap().to_string();
+            self.enter(&host, 1);
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            self.enter(&host, -1);
+            Ok(HttpResponse { status: 200, headers: vec![], body: b"%PDF-1.7".to_vec() })
+        }
+    }
+
+    fn queued(url: String) -> QueuedUrl {
+        QueuedUrl { url, priority: 1.0, depth: 0, reason: "test".to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_burst_of_mixed_host_urls_stays_within_limits() {
+        let transport = Arc::new(SlowHosts::default());
+        let limits = CrawlLimits { max_concurrent: 4, max_per_host: 2, ..unthrottled() };
+        let crawler = AdaptiveCrawler::new(transport.clone(), limits);
+
+        let urls: Vec<QueuedUrl> = (1..=6)
+            .flat_map(|n| {
+                ["www.netze-bw.de", "www.bayernwerk-netz.de", "www.westnetz.de"]
+                    .map(|host| queued(format!("https://{}/files/preisblatt-{}.pdf", host, 2018 + n)))
+            })
+            .collect();
+        let outcomes = futures::future::join_all(urls.iter().map(|next| crawler.process_url(next, None, CacheDirective::default(), None))).await;
+        assert!(outcomes.iter().all(|outcome| matches!(outcome, Ok(Some(_)))));
+
+        let peak = transport.peak.lock().unwrap();
+        assert!(peak.0 > 2, "requests should overlap across hosts, peak was {}", peak.0);
+        assert!(peak.0 <= 4, "global limit exceeded: {}", peak.0);
+        assert!(peak.1.values().all(|&on_host| on_host <= 2), "per-host limit exceeded: {:?}", peak.1);
+    }
+
+    #[tokio::test]
+    async fn test_host_rate_is_limited_after_the_burst() {
+        let limits = CrawlLimits { max_concurrent: 10, max_per_host: 10, requests_per_second: 20.0, burst: 2 };
+        let crawler = AdaptiveCrawler::new(Arc::new(SlowHosts::default()), limits);
+
+        let started = Instant::now();
+        let urls: Vec<QueuedUrl> =
+            (2019..2025).map(|year| queued(format!("https://www.netze-bw.de/files/preisblatt-{}.pdf", year))).collect();
+        futures::future::join_all(urls.iter().map(|next| crawler.process_url(next, None, CacheDirective::default(), None))).await;
+
+        // Two from the burst, then four more at 50 ms intervals
+        assert!(started.elapsed() >= Duration::from_millis(190), "took only {:?}", started.elapsed());
+    }
+
+    /// A homepage listing eight price sheets; while `hang_after` is set, every
+    /// fetch after that many never answers, like a crawler process dying mid-crawl
+    #[derive(Default)]
+    struct Interruptible {
+        fetched: Mutex<Vec<String>>,
+        hang_after: Mutex<Option<usize>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for Interruptible {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let fetched = {
+                let mut fetched = self.fetched.lock().unwrap();
+                fetched.push(request.url.clone());
+                fetched.len()
+            };
+            if self.hang_after.lock().unwrap().is_some_and(|n| fetched > n) {
+                std::future::pending::<()>().await;
+            }
+            let body = if request.url == "https://www.netze-bw.de/" {
+                (2017..2025)
+                    .map(|year| format!(r#"<a href="/files/preisblatt-{}.pdf">Preisblatt {}</a>"#, year, year))
+                    .collect()
+            } else {
+                "%PDF-1.7 Preisblatt".to_string()
+            };
+            Ok(HttpResponse { status: 200, headers: vec![], body: body.into_bytes() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resumed_crawl_processes_no_url_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoints = Arc::new(CheckpointStore::new(dir.path()).every(1));
+        let transport = Arc::new(Interruptible { hang_after: Mutex::new(Some(4)), ..Default::default() });
+        let session_id = Uuid::new_v4();
+
+        // The homepage and three price sheets, then the fifth fetch hangs and the crawl is dropped
+        let crawler = AdaptiveCrawler::new(transport.clone(), unthrottled())
+            .with_strategies(vec![CrawlStrategy::SiteNavigation])
+            .with_checkpoints(checkpoints.clone());
+        let context = netze_bw("discovery");
+        let crawl = crawler.execute_checkpointed_crawl(session_id, &context);
+        assert!(tokio::time::timeout(Duration::from_millis(300), crawl).await.is_err());
+        let saved = checkpoints.load(session_id).unwrap();
+        assert_eq!(saved.result.processed_urls.len(), 4);
+        assert_eq!(saved.queue.len(), 5);
+        let interrupted = transport.fetched.lock().unwrap().last().cloned().unwrap();
+
+        // A new crawler, as after a restart
+        *transport.hang_after.lock().unwrap() = None;
+        let crawler = AdaptiveCrawler::new(transport.clone(), unthrottled())
+            .with_strategies(vec![CrawlStrategy::SiteNavigation])
+            .with_checkpoints(checkpoints.clone());
+        let result = crawler.resume(session_id).await.unwrap();
+
+        assert_eq!(result.completion, CrawlCompletion::Complete);
+        assert_eq!(result.processed_urls.len(), 9);
+        let unique: BTreeSet<&String> = result.processed_urls.iter().collect();
+        assert_eq!(unique.len(), 9, "processed twice: {:?}", result.processed_urls);
+        assert_eq!(result.documents.len(), 8);
+        // Only the fetch cut off by the interruption was repeated
+        let fetched = transport.fetched.lock().unwrap().clone();
+        assert_eq!(fetched.len(), 10);
+        assert!(fetched.iter().all(|url| fetched.iter().filter(|u| *u == url).count() == 1 || *url == interrupted));
+        // A finished crawl leaves no checkpoint behind
+        assert!(checkpoints.load(session_id).is_err());
+        assert!(crawler.resume(session_id).await.is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::content::ContentType;
+use crate::extraction::Document;
+use anyhow::{bail, Context, Result};
+use std::io::{Cursor, Read};
+use tracing::{debug, warn};
+
+/// Bounds on what unpacking an archive may produce, as a zip bomb guard
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveLimits {
+    /// Total uncompressed bytes across all members, including nested archives
+    pub max_uncompressed_bytes: u64,
+    pub max_members: usize,
+    /// Archives inside archives deeper than this are not unpacked
+    pub max_depth: usize,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_uncompressed_bytes: 200 * 1024 * 1024,
+            max_members: 500,
+            max_depth: 2,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    /// `CRAWLER_ARCHIVE_MAX_BYTES`, falling back to 200 MiB
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_uncompressed_bytes: std::env::var("CRAWLER_ARCHIVE_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_uncompressed_bytes),
+            ..defaults
+        }
+    }
+}
+
+/// Unpack a zip document into its member documents, recursing into nested
+/// zips. Members get the URL `<archive url>!/<member path>` and keep the
+/// archive's URL in `Document::parent_url`. Directories and members that are
+/// neither classifiable nor text are skipped.
+pub fn unpack_archive(archive: &Document, limits: &ArchiveLimits) -> Result<Vec<Document>> {
+    let mut budget = limits.max_uncompressed_bytes;
+    let mut members = Vec::new();
+    unpack_into(archive, limits, 0, &mut budget, &mut members)?;
+    Ok(members)
+}
+
+fn unpack_into(
+    archive: &Document,
+    limits: &ArchiveLimits,
+    depth: usize,
+    budget: &mut u64,
+    members: &mut Vec<Document>,
+) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(archive.bytes.as_slice()))
+        .with_context(|| format!("{} is not a readable zip archive", archive.url))?;
+
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(name) = file.enclosed_name().map(|path| path.to_string_lossy().replace('\\', "/")) else {
+            warn!("Skipping archive member with unsafe path in {}", archive.url);
+            continue;
+        };
+        if members.len() >= limits.max_members {
+            bail!("{} has more than {} members", archive.url, limits.max_members);
+        }
+        // The declared size can lie, so the read itself is capped as well
+        if file.size() > *budget {
+            bail!("{} exceeds the {} byte uncompressed size limit", archive.url, limits.max_uncompressed_bytes);
+        }
+        let mut bytes = Vec::new();
+        (&mut file).take(*budget + 1).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > *budget {
+            bail!("{} exceeds the {} byte uncompressed size limit", archive.url, limits.max_uncompressed_bytes);
+        }
+        *budget -= bytes.len() as u64;
+
+        let content_type = ContentType::detect(&name, None, &bytes);
+        let member = Document {
+            url: format!("{}!/{}", archive.url, name),
+            content_type: Some(content_type.mime().to_string()),
+            bytes,
+            text: None,
+            parent_url: Some(archive.parent_url.clone().unwrap_or_else(|| archive.url.clone())),
+        };
+
+        match content_type {
+            ContentType::Archive if depth + 1 < limits.max_depth => {
+                unpack_into(&member, limits, depth + 1, budget, members)?;
+            }
+            ContentType::Archive => warn!("Not unpacking {}: nested deeper than {}", member.url, limits.max_depth),
+            ContentType::Unknown => debug!("Skipping unclassified archive member {}", member.url),
+            _ => members.push(member),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_with(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, bytes) in files {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn archive(bytes: Vec<u8>) -> Document {
+        Document {
+            url: "https://netze.example/preisblaetter-2024.zip".to_string(),
+            content_type: Some("application/zip".to_string()),
+            bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_zip_with_pdf_and_csv_yields_two_children() {
+        let bytes = zip_with(&[
+            ("preisblatt/netzentgelte-2024.pdf", b"%PDF-1.7 ..."),
+            ("preisblatt/hlzf-2024.csv", b"Saison;Beginn;Ende\nWinter;17:00;19:30\n"),
+        ]);
+
+        let members = unpack_archive(&archive(bytes), &ArchiveLimits::default()).unwrap();
+        assert_eq!(members.len(), 2);
+
+        assert_eq!(members[0].url, "https://netze.example/preisblaetter-2024.zip!/preisblatt/netzentgelte-2024.pdf");
+        assert_eq!(members[0].content_type.as_deref(), Some("application/pdf"));
+        assert_eq!(members[1].content_type.as_deref(), Some("text/csv"));
+        for member in &members {
+            assert_eq!(member.parent_url.as_deref(), Some("https://netze.example/preisblaetter-2024.zip"));
+        }
+    }
+
+    #[test]
+    fn test_uncompressed_size_cap_rejects_bombs() {
+        let bytes = zip_with(&[("zeros.csv", &vec![b'0'; 64 * 1024])]);
+        let limits = ArchiveLimits { max_uncompressed_bytes: 1024, ..Default::default() };
+        assert!(unpack_archive(&archive(bytes), &limits).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::adaptive::CrawlResult;
+use crate::queue::CrawlQueue;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use dno_core::crawl_plan::CrawlContext;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// URLs processed between two checkpoints when `CRAWLER_CHECKPOINT_EVERY` is unset
+const DEFAULT_EVERY: usize = 10;
+
+/// Where a discovery crawl was: its context, the queue with every URL seen
+/// so far, and the result up to that point
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrawlCheckpoint {
+    pub session_id: Uuid,
+    /// The context with the DNO's override already merged in
+    pub context: CrawlContext,
+    pub queue: CrawlQueue,
+    pub result: CrawlResult,
+    pub saved_at: DateTime<Utc>,
+}
+
+/// Checkpoints of running discovery crawls, one JSON file per session
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    dir: PathBuf,
+    every: usize,
+}
+
+impl CheckpointStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), every: DEFAULT_EVERY }
+    }
+
+    /// `CRAWLER_CHECKPOINT_DIR` (default `./storage/checkpoints`) and
+    /// `CRAWLER_CHECKPOINT_EVERY`
+    pub fn from_env() -> Self {
+        let dir = std::env::var("CRAWLER_CHECKPOINT_DIR").unwrap_or_else(|_| "./storage/checkpoints".to_string());
+        let every = std::env::var("CRAWLER_CHECKPOINT_EVERY")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_EVERY);
+        Self::new(dir).every(every)
+    }
+
+    /// Save after every `urls` processed URLs. After a crash, the URLs since
+    /// the last checkpoint are crawled again.
+    pub fn every(mut self, urls: usize) -> Self {
+        self.every = urls.max(1);
+        self
+    }
+
+    /// Whether a crawl that has processed `processed` URLs is due a checkpoint
+    pub fn is_due(&self, processed: usize) -> bool {
+        processed.is_multiple_of(self.every)
+    }
+
+    fn path(&self, session_id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", session_id))
+    }
+
+    /// Write the checkpoint. The file is replaced atomically, so a crash
+    /// mid-write keeps the previous checkpoint.
+    pub fn save(&self, checkpoint: &CrawlCheckpoint) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create checkpoint directory {}", self.dir.display()))?;
+        let path = self.path(checkpoint.session_id);
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec(checkpoint)?)
+            .with_context(|| format!("Failed to write checkpoint {}", temporary.display()))?;
+        fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+
+    pub fn load(&self, session_id: Uuid) -> Result<CrawlCheckpoint> {
+        let path = self.path(session_id);
+        let json = fs::read(&path).with_context(|| format!("No checkpoint for session {}", session_id))?;
+        serde_json::from_slice(&json).with_context(|| format!("Corrupt checkpoint {}", path.display()))
+    }
+
+    /// Drop the checkpoint of a crawl that needs no resuming
+    pub fn remove(&self, session_id: Uuid) -> Result<()> {
+        match fs::remove_file(self.path(session_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,30 +1,15 @@
 use clap::Subcommand;
-use chrono::Datelike;
-use crate::ai_agent::IntelligentGatheringAgent;
-use crate::evaluation_engine::DataEvaluationEngine;
+use std::path::PathBuf;
+use std::sync::Arc;
+use crawler::http::{HttpCache, HttpCacheConfig, HttpClientConfig, HttpTransport, ProxyConfig, TransportMode};
+use dno_core::cache::{CacheLayer, MemoryCache, RedisCache};
+use dno_core::repository::DnoRepository;
+use crawler::reverse::DownloadConfig;
+use crawler::search::SearchService;
+use crawler::reverse_cli::ReverseCrawlArgs;
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// AI-driven intelligent storage gathering (primary method)
-    AiGather {
-        /// DNO name
-        dno: String,
-        /// Data types to gather (comma-separated: netzentgelte,hlzf,contact)
-        #[arg(long, default_value = "netzentgelte")]
-        data_types: String,
-        /// Target years (comma-separated)
-        #[arg(long)]
-        years: Option<String>,
-        /// Return structured JSON output
-        #[arg(long)]
-        json: bool,
-        /// Maximum time in seconds
-        #[arg(long, default_value = "120")]
-        max_time: u64,
-        /// Priority mode (speed, quality, completeness)
-        #[arg(long, default_value = "quality")]
-        priority: String,
-    },
     /// Simple search for testing SearXNG connectivity
     Search {
         /// Search query
@@ -32,126 +17,307 @@ pub enum Commands {
         /// Return structured JSON output
         #[arg(long)]
         json: bool,
+        /// Route this crawl through a proxy (overrides HTTP_PROXY/HTTPS_PROXY/ALL_PROXY)
+        #[arg(long)]
+        proxy: Option<String>,
+    },
+    /// Look for a DNO's documents of earlier years by reconstructing URLs
+    /// from the ones it publishes now
+    ReverseCrawl(ReverseCrawlArgs),
+    /// Upsert the committed list of known German DNOs into the database
+    Seed {
+        /// Database to seed (defaults to DATABASE_URL)
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+    /// Upsert DNOs from the Bundesnetzagentur operator list (CSV)
+    ImportDnos {
+        /// CSV export with a name column and optional slug, region and website
+        #[arg(long)]
+        file: PathBuf,
+        /// Database to import into (defaults to DATABASE_URL)
+        #[arg(long)]
+        database_url: Option<String>,
     },
 }
 
-pub async fn handle_search(query: String, _json_output: bool) -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔍 Testing SearXNG connectivity with query: {}", query);
-    
-    // Use SearXNG instance - check for environment variable or use default localhost
-    let searxng_url = std::env::var("SEARXNG_URL")
-        .unwrap_or_else(|_| "http://localhost:8080".to_string());
-    
-    // Simple connectivity test
-    let client = reqwest::Client::new();
-    let search_url = format!("{}/search", searxng_url);
-    
-    let response = client
-        .get(&search_url)
-        .query(&[("q", &query), ("format", &"json".to_string())])
-        .send()
-        .await?;
-    
-    if response.status().is_success() {
-        let results: serde_json::Value = response.json().await?;
-        println!("✅ SearXNG connectivity test successful");
-        println!("📊 Found {} results", results["results"].as_array().map(|a| a.len()).unwrap_or(0));
-    } else {
-        println!("❌ SearXNG connectivity test failed: {}", response.status());
-    }
-    
+async fn connect(database_url: Option<String>) -> Result<sqlx::PgPool, Box<dyn std::error::Error>> {
+    let url = match database_url {
+        Some(url) => url,
+        None => std::env::var("DATABASE_URL").map_err(|_| "DATABASE_URL is required (or pass --database-url)")?,
+    };
+    let pool = dno_core::database::create_pool(&dno_core::DatabaseConfig {
+        url,
+        max_connections: 2,
+        min_connections: 1,
+        connect_timeout: 30,
+        idle_timeout: 60,
+    })
+    .await?;
+    Ok(pool)
+}
+
+pub async fn handle_seed(database_url: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = connect(database_url).await?;
+    let report = dno_core::seed::seed_dnos(&pool).await?;
+    println!("🌱 Seeded DNOs: {} inserted, {} updated", report.inserted, report.updated);
+    Ok(())
+}
+
+pub async fn handle_import_dnos(file: PathBuf, database_url: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let pool = connect(database_url).await?;
+
+    // With APP_REDIS_URL set, the API's cached DNO lists are invalidated too
+    let report = match dno_core::RedisCacheConfig::from_env() {
+        Ok(config) => import(DnoRepository::new(pool, Arc::new(RedisCache::new(config).await?)), &text).await?,
+        Err(_) => import(DnoRepository::new(pool, Arc::new(MemoryCache::new())), &text).await?,
+    };
+    println!(
+        "📥 Imported DNOs: {} inserted, {} updated, {} unchanged",
+        report.inserted, report.updated, report.unchanged
+    );
     Ok(())
 }
 
-pub async fn handle_ai_gather(
-    dno: String,
-    data_types: String,
-    years: Option<String>,
+async fn import<C: CacheLayer>(
+    repo: DnoRepository<C>,
+    text: &str,
+) -> Result<dno_core::repository::UpsertReport, Box<dyn std::error::Error>> {
+    Ok(crawler::dno_import::import_dnos(&repo, text).await?)
+}
+
+/// Transport for CLI commands: `CRAWLER_HTTP_MODE` selects live, record or
+/// replay, and `CRAWLER_HTTP_CACHE` adds the on-disk HTTP cache
+async fn transport(client_config: &HttpClientConfig) -> Result<Arc<dyn HttpTransport>, Box<dyn std::error::Error>> {
+    let mode = TransportMode::from_env()?;
+    let transport = match HttpCacheConfig::from_env() {
+        Some(cache_config) => {
+            let cache = HttpCache::from_config(&cache_config).await?;
+            mode.build_with_cache(client_config, Arc::new(cache))?
+        }
+        None => mode.build(client_config)?,
+    };
+    Ok(transport)
+}
+
+pub async fn handle_reverse_crawl(args: ReverseCrawlArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let storage_path = std::env::var("STORAGE_PATH").unwrap_or_else(|_| "./storage".to_string());
+    let transport = transport(&HttpClientConfig::from_env()).await?;
+
+    let summary = reverse_crawl(&args, transport, &storage_path).await?;
+    println!("{}", summary.trim_end());
+    Ok(())
+}
+
+/// Run `args` over `transport`, storing the documents under `storage_path`
+async fn reverse_crawl(
+    args: &ReverseCrawlArgs,
+    transport: Arc<dyn HttpTransport>,
+    storage_path: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let sources = Arc::new(dno_core::source_manager::SourceManager::open(storage_path)?);
+    Ok(args.run(transport, sources, DownloadConfig::from_env()).await?)
+}
+
+/// Client settings for `search`: `--proxy` replaces the proxies from the environment
+fn search_client_config(proxy: Option<&str>) -> HttpClientConfig {
+    HttpClientConfig {
+        proxy: ProxyConfig::from_env().with_override(proxy),
+        ..HttpClientConfig::from_env()
+    }
+}
+
+pub async fn handle_search(
+    query: String,
     json_output: bool,
-    max_time: u64,
-    priority: String,
+    proxy: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !json_output {
-        println!("🤖 AI-driven storage gathering for: {}", dno);
-        println!("📊 Data types: {}", data_types);
-        println!("⚙️  Priority: {}, Max time: {}s", priority, max_time);
+        println!("🔍 Testing SearXNG connectivity with query: {}", query);
     }
 
-    // Parse storage types
-    let target_data_types: Vec<String> = data_types
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .collect();
-
-    // Parse years or use default
-    let target_years: Vec<i32> = match years {
-        Some(years_str) => years_str
-            .split(',')
-            .filter_map(|s| s.trim().parse().ok())
-            .collect(),
-        None => {
-            let current_year = chrono::Utc::now().year();
-            vec![current_year - 1, current_year, current_year + 1]
+    // Simple connectivity test (CRAWLER_HTTP_MODE selects live, record or replay)
+    let transport = transport(&search_client_config(proxy.as_deref())).await?;
+    let search = SearchService::from_env(transport);
+
+    match search.search(&query, None).await {
+        Ok(results) if json_output => println!("{}", serde_json::to_string_pretty(&results)?),
+        Ok(results) => {
+            println!("✅ SearXNG connectivity test successful");
+            println!("📊 Found {} results (one per host)", results.len());
+            for result in results.iter().take(10) {
+                println!("  {:>6.2}  {}", result.score, result.url);
+            }
         }
-    };
+        Err(e) => println!("❌ SearXNG connectivity test failed: {}", e),
+    }
 
-    if !json_output {
-        println!("📅 Target years: {:?}", target_years);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::Parser;
+    use crawler::http::{build_client, LiveTransport};
+    use sqlx::PgPool;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// `--database-url` for the test database behind `pool`
+    fn database_url(pool: &PgPool) -> String {
+        let mut url = url::Url::parse(&std::env::var("DATABASE_URL").unwrap()).unwrap();
+        url.set_path(pool.connect_options().get_database().unwrap());
+        url.to_string()
     }
 
-    // Initialize AI agent
-    let storage_path = format!("ai_model_{}.json", dno.to_lowercase().replace(" ", "_"));
-    let mut ai_agent = IntelligentGatheringAgent::new(storage_path);
-
-    // Execute AI-driven storage gathering
-    let start_time = std::time::Instant::now();
-    let gathered_data = ai_agent.gather_data_intelligently(
-        &dno,
-        target_data_types.clone(),
-        target_years.clone()
-    ).await?;
-
-    // Evaluate storage quality
-    let mut evaluation_engine = DataEvaluationEngine::new();
-    let evaluation = evaluation_engine.evaluate_gathered_data(
-        &gathered_data,
-        &target_data_types,
-        &dno
-    ).await?;
-
-    let processing_time = start_time.elapsed().as_secs();
-    let ai_metrics = ai_agent.get_performance_metrics();
-
-    if json_output {
-        let result = serde_json::json!({
-            "success": true,
-            "dno": dno,
-            "data_types": target_data_types,
-            "target_years": target_years,
-            "gathered_data": gathered_data,
-            "evaluation": evaluation,
-            "ai_metrics": ai_metrics,
-            "processing_time_seconds": processing_time,
-            "metadata": {
-                "ai_engine": "intelligent_gathering_agent",
-                "crawler_version": "2.0.0-ai",
-                "generated_at": chrono::Utc::now()
-            }
+    async fn dno_count(pool: &PgPool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM dnos").fetch_one(pool).await.unwrap()
+    }
+
+    /// Answers every request with one SearXNG result and hands back the request line
+    async fn searxng_proxy() -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let body = r#"{"results":[{"url":"https://www.netze-bw.de/netzentgelte","title":"Netzentgelte"}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+            request.lines().next().unwrap_or_default().to_string()
         });
-        println!("{}", serde_json::to_string_pretty(&result)?);
-    } else {
-        println!("✅ AI storage gathering completed in {}s", processing_time);
-        println!("📊 Found {} storage fields", gathered_data.len());
-        println!("🎯 Overall evaluation score: {:.2}", evaluation.overall_score);
-        println!("🤖 AI confidence: {:.2}", ai_metrics.get("average_reward").unwrap_or(&0.0));
-        
-        if !evaluation.recommendations.is_empty() {
-            println!("\n💡 Recommendations:");
-            for rec in &evaluation.recommendations {
-                println!("  • {}", rec);
+        (proxy_url, proxy)
+    }
+
+    #[tokio::test]
+    async fn test_search_proxy_flag_routes_the_search() {
+        let (proxy_url, proxy) = searxng_proxy().await;
+        let cli = Cli::try_parse_from(["crawler", "search", "netzentgelte", "--proxy", &proxy_url]).unwrap();
+        let Commands::Search { query, proxy: Some(flag), .. } = cli.command else {
+            panic!("search --proxy was not parsed");
+        };
+
+        let transport = transport(&search_client_config(Some(&flag))).await.unwrap();
+        let results = SearchService::new(transport, "http://searx.example").search(&query, None).await.unwrap();
+
+        assert_eq!(results[0].url, "https://www.netze-bw.de/netzentgelte");
+        assert_eq!(proxy.await.unwrap(), "GET http://searx.example/search?q=netzentgelte&format=json HTTP/1.1");
+    }
+
+    /// A DNO site whose tariff page links the 2024 price sheet; the 2023 sheet
+    /// is only reachable by reconstructing its URL
+    async fn dno_site() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let site = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (content_type, body) = match path.as_str() {
+                    "/" => ("text/html", r#"<a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>"#.to_string()),
+                    "/files/preisblatt-2023.pdf" | "/files/preisblatt-2024.pdf" => ("application/pdf", format!("%PDF-1.7 {}", path)),
+                    _ => ("", String::new()),
+                };
+                let response = if content_type.is_empty() {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        content_type,
+                        body.len(),
+                        body
+                    )
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
             }
-        }
+        });
+        site
     }
 
-    Ok(())
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_reverse_crawl_subcommand_stores_earlier_price_sheets() {
+        let site = dno_site().await;
+        let cli = Cli::try_parse_from(["crawler", "reverse-crawl", "--dno", &site, "--known-years", "2024", "--max-depth", "1"]).unwrap();
+        let Commands::ReverseCrawl(args) = cli.command else {
+            panic!("reverse-crawl was not parsed");
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let storage_path = dir.path().to_str().unwrap();
+
+        let live = LiveTransport::new(build_client(&HttpClientConfig::default()).unwrap()).trust_host("127.0.0.1");
+        let summary = reverse_crawl(&args, Arc::new(live), storage_path).await.unwrap();
+
+        assert!(summary.contains("💾 Stored 1 files, 9 URLs missing or failed"), "{}", summary);
+        // The stored sheet is picked up by the next run over the same storage
+        let sources = dno_core::source_manager::SourceManager::open(storage_path).unwrap();
+        let years: Vec<i32> = sources.list_files().iter().map(|file| file.year).collect();
+        assert_eq!(years, [2023]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_seed_subcommand_upserts_known_dnos(pool: PgPool) {
+        dno_core::test_schema::create(&pool).await;
+        let cli = Cli::try_parse_from(["crawler", "seed", "--database-url", &database_url(&pool)]).unwrap();
+        let Commands::Seed { database_url } = cli.command else {
+            panic!("seed was not parsed");
+        };
+
+        handle_seed(database_url.clone()).await.unwrap();
+        let known = dno_core::seed::known_dnos().unwrap().len() as i64;
+        assert_eq!(dno_count(&pool).await, known);
+
+        // Safe to re-run
+        handle_seed(database_url).await.unwrap();
+        assert_eq!(dno_count(&pool).await, known);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_import_dnos_subcommand_upserts_the_csv(pool: PgPool) {
+        dno_core::test_schema::create(&pool).await;
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("netzbetreiber.csv");
+        std::fs::write(
+            &file,
+            "Netzbetreiber;Bundesland;Internetseite\n\
+             Netze BW GmbH;Baden-Württemberg;https://www.netze-bw.de\n\
+             Westnetz GmbH;Nordrhein-Westfalen;www.westnetz.de\n",
+        )
+        .unwrap();
+        let cli = Cli::try_parse_from([
+            "crawler",
+            "import-dnos",
+            "--file",
+            file.to_str().unwrap(),
+            "--database-url",
+            &database_url(&pool),
+        ])
+        .unwrap();
+        let Commands::ImportDnos { file, database_url } = cli.command else {
+            panic!("import-dnos was not parsed");
+        };
+
+        handle_import_dnos(file.clone(), database_url.clone()).await.unwrap();
+        assert_eq!(dno_count(&pool).await, 2);
+        let website: Option<String> = sqlx::query_scalar("SELECT website FROM dnos WHERE name = 'Westnetz GmbH'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(website.as_deref(), Some("https://www.westnetz.de"));
+
+        // Re-importing the same list adds nothing
+        handle_import_dnos(file, database_url).await.unwrap();
+        assert_eq!(dno_count(&pool).await, 2);
+    }
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Leading bytes of a body looked at when sniffing its type
+pub const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Kind of document behind a URL or archive member, deciding which extractors apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+    Html,
+    Pdf,
+    Csv,
+    Excel,
+    Xml,
+    /// JSON, e.g. an API answer
+    Json,
+    /// PNG, JPEG or TIFF, e.g. a scanned price sheet
+    Image,
+    /// Zip archive bundling several documents
+    Archive,
+    Text,
+    Unknown,
+}
+
+impl ContentType {
+    pub const ALL: [ContentType; 10] = [
+        ContentType::Html,
+        ContentType::Pdf,
+        ContentType::Csv,
+        ContentType::Excel,
+        ContentType::Xml,
+        ContentType::Json,
+        ContentType::Image,
+        ContentType::Archive,
+        ContentType::Text,
+        ContentType::Unknown,
+    ];
+
+    /// Name as written in configs (`pdf`, `excel`, ...)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::Html => "html",
+            ContentType::Pdf => "pdf",
+            ContentType::Csv => "csv",
+            ContentType::Excel => "excel",
+            ContentType::Xml => "xml",
+            ContentType::Json => "json",
+            ContentType::Image => "image",
+            ContentType::Archive => "archive",
+            ContentType::Text => "text",
+            ContentType::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|content| content.as_str() == name.trim())
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ContentType::Html => "text/html",
+            ContentType::Pdf => "application/pdf",
+            ContentType::Csv => "text/csv",
+            ContentType::Excel => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ContentType::Xml => "application/xml",
+            ContentType::Json => "application/json",
+            ContentType::Image => "image/png",
+            ContentType::Archive => "application/zip",
+            ContentType::Text => "text/plain",
+            ContentType::Unknown => "application/octet-stream",
+        }
+    }
+
+    /// From a `Content-Type` header value (parameters such as charset are ignored)
+    pub fn from_mime(mime: &str) -> Self {
+        let mime = mime.split(';').next().unwrap_or_default().trim().to_lowercase();
+        match mime.as_str() {
+            "text/html" | "application/xhtml+xml" => ContentType::Html,
+            "application/pdf" => ContentType::Pdf,
+            "text/csv" => ContentType::Csv,
+            "application/vnd.ms-excel" | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+                ContentType::Excel
+            }
+            "application/xml" | "text/xml" => ContentType::Xml,
+            "application/json" => ContentType::Json,
+            "image/png" | "image/jpeg" | "image/tiff" => ContentType::Image,
+            "application/zip" | "application/x-zip-compressed" => ContentType::Archive,
+            m if m.starts_with("text/") => ContentType::Text,
+            _ => ContentType::Unknown,
+        }
+    }
+
+    /// From the file extension of a URL path or archive member name
+    pub fn from_path(path: &str) -> Self {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "html" | "htm" => ContentType::Html,
+            "pdf" => ContentType::Pdf,
+            "csv" => ContentType::Csv,
+            "xlsx" | "xls" => ContentType::Excel,
+            "xml" => ContentType::Xml,
+            "json" => ContentType::Json,
+            "png" | "jpg" | "jpeg" | "tif" | "tiff" => ContentType::Image,
+            "zip" => ContentType::Archive,
+            "txt" => ContentType::Text,
+            _ => ContentType::Unknown,
+        }
+    }
+
+    /// From the body alone: magic bytes (via `infer`) for PDF, images, zips
+    /// and spreadsheets, leading markup for HTML and XML, and a body that
+    /// reads as JSON. Only the first `SNIFF_BYTES` are looked at, so a
+    /// prefix of the body is enough. `None` when the bytes don't tell.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        let prefix = &bytes[..bytes.len().min(SNIFF_BYTES)];
+        if let Some(kind) = infer::get(prefix) {
+            let sniffed = match Self::from_mime(kind.mime_type()) {
+                ContentType::Unknown | ContentType::Text => None,
+                known => Some(known),
+            };
+            if sniffed.is_some() {
+                return sniffed;
+            }
+        }
+        looks_like_json(prefix).then_some(ContentType::Json)
+    }
+
+    /// Classify a document by its body, file name and declared `Content-Type`.
+    /// What the body looks like wins, since a `.pdf` link may answer with an
+    /// HTML error page; disagreements are logged. Otherwise the file name beats
+    /// the header (servers often send octet-stream). XLSX files are zips too,
+    /// so a zip with a spreadsheet name or declared spreadsheet type stays
+    /// Excel. `bytes` may be just the first `SNIFF_BYTES` of the body.
+    pub fn detect(path: &str, declared: Option<&str>, bytes: &[u8]) -> Self {
+        let by_path = Self::from_path(path);
+        let by_header = declared.map(Self::from_mime).filter(|declared| *declared != ContentType::Unknown);
+        let Some(sniffed) = Self::sniff(bytes) else {
+            if by_path != ContentType::Unknown {
+                return by_path;
+            }
+            return by_header.unwrap_or(ContentType::Unknown);
+        };
+
+        if sniffed == ContentType::Archive && (by_path == ContentType::Excel || by_header == Some(ContentType::Excel)) {
+            return ContentType::Excel;
+        }
+        let path_disagrees = by_path != ContentType::Unknown && by_path != sniffed;
+        // A declared text/plain or octet-stream says too little to disagree with
+        let header_disagrees = by_header.is_some_and(|declared| declared != sniffed && declared != ContentType::Text);
+        if path_disagrees || header_disagrees {
+            debug!(
+                "{} looks like {} but its name says {} and its header {}; treating it as {}",
+                path,
+                sniffed.as_str(),
+                by_path.as_str(),
+                declared.unwrap_or("is missing"),
+                sniffed.as_str()
+            );
+        }
+        sniffed
+    }
+}
+
+/// A body starting like a JSON object or array that parses, or that only
+/// fails because the prefix ends early
+fn looks_like_json(prefix: &[u8]) -> bool {
+    let text = prefix.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(prefix);
+    let Some(first) = text.iter().find(|byte| !byte.is_ascii_whitespace()) else {
+        return false;
+    };
+    if !matches!(first, b'{' | b'[') {
+        return false;
+    }
+    match serde_json::from_slice::<serde::de::IgnoredAny>(text) {
+        Ok(_) => true,
+        Err(error) => error.is_eof() && prefix.len() == SNIFF_BYTES,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_bytes_win_over_declared_type() {
+        assert_eq!(ContentType::detect("/download?id=7", Some("application/octet-stream"), b"%PDF-1.7"), ContentType::Pdf);
+        assert_eq!(ContentType::detect("/preise.xlsx", None, b"PK\x03\x04"), ContentType::Excel);
+        assert_eq!(ContentType::detect("/preise-2024.zip", None, b"PK\x03\x04"), ContentType::Archive);
+        assert_eq!(ContentType::detect("/netzentgelte", Some("text/html; charset=utf-8"), b"<html>"), ContentType::Html);
+        assert_eq!(ContentType::detect("/scan", Some("application/octet-stream"), b"\x89PNG\r\n"), ContentType::Image);
+    }
+
+    #[test]
+    fn test_pdf_link_answering_html_is_html() {
+        let error_page = b"\n  <!DOCTYPE html>\n<html lang=\"de\"><head><title>Seite nicht gefunden</title></head></html>";
+        assert_eq!(ContentType::detect("/downloads/preisblatt-2024.pdf", Some("application/pdf"), error_page), ContentType::Html);
+        assert_eq!(ContentType::detect("/downloads/preisblatt-2024.pdf", None, error_page), ContentType::Html);
+
+        // Bodies that don't tell keep the type of their name
+        assert_eq!(ContentType::detect("/netzentgelte-2024.csv", Some("text/plain"), b"Spannungsebene;Arbeitspreis\nMS;1,26"), ContentType::Csv);
+        assert_eq!(ContentType::sniff(b"Spannungsebene;Arbeitspreis"), None);
+    }
+
+    #[test]
+    fn test_headerless_json_body_is_json() {
+        let body = br#"  {"netzbetreiber": "Netze BW", "jahr": 2024, "preise": [{"ebene": "MS", "arbeit": 1.26}]}"#;
+        assert_eq!(ContentType::detect("/api/netzentgelte", None, body), ContentType::Json);
+        assert_eq!(ContentType::detect("/api/netzentgelte", Some("application/octet-stream"), body), ContentType::Json);
+
+        // A prefix cut mid-document still reads as JSON; a broken short body doesn't
+        let mut long = b"[".to_vec();
+        while long.len() < 2 * SNIFF_BYTES {
+            long.extend_from_slice(br#"{"ebene": "NS", "arbeit": 7.14},"#);
+        }
+        assert_eq!(ContentType::sniff(&long), Some(ContentType::Json));
+        assert_eq!(ContentType::sniff(b"{ nicht json"), None);
+        assert_eq!(ContentType::sniff(b"[1, 2"), None);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::content::ContentType;
+use crate::extraction::{Document, Extraction, Extractor};
+use crate::pipeline::ExtractionMethod;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use dno_core::models::FieldProvenance;
+use serde_json::{json, Map, Value};
+
+/// CSV exports have a header row, but every value is untyped text
+const CSV_CONFIDENCE: f64 = 0.85;
+
+/// Delimiters recognized in a header line, in order of preference on a tie
+const DELIMITERS: [u8; 3] = [b';', b',', b'\t'];
+
+/// Reads CSV exports into rows keyed by the header line. The delimiter is
+/// sniffed from the header (German exports mostly use `;`), quoting follows
+/// RFC 4180 and a UTF-8 byte order mark is dropped.
+#[derive(Debug, Default, Clone)]
+pub struct CsvExtractor;
+
+impl CsvExtractor {
+    /// Rows as objects keyed by the header line
+    pub fn parse_csv_to_json(&self, text: &str) -> Result<Value> {
+        let extraction = self.extract_csv("", text)?;
+        Ok(extraction.data["tables"][0]["rows"].clone())
+    }
+
+    /// `{"tables": [{"headers": [...], "rows": [...]}]}` with provenance for
+    /// every cell; empty cells are left out
+    pub fn extract_csv(&self, url: &str, text: &str) -> Result<Extraction> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let delimiter = sniff_delimiter(text.lines().next().unwrap_or_default());
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+
+        let headers: Vec<String> = reader
+            .headers()
+            .with_context(|| format!("Failed to read the CSV header of {}", url))?
+            .iter()
+            .map(str::to_string)
+            .collect();
+
+        let mut extraction = Extraction::default();
+        let mut rows = Vec::new();
+        for (record_index, record) in reader.records().enumerate() {
+            let record = record.with_context(|| format!("Malformed CSV record {} in {}", record_index + 1, url))?;
+            let mut object = Map::new();
+            for (column_index, cell) in record.iter().enumerate().filter(|(_, cell)| !cell.is_empty()) {
+                let key = headers
+                    .get(column_index)
+                    .filter(|h| !h.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("column_{}", column_index));
+                extraction.record(
+                    format!("tables[0].rows[{}].{}", rows.len(), key),
+                    json!(cell),
+                    FieldProvenance {
+                        source_url: url.to_string(),
+                        page: None,
+                        // The header is row 0
+                        row: Some(record_index + 1),
+                        column: Some(column_index),
+                        selector: None,
+                        archive: None,
+                        method: ExtractionMethod::Csv.as_str().to_string(),
+                        confidence: CSV_CONFIDENCE,
+                    },
+                );
+                object.insert(key, json!(cell));
+            }
+            if !object.is_empty() {
+                rows.push(Value::Object(object));
+            }
+        }
+
+        extraction.data = json!({ "tables": [{ "headers": headers, "rows": rows }] });
+        Ok(extraction)
+    }
+}
+
+/// The delimiter occurring most often outside quotes in the header line; `,` if none does
+pub(crate) fn sniff_delimiter(header: &str) -> u8 {
+    let mut counts = [0usize; DELIMITERS.len()];
+    let mut quoted = false;
+    for byte in header.bytes() {
+        if byte == b'"' {
+            quoted = !quoted;
+        } else if let Some(i) = DELIMITERS.iter().position(|&d| d == byte).filter(|_| !quoted) {
+            counts[i] += 1;
+        }
+    }
+    let (best, count) = counts.iter().enumerate().fold((0, 0), |best, (i, &n)| if n > best.1 { (i, n) } else { best });
+    if count == 0 {
+        b','
+    } else {
+        DELIMITERS[best]
+    }
+}
+
+#[async_trait]
+impl Extractor for CsvExtractor {
+    fn method(&self) -> ExtractionMethod {
+        ExtractionMethod::Csv
+    }
+
+    async fn extract(&self, document: &Document) -> Result<Option<Extraction>> {
+        let content_type = ContentType::detect(&document.url, document.content_type.as_deref(), &document.bytes);
+        if content_type != ContentType::Csv {
+            return Ok(None);
+        }
+        let extraction = self.extract_csv(&document.url, &String::from_utf8_lossy(&document.bytes))?;
+        let has_rows = extraction.data["tables"][0]["rows"].as_array().is_some_and(|rows| !rows.is_empty());
+        Ok(has_rows.then_some(extraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semicolon_delimited_german_export() {
+        let csv = "Spannungsebene;Arbeitspreis ct/kWh;Leistungspreis €/kW\nMS;5,82;63,18\nNS;7,10;\n";
+
+        let rows = CsvExtractor.parse_csv_to_json(csv).unwrap();
+
+        assert_eq!(
+            rows,
+            json!([
+                { "Spannungsebene": "MS", "Arbeitspreis ct/kWh": "5,82", "Leistungspreis €/kW": "63,18" },
+                { "Spannungsebene": "NS", "Arbeitspreis ct/kWh": "7,10" }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_quoted_fields_keep_their_commas() {
+        let csv = "Ebene,Bemerkung,Preis\nMS,\"gilt für Entnahme, nicht Einspeisung\",58.21\nHS,\"Sonderregel \"\"§19\"\"\",41.00\n";
+
+        let rows = CsvExtractor.parse_csv_to_json(csv).unwrap();
+
+        assert_eq!(
+            rows,
+            json!([
+                { "Ebene": "MS", "Bemerkung": "gilt für Entnahme, nicht Einspeisung", "Preis": "58.21" },
+                { "Ebene": "HS", "Bemerkung": "Sonderregel \"§19\"", "Preis": "41.00" }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_byte_order_mark_is_not_part_of_the_first_header() {
+        let csv = "\u{feff}\"Jahr\";\"Spannungsebene\";\"Arbeitspreis\"\n2024;MS;5,82\n";
+
+        let extraction = CsvExtractor.extract_csv("https://netze.example/preise.csv", csv).unwrap();
+
+        assert_eq!(extraction.data["tables"][0]["headers"], json!(["Jahr", "Spannungsebene", "Arbeitspreis"]));
+        assert_eq!(extraction.data["tables"][0]["rows"], json!([{ "Jahr": "2024", "Spannungsebene": "MS", "Arbeitspreis": "5,82" }]));
+        let field = &extraction.provenance["tables[0].rows[0].Jahr"];
+        assert_eq!((field.provenance.row, field.provenance.column), (Some(1), Some(0)));
+    }
+}
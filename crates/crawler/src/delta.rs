@@ -0,0 +1,388 @@
+use crate::extraction::{Document, Extraction, Extractor};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ops::Range;
+use tracing::debug;
+
+/// When a re-extraction may be limited to the changed parts of a document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeltaPolicy {
+    /// Largest share of the current text's lines that may have changed for a
+    /// scoped re-extraction; above it the whole document is extracted again
+    pub max_changed_share: f64,
+}
+
+impl Default for DeltaPolicy {
+    fn default() -> Self {
+        Self { max_changed_share: 0.25 }
+    }
+}
+
+impl DeltaPolicy {
+    /// Read `CRAWLER_DELTA_MAX_CHANGED_SHARE` (0.0-1.0; 0 disables scoped re-extraction)
+    pub fn from_env() -> Self {
+        let max_changed_share = std::env::var("CRAWLER_DELTA_MAX_CHANGED_SHARE")
+            .ok()
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .filter(|share| (0.0..=1.0).contains(share))
+            .unwrap_or(Self::default().max_changed_share);
+        Self { max_changed_share }
+    }
+}
+
+/// Blocks of the current text that replace blocks of the previous one. A
+/// block is a paragraph or table: lines up to the next blank line or page break.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangedRegion {
+    pub previous_blocks: Range<usize>,
+    pub current_blocks: Range<usize>,
+    /// The region's lines in the current text
+    pub current_lines: Range<usize>,
+}
+
+/// Which part of a document a re-extraction covered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "scope")]
+pub enum ReExtractionScope {
+    /// The text did not change; the previous data was kept
+    Unchanged,
+    /// Only the changed regions were extracted and merged into the previous data
+    Scoped { regions: Vec<ChangedRegion> },
+    /// The whole document was extracted again
+    Full { reason: String },
+}
+
+/// Data and text of the last extraction of the same source
+#[derive(Debug, Clone)]
+pub struct PreviousExtraction {
+    pub text: String,
+    pub extraction: Extraction,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReExtraction {
+    pub scope: ReExtractionScope,
+    /// Share of the current text's lines inside changed regions
+    pub changed_share: f64,
+    /// `None` if the extractor found nothing in a full re-extraction
+    pub extraction: Option<Extraction>,
+}
+
+struct Block {
+    lines: Range<usize>,
+    /// Whitespace-normalized text, for comparing blocks
+    text: String,
+}
+
+fn blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(usize, Vec<String>)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let page_break = line.starts_with('\u{c}');
+        let line = line.trim_start_matches('\u{c}');
+        if line.trim().is_empty() || page_break {
+            if let Some((start, words)) = current.take() {
+                blocks.push(Block { lines: start..index, text: words.join("\n") });
+            }
+        }
+        if !line.trim().is_empty() {
+            let words = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            current.get_or_insert_with(|| (index, Vec::new())).1.push(words);
+        }
+    }
+    if let Some((start, words)) = current {
+        blocks.push(Block { lines: start..text.lines().count(), text: words.join("\n") });
+    }
+    blocks
+}
+
+/// Regions of `current` that differ from `previous`, from a longest common
+/// subsequence of their blocks
+fn changed_regions(previous: &[Block], current: &[Block]) -> Vec<ChangedRegion> {
+    let (n, m) = (previous.len(), current.len());
+    // common[i][j]: length of the LCS of previous[i..] and current[j..]
+    let mut common = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if previous[i].text == current[j].text {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut regions = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut region_i, mut region_j) = (0, 0);
+    let mut flush = |i: usize, j: usize, region_i: usize, region_j: usize| {
+        if region_i < i || region_j < j {
+            let current_lines = match (current.get(region_j), j.checked_sub(1).and_then(|last| current.get(last))) {
+                (Some(first), Some(last)) if region_j < j => first.lines.start..last.lines.end,
+                _ => 0..0,
+            };
+            regions.push(ChangedRegion { previous_blocks: region_i..i, current_blocks: region_j..j, current_lines });
+        }
+    };
+    while i < n || j < m {
+        if i < n && j < m && previous[i].text == current[j].text {
+            flush(i, j, region_i, region_j);
+            i += 1;
+            j += 1;
+            (region_i, region_j) = (i, j);
+        } else if j < m && (i == n || common[i][j + 1] >= common[i + 1][j]) {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    flush(i, j, region_i, region_j);
+    regions
+}
+
+/// Extract a document again, limited to what changed since `previous` when
+/// the change is localized. Each changed region is extracted on its own and
+/// its table rows are merged into the previous data, matched by their first
+/// column. Falls back to a full extraction when too much changed, when content
+/// was removed, or when a region's data does not fit the previous tables.
+pub async fn reextract(
+    extractor: &dyn Extractor,
+    document: &Document,
+    previous: &PreviousExtraction,
+    policy: &DeltaPolicy,
+) -> Result<ReExtraction> {
+    let Some(text) = document.text() else {
+        return full(extractor, document, 1.0, "document has no text layer").await;
+    };
+    let previous_blocks = blocks(&previous.text);
+    let current_blocks = blocks(&text);
+    let regions = changed_regions(&previous_blocks, &current_blocks);
+    if regions.is_empty() {
+        return Ok(ReExtraction {
+            scope: ReExtractionScope::Unchanged,
+            changed_share: 0.0,
+            extraction: Some(previous.extraction.clone()),
+        });
+    }
+
+    let total_lines = current_blocks.iter().map(|block| block.lines.len()).sum::<usize>().max(1);
+    let changed_lines: usize = regions
+        .iter()
+        .flat_map(|region| &current_blocks[region.current_blocks.clone()])
+        .map(|block| block.lines.len())
+        .sum();
+    let changed_share = changed_lines as f64 / total_lines as f64;
+    if changed_share > policy.max_changed_share {
+        let reason = format!("{:.0}% of the text changed", changed_share * 100.0);
+        return full(extractor, document, changed_share, &reason).await;
+    }
+    if regions.iter().any(|region| region.current_blocks.is_empty()) {
+        return full(extractor, document, changed_share, "content was removed").await;
+    }
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut merged = previous.extraction.clone();
+    for region in &regions {
+        let scoped_document = Document {
+            text: Some(lines[region.current_lines.clone()].join("\n")),
+            ..document.clone()
+        };
+        let merged_region = extractor
+            .extract(&scoped_document)
+            .await?
+            .and_then(|scoped| merge_tables(&merged, scoped));
+        match merged_region {
+            Some(extraction) => merged = extraction,
+            None => {
+                let reason = format!("lines {:?} do not match the previous tables", region.current_lines);
+                return full(extractor, document, changed_share, &reason).await;
+            }
+        }
+    }
+
+    debug!("Re-extracted {} changed regions of {}", regions.len(), document.url);
+    Ok(ReExtraction {
+        scope: ReExtractionScope::Scoped { regions },
+        changed_share,
+        extraction: Some(merged),
+    })
+}
+
+async fn full(extractor: &dyn Extractor, document: &Document, changed_share: f64, reason: &str) -> Result<ReExtraction> {
+    debug!("Full re-extraction of {}: {}", document.url, reason);
+    Ok(ReExtraction {
+        scope: ReExtractionScope::Full { reason: reason.to_string() },
+        changed_share,
+        extraction: extractor.extract(document).await?,
+    })
+}
+
+/// Merge the tables of a scoped extraction into `previous`: rows replace the
+/// row with the same first-column value in the table with the same headers,
+/// or are appended. `None` if a table has no counterpart in `previous`.
+fn merge_tables(previous: &Extraction, scoped: Extraction) -> Option<Extraction> {
+    let mut merged = previous.clone();
+    let scoped_tables = scoped.data.get("tables")?.as_array()?;
+    for (scoped_index, scoped_table) in scoped_tables.iter().enumerate() {
+        let headers = scoped_table.get("headers")?;
+        let key = headers.get(0)?.as_str()?;
+        let tables = merged.data.get_mut("tables")?.as_array_mut()?;
+        let table_index = tables.iter().position(|table| table.get("headers") == Some(headers))?;
+        let rows = tables[table_index].get_mut("rows")?.as_array_mut()?;
+
+        for (scoped_row, row) in scoped_table.get("rows")?.as_array()?.iter().enumerate() {
+            let row_key = row.get(key).filter(|value| !value.is_null())?;
+            let row_index = match rows.iter().position(|existing| existing.get(key) == Some(row_key)) {
+                Some(index) => {
+                    rows[index] = row.clone();
+                    index
+                }
+                None => {
+                    rows.push(row.clone());
+                    rows.len() - 1
+                }
+            };
+
+            let old_prefix = format!("tables[{}].rows[{}].", table_index, row_index);
+            merged.provenance.retain(|path, _| !path.starts_with(&old_prefix));
+            let scoped_prefix = format!("tables[{}].rows[{}].", scoped_index, scoped_row);
+            for (path, field) in &scoped.provenance {
+                if let Some(column) = path.strip_prefix(&scoped_prefix) {
+                    merged.provenance.insert(format!("{}{}", old_prefix, column), field.clone());
+                }
+            }
+        }
+    }
+    // Only tables are merged; other top-level data must not have changed
+    let other_keys = |data: &Value| data.as_object().map(|object| object.keys().any(|key| key != "tables"));
+    (other_keys(&scoped.data) != Some(true)).then_some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_table::PdfTableExtractor;
+    use crate::pipeline::ExtractionMethod;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Extracts PDF tables and records the text of every call
+    #[derive(Default)]
+    struct Recording {
+        texts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Extractor for Recording {
+        fn method(&self) -> ExtractionMethod {
+            ExtractionMethod::PdfText
+        }
+
+        async fn extract(&self, document: &Document) -> Result<Option<Extraction>> {
+            let text = document.text.clone().unwrap_or_default();
+            self.texts.lock().unwrap().push(text.clone());
+            Ok(PdfTableExtractor.extract_text(&document.url, &text))
+        }
+    }
+
+    const PREISBLATT: &str = "\
+Preisblatt Netzentgelte 2024
+Netze BW GmbH, gültig ab 01.01.2024
+
+Die Entgelte gelten für die Entnahme aus dem Netz der Netze BW GmbH.
+Sie werden je Abnahmestelle und Jahr abgerechnet.
+Grundlage ist die Genehmigung der Regulierungsbehörde.
+
+Spannungsebene        Leistungspreis    Arbeitspreis
+HS                    25,10             0,85
+HS/MS                 38,42             1,02
+MS                    58,21             1,26
+MS/NS                 71,93             1,88
+NS                    21,04             5,67
+
+Alle Preise zzgl. USt.
+Abweichende Regelungen für Speicher und Ladeeinrichtungen bleiben unberührt.
+Messstellenbetrieb wird gesondert in Rechnung gestellt.
+Es gelten die ergänzenden Bedingungen zum Netznutzungsvertrag.
+
+Hochlastzeitfenster für die atypische Netznutzung
+
+Spannungsebene        Winter            Sommer
+HS                    08:00-12:00       11:00-13:00
+MS                    07:30-12:30       10:30-13:30
+NS                    17:00-20:00       keine
+
+Die Zeitfenster gelten werktags.
+Feiertage nach dem Recht des Landes Baden-Württemberg sind ausgenommen.
+Für Kunden mit Eigenerzeugung gelten gesonderte Bedingungen.
+Änderungen werden mindestens vier Wochen im Voraus veröffentlicht.
+Stand: Dezember 2023
+";
+
+    fn document(text: &str) -> Document {
+        Document {
+            url: "https://netze.example/preisblatt-2024.pdf".to_string(),
+            content_type: Some("application/pdf".to_string()),
+            bytes: b"%PDF-1.7".to_vec(),
+            text: Some(text.to_string()),
+            parent_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_small_change_is_reextracted_in_scope() {
+        let extractor = Recording::default();
+        let initial = extractor.extract(&document(PREISBLATT)).await.unwrap().unwrap();
+        let previous = PreviousExtraction { text: PREISBLATT.to_string(), extraction: initial };
+        extractor.texts.lock().unwrap().clear();
+
+        // The MS working price is corrected in a reissued sheet
+        let reissued = PREISBLATT.replace("58,21             1,26", "58,21             1,31");
+        let result = reextract(&extractor, &document(&reissued), &previous, &DeltaPolicy::default())
+            .await
+            .unwrap();
+
+        let ReExtractionScope::Scoped { regions } = &result.scope else {
+            panic!("expected a scoped re-extraction, got {:?}", result.scope);
+        };
+        assert_eq!(regions.len(), 1);
+        let texts = extractor.texts.lock().unwrap().clone();
+        assert_eq!(texts.len(), 1);
+        assert!(texts[0].starts_with("Spannungsebene") && texts[0].ends_with("5,67"));
+        assert!(!texts[0].contains("Preisblatt") && !texts[0].contains("USt."));
+
+        let extraction = result.extraction.unwrap();
+        let rows = extraction.data["tables"][0]["rows"].as_array().unwrap();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[2]["Arbeitspreis"], "1,31");
+        assert_eq!(rows[4]["Arbeitspreis"], "5,67");
+        assert_eq!(extraction.provenance["tables[0].rows[2].Arbeitspreis"].value, "1,31");
+
+        // Unchanged text keeps the previous data without calling the extractor
+        let unchanged = reextract(&extractor, &document(PREISBLATT), &previous, &DeltaPolicy::default())
+            .await
+            .unwrap();
+        assert_eq!(unchanged.scope, ReExtractionScope::Unchanged);
+        assert_eq!(extractor.texts.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_widespread_change_falls_back_to_full_extraction() {
+        let extractor = Recording::default();
+        let initial = extractor.extract(&document(PREISBLATT)).await.unwrap().unwrap();
+        let previous = PreviousExtraction { text: PREISBLATT.to_string(), extraction: initial };
+
+        let rewritten = PREISBLATT
+            .replace("2024", "2025")
+            .replace("Netze BW GmbH", "Netze BW GmbH (neu)")
+            .replace(',', ".")
+            .replace(":00", ":15");
+        let result = reextract(&extractor, &document(&rewritten), &previous, &DeltaPolicy::default())
+            .await
+            .unwrap();
+        assert!(matches!(result.scope, ReExtractionScope::Full { .. }), "{:?}", result.scope);
+        assert!(result.changed_share > 0.25);
+        assert_eq!(extractor.texts.lock().unwrap().last().unwrap(), &rewritten);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::csv_table::sniff_delimiter;
+use anyhow::{bail, Context, Result};
+use dno_core::cache::CacheLayer;
+use dno_core::repository::{DnoRepository, UpsertReport};
+use dno_core::CreateDno;
+
+/// Header names accepted per field, compared case-insensitively. The
+/// Bundesnetzagentur export uses the German ones.
+const NAME_HEADERS: [&str; 4] = ["name", "netzbetreiber", "betreiber", "unternehmen"];
+const OFFICIAL_NAME_HEADERS: [&str; 2] = ["official_name", "firmierung"];
+const SLUG_HEADERS: [&str; 1] = ["slug"];
+const REGION_HEADERS: [&str; 3] = ["region", "bundesland", "netzgebiet"];
+const WEBSITE_HEADERS: [&str; 5] = ["website", "webseite", "internetseite", "homepage", "url"];
+
+/// Read an operator list as exported by the Bundesnetzagentur: one DNO per
+/// row with its name and optionally slug, official name, region and website.
+/// The delimiter is sniffed like for `CsvExtractor`. Websites without a
+/// scheme get `https://`.
+pub fn parse_dno_list(text: &str) -> Result<Vec<CreateDno>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(sniff_delimiter(text.lines().next().unwrap_or_default()))
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .context("Failed to read the header of the DNO list")?
+        .iter()
+        .map(str::to_lowercase)
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|header| names.contains(&header.as_str()));
+    let Some(name_column) = column(&NAME_HEADERS) else {
+        bail!("The DNO list has no name column (expected one of: {})", NAME_HEADERS.join(", "));
+    };
+    let (official_name, slug, region, website) =
+        (column(&OFFICIAL_NAME_HEADERS), column(&SLUG_HEADERS), column(&REGION_HEADERS), column(&WEBSITE_HEADERS));
+
+    let mut dnos = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // The header is line 1
+        let line = index + 2;
+        let record = record.with_context(|| format!("Malformed record on line {} of the DNO list", line))?;
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let cell = |column: Option<usize>| {
+            column.and_then(|i| record.get(i)).filter(|value| !value.is_empty()).map(str::to_string)
+        };
+        let Some(name) = cell(Some(name_column)) else {
+            bail!("Line {} of the DNO list has no name", line);
+        };
+        dnos.push(CreateDno {
+            slug: cell(slug).unwrap_or_default(),
+            name,
+            official_name: cell(official_name),
+            description: None,
+            region: cell(region),
+            website: cell(website).map(|url| if url.contains("://") { url } else { format!("https://{}", url) }),
+        });
+    }
+    Ok(dnos)
+}
+
+/// Upsert the DNOs of an operator list; see `DnoRepository::upsert_many`
+/// for how rows are matched to stored DNOs
+pub async fn import_dnos<C: CacheLayer>(repo: &DnoRepository<C>, text: &str) -> Result<UpsertReport> {
+    let dnos = parse_dno_list(text)?;
+    Ok(repo.upsert_many(dnos).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dno_core::cache::MemoryCache;
+    use sqlx::PgPool;
+    use std::sync::Arc;
+
+    const BNETZA_LIST: &str = "\u{feff}Netzbetreiber;Bundesland;Internetseite\n\
+        Netze BW GmbH;Baden-Württemberg;https://www.netze-bw.de\n\
+        \"Westnetz  GmbH\";Nordrhein-Westfalen;www.westnetz.de\n\
+        Thüringer Energienetze GmbH & Co. KG;Thüringen;\n";
+
+    #[test]
+    fn test_bnetza_headers_and_websites() {
+        let dnos = parse_dno_list(BNETZA_LIST).unwrap();
+
+        assert_eq!(dnos.len(), 3);
+        assert_eq!(dnos[0].name, "Netze BW GmbH");
+        assert_eq!(dnos[0].region.as_deref(), Some("Baden-Württemberg"));
+        assert_eq!(dnos[1].website.as_deref(), Some("https://www.westnetz.de"));
+        assert_eq!(dnos[2].website, None);
+        assert!(dnos.iter().all(|dno| dno.slug.is_empty()));
+
+        assert!(parse_dno_list("Bundesland,Website\nBayern,https://bayernwerk.de\n").is_err());
+        assert!(parse_dno_list("Name,Region\n,Bayern\n").is_err());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_importing_the_same_list_twice_changes_nothing(pool: PgPool) {
+        dno_core::test_schema::create(&pool).await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO dnos (slug, name, official_name, description, region)
+                VALUES ('netze-bw', 'Netze BW', 'Netze BW GmbH', 'Seeded by hand', 'BW');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let repo = DnoRepository::new(pool.clone(), Arc::new(MemoryCache::new()));
+
+        let first = import_dnos(&repo, BNETZA_LIST).await.unwrap();
+        assert_eq!(first, UpsertReport { inserted: 2, updated: 1, unchanged: 0 });
+
+        let second = import_dnos(&repo, BNETZA_LIST).await.unwrap();
+        assert_eq!(second, UpsertReport { inserted: 0, updated: 0, unchanged: 3 });
+
+        let rows: Vec<(String, String, Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT slug, name, description, region FROM dnos ORDER BY slug")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("netze-bw".into(), "Netze BW".into(), Some("Seeded by hand".into()), Some("Baden-Württemberg".into())),
+                ("thueringer-energienetze-gmbh-co-kg".into(), "Thüringer Energienetze GmbH & Co. KG".into(), None, Some("Thüringen".into())),
+                ("westnetz-gmbh".into(), "Westnetz GmbH".into(), None, Some("Nordrhein-Westfalen".into())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::http::RedirectError;
+use thiserror::Error;
+
+/// Why a crawl step failed. Fetch, discovery and extraction paths return these
+/// (usually inside an `anyhow::Error`), so callers and `FailureRecoverySystem`
+/// can act on the kind of failure instead of its message.
+#[derive(Debug, Error)]
+pub enum CrawlError {
+    #[error("Timed out fetching {url}")]
+    Timeout { url: String },
+
+    #[error("Network error fetching {url}: {message}")]
+    Network { url: String, message: String },
+
+    /// The proxy in front of the target could not be reached or refused the
+    /// connection; the target itself may be fine
+    #[error("Proxy {proxy} failed for {url}: {message}")]
+    Proxy { proxy: String, url: String, message: String },
+
+    #[error("{url} returned HTTP {status}")]
+    Http { url: String, status: u16 },
+
+    /// Refused before sending: SSRF guard, non-HTTP scheme, robots rules
+    #[error("Blocked {url}: {reason}")]
+    Blocked { url: String, reason: String },
+
+    #[error(transparent)]
+    Redirect(#[from] RedirectError),
+
+    #[error("Failed to parse {url}: {message}")]
+    Parse { url: String, message: String },
+
+    #[error("Extraction failed for {url}: {message}")]
+    Extraction { url: String, message: String },
+
+    /// A time, request or size budget ran out
+    #[error("Crawl budget exhausted: {0}")]
+    Budget(String),
+
+    #[error("Crawl cancelled")]
+    Cancelled,
+
+    /// Failure without a more specific kind
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl CrawlError {
+    pub fn from_reqwest(url: &str, error: reqwest::Error) -> Self {
+        // The SSRF guard inside the client (redirect hops, DNS) says why it refused
+        let mut source = std::error::Error::source(&error);
+        while let Some(inner) = source {
+            if let Some(CrawlError::Blocked { url, reason }) = inner.downcast_ref::<CrawlError>() {
+                return CrawlError::Blocked { url: url.clone(), reason: reason.clone() };
+            }
+            source = inner.source();
+        }
+
+        if error.is_timeout() {
+            CrawlError::Timeout { url: url.to_string() }
+        } else {
+            CrawlError::Network { url: url.to_string(), message: error.to_string() }
+        }
+    }
+
+    /// Short name of the failure's kind, for reports and logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CrawlError::Timeout { .. } => "timeout",
+            CrawlError::Network { .. } => "network",
+            CrawlError::Proxy { .. } => "proxy",
+            CrawlError::Http { .. } => "http",
+            CrawlError::Blocked { .. } => "blocked",
+            CrawlError::Redirect(_) => "redirect",
+            CrawlError::Parse { .. } => "parse",
+            CrawlError::Extraction { .. } => "extraction",
+            CrawlError::Budget(_) => "budget",
+            CrawlError::Cancelled => "cancelled",
+            CrawlError::Other(_) => "other",
+        }
+    }
+
+    /// Recover the typed error from an `anyhow::Error` returned by a crawl path
+    pub fn from_anyhow(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<CrawlError>() {
+            Ok(crawl) => return crawl,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<RedirectError>() {
+            Ok(redirect) => return CrawlError::Redirect(redirect),
+            Err(error) => error,
+        };
+        match error.downcast::<reqwest::Error>() {
+            Ok(reqwest) => {
+                let url = reqwest.url().map(|url| url.to_string()).unwrap_or_default();
+                CrawlError::from_reqwest(&url, reqwest)
+            }
+            Err(error) => CrawlError::Other(error),
+        }
+    }
+}
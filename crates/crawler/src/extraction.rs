@@ -0,0 +1,430 @@
+use crate::archive::{unpack_archive, ArchiveLimits};
+use crate::content::ContentType;
+use crate::error::CrawlError;
+use crate::language::{LanguageCheck, LanguagePolicy};
+use crate::pipeline::{ExtractionMethod, PipelineConfig};
+use crate::sanitize::{ExtractedContent, RawDataPolicy};
+use async_trait::async_trait;
+use dno_core::models::{ExtractedField, FieldProvenance};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn, Instrument};
+
+/// A fetched document awaiting extraction
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub url: String,
+    pub content_type: Option<String>,
+    pub bytes: Vec<u8>,
+    /// Text already extracted from the document (e.g. the PDF text layer)
+    pub text: Option<String>,
+    /// URL of the archive this document was unpacked from
+    pub parent_url: Option<String>,
+}
+
+impl Document {
+    /// Readable text for language detection: the extracted text if present,
+    /// otherwise the visible text of HTML or plain-text bodies
+    pub fn text(&self) -> Option<String> {
+        if let Some(text) = &self.text {
+            return Some(text.clone());
+        }
+        let content_type = self.content_type.as_deref().unwrap_or_default();
+        if content_type.contains("html") {
+            let html = scraper::Html::parse_document(&String::from_utf8_lossy(&self.bytes));
+            Some(html.root_element().text().collect::<Vec<_>>().join("\n"))
+        } else if content_type.starts_with("text/") {
+            Some(String::from_utf8_lossy(&self.bytes).into_owned())
+        } else {
+            None
+        }
+    }
+}
+
+/// Structured data produced by one extractor, with provenance for each value
+#[derive(Debug, Clone, Default)]
+pub struct Extraction {
+    pub data: serde_json::Value,
+    /// Keyed by the field's path in `data`, e.g. `tables[0].rows[1].Arbeitspreis`
+    pub provenance: BTreeMap<String, ExtractedField>,
+}
+
+impl Extraction {
+    pub fn new(data: serde_json::Value) -> Self {
+        Self { data, provenance: BTreeMap::new() }
+    }
+
+    pub fn record(&mut self, path: impl Into<String>, value: serde_json::Value, provenance: FieldProvenance) {
+        self.provenance.insert(path.into(), ExtractedField { value, provenance });
+    }
+
+    /// Provenance map as stored in `data_sources.extracted_data`
+    pub fn extracted_data(&self) -> serde_json::Value {
+        serde_json::to_value(&self.provenance).unwrap_or_default()
+    }
+}
+
+/// Time one extractor may spend on a document before its attempt is cut off
+pub const DEFAULT_EXTRACTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Read `CRAWLER_EXTRACTION_TIMEOUT_SECS`; `DEFAULT_EXTRACTION_TIMEOUT` when unset or invalid
+pub fn extraction_timeout_from_env() -> Duration {
+    std::env::var("CRAWLER_EXTRACTION_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map_or(DEFAULT_EXTRACTION_TIMEOUT, Duration::from_secs)
+}
+
+/// Where an extractor leaves the data it has so far, so it can be salvaged
+/// if the attempt times out (e.g. tables parsed before the AI step stalled)
+#[derive(Debug, Clone, Default)]
+pub struct PartialResults(Arc<Mutex<Option<Extraction>>>);
+
+impl PartialResults {
+    /// Replace the data saved so far
+    pub fn save(&self, extraction: &Extraction) {
+        *self.0.lock().unwrap() = Some(extraction.clone());
+    }
+
+    fn take(&self) -> Option<Extraction> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+/// One extraction method. `Ok(None)` means the method does not apply to this
+/// document (e.g. no table found) and the next method should be tried.
+#[async_trait]
+pub trait Extractor: Send + Sync {
+    fn method(&self) -> ExtractionMethod;
+
+    async fn extract(&self, document: &Document) -> anyhow::Result<Option<Extraction>>;
+
+    /// `extract` for extractors that work in stages: save intermediate data to
+    /// `partial` as it becomes available. The default keeps nothing to salvage.
+    async fn extract_with_partial(
+        &self,
+        document: &Document,
+        partial: &PartialResults,
+    ) -> anyhow::Result<Option<Extraction>> {
+        let _ = partial;
+        self.extract(document).await
+    }
+}
+
+/// Whether an extraction ran to completion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionStatus {
+    #[default]
+    Complete,
+    /// The extractor timed out; the data is what it had produced by then
+    Partial,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractionOutcome {
+    pub method: ExtractionMethod,
+    pub data: serde_json::Value,
+    pub provenance: BTreeMap<String, ExtractedField>,
+    /// Methods tried, in order, including the successful one
+    pub attempted: Vec<ExtractionMethod>,
+    /// Detected document language; `flagged` if not predominantly German
+    pub language: Option<LanguageCheck>,
+    /// Page content to store with the data, for HTML documents
+    pub content: Option<ExtractedContent>,
+    pub status: ExtractionStatus,
+    /// Why the data is incomplete, for `Partial` outcomes
+    pub note: Option<String>,
+}
+
+/// Count the mean field confidence of an extraction in the process metrics
+fn record_confidence(method: ExtractionMethod, provenance: &BTreeMap<String, ExtractedField>) {
+    if provenance.is_empty() {
+        return;
+    }
+    let confidence = provenance.values().map(|field| field.provenance.confidence).sum::<f64>() / provenance.len() as f64;
+    dno_core::metrics::global().observe_extraction(method.as_str(), confidence);
+}
+
+/// Runs the registered extractors in the order given by the `PipelineConfig`
+/// until one produces data
+pub struct MultiModalExtractor {
+    extractors: HashMap<ExtractionMethod, Arc<dyn Extractor>>,
+    config: Arc<PipelineConfig>,
+    language: LanguagePolicy,
+    raw_data: RawDataPolicy,
+    timeout: Duration,
+}
+
+impl MultiModalExtractor {
+    pub fn new(config: Arc<PipelineConfig>) -> Self {
+        Self {
+            extractors: HashMap::new(),
+            config,
+            language: LanguagePolicy::default(),
+            raw_data: RawDataPolicy::default(),
+            timeout: DEFAULT_EXTRACTION_TIMEOUT,
+        }
+    }
+
+    pub fn with_language_policy(mut self, language: LanguagePolicy) -> Self {
+        self.language = language;
+        self
+    }
+
+    pub fn with_raw_data_policy(mut self, raw_data: RawDataPolicy) -> Self {
+        self.raw_data = raw_data;
+        self
+    }
+
+    /// Time limit for each extractor attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn register(mut self, extractor: Arc<dyn Extractor>) -> Self {
+        self.extractors.insert(extractor.method(), extractor);
+        self
+    }
+
+    /// Configured methods that have an extractor registered, in attempt order
+    pub fn attempt_order(&self, dno_key: Option<&str>) -> Vec<ExtractionMethod> {
+        self.config
+            .extraction_order_for(dno_key)
+            .iter()
+            .copied()
+            .filter(|method| self.extractors.contains_key(method))
+            .collect()
+    }
+
+    /// Run the extractors in order until one produces data. Each attempt is
+    /// limited to the configured timeout. An attempt that times out after
+    /// saving partial data is kept as a `Partial` outcome, returned only if no
+    /// later method produces complete data.
+    #[tracing::instrument(name = "crawl.extract", skip_all, fields(url = %document.url))]
+    pub async fn extract(&self, dno_key: Option<&str>, document: &Document) -> anyhow::Result<ExtractionOutcome> {
+        let language = document.text().and_then(|text| self.language.check(&text));
+        if let Some(check) = language.as_ref().filter(|check| check.skip) {
+            anyhow::bail!(
+                "skipped {}: not predominantly German (detected {}, {:.0}% German)",
+                document.url,
+                check.language,
+                check.german_share * 100.0
+            );
+        }
+        if language.as_ref().is_some_and(|check| check.flagged) {
+            warn!("{} does not look German, flagging extraction", document.url);
+        }
+
+        let mut attempted = Vec::new();
+        let mut last_error = None;
+        let mut salvaged = None;
+
+        for method in self.attempt_order(dno_key) {
+            attempted.push(method);
+            // AI attempts call out to Ollama, so they get their own span name
+            let span = tracing::info_span!(
+                "extraction.attempt",
+                otel.name = if method == ExtractionMethod::Ai { "ai.extract" } else { "extraction.attempt" },
+                method = %method,
+            );
+            let partial = PartialResults::default();
+            let attempt = self.extractors[&method].extract_with_partial(document, &partial).instrument(span);
+            match tokio::time::timeout(self.timeout, attempt).await {
+                Ok(Ok(Some(Extraction { data, provenance }))) => {
+                    debug!("{} extracted {} after {:?}", method, document.url, attempted);
+                    record_confidence(method, &provenance);
+                    let content = ExtractedContent::from_document(document, &self.raw_data);
+                    return Ok(ExtractionOutcome {
+                        method,
+                        data,
+                        provenance,
+                        attempted,
+                        language,
+                        content,
+                        status: ExtractionStatus::Complete,
+                        note: None,
+                    });
+                }
+                Ok(Ok(None)) => debug!("{} found nothing in {}", method, document.url),
+                Ok(Err(e)) => {
+                    warn!("{} failed on {}: {}", method, document.url, e);
+                    last_error = Some(e);
+                }
+                Err(_) => {
+                    let note = format!("{} timed out after {}s", method, self.timeout.as_secs_f64());
+                    match partial.take() {
+                        Some(extraction) if salvaged.is_none() => {
+                            warn!("{} on {}, keeping the data produced so far", note, document.url);
+                            salvaged = Some((method, extraction, note));
+                        }
+                        _ => {
+                            warn!("{} on {}", note, document.url);
+                            last_error = Some(anyhow::anyhow!(note));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((method, Extraction { data, provenance }, note)) = salvaged {
+            record_confidence(method, &provenance);
+            let content = ExtractedContent::from_document(document, &self.raw_data);
+            return Ok(ExtractionOutcome {
+                method,
+                data,
+                provenance,
+                attempted,
+                language,
+                content,
+                status: ExtractionStatus::Partial,
+                note: Some(note),
+            });
+        }
+
+        let message = match last_error {
+            Some(e) => format!("{:#}", e),
+            None => format!("no extractor produced data (tried {:?})", attempted),
+        };
+        Err(CrawlError::Extraction { url: document.url.clone(), message }.into())
+    }
+
+    /// Extract a document that may be a zip archive. Archives are unpacked
+    /// and each member is extracted on its own, with the archive URL recorded
+    /// in every field's provenance; other documents yield a single outcome.
+    /// A member that fails does not fail its siblings.
+    pub async fn extract_all(
+        &self,
+        dno_key: Option<&str>,
+        document: &Document,
+        limits: &ArchiveLimits,
+    ) -> anyhow::Result<Vec<(Document, anyhow::Result<ExtractionOutcome>)>> {
+        let content_type = ContentType::detect(&document.url, document.content_type.as_deref(), &document.bytes);
+        if content_type != ContentType::Archive {
+            let outcome = self.extract(dno_key, document).await;
+            return Ok(vec![(document.clone(), outcome)]);
+        }
+
+        let members = unpack_archive(document, limits)?;
+        debug!("Unpacked {} documents from {}", members.len(), document.url);
+
+        let mut outcomes = Vec::with_capacity(members.len());
+        for member in members {
+            let outcome = self.extract(dno_key, &member).await.map(|mut outcome| {
+                for field in outcome.provenance.values_mut() {
+                    field.provenance.archive = member.parent_url.clone();
+                }
+                outcome
+            });
+            outcomes.push((member, outcome));
+        }
+        Ok(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Recording {
+        method: ExtractionMethod,
+        calls: Arc<Mutex<Vec<ExtractionMethod>>>,
+    }
+
+    #[async_trait]
+    impl Extractor for Recording {
+        fn method(&self) -> ExtractionMethod {
+            self.method
+        }
+
+        async fn extract(&self, _document: &Document) -> anyhow::Result<Option<Extraction>> {
+            self.calls.lock().unwrap().push(self.method);
+            Ok(Some(Extraction::new(serde_json::json!({ "method": self.method }))))
+        }
+    }
+
+    fn extractor(config: PipelineConfig, calls: &Arc<Mutex<Vec<ExtractionMethod>>>) -> MultiModalExtractor {
+        [ExtractionMethod::PdfText, ExtractionMethod::Ocr, ExtractionMethod::Ai]
+            .into_iter()
+            .fold(MultiModalExtractor::new(Arc::new(config)), |extractor, method| {
+                extractor.register(Arc::new(Recording { method, calls: calls.clone() }))
+            })
+    }
+
+    #[tokio::test]
+    async fn test_custom_order_changes_first_attempted_method() {
+        let document = Document { url: "https://example.com/netzentgelte-2024.pdf".to_string(), ..Default::default() };
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let outcome = extractor(PipelineConfig::default(), &calls).extract(None, &document).await.unwrap();
+        assert_eq!(outcome.method, ExtractionMethod::PdfText);
+
+        let config = PipelineConfig::from_json(r#"{"dno_extraction_order": {"scanned-dno": ["ocr", "ai", "pdf_text"]}}"#).unwrap();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let outcome = extractor(config, &calls).extract(Some("scanned-dno"), &document).await.unwrap();
+        assert_eq!(outcome.method, ExtractionMethod::Ocr);
+        assert_eq!(*calls.lock().unwrap(), vec![ExtractionMethod::Ocr]);
+    }
+
+    /// Parses the tables, saves them, then stalls in the AI step
+    struct StallingAi;
+
+    #[async_trait]
+    impl Extractor for StallingAi {
+        fn method(&self) -> ExtractionMethod {
+            ExtractionMethod::Ai
+        }
+
+        async fn extract(&self, document: &Document) -> anyhow::Result<Option<Extraction>> {
+            self.extract_with_partial(document, &PartialResults::default()).await
+        }
+
+        async fn extract_with_partial(
+            &self,
+            document: &Document,
+            partial: &PartialResults,
+        ) -> anyhow::Result<Option<Extraction>> {
+            let mut extraction = Extraction::new(serde_json::json!({
+                "tables": [{
+                    "headers": ["Spannungsebene", "Arbeitspreis"],
+                    "rows": [{ "Spannungsebene": "MS", "Arbeitspreis": "1,26" }]
+                }]
+            }));
+            let provenance = FieldProvenance {
+                source_url: document.url.clone(),
+                page: Some(1),
+                row: Some(1),
+                column: Some(1),
+                selector: None,
+                archive: None,
+                method: ExtractionMethod::PdfText.as_str().to_string(),
+                confidence: 0.9,
+            };
+            extraction.record("tables[0].rows[0].Arbeitspreis", serde_json::json!("1,26"), provenance);
+            partial.save(&extraction);
+            std::future::pending::<()>().await;
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_extractor_yields_partial_data() {
+        let document = Document { url: "https://example.com/preisblatt-2024.pdf".to_string(), ..Default::default() };
+        let config = PipelineConfig::from_json(r#"{"extraction_order": ["ai"]}"#).unwrap();
+        let extractor = MultiModalExtractor::new(Arc::new(config))
+            .register(Arc::new(StallingAi))
+            .with_timeout(Duration::from_millis(50));
+
+        let outcome = extractor.extract(None, &document).await.unwrap();
+        assert_eq!(outcome.status, ExtractionStatus::Partial);
+        assert_eq!(outcome.method, ExtractionMethod::Ai);
+        assert_eq!(outcome.data["tables"][0]["rows"][0]["Arbeitspreis"], "1,26");
+        assert!(outcome.provenance.contains_key("tables[0].rows[0].Arbeitspreis"));
+        assert!(outcome.note.unwrap().contains("timed out"));
+    }
+}
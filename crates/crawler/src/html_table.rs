@@ -0,0 +1,155 @@
+use crate::extraction::{Document, Extraction, Extractor};
+use crate::pipeline::ExtractionMethod;
+use async_trait::async_trait;
+use dno_core::models::FieldProvenance;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{json, Map, Value};
+
+/// Confidence for cells read under a proper header row versus positional columns
+const HEADED_CONFIDENCE: f64 = 0.9;
+const HEADERLESS_CONFIDENCE: f64 = 0.6;
+
+/// Reads every `<table>` in an HTML page into rows keyed by the header cells,
+/// recording the row/column and selector each value came from
+#[derive(Debug, Default, Clone)]
+pub struct HtmlTableExtractor;
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("static selector")
+}
+
+fn cell_text(cell: ElementRef<'_>) -> String {
+    cell.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl HtmlTableExtractor {
+    pub fn extract_html(&self, url: &str, html: &str) -> Option<Extraction> {
+        let document = Html::parse_document(html);
+        let (table_sel, row_sel, cell_sel) = (selector("table"), selector("tr"), selector("th, td"));
+
+        let mut tables = Vec::new();
+        let mut extraction = Extraction::default();
+
+        for (table_index, table) in document.select(&table_sel).enumerate() {
+            let rows: Vec<ElementRef> = table.select(&row_sel).collect();
+            let Some(first) = rows.first() else { continue };
+
+            // A first row made only of <th> cells is the header
+            let first_cells: Vec<ElementRef> = first.select(&cell_sel).collect();
+            let has_header = !first_cells.is_empty() && first_cells.iter().all(|c| c.value().name() == "th");
+            let headers: Vec<String> = if has_header {
+                first_cells.into_iter().map(cell_text).collect()
+            } else {
+                Vec::new()
+            };
+            let confidence = if has_header { HEADED_CONFIDENCE } else { HEADERLESS_CONFIDENCE };
+
+            let mut parsed_rows = Vec::new();
+            for (row_index, row) in rows.iter().enumerate().skip(usize::from(has_header)) {
+                let mut object = Map::new();
+                for (column_index, cell) in row.select(&cell_sel).enumerate() {
+                    let text = cell_text(cell);
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let key = headers
+                        .get(column_index)
+                        .filter(|h| !h.is_empty())
+                        .cloned()
+                        .unwrap_or_else(|| format!("column_{}", column_index));
+
+                    let data_row = parsed_rows.len();
+                    extraction.record(
+                        format!("tables[{}].rows[{}].{}", table_index, data_row, key),
+                        json!(text),
+                        FieldProvenance {
+                            source_url: url.to_string(),
+                            page: None,
+                            row: Some(row_index),
+                            column: Some(column_index),
+                            selector: Some(format!(
+                                "table:nth-of-type({}) tr:nth-of-type({}) > :nth-child({})",
+                                table_index + 1,
+                                row_index + 1,
+                                column_index + 1
+                            )),
+                            archive: None,
+                            method: ExtractionMethod::HtmlTable.as_str().to_string(),
+                            confidence,
+                        },
+                    );
+                    object.insert(key, Value::String(text));
+                }
+                if !object.is_empty() {
+                    parsed_rows.push(Value::Object(object));
+                }
+            }
+
+            if !parsed_rows.is_empty() {
+                tables.push(json!({ "headers": headers, "rows": parsed_rows }));
+            }
+        }
+
+        if tables.is_empty() {
+            return None;
+        }
+        extraction.data = json!({ "tables": tables });
+        Some(extraction)
+    }
+}
+
+#[async_trait]
+impl Extractor for HtmlTableExtractor {
+    fn method(&self) -> ExtractionMethod {
+        ExtractionMethod::HtmlTable
+    }
+
+    async fn extract(&self, document: &Document) -> anyhow::Result<Option<Extraction>> {
+        if !document.content_type.as_deref().unwrap_or_default().contains("html") {
+            return Ok(None);
+        }
+        let html = String::from_utf8_lossy(&document.bytes);
+        Ok(self.extract_html(&document.url, &html))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_value_records_row_and_column() {
+        let html = r#"
+            <html><body>
+            <table>
+                <tr><th>Spannungsebene</th><th>Leistungspreis</th><th>Arbeitspreis</th></tr>
+                <tr><td>MS</td><td>58,21 €/kWa</td><td>1,26 ct/kWh</td></tr>
+                <tr><td>NS</td><td>21,04 €/kWa</td><td>5,67 ct/kWh</td></tr>
+            </table>
+            </body></html>
+        "#;
+
+        let extraction = HtmlTableExtractor
+            .extract_html("https://netze.example/netzentgelte", html)
+            .unwrap();
+
+        assert_eq!(extraction.data["tables"][0]["rows"][1]["Arbeitspreis"], "5,67 ct/kWh");
+        assert_eq!(extraction.provenance.len(), 6);
+
+        let field = &extraction.provenance["tables[0].rows[1].Arbeitspreis"];
+        assert_eq!(field.value, "5,67 ct/kWh");
+        assert_eq!(field.provenance.row, Some(2));
+        assert_eq!(field.provenance.column, Some(2));
+        assert_eq!(field.provenance.method, "html_table");
+        assert_eq!(field.provenance.source_url, "https://netze.example/netzentgelte");
+        assert_eq!(
+            field.provenance.selector.as_deref(),
+            Some("table:nth-of-type(1) tr:nth-of-type(3) > :nth-child(3)")
+        );
+
+        for (path, field) in &extraction.provenance {
+            assert!(field.provenance.row.is_some() && field.provenance.column.is_some(), "{}", path);
+        }
+        assert_eq!(extraction.extracted_data()["tables[0].rows[0].Spannungsebene"]["provenance"]["row"], 1);
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::transport::HttpResponse;
+
+/// Where `CrawlerClient` keeps validators and bodies of fetched pages
+#[derive(Debug, Clone)]
+pub struct HttpCacheConfig {
+    pub path: PathBuf,
+    /// How long a stored page is revalidated instead of fetched in full
+    pub ttl: Duration,
+}
+
+impl HttpCacheConfig {
+    /// `CRAWLER_HTTP_CACHE` (SQLite file; unset disables the cache) and
+    /// `CRAWLER_HTTP_CACHE_TTL_SECS` (default 7 days)
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var("CRAWLER_HTTP_CACHE").ok().filter(|path| !path.is_empty())?;
+        let ttl = std::env::var("CRAWLER_HTTP_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(7 * 24 * 60 * 60);
+        Some(Self { path: PathBuf::from(path), ttl: Duration::from_secs(ttl) })
+    }
+}
+
+/// How the HTTP cache treats one request
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheDirective {
+    /// Fetch in full and leave the stored entry alone
+    pub bypass: bool,
+    /// Overrides the cache's TTL
+    pub ttl: Option<Duration>,
+}
+
+/// A stored response and the validators to revalidate it with
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub response: HttpResponse,
+}
+
+/// On-disk store of successful GET responses that carry an `ETag` or
+/// `Last-Modified`, keyed by URL, so a re-crawl can send conditional
+/// requests and reuse the body on `304 Not Modified`
+pub struct HttpCache {
+    pool: SqlitePool,
+    ttl: Duration,
+}
+
+impl HttpCache {
+    /// Open (or create) the SQLite file at `path`
+    pub async fn open(path: &Path, ttl: Duration) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create HTTP cache directory {}", dir.display()))?;
+        }
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .with_context(|| format!("Failed to open HTTP cache {}", path.display()))?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS http_cache (
+                url TEXT PRIMARY KEY,
+                etag TEXT,
+                last_modified TEXT,
+                status INTEGER NOT NULL,
+                headers TEXT NOT NULL,
+                body BLOB NOT NULL,
+                stored_at INTEGER NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .context("Failed to create the http_cache table")?;
+        Ok(Self { pool, ttl })
+    }
+
+    pub async fn from_config(config: &HttpCacheConfig) -> Result<Self> {
+        Self::open(&config.path, config.ttl).await
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The entry for `url` if it was stored or revalidated within `ttl`
+    pub async fn lookup(&self, url: &str, ttl: Duration) -> Result<Option<CachedResponse>> {
+        let oldest = now() - ttl.as_secs().min(i64::MAX as u64) as i64;
+        let row = sqlx::query(
+            "SELECT etag, last_modified, status, headers, body FROM http_cache WHERE url = ? AND stored_at >= ?",
+        )
+        .bind(url)
+        .bind(oldest)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let headers: String = row.try_get("headers")?;
+        Ok(Some(CachedResponse {
+            etag: row.try_get("etag")?,
+            last_modified: row.try_get("last_modified")?,
+            response: HttpResponse {
+                status: row.try_get::<i64, _>("status")? as u16,
+                headers: serde_json::from_str(&headers).context("Corrupt headers in HTTP cache")?,
+                body: row.try_get("body")?,
+            },
+        }))
+    }
+
+    /// Keep a `200` response for revalidation; returns `false` when it has no validators
+    pub async fn store(&self, url: &str, response: &HttpResponse) -> Result<bool> {
+        let etag = response.header("etag");
+        let last_modified = response.header("last-modified");
+        if response.status != 200 || (etag.is_none() && last_modified.is_none()) {
+            return Ok(false);
+        }
+        sqlx::query(
+            "INSERT INTO http_cache (url, etag, last_modified, status, headers, body, stored_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (url) DO UPDATE SET etag = excluded.etag, last_modified = excluded.last_modified,
+                status = excluded.status, headers = excluded.headers, body = excluded.body, stored_at = excluded.stored_at",
+        )
+        .bind(url)
+        .bind(etag)
+        .bind(last_modified)
+        .bind(response.status as i64)
+        .bind(serde_json::to_string(&response.headers)?)
+        .bind(&response.body)
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+        Ok(true)
+    }
+
+    /// Restart the TTL of `url` after the server confirmed it is unchanged
+    pub async fn touch(&self, url: &str) -> Result<()> {
+        sqlx::query("UPDATE http_cache SET stored_at = ? WHERE url = ?")
+            .bind(now())
+            .bind(url)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(headers: &[(&str, &str)]) -> HttpResponse {
+        HttpResponse {
+            status: 200,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: b"%PDF-1.7 Preisblatt".to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_validated_pages_are_stored_and_expire_with_the_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HttpCache::open(&dir.path().join("cache/http.sqlite"), Duration::from_secs(60)).await.unwrap();
+        let url = "https://www.netze-bw.de/preisblatt-2024.pdf";
+
+        assert!(!cache.store(url, &page(&[("Content-Type", "application/pdf")])).await.unwrap());
+        assert_eq!(cache.lookup(url, cache.ttl()).await.unwrap(), None);
+
+        let response = page(&[("ETag", "\"v1\""), ("Last-Modified", "Mon, 01 Jan 2024 00:00:00 GMT")]);
+        assert!(cache.store(url, &response).await.unwrap());
+        let stored = cache.lookup(url, cache.ttl()).await.unwrap().unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(stored.last_modified.as_deref(), Some("Mon, 01 Jan 2024 00:00:00 GMT"));
+        assert_eq!(stored.response, response);
+
+        sqlx::query("UPDATE http_cache SET stored_at = stored_at - 120").execute(&cache.pool).await.unwrap();
+        assert_eq!(cache.lookup(url, cache.ttl()).await.unwrap(), None);
+        assert!(cache.lookup(url, Duration::from_secs(600)).await.unwrap().is_some());
+        cache.touch(url).await.unwrap();
+        assert!(cache.lookup(url, cache.ttl()).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::cache::HttpCache;
+use super::client::{build_client, HttpClientConfig};
+use super::transport::{HttpRequest, HttpResponse, HttpTransport, LiveTransport};
+
+/// The HTTP client every crawler component shares: a transport built from
+/// one `HttpClientConfig`, with backoff retries for rate-limited (429) and
+/// unavailable (503) responses. A `Retry-After` header (seconds or HTTP
+/// date) sets the wait; otherwise it doubles from `retry_base_delay`.
+/// Other failures are returned as they are, for `FailureRecoverySystem`.
+///
+/// With an `HttpCache`, GET requests for stored pages carry `If-None-Match`
+/// and `If-Modified-Since`, and a `304` answer returns the stored body.
+pub struct CrawlerClient {
+    inner: Arc<dyn HttpTransport>,
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    cache: Option<Arc<HttpCache>>,
+}
+
+impl CrawlerClient {
+    /// A live client with the strict and relaxed-TLS reqwest clients and the
+    /// proxy pool from `config`
+    pub fn new(config: &HttpClientConfig) -> Result<Self> {
+        let live = LiveTransport::new(build_client(config)?).with_relaxed_tls(config)?.with_proxy_pool(config)?;
+        Ok(Self::with_transport(Arc::new(live), config))
+    }
+
+    /// Retry over another transport, e.g. a recording or replaying one
+    pub fn with_transport(inner: Arc<dyn HttpTransport>, config: &HttpClientConfig) -> Self {
+        Self {
+            inner,
+            max_retries: config.max_retries,
+            base_delay: config.retry_base_delay,
+            max_delay: config.retry_max_delay,
+            cache: None,
+        }
+    }
+
+    /// Revalidate pages stored in `cache` instead of downloading them again
+    pub fn with_cache(mut self, cache: Arc<HttpCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Wait before retry number `attempt` (0-based); `None` when the server
+    /// asks for longer than `max_delay`, so the crawl moves on instead of stalling
+    fn delay(&self, response: &HttpResponse, attempt: u32) -> Option<Duration> {
+        match response.header("retry-after").and_then(parse_retry_after) {
+            Some(wait) if wait > self.max_delay => None,
+            Some(wait) => Some(wait),
+            None => Some(self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay)),
+        }
+    }
+
+    /// Send `request`, retrying 429 and 503 answers
+    async fn execute_with_retries(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut attempt = 0;
+        loop {
+            let response = self.inner.execute(request.clone()).await?;
+            if !matches!(response.status, 429 | 503) || attempt >= self.max_retries {
+                return Ok(response);
+            }
+            let Some(delay) = self.delay(&response, attempt) else {
+                warn!("{} answered {} and asks to wait longer than {:?}, not retrying", request.url, response.status, self.max_delay);
+                return Ok(response);
+            };
+            debug!("{} answered {}, retry {} of {} in {:?}", request.url, response.status, attempt + 1, self.max_retries, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// `Retry-After` as delay seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+#[async_trait]
+impl HttpTransport for CrawlerClient {
+    async fn execute(&self, mut request: HttpRequest) -> Result<HttpResponse> {
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|_| request.method.eq_ignore_ascii_case("GET") && !request.cache.bypass);
+        let Some(cache) = cache else {
+            return self.execute_with_retries(request).await;
+        };
+
+        let url = request.url.clone();
+        let ttl = request.cache.ttl.unwrap_or(cache.ttl());
+        // A broken cache file must not fail the crawl, only make it download everything
+        let stored = cache.lookup(&url, ttl).await.unwrap_or_else(|e| {
+            warn!("HTTP cache lookup for {} failed: {:#}", url, e);
+            None
+        });
+        if let Some(stored) = &stored {
+            if let Some(etag) = &stored.etag {
+                request = request.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &stored.last_modified {
+                request = request.header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let response = self.execute_with_retries(request).await?;
+        match stored {
+            Some(stored) if response.status == 304 => {
+                debug!("{} is unchanged, reusing the stored body", url);
+                if let Err(e) = cache.touch(&url).await {
+                    warn!("HTTP cache refresh for {} failed: {:#}", url, e);
+                }
+                Ok(stored.response)
+            }
+            _ => {
+                if let Err(e) = cache.store(&url, &response).await {
+                    warn!("HTTP cache write for {} failed: {:#}", url, e);
+                }
+                Ok(response)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::cache::CacheDirective;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers 503 with `Retry-After: 0` to the first request, then 200
+    async fn overloaded_server(requests: Arc<AtomicUsize>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+                let response: &[u8] = if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                    b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\nConnection: close\r\n\r\nPreisblatt ok"
+                };
+                socket.write_all(response).await.unwrap();
+            }
+        });
+        port
+    }
+
+    /// Serves a Preisblatt with `ETag: "v1"` and answers a matching
+    /// `If-None-Match` with 304; keeps the raw requests it received
+    async fn validating_server(requests: Arc<std::sync::Mutex<Vec<String>>>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let read = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..read]).to_lowercase();
+                let response: &[u8] = if request.contains("if-none-match: \"v1\"") {
+                    b"HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 13\r\nConnection: close\r\n\r\nPreisblatt ok"
+                };
+                requests.lock().unwrap().push(request);
+                socket.write_all(response).await.unwrap();
+            }
+        });
+        port
+    }
+
+    async fn cached_client(dir: &tempfile::TempDir) -> CrawlerClient {
+        let config = HttpClientConfig::default();
+        let live = LiveTransport::new(build_client(&config).unwrap()).trust_host("127.0.0.1");
+        let cache = HttpCache::open(&dir.path().join("http.sqlite"), Duration::from_secs(3600)).await.unwrap();
+        CrawlerClient::with_transport(Arc::new(live), &config).with_cache(Arc::new(cache))
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_page_is_revalidated_not_downloaded() {
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let port = validating_server(requests.clone()).await;
+        let dir = tempfile::tempdir().unwrap();
+        let client = cached_client(&dir).await;
+        let url = format!("http://127.0.0.1:{}/preisblatt.pdf", port);
+
+        let first = client.execute(HttpRequest::get(&url)).await.unwrap();
+        let second = client.execute(HttpRequest::get(&url)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        // The 304 carried no body; the stored one is returned in its place
+        assert_eq!(second.status, 200);
+        assert_eq!(second.text(), "Preisblatt ok");
+        assert_eq!(second, first);
+    }
+
+    #[tokio::test]
+    async fn test_bypass_fetches_without_validators() {
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let port = validating_server(requests.clone()).await;
+        let dir = tempfile::tempdir().unwrap();
+        let client = cached_client(&dir).await;
+        let url = format!("http://127.0.0.1:{}/preisblatt.pdf", port);
+
+        client.execute(HttpRequest::get(&url)).await.unwrap();
+        let bypass = CacheDirective { bypass: true, ..Default::default() };
+        client.execute(HttpRequest::get(&url).with_cache(bypass)).await.unwrap();
+        // With a zero TTL the page stored a second ago has expired
+        let expired = CacheDirective { ttl: Some(Duration::ZERO), ..Default::default() };
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let response = client.execute(HttpRequest::get(&url).with_cache(expired)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|request| !request.contains("if-none-match")));
+        assert_eq!(response.text(), "Preisblatt ok");
+    }
+
+    #[tokio::test]
+    async fn test_503_is_retried_until_the_server_recovers() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let port = overloaded_server(requests.clone()).await;
+        let config = HttpClientConfig { retry_base_delay: Duration::from_millis(10), ..Default::default() };
+        let live = LiveTransport::new(build_client(&config).unwrap()).trust_host("127.0.0.1");
+        let client = CrawlerClient::with_transport(Arc::new(live), &config);
+
+        let response = client.execute(HttpRequest::get(format!("http://127.0.0.1:{}/preisblatt", port))).await.unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.text(), "Preisblatt ok");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_the_limit_and_on_long_retry_after() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let port = overloaded_server(requests.clone()).await;
+        let config = HttpClientConfig { max_retries: 0, ..Default::default() };
+        let live = LiveTransport::new(build_client(&config).unwrap()).trust_host("127.0.0.1");
+        let client = CrawlerClient::with_transport(Arc::new(live), &config);
+
+        let response = client.execute(HttpRequest::get(format!("http://127.0.0.1:{}/", port))).await.unwrap();
+        assert_eq!(response.status, 503);
+
+        let busy = HttpResponse { status: 429, headers: vec![("Retry-After".to_string(), "3600".to_string())], body: Vec::new() };
+        assert_eq!(client.delay(&busy, 0), None);
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+    }
+}
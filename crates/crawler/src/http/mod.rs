@@ -0,0 +1,16 @@
+pub mod cache;
+pub mod client;
+pub mod crawler_client;
+pub mod proxy;
+pub mod redirect;
+pub mod transport;
+
+pub use transport::{
+    Cassette, HttpRequest, HttpResponse, HttpTransport, LiveTransport, RecordingTransport,
+    ReplayTransport, TransportMode,
+};
+pub use cache::{CacheDirective, CachedResponse, HttpCache, HttpCacheConfig};
+pub use crawler_client::CrawlerClient;
+pub use proxy::{PoolProxy, ProxyPool, ProxyRotation};
+pub use client::{build_client, build_relaxed_client, validate_target, HttpClientConfig, ProxyConfig};
+pub use redirect::{follow_redirects, RedirectChain, RedirectError, RedirectHop};
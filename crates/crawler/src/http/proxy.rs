@@ -0,0 +1,282 @@
+use super::client::{build_client, build_relaxed_client, redact, HttpClientConfig, ProxyConfig};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::debug;
+
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+/// How `ProxyPool` picks a proxy for a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyRotation {
+    /// The next proxy of the pool for every request
+    #[default]
+    RoundRobin,
+    /// A host keeps the proxy it got first, so a site sees one address per crawl
+    Sticky,
+}
+
+impl ProxyRotation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "round_robin" | "round-robin" => Some(Self::RoundRobin),
+            "sticky" | "sticky_per_host" => Some(Self::Sticky),
+            _ => None,
+        }
+    }
+}
+
+/// One proxy of the pool with the clients that go through it
+pub struct PoolProxy {
+    /// The proxy URL without credentials, for errors and logs
+    pub label: String,
+    pub client: reqwest::Client,
+    /// Client without certificate verification for `allow_invalid_certs` hosts
+    pub relaxed: Option<reqwest::Client>,
+}
+
+/// Proxies a `LiveTransport` spreads its requests over, from
+/// `ProxyConfig::pool`. Hosts on the `no_proxy` list go out directly.
+pub struct ProxyPool {
+    proxies: Vec<PoolProxy>,
+    rotation: ProxyRotation,
+    /// Hosts and domains that bypass the pool
+    bypass: Vec<String>,
+    next: AtomicUsize,
+    /// Proxy index per host, for `ProxyRotation::Sticky`
+    assigned: Mutex<HashMap<String, usize>>,
+}
+
+impl ProxyPool {
+    /// The pool of `config.proxy`; `None` when it lists no proxies
+    pub fn from_config(config: &HttpClientConfig) -> Result<Option<Self>> {
+        if config.proxy.pool.is_empty() {
+            return Ok(None);
+        }
+        let mut proxies = Vec::with_capacity(config.proxy.pool.len());
+        for url in &config.proxy.pool {
+            let parsed = url::Url::parse(url).with_context(|| format!("Invalid proxy URL: {}", redact(url)))?;
+            if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+                bail!("Unsupported proxy scheme in {} (supported: {})", redact(url), PROXY_SCHEMES.join(", "));
+            }
+            // Each client sends everything through its one proxy; the pool applies `no_proxy` itself
+            let single = HttpClientConfig {
+                proxy: ProxyConfig { all: Some(url.clone()), ..ProxyConfig::default() },
+                ..config.clone()
+            };
+            proxies.push(PoolProxy {
+                label: redact(url),
+                client: build_client(&single)?,
+                relaxed: build_relaxed_client(&single)?,
+            });
+        }
+        let bypass = config
+            .proxy
+            .no_proxy
+            .iter()
+            .flat_map(|list| list.split(','))
+            .map(|entry| entry.trim().trim_start_matches('.').to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        debug!("Crawler requests rotate over {} proxies ({:?})", proxies.len(), config.proxy.rotation);
+        Ok(Some(Self {
+            proxies,
+            rotation: config.proxy.rotation,
+            bypass,
+            next: AtomicUsize::new(0),
+            assigned: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.proxies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+    }
+
+    /// Whether requests to `host` skip the pool: `*`, the host itself or a
+    /// domain it belongs to is on the `no_proxy` list
+    pub fn bypasses(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.bypass
+            .iter()
+            .any(|entry| entry == "*" || host == *entry || host.ends_with(&format!(".{}", entry)))
+    }
+
+    /// The proxy a request to `host` goes through
+    pub fn select(&self, host: &str) -> &PoolProxy {
+        let next = || self.next.fetch_add(1, Ordering::Relaxed) % self.proxies.len();
+        let index = match self.rotation {
+            ProxyRotation::RoundRobin => next(),
+            ProxyRotation::Sticky => *self.assigned.lock().unwrap().entry(host.to_lowercase()).or_insert_with(next),
+        };
+        &self.proxies[index]
+    }
+
+    /// Forget the proxy `host` sticks to, after the proxy failed, so its
+    /// next request tries another one
+    pub fn release(&self, host: &str) {
+        self.assigned.lock().unwrap().remove(&host.to_lowercase());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CrawlError;
+    use crate::http::{HttpRequest, HttpTransport, LiveTransport};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// An HTTP proxy answering every request with its `name`; keeps the
+    /// request lines it received
+    async fn http_proxy(name: &'static str, seen: Arc<Mutex<Vec<String>>>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let read = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..read]).to_string();
+                seen.lock().unwrap().push(request.lines().next().unwrap_or_default().to_string());
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    name.len(),
+                    name
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    /// A SOCKS5 proxy without authentication; keeps the target each
+    /// connection asked for, as `host:port` or `resolved` when the client sent an address
+    async fn socks5_proxy(targets: Arc<Mutex<Vec<String>>>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut greeting = [0u8; 2];
+                socket.read_exact(&mut greeting).await.unwrap();
+                let mut methods = vec![0u8; greeting[1] as usize];
+                socket.read_exact(&mut methods).await.unwrap();
+                socket.write_all(&[5, 0]).await.unwrap();
+
+                // VER CMD RSV ATYP, then the target
+                let mut head = [0u8; 4];
+                socket.read_exact(&mut head).await.unwrap();
+                let target = if head[3] == 3 {
+                    let mut len = [0u8; 1];
+                    socket.read_exact(&mut len).await.unwrap();
+                    let mut name = vec![0u8; len[0] as usize];
+                    socket.read_exact(&mut name).await.unwrap();
+                    let mut port = [0u8; 2];
+                    socket.read_exact(&mut port).await.unwrap();
+                    format!("{}:{}", String::from_utf8_lossy(&name), u16::from_be_bytes(port))
+                } else {
+                    "resolved".to_string()
+                };
+                targets.lock().unwrap().push(target);
+                socket.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await.unwrap();
+
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+                socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\ntor")
+                    .await
+                    .unwrap();
+            }
+        });
+        addr
+    }
+
+    fn transport(proxy: ProxyConfig) -> LiveTransport {
+        let config = HttpClientConfig { proxy, ..Default::default() };
+        LiveTransport::new(build_client(&config).unwrap()).with_proxy_pool(&config).unwrap()
+    }
+
+    async fn fetch(transport: &LiveTransport, url: &str) -> anyhow::Result<String> {
+        Ok(transport.execute(HttpRequest::get(url)).await?.text())
+    }
+
+    #[tokio::test]
+    async fn test_requests_rotate_over_the_pool() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let a = http_proxy("a", seen.clone()).await;
+        let b = http_proxy("b", seen.clone()).await;
+        let pool = vec![format!("http://{}", a), format!("http://{}", b)];
+
+        let round_robin = transport(ProxyConfig { pool: pool.clone(), ..Default::default() });
+        let mut via = Vec::new();
+        for _ in 0..4 {
+            via.push(fetch(&round_robin, "http://netze.example/netzentgelte").await.unwrap());
+        }
+        assert_eq!(via, ["a", "b", "a", "b"]);
+        assert!(seen.lock().unwrap().iter().all(|line| line == "GET http://netze.example/netzentgelte HTTP/1.1"));
+
+        let sticky = transport(ProxyConfig { pool, rotation: ProxyRotation::Sticky, ..Default::default() });
+        let mut via = Vec::new();
+        for url in ["http://netze.example/", "http://westnetz.example/", "http://netze.example/preise", "http://westnetz.example/preise"] {
+            via.push(fetch(&sticky, url).await.unwrap());
+        }
+        assert_eq!(via, ["a", "b", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_proxy_is_a_proxy_error() {
+        let dead = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let live = http_proxy("live", Arc::new(Mutex::new(Vec::new()))).await;
+        let pool = vec![format!("http://user:secret@{}", dead), format!("http://{}", live)];
+        let transport = transport(ProxyConfig { pool, rotation: ProxyRotation::Sticky, ..Default::default() });
+
+        let error = CrawlError::from_anyhow(fetch(&transport, "http://netze.example/").await.unwrap_err());
+        match error {
+            CrawlError::Proxy { proxy, url, .. } => {
+                assert_eq!(proxy, format!("http://***@{}/", dead));
+                assert_eq!(url, "http://netze.example/");
+            }
+            other => panic!("expected a proxy error, got {:?}", other),
+        }
+        // The host lost its dead proxy and moves on to the next one
+        assert_eq!(fetch(&transport, "http://netze.example/").await.unwrap(), "live");
+    }
+
+    #[tokio::test]
+    async fn test_tor_mode_leaves_dns_to_the_proxy() {
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let tor = socks5_proxy(targets.clone()).await;
+        let transport = transport(ProxyConfig::default().with_tor(&tor.to_string()));
+
+        assert_eq!(fetch(&transport, "http://netze.example/netzentgelte").await.unwrap(), "tor");
+        assert_eq!(*targets.lock().unwrap(), ["netze.example:80"]);
+    }
+
+    #[test]
+    fn test_no_proxy_hosts_bypass_the_pool() {
+        let config = HttpClientConfig {
+            proxy: ProxyConfig {
+                pool: vec!["socks5h://127.0.0.1:9050".to_string()],
+                no_proxy: Some("searxng, .intern.example".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let pool = ProxyPool::from_config(&config).unwrap().unwrap();
+        assert!(pool.bypasses("searxng") && pool.bypasses("wiki.intern.example"));
+        assert!(!pool.bypasses("www.netze-bw.de"));
+
+        let ftp = HttpClientConfig { proxy: ProxyConfig { pool: vec!["ftp://proxy:21".to_string()], ..Default::default() }, ..Default::default() };
+        assert!(ProxyPool::from_config(&ftp).is_err());
+        assert!(ProxyPool::from_config(&HttpClientConfig::default()).unwrap().is_none());
+    }
+}
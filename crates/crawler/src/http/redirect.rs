@@ -0,0 +1,151 @@
+use super::transport::{HttpRequest, HttpResponse, HttpTransport};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::debug;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedirectError {
+    #[error("Redirect loop: {} -> {}", chain.join(" -> "), repeated)]
+    RedirectLoop { chain: Vec<String>, repeated: String },
+
+    #[error("Too many redirects (max {max}) starting at {}", chain.first().map(String::as_str).unwrap_or_default())]
+    TooManyRedirects { max: usize, chain: Vec<String> },
+
+    #[error("Redirect {status} from {url} has no usable Location header")]
+    MissingLocation { url: String, status: u16 },
+}
+
+/// One redirect response in a captured chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub url: String,
+    pub status: u16,
+    pub location: String,
+}
+
+/// Redirects taken to reach the final response
+#[derive(Debug, Clone)]
+pub struct RedirectChain {
+    pub hops: Vec<RedirectHop>,
+    pub final_url: String,
+    pub response: HttpResponse,
+}
+
+/// Follow redirects by hand, recording every hop. The transport's client must
+/// not follow redirects itself (`HttpClientConfig::max_redirects = 0`).
+/// Fails with `RedirectError::RedirectLoop` as soon as a URL repeats, rather
+/// than spending the whole `max_redirects` budget going in circles.
+pub async fn follow_redirects(
+    transport: &dyn HttpTransport,
+    request: HttpRequest,
+    max_redirects: usize,
+) -> Result<RedirectChain> {
+    let mut request = request;
+    let mut hops = Vec::new();
+    let mut visited = HashSet::from([normalize(&request.url)]);
+
+    loop {
+        let response = transport.execute(request.clone()).await?;
+        if !is_redirect(response.status) {
+            return Ok(RedirectChain { hops, final_url: request.url, response });
+        }
+
+        let location = response
+            .header("location")
+            .and_then(|location| url::Url::parse(&request.url).ok()?.join(location).ok())
+            .ok_or_else(|| RedirectError::MissingLocation { url: request.url.clone(), status: response.status })?
+            .to_string();
+
+        hops.push(RedirectHop { url: request.url.clone(), status: response.status, location: location.clone() });
+        let chain = || hops.iter().map(|hop| hop.url.clone()).collect::<Vec<_>>();
+
+        if !visited.insert(normalize(&location)) {
+            return Err(RedirectError::RedirectLoop { chain: chain(), repeated: location }.into());
+        }
+        if hops.len() > max_redirects {
+            return Err(RedirectError::TooManyRedirects { max: max_redirects, chain: chain() }.into());
+        }
+
+        debug!("Redirect {} {} -> {}", response.status, request.url, location);
+        // 301/302/303 turn into a GET without body; 307/308 repeat the request as is
+        if matches!(response.status, 301..=303) {
+            request.method = "GET".to_string();
+            request.body.clear();
+        }
+        request.url = location;
+    }
+}
+
+fn is_redirect(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// URLs that differ only in fragment or default port are the same hop
+/// (parsing drops default ports)
+fn normalize(url: &str) -> String {
+    url::Url::parse(url)
+        .map(|mut parsed| {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        })
+        .unwrap_or_else(|_| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{build_client, HttpClientConfig, LiveTransport};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves `/a` -> `/b` -> `/a` forever
+    async fn looping_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let target = if request.starts_with("GET /a ") { "/b" } else { "/a" };
+                let response = format!(
+                    "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    target
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        base
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_is_detected() {
+        let base = looping_server().await;
+        let client = build_client(&HttpClientConfig { max_redirects: 0, ..Default::default() }).unwrap();
+        let transport = LiveTransport::new(client).trust_host("127.0.0.1");
+
+        let error = follow_redirects(&transport, HttpRequest::get(format!("{}/a", base)), 10)
+            .await
+            .unwrap_err();
+
+        match error.downcast_ref::<RedirectError>() {
+            Some(RedirectError::RedirectLoop { chain, repeated }) => {
+                assert_eq!(chain, &vec![format!("{}/a", base), format!("{}/b", base)]);
+                assert_eq!(repeated, &format!("{}/a", base));
+            }
+            other => panic!("expected RedirectLoop, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_stops_after_max_redirects() {
+        let base = looping_server().await;
+        let client = build_client(&HttpClientConfig { max_redirects: 3, ..Default::default() }).unwrap();
+
+        let error = client.get(format!("{}/a", base)).send().await.unwrap_err();
+        assert!(error.is_redirect());
+    }
+}
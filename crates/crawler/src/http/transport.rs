@@ -0,0 +1,551 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn, Instrument};
+
+use super::cache::{CacheDirective, HttpCache};
+use super::client::{build_client, build_relaxed_client, internal_service_hosts, HttpClientConfig};
+use super::proxy::{PoolProxy, ProxyPool};
+use super::crawler_client::CrawlerClient;
+use crate::error::CrawlError;
+
+/// Transport-agnostic HTTP request used by the crawler clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    #[serde(with = "body_encoding")]
+    pub body: Vec<u8>,
+    /// Only read by a `CrawlerClient` with an HTTP cache; not recorded in cassettes
+    #[serde(skip)]
+    pub cache: CacheDirective,
+}
+
+impl HttpRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            method: "GET".to_string(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            cache: CacheDirective::default(),
+        }
+    }
+
+    pub fn with_cache(mut self, cache: CacheDirective) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Append query parameters to the request URL
+    pub fn query(mut self, params: &[(&str, &str)]) -> Result<Self> {
+        let mut url = url::Url::parse(&self.url)
+            .with_context(|| format!("Invalid request URL: {}", self.url))?;
+        url.query_pairs_mut().extend_pairs(params);
+        self.url = url.to_string();
+        Ok(self)
+    }
+}
+
+/// Transport-agnostic HTTP response returned to the crawler clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    #[serde(with = "body_encoding")]
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).context("Failed to decode JSON response body")
+    }
+}
+
+/// Pluggable HTTP transport so crawls can run live, be recorded, or be replayed
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+/// Transport that performs real network requests through reqwest
+#[derive(Clone)]
+pub struct LiveTransport {
+    client: reqwest::Client,
+    /// Internal hosts the crawler itself depends on (the SearXNG instance), exempt from the SSRF guard
+    trusted_hosts: Vec<String>,
+    /// Client without certificate verification and the hosts it may be used for
+    relaxed_tls: Option<(reqwest::Client, Vec<String>)>,
+    /// Proxies requests rotate over, instead of `client`'s own proxy settings;
+    /// clones share the pool and its rotation
+    proxies: Option<Arc<ProxyPool>>,
+}
+
+impl LiveTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client, trusted_hosts: internal_service_hosts(), relaxed_tls: None, proxies: None }
+    }
+
+    /// Send requests to `config.allow_invalid_certs` hosts through a client that
+    /// skips certificate verification; all other hosts keep the strict client
+    pub fn with_relaxed_tls(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.relaxed_tls = build_relaxed_client(config)?
+            .map(|client| (client, config.allow_invalid_certs.clone()));
+        Ok(self)
+    }
+
+    /// Spread requests over the proxy pool of `config.proxy`, if it has one
+    pub fn with_proxy_pool(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.proxies = ProxyPool::from_config(config)?.map(Arc::new);
+        Ok(self)
+    }
+
+    /// Exempt an internal host (e.g. a local SearXNG) from the SSRF guard
+    pub fn trust_host(mut self, host: impl Into<String>) -> Self {
+        self.trusted_hosts.push(host.into());
+        self
+    }
+
+    /// The client for `url` and the pool proxy it goes through, logging an
+    /// audit warning whenever verification is relaxed
+    fn client_for(&self, url: &str, host: Option<&str>) -> (&reqwest::Client, Option<&PoolProxy>) {
+        let Some(host) = host else {
+            return (&self.client, None);
+        };
+        // Our own services are never reached through the pool
+        let proxy = self
+            .proxies
+            .as_ref()
+            .filter(|pool| !pool.bypasses(host) && !self.is_trusted(url))
+            .map(|pool| pool.select(host));
+        let relaxed = match &self.relaxed_tls {
+            Some((client, hosts)) if hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) => {
+                warn!(target: "audit", host = %host, url = %url, "Connecting without TLS certificate verification");
+                Some(client)
+            }
+            _ => None,
+        };
+        match (proxy, relaxed) {
+            (Some(proxy), Some(_)) => (proxy.relaxed.as_ref().unwrap_or(&proxy.client), Some(proxy)),
+            (Some(proxy), None) => (&proxy.client, Some(proxy)),
+            (None, Some(relaxed)) => (relaxed, None),
+            (None, None) => (&self.client, None),
+        }
+    }
+
+    fn is_trusted(&self, url: &str) -> bool {
+        url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .is_some_and(|host| self.trusted_hosts.iter().any(|t| t.eq_ignore_ascii_case(&host)))
+    }
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        // Guard the real target even when the client sends it through a proxy
+        let trusted = self.is_trusted(&request.url);
+        if !trusted {
+            super::client::validate_target(&request.url)?;
+        }
+        // Trace context only goes to our own services (SearXNG, Ollama), never to DNO sites
+        #[cfg(feature = "otel")]
+        let request = {
+            let mut request = request;
+            if trusted {
+                request.headers.extend(dno_core::telemetry::trace_headers(&tracing::Span::current()));
+            }
+            request
+        };
+
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .with_context(|| format!("Invalid HTTP method: {}", request.method))?;
+
+        let host = url::Url::parse(&request.url).ok().and_then(|url| url.host_str().map(str::to_string));
+        let (client, proxy) = self.client_for(&request.url, host.as_deref());
+        let mut builder = client.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body.clone());
+        }
+
+        let response = builder.send().await.map_err(|e| match proxy {
+            // Through a proxy, the connection we open is the one to the proxy
+            Some(proxy) if e.is_connect() => {
+                if let (Some(pool), Some(host)) = (&self.proxies, &host) {
+                    pool.release(host);
+                }
+                CrawlError::Proxy { proxy: proxy.label.clone(), url: request.url.clone(), message: e.to_string() }
+            }
+            _ => CrawlError::from_reqwest(&request.url, e),
+        })?;
+        let status = response.status().as_u16();
+        tracing::Span::current().record("http.status_code", status);
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value.to_str().ok().map(|v| (name.to_string(), v.to_string()))
+            })
+            .collect();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| CrawlError::from_reqwest(&request.url, e))?
+            .to_vec();
+
+        Ok(HttpResponse { status, headers, body })
+    }
+}
+
+#[async_trait]
+impl HttpTransport for LiveTransport {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let span = tracing::info_span!(
+            "crawl.fetch",
+            otel.kind = "client",
+            http.method = %request.method,
+            http.url = %request.url,
+            http.status_code = tracing::field::Empty,
+        );
+        self.send(request).instrument(span).await
+    }
+}
+
+/// A single recorded request/response pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: HttpRequest,
+    pub response: HttpResponse,
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Interaction {
+    /// Key used to match replayed requests; headers are ignored so that
+    /// user-agent or auth changes don't invalidate a cassette
+    fn match_key(request: &HttpRequest) -> String {
+        use sha2::{Digest, Sha256};
+        let body_hash = format!("{:x}", Sha256::digest(&request.body));
+        format!("{} {} {}", request.method.to_uppercase(), request.url, &body_hash[..16])
+    }
+}
+
+/// VCR-style cassette file holding recorded interactions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub version: u32,
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse cassette {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write cassette {}", path.display()))
+    }
+}
+
+/// Transport that forwards to an inner transport and records every interaction
+pub struct RecordingTransport {
+    inner: Arc<dyn HttpTransport>,
+    cassette_path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn HttpTransport>, cassette_path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            cassette_path: cassette_path.into(),
+            cassette: Mutex::new(Cassette { version: 1, interactions: Vec::new() }),
+        }
+    }
+
+    pub fn interaction_count(&self) -> usize {
+        self.cassette.lock().unwrap().interactions.len()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for RecordingTransport {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let response = self.inner.execute(request.clone()).await?;
+
+        // Persist after every interaction so a crashed crawl still leaves a usable cassette
+        let snapshot = {
+            let mut cassette = self.cassette.lock().unwrap();
+            cassette.interactions.push(Interaction {
+                request,
+                response: response.clone(),
+                recorded_at: chrono::Utc::now(),
+            });
+            cassette.clone()
+        };
+        snapshot.save(&self.cassette_path)?;
+
+        debug!("Recorded interaction #{} to {}", snapshot.interactions.len(), self.cassette_path.display());
+        Ok(response)
+    }
+}
+
+/// Transport that serves responses from a cassette without touching the network
+pub struct ReplayTransport {
+    interactions: HashMap<String, Vec<HttpResponse>>,
+    cursors: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        let mut interactions: HashMap<String, Vec<HttpResponse>> = HashMap::new();
+        for interaction in cassette.interactions {
+            interactions
+                .entry(Interaction::match_key(&interaction.request))
+                .or_default()
+                .push(interaction.response);
+        }
+
+        Self {
+            interactions,
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReplayTransport {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let key = Interaction::match_key(&request);
+        let responses = self.interactions.get(&key).ok_or_else(|| {
+            anyhow!("No recorded interaction for {} {}", request.method, request.url)
+        })?;
+
+        // Identical requests are served in recorded order; the last response repeats
+        let mut cursors = self.cursors.lock().unwrap();
+        let cursor = cursors.entry(key).or_insert(0);
+        let response = responses[(*cursor).min(responses.len() - 1)].clone();
+        *cursor += 1;
+
+        Ok(response)
+    }
+}
+
+/// How the crawler clients talk to the network
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportMode {
+    Live,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl TransportMode {
+    /// Read `CRAWLER_HTTP_MODE` (live, record, replay) and `CRAWLER_CASSETTE`
+    pub fn from_env() -> Result<Self> {
+        let mode = std::env::var("CRAWLER_HTTP_MODE").unwrap_or_else(|_| "live".to_string());
+        let cassette = || {
+            std::env::var("CRAWLER_CASSETTE")
+                .map(PathBuf::from)
+                .map_err(|_| anyhow!("CRAWLER_CASSETTE is required for {} mode", mode))
+        };
+
+        match mode.to_lowercase().as_str() {
+            "live" => Ok(Self::Live),
+            "record" => Ok(Self::Record(cassette()?)),
+            "replay" => Ok(Self::Replay(cassette()?)),
+            other => Err(anyhow!("Unknown CRAWLER_HTTP_MODE: {}", other)),
+        }
+    }
+
+    /// Build the `CrawlerClient` for this mode from `config`. Recording sits
+    /// below the retries, so every attempt is recorded and replayed in order.
+    pub fn build(&self, config: &HttpClientConfig) -> Result<Arc<dyn HttpTransport>> {
+        Ok(Arc::new(self.client(config)?))
+    }
+
+    /// `build`, revalidating pages stored in `cache` instead of downloading them again
+    pub fn build_with_cache(&self, config: &HttpClientConfig, cache: Arc<HttpCache>) -> Result<Arc<dyn HttpTransport>> {
+        Ok(Arc::new(self.client(config)?.with_cache(cache)))
+    }
+
+    fn client(&self, config: &HttpClientConfig) -> Result<CrawlerClient> {
+        let live = || -> Result<Arc<dyn HttpTransport>> {
+            Ok(Arc::new(LiveTransport::new(build_client(config)?).with_relaxed_tls(config)?.with_proxy_pool(config)?))
+        };
+        let transport: Arc<dyn HttpTransport> = match self {
+            Self::Live => live()?,
+            Self::Record(path) => {
+                info!("Recording crawler HTTP traffic to {}", path.display());
+                Arc::new(RecordingTransport::new(live()?, path.clone()))
+            }
+            Self::Replay(path) => {
+                info!("Replaying crawler HTTP traffic from {}", path.display());
+                Arc::new(ReplayTransport::from_file(path)?)
+            }
+        };
+        Ok(CrawlerClient::with_transport(transport, config))
+    }
+}
+
+/// Bodies are stored base64-encoded so binary documents (PDF, XLSX) survive the cassette
+mod body_encoding {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(body: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(body))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Stand-in for a live DNO site
+    struct FakeSite {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HttpTransport for FakeSite {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let body = match request.url.as_str() {
+                "https://dno.example/" => r#"<a href="/netzentgelte-2024.pdf">2024</a>"#.as_bytes().to_vec(),
+                "https://dno.example/netzentgelte-2024.pdf" => vec![0x25, 0x50, 0x44, 0x46, 0x00, 0xff],
+                _ => return Ok(HttpResponse { status: 404, headers: vec![], body: vec![] }),
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), "text/html".to_string())],
+                body,
+            })
+        }
+    }
+
+    async fn crawl(transport: &dyn HttpTransport) -> Vec<HttpResponse> {
+        let mut responses = Vec::new();
+        for url in ["https://dno.example/", "https://dno.example/netzentgelte-2024.pdf", "https://dno.example/missing"] {
+            responses.push(transport.execute(HttpRequest::get(url)).await.unwrap());
+        }
+        responses
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_is_identical() {
+        let dir = tempfile::tempdir().unwrap();
+        let cassette_path = dir.path().join("crawl.json");
+
+        let site = Arc::new(FakeSite { calls: AtomicUsize::new(0) });
+        let recorder = RecordingTransport::new(site.clone(), &cassette_path);
+        let recorded = crawl(&recorder).await;
+        assert_eq!(recorder.interaction_count(), 3);
+        assert_eq!(site.calls.load(Ordering::SeqCst), 3);
+
+        let replay = ReplayTransport::from_file(&cassette_path).unwrap();
+        let replayed = crawl(&replay).await;
+
+        assert_eq!(recorded, replayed);
+        assert_eq!(site.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_unrecorded_request() {
+        let replay = ReplayTransport::new(Cassette::default());
+        let result = replay.execute(HttpRequest::get("https://dno.example/")).await;
+        assert!(result.is_err());
+    }
+
+    /// HTTPS server for `localhost` with a self-signed certificate, like a
+    /// municipal DNO site with a broken chain
+    async fn self_signed_server() -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_native_tls::native_tls;
+
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let identity =
+            native_tls::Identity::from_pkcs8(cert.pem().as_bytes(), key_pair.serialize_pem().as_bytes()).unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    // Verifying clients abort the handshake
+                    let Ok(mut stream) = acceptor.accept(socket).await else { return };
+                    let mut buf = vec![0u8; 4096];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                        .await;
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_only_allowlisted_hosts_skip_certificate_verification() {
+        let port = self_signed_server().await;
+        let config = HttpClientConfig { allow_invalid_certs: vec!["localhost".to_string()], ..Default::default() };
+        let transport = LiveTransport::new(build_client(&config).unwrap())
+            .with_relaxed_tls(&config)
+            .unwrap()
+            .trust_host("localhost")
+            .trust_host("127.0.0.1");
+
+        let relaxed = transport.execute(HttpRequest::get(format!("https://localhost:{}/", port))).await.unwrap();
+        assert_eq!(relaxed.text(), "ok");
+
+        // The same server under a host that did not opt in is still verified
+        let strict = transport.execute(HttpRequest::get(format!("https://127.0.0.1:{}/", port))).await;
+        assert!(strict.is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::adaptive::{AdaptiveCrawler, CrawlLimits, CrawlResult};
+use crate::http::{HttpClientConfig, HttpTransport, TransportMode};
+use crate::overrides::DnoOverrides;
+use crate::robots::RobotsCache;
+use crate::search::SearchService;
+use anyhow::Result;
+use async_trait::async_trait;
+use dno_core::crawl_job::{CrawlJobSummary, CrawlRunner};
+use dno_core::crawl_plan::CrawlContext;
+use dno_core::live::{LiveCrawlSession, LogLevel};
+use dno_core::source_manager::SourceManager;
+use dno_core::AppError;
+use std::sync::Arc;
+
+/// Runs the jobs of `dno_core::crawl_job::CrawlJobs` as discovery crawls. Every
+/// job gets its own `AdaptiveCrawler`, so jobs of different sessions never
+/// share a queue; transport, storage and overrides are shared.
+pub struct AdaptiveCrawlRunner {
+    transport: Arc<dyn HttpTransport>,
+    sources: Arc<SourceManager>,
+    overrides: Arc<DnoOverrides>,
+    /// Shared, so its circuit breaker sees every job's searches
+    search: Arc<SearchService>,
+    limits: CrawlLimits,
+    user_agent: String,
+}
+
+impl AdaptiveCrawlRunner {
+    pub fn new(transport: Arc<dyn HttpTransport>, sources: Arc<SourceManager>) -> Self {
+        Self {
+            search: Arc::new(SearchService::from_env(transport.clone())),
+            transport,
+            sources,
+            overrides: Arc::new(DnoOverrides::default()),
+            limits: CrawlLimits::default(),
+            user_agent: HttpClientConfig::default().user_agent,
+        }
+    }
+
+    /// Transport from `CRAWLER_HTTP_MODE` and the HTTP client settings, crawl
+    /// limits and DNO overrides from the environment
+    pub fn from_env(sources: Arc<SourceManager>) -> Result<Self> {
+        let config = HttpClientConfig::from_env();
+        let transport = TransportMode::from_env()?.build(&config)?;
+        Ok(Self {
+            search: Arc::new(SearchService::from_env(transport.clone())),
+            transport,
+            sources,
+            overrides: Arc::new(DnoOverrides::from_env()?),
+            limits: CrawlLimits::from_env(),
+            user_agent: config.user_agent,
+        })
+    }
+}
+
+#[async_trait]
+impl CrawlRunner for AdaptiveCrawlRunner {
+    async fn run(&self, context: CrawlContext, session: &LiveCrawlSession) -> Result<CrawlJobSummary, AppError> {
+        let robots = Arc::new(RobotsCache::new(self.transport.clone(), self.user_agent.clone()));
+        let crawler = AdaptiveCrawler::new(self.transport.clone(), self.limits)
+            .with_sources(self.sources.clone())
+            .with_overrides(self.overrides.clone())
+            .with_robots(robots)
+            .with_search(self.search.clone());
+        let result = crawler.execute_discovery_crawl(&context).await;
+        let summary = summarize(&result);
+        session.log(
+            LogLevel::Info,
+            format!(
+                "{} {}: {} pages, {} documents, {} failed URLs ({})",
+                result.dno_slug,
+                result.year,
+                summary.pages_processed,
+                summary.documents_found,
+                summary.failed_urls,
+                summary.completion
+            ),
+        );
+        Ok(summary)
+    }
+}
+
+fn summarize(result: &CrawlResult) -> CrawlJobSummary {
+    CrawlJobSummary {
+        completion: serde_json::to_value(result.completion)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        pages_processed: result.processed_urls.len() as u32,
+        documents_found: result.documents.len() as u32,
+        failed_urls: result.failed_urls.len() as u32,
+        manual_review: result.manual_review.clone(),
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use whatlang::Lang;
+
+/// Chunks shorter than this are too short for reliable detection
+const MIN_CHUNK_CHARS: usize = 40;
+
+/// What to do with a document that is not predominantly German
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguageAction {
+    /// Don't check
+    Off,
+    /// Extract, but mark the result as flagged
+    Flag,
+    /// Don't extract
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LanguagePolicy {
+    pub action: LanguageAction,
+    /// Minimum share of detected text (by characters) that must be German
+    pub min_german_share: f64,
+}
+
+impl Default for LanguagePolicy {
+    fn default() -> Self {
+        Self {
+            action: LanguageAction::Flag,
+            min_german_share: 0.6,
+        }
+    }
+}
+
+impl LanguagePolicy {
+    /// `CRAWLER_LANGUAGE_ACTION` (off | flag | skip) and `CRAWLER_MIN_GERMAN_SHARE` (0.0-1.0)
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let action = match std::env::var("CRAWLER_LANGUAGE_ACTION").as_deref().map(str::trim) {
+            Ok("off") => LanguageAction::Off,
+            Ok("skip") => LanguageAction::Skip,
+            Ok("flag") => LanguageAction::Flag,
+            _ => defaults.action,
+        };
+        let min_german_share = std::env::var("CRAWLER_MIN_GERMAN_SHARE")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|share| (0.0..=1.0).contains(share))
+            .unwrap_or(defaults.min_german_share);
+        Self { action, min_german_share }
+    }
+
+    /// Detect the language mix of `text`. Returns `None` when checking is off
+    /// or there is too little text to judge.
+    pub fn check(&self, text: &str) -> Option<LanguageCheck> {
+        if self.action == LanguageAction::Off {
+            return None;
+        }
+        let check = detect_language(text)?;
+        let is_german = check.german_share >= self.min_german_share;
+        Some(LanguageCheck {
+            flagged: !is_german,
+            skip: !is_german && self.action == LanguageAction::Skip,
+            ..check
+        })
+    }
+}
+
+/// Language detected in a document, recorded in extraction metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageCheck {
+    /// ISO 639-3 code of the dominant language, e.g. `deu`, `eng`
+    pub language: String,
+    /// Share of the detected text (by characters) that is German
+    pub german_share: f64,
+    /// Not predominantly German
+    pub flagged: bool,
+    /// Policy says not to extract this document
+    pub skip: bool,
+}
+
+/// Detect language per paragraph so multilingual pages are judged by their mix
+/// rather than by whichever language whatlang guesses for the whole text
+pub fn detect_language(text: &str) -> Option<LanguageCheck> {
+    let mut chars_by_lang: HashMap<Lang, usize> = HashMap::new();
+
+    for chunk in text.split("\n\n").flat_map(|p| p.split('\n')) {
+        let chunk = chunk.trim();
+        let len = chunk.chars().filter(|c| c.is_alphabetic()).count();
+        if len < MIN_CHUNK_CHARS {
+            continue;
+        }
+        if let Some(info) = whatlang::detect(chunk) {
+            *chars_by_lang.entry(info.lang()).or_default() += len;
+        }
+    }
+
+    // Short documents (e.g. a table with a heading) are judged as a whole
+    if chars_by_lang.is_empty() {
+        let info = whatlang::detect(text)?;
+        chars_by_lang.insert(info.lang(), 1);
+    }
+
+    let total: usize = chars_by_lang.values().sum();
+    let (dominant, _) = chars_by_lang.iter().max_by_key(|(_, chars)| **chars)?;
+    let german = chars_by_lang.get(&Lang::Deu).copied().unwrap_or(0);
+
+    Some(LanguageCheck {
+        language: dominant.code().to_string(),
+        german_share: german as f64 / total as f64,
+        flagged: false,
+        skip: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GERMAN: &str = "Die Netzentgelte für die Nutzung des Stromverteilnetzes gelten ab dem 1. Januar 2024.\n\
+        Der Leistungspreis wird pro Kilowatt und Jahr berechnet, der Arbeitspreis pro Kilowattstunde.\n\
+        Für Entnahmestellen mit weniger als 2500 Benutzungsstunden gelten gesonderte Preise.";
+
+    const ENGLISH: &str = "The network charges for the use of the electricity distribution grid apply from January 2024.\n\
+        The capacity price is charged per kilowatt and year, the energy price per kilowatt hour.\n\
+        Separate prices apply to withdrawal points with fewer than 2500 hours of use per year.";
+
+    #[test]
+    fn test_english_document_is_flagged_and_german_passes() {
+        let policy = LanguagePolicy::default();
+
+        let english = policy.check(ENGLISH).unwrap();
+        assert_eq!(english.language, "eng");
+        assert!(english.flagged);
+        assert!(!english.skip);
+
+        let german = policy.check(GERMAN).unwrap();
+        assert_eq!(german.language, "deu");
+        assert!(!german.flagged);
+    }
+
+    #[test]
+    fn test_skip_policy_and_mostly_german_mix() {
+        let policy = LanguagePolicy { action: LanguageAction::Skip, ..Default::default() };
+        assert!(policy.check(ENGLISH).unwrap().skip);
+
+        // Two German paragraphs and one English one is still predominantly German
+        let mixed = format!("{}\n\n{}", GERMAN, ENGLISH.lines().next().unwrap());
+        let check = policy.check(&mixed).unwrap();
+        assert!(!check.flagged);
+        assert!(check.german_share < 1.0);
+    }
+}
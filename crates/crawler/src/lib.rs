@@ -1 +1,30 @@
-pub mod cli;
\ No newline at end of file
+pub mod error;
+pub mod http;
+pub mod pipeline;
+pub mod extraction;
+pub mod recovery;
+pub mod language;
+pub mod html_table;
+pub mod csv_table;
+pub mod pdf_table;
+pub mod pdf_analysis;
+pub mod spreadsheet;
+pub mod xml;
+pub mod ocr;
+pub mod content;
+pub mod sanitize;
+pub mod delta;
+pub mod archive;
+pub mod queue;
+pub mod robots;
+pub mod search;
+pub mod sitemap;
+pub mod pagination;
+pub mod reconstruct;
+pub mod adaptive;
+pub mod overrides;
+pub mod checkpoint;
+pub mod dno_import;
+pub mod job_runner;
+pub mod reverse;
+pub mod reverse_cli;
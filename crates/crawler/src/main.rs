@@ -15,25 +15,47 @@ struct Cli {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
-    tracing_subscriber::registry()
+    let registry = tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "crawler=debug".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+        .with(tracing_subscriber::fmt::layer());
+
+    // Export spans when OTEL_EXPORTER_OTLP_ENDPOINT is set
+    #[cfg(feature = "otel")]
+    let tracer_provider = dno_core::telemetry::OtelConfig::from_env("dno-crawler")
+        .map(|config| dno_core::telemetry::tracer_provider(&config))
+        .transpose()?;
+    #[cfg(feature = "otel")]
+    let registry = registry.with(tracer_provider.as_ref().map(dno_core::telemetry::layer));
+
+    registry.init();
 
     let cli = Cli::parse();
     
     match cli.command {
-        cli::Commands::Search { query, json } => {
+        cli::Commands::Search { query, json, proxy } => {
             info!("Testing SearXNG connectivity with query: {}", query);
-            cli::handle_search(query, json).await?;
+            cli::handle_search(query, json, proxy).await?;
+        }
+        cli::Commands::ReverseCrawl(args) => {
+            info!("Reverse crawl for DNO: {}", args.dno);
+            cli::handle_reverse_crawl(args).await?;
         }
-        cli::Commands::AiGather { dno, data_types, years, json, max_time, priority } => {
-            info!("AI-driven storage gathering for DNO: {}", dno);
-            cli::handle_ai_gather(dno, data_types, years, json, max_time, priority).await?;
+        cli::Commands::Seed { database_url } => {
+            info!("Seeding known DNOs");
+            cli::handle_seed(database_url).await?;
         }
+        cli::Commands::ImportDnos { file, database_url } => {
+            info!("Importing DNOs from {}", file.display());
+            cli::handle_import_dnos(file, database_url).await?;
+        }
+    }
+
+    #[cfg(feature = "otel")]
+    if let Some(provider) = tracer_provider {
+        let _ = provider.shutdown();
     }
 
     Ok(())
@@ -0,0 +1,208 @@
+use crate::content::ContentType;
+use crate::extraction::{Document, Extraction, Extractor};
+use crate::pipeline::ExtractionMethod;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use dno_core::models::FieldProvenance;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::debug;
+
+/// Tesseract language packs used unless `CRAWLER_OCR_LANGUAGES` names others
+pub const DEFAULT_OCR_LANGUAGES: &str = "deu+eng";
+
+/// Text and confidence returned when the crate is built without the `ocr` feature
+#[cfg(not(feature = "ocr"))]
+const PLACEHOLDER_TEXT: &str = "OCR not implemented";
+#[cfg(not(feature = "ocr"))]
+const PLACEHOLDER_CONFIDENCE: f64 = 0.3;
+
+/// Text recognized in an image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrResult {
+    pub text: String,
+    /// Tesseract's mean word confidence, scaled to 0.0–1.0
+    pub confidence: f64,
+}
+
+/// Reads scanned price sheets (PNG, JPEG, TIFF) with Tesseract. Needs the
+/// `ocr` feature and libtesseract; without them `perform_ocr` returns a
+/// placeholder and the extractor skips images.
+#[derive(Debug, Clone)]
+pub struct ImageProcessor {
+    /// Tesseract language string, e.g. `deu+eng`
+    languages: String,
+}
+
+impl Default for ImageProcessor {
+    fn default() -> Self {
+        Self { languages: DEFAULT_OCR_LANGUAGES.to_string() }
+    }
+}
+
+impl ImageProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read `CRAWLER_OCR_LANGUAGES`; `DEFAULT_OCR_LANGUAGES` when unset or blank
+    pub fn from_env() -> Self {
+        match std::env::var("CRAWLER_OCR_LANGUAGES") {
+            Ok(languages) if !languages.trim().is_empty() => Self::new().with_languages(languages.trim()),
+            _ => Self::new(),
+        }
+    }
+
+    pub fn with_languages(mut self, languages: impl Into<String>) -> Self {
+        self.languages = languages.into();
+        self
+    }
+
+    /// Whether the crate was built with a real OCR backend
+    pub const fn ocr_available() -> bool {
+        cfg!(feature = "ocr")
+    }
+
+    /// Recognize the text of an encoded image. This blocks for up to a few
+    /// seconds per page, so async callers should use `spawn_blocking`.
+    #[cfg(feature = "ocr")]
+    pub fn perform_ocr(&self, image: &[u8]) -> Result<OcrResult> {
+        let mut tesseract = leptess::LepTess::new(None, &self.languages)
+            .with_context(|| format!("Failed to load Tesseract language data '{}'", self.languages))?;
+        tesseract.set_image_from_mem(image).context("Failed to decode image for OCR")?;
+        // Scans rarely carry a usable DPI; without one Tesseract guesses and warns
+        tesseract.set_source_resolution(300);
+        let text = tesseract.get_utf8_text().context("Tesseract returned invalid UTF-8")?;
+        // mean_text_conf averages the per-word confidences (0-100, -1 without words)
+        let confidence = f64::from(tesseract.mean_text_conf().max(0)) / 100.0;
+        Ok(OcrResult { text: text.trim().to_string(), confidence })
+    }
+
+    /// Placeholder for builds without libtesseract
+    #[cfg(not(feature = "ocr"))]
+    pub fn perform_ocr(&self, image: &[u8]) -> Result<OcrResult> {
+        let _ = image;
+        Ok(OcrResult { text: PLACEHOLDER_TEXT.to_string(), confidence: PLACEHOLDER_CONFIDENCE })
+    }
+}
+
+#[async_trait]
+impl Extractor for ImageProcessor {
+    fn method(&self) -> ExtractionMethod {
+        ExtractionMethod::Ocr
+    }
+
+    async fn extract(&self, document: &Document) -> Result<Option<Extraction>> {
+        let content_type = ContentType::detect(&document.url, document.content_type.as_deref(), &document.bytes);
+        if content_type != ContentType::Image {
+            return Ok(None);
+        }
+        if !Self::ocr_available() {
+            debug!("Skipping OCR of {}: built without the ocr feature", document.url);
+            return Ok(None);
+        }
+
+        let processor = self.clone();
+        let bytes = document.bytes.clone();
+        let result = tokio::task::spawn_blocking(move || processor.perform_ocr(&bytes))
+            .await
+            .context("OCR task panicked")?
+            .with_context(|| format!("OCR failed on {}", document.url))?;
+        if result.text.is_empty() {
+            return Ok(None);
+        }
+
+        let mut extraction = Extraction::new(json!({ "text": result.text, "confidence": result.confidence }));
+        extraction.record(
+            "text",
+            json!(result.text),
+            FieldProvenance {
+                source_url: document.url.clone(),
+                page: None,
+                row: None,
+                column: None,
+                selector: None,
+                archive: None,
+                method: ExtractionMethod::Ocr.as_str().to_string(),
+                confidence: result.confidence,
+            },
+        );
+        Ok(Some(extraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 5x7 glyphs for the characters of the rendered test text
+    #[cfg(feature = "ocr")]
+    fn glyph(c: char) -> [&'static str; 7] {
+        match c {
+            'N' => ["10001", "11001", "10101", "10011", "10001", "10001", "10001"],
+            'E' => ["11111", "10000", "10000", "11110", "10000", "10000", "11111"],
+            'T' => ["11111", "00100", "00100", "00100", "00100", "00100", "00100"],
+            'Z' => ["11111", "00001", "00010", "00100", "01000", "10000", "11111"],
+            '2' => ["01110", "10001", "00001", "00010", "00100", "01000", "11111"],
+            '0' => ["01110", "10001", "10011", "10101", "11001", "10001", "01110"],
+            '4' => ["00010", "00110", "01010", "10010", "11111", "00010", "00010"],
+            _ => ["00000"; 7],
+        }
+    }
+
+    /// Black text on white as a binary PGM, each glyph pixel drawn as a
+    /// `scale`×`scale` block
+    #[cfg(feature = "ocr")]
+    fn render(text: &str, scale: usize) -> Vec<u8> {
+        let margin = 4 * scale;
+        let width = text.chars().count() * 6 * scale + 2 * margin;
+        let height = 7 * scale + 2 * margin;
+        let mut pixels = vec![255u8; width * height];
+        for (i, c) in text.chars().enumerate() {
+            for (row, line) in glyph(c).iter().enumerate() {
+                for (column, _) in line.bytes().enumerate().filter(|(_, bit)| *bit == b'1') {
+                    for y in 0..scale {
+                        let x0 = margin + (i * 6 + column) * scale;
+                        let offset = (margin + row * scale + y) * width + x0;
+                        pixels[offset..offset + scale].fill(0);
+                    }
+                }
+            }
+        }
+        let mut image = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+        image.extend(pixels);
+        image
+    }
+
+    #[cfg(feature = "ocr")]
+    #[tokio::test]
+    async fn test_rendered_text_is_recognized() {
+        let document = Document {
+            url: "https://netze.example/scans/preisblatt-2024.pgm".to_string(),
+            content_type: Some("image/png".to_string()),
+            bytes: render("NETZ 2024", 10),
+            ..Default::default()
+        };
+
+        let extraction = ImageProcessor::new().extract(&document).await.unwrap().unwrap();
+
+        let text = extraction.data["text"].as_str().unwrap();
+        assert!(text.contains("NETZ") && text.contains("2024"), "recognized {:?}", text);
+        let field = &extraction.provenance["text"];
+        assert_eq!(field.provenance.method, "ocr");
+        assert!(field.provenance.confidence > 0.5, "confidence {}", field.provenance.confidence);
+    }
+
+    #[cfg(not(feature = "ocr"))]
+    #[tokio::test]
+    async fn test_images_are_skipped_without_the_ocr_feature() {
+        let document = Document {
+            url: "https://netze.example/scans/preisblatt-2024.png".to_string(),
+            bytes: b"\x89PNG\r\n\x1a\n".to_vec(),
+            ..Default::default()
+        };
+
+        assert!(ImageProcessor::new().extract(&document).await.unwrap().is_none());
+        assert_eq!(ImageProcessor::new().perform_ocr(&document.bytes).unwrap().confidence, PLACEHOLDER_CONFIDENCE);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::content::ContentType;
+use anyhow::{anyhow, bail, Context, Result};
+use dno_core::crawl_plan::{CrawlContext, CrawlStrategy};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use url::Url;
+
+const MODES: [&str; 3] = ["targeted", "discovery", "hybrid"];
+
+/// What we know about crawling one DNO's site, e.g. that it only publishes
+/// PDFs or needs a start page deep inside the site. Unset fields keep the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DnoOverride {
+    /// `targeted`, `discovery` or `hybrid`
+    pub mode: Option<String>,
+    pub start_urls: Vec<String>,
+    pub content_type: Option<ContentType>,
+    pub strategy_order: Option<Vec<CrawlStrategy>>,
+}
+
+impl DnoOverride {
+    fn validate(&self, dno_key: &str) -> Result<()> {
+        if let Some(mode) = &self.mode {
+            if !MODES.contains(&mode.as_str()) {
+                bail!("dno_overrides.{}.mode: unknown mode '{}' (known: {})", dno_key, mode, MODES.join(", "));
+            }
+        }
+        for url in &self.start_urls {
+            let parsed = Url::parse(url).with_context(|| format!("dno_overrides.{}.start_urls: invalid URL {}", dno_key, url))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                bail!("dno_overrides.{}.start_urls: {} is not an http(s) URL", dno_key, url);
+            }
+        }
+        if let Some(order) = &self.strategy_order {
+            if order.is_empty() {
+                bail!("dno_overrides.{}.strategy_order must not be empty", dno_key);
+            }
+            if let Some((i, strategy)) = order.iter().enumerate().find(|(i, s)| order[..*i].contains(*s)) {
+                bail!("dno_overrides.{}.strategy_order lists '{}' twice (position {})", dno_key, strategy.as_str(), i + 1);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct OverridesFile {
+    dno_overrides: HashMap<String, DnoOverride>,
+}
+
+/// Per-DNO crawl overrides keyed by DNO key (slug), read from the
+/// `[dno_overrides.<key>]` tables of a TOML file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DnoOverrides {
+    overrides: HashMap<String, DnoOverride>,
+}
+
+impl DnoOverrides {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: OverridesFile = toml::from_str(text).map_err(|e| anyhow!("invalid dno_overrides config: {}", e))?;
+        for (dno_key, dno_override) in &file.dno_overrides {
+            dno_override.validate(dno_key)?;
+        }
+        Ok(Self { overrides: file.dno_overrides })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read DNO overrides from {}", path.display()))?;
+        Self::from_toml(&text).with_context(|| path.display().to_string())
+    }
+
+    /// `CRAWLER_DNO_OVERRIDES` (path to a TOML file); none when unset
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("CRAWLER_DNO_OVERRIDES") {
+            Ok(path) if !path.is_empty() => Self::from_file(Path::new(&path)),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn get(&self, dno_key: &str) -> Option<&DnoOverride> {
+        self.overrides.get(dno_key)
+    }
+
+    /// `context` with the override for its DNO merged in; start URLs are
+    /// added in front of any already set. Without an override it is unchanged.
+    pub fn apply(&self, mut context: CrawlContext) -> CrawlContext {
+        let Some(dno_override) = self.get(&context.dno.slug) else {
+            return context;
+        };
+        if let Some(mode) = &dno_override.mode {
+            context.mode = mode.clone();
+        }
+        let mut start_urls = dno_override.start_urls.clone();
+        start_urls.extend(context.start_urls.into_iter().filter(|url| !dno_override.start_urls.contains(url)));
+        context.start_urls = start_urls;
+        if let Some(content_type) = dno_override.content_type {
+            context.preferred_content_type = Some(content_type.as_str().to_string());
+        }
+        if let Some(order) = &dno_override.strategy_order {
+            context.strategy_order = Some(order.clone());
+        }
+        context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dno_core::crawl_request::CrawlConstraintsPlan;
+    use uuid::Uuid;
+
+    const CONFIG: &str = r#"
+        [dno_overrides.westnetz]
+        mode = "targeted"
+        start_urls = ["https://www.westnetz.de/de/netzentgelte.html"]
+        content_type = "pdf"
+        strategy_order = ["known_sources", "site_navigation"]
+
+        [dno_overrides.netze-bw]
+        content_type = "excel"
+    "#;
+
+    fn context(slug: &str) -> CrawlContext {
+        let now = chrono::Utc::now();
+        CrawlContext {
+            dno: dno_core::Dno {
+                id: Uuid::new_v4(),
+                slug: slug.to_string(),
+                name: slug.to_string(),
+                official_name: None,
+                description: None,
+                region: None,
+                website: None,
+                created_at: now,
+                updated_at: now,
+            },
+            year: 2024,
+            data_type: "netzentgelte".to_string(),
+            mode: "hybrid".to_string(),
+            constraints: CrawlConstraintsPlan { max_depth: 2, max_pages: 50, timeout_secs: 300 },
+            ignore_robots: false,
+            bypass_http_cache: false,
+            http_cache_ttl_secs: None,
+            start_urls: Vec::new(),
+            strategy_order: None,
+            preferred_content_type: None,
+        }
+    }
+
+    #[test]
+    fn test_overrides_from_toml_are_merged_into_the_context() {
+        let overrides = DnoOverrides::from_toml(CONFIG).unwrap();
+
+        let westnetz = overrides.apply(context("westnetz"));
+        assert_eq!(westnetz.mode, "targeted");
+        assert_eq!(westnetz.start_urls, ["https://www.westnetz.de/de/netzentgelte.html"]);
+        assert_eq!(westnetz.preferred_content_type.as_deref(), Some("pdf"));
+        assert_eq!(westnetz.strategy_order, Some(vec![CrawlStrategy::KnownSources, CrawlStrategy::SiteNavigation]));
+
+        // Only what the override sets changes
+        let netze_bw = overrides.apply(context("netze-bw"));
+        assert_eq!((netze_bw.mode.as_str(), netze_bw.preferred_content_type.as_deref()), ("hybrid", Some("excel")));
+        assert!(netze_bw.start_urls.is_empty() && netze_bw.strategy_order.is_none());
+
+        let other = overrides.apply(context("e-dis"));
+        assert_eq!((other.mode.as_str(), other.strategy_order), ("hybrid", None));
+    }
+
+    #[test]
+    fn test_invalid_overrides_are_rejected() {
+        let unknown_mode = DnoOverrides::from_toml("[dno_overrides.westnetz]\nmode = \"aggressive\"").unwrap_err();
+        assert!(unknown_mode.to_string().contains("dno_overrides.westnetz.mode"));
+        assert!(DnoOverrides::from_toml("[dno_overrides.westnetz]\ncontent_type = \"hologram\"").is_err());
+        assert!(DnoOverrides::from_toml("[dno_overrides.westnetz]\nstart_urls = [\"ftp://westnetz.de/\"]").is_err());
+        let twice = "[dno_overrides.westnetz]\nstrategy_order = [\"search\", \"search\"]";
+        assert!(DnoOverrides::from_toml(twice).unwrap_err().to_string().contains("twice"));
+        assert_eq!(DnoOverrides::from_toml("").unwrap(), DnoOverrides::default());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::error::CrawlError;
+use crate::http::{HttpRequest, HttpTransport};
+use anyhow::{Context, Result};
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use tracing::{debug, warn};
+use url::Url;
+
+/// Link labels that lead to the next (older) page of a listing
+const NEXT_WORDS: [&str; 6] = ["next", "weiter", "nächste", "older", "ältere", "vor"];
+
+/// All item links of a paginated listing
+#[derive(Debug, Clone, Default)]
+pub struct PaginatedListing {
+    /// Pages fetched, in order
+    pub pages: Vec<String>,
+    /// Links found on the pages that are not pagination links, first occurrence first
+    pub items: Vec<(Url, String)>,
+    /// Stopped at `max_pages` although another page was linked
+    pub truncated: bool,
+    /// Stopped because the next link pointed at a page already fetched
+    pub loop_detected: bool,
+}
+
+fn is_next_label(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    let words = text.trim_end_matches(['»', '›', '>', ' ']).trim();
+    (words.is_empty() && !text.is_empty())
+        || NEXT_WORDS
+            .iter()
+            .any(|word| words == *word || words.starts_with(&format!("{} ", word)))
+}
+
+/// Absolute http(s) links of a page with their whitespace-normalized anchor text
+pub(crate) fn links_on_page(base: &Url, html: &str) -> Vec<(Url, String)> {
+    let document = Html::parse_document(html);
+    let anchors = Selector::parse("a[href]").expect("static selector");
+    document
+        .select(&anchors)
+        .filter_map(|anchor| {
+            let mut url = base.join(anchor.value().attr("href")?).ok()?;
+            url.set_fragment(None);
+            let text = anchor
+                .text()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            matches!(url.scheme(), "http" | "https").then_some((url, text))
+        })
+        .collect()
+}
+
+/// Links of a page that move between pages of the listing: `rel=next`, next
+/// labels ("Ältere »", "weiter") and page numbers
+fn pagination_links(base: &Url, html: &str) -> (Option<Url>, HashSet<Url>, Vec<(u32, Url)>) {
+    let document = Html::parse_document(html);
+    let rel_next = Selector::parse(r#"a[rel~="next"][href], link[rel~="next"][href]"#)
+        .expect("static selector");
+    let anchors = Selector::parse("a[href]").expect("static selector");
+    let resolve = |href: &str| {
+        let mut url = base.join(href).ok()?;
+        url.set_fragment(None);
+        Some(url)
+    };
+
+    let mut next = document
+        .select(&rel_next)
+        .find_map(|element| resolve(element.value().attr("href")?));
+    let mut navigation = HashSet::new();
+    let mut numbered = Vec::new();
+    for anchor in document.select(&anchors) {
+        let Some(url) = anchor.value().attr("href").and_then(resolve) else {
+            continue;
+        };
+        let text = anchor.text().collect::<String>();
+        if is_next_label(&text)
+            || anchor
+                .value()
+                .attr("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| r == "next" || r == "prev"))
+        {
+            next.get_or_insert_with(|| url.clone());
+            navigation.insert(url);
+        } else if let Ok(number) = text.trim().parse::<u32>() {
+            numbered.push((number, url.clone()));
+            navigation.insert(url);
+        }
+    }
+
+    (next, navigation, numbered)
+}
+
+/// URL of the page after page `page_number` of a listing, if any
+pub fn next_page(base: &Url, html: &str, page_number: u32) -> Option<Url> {
+    let (next, _, numbered) = pagination_links(base, html);
+    next.or_else(|| {
+        numbered
+            .into_iter()
+            .find(|(number, _)| *number == page_number + 1)
+            .map(|(_, url)| url)
+    })
+}
+
+/// Whether a page is an archive index: a paginated listing of dated entries
+/// or documents, such as a "Preisblätter" archive with "ältere Einträge »"
+pub fn is_archive_index(base: &Url, html: &str) -> bool {
+    if next_page(base, html, 1).is_none() {
+        return false;
+    }
+    let entries = links_on_page(base, html)
+        .iter()
+        .filter(|(url, text)| {
+            let path = url.path().to_lowercase();
+            [".pdf", ".xlsx", ".xls", ".csv", ".zip"]
+                .iter()
+                .any(|ext| path.ends_with(ext))
+                || text
+                    .split(|c: char| !c.is_ascii_digit())
+                    .any(|part| part.len() == 4 && part.starts_with("20"))
+        })
+        .count();
+    entries >= 3
+}
+
+/// Follow a listing's pagination from `start_url` until no further page is
+/// linked, collecting the item links of every page. Stops after `max_pages`
+/// pages or when a next link leads back to a page already fetched.
+pub async fn exhaust_pagination(
+    transport: &dyn HttpTransport,
+    start_url: &str,
+    max_pages: usize,
+) -> Result<PaginatedListing> {
+    let start = Url::parse(start_url).with_context(|| format!("Invalid URL: {}", start_url))?;
+    let html = fetch_page(transport, &start).await?;
+    collect_pages(transport, start, html, max_pages).await
+}
+
+/// `exhaust_pagination` for a start page that has already been fetched
+pub(crate) async fn collect_pages(
+    transport: &dyn HttpTransport,
+    start: Url,
+    first_page: String,
+    max_pages: usize,
+) -> Result<PaginatedListing> {
+    let mut listing = PaginatedListing::default();
+    let mut fetched: HashSet<Url> = HashSet::new();
+    let mut seen_items: HashSet<Url> = HashSet::new();
+    let mut page = Some((start, first_page));
+
+    while let Some((url, html)) = page.take() {
+        fetched.insert(url.clone());
+        listing.pages.push(url.to_string());
+
+        let (_, navigation, _) = pagination_links(&url, &html);
+        for (link, text) in links_on_page(&url, &html) {
+            if !navigation.contains(&link)
+                && !fetched.contains(&link)
+                && seen_items.insert(link.clone())
+            {
+                listing.items.push((link, text));
+            }
+        }
+
+        let Some(next) = next_page(&url, &html, listing.pages.len() as u32) else {
+            break;
+        };
+        if fetched.contains(&next) {
+            warn!("Pagination of {} loops back to {}", listing.pages[0], next);
+            listing.loop_detected = true;
+            break;
+        }
+        if listing.pages.len() >= max_pages {
+            listing.truncated = true;
+            break;
+        }
+        let html = fetch_page(transport, &next).await?;
+        page = Some((next, html));
+    }
+
+    // Items found before a later page turned out to be another listing page
+    listing.items.retain(|(link, _)| !fetched.contains(link));
+    debug!(
+        "{} items over {} pages of {}",
+        listing.items.len(),
+        listing.pages.len(),
+        listing.pages[0]
+    );
+    Ok(listing)
+}
+
+async fn fetch_page(transport: &dyn HttpTransport, url: &Url) -> Result<String> {
+    let response = transport.execute(HttpRequest::get(url.as_str())).await?;
+    if !response.is_success() {
+        return Err(CrawlError::Http { url: url.to_string(), status: response.status }.into());
+    }
+    Ok(response.text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpResponse;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+
+    /// A three-page archive, each page using a different pagination style
+    struct Archive(HashMap<&'static str, &'static str>);
+
+    impl Archive {
+        fn new() -> Self {
+            Self(HashMap::from([
+                (
+                    "https://netze.example/archiv",
+                    r#"<a href="/">Start</a>
+                       <a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>
+                       <a href="/files/preisblatt-2023.pdf">Preisblatt 2023</a>
+                       <a href="/files/preisblatt-2022.pdf">Preisblatt 2022</a>
+                       <a rel="next" href="/archiv?page=2">2</a>"#,
+                ),
+                (
+                    "https://netze.example/archiv?page=2",
+                    r#"<a href="/">Start</a>
+                       <a href="/files/preisblatt-2021.pdf">Preisblatt 2021</a>
+                       <a href="/files/preisblatt-2020.pdf">Preisblatt 2020</a>
+                       <a href="/archiv">« Neuere</a>
+                       <a href="/archiv?page=3">Ältere Einträge »</a>"#,
+                ),
+                (
+                    "https://netze.example/archiv?page=3",
+                    r#"<a href="/">Start</a>
+                       <a href="/files/preisblatt-2019.pdf">Preisblatt 2019</a>
+                       <a href="/archiv">1</a> <a href="/archiv?page=2">2</a> <span>3</span>"#,
+                ),
+            ]))
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for Archive {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let body = self
+                .0
+                .get(request.url.as_str())
+                .unwrap_or_else(|| panic!("unexpected fetch of {}", request.url));
+            Ok(HttpResponse {
+                status: 200,
+                headers: vec![],
+                body: body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_items_of_all_pages_are_collected() {
+        let archive = Archive::new();
+        let start = Url::parse("https://netze.example/archiv").unwrap();
+        assert!(is_archive_index(
+            &start,
+            archive.0["https://netze.example/archiv"]
+        ));
+
+        let listing = exhaust_pagination(&archive, start.as_str(), 10)
+            .await
+            .unwrap();
+        assert_eq!(listing.pages.len(), 3);
+        assert!(!listing.truncated && !listing.loop_detected);
+        let documents: Vec<&str> = listing
+            .items
+            .iter()
+            .map(|(url, _)| url.path())
+            .filter(|path| path.ends_with(".pdf"))
+            .collect();
+        assert_eq!(
+            documents,
+            [2024, 2023, 2022, 2021, 2020, 2019]
+                .map(|year| format!("/files/preisblatt-{}.pdf", year))
+        );
+        assert!(!listing.items.iter().any(|(url, _)| url.path() == "/archiv"));
+
+        let capped = exhaust_pagination(&archive, start.as_str(), 2)
+            .await
+            .unwrap();
+        assert_eq!(capped.pages.len(), 2);
+        assert!(capped.truncated);
+    }
+}
@@ -0,0 +1,503 @@
+use anyhow::{Context, Result};
+use dno_core::ollama::{AiCallStats, OllamaService};
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+/// Instructions sent ahead of the table region; the model answers with JSON only
+const TABLE_PROMPT: &str = r#"The text below is a table from a German electricity network operator's price sheet (Preisblatt).
+Cells are separated by tabs, rows by newlines. Answer with a JSON object only:
+{"year": number or null, "entries": [{"voltage_level": "...", "leistungspreis": number or null, "arbeitspreis": number or null, "grundpreis": number or null}]}
+Use one entry per voltage level (HS, HS/MS, MS, MS/NS, NS) and decimal points instead of commas.
+Table:
+"#;
+
+/// Average glyph advance in ems. Font width tables are not consulted, so
+/// span widths are estimates; they only need to keep columns apart.
+const GLYPH_WIDTH_EM: f32 = 0.5;
+/// Spans on one line closer than this many ems belong to the same cell
+const CELL_GAP_EM: f32 = 1.0;
+/// Table rows further apart than this many ems start a new cluster
+const ROW_GAP_EM: f32 = 2.5;
+/// `TJ` adjustments (thousandths of an em) past this are read as a space
+const TJ_SPACE: f32 = 200.0;
+
+/// A rectangle in PDF user space: points from the bottom-left page corner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+/// Text placed by one text-showing operator
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub x: f32,
+    /// Baseline
+    pub y: f32,
+    pub width: f32,
+    /// Effective font size in points
+    pub size: f32,
+    pub text: String,
+}
+
+/// A cell of a layout line: neighbouring spans without a column gap between them
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    x0: f32,
+    x1: f32,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Line {
+    y: f32,
+    size: f32,
+    cells: Vec<Cell>,
+}
+
+/// The densest table found in a PDF, with where it sits
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRegion {
+    /// 1-based page number
+    pub page: u32,
+    pub bbox: BoundingBox,
+    pub rows: Vec<Vec<String>>,
+    score: usize,
+}
+
+impl TableRegion {
+    pub fn columns(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or_default()
+    }
+
+    /// Rows separated by newlines, cells by tabs
+    pub fn text(&self) -> String {
+        self.rows.iter().map(|row| row.join("\t")).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Where the analyzed text came from, returned alongside the model's answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableRegionMetadata {
+    pub page: u32,
+    pub bbox: BoundingBox,
+    pub rows: usize,
+    pub columns: usize,
+    /// Characters sent to the model
+    pub prompt_chars: usize,
+    pub ai: AiCallStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PdfAnalysis {
+    pub data: Value,
+    pub metadata: TableRegionMetadata,
+}
+
+/// 2D affine transform `[a b c d e f]` as used by PDF content streams
+#[derive(Debug, Clone, Copy)]
+struct Matrix([f32; 6]);
+
+impl Matrix {
+    const IDENTITY: Matrix = Matrix([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    fn translate(x: f32, y: f32) -> Self {
+        Matrix([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    /// `self` applied first, then `other`
+    fn then(self, other: Matrix) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Matrix([
+            a * a2 + b * c2,
+            a * b2 + b * d2,
+            c * a2 + d * c2,
+            c * b2 + d * d2,
+            e * a2 + f * c2 + e2,
+            e * b2 + f * d2 + f2,
+        ])
+    }
+
+    fn scale(&self) -> f32 {
+        let [a, b, c, d, ..] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+}
+
+fn number(object: &Object) -> f32 {
+    object.as_float().unwrap_or(0.0)
+}
+
+/// String operand bytes as text. Simple fonts in DNO price sheets use
+/// WinAnsi, which matches Latin-1 for letters and umlauts.
+fn decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Positioned text of one page, from its content stream
+pub fn page_spans(document: &Document, page_id: lopdf::ObjectId) -> Result<Vec<TextSpan>> {
+    let content = document.get_and_decode_page_content(page_id).context("Unreadable page content")?;
+
+    let mut spans = Vec::new();
+    let mut ctm = Matrix::IDENTITY;
+    let mut saved = Vec::new();
+    let (mut tm, mut tlm) = (Matrix::IDENTITY, Matrix::IDENTITY);
+    let (mut font_size, mut leading) = (0.0f32, 0.0f32);
+
+    for operation in &content.operations {
+        let operands = &operation.operands;
+        let operand = |index: usize| operands.get(index).map(number).unwrap_or(0.0);
+        match operation.operator.as_str() {
+            "q" => saved.push(ctm),
+            "Q" => ctm = saved.pop().unwrap_or(Matrix::IDENTITY),
+            "cm" if operands.len() == 6 => ctm = Matrix(std::array::from_fn(operand)).then(ctm),
+            "BT" => (tm, tlm) = (Matrix::IDENTITY, Matrix::IDENTITY),
+            "Tf" => font_size = operand(1),
+            "TL" => leading = operand(0),
+            "Tm" if operands.len() == 6 => {
+                tlm = Matrix(std::array::from_fn(operand));
+                tm = tlm;
+            }
+            "Td" | "TD" => {
+                if operation.operator == "TD" {
+                    leading = -operand(1);
+                }
+                tlm = Matrix::translate(operand(0), operand(1)).then(tlm);
+                tm = tlm;
+            }
+            "T*" | "'" | "\"" | "Tj" | "TJ" => {
+                if matches!(operation.operator.as_str(), "T*" | "'" | "\"") {
+                    tlm = Matrix::translate(0.0, -leading).then(tlm);
+                    tm = tlm;
+                }
+                // Text and advance in unscaled text space (ems times font size)
+                let (text, advance) = match operation.operator.as_str() {
+                    "Tj" | "'" => match operands.first() {
+                        Some(Object::String(bytes, _)) => (decode(bytes), bytes.len() as f32 * GLYPH_WIDTH_EM * font_size),
+                        _ => continue,
+                    },
+                    "\"" => match operands.get(2) {
+                        Some(Object::String(bytes, _)) => (decode(bytes), bytes.len() as f32 * GLYPH_WIDTH_EM * font_size),
+                        _ => continue,
+                    },
+                    "TJ" => {
+                        let Some(Object::Array(items)) = operands.first() else { continue };
+                        let (mut text, mut advance) = (String::new(), 0.0);
+                        for item in items {
+                            match item {
+                                Object::String(bytes, _) => {
+                                    text.push_str(&decode(bytes));
+                                    advance += bytes.len() as f32 * GLYPH_WIDTH_EM * font_size;
+                                }
+                                adjustment => {
+                                    let adjustment = number(adjustment);
+                                    if adjustment < -TJ_SPACE {
+                                        text.push(' ');
+                                    }
+                                    advance -= adjustment / 1000.0 * font_size;
+                                }
+                            }
+                        }
+                        (text, advance)
+                    }
+                    _ => continue,
+                };
+
+                let placement = tm.then(ctm);
+                let [.., x, y] = placement.0;
+                if !text.trim().is_empty() {
+                    spans.push(TextSpan {
+                        x,
+                        y,
+                        width: advance * placement.scale(),
+                        size: font_size * placement.scale(),
+                        text,
+                    });
+                }
+                tm = Matrix::translate(advance, 0.0).then(tm);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(spans)
+}
+
+/// Group spans into lines top to bottom, merging close spans into cells
+fn lines(mut spans: Vec<TextSpan>) -> Vec<Line> {
+    spans.sort_by(|a, b| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+
+    let mut lines: Vec<(f32, f32, Vec<TextSpan>)> = Vec::new();
+    for span in spans {
+        match lines.last_mut() {
+            Some((y, size, members)) if (*y - span.y).abs() <= size.max(span.size) * 0.5 => {
+                *size = size.max(span.size);
+                members.push(span);
+            }
+            _ => lines.push((span.y, span.size, vec![span])),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(y, size, mut members)| {
+            members.sort_by(|a, b| a.x.total_cmp(&b.x));
+            let mut cells: Vec<Cell> = Vec::new();
+            for span in members {
+                let text = span.text.trim();
+                match cells.last_mut() {
+                    Some(cell) if span.x - cell.x1 < CELL_GAP_EM * size => {
+                        cell.text = format!("{} {}", cell.text, text);
+                        cell.x1 = cell.x1.max(span.x + span.width);
+                    }
+                    _ => cells.push(Cell { x0: span.x, x1: span.x + span.width, text: text.to_string() }),
+                }
+            }
+            Line { y, size, cells }
+        })
+        .collect()
+}
+
+fn has_amount(text: &str) -> bool {
+    text.as_bytes()
+        .windows(3)
+        .any(|w| w[0].is_ascii_digit() && matches!(w[1], b',' | b'.') && w[2].is_ascii_digit())
+}
+
+/// Runs of multi-cell lines whose cells line up with the run's columns.
+/// Prose lines have one cell each, so prose pages yield no regions.
+fn page_regions(page: u32, lines: &[Line]) -> Vec<TableRegion> {
+    let mut regions = Vec::new();
+    let mut run: Vec<&Line> = Vec::new();
+    let mut columns: Vec<(f32, f32)> = Vec::new();
+
+    let close = |run: &mut Vec<&Line>, regions: &mut Vec<TableRegion>| {
+        if run.len() >= 2 {
+            regions.push(region(page, run));
+        }
+        run.clear();
+    };
+
+    for line in lines {
+        let aligned = line
+            .cells
+            .iter()
+            .filter(|cell| columns.iter().any(|(x0, x1)| cell.x0 <= *x1 && cell.x1 >= *x0))
+            .count();
+        let continues = run.last().is_some_and(|previous| {
+            previous.y - line.y <= ROW_GAP_EM * previous.size.max(line.size) && aligned >= 2
+        });
+
+        if line.cells.len() < 2 {
+            close(&mut run, &mut regions);
+            columns.clear();
+            continue;
+        }
+        if !continues {
+            close(&mut run, &mut regions);
+            columns.clear();
+        }
+        for cell in &line.cells {
+            match columns.iter_mut().find(|(x0, x1)| cell.x0 <= *x1 && cell.x1 >= *x0) {
+                Some(column) => *column = (column.0.min(cell.x0), column.1.max(cell.x1)),
+                None => columns.push((cell.x0, cell.x1)),
+            }
+        }
+        run.push(line);
+    }
+    close(&mut run, &mut regions);
+
+    regions
+}
+
+fn region(page: u32, run: &[&Line]) -> TableRegion {
+    let cells = run.iter().flat_map(|line| &line.cells);
+    let bbox = BoundingBox {
+        x0: cells.clone().map(|cell| cell.x0).fold(f32::INFINITY, f32::min),
+        x1: cells.clone().map(|cell| cell.x1).fold(f32::NEG_INFINITY, f32::max),
+        // Baselines, widened by descenders below and the glyph height above
+        y0: run.iter().map(|line| line.y - 0.25 * line.size).fold(f32::INFINITY, f32::min),
+        y1: run.iter().map(|line| line.y + line.size).fold(f32::NEG_INFINITY, f32::max),
+    };
+    // Amounts weigh double: a tariff table is mostly numbers
+    let score = cells.clone().count() + cells.filter(|cell| has_amount(&cell.text)).count();
+    let rows = run.iter().map(|line| line.cells.iter().map(|cell| cell.text.clone()).collect()).collect();
+    TableRegion { page, bbox, rows, score }
+}
+
+/// The densest tabular region of a PDF; the earlier page wins a tie.
+/// `None` when no page has a table, e.g. scans without a text layer.
+pub fn find_table_region(pdf: &[u8]) -> Result<Option<TableRegion>> {
+    let document = Document::load_mem(pdf).context("Unreadable PDF")?;
+
+    let mut best: Option<TableRegion> = None;
+    for (page, page_id) in document.get_pages() {
+        let spans = page_spans(&document, page_id).with_context(|| format!("Page {}", page))?;
+        for region in page_regions(page, &lines(spans)) {
+            if best.as_ref().is_none_or(|best| region.score > best.score) {
+                best = Some(region);
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+/// Extracts tariffs from PDFs with the local model
+#[derive(Debug, Clone)]
+pub struct PdfAnalysisService {
+    ollama: OllamaService,
+}
+
+impl PdfAnalysisService {
+    pub fn new(ollama: OllamaService) -> Self {
+        Self { ollama }
+    }
+
+    /// Send only the densest table region to the model instead of the whole
+    /// document. `None` when the PDF has no tabular region.
+    pub async fn analyze_table_region(&self, pdf: &[u8]) -> Result<Option<PdfAnalysis>> {
+        let Some(region) = find_table_region(pdf)? else {
+            return Ok(None);
+        };
+        let prompt = format!("{}{}", TABLE_PROMPT, region.text());
+        debug!("Analyzing table region on page {} ({} characters)", region.page, prompt.len());
+
+        let response = self.ollama.generate_json(&prompt).await?;
+        let data: Value = serde_json::from_str(&response.text).context("Model returned unreadable JSON")?;
+        // Logged here; storing goes through `dno_core::extraction_schema::persist_extraction`, which flags bad entries
+        if let Err(errors) = dno_core::extraction_schema::validate_extraction(&data) {
+            warn!("Table analysis on page {} does not fit the Netzentgelte schema: {} problems", region.page, errors.len());
+        }
+        Ok(Some(PdfAnalysis {
+            data,
+            metadata: TableRegionMetadata {
+                page: region.page,
+                bbox: region.bbox,
+                rows: region.rows.len(),
+                columns: region.columns(),
+                prompt_chars: prompt.len(),
+                ai: response.stats,
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Stream};
+
+    /// `(x, y, text)` placed with 10pt Helvetica
+    type Placed = (i64, i64, &'static str);
+
+    fn sample_pdf(pages: &[Vec<Placed>]) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+
+        let mut kids: Vec<Object> = Vec::new();
+        for placed in pages {
+            let mut operations = Vec::new();
+            for (x, y, text) in placed {
+                operations.push(Operation::new("BT", vec![]));
+                operations.push(Operation::new("Tf", vec!["F1".into(), 10.into()]));
+                operations.push(Operation::new("Td", vec![(*x).into(), (*y).into()]));
+                operations.push(Operation::new("Tj", vec![Object::string_literal(*text)]));
+                operations.push(Operation::new("ET", vec![]));
+            }
+            let content = Content { operations };
+            let content_id = document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            let page_id = document.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn prose(title: &'static str) -> Vec<Placed> {
+        vec![
+            (72, 780, title),
+            (480, 780, "Stand 01.01.2024"),
+            (72, 740, "Die nachfolgenden Preise gelten fuer die Nutzung des Netzes ab dem 1. Januar 2024."),
+            (72, 726, "Sie verstehen sich zuzueglich Umsatzsteuer in Hoehe von 19,00 Prozent."),
+            (72, 712, "Abrechnungszeitraum ist das Kalenderjahr; unterjaehrige Nutzung wird anteilig berechnet."),
+        ]
+    }
+
+    fn tariff_table() -> Vec<Placed> {
+        let mut page = vec![(72, 780, "Preisblatt Netzentgelte 2024"), (72, 760, "Entnahme mit Leistungsmessung")];
+        let rows: [(i64, [&'static str; 3]); 6] = [
+            (700, ["Spannungsebene", "Leistungspreis", "Arbeitspreis"]),
+            (686, ["HS", "25,10", "0,85"]),
+            (672, ["HS/MS", "38,42", "1,02"]),
+            (658, ["MS", "58,21", "1,26"]),
+            (644, ["MS/NS", "71,93", "1,88"]),
+            (630, ["NS", "21,04", "5,67"]),
+        ];
+        for (y, [level, power, energy]) in rows {
+            page.extend([(72, y, level), (250, y, power), (400, y, energy)]);
+        }
+        page.push((72, 600, "Alle Preise zzgl. USt."));
+        page
+    }
+
+    #[test]
+    fn test_table_page_is_selected_over_prose_pages() {
+        let pdf = sample_pdf(&[prose("Ergaenzende Bedingungen"), tariff_table(), prose("Hinweise")]);
+
+        let region = find_table_region(&pdf).unwrap().unwrap();
+        assert_eq!(region.page, 2);
+        assert_eq!(region.rows.len(), 6);
+        assert_eq!(region.columns(), 3);
+        assert_eq!(region.rows[2], ["HS/MS", "38,42", "1,02"]);
+
+        // Only the table, not the title above or the note below it
+        let text = region.text();
+        assert!(text.starts_with("Spannungsebene\tLeistungspreis\tArbeitspreis\nHS\t25,10"));
+        assert!(!text.contains("Preisblatt") && !text.contains("USt."));
+
+        let BoundingBox { x0, y0, x1, y1 } = region.bbox;
+        assert_eq!(x0, 72.0);
+        assert!((400.0..500.0).contains(&x1));
+        assert!((620.0..630.0).contains(&y0) && (700.0..720.0).contains(&y1));
+    }
+
+    #[test]
+    fn test_prose_only_pdf_has_no_region() {
+        let pdf = sample_pdf(&[prose("Ergaenzende Bedingungen")]);
+        assert_eq!(find_table_region(&pdf).unwrap(), None);
+    }
+}
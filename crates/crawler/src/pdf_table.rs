@@ -0,0 +1,250 @@
+use crate::content::ContentType;
+use crate::extraction::{Document, Extraction, Extractor};
+use crate::pipeline::ExtractionMethod;
+use async_trait::async_trait;
+use dno_core::models::FieldProvenance;
+use serde_json::{json, Map, Value};
+
+/// Confidence for cells read under a header row versus positional columns;
+/// lower than for HTML since columns are inferred from the text layout
+const HEADED_CONFIDENCE: f64 = 0.85;
+const HEADERLESS_CONFIDENCE: f64 = 0.55;
+
+/// Page separator in the PDF text layer (as written by `pdftotext`)
+const PAGE_BREAK: char = '\u{c}';
+
+/// One table line of the PDF text layer, split into cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfRow {
+    /// 1-based page number
+    pub page: u32,
+    /// 0-based line within the page
+    pub line: usize,
+    pub cells: Vec<String>,
+}
+
+/// A table from the PDF text layer, stitched together when it continues over
+/// page breaks
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<PdfRow>,
+    /// Pages that contributed rows, in order
+    pub pages: Vec<u32>,
+}
+
+impl PdfTable {
+    fn width(&self) -> usize {
+        if self.headers.is_empty() {
+            self.rows.first().map(|row| row.cells.len()).unwrap_or_default()
+        } else {
+            self.headers.len()
+        }
+    }
+}
+
+/// Cells of a layout line: columns are separated by tabs or runs of two or more spaces
+fn cells(line: &str) -> Vec<String> {
+    line.split('\t')
+        .flat_map(|part| part.split("  "))
+        .map(str::trim)
+        .filter(|cell| !cell.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A header row names columns and carries no amounts
+fn is_header(cells: &[String]) -> bool {
+    cells.iter().all(|cell| {
+        !cell
+            .as_bytes()
+            .windows(3)
+            .any(|w| w[0].is_ascii_digit() && matches!(w[1], b',' | b'.') && w[2].is_ascii_digit())
+    })
+}
+
+/// Tables on one page: runs of at least two consecutive lines with two or more cells
+pub fn page_tables(page: u32, text: &str) -> Vec<PdfTable> {
+    let mut tables = Vec::new();
+    let mut run: Vec<PdfRow> = Vec::new();
+
+    let lines = text.lines().map(Some).chain(std::iter::once(None));
+    for (line, text) in lines.enumerate() {
+        let cells = text.map(cells).unwrap_or_default();
+        if cells.len() >= 2 {
+            run.push(PdfRow { page, line, cells });
+            continue;
+        }
+        if run.len() >= 2 {
+            let mut rows = std::mem::take(&mut run);
+            let headers = if is_header(&rows[0].cells) {
+                rows.remove(0).cells
+            } else {
+                Vec::new()
+            };
+            tables.push(PdfTable { headers, rows, pages: vec![page] });
+        }
+        run.clear();
+    }
+
+    tables
+}
+
+/// Join a table that ends a page with the first table of the next page when
+/// the latter repeats the header, or has no header and the same column count
+pub fn stitch_tables(tables: Vec<PdfTable>) -> Vec<PdfTable> {
+    let mut stitched: Vec<PdfTable> = Vec::new();
+
+    for table in tables {
+        let page = table.pages[0];
+        // Only the last table so far (the one ending the previous page) can continue
+        let first_on_page = !stitched.iter().any(|t| t.pages.contains(&page));
+        let continues = first_on_page
+            && stitched.last().is_some_and(|previous| {
+                previous.pages.last().is_some_and(|last| last + 1 == page)
+                    && if table.headers.is_empty() {
+                        table.width() == previous.width()
+                    } else {
+                        table.headers == previous.headers
+                    }
+            });
+
+        match stitched.last_mut() {
+            Some(previous) if continues => {
+                previous.rows.extend(table.rows);
+                previous.pages.push(page);
+            }
+            _ => stitched.push(table),
+        }
+    }
+
+    stitched
+}
+
+/// All tables of a PDF text layer whose pages are separated by form feeds
+pub fn extract_tables(text: &str) -> Vec<PdfTable> {
+    let tables = text
+        .split(PAGE_BREAK)
+        .enumerate()
+        .flat_map(|(index, page)| page_tables(index as u32 + 1, page))
+        .collect();
+    stitch_tables(tables)
+}
+
+/// Reads tables from the PDF text layer (`Document::text`), reassembling
+/// tables split by page breaks so later stages see each table once
+#[derive(Debug, Default, Clone)]
+pub struct PdfTableExtractor;
+
+impl PdfTableExtractor {
+    pub fn extract_text(&self, url: &str, text: &str) -> Option<Extraction> {
+        let mut tables = Vec::new();
+        let mut extraction = Extraction::default();
+
+        for (table_index, table) in extract_tables(text).into_iter().enumerate() {
+            let confidence = if table.headers.is_empty() { HEADERLESS_CONFIDENCE } else { HEADED_CONFIDENCE };
+            let mut rows = Vec::new();
+
+            for (row_index, row) in table.rows.iter().enumerate() {
+                let mut object = Map::new();
+                for (column_index, cell) in row.cells.iter().enumerate() {
+                    let key = table
+                        .headers
+                        .get(column_index)
+                        .cloned()
+                        .unwrap_or_else(|| format!("column_{}", column_index));
+                    extraction.record(
+                        format!("tables[{}].rows[{}].{}", table_index, row_index, key),
+                        json!(cell),
+                        FieldProvenance {
+                            source_url: url.to_string(),
+                            page: Some(row.page),
+                            row: Some(row.line),
+                            column: Some(column_index),
+                            selector: None,
+                            archive: None,
+                            method: ExtractionMethod::PdfText.as_str().to_string(),
+                            confidence,
+                        },
+                    );
+                    object.insert(key, Value::String(cell.clone()));
+                }
+                rows.push(Value::Object(object));
+            }
+
+            tables.push(json!({ "headers": table.headers, "rows": rows, "pages": table.pages }));
+        }
+
+        if tables.is_empty() {
+            return None;
+        }
+        extraction.data = json!({ "tables": tables });
+        Some(extraction)
+    }
+}
+
+#[async_trait]
+impl Extractor for PdfTableExtractor {
+    fn method(&self) -> ExtractionMethod {
+        ExtractionMethod::PdfText
+    }
+
+    async fn extract(&self, document: &Document) -> anyhow::Result<Option<Extraction>> {
+        let content_type = ContentType::detect(&document.url, document.content_type.as_deref(), &document.bytes);
+        match (&document.text, content_type) {
+            (Some(text), ContentType::Pdf) => Ok(self.extract_text(&document.url, text)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_PAGES: &str = "\
+Preisblatt Netzentgelte 2024
+Seite 1
+
+Spannungsebene        Leistungspreis    Arbeitspreis
+HS                    25,10             0,85
+HS/MS                 38,42             1,02
+MS                    58,21             1,26
+\u{c}Preisblatt Netzentgelte 2024
+Seite 2
+
+Spannungsebene        Leistungspreis    Arbeitspreis
+MS/NS                 71,93             1,88
+NS                    21,04             5,67
+
+Alle Preise zzgl. USt.
+";
+
+    #[test]
+    fn test_table_across_page_break_is_reassembled() {
+        let tables = extract_tables(TWO_PAGES);
+        assert_eq!(tables.len(), 1);
+
+        let table = &tables[0];
+        assert_eq!(table.headers, ["Spannungsebene", "Leistungspreis", "Arbeitspreis"]);
+        assert_eq!(table.pages, [1, 2]);
+        let levels: Vec<&str> = table.rows.iter().map(|row| row.cells[0].as_str()).collect();
+        assert_eq!(levels, ["HS", "HS/MS", "MS", "MS/NS", "NS"]);
+
+        let extraction = PdfTableExtractor.extract_text("https://netze.example/preisblatt.pdf", TWO_PAGES).unwrap();
+        assert_eq!(extraction.data["tables"][0]["rows"][4]["Arbeitspreis"], "5,67");
+        assert_eq!(extraction.data["tables"][0]["pages"], json!([1, 2]));
+        let field = &extraction.provenance["tables[0].rows[3].Leistungspreis"];
+        assert_eq!((field.provenance.page, field.provenance.row), (Some(2), Some(4)));
+    }
+
+    #[test]
+    fn test_headerless_continuation_needs_matching_columns() {
+        let text = "A  B  C\n1,0  2,0  3,0\n\u{c}4,0  5,0  6,0\n7,0  8,0  9,0\n\u{c}1,5  2,5\n3,5  4,5\n";
+        let tables = extract_tables(text);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].pages, [1, 2]);
+        assert_eq!(tables[0].rows.len(), 3);
+        assert_eq!(tables[1].pages, [3]);
+    }
+}
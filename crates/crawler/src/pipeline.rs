@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Ways of turning a fetched document into structured data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionMethod {
+    HtmlTable,
+    PdfText,
+    /// Worksheets of XLSX/XLS workbooks
+    Spreadsheet,
+    /// CSV exports
+    Csv,
+    /// XML data feeds
+    Xml,
+    Ocr,
+    Ai,
+}
+
+/// What to try after a URL failed to fetch or extract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryStrategy {
+    Retry,
+    AlternateExtractor,
+    AlternateUrl,
+    ArchiveLookup,
+    ManualIntervention,
+}
+
+impl ExtractionMethod {
+    pub const ALL: [ExtractionMethod; 7] = [
+        ExtractionMethod::HtmlTable,
+        ExtractionMethod::PdfText,
+        ExtractionMethod::Spreadsheet,
+        ExtractionMethod::Csv,
+        ExtractionMethod::Xml,
+        ExtractionMethod::Ocr,
+        ExtractionMethod::Ai,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExtractionMethod::HtmlTable => "html_table",
+            ExtractionMethod::PdfText => "pdf_text",
+            ExtractionMethod::Spreadsheet => "spreadsheet",
+            ExtractionMethod::Csv => "csv",
+            ExtractionMethod::Xml => "xml",
+            ExtractionMethod::Ocr => "ocr",
+            ExtractionMethod::Ai => "ai",
+        }
+    }
+}
+
+impl RecoveryStrategy {
+    pub const ALL: [RecoveryStrategy; 5] = [
+        RecoveryStrategy::Retry,
+        RecoveryStrategy::AlternateExtractor,
+        RecoveryStrategy::AlternateUrl,
+        RecoveryStrategy::ArchiveLookup,
+        RecoveryStrategy::ManualIntervention,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecoveryStrategy::Retry => "retry",
+            RecoveryStrategy::AlternateExtractor => "alternate_extractor",
+            RecoveryStrategy::AlternateUrl => "alternate_url",
+            RecoveryStrategy::ArchiveLookup => "archive_lookup",
+            RecoveryStrategy::ManualIntervention => "manual_intervention",
+        }
+    }
+}
+
+impl fmt::Display for ExtractionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for RecoveryStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ExtractionMethod {
+    type Err = PipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.as_str() == s.trim())
+            .ok_or_else(|| PipelineError::UnknownExtractionMethod(s.trim().to_string()))
+    }
+}
+
+impl FromStr for RecoveryStrategy {
+    type Err = PipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|r| r.as_str() == s.trim())
+            .ok_or_else(|| PipelineError::UnknownRecoveryStrategy(s.trim().to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PipelineError {
+    #[error("unknown extraction method '{0}' (known: html_table, pdf_text, spreadsheet, csv, xml, ocr, ai)")]
+    UnknownExtractionMethod(String),
+    #[error("unknown recovery strategy '{0}' (known: retry, alternate_extractor, alternate_url, archive_lookup, manual_intervention)")]
+    UnknownRecoveryStrategy(String),
+    #[error("'{0}' is listed more than once in {1}")]
+    Duplicate(String, String),
+    #[error("{0} must not be empty")]
+    Empty(String),
+    #[error("invalid pipeline config: {0}")]
+    Invalid(String),
+}
+
+/// Order in which extractors and recovery strategies are tried. Operators can
+/// reorder both globally and pin a different extraction order per DNO, e.g.
+/// OCR first for a DNO known to publish scanned PDFs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub extraction_order: Vec<ExtractionMethod>,
+    pub recovery_order: Vec<RecoveryStrategy>,
+    /// Keyed by DNO key (slug)
+    pub dno_extraction_order: HashMap<String, Vec<ExtractionMethod>>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            extraction_order: vec![
+                ExtractionMethod::HtmlTable,
+                ExtractionMethod::Spreadsheet,
+                ExtractionMethod::Csv,
+                ExtractionMethod::Xml,
+                ExtractionMethod::PdfText,
+                ExtractionMethod::Ai,
+                ExtractionMethod::Ocr,
+            ],
+            recovery_order: vec![
+                RecoveryStrategy::Retry,
+                RecoveryStrategy::AlternateExtractor,
+                RecoveryStrategy::AlternateUrl,
+                RecoveryStrategy::ArchiveLookup,
+                RecoveryStrategy::ManualIntervention,
+            ],
+            dno_extraction_order: HashMap::new(),
+        }
+    }
+}
+
+/// Pipeline config as written by operators, before validation
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RawPipelineConfig {
+    pub extraction_order: Option<Vec<String>>,
+    pub recovery_order: Option<Vec<String>>,
+    pub dno_extraction_order: HashMap<String, Vec<String>>,
+}
+
+fn parse_order<T>(names: &[String], field: &str) -> Result<Vec<T>, Vec<PipelineError>>
+where
+    T: FromStr<Err = PipelineError> + PartialEq + fmt::Display,
+{
+    if names.is_empty() {
+        return Err(vec![PipelineError::Empty(field.to_string())]);
+    }
+
+    let mut errors = Vec::new();
+    let mut order: Vec<T> = Vec::new();
+    for name in names {
+        match name.parse::<T>() {
+            Ok(item) if order.contains(&item) => {
+                errors.push(PipelineError::Duplicate(item.to_string(), field.to_string()))
+            }
+            Ok(item) => order.push(item),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() { Ok(order) } else { Err(errors) }
+}
+
+impl RawPipelineConfig {
+    /// Validate every name, reporting all problems at once
+    pub fn validate(self) -> Result<PipelineConfig, Vec<PipelineError>> {
+        let defaults = PipelineConfig::default();
+        let mut errors = Vec::new();
+        let mut config = defaults.clone();
+
+        if let Some(names) = &self.extraction_order {
+            match parse_order(names, "extraction_order") {
+                Ok(order) => config.extraction_order = order,
+                Err(e) => errors.extend(e),
+            }
+        }
+        if let Some(names) = &self.recovery_order {
+            match parse_order(names, "recovery_order") {
+                Ok(order) => config.recovery_order = order,
+                Err(e) => errors.extend(e),
+            }
+        }
+        for (dno, names) in &self.dno_extraction_order {
+            match parse_order(names, &format!("dno_extraction_order.{}", dno)) {
+                Ok(order) => {
+                    config.dno_extraction_order.insert(dno.clone(), order);
+                }
+                Err(e) => errors.extend(e),
+            }
+        }
+
+        if errors.is_empty() { Ok(config) } else { Err(errors) }
+    }
+}
+
+impl PipelineConfig {
+    pub fn from_json(json: &str) -> Result<Self, Vec<PipelineError>> {
+        serde_json::from_str::<RawPipelineConfig>(json)
+            .map_err(|e| vec![PipelineError::Invalid(e.to_string())])?
+            .validate()
+    }
+
+    /// Read `CRAWLER_PIPELINE_CONFIG` (path to a JSON file), then let
+    /// `CRAWLER_EXTRACTION_ORDER` / `CRAWLER_RECOVERY_ORDER` (comma-separated)
+    /// override the global orders
+    pub fn from_env() -> Result<Self, Vec<PipelineError>> {
+        let mut raw = match std::env::var("CRAWLER_PIPELINE_CONFIG") {
+            Ok(path) => {
+                let json = std::fs::read_to_string(&path)
+                    .map_err(|e| vec![PipelineError::Invalid(format!("{}: {}", path, e))])?;
+                serde_json::from_str::<RawPipelineConfig>(&json)
+                    .map_err(|e| vec![PipelineError::Invalid(format!("{}: {}", path, e))])?
+            }
+            Err(_) => RawPipelineConfig::default(),
+        };
+
+        let split = |v: String| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        if let Ok(order) = std::env::var("CRAWLER_EXTRACTION_ORDER") {
+            raw.extraction_order = Some(split(order));
+        }
+        if let Ok(order) = std::env::var("CRAWLER_RECOVERY_ORDER") {
+            raw.recovery_order = Some(split(order));
+        }
+        raw.validate()
+    }
+
+    /// Extraction order for a DNO, falling back to the global order
+    pub fn extraction_order_for(&self, dno_key: Option<&str>) -> &[ExtractionMethod] {
+        dno_key
+            .and_then(|key| self.dno_extraction_order.get(key))
+            .unwrap_or(&self.extraction_order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_and_duplicate_methods_are_rejected() {
+        let errors = PipelineConfig::from_json(
+            r#"{"extraction_order": ["ocr", "magic", "ocr"], "recovery_order": ["teleport"]}"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                PipelineError::UnknownExtractionMethod("magic".to_string()),
+                PipelineError::Duplicate("ocr".to_string(), "extraction_order".to_string()),
+                PipelineError::UnknownRecoveryStrategy("teleport".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dno_override_falls_back_to_global_order() {
+        let config = PipelineConfig::from_json(
+            r#"{"recovery_order": ["archive_lookup", "retry"], "dno_extraction_order": {"netze-bw": ["ocr", "ai"]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.extraction_order_for(Some("netze-bw"))[0], ExtractionMethod::Ocr);
+        assert_eq!(config.extraction_order_for(Some("westnetz")), PipelineConfig::default().extraction_order.as_slice());
+        assert_eq!(config.recovery_order, vec![RecoveryStrategy::ArchiveLookup, RecoveryStrategy::Retry]);
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use url::Url;
+
+/// A URL waiting to be crawled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedUrl {
+    pub url: String,
+    /// Higher is crawled first
+    pub priority: f64,
+    /// Link depth from the crawl's seed
+    pub depth: u32,
+    /// Why the URL was queued, for logs and crawl reports
+    pub reason: String,
+}
+
+impl Eq for QueuedUrl {}
+
+impl Ord for QueuedUrl {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .total_cmp(&other.priority)
+            // Shallower first, then a stable order for equal priorities
+            .then_with(|| other.depth.cmp(&self.depth))
+            .then_with(|| other.url.cmp(&self.url))
+    }
+}
+
+impl PartialOrd for QueuedUrl {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Priority queue of URLs to crawl; each URL is queued at most once per crawl,
+/// compared by `normalize_url`. Serializes with the URLs already seen, so a
+/// resumed crawl keeps skipping them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrawlQueue {
+    heap: BinaryHeap<QueuedUrl>,
+    seen: HashSet<String>,
+}
+
+impl CrawlQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a URL; returns `false` if it, or a URL that only differs in
+    /// its fragment or query order, was queued or crawled before
+    pub fn push(&mut self, entry: QueuedUrl) -> bool {
+        if !self.seen.insert(normalize_url(&entry.url)) {
+            return false;
+        }
+        self.heap.push(entry);
+        true
+    }
+
+    /// Queue a URL that was already crawled again, for a retry
+    pub fn requeue(&mut self, entry: QueuedUrl) {
+        self.seen.insert(normalize_url(&entry.url));
+        self.heap.push(entry);
+    }
+
+    pub fn pop(&mut self) -> Option<QueuedUrl> {
+        self.heap.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+/// The form URLs are compared in: without fragment, query parameters sorted,
+/// an empty query dropped. Parsing already lowercases the host and drops a
+/// default port. A URL that doesn't parse is only trimmed.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    parsed.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+    if pairs.is_empty() {
+        parsed.set_query(None);
+    } else {
+        pairs.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    parsed.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str) -> QueuedUrl {
+        QueuedUrl { url: url.to_string(), priority: 1.0, depth: 1, reason: "test".to_string() }
+    }
+
+    #[test]
+    fn test_equivalent_urls_are_queued_once() {
+        let mut queue = CrawlQueue::new();
+        assert!(queue.push(entry("https://www.netze-bw.de/netzentgelte?jahr=2024&typ=strom")));
+        assert!(!queue.push(entry("https://www.netze-bw.de/netzentgelte?jahr=2024&typ=strom")));
+        assert!(!queue.push(entry("https://www.netze-bw.de/netzentgelte?typ=strom&jahr=2024")));
+        assert!(!queue.push(entry("https://WWW.Netze-BW.de:443/netzentgelte?jahr=2024&typ=strom#preisblatt")));
+        assert!(queue.push(entry("https://www.netze-bw.de/netzentgelte?jahr=2025&typ=strom")));
+        assert_eq!(queue.len(), 2);
+
+        // Fragment-only differences are the same page
+        assert!(queue.push(entry("https://www.netze-bw.de/downloads")));
+        assert!(!queue.push(entry("https://www.netze-bw.de/downloads#2024")));
+        assert!(!queue.push(entry("https://www.netze-bw.de/downloads?")));
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn test_crawled_urls_are_not_queued_again() {
+        let mut queue = CrawlQueue::new();
+        queue.push(entry("https://www.westnetz.de/preisblaetter?b=2&a=1"));
+        let crawled = queue.pop().unwrap();
+        assert!(queue.is_empty());
+
+        assert!(!queue.push(entry("https://www.westnetz.de/preisblaetter?a=1&b=2#top")));
+        queue.requeue(crawled);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(normalize_url(" not a url "), "not a url");
+    }
+}
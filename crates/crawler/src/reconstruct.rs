@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// URLs generated per pattern unless `CRAWLER_MAX_URLS_PER_PATTERN` says otherwise
+pub const DEFAULT_MAX_URLS_PER_PATTERN: usize = 100;
+
+/// A placeholder in a URL template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    /// `{year}`, four digits
+    Year,
+    /// `{month}`, `01` to `12`
+    Month,
+    /// `{quarter}`, `1` to `4`, usually written `Q{quarter}`
+    Quarter,
+}
+
+impl VariableType {
+    const ALL: [VariableType; 3] = [VariableType::Year, VariableType::Month, VariableType::Quarter];
+
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            VariableType::Year => "{year}",
+            VariableType::Month => "{month}",
+            VariableType::Quarter => "{quarter}",
+        }
+    }
+
+    /// The values substituted for one year
+    fn values(self) -> Vec<String> {
+        match self {
+            VariableType::Year => Vec::new(),
+            VariableType::Month => (1..=12).map(|month| format!("{:02}", month)).collect(),
+            VariableType::Quarter => (1..=4).map(|quarter| quarter.to_string()).collect(),
+        }
+    }
+}
+
+/// A URL template such as `/archiv/{year}/{month}/tarif.pdf` and the
+/// placeholders to fill in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UrlPattern {
+    pub template: String,
+    pub variables: Vec<VariableType>,
+}
+
+impl UrlPattern {
+    /// A pattern filling in every placeholder the template contains
+    pub fn new(template: impl Into<String>) -> Self {
+        let template = template.into();
+        let variables = VariableType::ALL
+            .into_iter()
+            .filter(|variable| template.contains(variable.placeholder()))
+            .collect();
+        Self { template, variables }
+    }
+}
+
+/// Expands URL patterns into candidate URLs for a range of years
+#[derive(Debug, Clone)]
+pub struct UrlReconstructor {
+    pub max_urls_per_pattern: usize,
+}
+
+impl Default for UrlReconstructor {
+    fn default() -> Self {
+        Self { max_urls_per_pattern: DEFAULT_MAX_URLS_PER_PATTERN }
+    }
+}
+
+impl UrlReconstructor {
+    /// Read `CRAWLER_MAX_URLS_PER_PATTERN`; the default when unset or zero
+    pub fn from_env() -> Self {
+        let max_urls_per_pattern = std::env::var("CRAWLER_MAX_URLS_PER_PATTERN")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_URLS_PER_PATTERN);
+        Self { max_urls_per_pattern }
+    }
+
+    /// Every combination of the pattern's variables over `years`: each year
+    /// with each month and quarter the pattern uses, in calendar order, at
+    /// most `max_urls_per_pattern` URLs
+    pub fn generate_urls(&self, pattern: &UrlPattern, years: RangeInclusive<i32>) -> Vec<String> {
+        let mut urls = Vec::new();
+        for year in years {
+            let mut expanded = vec![pattern.template.clone()];
+            if pattern.variables.contains(&VariableType::Year) {
+                expanded = expanded.iter().map(|url| url.replace("{year}", &year.to_string())).collect();
+            }
+            for variable in pattern.variables.iter().filter(|variable| **variable != VariableType::Year) {
+                expanded = expanded
+                    .iter()
+                    .flat_map(|url| variable.values().into_iter().map(move |value| url.replace(variable.placeholder(), &value)))
+                    .collect();
+            }
+            for url in expanded {
+                if urls.len() >= self.max_urls_per_pattern {
+                    return urls;
+                }
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+        }
+        urls
+    }
+
+    /// Candidate URLs for a single year
+    pub fn generate_urls_for_year(&self, pattern: &UrlPattern, year: i32) -> Vec<String> {
+        self.generate_urls(pattern, year..=year)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_template_expands_over_two_years() {
+        let pattern = UrlPattern::new("https://netze.example/archiv/{year}/{month}/tarif.pdf");
+        assert_eq!(pattern.variables, [VariableType::Year, VariableType::Month]);
+
+        let urls = UrlReconstructor::default().generate_urls(&pattern, 2023..=2024);
+        let expected: Vec<String> = (2023..=2024)
+            .flat_map(|year| (1..=12).map(move |month| format!("https://netze.example/archiv/{}/{:02}/tarif.pdf", year, month)))
+            .collect();
+        assert_eq!(urls, expected);
+
+        let capped = UrlReconstructor { max_urls_per_pattern: 5 }.generate_urls(&pattern, 2023..=2024);
+        assert_eq!(capped, expected[..5]);
+
+        let quarters = UrlReconstructor::default()
+            .generate_urls_for_year(&UrlPattern::new("https://netze.example/{year}/Q{quarter}/preisblatt.pdf"), 2024);
+        assert_eq!(
+            quarters,
+            [
+                "https://netze.example/2024/Q1/preisblatt.pdf",
+                "https://netze.example/2024/Q2/preisblatt.pdf",
+                "https://netze.example/2024/Q3/preisblatt.pdf",
+                "https://netze.example/2024/Q4/preisblatt.pdf",
+            ]
+        );
+    }
+}
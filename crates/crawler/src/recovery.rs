@@ -0,0 +1,198 @@
+use crate::error::CrawlError;
+use crate::pipeline::{PipelineConfig, RecoveryStrategy};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// How often failed fetches may be retried: per URL, and in total across a
+/// crawl session so a flaky site cannot multiply retries over hundreds of URLs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryLimits {
+    pub per_url: u32,
+    pub per_session: u32,
+}
+
+impl Default for RetryLimits {
+    fn default() -> Self {
+        Self { per_url: 2, per_session: 20 }
+    }
+}
+
+impl RetryLimits {
+    /// Read `CRAWLER_MAX_RETRIES_PER_URL` and `CRAWLER_SESSION_RETRY_BUDGET`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u32>().ok());
+        Self {
+            per_url: var("CRAWLER_MAX_RETRIES_PER_URL").unwrap_or(defaults.per_url),
+            per_session: var("CRAWLER_SESSION_RETRY_BUDGET").unwrap_or(defaults.per_session),
+        }
+    }
+}
+
+/// Retries one crawl session has spent, shared by all of its URLs
+#[derive(Debug)]
+pub struct RetryBudget {
+    limits: RetryLimits,
+    /// Retries used in total and per URL
+    used: Mutex<(u32, HashMap<String, u32>)>,
+}
+
+impl RetryBudget {
+    pub fn new(limits: RetryLimits) -> Self {
+        Self { limits, used: Mutex::new((0, HashMap::new())) }
+    }
+
+    /// Take a retry for `url`; `false` once the URL or the session has none left
+    pub fn try_acquire(&self, url: &str) -> bool {
+        let mut used = self.used.lock().unwrap();
+        let (total, per_url) = &mut *used;
+        let for_url = per_url.entry(url.to_string()).or_default();
+        if *total >= self.limits.per_session || *for_url >= self.limits.per_url {
+            return false;
+        }
+        *total += 1;
+        *for_url += 1;
+        true
+    }
+
+    /// Retries the session has left
+    pub fn remaining(&self) -> u32 {
+        self.limits.per_session.saturating_sub(self.used.lock().unwrap().0)
+    }
+
+    pub fn used(&self) -> u32 {
+        self.used.lock().unwrap().0
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+/// Chooses the next recovery strategy for a failed URL, walking the order
+/// configured in `PipelineConfig::recovery_order`
+#[derive(Debug, Clone)]
+pub struct FailureRecoverySystem {
+    order: Vec<RecoveryStrategy>,
+}
+
+impl FailureRecoverySystem {
+    pub fn new(config: &PipelineConfig) -> Self {
+        Self {
+            order: config.recovery_order.clone(),
+        }
+    }
+
+    pub fn strategies(&self) -> &[RecoveryStrategy] {
+        &self.order
+    }
+
+    /// Strategy to try after `previous`; the first one when nothing was tried yet
+    pub fn next_strategy(&self, previous: Option<RecoveryStrategy>) -> Option<RecoveryStrategy> {
+        match previous {
+            None => self.order.first().copied(),
+            Some(previous) => self
+                .order
+                .iter()
+                .position(|s| *s == previous)
+                .and_then(|i| self.order.get(i + 1))
+                .copied(),
+        }
+    }
+
+    /// First strategy to try for a failure, by its kind: strategies that suit
+    /// the failure are tried in preference order, skipping any left out of the
+    /// configured order. `None` when the failure cannot be recovered from
+    /// (cancelled, budget exhausted) or no suitable strategy is configured.
+    pub fn classify_failure(&self, error: &CrawlError) -> Option<RecoveryStrategy> {
+        use RecoveryStrategy::*;
+        let suitable: &[RecoveryStrategy] = match error {
+            CrawlError::Timeout { .. } | CrawlError::Network { .. } => &[Retry, ArchiveLookup, ManualIntervention],
+            // A retry goes out through another proxy of the pool
+            CrawlError::Proxy { .. } => &[Retry, ManualIntervention],
+            CrawlError::Http { status, .. } => match status {
+                429 | 500..=599 => &[Retry, ArchiveLookup, ManualIntervention],
+                // Behind a login or denied outright: someone has to get access first
+                401 | 403 => &[ManualIntervention, ArchiveLookup],
+                404 | 410 => &[ArchiveLookup, AlternateUrl, ManualIntervention],
+                _ => &[AlternateUrl, ArchiveLookup, ManualIntervention],
+            },
+            CrawlError::Redirect(_) => &[AlternateUrl, ArchiveLookup, ManualIntervention],
+            CrawlError::Parse { .. } | CrawlError::Extraction { .. } => &[AlternateExtractor, ManualIntervention],
+            CrawlError::Blocked { .. } => &[ManualIntervention],
+            CrawlError::Budget(_) | CrawlError::Cancelled => &[],
+            CrawlError::Other(_) => return self.next_strategy(None),
+        };
+        suitable.iter().copied().find(|strategy| self.order.contains(strategy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_recovery_order_is_followed() {
+        let default = FailureRecoverySystem::new(&PipelineConfig::default());
+        assert_eq!(default.next_strategy(None), Some(RecoveryStrategy::Retry));
+
+        let config = PipelineConfig::from_json(r#"{"recovery_order": ["archive_lookup", "manual_intervention"]}"#).unwrap();
+        let recovery = FailureRecoverySystem::new(&config);
+        assert_eq!(recovery.next_strategy(None), Some(RecoveryStrategy::ArchiveLookup));
+        assert_eq!(
+            recovery.next_strategy(Some(RecoveryStrategy::ArchiveLookup)),
+            Some(RecoveryStrategy::ManualIntervention)
+        );
+        assert_eq!(recovery.next_strategy(Some(RecoveryStrategy::ManualIntervention)), None);
+    }
+
+    #[test]
+    fn test_failures_map_to_strategies_by_kind() {
+        let recovery = FailureRecoverySystem::new(&PipelineConfig::default());
+        let url = || "https://netze.example/preisblatt.pdf".to_string();
+        let cases = [
+            (CrawlError::Timeout { url: url() }, Some(RecoveryStrategy::Retry)),
+            (CrawlError::Http { url: url(), status: 503 }, Some(RecoveryStrategy::Retry)),
+            (
+                CrawlError::Proxy { proxy: "socks5h://127.0.0.1:9050".to_string(), url: url(), message: "connection refused".to_string() },
+                Some(RecoveryStrategy::Retry),
+            ),
+            (CrawlError::Http { url: url(), status: 404 }, Some(RecoveryStrategy::ArchiveLookup)),
+            (CrawlError::Http { url: url(), status: 403 }, Some(RecoveryStrategy::ManualIntervention)),
+            (CrawlError::Http { url: url(), status: 401 }, Some(RecoveryStrategy::ManualIntervention)),
+            (CrawlError::Http { url: url(), status: 400 }, Some(RecoveryStrategy::AlternateUrl)),
+            (CrawlError::Blocked { url: url(), reason: "internal target address".to_string() }, Some(RecoveryStrategy::ManualIntervention)),
+            (CrawlError::Parse { url: url(), message: "bad table".to_string() }, Some(RecoveryStrategy::AlternateExtractor)),
+            (CrawlError::Extraction { url: url(), message: "no data".to_string() }, Some(RecoveryStrategy::AlternateExtractor)),
+            (CrawlError::Budget("time limit".to_string()), None),
+            (CrawlError::Cancelled, None),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(recovery.classify_failure(&error), expected, "{:?}", error);
+        }
+
+        // Without retries configured, a timeout goes to the archive instead
+        let config = PipelineConfig::from_json(r#"{"recovery_order": ["archive_lookup", "manual_intervention"]}"#).unwrap();
+        let no_retry = FailureRecoverySystem::new(&config);
+        assert_eq!(no_retry.classify_failure(&CrawlError::Timeout { url: url() }), Some(RecoveryStrategy::ArchiveLookup));
+    }
+
+    #[test]
+    fn test_kind_survives_anyhow_and_messages_are_not_matched() {
+        let recovery = FailureRecoverySystem::new(&PipelineConfig::default());
+
+        let wrapped = anyhow::Error::from(CrawlError::Http { url: "https://netze.example/".to_string(), status: 410 })
+            .context("discovering related documents");
+        let error = CrawlError::from_anyhow(wrapped);
+        assert!(matches!(error, CrawlError::Http { status: 410, .. }));
+        assert_eq!(recovery.classify_failure(&error), Some(RecoveryStrategy::ArchiveLookup));
+
+        let redirect = anyhow::Error::from(crate::http::RedirectError::MissingLocation { url: "https://netze.example/".to_string(), status: 302 });
+        assert_eq!(recovery.classify_failure(&CrawlError::from_anyhow(redirect)), Some(RecoveryStrategy::AlternateUrl));
+
+        // An untyped error stays untyped even when its text mentions a timeout
+        let untyped = CrawlError::from_anyhow(anyhow::anyhow!("operation timed out (HTTP 404)"));
+        assert!(matches!(untyped, CrawlError::Other(_)));
+        assert_eq!(recovery.classify_failure(&untyped), recovery.next_strategy(None));
+    }
+}
@@ -0,0 +1,862 @@
+use crate::adaptive::{url_shape, year_in};
+use crate::content::ContentType;
+use crate::error::CrawlError;
+use crate::http::{HttpRequest, HttpTransport};
+use crate::pagination::links_on_page;
+use crate::reconstruct::{UrlPattern, UrlReconstructor};
+use crate::sitemap::{parse_sitemap, DiscoveredUrl, DiscoveryMethod, Sitemap, TemporalData};
+use anyhow::{anyhow, bail, Context, Result};
+use dno_core::navigation::{get_navigation_path, NavigationAction, NavigationStep};
+use dno_core::source_manager::{FileMetadata, NewSourceFile, SourceManager};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+/// Sitemap index nesting followed below `/sitemap.xml`
+const MAX_SITEMAP_DEPTH: u32 = 3;
+/// Sitemaps fetched per discovery, so a huge or looping index cannot stall it
+const MAX_SITEMAPS: usize = 50;
+/// Pages loaded per historical discovery when following links from the start page
+const MAX_DISCOVERY_PAGES: usize = 50;
+/// How many years before the earliest known one a historical discovery looks for
+pub const HISTORY_YEARS: i32 = 10;
+
+/// Limits for fetching discovered documents, tuned separately from page navigation
+#[derive(Debug, Clone)]
+pub struct DownloadConfig {
+    /// Downloads in flight across all hosts
+    pub max_concurrent_downloads: usize,
+    /// Downloads in flight against one host
+    pub max_downloads_per_host: usize,
+    /// Minimum time between the starts of two downloads from the same host
+    pub per_host_delay: Duration,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_downloads: 4,
+            max_downloads_per_host: 2,
+            per_host_delay: Duration::from_millis(250),
+        }
+    }
+}
+
+impl DownloadConfig {
+    /// Read `CRAWLER_MAX_CONCURRENT_DOWNLOADS`, `CRAWLER_MAX_DOWNLOADS_PER_HOST`
+    /// and `CRAWLER_DOWNLOAD_DELAY_MS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            max_concurrent_downloads: var("CRAWLER_MAX_CONCURRENT_DOWNLOADS")
+                .filter(|&n| n > 0)
+                .map_or(defaults.max_concurrent_downloads, |n| n as usize),
+            max_downloads_per_host: var("CRAWLER_MAX_DOWNLOADS_PER_HOST")
+                .filter(|&n| n > 0)
+                .map_or(defaults.max_downloads_per_host, |n| n as usize),
+            per_host_delay: var("CRAWLER_DOWNLOAD_DELAY_MS")
+                .map_or(defaults.per_host_delay, Duration::from_millis),
+        }
+    }
+}
+
+/// Per-host download slots and request spacing
+struct HostGate {
+    slots: Arc<Semaphore>,
+    next_start: tokio::sync::Mutex<Instant>,
+}
+
+impl HostGate {
+    fn new(slots: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(slots)),
+            next_start: tokio::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for a free slot on the host, then for the host's request spacing
+    async fn enter(&self, delay: Duration) -> OwnedSemaphorePermit {
+        let permit = self.slots.clone().acquire_owned().await.expect("host semaphore is never closed");
+        let mut next_start = self.next_start.lock().await;
+        tokio::time::sleep_until(*next_start).await;
+        *next_start = Instant::now() + delay;
+        permit
+    }
+}
+
+/// A URL reconstructed from the naming pattern of a document that was
+/// found before; nothing has been fetched from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateUrl {
+    pub url: String,
+    /// The year the URL was generated for
+    pub year: i32,
+    /// The shape the URL was generated from, e.g. `/files/preisblatt-{year}.pdf`
+    pub pattern: UrlPattern,
+    /// The successful endpoint the pattern was learned from
+    pub source_url: String,
+}
+
+/// What happened to one document URL
+#[derive(Debug)]
+pub struct DownloadResult {
+    pub url: String,
+    pub outcome: Result<FileMetadata>,
+}
+
+/// Where replaying a navigation path got to
+#[derive(Debug)]
+pub struct PathRun {
+    /// Pages loaded, in order, including the downloaded document
+    pub visited: Vec<String>,
+    /// URL of the last step
+    pub reached: String,
+    /// The stored document, when the path ends in a `download` step
+    pub document: Option<FileMetadata>,
+}
+
+/// What `discover_historical_data` found, and stored unless it was a dry run
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoricalDiscovery {
+    pub dno: String,
+    pub site_url: String,
+    pub known_years: Vec<i32>,
+    /// Years looked for: up to `HISTORY_YEARS` before the earliest known one, known ones excluded
+    pub searched_years: Vec<i32>,
+    /// Documents on the site, from its sitemap or linked from its pages
+    pub discovered: Vec<DiscoveredUrl>,
+    /// Patterns learned from the documents of the known years
+    pub patterns: Vec<UrlPattern>,
+    /// URLs reconstructed from `patterns` for the searched years
+    pub candidates: Vec<CandidateUrl>,
+    pub stored: Vec<FileMetadata>,
+    /// URLs that did not exist or could not be stored
+    pub failed: Vec<String>,
+    pub dry_run: bool,
+}
+
+/// Fetches the documents discovered for a DNO and stores them through the
+/// `SourceManager`, with download limits independent of page navigation
+pub struct ReverseCrawler {
+    transport: Arc<dyn HttpTransport>,
+    sources: Arc<SourceManager>,
+    downloads: DownloadConfig,
+    hosts: Mutex<HashMap<String, Arc<HostGate>>>,
+    /// Expands the patterns of successful endpoints into candidate URLs
+    reconstructor: UrlReconstructor,
+}
+
+impl ReverseCrawler {
+    pub fn new(transport: Arc<dyn HttpTransport>, sources: Arc<SourceManager>, downloads: DownloadConfig) -> Self {
+        Self {
+            transport,
+            sources,
+            downloads,
+            hosts: Mutex::new(HashMap::new()),
+            reconstructor: UrlReconstructor::from_env(),
+        }
+    }
+
+    /// Cap URLs per pattern with this reconstructor instead of the environment's
+    pub fn with_reconstructor(mut self, reconstructor: UrlReconstructor) -> Self {
+        self.reconstructor = reconstructor;
+        self
+    }
+
+    /// The URLs a reverse crawl from `endpoints` would fetch, without
+    /// fetching or storing anything. Each endpoint's path becomes a pattern
+    /// (years, months and quarters replaced by placeholders), which is
+    /// expanded for every year in `years`. Endpoints without a year in their
+    /// path, HT/NT variants and the endpoints themselves are left out.
+    pub fn plan(&self, endpoints: &[String], years: RangeInclusive<i32>) -> Vec<CandidateUrl> {
+        let known: HashSet<&str> = endpoints.iter().map(String::as_str).collect();
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::new();
+
+        for endpoint in endpoints {
+            let Ok(url) = Url::parse(endpoint) else {
+                warn!("Skipping unparseable endpoint {}", endpoint);
+                continue;
+            };
+            let shape = url_shape(&url);
+            // Which variant exists is only known once a page lists them
+            if !shape.contains("{year}") || shape.contains("{variant}") {
+                debug!("No reconstructable pattern in {}", endpoint);
+                continue;
+            }
+            let pattern = UrlPattern::new(shape);
+            for year in years.clone() {
+                for path in self.reconstructor.generate_urls_for_year(&pattern, year) {
+                    let Ok(candidate) = url.join(&path) else { continue };
+                    let candidate = candidate.to_string();
+                    if known.contains(candidate.as_str()) || !seen.insert(candidate.clone()) {
+                        continue;
+                    }
+                    candidates.push(CandidateUrl {
+                        url: candidate,
+                        year,
+                        pattern: pattern.clone(),
+                        source_url: endpoint.clone(),
+                    });
+                }
+            }
+        }
+        info!("{} candidate URLs from {} endpoints for {:?}", candidates.len(), endpoints.len(), years);
+        candidates
+    }
+
+    /// Reverse crawl from documents that were found before: `plan` the
+    /// candidate URLs, then download and store every one that exists, under
+    /// the year it was generated for
+    pub async fn reverse_crawl_from_successful_endpoints(
+        &self,
+        dno: &str,
+        data_type: &str,
+        endpoints: &[String],
+        years: RangeInclusive<i32>,
+    ) -> Vec<DownloadResult> {
+        let candidates = self.plan(endpoints, years);
+        stream::iter(&candidates)
+            .map(|candidate| async move {
+                let outcome = self.download_and_store(dno, candidate.year, data_type, &candidate.url).await;
+                if let Err(e) = &outcome {
+                    debug!("Candidate {} from {} not stored: {:#}", candidate.url, candidate.pattern.template, e);
+                }
+                DownloadResult { url: candidate.url.clone(), outcome }
+            })
+            .buffer_unordered(self.downloads.max_concurrent_downloads.max(1))
+            .collect()
+            .await
+    }
+
+    fn host_gate(&self, host: &str) -> Arc<HostGate> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(HostGate::new(self.downloads.max_downloads_per_host.max(1))))
+            .clone()
+    }
+
+    /// Download documents and store them under the DNO and year, at most
+    /// `max_concurrent_downloads` at a time and within the per-host limits.
+    /// Results come back in completion order; one failed URL does not stop the others.
+    pub async fn download_and_store_content(
+        &self,
+        dno: &str,
+        year: i32,
+        data_type: &str,
+        urls: &[String],
+    ) -> Vec<DownloadResult> {
+        stream::iter(urls)
+            .map(|url| async move {
+                let outcome = self.download_and_store(dno, year, data_type, url).await;
+                if let Err(e) = &outcome {
+                    warn!("Download of {} failed: {:#}", url, e);
+                }
+                DownloadResult { url: url.clone(), outcome }
+            })
+            .buffer_unordered(self.downloads.max_concurrent_downloads.max(1))
+            .collect()
+            .await
+    }
+
+    /// Replay a hand-authored navigation path: load pages, follow links by
+    /// URL or anchor text, and store the document of a final `download` step
+    pub async fn follow_path(
+        &self,
+        dno: &str,
+        year: i32,
+        data_type: &str,
+        steps: &[NavigationStep],
+    ) -> Result<PathRun> {
+        let mut page: Option<(Url, String)> = None;
+        let mut visited = Vec::new();
+        let mut document = None;
+
+        for (i, step) in steps.iter().enumerate() {
+            let target = match (&step.url, &step.link_text, &page) {
+                (Some(url), _, Some((base, _))) => base.join(url).ok(),
+                (Some(url), _, None) => Url::parse(url).ok(),
+                (None, Some(text), Some((base, html))) => {
+                    let wanted = text.to_lowercase();
+                    links_on_page(base, html)
+                        .into_iter()
+                        .find(|(_, anchor)| anchor.to_lowercase().contains(&wanted))
+                        .map(|(url, _)| url)
+                }
+                (None, _, _) => None,
+            };
+            let Some(target) = target else {
+                let on = page.as_ref().map_or("the start".to_string(), |(url, _)| url.to_string());
+                bail!("Step {} ({}) found no target on {}", i + 1, step.action.as_str(), on);
+            };
+            debug!("Navigation step {}: {} {}", i + 1, step.action.as_str(), target);
+            visited.push(target.to_string());
+
+            if step.action == NavigationAction::Download {
+                document = Some(self.download_and_store(dno, year, data_type, target.as_str()).await?);
+                break;
+            }
+            let response = self.transport.execute(HttpRequest::get(target.as_str())).await?;
+            if !response.is_success() {
+                return Err(CrawlError::Http { url: target.to_string(), status: response.status }.into());
+            }
+            page = Some((target, response.text()));
+        }
+
+        let reached = visited.last().cloned().ok_or_else(|| anyhow!("Navigation path has no steps"))?;
+        Ok(PathRun { visited, reached, document })
+    }
+
+    /// Run a reverse crawl along the navigation path stored under `path_id`
+    pub async fn run_stored_path(
+        &self,
+        pool: &sqlx::PgPool,
+        path_id: Uuid,
+        year: i32,
+        data_type: &str,
+    ) -> Result<PathRun> {
+        let path = get_navigation_path(pool, path_id)
+            .await?
+            .with_context(|| format!("Navigation path {} not found", path_id))?;
+        info!("Following stored path {} for {} {} {}", path_id, path.dno_name, data_type, year);
+        self.follow_path(&path.dno_name, year, data_type, &path.steps).await
+    }
+
+    /// List the pages and documents in the site's `/sitemap.xml` (or
+    /// `/sitemap.xml.gz`), following nested sitemap indexes. Each URL is
+    /// tagged with its year and `<lastmod>`; the document URLs among them can
+    /// go on to `download_and_store_content`. A child sitemap that fails is
+    /// skipped, a site without any sitemap is an error.
+    pub async fn discover_from_sitemap(&self, base_url: &str) -> Result<Vec<DiscoveredUrl>> {
+        let base = Url::parse(base_url).with_context(|| format!("Invalid site URL: {}", base_url))?;
+        let root = match self.fetch_sitemap(&base.join("/sitemap.xml")?).await {
+            Ok(root) => root,
+            Err(e) => {
+                debug!("{:#}, trying the gzipped sitemap", e);
+                self.fetch_sitemap(&base.join("/sitemap.xml.gz")?).await?
+            }
+        };
+
+        let mut pending = VecDeque::from([(root, 0)]);
+        let mut fetched = 1;
+        let mut seen = HashSet::new();
+        let mut discovered = Vec::new();
+        while let Some(((sitemap_url, sitemap), depth)) = pending.pop_front() {
+            match sitemap {
+                Sitemap::Index(children) => {
+                    for child in children {
+                        let Ok(child_url) = sitemap_url.join(&child.loc) else { continue };
+                        if depth >= MAX_SITEMAP_DEPTH || fetched >= MAX_SITEMAPS || !seen.insert(child_url.to_string()) {
+                            warn!("Not following sitemap {} listed in {}", child_url, sitemap_url);
+                            continue;
+                        }
+                        fetched += 1;
+                        match self.fetch_sitemap(&child_url).await {
+                            Ok(child) => pending.push_back((child, depth + 1)),
+                            Err(e) => warn!("Skipping sitemap {}: {:#}", child_url, e),
+                        }
+                    }
+                }
+                Sitemap::UrlSet(entries) => {
+                    for entry in entries {
+                        let Ok(url) = sitemap_url.join(&entry.loc) else { continue };
+                        if !seen.insert(url.to_string()) {
+                            continue;
+                        }
+                        discovered.push(DiscoveredUrl {
+                            method: DiscoveryMethod::SitemapParsing,
+                            temporal: TemporalData { year: year_in(url.path()), last_modified: entry.last_modified },
+                            found_in: sitemap_url.to_string(),
+                            url: url.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        info!("Sitemap of {} lists {} URLs across {} sitemaps", base_url, discovered.len(), fetched);
+        Ok(discovered)
+    }
+
+    /// Look for a DNO's documents of earlier years. The documents on the site
+    /// are found through its sitemap and by following links from `site_url`,
+    /// at most `max_depth` links deep on the same host. The ones of
+    /// `known_years` become patterns, expanded for up to `HISTORY_YEARS`
+    /// before the earliest known year. Discovered documents of the searched
+    /// years and the reconstructed candidates are then downloaded and stored
+    /// under `data_type`; with `dry_run` nothing is downloaded.
+    pub async fn discover_historical_data(
+        &self,
+        dno: &str,
+        site_url: &str,
+        data_type: &str,
+        known_years: &[i32],
+        max_depth: u32,
+        dry_run: bool,
+    ) -> Result<HistoricalDiscovery> {
+        let (Some(&earliest), Some(&latest)) = (known_years.iter().min(), known_years.iter().max()) else {
+            bail!("At least one known year is needed to learn URL patterns from");
+        };
+        let searched_years: Vec<i32> =
+            (earliest - HISTORY_YEARS..=latest).filter(|year| !known_years.contains(year)).collect();
+
+        let mut discovered = match self.discover_from_sitemap(site_url).await {
+            Ok(listed) => listed,
+            Err(e) => {
+                debug!("No sitemap for {}: {:#}", site_url, e);
+                Vec::new()
+            }
+        };
+        for found in self.discover_linked(site_url, max_depth).await? {
+            if !discovered.iter().any(|listed| listed.url == found.url) {
+                discovered.push(found);
+            }
+        }
+        discovered.retain(|found| {
+            Url::parse(&found.url).is_ok_and(|url| {
+                !matches!(ContentType::from_path(url.path()), ContentType::Html | ContentType::Text | ContentType::Unknown)
+            })
+        });
+
+        let endpoints: Vec<String> = discovered
+            .iter()
+            .filter(|found| found.temporal.year.is_some_and(|year| known_years.contains(&year)))
+            .map(|found| found.url.clone())
+            .collect();
+        let candidates: Vec<CandidateUrl> = self
+            .plan(&endpoints, earliest - HISTORY_YEARS..=latest)
+            .into_iter()
+            .filter(|candidate| searched_years.contains(&candidate.year))
+            .filter(|candidate| !discovered.iter().any(|found| found.url == candidate.url))
+            .collect();
+        let mut patterns: Vec<UrlPattern> = Vec::new();
+        for candidate in &candidates {
+            if !patterns.contains(&candidate.pattern) {
+                patterns.push(candidate.pattern.clone());
+            }
+        }
+
+        let mut stored = Vec::new();
+        let mut failed = Vec::new();
+        if !dry_run {
+            let targets: Vec<(String, i32)> = discovered
+                .iter()
+                .filter_map(|found| Some((found.url.clone(), found.temporal.year?)))
+                .filter(|(_, year)| searched_years.contains(year))
+                .chain(candidates.iter().map(|candidate| (candidate.url.clone(), candidate.year)))
+                .collect();
+            let results: Vec<DownloadResult> = stream::iter(&targets)
+                .map(|(url, year)| async move {
+                    let outcome = self.download_and_store(dno, *year, data_type, url).await;
+                    if let Err(e) = &outcome {
+                        debug!("Historical document {} not stored: {:#}", url, e);
+                    }
+                    DownloadResult { url: url.clone(), outcome }
+                })
+                .buffer_unordered(self.downloads.max_concurrent_downloads.max(1))
+                .collect()
+                .await;
+            for result in results {
+                match result.outcome {
+                    Ok(file) => stored.push(file),
+                    Err(_) => failed.push(result.url),
+                }
+            }
+            stored.sort_by_key(|file| file.year);
+            failed.sort();
+        }
+
+        info!(
+            "Historical discovery for {}: {} documents, {} patterns, {} candidates, {} stored",
+            dno,
+            discovered.len(),
+            patterns.len(),
+            candidates.len(),
+            stored.len()
+        );
+        Ok(HistoricalDiscovery {
+            dno: dno.to_string(),
+            site_url: site_url.to_string(),
+            known_years: known_years.to_vec(),
+            searched_years,
+            discovered,
+            patterns,
+            candidates,
+            stored,
+            failed,
+            dry_run,
+        })
+    }
+
+    /// Links on the pages of the site, breadth first from `start_url` and
+    /// at most `max_depth` links deep; only pages on the start host are loaded.
+    /// A start page that cannot be loaded is an error, later pages are skipped.
+    async fn discover_linked(&self, start_url: &str, max_depth: u32) -> Result<Vec<DiscoveredUrl>> {
+        let start = Url::parse(start_url).with_context(|| format!("Invalid site URL: {}", start_url))?;
+        let mut pending = VecDeque::from([(start.clone(), 0)]);
+        let mut seen = HashSet::from([start.to_string()]);
+        let mut loaded = 0;
+        let mut discovered = Vec::new();
+
+        while let Some((page_url, depth)) = pending.pop_front() {
+            if loaded >= MAX_DISCOVERY_PAGES {
+                warn!("Stopped following links after {} pages of {}", loaded, start_url);
+                break;
+            }
+            loaded += 1;
+            let html = match self.transport.execute(HttpRequest::get(page_url.as_str())).await {
+                Ok(response) if response.is_success() => response.text(),
+                Ok(response) if page_url == start => {
+                    return Err(CrawlError::Http { url: page_url.to_string(), status: response.status }.into());
+                }
+                Err(e) if page_url == start => return Err(e),
+                Ok(response) => {
+                    debug!("Skipping {}: HTTP {}", page_url, response.status);
+                    continue;
+                }
+                Err(e) => {
+                    debug!("Skipping {}: {:#}", page_url, e);
+                    continue;
+                }
+            };
+
+            for (mut url, _) in links_on_page(&page_url, &html) {
+                url.set_fragment(None);
+                if !seen.insert(url.to_string()) {
+                    continue;
+                }
+                let is_page = matches!(ContentType::from_path(url.path()), ContentType::Html | ContentType::Unknown);
+                if is_page {
+                    if depth < max_depth && url.host_str() == start.host_str() {
+                        pending.push_back((url, depth + 1));
+                    }
+                    continue;
+                }
+                discovered.push(DiscoveredUrl {
+                    method: DiscoveryMethod::LinkFollowing,
+                    temporal: TemporalData { year: year_in(url.path()), last_modified: None },
+                    found_in: page_url.to_string(),
+                    url: url.to_string(),
+                });
+            }
+        }
+        Ok(discovered)
+    }
+
+    async fn fetch_sitemap(&self, url: &Url) -> Result<(Url, Sitemap)> {
+        let response = self.transport.execute(HttpRequest::get(url.as_str())).await?;
+        if !response.is_success() {
+            return Err(CrawlError::Http { url: url.to_string(), status: response.status }.into());
+        }
+        Ok((url.clone(), parse_sitemap(url.as_str(), &response.body)?))
+    }
+
+    async fn download_and_store(&self, dno: &str, year: i32, data_type: &str, url: &str) -> Result<FileMetadata> {
+        let parsed = Url::parse(url).with_context(|| format!("Invalid document URL: {}", url))?;
+        let host = parsed.host_str().context("Document URL has no host")?.to_string();
+        let file_name = parsed
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("document")
+            .to_string();
+
+        let response = {
+            let _slot = self.host_gate(&host).enter(self.downloads.per_host_delay).await;
+            debug!("Downloading {}", url);
+            self.transport.execute(HttpRequest::get(url)).await?
+        };
+        if !response.is_success() {
+            return Err(CrawlError::Http { url: url.to_string(), status: response.status }.into());
+        }
+
+        let stored = self.sources.store_file(
+            NewSourceFile {
+                dno,
+                year,
+                data_type,
+                file_name: &file_name,
+                source_url: Some(url),
+                content_type: response.header("content-type"),
+                bytes: &response.body,
+            },
+            "crawler",
+        )?;
+        Ok(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpResponse;
+    use async_trait::async_trait;
+
+    /// Serves every URL slowly and records the peak number of requests in flight
+    #[derive(Default)]
+    struct SlowFiles {
+        in_flight: Mutex<(usize, HashMap<String, usize>)>,
+        peak: Mutex<(usize, HashMap<String, usize>)>,
+    }
+
+    impl SlowFiles {
+        fn enter(&self, host: &str, step: isize) {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight.0 = in_flight.0.checked_add_signed(step).unwrap();
+            let on_host = in_flight.1.entry(host.to_string()).or_default();
+            *on_host = on_host.checked_add_signed(step).unwrap();
+            let on_host = *on_host;
+
+            let total = in_flight.0;
+            let mut peak = self.peak.lock().unwrap();
+            peak.0 = peak.0.max(total);
+            let host_peak = peak.1.entry(host.to_string()).or_default();
+            *host_peak = (*host_peak).max(on_host);
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for SlowFiles {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let host = Url::parse(&request.url)?.host_str().unwrap().to_string();
+            self.enter(&host, 1);
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            self.enter(&host, -1);
+            Ok(HttpResponse {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), "application/pdf".to_string())],
+                body: format!("%PDF-1.7 {}", request.url).into_bytes(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_downloads_run_concurrently_within_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = Arc::new(SlowFiles::default());
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let config = DownloadConfig {
+            max_concurrent_downloads: 4,
+            max_downloads_per_host: 2,
+            per_host_delay: Duration::ZERO,
+        };
+        let crawler = ReverseCrawler::new(transport.clone(), sources.clone(), config);
+
+        let urls: Vec<String> = ["netze-bw.example", "bayernwerk.example", "westnetz.example"]
+            .iter()
+            .flat_map(|host| (1..=4).map(move |n| format!("https://{}/files/{}-preisblatt-{}.pdf", host, host, n)))
+            .collect();
+        let results = crawler.download_and_store_content("Netze BW", 2024, "netzentgelte", &urls).await;
+
+        assert_eq!(results.len(), 12);
+        assert!(results.iter().all(|result| result.outcome.is_ok()));
+        assert_eq!(sources.list_files().len(), 12);
+
+        let peak = transport.peak.lock().unwrap();
+        assert!(peak.0 > 2, "downloads should overlap across hosts, peak was {}", peak.0);
+        assert!(peak.0 <= 4, "global limit exceeded: {}", peak.0);
+        assert!(peak.1.values().all(|&on_host| on_host <= 2), "per-host limit exceeded: {:?}", peak.1);
+    }
+
+    /// A DNO site: start page, a tariff overview and the price sheet
+    struct DnoSite;
+
+    #[async_trait]
+    impl HttpTransport for DnoSite {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let (content_type, body) = match request.url.as_str() {
+                "https://netze.example/" => (
+                    "text/html",
+                    r#"<a href="/karriere">Karriere</a> <a href="/netz/entgelte">Netzentgelte &amp; Preise</a>"#.to_string(),
+                ),
+                "https://netze.example/netz/entgelte" => (
+                    "text/html",
+                    r#"<a href="archiv">Archiv</a> <a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>"#.to_string(),
+                ),
+                "https://netze.example/files/preisblatt-2024.pdf" => ("application/pdf", "%PDF-1.7 preisblatt".to_string()),
+                _ => return Ok(HttpResponse { status: 404, headers: Vec::new(), body: Vec::new() }),
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), content_type.to_string())],
+                body: body.into_bytes(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stored_manual_path_reaches_target_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let crawler = ReverseCrawler::new(Arc::new(DnoSite), sources.clone(), DownloadConfig::default());
+
+        // As stored in navigation_paths.steps
+        let steps: Vec<NavigationStep> = serde_json::from_value(serde_json::json!([
+            { "action": "visit", "url": "https://netze.example/" },
+            { "action": "follow_link", "link_text": "netzentgelte" },
+            { "action": "download", "link_text": "Preisblatt 2024" }
+        ]))
+        .unwrap();
+        let run = crawler.follow_path("Netze BW", 2024, "netzentgelte", &steps).await.unwrap();
+
+        assert_eq!(
+            run.visited,
+            [
+                "https://netze.example/",
+                "https://netze.example/netz/entgelte",
+                "https://netze.example/files/preisblatt-2024.pdf"
+            ]
+        );
+        assert_eq!(run.reached, "https://netze.example/files/preisblatt-2024.pdf");
+        let stored = run.document.unwrap();
+        assert_eq!(stored.source_url.as_deref(), Some("https://netze.example/files/preisblatt-2024.pdf"));
+        assert_eq!(sources.list_files().len(), 1);
+
+        let broken: Vec<NavigationStep> = serde_json::from_value(serde_json::json!([
+            { "action": "visit", "url": "https://netze.example/" },
+            { "action": "follow_link", "link_text": "Hochspannung" }
+        ]))
+        .unwrap();
+        let error = crawler.follow_path("Netze BW", 2024, "netzentgelte", &broken).await.unwrap_err();
+        assert!(error.to_string().contains("Step 2 (follow_link)"), "{}", error);
+    }
+
+    /// A sitemap index with a plain and a gzipped child sitemap
+    struct SitemapSite;
+
+    fn gzip(xml: &str) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[async_trait]
+    impl HttpTransport for SitemapSite {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let body = match request.url.as_str() {
+                "https://netze.example/sitemap.xml" => r#"<?xml version="1.0" encoding="UTF-8"?>
+                    <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                      <sitemap><loc>https://netze.example/sitemaps/archiv.xml</loc></sitemap>
+                      <sitemap><loc>/sitemaps/aktuell.xml.gz</loc><lastmod>2024-12-01</lastmod></sitemap>
+                    </sitemapindex>"#
+                    .as_bytes()
+                    .to_vec(),
+                "https://netze.example/sitemaps/archiv.xml" => r#"<?xml version="1.0" encoding="UTF-8"?>
+                    <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                      <url><loc>https://netze.example/netzentgelte/archiv/2022</loc><lastmod>2022-01-03</lastmod></url>
+                      <url><loc>https://netze.example/files/preisblatt-2023.pdf</loc><lastmod>2022-12-15T09:00:00+01:00</lastmod></url>
+                    </urlset>"#
+                    .as_bytes()
+                    .to_vec(),
+                "https://netze.example/sitemaps/aktuell.xml.gz" => gzip(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+                    <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                      <url><loc>https://netze.example/files/preisblatt-2024.pdf</loc><lastmod>2023-12-20</lastmod></url>
+                      <url><loc>https://netze.example/files/preisblatt-2023.pdf</loc></url>
+                      <url><loc>https://netze.example/kontakt</loc></url>
+                    </urlset>"#,
+                ),
+                _ => return Ok(HttpResponse { status: 404, headers: Vec::new(), body: Vec::new() }),
+            };
+            Ok(HttpResponse { status: 200, headers: Vec::new(), body })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_index_children_are_discovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let crawler = ReverseCrawler::new(Arc::new(SitemapSite), sources, DownloadConfig::default());
+
+        let discovered = crawler.discover_from_sitemap("https://netze.example/netz").await.unwrap();
+
+        let urls: Vec<&str> = discovered.iter().map(|found| found.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://netze.example/netzentgelte/archiv/2022",
+                "https://netze.example/files/preisblatt-2023.pdf",
+                "https://netze.example/files/preisblatt-2024.pdf",
+                "https://netze.example/kontakt",
+            ]
+        );
+        assert!(discovered.iter().all(|found| found.method == DiscoveryMethod::SitemapParsing));
+        let years: Vec<Option<i32>> = discovered.iter().map(|found| found.temporal.year).collect();
+        assert_eq!(years, [Some(2022), Some(2023), Some(2024), None]);
+
+        let sheet_2024 = &discovered[2];
+        assert_eq!(sheet_2024.found_in, "https://netze.example/sitemaps/aktuell.xml.gz");
+        assert_eq!(
+            sheet_2024.temporal.last_modified.map(|at| at.date_naive().to_string()).as_deref(),
+            Some("2023-12-20")
+        );
+        assert_eq!(
+            discovered[1].temporal.last_modified.map(|at| at.to_rfc3339()).as_deref(),
+            Some("2022-12-15T08:00:00+00:00")
+        );
+
+        let error = ReverseCrawler::new(Arc::new(DnoSite), Arc::new(SourceManager::new(dir.path())), DownloadConfig::default())
+            .discover_from_sitemap("https://netze.example/")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("sitemap.xml.gz"), "{}", error);
+    }
+
+    /// Counts requests; planning must not make any
+    #[derive(Default)]
+    struct CountingTransport {
+        requests: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for CountingTransport {
+        async fn execute(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            *self.requests.lock().unwrap() += 1;
+            Ok(HttpResponse { status: 404, headers: Vec::new(), body: Vec::new() })
+        }
+    }
+
+    #[test]
+    fn test_plan_lists_candidates_without_fetching() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = Arc::new(CountingTransport::default());
+        let sources = Arc::new(SourceManager::new(dir.path()));
+        let crawler = ReverseCrawler::new(transport.clone(), sources.clone(), DownloadConfig::default())
+            .with_reconstructor(UrlReconstructor::default());
+
+        let endpoints = [
+            "https://netze.example/files/preisblatt-2023.pdf",
+            "https://netze.example/archiv/2023/03/tarif.pdf",
+            // No year, nothing to reconstruct
+            "https://netze.example/files/netzentgelte-aktuell.pdf",
+        ]
+        .map(str::to_string);
+        let candidates = crawler.plan(&endpoints, 2021..=2024);
+
+        // Four years of the yearly sheet and 48 months of the archive, minus the two endpoints
+        assert_eq!(candidates.len(), 3 + 47);
+        let yearly: Vec<&CandidateUrl> =
+            candidates.iter().filter(|candidate| candidate.source_url == endpoints[0]).collect();
+        assert_eq!(yearly.len(), 3);
+        assert!(yearly.iter().all(|candidate| candidate.pattern.template == "/files/preisblatt-{year}.pdf"));
+        assert_eq!(
+            (yearly[0].url.as_str(), yearly[0].year),
+            ("https://netze.example/files/preisblatt-2021.pdf", 2021)
+        );
+        assert!(candidates.iter().any(|candidate| candidate.url == "https://netze.example/archiv/2024/12/tarif.pdf"));
+
+        assert_eq!(*transport.requests.lock().unwrap(), 0);
+        assert!(sources.list_files().is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::http::HttpTransport;
+use crate::reverse::{DownloadConfig, HistoricalDiscovery, ReverseCrawler};
+use anyhow::{bail, Context, Result};
+use dno_core::source_manager::SourceManager;
+use std::fmt::Write;
+use std::sync::Arc;
+use url::Url;
+
+/// `crawler reverse-crawl`: look for one DNO's documents of earlier years
+#[derive(Debug, Clone, clap::Args)]
+pub struct ReverseCrawlArgs {
+    /// DNO slug or name from the known DNO list, or the URL of its website
+    #[arg(long)]
+    pub dno: String,
+    /// Years the site is known to publish documents for (comma-separated);
+    /// their URLs are the patterns for earlier years
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub known_years: Vec<i32>,
+    /// How many links deep to follow from the start page
+    #[arg(long, default_value = "2")]
+    pub max_depth: u32,
+    /// Data type to store the documents under
+    #[arg(long, default_value = "netzentgelte")]
+    pub data_type: String,
+    /// List what would be downloaded without downloading anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Return structured JSON output
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl ReverseCrawlArgs {
+    /// Run the reverse crawl over `transport`, storing into `sources`, and
+    /// return the summary to print
+    pub async fn run(
+        &self,
+        transport: Arc<dyn HttpTransport>,
+        sources: Arc<SourceManager>,
+        downloads: DownloadConfig,
+    ) -> Result<String> {
+        let (dno, site_url) = resolve_site(&self.dno)?;
+        let crawler = ReverseCrawler::new(transport, sources, downloads);
+        let discovery = crawler
+            .discover_historical_data(&dno, &site_url, &self.data_type, &self.known_years, self.max_depth, self.dry_run)
+            .await?;
+        if self.json {
+            return Ok(serde_json::to_string_pretty(&discovery)?);
+        }
+        Ok(summary(&discovery))
+    }
+}
+
+/// DNO name and website for `--dno`: a URL is used as it is, otherwise the
+/// known DNO list is searched by slug or name
+pub fn resolve_site(dno: &str) -> Result<(String, String)> {
+    if let Ok(url) = Url::parse(dno) {
+        if matches!(url.scheme(), "http" | "https") {
+            let host = url.host_str().context("Site URL has no host")?.to_string();
+            return Ok((host, url.to_string()));
+        }
+    }
+    let known = dno_core::seed::known_dnos()?;
+    let Some(found) = known
+        .into_iter()
+        .find(|known| known.slug == dno || known.name.eq_ignore_ascii_case(dno))
+    else {
+        bail!("Unknown DNO '{}'; pass its slug, name or website URL", dno);
+    };
+    match found.website {
+        Some(website) => Ok((found.name, website)),
+        None => bail!("No website on record for {}; pass its URL as --dno", found.name),
+    }
+}
+
+fn summary(discovery: &HistoricalDiscovery) -> String {
+    let mut out = String::new();
+    let years = match (discovery.searched_years.first(), discovery.searched_years.last()) {
+        (Some(first), Some(last)) => format!("{}-{}", first, last),
+        _ => "no years".to_string(),
+    };
+    let _ = writeln!(out, "🔁 Reverse crawl of {} ({}) for {}", discovery.dno, discovery.site_url, years);
+    let _ = writeln!(out, "📄 {} documents found on the site", discovery.discovered.len());
+    for found in &discovery.discovered {
+        let year = found.temporal.year.map_or("-".to_string(), |year| year.to_string());
+        let _ = writeln!(out, "  • {} ({})", found.url, year);
+    }
+    let _ = writeln!(out, "🧩 {} URL patterns", discovery.patterns.len());
+    for pattern in &discovery.patterns {
+        let _ = writeln!(out, "  • {}", pattern.template);
+    }
+    let _ = writeln!(out, "🔗 {} candidate URLs", discovery.candidates.len());
+    if discovery.dry_run {
+        for candidate in &discovery.candidates {
+            let _ = writeln!(out, "  • {} ({})", candidate.url, candidate.year);
+        }
+        let _ = writeln!(out, "🧪 Dry run: nothing was downloaded");
+        return out;
+    }
+    let _ = writeln!(out, "💾 Stored {} files, {} URLs missing or failed", discovery.stored.len(), discovery.failed.len());
+    for file in &discovery.stored {
+        let _ = writeln!(out, "  • {} {}", file.year, file.file_path.display());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{build_client, HttpClientConfig, LiveTransport};
+    use clap::Parser;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(subcommand)]
+        command: Command,
+    }
+
+    #[derive(clap::Subcommand)]
+    enum Command {
+        ReverseCrawl(ReverseCrawlArgs),
+    }
+
+    /// A DNO site linking the 2024 price sheet from its tariff page; the
+    /// 2022 and 2023 sheets exist under the same naming scheme but are not linked
+    async fn dno_site(paths: Arc<Mutex<Vec<String>>>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let read = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..read]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (content_type, body) = match path.as_str() {
+                    "/" => ("text/html", r#"<a href="/netz/entgelte">Netzentgelte</a>"#.to_string()),
+                    "/netz/entgelte" => ("text/html", r#"<a href="/files/preisblatt-2024.pdf">Preisblatt 2024</a>"#.to_string()),
+                    "/files/preisblatt-2022.pdf" | "/files/preisblatt-2023.pdf" | "/files/preisblatt-2024.pdf" => {
+                        ("application/pdf", format!("%PDF-1.7 {}", path))
+                    }
+                    _ => ("", String::new()),
+                };
+                let response = if content_type.is_empty() {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        content_type,
+                        body.len(),
+                        body
+                    )
+                };
+                paths.lock().unwrap().push(path);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        port
+    }
+
+    async fn reverse_crawl(args: &[&str], sources: Arc<SourceManager>) -> String {
+        let Command::ReverseCrawl(args) = Cli::try_parse_from([&["crawler", "reverse-crawl"][..], args].concat()).unwrap().command;
+        let config = HttpClientConfig::default();
+        let transport = LiveTransport::new(build_client(&config).unwrap()).trust_host("127.0.0.1");
+        args.run(Arc::new(transport), sources, DownloadConfig::default()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reverse_crawl_subcommand_finds_earlier_price_sheets() {
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let site = format!("http://127.0.0.1:{}/", dno_site(paths.clone()).await);
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Arc::new(SourceManager::new(dir.path()));
+
+        let output = reverse_crawl(&["--dno", &site, "--known-years", "2024", "--dry-run", "--json"], sources.clone()).await;
+        let planned: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(planned["patterns"][0]["template"], "/files/preisblatt-{year}.pdf");
+        assert_eq!(planned["candidates"].as_array().unwrap().len(), 10);
+        assert_eq!(planned["candidates"][0]["url"], format!("{}files/preisblatt-2014.pdf", site));
+        assert!(planned["stored"].as_array().unwrap().is_empty());
+        // The dry run only loaded pages
+        assert!(!paths.lock().unwrap().iter().any(|path| path.starts_with("/files/")));
+        assert!(sources.list_files().is_empty());
+
+        let output = reverse_crawl(&["--dno", &site, "--known-years", "2024", "--max-depth", "1"], sources.clone()).await;
+        assert!(output.contains("🧩 1 URL patterns\n  • /files/preisblatt-{year}.pdf"), "{}", output);
+        assert!(output.contains("💾 Stored 2 files, 8 URLs missing or failed"), "{}", output);
+        let mut years: Vec<i32> = sources.list_files().iter().map(|file| file.year).collect();
+        years.sort();
+        assert_eq!(years, [2022, 2023]);
+    }
+
+    #[test]
+    fn test_dno_is_resolved_from_url_or_known_list() {
+        let (name, url) = resolve_site("https://www.netze-bw.de/").unwrap();
+        assert_eq!((name.as_str(), url.as_str()), ("www.netze-bw.de", "https://www.netze-bw.de/"));
+        assert!(resolve_site("Stadtwerke Atlantis").unwrap_err().to_string().contains("Unknown DNO"));
+    }
+}
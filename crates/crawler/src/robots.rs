@@ -0,0 +1,214 @@
+use crate::http::{HttpRequest, HttpTransport};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+use url::Url;
+
+/// Longest `Crawl-delay` honoured; longer values would stall a crawl
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    /// Pattern length, the more specific rule wins
+    specificity: usize,
+    pattern: Regex,
+}
+
+/// The `robots.txt` rules that apply to our user agent
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    pub crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Parse `robots.txt`, keeping the groups for `user_agent` (matched by its
+    /// product token, e.g. `DNO-Data-Gatherer`) or, if there are none, the
+    /// `*` groups
+    pub fn parse(robots_txt: &str, user_agent: &str) -> Self {
+        let token = user_agent.split('/').next().unwrap_or_default().trim().to_lowercase();
+        let mut specific = RobotsRules::default();
+        let mut wildcard = RobotsRules::default();
+        let mut has_specific = false;
+
+        // Agents of the group being read, and whether its rules started
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_lowercase());
+                continue;
+            }
+            in_rules = true;
+            let is_specific = agents.iter().any(|agent| agent != "*" && !agent.is_empty() && token.starts_with(agent.as_str()));
+            has_specific |= is_specific;
+            let target = if is_specific {
+                &mut specific
+            } else if agents.iter().any(|agent| agent == "*") {
+                &mut wildcard
+            } else {
+                continue;
+            };
+
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => {
+                    if let Some(pattern) = compile(value) {
+                        target.rules.push(Rule { allow: key == "allow", specificity: value.len(), pattern });
+                    }
+                }
+                "crawl-delay" => {
+                    target.crawl_delay = value.parse::<f64>().ok().filter(|s| s.is_finite() && *s >= 0.0).map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+
+        if has_specific {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    /// Whether `path` (with its query) may be fetched: the longest matching
+    /// rule decides, `Allow` wins a tie, and no match means allowed
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.pattern.is_match(path))
+            .max_by_key(|rule| (rule.specificity, rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// A robots.txt path pattern: `*` matches anything, a trailing `$` anchors the end
+fn compile(pattern: &str) -> Option<Regex> {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let body = pattern.split('*').map(regex::escape).collect::<Vec<_>>().join(".*");
+    Regex::new(&format!("^{}{}", body, if anchored { "$" } else { "" })).ok()
+}
+
+struct HostRobots {
+    rules: OnceCell<RobotsRules>,
+    next_request: tokio::sync::Mutex<Instant>,
+}
+
+/// `robots.txt` rules per host, fetched once per host on first use. A
+/// missing or unreadable `robots.txt` allows everything.
+pub struct RobotsCache {
+    transport: Arc<dyn HttpTransport>,
+    user_agent: String,
+    hosts: Mutex<HashMap<String, Arc<HostRobots>>>,
+}
+
+impl RobotsCache {
+    pub fn new(transport: Arc<dyn HttpTransport>, user_agent: impl Into<String>) -> Self {
+        Self { transport, user_agent: user_agent.into(), hosts: Mutex::new(HashMap::new()) }
+    }
+
+    fn host(&self, origin: &str) -> Arc<HostRobots> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .entry(origin.to_string())
+            .or_insert_with(|| {
+                Arc::new(HostRobots { rules: OnceCell::new(), next_request: tokio::sync::Mutex::new(Instant::now()) })
+            })
+            .clone()
+    }
+
+    /// The entry for `url`'s host, with its rules fetched
+    async fn loaded(&self, url: &Url) -> Arc<HostRobots> {
+        let origin = url.origin().ascii_serialization();
+        let host = self.host(&origin);
+        host.rules
+            .get_or_init(|| async {
+                let robots_url = format!("{}/robots.txt", origin);
+                match self.transport.execute(HttpRequest::get(&robots_url)).await {
+                    Ok(response) if response.is_success() => RobotsRules::parse(&response.text(), &self.user_agent),
+                    Ok(response) => {
+                        debug!("No robots.txt at {} ({}), allowing everything", robots_url, response.status);
+                        RobotsRules::default()
+                    }
+                    Err(e) => {
+                        warn!("Could not fetch {}, allowing everything: {}", robots_url, e);
+                        RobotsRules::default()
+                    }
+                }
+            })
+            .await;
+        host
+    }
+
+    /// Whether our user agent may fetch `url`
+    pub async fn is_allowed(&self, url: &str) -> bool {
+        let Ok(parsed) = Url::parse(url) else { return true };
+        let host = self.loaded(&parsed).await;
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        let allowed = host.rules.get().is_none_or(|rules| rules.is_allowed(&path));
+        if !allowed {
+            debug!("robots.txt disallows {}", url);
+        }
+        allowed
+    }
+
+    /// Wait until the host's `Crawl-delay` has passed since the last request to it
+    pub async fn wait_turn(&self, url: &str) {
+        let Ok(parsed) = Url::parse(url) else { return };
+        let host = self.loaded(&parsed).await;
+        let Some(delay) = host.rules.get().and_then(|rules| rules.crawl_delay) else { return };
+        let mut next_request = host.next_request.lock().await;
+        tokio::time::sleep_until(*next_request).await;
+        *next_request = Instant::now() + delay.min(MAX_CRAWL_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_for_our_agent_override_the_wildcard_group() {
+        let robots = "
+            User-agent: *
+            Disallow: /private
+            Crawl-delay: 2
+
+            User-agent: Googlebot
+            User-agent: DNO-Data-Gatherer
+            Disallow: /intern/
+            Allow: /intern/netzentgelte*.pdf$
+            Crawl-delay: 0.5
+        ";
+
+        let ours = RobotsRules::parse(robots, "DNO-Data-Gatherer/0.0.1");
+        assert!(!ours.is_allowed("/intern/berichte"));
+        assert!(ours.is_allowed("/intern/netzentgelte-2024.pdf"));
+        assert!(!ours.is_allowed("/intern/netzentgelte-2024.pdf?download=1"));
+        assert!(ours.is_allowed("/private/preisblatt.pdf"));
+        assert_eq!(ours.crawl_delay, Some(Duration::from_millis(500)));
+
+        let others = RobotsRules::parse(robots, "OtherBot/1.0");
+        assert!(!others.is_allowed("/private/preisblatt.pdf"));
+        assert_eq!(others.crawl_delay, Some(Duration::from_secs(2)));
+    }
+}
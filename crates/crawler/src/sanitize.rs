@@ -0,0 +1,278 @@
+use crate::extraction::Document;
+use dno_core::error::AppError;
+use dno_core::source_manager::{FileMetadata, NewSourceFile, SourceManager};
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Elements dropped together with their content
+const DROPPED: [&str; 10] = [
+    "script", "style", "noscript", "template", "iframe", "object", "embed", "svg", "link", "meta",
+];
+/// Elements without a closing tag
+const VOID: [&str; 9] = ["br", "hr", "img", "input", "area", "base", "col", "wbr", "source"];
+/// Elements that start a new line in the output, so diffs show changed rows
+const BLOCKS: [&str; 22] = [
+    "html", "head", "title", "body", "div", "section", "article", "header", "footer", "nav", "main", "p",
+    "ul", "ol", "li", "table", "thead", "tbody", "tr", "h1", "h2", "h3",
+];
+
+/// Elements whose text is trimmed at the edges; whitespace only matters inside inline elements
+const TRIMMED: [&str; 3] = ["td", "th", "caption"];
+
+/// How the page content of an extraction is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawDataPolicy {
+    /// Store sanitized HTML instead of the page as fetched
+    pub sanitize: bool,
+    /// Also keep the page as fetched next to the sanitized version
+    pub keep_full_raw: bool,
+}
+
+impl Default for RawDataPolicy {
+    fn default() -> Self {
+        Self { sanitize: true, keep_full_raw: false }
+    }
+}
+
+impl RawDataPolicy {
+    /// Read `CRAWLER_SANITIZE_RAW_DATA` (default on) and `CRAWLER_KEEP_FULL_RAW` (default off)
+    pub fn from_env() -> Self {
+        let flag = |name: &str, default: bool| {
+            std::env::var(name)
+                .map(|v| match v.trim().to_lowercase().as_str() {
+                    "false" | "0" | "no" | "off" => false,
+                    "true" | "1" | "yes" | "on" => true,
+                    _ => default,
+                })
+                .unwrap_or(default)
+        };
+        Self {
+            sanitize: flag("CRAWLER_SANITIZE_RAW_DATA", true),
+            keep_full_raw: flag("CRAWLER_KEEP_FULL_RAW", false),
+        }
+    }
+}
+
+/// Page content stored with an extraction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedContent {
+    pub url: String,
+    pub content_type: Option<String>,
+    /// Sanitized HTML, or the page as fetched when sanitizing is off
+    pub raw_data: String,
+    pub sanitized: bool,
+    /// The page as fetched, with `keep_full_raw`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_raw: Option<String>,
+    /// Size of the page as fetched
+    pub original_bytes: usize,
+}
+
+impl ExtractedContent {
+    /// Content of an HTML document; `None` for PDFs and other binary documents
+    pub fn from_document(document: &Document, policy: &RawDataPolicy) -> Option<Self> {
+        if !document.content_type.as_deref().is_some_and(|t| t.contains("html")) {
+            return None;
+        }
+        let html = String::from_utf8_lossy(&document.bytes).into_owned();
+        let raw_data = if policy.sanitize { sanitize_html(&html) } else { html.clone() };
+        Some(Self {
+            url: document.url.clone(),
+            content_type: document.content_type.clone(),
+            raw_data,
+            sanitized: policy.sanitize,
+            full_raw: (policy.sanitize && policy.keep_full_raw).then_some(html),
+            original_bytes: document.bytes.len(),
+        })
+    }
+
+    /// Hex SHA-256 of `raw_data`, the content that gets stored
+    pub fn sha256(&self) -> String {
+        format!("{:x}", Sha256::digest(self.raw_data.as_bytes()))
+    }
+
+    /// Store `raw_data` as an `.html` file named after the page. The returned
+    /// metadata carries the content hash and the path the file was written to.
+    pub fn store(
+        &self,
+        sources: &SourceManager,
+        dno: &str,
+        year: i32,
+        data_type: &str,
+        actor: &str,
+    ) -> Result<FileMetadata, AppError> {
+        sources.store_file(
+            NewSourceFile {
+                dno,
+                year,
+                data_type,
+                file_name: &self.file_name(),
+                source_url: Some(&self.url),
+                content_type: self.content_type.as_deref(),
+                bytes: self.raw_data.as_bytes(),
+            },
+            actor,
+        )
+    }
+
+    /// Last path segment of the page URL, `index` for directories, with an `.html` extension
+    fn file_name(&self) -> String {
+        let segment = Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+            .filter(|segment| !segment.is_empty())
+            .unwrap_or_else(|| "index".to_string());
+        let lower = segment.to_lowercase();
+        if lower.ends_with(".html") || lower.ends_with(".htm") {
+            segment
+        } else {
+            format!("{}.html", segment)
+        }
+    }
+}
+
+/// Normalize a page for storage: scripts, styles, comments, event handlers
+/// and `javascript:` URLs are removed, attributes sorted and whitespace
+/// collapsed. Equivalent pages give identical output.
+pub fn sanitize_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut out = String::with_capacity(html.len() / 2);
+    write_element(document.root_element(), &mut out);
+    out.trim().to_string()
+}
+
+fn write_element(element: ElementRef<'_>, out: &mut String) {
+    let name = element.value().name();
+    if DROPPED.contains(&name) {
+        return;
+    }
+
+    let mut attributes: Vec<(&str, &str)> = element
+        .value()
+        .attrs()
+        .filter(|(attribute, value)| {
+            let attribute = attribute.to_lowercase();
+            !attribute.starts_with("on")
+                && attribute != "style"
+                && !value.trim_start().to_lowercase().starts_with("javascript:")
+        })
+        .collect();
+    attributes.sort_unstable();
+
+    if BLOCKS.contains(&name) && !out.is_empty() {
+        out.push('\n');
+    }
+    out.push('<');
+    out.push_str(name);
+    for (attribute, value) in attributes {
+        out.push_str(&format!(" {}=\"{}\"", attribute, escape(value, true)));
+    }
+    out.push('>');
+    if VOID.contains(&name) {
+        return;
+    }
+
+    let trim = BLOCKS.contains(&name) || TRIMMED.contains(&name);
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape(&collapse(text, trim), false)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+/// Whitespace runs as single spaces; dropped at the edges when `trim`
+fn collapse(text: &str, trim: bool) -> String {
+    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if trim || (words.is_empty() && text.is_empty()) {
+        return words;
+    }
+    if words.is_empty() {
+        return " ".to_string();
+    }
+    let lead = if text.starts_with(char::is_whitespace) { " " } else { "" };
+    let trail = if text.ends_with(char::is_whitespace) { " " } else { "" };
+    format!("{}{}{}", lead, words, trail)
+}
+
+fn escape(text: &str, attribute: bool) -> String {
+    let text = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    if attribute {
+        text.replace('"', "&quot;")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_are_removed_and_output_is_deterministic() {
+        let page = r#"<!DOCTYPE html>
+<html><head><title>Netzentgelte   2024</title>
+<script>window.tracker = "alert(1)";</script><style>td { color: red }</style></head>
+<body onload="init()">
+  <!-- generated 2024-01-02 12:00 -->
+  <table class="preise" id="ne">
+    <tr><td>MS</td>   <td>58,21</td></tr>
+  </table>
+  <a href="javascript:void(0)" onclick="track()">Drucken</a>
+</body></html>"#;
+        let equivalent = r#"<html><head><title>Netzentgelte 2024</title>
+<script src="/app.js"></script></head><body>
+<!-- generated 2024-05-06 08:30 -->
+<table id="ne" class="preise"><tr><td>MS</td><td>58,21</td></tr></table>
+<a>Drucken</a><noscript>Bitte JavaScript aktivieren</noscript>
+</body></html>"#;
+
+        let sanitized = sanitize_html(page);
+        for removed in ["<script", "tracker", "<style", "color: red", "onload", "onclick", "javascript:", "generated"] {
+            assert!(!sanitized.contains(removed), "{} left in {}", removed, sanitized);
+        }
+        assert!(sanitized.contains(r#"<table class="preise" id="ne">"#));
+        assert!(sanitized.contains("<td>58,21</td>"));
+        assert!(sanitized.contains("<title>Netzentgelte 2024</title>"));
+
+        assert_eq!(sanitized, sanitize_html(equivalent));
+        assert_eq!(sanitized, sanitize_html(&sanitized));
+        assert!(sanitized.len() < page.len());
+    }
+
+    #[test]
+    fn test_stored_content_is_hashed_and_written_where_reported() {
+        let page = |body: &str| Document {
+            url: "https://www.netze-bw.de/netzentgelte".to_string(),
+            content_type: Some("text/html".to_string()),
+            bytes: format!("<html><body><table><tr><td>{}</td></tr></table></body></html>", body).into_bytes(),
+            ..Default::default()
+        };
+        let policy = RawDataPolicy::default();
+        let ms = ExtractedContent::from_document(&page("58,21"), &policy).unwrap();
+        let ns = ExtractedContent::from_document(&page("71,04"), &policy).unwrap();
+        let ms_again = ExtractedContent::from_document(&page("58,21"), &policy).unwrap();
+
+        assert_ne!(ms.sha256(), ns.sha256());
+        assert_eq!(ms.sha256(), ms_again.sha256());
+        assert_eq!(ms.sha256().len(), 64);
+
+        let dir = tempfile::tempdir().unwrap();
+        let sources = SourceManager::new(dir.path());
+        let stored = ms.store(&sources, "Netze BW", 2024, "netzentgelte", "crawler").unwrap();
+        assert_eq!(stored.sha256, ms.sha256());
+        assert_eq!(stored.file_name, "netzentgelte.html");
+        assert_eq!(std::fs::read_to_string(dir.path().join(&stored.file_path)).unwrap(), ms.raw_data);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::http::{HttpRequest, HttpTransport};
+use anyhow::{bail, Result};
+use dno_core::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use dno_core::Dno;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use url::Url;
+
+/// Added for results on the DNO's own website or a subdomain of it
+const OFFICIAL_DOMAIN_BOOST: f64 = 10.0;
+/// Added for results whose host contains the DNO's slug, e.g. `netze-bw.de`
+const SLUG_HOST_BOOST: f64 = 5.0;
+/// Subtracted for comparison portals, encyclopedias, news and social sites
+const AGGREGATOR_PENALTY: f64 = 5.0;
+
+/// Domains that write about many DNOs instead of publishing their own prices
+const AGGREGATOR_DOMAINS: [&str; 18] = [
+    "wikipedia.org",
+    "verivox.de",
+    "check24.de",
+    "stromauskunft.de",
+    "finanztip.de",
+    "strom-report.com",
+    "energate-messenger.de",
+    "zfk.de",
+    "presseportal.de",
+    "spiegel.de",
+    "faz.net",
+    "sueddeutsche.de",
+    "handelsblatt.com",
+    "heise.de",
+    "northdata.de",
+    "linkedin.com",
+    "facebook.com",
+    "youtube.com",
+];
+/// Host labels that mark a news site
+const NEWS_LABELS: [&str; 3] = ["news", "zeitung", "nachrichten"];
+
+/// One result of a SearXNG search, ranked for DNO discovery
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchSource {
+    pub url: String,
+    pub title: String,
+    /// The snippet SearXNG shows under the title
+    pub content: Option<String>,
+    /// Engine that returned the result
+    pub engine: Option<String>,
+    /// Host without a leading `www.`
+    pub host: String,
+    /// Higher ranks first: SearXNG's order, boosted for the DNO's own domains
+    /// and penalized for aggregators
+    pub score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResult {
+    url: String,
+    #[serde(default)]
+    title: String,
+    content: Option<String>,
+    engine: Option<String>,
+}
+
+/// Searches through SearXNG's JSON API and ranks what it returns, so a DNO's
+/// official domains come first
+pub struct SearchService {
+    transport: Arc<dyn HttpTransport>,
+    base_url: String,
+    /// Fails searches fast while SearXNG is down
+    breaker: CircuitBreaker,
+}
+
+impl SearchService {
+    pub fn new(transport: Arc<dyn HttpTransport>, base_url: impl Into<String>) -> Self {
+        Self {
+            transport,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            breaker: CircuitBreaker::new("searxng", CircuitBreakerConfig::from_env("SEARXNG")),
+        }
+    }
+
+    /// Use this breaker instead of one configured from `SEARXNG_BREAKER_*`
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// The instance at `SEARXNG_URL`
+    pub fn from_env(transport: Arc<dyn HttpTransport>) -> Self {
+        let base_url = std::env::var("SEARXNG_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
+        Self::new(transport, base_url)
+    }
+
+    /// Search for `query` and rank the results for `dno`, one per host, best
+    /// first. Fails with `CircuitOpen` while the circuit breaker is open.
+    pub async fn search(&self, query: &str, dno: Option<&Dno>) -> Result<Vec<SearchSource>> {
+        let request =
+            HttpRequest::get(format!("{}/search", self.base_url)).query(&[("q", query), ("format", "json")])?;
+        let response = self
+            .breaker
+            .call(async {
+                let response = self.transport.execute(request).await?;
+                if !response.is_success() {
+                    bail!("SearXNG search for '{}' failed: HTTP {}", query, response.status);
+                }
+                Ok(response)
+            })
+            .await??;
+        let found: SearxngResponse = response.json()?;
+        Ok(rank(found.results, dno))
+    }
+}
+
+/// Score results by their position, the DNO's domains and known aggregators,
+/// keep the best result per host and sort by score. Results without a
+/// parsable host are dropped.
+fn rank(results: Vec<SearxngResult>, dno: Option<&Dno>) -> Vec<SearchSource> {
+    let official = dno.and_then(website_host);
+    let slug_forms: Vec<String> = dno
+        .map(|dno| vec![dno.slug.to_lowercase(), dno.slug.to_lowercase().replace('-', "")])
+        .unwrap_or_default();
+
+    let mut ranked: Vec<SearchSource> = results
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, result)| {
+            let host = Url::parse(&result.url).ok()?.host_str().map(bare_host)?;
+            let mut score = 1.0 / (1.0 + position as f64);
+            if official.as_deref().is_some_and(|official| on_domain(&host, official)) {
+                score += OFFICIAL_DOMAIN_BOOST;
+            } else if slug_forms.iter().any(|slug| !slug.is_empty() && host.contains(slug.as_str())) {
+                score += SLUG_HOST_BOOST;
+            }
+            if is_aggregator(&host) {
+                score -= AGGREGATOR_PENALTY;
+            }
+            Some(SearchSource {
+                url: result.url,
+                title: result.title,
+                content: result.content,
+                engine: result.engine,
+                host,
+                score,
+            })
+        })
+        .collect();
+
+    // Stable, so equal scores keep SearXNG's order
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut hosts = HashSet::new();
+    ranked.retain(|source| hosts.insert(source.host.clone()));
+    ranked
+}
+
+fn website_host(dno: &Dno) -> Option<String> {
+    let website = dno.website.as_deref()?.trim();
+    let url = if website.contains("://") { Url::parse(website) } else { Url::parse(&format!("https://{}", website)) };
+    url.ok()?.host_str().map(bare_host)
+}
+
+fn bare_host(host: &str) -> String {
+    let host = host.to_lowercase();
+    host.strip_prefix("www.").map(str::to_string).unwrap_or(host)
+}
+
+/// `host` is `domain` or one of its subdomains
+fn on_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+fn is_aggregator(host: &str) -> bool {
+    AGGREGATOR_DOMAINS.iter().any(|domain| on_domain(host, domain))
+        || host.split(['.', '-']).any(|label| NEWS_LABELS.contains(&label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpResponse;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// SearXNG answering every search with the same results
+    struct MockSearxng {
+        requested: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for MockSearxng {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.requested.lock().unwrap().push(request.url);
+            let body = serde_json::json!({
+                "query": "Netze BW netzentgelte 2024",
+                "results": [
+                    { "url": "https://www.verivox.de/strom/netzentgelte/", "title": "Netzentgelte im Vergleich", "engine": "google" },
+                    { "url": "https://de.wikipedia.org/wiki/Netze_BW", "title": "Netze BW – Wikipedia", "engine": "bing" },
+                    { "url": "https://www.energie-news.de/netze-bw-erhoeht-entgelte", "title": "Netze BW erhöht Entgelte", "engine": "bing" },
+                    { "url": "https://www.netze-bw.de/netzentgelte", "title": "Netzentgelte | Netze BW", "content": "Preisblätter 2024", "engine": "google" },
+                    { "url": "https://www.netze-bw.de/unsernetz/netzentgelte/2024", "title": "Preisblatt 2024", "engine": "duckduckgo" },
+                    { "url": "https://netzebw-partner.example/downloads", "title": "Downloads", "engine": "google" },
+                    { "url": "not a url", "title": "broken" }
+                ]
+            });
+            Ok(HttpResponse { status: 200, headers: vec![], body: body.to_string().into_bytes() })
+        }
+    }
+
+    fn netze_bw(website: Option<&str>) -> Dno {
+        let now = chrono::Utc::now();
+        Dno {
+            id: uuid::Uuid::new_v4(),
+            slug: "netze-bw".to_string(),
+            name: "Netze BW".to_string(),
+            official_name: Some("Netze BW GmbH".to_string()),
+            description: None,
+            region: Some("Baden-Württemberg".to_string()),
+            website: website.map(str::to_string),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_official_domain_sorts_to_the_top() {
+        let transport = Arc::new(MockSearxng { requested: Mutex::new(Vec::new()) });
+        let service = SearchService::new(transport.clone(), "http://searxng:8080/");
+
+        let dno = netze_bw(Some("www.netze-bw.de"));
+        let ranked = service.search("Netze BW netzentgelte 2024", Some(&dno)).await.unwrap();
+
+        assert_eq!(
+            transport.requested.lock().unwrap()[0],
+            "http://searxng:8080/search?q=Netze+BW+netzentgelte+2024&format=json"
+        );
+        let hosts: Vec<&str> = ranked.iter().map(|source| source.host.as_str()).collect();
+        assert_eq!(
+            hosts,
+            ["netze-bw.de", "netzebw-partner.example", "verivox.de", "de.wikipedia.org", "energie-news.de"]
+        );
+        // One result per host: the better-placed netze-bw.de page
+        assert_eq!(ranked[0].url, "https://www.netze-bw.de/netzentgelte");
+        assert_eq!(ranked[0].content.as_deref(), Some("Preisblätter 2024"));
+        assert!(ranked[0].score > OFFICIAL_DOMAIN_BOOST);
+        assert!(ranked[2..].iter().all(|source| source.score < 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_slug_in_host_is_boosted_without_a_known_website() {
+        let transport = Arc::new(MockSearxng { requested: Mutex::new(Vec::new()) });
+        let service = SearchService::new(transport, "http://searxng:8080");
+
+        let ranked = service.search("Netze BW", Some(&netze_bw(None))).await.unwrap();
+        assert_eq!(ranked[0].host, "netze-bw.de");
+        assert_eq!(ranked[1].host, "netzebw-partner.example");
+
+        // Without a DNO only the penalties and SearXNG's order count
+        let unranked = service.search("Netze BW", None).await.unwrap();
+        assert_eq!(unranked[0].host, "netze-bw.de");
+        assert_eq!(unranked.last().unwrap().host, "energie-news.de");
+    }
+
+    /// SearXNG answering 502 until it is told to recover
+    #[derive(Default)]
+    struct FlakySearxng {
+        healthy: std::sync::atomic::AtomicBool,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HttpTransport for FlakySearxng {
+        async fn execute(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            use std::sync::atomic::Ordering;
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let (status, body) = match self.healthy.load(Ordering::SeqCst) {
+                true => (200, r#"{"results": []}"#),
+                false => (502, "Bad Gateway"),
+            };
+            Ok(HttpResponse { status, headers: vec![], body: body.as_bytes().to_vec() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_searxng_trips_the_breaker() {
+        use dno_core::circuit_breaker::{CircuitOpen, CircuitState};
+        use std::sync::atomic::Ordering;
+        use std::time::Duration;
+
+        let transport = Arc::new(FlakySearxng::default());
+        let breaker = CircuitBreaker::new(
+            "searxng",
+            CircuitBreakerConfig { failure_threshold: 2, cooldown: Duration::from_millis(30) },
+        );
+        let service = SearchService::new(transport.clone(), "http://searxng:8080").with_breaker(breaker.clone());
+
+        for _ in 0..2 {
+            let error = service.search("Netze BW", None).await.unwrap_err();
+            assert!(error.to_string().contains("HTTP 502"), "{}", error);
+        }
+        let error = service.search("Netze BW", None).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<CircuitOpen>().map(|open| open.service), Some("searxng"));
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        transport.healthy.store(true, Ordering::SeqCst);
+        assert!(service.search("Netze BW", None).await.unwrap().is_empty());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+/// gzip magic bytes, for `sitemap.xml.gz` and servers that send it unlabelled
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Largest decompressed sitemap; the protocol allows 50 MB
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+
+/// How a candidate URL was found, outside of page navigation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryMethod {
+    /// Listed in the site's `sitemap.xml` or one of its child sitemaps
+    SitemapParsing,
+    /// Linked from one of the site's pages
+    LinkFollowing,
+}
+
+/// What is known about the period a discovered URL covers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemporalData {
+    /// Year named in the URL, e.g. `preisblatt-2024.pdf`
+    pub year: Option<i32>,
+    /// `<lastmod>` of the sitemap entry; when the page changed, not the year it covers
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// A URL found by a discovery method, before anything was fetched from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveredUrl {
+    pub url: String,
+    pub method: DiscoveryMethod,
+    pub temporal: TemporalData,
+    /// The sitemap or page that listed the URL
+    pub found_in: String,
+}
+
+/// A parsed sitemap: either a `<sitemapindex>` of child sitemaps or a
+/// `<urlset>` of pages, each with its `<lastmod>`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sitemap {
+    Index(Vec<SitemapEntry>),
+    UrlSet(Vec<SitemapEntry>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Parse a sitemap as served, gunzipping it first if needed
+pub fn parse_sitemap(url: &str, body: &[u8]) -> Result<Sitemap> {
+    let xml = if body.starts_with(&GZIP_MAGIC) {
+        let mut xml = String::new();
+        GzDecoder::new(body)
+            .take(MAX_SITEMAP_BYTES)
+            .read_to_string(&mut xml)
+            .with_context(|| format!("Failed to decompress {}", url))?;
+        xml
+    } else {
+        String::from_utf8_lossy(body).into_owned()
+    };
+
+    let document = roxmltree::Document::parse(&xml).with_context(|| format!("Invalid sitemap XML in {}", url))?;
+    let root = document.root_element();
+    let (entry_tag, index) = match root.tag_name().name() {
+        "sitemapindex" => ("sitemap", true),
+        "urlset" => ("url", false),
+        other => anyhow::bail!("{} is not a sitemap (root element <{}>)", url, other),
+    };
+    let entries = root
+        .children()
+        .filter(|node| node.has_tag_name(entry_tag))
+        .filter_map(|entry| {
+            let text = |tag: &str| {
+                entry
+                    .children()
+                    .find(|node| node.has_tag_name(tag))
+                    .and_then(|node| node.text())
+                    .map(str::trim)
+                    .filter(|text| !text.is_empty())
+            };
+            Some(SitemapEntry { loc: text("loc")?.to_string(), last_modified: text("lastmod").and_then(parse_lastmod) })
+        })
+        .collect();
+    Ok(if index { Sitemap::Index(entries) } else { Sitemap::UrlSet(entries) })
+}
+
+/// A W3C datetime as used by `<lastmod>`: a full timestamp, or a date of
+/// which the missing parts default to the start of the period
+fn parse_lastmod(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let date = match value.len() {
+        4 => NaiveDate::from_ymd_opt(value.parse().ok()?, 1, 1)?,
+        7 => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").ok()?,
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?,
+    };
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lastmod_accepts_w3c_datetime_precisions() {
+        let start_of = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        assert_eq!(parse_lastmod("2024-03-15"), Some(start_of(2024, 3, 15)));
+        assert_eq!(parse_lastmod("2024-03"), Some(start_of(2024, 3, 1)));
+        assert_eq!(parse_lastmod("2024"), Some(start_of(2024, 1, 1)));
+        assert_eq!(
+            parse_lastmod("2024-03-15T10:30:00+01:00"),
+            Some(start_of(2024, 3, 15) + chrono::Duration::minutes(9 * 60 + 30))
+        );
+        assert_eq!(parse_lastmod("gestern"), None);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::content::ContentType;
+use crate::extraction::{Document, Extraction, Extractor};
+use crate::pipeline::ExtractionMethod;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use chrono::Timelike;
+use dno_core::models::FieldProvenance;
+use serde_json::{json, Map, Value};
+use std::io::Cursor;
+
+/// Cells of a workbook carry their type, unlike text scraped from HTML or PDFs
+const SPREADSHEET_CONFIDENCE: f64 = 0.9;
+
+/// Reads every worksheet of an XLSX/XLS workbook into rows keyed by the
+/// sheet's header row (its first non-empty row)
+#[derive(Debug, Default, Clone)]
+pub struct SpreadsheetExtractor;
+
+impl SpreadsheetExtractor {
+    /// `{"sheets": {"<sheet name>": [{"<header>": value, ...}, ...]}}`, with
+    /// every sheet present; empty sheets map to `[]`. Numbers stay numbers,
+    /// whole floats become integers and dates become ISO 8601 strings.
+    pub fn extract_excel_data(&self, url: &str, bytes: &[u8]) -> Result<Extraction> {
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+            .with_context(|| format!("Failed to open workbook {}", url))?;
+
+        let mut sheets = Map::new();
+        let mut extraction = Extraction::default();
+        for sheet in workbook.sheet_names() {
+            let range = workbook
+                .worksheet_range(&sheet)
+                .with_context(|| format!("Failed to read sheet '{}' of {}", sheet, url))?;
+            // Ranges start at the first used cell, not at A1
+            let (first_row, first_column) = range.start().unwrap_or_default();
+
+            let mut rows = range.rows().enumerate().skip_while(|(_, cells)| cells.iter().all(is_blank));
+            let headers: Vec<String> = rows
+                .next()
+                .map(|(_, cells)| cells.iter().map(|cell| header_text(cell).unwrap_or_default()).collect())
+                .unwrap_or_default();
+
+            let mut parsed_rows = Vec::new();
+            for (row_index, cells) in rows {
+                let mut object = Map::new();
+                for (column_index, cell) in cells.iter().enumerate() {
+                    let Some(value) = cell_value(cell) else { continue };
+                    let key = headers
+                        .get(column_index)
+                        .filter(|h| !h.is_empty())
+                        .cloned()
+                        .unwrap_or_else(|| format!("column_{}", column_index));
+                    let (row, column) = (first_row as usize + row_index, first_column as usize + column_index);
+                    extraction.record(
+                        format!("sheets[{}][{}].{}", sheet, parsed_rows.len(), key),
+                        value.clone(),
+                        FieldProvenance {
+                            source_url: url.to_string(),
+                            page: None,
+                            row: Some(row),
+                            column: Some(column),
+                            selector: Some(format!("{}!{}{}", sheet, column_name(column), row + 1)),
+                            archive: None,
+                            method: ExtractionMethod::Spreadsheet.as_str().to_string(),
+                            confidence: SPREADSHEET_CONFIDENCE,
+                        },
+                    );
+                    object.insert(key, value);
+                }
+                if !object.is_empty() {
+                    parsed_rows.push(Value::Object(object));
+                }
+            }
+            sheets.insert(sheet, Value::Array(parsed_rows));
+        }
+
+        extraction.data = json!({ "sheets": sheets });
+        Ok(extraction)
+    }
+}
+
+fn is_blank(cell: &Data) -> bool {
+    cell_value(cell).is_none()
+}
+
+fn header_text(cell: &Data) -> Option<String> {
+    match cell_value(cell)? {
+        Value::String(text) => Some(text),
+        other => Some(other.to_string()),
+    }
+}
+
+/// JSON for a cell; `None` for empty and error cells
+fn cell_value(cell: &Data) -> Option<Value> {
+    match cell {
+        Data::Empty | Data::Error(_) => None,
+        Data::String(text) => {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!text.is_empty()).then_some(Value::String(text))
+        }
+        Data::Int(n) => Some(json!(n)),
+        // Excel stores every number as a float; years and counts read better as integers
+        Data::Float(n) if n.fract() == 0.0 && n.abs() < 1e15 => Some(json!(*n as i64)),
+        Data::Float(n) => Some(json!(n)),
+        Data::Bool(b) => Some(json!(b)),
+        Data::DateTime(date) => match date.as_datetime() {
+            Some(at) if at.time().num_seconds_from_midnight() == 0 => Some(json!(at.date().to_string())),
+            Some(at) => Some(json!(at.format("%Y-%m-%dT%H:%M:%S").to_string())),
+            None => Some(json!(date.as_f64())),
+        },
+        Data::DateTimeIso(text) | Data::DurationIso(text) => Some(json!(text)),
+    }
+}
+
+/// Spreadsheet column letters for a zero-based index: 0 → A, 26 → AA
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("ASCII letters")
+}
+
+#[async_trait]
+impl Extractor for SpreadsheetExtractor {
+    fn method(&self) -> ExtractionMethod {
+        ExtractionMethod::Spreadsheet
+    }
+
+    async fn extract(&self, document: &Document) -> Result<Option<Extraction>> {
+        let content_type = ContentType::detect(&document.url, document.content_type.as_deref(), &document.bytes);
+        if content_type != ContentType::Excel {
+            return Ok(None);
+        }
+        let extraction = self.extract_excel_data(&document.url, &document.bytes)?;
+        let has_rows = extraction.data["sheets"]
+            .as_object()
+            .is_some_and(|sheets| sheets.values().any(|rows| rows.as_array().is_some_and(|rows| !rows.is_empty())));
+        Ok(has_rows.then_some(extraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    /// A minimal XLSX: one sheet per `(name, sheet XML rows)`, style 1 formats dates
+    fn workbook(sheets: &[(&str, &str)]) -> Vec<u8> {
+        let mut files = vec![
+            (
+                "[Content_Types].xml".to_string(),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>{}</Types>"#,
+                    (1..=sheets.len())
+                        .map(|n| format!(r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#, n))
+                        .collect::<String>()
+                ),
+            ),
+            (
+                "_rels/.rels".to_string(),
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#.to_string(),
+            ),
+            (
+                "xl/workbook.xml".to_string(),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>{}</sheets></workbook>"#,
+                    sheets
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (name, _))| format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, name, i + 1, i + 1))
+                        .collect::<String>()
+                ),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels".to_string(),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}<Relationship Id="rIdStyles" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#,
+                    (1..=sheets.len())
+                        .map(|n| format!(r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#, n, n))
+                        .collect::<String>()
+                ),
+            ),
+            (
+                "xl/styles.xml".to_string(),
+                r#"<?xml version="1.0" encoding="UTF-8"?><styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><cellXfs count="2"><xf numFmtId="0"/><xf numFmtId="14" applyNumberFormat="1"/></cellXfs></styleSheet>"#.to_string(),
+            ),
+        ];
+        for (i, (_, rows)) in sheets.iter().enumerate() {
+            files.push((
+                format!("xl/worksheets/sheet{}.xml", i + 1),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#,
+                    rows
+                ),
+            ));
+        }
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer.start_file(name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn text(reference: &str, value: &str) -> String {
+        format!(r#"<c r="{}" t="inlineStr"><is><t>{}</t></is></c>"#, reference, value)
+    }
+
+    #[tokio::test]
+    async fn test_workbook_sheets_become_rows_keyed_by_header() {
+        let tariffs = [
+            format!(r#"<row r="1">{}{}{}{}</row>"#, text("A1", "Spannungsebene"), text("B1", "Arbeitspreis"), text("C1", "Jahr"), text("D1", "Gültig ab")),
+            format!(r#"<row r="2">{}<c r="B2"><v>5.82</v></c><c r="C2"><v>2024</v></c><c r="D2" s="1"><v>45292</v></c></row>"#, text("A2", "MS")),
+            format!(r#"<row r="3">{}<c r="B3"><v>7.1</v></c><c r="C3"><v>2024</v></c></row>"#, text("A3", "NS")),
+        ]
+        .concat();
+        let bytes = workbook(&[("Netzentgelte 2024", &tariffs), ("Hinweise", "")]);
+        let document = Document {
+            url: "https://netze.example/files/preisblatt-2024.xlsx".to_string(),
+            content_type: Some("application/octet-stream".to_string()),
+            bytes,
+            ..Default::default()
+        };
+
+        let extraction = SpreadsheetExtractor.extract(&document).await.unwrap().unwrap();
+
+        assert_eq!(
+            extraction.data,
+            json!({
+                "sheets": {
+                    "Netzentgelte 2024": [
+                        { "Spannungsebene": "MS", "Arbeitspreis": 5.82, "Jahr": 2024, "Gültig ab": "2024-01-01" },
+                        { "Spannungsebene": "NS", "Arbeitspreis": 7.1, "Jahr": 2024 }
+                    ],
+                    "Hinweise": []
+                }
+            })
+        );
+        let field = &extraction.provenance["sheets[Netzentgelte 2024][1].Arbeitspreis"];
+        assert_eq!(field.provenance.selector.as_deref(), Some("Netzentgelte 2024!B3"));
+        assert_eq!(field.provenance.method, "spreadsheet");
+    }
+}
@@ -0,0 +1,192 @@
+use crate::content::ContentType;
+use crate::extraction::{Document, Extraction, Extractor};
+use crate::pipeline::ExtractionMethod;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use dno_core::models::FieldProvenance;
+use roxmltree::Node;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Values come from named elements of a structured feed, but units and
+/// meanings still depend on the publisher's schema
+const XML_CONFIDENCE: f64 = 0.85;
+
+/// Converts XML data feeds (e.g. `<tarif><year>2024</year>...</tarif>`) into
+/// JSON: elements become objects keyed by tag name, repeated siblings become
+/// arrays, attributes go under `@attributes` and text next to child elements
+/// or attributes under `#text`
+#[derive(Debug, Default, Clone)]
+pub struct XmlExtractor;
+
+impl XmlExtractor {
+    /// The document as JSON, keyed by its root element
+    pub fn parse_xml_to_json(&self, xml: &str) -> Result<serde_json::Value> {
+        Ok(self.extract_xml("", xml)?.data)
+    }
+
+    /// `parse_xml_to_json` with provenance for every text and attribute value;
+    /// the selector is the element's path, e.g. `/tarif/preis[2]/@einheit`
+    pub fn extract_xml(&self, url: &str, xml: &str) -> Result<Extraction> {
+        let document = roxmltree::Document::parse(xml).with_context(|| format!("Invalid XML in {}", url))?;
+        let root = document.root_element();
+        let name = root.tag_name().name();
+
+        let mut extraction = Extraction::default();
+        let value = element_to_json(root, name, &format!("/{}", name), url, &mut extraction);
+        extraction.data = json!({ name: value });
+        Ok(extraction)
+    }
+}
+
+fn element_to_json(element: Node<'_, '_>, path: &str, xpath: &str, url: &str, extraction: &mut Extraction) -> Value {
+    let record = |extraction: &mut Extraction, path: String, selector: String, value: &str| {
+        extraction.record(
+            path,
+            json!(value),
+            FieldProvenance {
+                source_url: url.to_string(),
+                page: None,
+                row: None,
+                column: None,
+                selector: Some(selector),
+                archive: None,
+                method: ExtractionMethod::Xml.as_str().to_string(),
+                confidence: XML_CONFIDENCE,
+            },
+        );
+    };
+
+    // Text and CDATA directly inside the element
+    let text: String = element.children().filter(Node::is_text).filter_map(|node| node.text()).collect();
+    let text = text.trim();
+    let children: Vec<Node> = element.children().filter(Node::is_element).collect();
+    let attributes: Vec<(&str, &str)> = element.attributes().map(|a| (a.name(), a.value())).collect();
+
+    if children.is_empty() && attributes.is_empty() {
+        if text.is_empty() {
+            return Value::Null;
+        }
+        record(extraction, path.to_string(), xpath.to_string(), text);
+        return json!(text);
+    }
+
+    let mut object = Map::new();
+    if !attributes.is_empty() {
+        let mut values = Map::new();
+        for (name, value) in attributes {
+            record(extraction, format!("{}.@attributes.{}", path, name), format!("{}/@{}", xpath, name), value);
+            values.insert(name.to_string(), json!(value));
+        }
+        object.insert("@attributes".to_string(), Value::Object(values));
+    }
+    if !text.is_empty() {
+        record(extraction, format!("{}.#text", path), format!("{}/text()", xpath), text);
+        object.insert("#text".to_string(), json!(text));
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for child in &children {
+        *counts.entry(child.tag_name().name()).or_default() += 1;
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for child in children {
+        let name = child.tag_name().name();
+        if counts[name] > 1 {
+            let index = seen.entry(name).or_default();
+            let value = element_to_json(
+                child,
+                &format!("{}.{}[{}]", path, name, index),
+                &format!("{}/{}[{}]", xpath, name, *index + 1),
+                url,
+                extraction,
+            );
+            *index += 1;
+            object
+                .entry(name.to_string())
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+                .expect("repeated elements are collected in an array")
+                .push(value);
+        } else {
+            let value = element_to_json(child, &format!("{}.{}", path, name), &format!("{}/{}", xpath, name), url, extraction);
+            object.insert(name.to_string(), value);
+        }
+    }
+    Value::Object(object)
+}
+
+#[async_trait]
+impl Extractor for XmlExtractor {
+    fn method(&self) -> ExtractionMethod {
+        ExtractionMethod::Xml
+    }
+
+    async fn extract(&self, document: &Document) -> Result<Option<Extraction>> {
+        let content_type = ContentType::detect(&document.url, document.content_type.as_deref(), &document.bytes);
+        if content_type != ContentType::Xml {
+            return Ok(None);
+        }
+        let xml = String::from_utf8_lossy(&document.bytes);
+        self.extract_xml(&document.url, &xml).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_elements_and_repeated_siblings() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <tarif>
+                <year>2024</year>
+                <netzbetreiber><name>Netze BW</name><bundesland>Baden-Württemberg</bundesland></netzbetreiber>
+                <preis><ebene>MS</ebene><arbeitspreis>5,82</arbeitspreis></preis>
+                <preis><ebene>NS</ebene><arbeitspreis>7,10</arbeitspreis></preis>
+                <hinweis/>
+            </tarif>"#;
+
+        let value = XmlExtractor.parse_xml_to_json(xml).unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "tarif": {
+                    "year": "2024",
+                    "netzbetreiber": { "name": "Netze BW", "bundesland": "Baden-Württemberg" },
+                    "preis": [
+                        { "ebene": "MS", "arbeitspreis": "5,82" },
+                        { "ebene": "NS", "arbeitspreis": "7,10" }
+                    ],
+                    "hinweis": null
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_attributes_and_cdata() {
+        let xml = r#"<tarife xmlns="urn:example:netzentgelte" stand="2024-01-01">
+            <preis einheit="ct/kWh" ebene="MS">5,82</preis>
+            <bemerkung><![CDATA[Preise <netto> & ohne Umlagen]]></bemerkung>
+        </tarife>"#;
+
+        let extraction = XmlExtractor.extract_xml("https://netze.example/tarife.xml", xml).unwrap();
+
+        assert_eq!(
+            extraction.data,
+            json!({
+                "tarife": {
+                    "@attributes": { "stand": "2024-01-01" },
+                    "preis": { "@attributes": { "einheit": "ct/kWh", "ebene": "MS" }, "#text": "5,82" },
+                    "bemerkung": "Preise <netto> & ohne Umlagen"
+                }
+            })
+        );
+        let field = &extraction.provenance["tarife.preis.@attributes.einheit"];
+        assert_eq!(field.provenance.selector.as_deref(), Some("/tarife/preis/@einheit"));
+        assert_eq!(field.provenance.confidence, XML_CONFIDENCE);
+        assert!(extraction.provenance.contains_key("tarife.preis.#text"));
+    }
+}
@@ -18,7 +18,8 @@ CREATE TABLE dnos (
                       region VARCHAR(255),
                       website VARCHAR(500),
                       created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
-                      updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+                      updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+                      deleted_at TIMESTAMPTZ
 );
 
 CREATE INDEX idx_dnos_slug ON dnos(slug);
@@ -53,6 +54,7 @@ CREATE TABLE netzentgelte_data (
                                    arbeit_unter_2500h DECIMAL(10, 2),
                                    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
                                    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+                                   deleted_at TIMESTAMPTZ,
                                    UNIQUE(dno_id, year, voltage_level)
 );
 
@@ -69,6 +71,7 @@ CREATE TABLE hlzf_data (
                            end_time TIME,
                            created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
                            updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+                           deleted_at TIMESTAMPTZ,
                            UNIQUE(dno_id, year, season, period_number)
 );
 
@@ -257,6 +260,15 @@ ALTER TABLE hlzf_data ADD COLUMN verified_by UUID REFERENCES users(id);
 ALTER TABLE hlzf_data ADD COLUMN verified_at TIMESTAMPTZ;
 ALTER TABLE hlzf_data ADD COLUMN verification_notes TEXT;
 
+-- Original extracted strings, units and normalization flags per Netzentgelte value
+ALTER TABLE netzentgelte_data ADD COLUMN raw_values JSONB;
+
+-- When an entry first appeared and when a crawl last found it unchanged
+ALTER TABLE netzentgelte_data ADD COLUMN first_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE netzentgelte_data ADD COLUMN last_verified_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE hlzf_data ADD COLUMN first_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE hlzf_data ADD COLUMN last_verified_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
 -- Data entry history for audit trail
 CREATE TABLE data_entry_history (
                                     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -270,6 +282,8 @@ CREATE TABLE data_entry_history (
                                     data_after JSONB
 );
 
+CREATE INDEX idx_data_entry_history_entry ON data_entry_history(entry_id, version);
+
 -- Metrics table for Prometheus
 CREATE TABLE metrics (
                          id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -288,6 +302,117 @@ ALTER TABLE data_sources ADD COLUMN extraction_method VARCHAR(50);
 ALTER TABLE data_sources ADD COLUMN extraction_region JSONB;
 ALTER TABLE data_sources ADD COLUMN ocr_text TEXT;
 ALTER TABLE data_sources ADD COLUMN extraction_log JSONB;
+-- Extracted values keyed by field, each with its provenance (page/row/column or selector, method, confidence)
+ALTER TABLE data_sources ADD COLUMN extracted_data JSONB;
+
+-- Values two sources disagree on; the entry keeps its first value and is flagged until an admin resolves it
+CREATE TABLE data_conflicts (
+                                id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                                entry_type VARCHAR(20) NOT NULL, -- 'netzentgelte' or 'hlzf'
+                                entry_id UUID NOT NULL,
+                                dno_id UUID NOT NULL REFERENCES dnos(id) ON DELETE CASCADE,
+                                year INTEGER NOT NULL,
+                                field VARCHAR(50) NOT NULL,
+                                candidates JSONB NOT NULL, -- [{value, source_id, source_url, extraction_method, confidence, observed_at}]
+                                status VARCHAR(20) NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'resolved')),
+                                resolved_value JSONB,
+                                resolved_candidate INTEGER,
+                                resolved_by UUID REFERENCES users(id),
+                                resolved_at TIMESTAMPTZ,
+                                resolution_notes TEXT,
+                                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_data_conflicts_open ON data_conflicts(entry_id, field) WHERE status = 'open';
+CREATE INDEX idx_data_conflicts_dno_year ON data_conflicts(dno_id, year);
+
+-- Summaries of source file deduplication runs
+CREATE TABLE dedup_runs (
+                            id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                            started_at TIMESTAMPTZ NOT NULL,
+                            finished_at TIMESTAMPTZ NOT NULL,
+                            triggered_by UUID REFERENCES users(id) ON DELETE SET NULL,
+                            files_scanned INTEGER NOT NULL DEFAULT 0,
+                            groups_found INTEGER NOT NULL DEFAULT 0,
+                            duplicates_removed INTEGER NOT NULL DEFAULT 0,
+                            bytes_saved BIGINT NOT NULL DEFAULT 0,
+                            groups JSONB NOT NULL DEFAULT '[]' -- [{sha256, kept, kept_path, duplicates, bytes_saved}]
+);
+
+CREATE INDEX idx_dedup_runs_started_at ON dedup_runs(started_at DESC);
+
+-- Hand-authored navigation paths to a DNO's data, replayed by reverse crawls
+CREATE TABLE navigation_paths (
+                                  id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                                  dno_id UUID NOT NULL REFERENCES dnos(id) ON DELETE CASCADE,
+                                  name VARCHAR(255),
+                                  steps JSONB NOT NULL, -- [{action, url, link_text}]
+                                  created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+                                  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_navigation_paths_dno ON navigation_paths(dno_id, created_at DESC);
+
+-- A re-crawl with changed values adds a new version; the prior one points at it and keeps its values
+ALTER TABLE netzentgelte_data ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE netzentgelte_data ADD COLUMN superseded_by UUID REFERENCES netzentgelte_data(id) DEFERRABLE INITIALLY DEFERRED;
+ALTER TABLE netzentgelte_data DROP CONSTRAINT netzentgelte_data_dno_id_year_voltage_level_key;
+CREATE UNIQUE INDEX idx_netzentgelte_data_version ON netzentgelte_data(dno_id, year, voltage_level, version);
+CREATE UNIQUE INDEX idx_netzentgelte_data_current ON netzentgelte_data(dno_id, year, voltage_level) WHERE superseded_by IS NULL;
+
+ALTER TABLE hlzf_data ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE hlzf_data ADD COLUMN superseded_by UUID REFERENCES hlzf_data(id) DEFERRABLE INITIALLY DEFERRED;
+ALTER TABLE hlzf_data DROP CONSTRAINT hlzf_data_dno_id_year_season_period_number_key;
+CREATE UNIQUE INDEX idx_hlzf_data_version ON hlzf_data(dno_id, year, season, period_number, version);
+CREATE UNIQUE INDEX idx_hlzf_data_current ON hlzf_data(dno_id, year, season, period_number) WHERE superseded_by IS NULL;
+
+-- Crawls started through POST /api/v1/crawl; each runs one crawl job per DNO, year and data type
+CREATE TABLE crawl_sessions (
+                                id UUID PRIMARY KEY,
+                                user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+                                mode VARCHAR(20) NOT NULL,
+                                status job_status NOT NULL DEFAULT 'pending',
+                                error_message TEXT,
+                                started_at TIMESTAMPTZ,
+                                completed_at TIMESTAMPTZ,
+                                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                                updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_crawl_sessions_status ON crawl_sessions(status);
+
+ALTER TABLE crawl_jobs ADD COLUMN session_id UUID REFERENCES crawl_sessions(id) ON DELETE CASCADE;
+-- {completion, pages_processed, documents_found, failed_urls} of a finished job
+ALTER TABLE crawl_jobs ADD COLUMN result JSONB;
+CREATE INDEX idx_crawl_jobs_session_id ON crawl_jobs(session_id);
+
+-- URLs a crawl could not fetch without a human (access denied, blocked), for admins to check and retry
+CREATE TABLE manual_review_queue (
+                                     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                                     url TEXT NOT NULL,
+                                     reason TEXT NOT NULL,
+                                     context JSONB NOT NULL DEFAULT '{}', -- {error_kind, depth, queued_because, ...}
+                                     dno_id UUID NOT NULL REFERENCES dnos(id) ON DELETE CASCADE,
+                                     year INTEGER NOT NULL,
+                                     data_type VARCHAR(20) NOT NULL,
+                                     session_id UUID REFERENCES crawl_sessions(id) ON DELETE SET NULL,
+                                     job_id UUID REFERENCES crawl_jobs(id) ON DELETE SET NULL,
+                                     occurrences INTEGER NOT NULL DEFAULT 1,
+                                     status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'resubmitted')),
+                                     retried_by UUID REFERENCES users(id) ON DELETE SET NULL,
+                                     retried_at TIMESTAMPTZ,
+                                     retry_session_id UUID REFERENCES crawl_sessions(id) ON DELETE SET NULL,
+                                     created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                                     updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_manual_review_queue_pending ON manual_review_queue(url, dno_id, year, data_type) WHERE status = 'pending';
+CREATE INDEX idx_manual_review_queue_status ON manual_review_queue(status, created_at);
+
+-- Trigram indexes for fuzzy DNO name search
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX idx_dnos_name_trgm ON dnos USING gin (name gin_trgm_ops);
+CREATE INDEX idx_dnos_official_name_trgm ON dnos USING gin (official_name gin_trgm_ops);
 
 -- Create update timestamp trigger
 CREATE OR REPLACE FUNCTION update_updated_at_column()
@@ -323,6 +448,12 @@ CREATE TRIGGER update_crawl_jobs_updated_at BEFORE UPDATE ON crawl_jobs
 CREATE TRIGGER update_automated_jobs_updated_at BEFORE UPDATE ON automated_jobs
     FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
 
+CREATE TRIGGER update_crawl_sessions_updated_at BEFORE UPDATE ON crawl_sessions
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_manual_review_queue_updated_at BEFORE UPDATE ON manual_review_queue
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
 -- Insert example storage from the JSON
 INSERT INTO dnos (slug, name, official_name, description, region) VALUES
     ('netze-bw', 'Netze BW', 'Netze BW GmbH', 'Netzbetreiber in Baden-Württemberg', 'Baden-Württemberg');